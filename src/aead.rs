@@ -0,0 +1,302 @@
+//! ChaCha20-Poly1305 and XChaCha20-Poly1305 authenticated encryption with
+//! associated data, as described in [RFC
+//! 8439](https://www.rfc-editor.org/rfc/rfc8439) and
+//! [draft-irtf-cfrg-xchacha](https://datatracker.ietf.org/doc/html/draft-irtf-cfrg-xchacha),
+//! compatible with libsodium's `crypto_aead_chacha20poly1305_ietf_*` and
+//! `crypto_aead_xchacha20poly1305_ietf_*`.
+//!
+//! The ciphertext is the encrypted message followed by the 16 byte tag.
+//!
+//! This is used internally by formats which require a standard AEAD, and
+//! isn't exposed as part of the public API.
+use chacha20::cipher::{KeyIvInit, StreamCipher, StreamCipherSeek};
+use chacha20::{ChaCha20, Key, Nonce};
+use subtle::ConstantTimeEq;
+use zeroize::Zeroize;
+
+use crate::classic::crypto_core::{crypto_core_hchacha20, HChaCha20Key};
+use crate::constants::{
+    CRYPTO_AEAD_XCHACHA20POLY1305_IETF_ABYTES, CRYPTO_AEAD_XCHACHA20POLY1305_IETF_KEYBYTES,
+    CRYPTO_AEAD_XCHACHA20POLY1305_IETF_NPUBBYTES, CRYPTO_STREAM_CHACHA20_IETF_NONCEBYTES,
+};
+use crate::error::Error;
+use crate::poly1305::Poly1305;
+use crate::types::*;
+
+const KEYBYTES: usize = CRYPTO_AEAD_XCHACHA20POLY1305_IETF_KEYBYTES;
+const ABYTES: usize = CRYPTO_AEAD_XCHACHA20POLY1305_IETF_ABYTES;
+
+/// Encrypts `message` with `associated_data`, using ChaCha20-Poly1305.
+pub(crate) fn chacha20poly1305_ietf_encrypt(
+    message: &[u8],
+    associated_data: &[u8],
+    nonce: &[u8; CRYPTO_STREAM_CHACHA20_IETF_NONCEBYTES],
+    key: &[u8; KEYBYTES],
+) -> Vec<u8> {
+    let mut ciphertext = Vec::with_capacity(message.len() + ABYTES);
+    ciphertext.extend_from_slice(message);
+    let tag = chacha20poly1305_ietf_encrypt_detached(&mut ciphertext, associated_data, nonce, key);
+    ciphertext.extend_from_slice(&tag);
+
+    ciphertext
+}
+
+/// Encrypts `buffer` in place with `associated_data`, using
+/// ChaCha20-Poly1305, returning the tag.
+pub(crate) fn chacha20poly1305_ietf_encrypt_detached(
+    buffer: &mut [u8],
+    associated_data: &[u8],
+    nonce: &[u8; CRYPTO_STREAM_CHACHA20_IETF_NONCEBYTES],
+    key: &[u8; KEYBYTES],
+) -> [u8; ABYTES] {
+    let mut cipher = ChaCha20::new(Key::from_slice(key), Nonce::from_slice(nonce));
+    let mut mac_key = crate::poly1305::Key::new();
+    cipher.apply_keystream(mac_key.as_mut_slice());
+
+    cipher.seek(64);
+    cipher.apply_keystream(buffer);
+
+    let tag = compute_tag(&mac_key, associated_data, buffer);
+    mac_key.zeroize();
+    tag
+}
+
+/// Verifies and decrypts `ciphertext` with `associated_data`, using
+/// ChaCha20-Poly1305.
+pub(crate) fn chacha20poly1305_ietf_decrypt(
+    ciphertext: &[u8],
+    associated_data: &[u8],
+    nonce: &[u8; CRYPTO_STREAM_CHACHA20_IETF_NONCEBYTES],
+    key: &[u8; KEYBYTES],
+) -> Result<Vec<u8>, Error> {
+    if ciphertext.len() < ABYTES {
+        return Err(dryoc_error!(format!(
+            "ciphertext length {} less than tag length {}",
+            ciphertext.len(),
+            ABYTES
+        )));
+    }
+    let (ciphertext, tag) = ciphertext.split_at(ciphertext.len() - ABYTES);
+
+    let mut message = ciphertext.to_vec();
+    chacha20poly1305_ietf_decrypt_detached(
+        &mut message,
+        ByteArray::as_array(tag),
+        associated_data,
+        nonce,
+        key,
+    )?;
+
+    Ok(message)
+}
+
+/// Verifies `tag` and decrypts `buffer` in place with `associated_data`,
+/// using ChaCha20-Poly1305. `buffer` is left unchanged if verification fails.
+pub(crate) fn chacha20poly1305_ietf_decrypt_detached(
+    buffer: &mut [u8],
+    tag: &[u8; ABYTES],
+    associated_data: &[u8],
+    nonce: &[u8; CRYPTO_STREAM_CHACHA20_IETF_NONCEBYTES],
+    key: &[u8; KEYBYTES],
+) -> Result<(), Error> {
+    let mut cipher = ChaCha20::new(Key::from_slice(key), Nonce::from_slice(nonce));
+    let mut mac_key = crate::poly1305::Key::new();
+    cipher.apply_keystream(mac_key.as_mut_slice());
+    let computed_tag = compute_tag(&mac_key, associated_data, buffer);
+    mac_key.zeroize();
+
+    if tag.ct_eq(&computed_tag).unwrap_u8() == 0 {
+        return Err(dryoc_error!("authentication tags do not match"));
+    }
+
+    cipher.seek(64);
+    cipher.apply_keystream(buffer);
+
+    Ok(())
+}
+
+/// Encrypts `message` with `associated_data`, using XChaCha20-Poly1305.
+pub(crate) fn xchacha20poly1305_ietf_encrypt(
+    message: &[u8],
+    associated_data: &[u8],
+    nonce: &[u8; CRYPTO_AEAD_XCHACHA20POLY1305_IETF_NPUBBYTES],
+    key: &[u8; KEYBYTES],
+) -> Vec<u8> {
+    let (mut subkey, subnonce) = xchacha20_subkey(nonce, key);
+    let ciphertext = chacha20poly1305_ietf_encrypt(message, associated_data, &subnonce, &subkey);
+    subkey.zeroize();
+    ciphertext
+}
+
+/// Verifies and decrypts `ciphertext` with `associated_data`, using
+/// XChaCha20-Poly1305.
+pub(crate) fn xchacha20poly1305_ietf_decrypt(
+    ciphertext: &[u8],
+    associated_data: &[u8],
+    nonce: &[u8; CRYPTO_AEAD_XCHACHA20POLY1305_IETF_NPUBBYTES],
+    key: &[u8; KEYBYTES],
+) -> Result<Vec<u8>, Error> {
+    let (mut subkey, subnonce) = xchacha20_subkey(nonce, key);
+    let message = chacha20poly1305_ietf_decrypt(ciphertext, associated_data, &subnonce, &subkey);
+    subkey.zeroize();
+    message
+}
+
+/// Encrypts `buffer` in place with `associated_data`, using
+/// XChaCha20-Poly1305, returning the tag.
+pub(crate) fn xchacha20poly1305_ietf_encrypt_detached(
+    buffer: &mut [u8],
+    associated_data: &[u8],
+    nonce: &[u8; CRYPTO_AEAD_XCHACHA20POLY1305_IETF_NPUBBYTES],
+    key: &[u8; KEYBYTES],
+) -> [u8; ABYTES] {
+    let (mut subkey, subnonce) = xchacha20_subkey(nonce, key);
+    let tag = chacha20poly1305_ietf_encrypt_detached(buffer, associated_data, &subnonce, &subkey);
+    subkey.zeroize();
+    tag
+}
+
+/// Verifies `tag` and decrypts `buffer` in place with `associated_data`,
+/// using XChaCha20-Poly1305. `buffer` is left unchanged if verification fails.
+pub(crate) fn xchacha20poly1305_ietf_decrypt_detached(
+    buffer: &mut [u8],
+    tag: &[u8; ABYTES],
+    associated_data: &[u8],
+    nonce: &[u8; CRYPTO_AEAD_XCHACHA20POLY1305_IETF_NPUBBYTES],
+    key: &[u8; KEYBYTES],
+) -> Result<(), Error> {
+    let (mut subkey, subnonce) = xchacha20_subkey(nonce, key);
+    let result =
+        chacha20poly1305_ietf_decrypt_detached(buffer, tag, associated_data, &subnonce, &subkey);
+    subkey.zeroize();
+    result
+}
+
+fn xchacha20_subkey(
+    nonce: &[u8; CRYPTO_AEAD_XCHACHA20POLY1305_IETF_NPUBBYTES],
+    key: &[u8; KEYBYTES],
+) -> ([u8; KEYBYTES], [u8; CRYPTO_STREAM_CHACHA20_IETF_NONCEBYTES]) {
+    let mut subkey = HChaCha20Key::default();
+    crypto_core_hchacha20(
+        subkey.as_mut_array(),
+        ByteArray::as_array(&nonce[..16]),
+        key,
+        None,
+    );
+    let mut subnonce = [0u8; CRYPTO_STREAM_CHACHA20_IETF_NONCEBYTES];
+    subnonce[4..].copy_from_slice(&nonce[16..]);
+
+    let result = (*subkey.as_array(), subnonce);
+    subkey.zeroize();
+    result
+}
+
+fn compute_tag(
+    mac_key: &crate::poly1305::Key,
+    associated_data: &[u8],
+    ciphertext: &[u8],
+) -> [u8; ABYTES] {
+    let pad = [0u8; 16];
+    let mut mac = Poly1305::new(mac_key);
+    mac.update(associated_data);
+    mac.update(&pad[..(0x10usize.wrapping_sub(associated_data.len()) & 0xf)]);
+    mac.update(ciphertext);
+    mac.update(&pad[..(0x10usize.wrapping_sub(ciphertext.len()) & 0xf)]);
+    mac.update(&(associated_data.len() as u64).to_le_bytes());
+    mac.update(&(ciphertext.len() as u64).to_le_bytes());
+
+    let mut tag = [0u8; ABYTES];
+    mac.finalize(&mut tag);
+    tag
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rng::randombytes_buf;
+
+    #[test]
+    fn test_chacha20poly1305_ietf() {
+        use libsodium_sys::crypto_aead_chacha20poly1305_ietf_encrypt as so_encrypt;
+
+        for i in 0..40 {
+            let key: [u8; KEYBYTES] = *ByteArray::as_array(&randombytes_buf(KEYBYTES)[..]);
+            let nonce = [i as u8; CRYPTO_STREAM_CHACHA20_IETF_NONCEBYTES];
+            let message = randombytes_buf(i * 7);
+            let associated_data = randombytes_buf(i * 3 % 37);
+
+            let ciphertext =
+                chacha20poly1305_ietf_encrypt(&message, &associated_data, &nonce, &key);
+
+            let mut so_ciphertext = vec![0u8; message.len() + ABYTES];
+            let mut so_len = 0u64;
+            let ret = unsafe {
+                so_encrypt(
+                    so_ciphertext.as_mut_ptr(),
+                    &mut so_len,
+                    message.as_ptr(),
+                    message.len() as u64,
+                    associated_data.as_ptr(),
+                    associated_data.len() as u64,
+                    std::ptr::null(),
+                    nonce.as_ptr(),
+                    key.as_ptr(),
+                )
+            };
+            assert_eq!(ret, 0);
+            assert_eq!(ciphertext, so_ciphertext);
+
+            let decrypted =
+                chacha20poly1305_ietf_decrypt(&ciphertext, &associated_data, &nonce, &key)
+                    .expect("decrypt failed");
+            assert_eq!(decrypted, message);
+        }
+    }
+
+    #[test]
+    fn test_xchacha20poly1305_ietf() {
+        use libsodium_sys::crypto_aead_xchacha20poly1305_ietf_encrypt as so_encrypt;
+
+        for i in 0..40 {
+            let key: [u8; KEYBYTES] = *ByteArray::as_array(&randombytes_buf(KEYBYTES)[..]);
+            let nonce: [u8; CRYPTO_AEAD_XCHACHA20POLY1305_IETF_NPUBBYTES] = *ByteArray::as_array(
+                &randombytes_buf(CRYPTO_AEAD_XCHACHA20POLY1305_IETF_NPUBBYTES)[..],
+            );
+            let message = randombytes_buf(i * 7);
+            let associated_data = randombytes_buf(i * 3 % 37);
+
+            let ciphertext =
+                xchacha20poly1305_ietf_encrypt(&message, &associated_data, &nonce, &key);
+
+            let mut so_ciphertext = vec![0u8; message.len() + ABYTES];
+            let mut so_len = 0u64;
+            let ret = unsafe {
+                so_encrypt(
+                    so_ciphertext.as_mut_ptr(),
+                    &mut so_len,
+                    message.as_ptr(),
+                    message.len() as u64,
+                    associated_data.as_ptr(),
+                    associated_data.len() as u64,
+                    std::ptr::null(),
+                    nonce.as_ptr(),
+                    key.as_ptr(),
+                )
+            };
+            assert_eq!(ret, 0);
+            assert_eq!(ciphertext, so_ciphertext);
+
+            let decrypted =
+                xchacha20poly1305_ietf_decrypt(&ciphertext, &associated_data, &nonce, &key)
+                    .expect("decrypt failed");
+            assert_eq!(decrypted, message);
+
+            let mut tampered = ciphertext.clone();
+            tampered[i % ciphertext.len()] ^= 1;
+            assert!(
+                xchacha20poly1305_ietf_decrypt(&tampered, &associated_data, &nonce, &key).is_err()
+            );
+            assert!(xchacha20poly1305_ietf_decrypt(&ciphertext, b"other", &nonce, &key).is_err());
+        }
+    }
+}
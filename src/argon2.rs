@@ -87,6 +87,15 @@ struct BlockRegion {
     memory: Vec<Block>,
 }
 
+impl Drop for BlockRegion {
+    fn drop(&mut self) {
+        // Blocks are derived from the password, so wipe them
+        for block in self.memory.iter_mut() {
+            block.v.zeroize();
+        }
+    }
+}
+
 struct Argon2Instance {
     region: BlockRegion,
     pseudo_rands: Vec<u64>,
@@ -211,6 +220,10 @@ struct Argon2Context<'a> {
     lanes: u32,  // number of lanes
 }
 
+/// Computes an Argon2 hash of `password` into `output`. Calls `progress` with
+/// the number of segments filled so far and the total number of segments,
+/// before the first segment and after each one, and aborts with an error if it
+/// returns false.
 #[allow(clippy::too_many_arguments)]
 pub(crate) fn argon2_hash(
     t_cost: u32,
@@ -222,6 +235,7 @@ pub(crate) fn argon2_hash(
     ad: Option<&[u8]>,
     output: &mut [u8],
     type_: Argon2Type,
+    progress: &mut dyn FnMut(u32, u32) -> bool,
 ) -> Result<(), Error> {
     let memory_blocks = if m_cost < 2 * ARGON2_SYNC_POINTS * parallelism {
         2 * ARGON2_SYNC_POINTS * parallelism
@@ -257,8 +271,18 @@ pub(crate) fn argon2_hash(
     // slice
     argon2_fill_first_blocks(blockhash, &mut instance)?;
 
+    let total = instance
+        .passes
+        .saturating_mul(ARGON2_SYNC_POINTS * instance.lanes);
+    if !progress(0, total) {
+        return Err(dryoc_error!("hashing cancelled"));
+    }
+    let mut completed = 0;
     for pass in 0..instance.passes {
-        argon2_fill_memory_blocks(&mut instance, pass);
+        argon2_fill_memory_blocks(&mut instance, pass, &mut || {
+            completed += 1;
+            progress(completed, total)
+        })?;
     }
 
     argon2_finalize(context, instance)
@@ -334,7 +358,11 @@ struct Argon2Position {
     index: u32,
 }
 
-fn argon2_fill_memory_blocks(instance: &mut Argon2Instance, pass: u32) {
+fn argon2_fill_memory_blocks(
+    instance: &mut Argon2Instance,
+    pass: u32,
+    on_segment: &mut dyn FnMut() -> bool,
+) -> Result<(), Error> {
     let mut position = Argon2Position {
         pass,
         ..Default::default()
@@ -345,8 +373,12 @@ fn argon2_fill_memory_blocks(instance: &mut Argon2Instance, pass: u32) {
             position.lane = l;
             position.index = 0;
             fill_segment(instance, &mut position);
+            if !on_segment() {
+                return Err(dryoc_error!("hashing cancelled"));
+            }
         }
     }
+    Ok(())
 }
 
 fn fill_segment(instance: &mut Argon2Instance, position: &mut Argon2Position) {
@@ -373,6 +405,7 @@ fn fill_segment(instance: &mut Argon2Instance, position: &mut Argon2Position) {
         curr_offset - 1
     };
 
+    #[allow(clippy::explicit_counter_loop)]
     for i in starting_index..instance.segment_length {
         if curr_offset % instance.lane_length == 1 {
             prev_offset = curr_offset - 1;
@@ -693,6 +726,7 @@ mod tests {
             Some(&ad),
             &mut hash,
             Argon2Type::Argon2i,
+            &mut |_, _| true,
         )
         .expect("argon2_hash failed");
 
@@ -742,6 +776,7 @@ mod tests {
             Some(&ad),
             &mut hash,
             Argon2Type::Argon2id,
+            &mut |_, _| true,
         )
         .expect("argon2_hash failed");
 
@@ -755,6 +790,46 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_progress() {
+        let password = [1u8; 32];
+        let salt = [2u8; 16];
+        let mut hash = [0u8; 32];
+        let mut calls = vec![];
+
+        super::argon2_hash(
+            3,
+            32,
+            4,
+            &password,
+            &salt,
+            None,
+            None,
+            &mut hash,
+            Argon2Type::Argon2id,
+            &mut |completed, total| {
+                calls.push((completed, total));
+                true
+            },
+        )
+        .expect("argon2_hash failed");
+        assert_eq!(calls, (0..=48).map(|i| (i, 48)).collect::<Vec<_>>());
+
+        super::argon2_hash(
+            3,
+            32,
+            4,
+            &password,
+            &salt,
+            None,
+            None,
+            &mut hash,
+            Argon2Type::Argon2id,
+            &mut |completed, _| completed < 10,
+        )
+        .expect_err("should be cancelled");
+    }
+
     #[test]
     fn test_vector_argon2id_so() {
         let password = [1u8; 32];
@@ -773,6 +848,7 @@ mod tests {
             None,
             &mut hash,
             Argon2Type::Argon2id,
+            &mut |_, _| true,
         )
         .expect("argon2_hash failed");
 
@@ -0,0 +1,367 @@
+//! # Attestation quotes
+//!
+//! A [`Quote`] is a signed statement of a node's measurements (such as hashes
+//! of its firmware, kernel, and configuration) in response to a challenge
+//! nonce, similar to the quotes produced by TPMs and trusted execution
+//! environments. A verifier sends a fresh random nonce, the node signs a quote
+//! of its current measurements with its identity key, and the verifier checks
+//! the signature, the nonce, and the quote's timestamp.
+//!
+//! Quotes are signed over their canonical encoding (refer to
+//! [crate::canonical]), so both sides agree on the exact bytes signed, and
+//! signatures are produced by any [`Signer`], such as a
+//! [`SigningKeyPair`](crate::sign::SigningKeyPair).
+//!
+//! The nonce prevents a recorded quote from being replayed in response to a
+//! later challenge, while the timestamp limits how long a quote is accepted
+//! for. Timestamps are seconds since the Unix epoch, and quotes timestamped
+//! up to [`QUOTE_MAX_CLOCK_SKEW`] seconds in the future are accepted, to allow
+//! for clock drift between nodes.
+//!
+//! ## Example
+//!
+//! ```
+//! use std::collections::BTreeMap;
+//!
+//! use dryoc::attestation::*;
+//! use dryoc::sign::SigningKeyPair;
+//!
+//! let node_identity = SigningKeyPair::gen_with_defaults();
+//!
+//! // The verifier sends a challenge
+//! let nonce = Nonce::gen();
+//!
+//! // The node signs its measurements
+//! let mut measurements = BTreeMap::new();
+//! measurements.insert("kernel".to_string(), b"kernel hash".to_vec());
+//! measurements.insert("config".to_string(), b"config hash".to_vec());
+//! let signed = Quote::new_now(measurements.clone(), &nonce)
+//!     .expect("clock error")
+//!     .sign(&node_identity)
+//!     .expect("sign failed");
+//! let bytes = signed.to_bytes();
+//!
+//! // The verifier accepts quotes up to 5 minutes old
+//! let received = SignedQuote::from_bytes(&bytes).expect("decode failed");
+//! let quote = received
+//!     .verify_now(&node_identity.public_key, &nonce, 300)
+//!     .expect("verify failed");
+//! assert_eq!(quote.measurements(), &measurements);
+//! ```
+
+use std::collections::BTreeMap;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use subtle::ConstantTimeEq;
+
+use crate::canonical::{self, CanonicalDecode, CanonicalEncode};
+use crate::cbor::Value;
+use crate::error::Error;
+use crate::sign::{SignatureAlgorithm, Signer, Verifier};
+pub use crate::types::*;
+
+/// Length of a challenge nonce.
+pub const QUOTE_NONCEBYTES: usize = 32;
+/// Number of seconds a quote's timestamp may be ahead of the verifier's
+/// clock.
+pub const QUOTE_MAX_CLOCK_SKEW: u64 = 60;
+
+/// Stack-allocated challenge nonce type alias.
+pub type Nonce = StackByteArray<QUOTE_NONCEBYTES>;
+
+/// A node's measurements, bound to a challenge nonce and a timestamp.
+///
+/// Refer to [crate::attestation] for sample usage.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Quote {
+    measurements: BTreeMap<String, Vec<u8>>,
+    nonce: Nonce,
+    timestamp: u64,
+}
+
+impl Quote {
+    /// Returns a new quote of `measurements` in response to `nonce`, at
+    /// `unix_time` seconds since the Unix epoch.
+    pub fn new(measurements: BTreeMap<String, Vec<u8>>, nonce: &Nonce, unix_time: u64) -> Self {
+        Self {
+            measurements,
+            nonce: nonce.clone(),
+            timestamp: unix_time,
+        }
+    }
+
+    /// Returns a new quote of `measurements` in response to `nonce`, at the
+    /// current system time.
+    pub fn new_now(measurements: BTreeMap<String, Vec<u8>>, nonce: &Nonce) -> Result<Self, Error> {
+        Ok(Self::new(measurements, nonce, now()?))
+    }
+
+    /// Returns the measurements, ordered by name.
+    pub fn measurements(&self) -> &BTreeMap<String, Vec<u8>> {
+        &self.measurements
+    }
+
+    /// Returns the measurement named `name`, if any.
+    pub fn measurement(&self, name: &str) -> Option<&[u8]> {
+        self.measurements.get(name).map(Vec::as_slice)
+    }
+
+    /// Returns the challenge nonce.
+    pub fn nonce(&self) -> &Nonce {
+        &self.nonce
+    }
+
+    /// Returns the timestamp, in seconds since the Unix epoch.
+    pub fn timestamp(&self) -> u64 {
+        self.timestamp
+    }
+
+    /// Signs this quote with `signer`, returning the signed quote.
+    pub fn sign<S: Signer + ?Sized>(self, signer: &S) -> Result<SignedQuote, Error> {
+        let algorithm = signer.algorithm();
+        let signature = signer.try_sign(&self.to_canonical_bytes())?;
+        Ok(SignedQuote {
+            quote: self,
+            algorithm,
+            signature,
+        })
+    }
+}
+
+impl CanonicalEncode for Quote {
+    fn to_canonical_bytes(&self) -> Vec<u8> {
+        let measurements = self
+            .measurements
+            .iter()
+            .map(|(name, value)| {
+                Value::Array(vec![Value::Text(name.clone()), Value::Bytes(value.clone())])
+            })
+            .collect();
+        canonical::encode(
+            "dryoc/Quote",
+            vec![
+                Value::Array(measurements),
+                Value::Bytes(self.nonce.to_vec()),
+                Value::Unsigned(self.timestamp),
+            ],
+        )
+    }
+}
+
+impl CanonicalDecode for Quote {
+    fn from_canonical_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        let fields = canonical::decode("dryoc/Quote", bytes, 3)?;
+        let mut measurements = BTreeMap::new();
+        for entry in canonical::array_field(&fields[0])? {
+            let entry = canonical::record_field(entry, 2)?;
+            let name = canonical::text_field(&entry[0])?.to_string();
+            if measurements.keys().next_back() >= Some(&name) {
+                return Err(dryoc_error!(
+                    "quote measurements are not in canonical order"
+                ));
+            }
+            measurements.insert(name, canonical::bytes_field(&entry[1])?.to_vec());
+        }
+        Ok(Self {
+            measurements,
+            nonce: canonical::byte_array_field(&fields[1])?,
+            timestamp: canonical::unsigned_field(&fields[2])?,
+        })
+    }
+}
+
+/// A [`Quote`] along with a signature over its canonical encoding.
+///
+/// Refer to [crate::attestation] for sample usage.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SignedQuote {
+    quote: Quote,
+    algorithm: SignatureAlgorithm,
+    signature: Vec<u8>,
+}
+
+impl SignedQuote {
+    /// Returns the quote, without verifying the signature or its freshness.
+    pub fn quote_unverified(&self) -> &Quote {
+        &self.quote
+    }
+
+    /// Returns the signature algorithm.
+    pub fn algorithm(&self) -> SignatureAlgorithm {
+        self.algorithm
+    }
+
+    /// Returns the signature.
+    pub fn signature(&self) -> &[u8] {
+        &self.signature
+    }
+
+    /// Verifies the signature with `verifier`, and that the quote responds to
+    /// `nonce` and is at most `max_age` seconds old at `unix_time`, returning
+    /// the quote if so.
+    pub fn verify_at<V: Verifier + ?Sized>(
+        &self,
+        verifier: &V,
+        nonce: &Nonce,
+        unix_time: u64,
+        max_age: u64,
+    ) -> Result<&Quote, Error> {
+        if verifier.algorithm() != self.algorithm {
+            return Err(dryoc_error!(format!(
+                "quote is signed with {}, but verifier uses {}",
+                self.algorithm.jws_name(),
+                verifier.algorithm().jws_name()
+            )));
+        }
+        verifier.verify(&self.quote.to_canonical_bytes(), &self.signature)?;
+        if self
+            .quote
+            .nonce
+            .as_slice()
+            .ct_eq(nonce.as_slice())
+            .unwrap_u8()
+            == 0
+        {
+            return Err(dryoc_error!("quote doesn't respond to the challenge nonce"));
+        }
+        if self.quote.timestamp > unix_time.saturating_add(QUOTE_MAX_CLOCK_SKEW) {
+            return Err(dryoc_error!(format!(
+                "quote timestamp {} is in the future",
+                self.quote.timestamp
+            )));
+        }
+        if self.quote.timestamp < unix_time.saturating_sub(max_age) {
+            return Err(dryoc_error!(format!(
+                "quote timestamp {} is more than {} seconds old",
+                self.quote.timestamp, max_age
+            )));
+        }
+        Ok(&self.quote)
+    }
+
+    /// Verifies the quote against the current system time. Refer to
+    /// [`SignedQuote::verify_at`] for details.
+    pub fn verify_now<V: Verifier + ?Sized>(
+        &self,
+        verifier: &V,
+        nonce: &Nonce,
+        max_age: u64,
+    ) -> Result<&Quote, Error> {
+        self.verify_at(verifier, nonce, now()?, max_age)
+    }
+
+    /// Returns the canonical encoding of the signed quote. Equivalent to
+    /// [`CanonicalEncode::to_canonical_bytes`].
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.to_canonical_bytes()
+    }
+
+    /// Parses a signed quote produced by [`SignedQuote::to_bytes`]. The
+    /// signature isn't verified.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        Self::from_canonical_bytes(bytes)
+    }
+}
+
+impl CanonicalEncode for SignedQuote {
+    fn to_canonical_bytes(&self) -> Vec<u8> {
+        canonical::encode(
+            "dryoc/SignedQuote",
+            vec![
+                Value::Bytes(self.quote.to_canonical_bytes()),
+                Value::from(self.algorithm.cose_id()),
+                Value::Bytes(self.signature.clone()),
+            ],
+        )
+    }
+}
+
+impl CanonicalDecode for SignedQuote {
+    fn from_canonical_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        let fields = canonical::decode("dryoc/SignedQuote", bytes, 3)?;
+        let cose_id = fields[1]
+            .as_integer()
+            .ok_or_else(|| dryoc_error!("expected an integer field"))?;
+        Ok(Self {
+            quote: Quote::from_canonical_bytes(canonical::bytes_field(&fields[0])?)?,
+            algorithm: SignatureAlgorithm::from_cose_id(cose_id)?,
+            signature: canonical::bytes_field(&fields[2])?.to_vec(),
+        })
+    }
+}
+
+fn now() -> Result<u64, Error> {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .map_err(|err| dryoc_error!(format!("system time before Unix epoch: {}", err)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sign::SigningKeyPair;
+
+    #[test]
+    fn test_quote() {
+        let keypair = SigningKeyPair::gen_with_defaults();
+        let nonce = Nonce::gen();
+        let mut measurements = BTreeMap::new();
+        measurements.insert("firmware".to_string(), vec![1, 2, 3]);
+        measurements.insert("bootloader".to_string(), vec![4, 5, 6]);
+
+        let signed = Quote::new(measurements, &nonce, 1_000_000)
+            .sign(&keypair)
+            .expect("sign failed");
+        let decoded = SignedQuote::from_bytes(&signed.to_bytes()).expect("decode failed");
+        assert_eq!(decoded, signed);
+
+        let quote = decoded
+            .verify_at(&keypair.public_key, &nonce, 1_000_100, 300)
+            .expect("verify failed");
+        assert_eq!(quote.measurement("firmware"), Some(&[1u8, 2, 3][..]));
+        assert_eq!(quote.timestamp(), 1_000_000);
+
+        // a small amount of clock skew is allowed
+        decoded
+            .verify_at(&keypair.public_key, &nonce, 1_000_000 - 30, 300)
+            .expect("verify failed");
+        decoded
+            .verify_at(&keypair.public_key, &nonce, 1_000_000 - 120, 300)
+            .expect_err("from the future");
+        decoded
+            .verify_at(&keypair.public_key, &nonce, 1_000_301, 300)
+            .expect_err("stale");
+        decoded
+            .verify_at(&keypair.public_key, &Nonce::gen(), 1_000_100, 300)
+            .expect_err("wrong nonce");
+        decoded
+            .verify_at(
+                &SigningKeyPair::gen_with_defaults().public_key,
+                &nonce,
+                1_000_100,
+                300,
+            )
+            .expect_err("wrong key");
+    }
+
+    #[test]
+    fn test_quote_tampering() {
+        let keypair = SigningKeyPair::gen_with_defaults();
+        let nonce = Nonce::gen();
+        let mut measurements = BTreeMap::new();
+        measurements.insert("kernel".to_string(), vec![7; 32]);
+        let signed = Quote::new(measurements.clone(), &nonce, 5000)
+            .sign(&keypair)
+            .expect("sign failed");
+
+        measurements.insert("kernel".to_string(), vec![8; 32]);
+        let forged = SignedQuote {
+            quote: Quote::new(measurements, &nonce, 5000),
+            ..signed
+        };
+        forged
+            .verify_at(&keypair.public_key, &nonce, 5000, 60)
+            .expect_err("forged measurements");
+    }
+}
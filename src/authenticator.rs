@@ -0,0 +1,358 @@
+//! # Challenge signing for authenticators
+//!
+//! Helpers for WebAuthn-style authentication with Ed25519, for devices which
+//! use dryoc as their authenticator core. A relying party (the server) sends
+//! a random [`Challenge`], and the authenticator returns an [`Assertion`]: a
+//! signature over the challenge, the origin it was requested by, and the
+//! authenticator's signature counter, serialized as CBOR.
+//!
+//! The relying party verifies the signature with the authenticator's
+//! registered public key, checks that the challenge and origin match what it
+//! expects, and checks that the counter has increased since the last
+//! assertion it accepted, which helps to detect cloned authenticators.
+//!
+//! The signed client data is the CBOR map `{1: challenge, 2: origin, 3:
+//! counter}`, with the challenge as a byte string, the origin as a text
+//! string, and the counter as an unsigned integer, using deterministic
+//! encoding. This is similar in spirit to WebAuthn, but isn't compatible with
+//! it.
+//!
+//! If the `serde` feature is enabled, the [`serde::Deserialize`] and
+//! [`serde::Serialize`] traits will be implemented for [`ClientData`] and
+//! [`Assertion`].
+//!
+//! ## Example
+//!
+//! ```
+//! use dryoc::authenticator::*;
+//! use dryoc::sign::SigningKeyPair;
+//! use dryoc::types::*;
+//!
+//! // On the authenticator
+//! let keypair = SigningKeyPair::gen_with_defaults();
+//!
+//! // The relying party sends a challenge
+//! let challenge = Challenge::gen();
+//!
+//! let assertion =
+//!     Assertion::sign(&keypair, &challenge, "https://example.com", 1).expect("sign failed");
+//! let encoded = assertion.to_cbor();
+//!
+//! // The relying party verifies the assertion, and stores the new counter
+//! let assertion = Assertion::from_cbor(&encoded).expect("decode failed");
+//! let counter = assertion
+//!     .verify(&keypair.public_key, &challenge, "https://example.com", 0)
+//!     .expect("verify failed");
+//! assert_eq!(counter, 1);
+//!
+//! // Assertions can't be replayed
+//! let replayed = assertion.verify(&keypair.public_key, &challenge, "https://example.com", counter);
+//! assert!(replayed.is_err());
+//! ```
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use subtle::ConstantTimeEq;
+use zeroize::Zeroize;
+
+use crate::cbor::Value;
+use crate::classic::crypto_sign::{crypto_sign_detached, crypto_sign_verify_detached};
+use crate::constants::{CRYPTO_SIGN_BYTES, CRYPTO_SIGN_PUBLICKEYBYTES, CRYPTO_SIGN_SECRETKEYBYTES};
+use crate::error::Error;
+use crate::sign::{Signature, SigningKeyPair};
+use crate::types::*;
+
+/// Length of a challenge.
+pub const CHALLENGE_BYTES: usize = 32;
+
+/// Random challenge, generated by the relying party.
+pub type Challenge = StackByteArray<CHALLENGE_BYTES>;
+
+const KEY_CHALLENGE: i64 = 1;
+const KEY_ORIGIN: i64 = 2;
+const KEY_COUNTER: i64 = 3;
+
+#[cfg_attr(
+    feature = "serde",
+    derive(Zeroize, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)
+)]
+#[cfg_attr(not(feature = "serde"), derive(Zeroize, Clone, Debug, PartialEq, Eq))]
+/// The client data signed by an authenticator.
+pub struct ClientData {
+    /// Challenge from the relying party
+    pub challenge: Challenge,
+    /// Origin of the relying party, such as `https://example.com`
+    pub origin: String,
+    /// Authenticator's signature counter
+    pub counter: u32,
+}
+
+impl ClientData {
+    /// Returns the deterministic CBOR encoding of this client data.
+    pub fn to_cbor(&self) -> Vec<u8> {
+        Value::Map(vec![
+            (
+                Value::from(KEY_CHALLENGE),
+                Value::Bytes(self.challenge.to_vec()),
+            ),
+            (Value::from(KEY_ORIGIN), Value::Text(self.origin.clone())),
+            (
+                Value::from(KEY_COUNTER),
+                Value::Unsigned(self.counter as u64),
+            ),
+        ])
+        .to_vec()
+    }
+
+    /// Decodes client data from its CBOR encoding. Returns an error if the
+    /// encoding isn't the deterministic encoding produced by
+    /// [`ClientData::to_cbor`].
+    pub fn from_cbor(bytes: &[u8]) -> Result<Self, Error> {
+        let value = Value::from_slice(bytes)?;
+        let challenge = value
+            .get(KEY_CHALLENGE)
+            .and_then(Value::as_bytes)
+            .ok_or_else(|| dryoc_error!("missing or invalid challenge"))?;
+        let origin = value
+            .get(KEY_ORIGIN)
+            .and_then(Value::as_text)
+            .ok_or_else(|| dryoc_error!("missing or invalid origin"))?;
+        let counter = value
+            .get(KEY_COUNTER)
+            .and_then(Value::as_unsigned)
+            .filter(|counter| *counter <= u32::MAX as u64)
+            .ok_or_else(|| dryoc_error!("missing or invalid counter"))?;
+
+        let client_data = Self {
+            challenge: Challenge::try_from(challenge)?,
+            origin: origin.to_string(),
+            counter: counter as u32,
+        };
+        if client_data.to_cbor() != bytes {
+            return Err(dryoc_error!("client data isn't deterministically encoded"));
+        }
+
+        Ok(client_data)
+    }
+}
+
+#[cfg_attr(
+    feature = "serde",
+    derive(Zeroize, Clone, Debug, Serialize, Deserialize)
+)]
+#[cfg_attr(not(feature = "serde"), derive(Zeroize, Clone, Debug))]
+/// An authenticator's signed response to a challenge.
+///
+/// Refer to [crate::authenticator] for sample usage.
+pub struct Assertion {
+    client_data: Vec<u8>,
+    signature: Signature,
+}
+
+impl Assertion {
+    /// Signs `challenge`, as requested by `origin`, with the authenticator's
+    /// `keypair` and signature `counter`. The authenticator should increment
+    /// its counter for each assertion, or always use 0 if it doesn't keep a
+    /// counter.
+    pub fn sign<
+        PublicKey: ByteArray<CRYPTO_SIGN_PUBLICKEYBYTES> + Zeroize,
+        SecretKey: ByteArray<CRYPTO_SIGN_SECRETKEYBYTES> + Zeroize,
+    >(
+        keypair: &SigningKeyPair<PublicKey, SecretKey>,
+        challenge: &Challenge,
+        origin: &str,
+        counter: u32,
+    ) -> Result<Self, Error> {
+        let client_data = ClientData {
+            challenge: challenge.clone(),
+            origin: origin.to_string(),
+            counter,
+        }
+        .to_cbor();
+
+        let mut signature = Signature::new_byte_array();
+        crypto_sign_detached(
+            signature.as_mut_array(),
+            &client_data,
+            keypair.secret_key.as_array(),
+        )?;
+
+        Ok(Self {
+            client_data,
+            signature,
+        })
+    }
+
+    /// Verifies this assertion with the authenticator's `public_key`, and
+    /// checks that it was made for `expected_challenge` and
+    /// `expected_origin`. Returns the assertion's signature counter, which
+    /// should be stored and passed as `last_counter` for the next
+    /// verification.
+    ///
+    /// The counter must be greater than `last_counter`, unless both are 0
+    /// (which indicates the authenticator doesn't keep a counter).
+    pub fn verify<PublicKey: ByteArray<CRYPTO_SIGN_PUBLICKEYBYTES>>(
+        &self,
+        public_key: &PublicKey,
+        expected_challenge: &Challenge,
+        expected_origin: &str,
+        last_counter: u32,
+    ) -> Result<u32, Error> {
+        crypto_sign_verify_detached(
+            self.signature.as_array(),
+            &self.client_data,
+            public_key.as_array(),
+        )?;
+
+        let client_data = ClientData::from_cbor(&self.client_data)?;
+        if client_data
+            .challenge
+            .as_slice()
+            .ct_eq(expected_challenge.as_slice())
+            .unwrap_u8()
+            == 0
+        {
+            return Err(dryoc_error!("challenge does not match"));
+        }
+        if client_data.origin != expected_origin {
+            return Err(dryoc_error!(format!(
+                "origin {} does not match {}",
+                client_data.origin, expected_origin
+            )));
+        }
+        if client_data.counter <= last_counter && (client_data.counter, last_counter) != (0, 0) {
+            return Err(dryoc_error!(format!(
+                "counter {} not greater than {}, authenticator may be cloned",
+                client_data.counter, last_counter
+            )));
+        }
+
+        Ok(client_data.counter)
+    }
+
+    /// Returns the decoded client data of this assertion, without verifying
+    /// it.
+    pub fn client_data(&self) -> Result<ClientData, Error> {
+        ClientData::from_cbor(&self.client_data)
+    }
+
+    /// Returns the signature of this assertion.
+    pub fn signature(&self) -> &Signature {
+        &self.signature
+    }
+
+    /// Returns the CBOR encoding of this assertion: an array of the encoded
+    /// client data and the signature, both as byte strings.
+    pub fn to_cbor(&self) -> Vec<u8> {
+        Value::Array(vec![
+            Value::Bytes(self.client_data.clone()),
+            Value::Bytes(self.signature.to_vec()),
+        ])
+        .to_vec()
+    }
+
+    /// Decodes an assertion from its CBOR encoding.
+    pub fn from_cbor(bytes: &[u8]) -> Result<Self, Error> {
+        match Value::from_slice(bytes)?.as_array() {
+            Some([Value::Bytes(client_data), Value::Bytes(signature)])
+                if signature.len() == CRYPTO_SIGN_BYTES =>
+            {
+                Ok(Self {
+                    client_data: client_data.clone(),
+                    signature: Signature::try_from(signature.as_slice())?,
+                })
+            }
+            _ => Err(dryoc_error!("invalid assertion encoding")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_client_data_encoding() {
+        let client_data = ClientData {
+            challenge: Challenge::from([7u8; CHALLENGE_BYTES]),
+            origin: "https://example.com".into(),
+            counter: 1000,
+        };
+        let encoded = client_data.to_cbor();
+        assert_eq!(
+            hex::encode(&encoded),
+            format!(
+                "a3015820{}027368747470733a2f2f6578616d706c652e636f6d031903e8",
+                "07".repeat(CHALLENGE_BYTES)
+            )
+        );
+        assert_eq!(
+            ClientData::from_cbor(&encoded).expect("decode"),
+            client_data
+        );
+
+        // non-minimal encoding of the counter
+        let mut non_minimal = encoded[..encoded.len() - 3].to_vec();
+        non_minimal.extend_from_slice(&[0x1a, 0, 0, 0x03, 0xe8]);
+        assert!(ClientData::from_cbor(&non_minimal).is_err());
+    }
+
+    #[test]
+    fn test_assertion() {
+        let keypair = SigningKeyPair::gen_with_defaults();
+        let challenge = Challenge::gen();
+        let origin = "https://example.com";
+
+        let assertion = Assertion::sign(&keypair, &challenge, origin, 5).expect("sign");
+        let assertion = Assertion::from_cbor(&assertion.to_cbor()).expect("decode");
+        assert_eq!(assertion.client_data().expect("client data").counter, 5);
+        assert_eq!(
+            assertion
+                .verify(&keypair.public_key, &challenge, origin, 4)
+                .expect("verify"),
+            5
+        );
+
+        assert!(
+            assertion
+                .verify(&keypair.public_key, &challenge, origin, 5)
+                .is_err()
+        );
+        assert!(
+            assertion
+                .verify(&keypair.public_key, &Challenge::gen(), origin, 0)
+                .is_err()
+        );
+        assert!(
+            assertion
+                .verify(&keypair.public_key, &challenge, "https://example.org", 0)
+                .is_err()
+        );
+        assert!(
+            assertion
+                .verify(
+                    &SigningKeyPair::gen_with_defaults().public_key,
+                    &challenge,
+                    origin,
+                    0
+                )
+                .is_err()
+        );
+
+        // authenticators without a counter always use 0
+        let assertion = Assertion::sign(&keypair, &challenge, origin, 0).expect("sign");
+        assert_eq!(
+            assertion
+                .verify(&keypair.public_key, &challenge, origin, 0)
+                .expect("verify"),
+            0
+        );
+
+        let mut tampered = assertion.clone();
+        tampered.client_data[2] ^= 1;
+        assert!(
+            tampered
+                .verify(&keypair.public_key, &challenge, origin, 0)
+                .is_err()
+        );
+    }
+}
@@ -0,0 +1,401 @@
+//! # Bech32m encoding
+//!
+//! Encoding and decoding of [Bech32m](https://github.com/bitcoin/bips/blob/master/bip-0350.mediawiki)
+//! strings, for public keys and key fingerprints which people need to read
+//! aloud, type in, or scan from a label.
+//!
+//! A Bech32m string consists of a human-readable prefix (such as `ed25519pk`)
+//! which identifies what the string contains, the separator `1`, and the data
+//! encoded with a 32 character alphabet, followed by a 6 character checksum.
+//! The alphabet omits the easily confused characters `1`, `b`, `i`, and `o`,
+//! and the checksum is guaranteed to detect up to 4 mistyped characters.
+//! Decoding accepts strings in either lowercase or uppercase (which is
+//! more compact in QR codes), but not mixed case.
+//!
+//! Characters are mapped to and from the alphabet without secret-dependent
+//! table lookups or branches, so that this encoding is also suitable for
+//! secrets.
+//!
+//! Strings are limited to 90 characters, as specified by BIP 173, which is
+//! enough for 32 byte keys with prefixes up to 31 characters.
+//!
+//! ## Example
+//!
+//! ```
+//! use dryoc::bech32;
+//! use dryoc::sign::SigningKeyPair;
+//!
+//! let keypair = SigningKeyPair::gen_with_defaults();
+//! let encoded = bech32::encode_signing_public_key(&keypair.public_key);
+//! assert!(encoded.starts_with("ed25519pk1"));
+//!
+//! // Uppercase is accepted too
+//! let public_key =
+//!     bech32::decode_signing_public_key(&encoded.to_uppercase()).expect("decode failed");
+//! assert_eq!(public_key, keypair.public_key);
+//!
+//! // A mistyped character is detected
+//! let mut mistyped = encoded.into_bytes();
+//! mistyped[20] = if mistyped[20] == b'q' { b'p' } else { b'q' };
+//! let mistyped = String::from_utf8(mistyped).unwrap();
+//! assert!(bech32::decode_signing_public_key(&mistyped).is_err());
+//!
+//! // Fingerprints are shorter, for comparing keys out of band
+//! let fingerprint = bech32::fingerprint(&keypair.public_key);
+//! assert_eq!(fingerprint.len(), 35);
+//! ```
+
+use crate::constants::{
+    CRYPTO_BOX_PUBLICKEYBYTES, CRYPTO_GENERICHASH_KEYBYTES, CRYPTO_SIGN_PUBLICKEYBYTES,
+};
+use crate::error::Error;
+use crate::generichash::GenericHash;
+use crate::types::*;
+
+/// Prefix for Ed25519 public keys, such as [`crate::sign::PublicKey`].
+pub const SIGNING_PUBLIC_KEY_HRP: &str = "ed25519pk";
+/// Prefix for X25519 public keys, such as [`crate::dryocbox::PublicKey`].
+pub const BOX_PUBLIC_KEY_HRP: &str = "x25519pk";
+/// Prefix for key fingerprints returned by [`fingerprint`].
+pub const FINGERPRINT_HRP: &str = "fp";
+/// Length of the digest encoded by [`fingerprint`], in bytes.
+pub const FINGERPRINT_BYTES: usize = 16;
+/// Maximum length of an encoded string.
+pub const MAX_LENGTH: usize = 90;
+
+const CHECKSUM_LENGTH: usize = 6;
+const BECH32M_CONST: u32 = 0x2bc830a3;
+const ALPHABET: &[u8; 32] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+const GENERATOR: [u32; 5] = [0x3b6a57b2, 0x26508e6d, 0x1ea119fa, 0x3d4233dd, 0x2a1462b3];
+
+/// Returns `u32::MAX` if `a == b`, and 0 otherwise, without branching.
+fn ct_eq_mask(a: u32, b: u32) -> u32 {
+    let x = a ^ b;
+    // the high bit of `x | -x` is set iff x != 0
+    ((x | x.wrapping_neg()) >> 31).wrapping_sub(1)
+}
+
+/// Maps a 5 bit value to its character.
+fn to_char(value: u8) -> u8 {
+    let mut c = 0u32;
+    for (i, &a) in ALPHABET.iter().enumerate() {
+        c |= ct_eq_mask(i as u32, value as u32) & a as u32;
+    }
+    c as u8
+}
+
+/// Maps a lowercase character to its 5 bit value, returning the value and a
+/// mask which is `u32::MAX` if the character is valid.
+fn from_char(c: u8) -> (u8, u32) {
+    let mut value = 0u32;
+    let mut valid = 0u32;
+    for (i, &a) in ALPHABET.iter().enumerate() {
+        let mask = ct_eq_mask(a as u32, c as u32);
+        value |= mask & i as u32;
+        valid |= mask;
+    }
+    (value as u8, valid)
+}
+
+fn polymod(values: impl Iterator<Item = u8>) -> u32 {
+    let mut chk = 1u32;
+    for v in values {
+        let b = chk >> 25;
+        chk = ((chk & 0x1ffffff) << 5) ^ v as u32;
+        for (i, g) in GENERATOR.iter().enumerate() {
+            chk ^= ((b >> i) & 1).wrapping_neg() & g;
+        }
+    }
+    chk
+}
+
+fn hrp_expand(hrp: &[u8]) -> impl Iterator<Item = u8> + '_ {
+    hrp.iter()
+        .map(|c| c >> 5)
+        .chain(std::iter::once(0))
+        .chain(hrp.iter().map(|c| c & 31))
+}
+
+fn checksum(hrp: &[u8], data: &[u8]) -> [u8; CHECKSUM_LENGTH] {
+    let values = hrp_expand(hrp)
+        .chain(data.iter().copied())
+        .chain([0u8; CHECKSUM_LENGTH]);
+    let modulus = polymod(values) ^ BECH32M_CONST;
+    let mut output = [0u8; CHECKSUM_LENGTH];
+    for (i, c) in output.iter_mut().enumerate() {
+        *c = ((modulus >> (5 * (5 - i))) & 31) as u8;
+    }
+    output
+}
+
+fn validate_hrp(hrp: &str) -> Result<(), Error> {
+    if hrp.is_empty() || hrp.len() > MAX_LENGTH - CHECKSUM_LENGTH - 1 {
+        return Err(dryoc_error!(format!(
+            "invalid human-readable prefix length {}",
+            hrp.len()
+        )));
+    }
+    if !hrp.bytes().all(|c| (33..=126).contains(&c)) {
+        return Err(dryoc_error!("invalid character in human-readable prefix"));
+    }
+    Ok(())
+}
+
+/// Regroups `data` from `from` bit groups to `to` bit groups, padding the
+/// final group with zeros if `pad` is true.
+fn convert_bits(data: &[u8], from: u32, to: u32, pad: bool) -> Result<Vec<u8>, Error> {
+    let mut acc = 0u32;
+    let mut bits = 0u32;
+    let mask = (1u32 << to) - 1;
+    let mut output = Vec::with_capacity(data.len() * from as usize / to as usize + 1);
+    for &value in data {
+        acc = (acc << from) | value as u32;
+        bits += from;
+        while bits >= to {
+            bits -= to;
+            output.push(((acc >> bits) & mask) as u8);
+        }
+    }
+    if pad {
+        if bits > 0 {
+            output.push(((acc << (to - bits)) & mask) as u8);
+        }
+    } else if bits >= from || (acc << (to - bits)) & mask != 0 {
+        return Err(dryoc_error!("invalid padding"));
+    }
+    Ok(output)
+}
+
+/// Encodes `data` as a Bech32m string with the human-readable prefix `hrp`.
+/// The prefix is converted to lowercase.
+pub fn encode(hrp: &str, data: &[u8]) -> Result<String, Error> {
+    validate_hrp(hrp)?;
+    let hrp = hrp.to_ascii_lowercase();
+    let values = convert_bits(data, 8, 5, true)?;
+    let length = hrp.len() + 1 + values.len() + CHECKSUM_LENGTH;
+    if length > MAX_LENGTH {
+        return Err(dryoc_error!(format!(
+            "encoded length {} exceeds maximum of {}",
+            length, MAX_LENGTH
+        )));
+    }
+
+    let checksum = checksum(hrp.as_bytes(), &values);
+    let mut output = String::with_capacity(length);
+    output.push_str(&hrp);
+    output.push('1');
+    for &v in values.iter().chain(checksum.iter()) {
+        output.push(to_char(v) as char);
+    }
+    Ok(output)
+}
+
+/// Decodes a Bech32m string, returning the human-readable prefix (in
+/// lowercase) and the data.
+pub fn decode(encoded: &str) -> Result<(String, Vec<u8>), Error> {
+    let (hrp, values) = decode_values(encoded)?;
+    let data = convert_bits(&values, 5, 8, false)?;
+    Ok((hrp, data))
+}
+
+/// Decodes a Bech32m string into its prefix and 5 bit values, without the
+/// checksum.
+fn decode_values(encoded: &str) -> Result<(String, Vec<u8>), Error> {
+    if encoded.len() > MAX_LENGTH {
+        return Err(dryoc_error!(format!(
+            "encoded length {} exceeds maximum of {}",
+            encoded.len(),
+            MAX_LENGTH
+        )));
+    }
+    let has_lower = encoded.bytes().any(|c| c.is_ascii_lowercase());
+    let has_upper = encoded.bytes().any(|c| c.is_ascii_uppercase());
+    if has_lower && has_upper {
+        return Err(dryoc_error!("mixed case in encoded string"));
+    }
+    let encoded = encoded.to_ascii_lowercase();
+
+    let separator = encoded
+        .rfind('1')
+        .ok_or_else(|| dryoc_error!("missing separator"))?;
+    let (hrp, rest) = encoded.split_at(separator);
+    validate_hrp(hrp)?;
+    let rest = &rest.as_bytes()[1..];
+    if rest.len() < CHECKSUM_LENGTH {
+        return Err(dryoc_error!("missing checksum"));
+    }
+
+    let mut values = Vec::with_capacity(rest.len());
+    let mut valid = u32::MAX;
+    for &c in rest {
+        let (value, mask) = from_char(c);
+        values.push(value);
+        valid &= mask;
+    }
+    if valid == 0 {
+        return Err(dryoc_error!("invalid character in encoded data"));
+    }
+
+    if polymod(hrp_expand(hrp.as_bytes()).chain(values.iter().copied())) != BECH32M_CONST {
+        return Err(dryoc_error!("invalid checksum"));
+    }
+
+    values.truncate(values.len() - CHECKSUM_LENGTH);
+    Ok((hrp.to_string(), values))
+}
+
+fn decode_expecting(encoded: &str, expected_hrp: &str) -> Result<Vec<u8>, Error> {
+    let (hrp, data) = decode(encoded)?;
+    if hrp != expected_hrp {
+        return Err(dryoc_error!(format!(
+            "expected prefix {:?}, got {:?}",
+            expected_hrp, hrp
+        )));
+    }
+    Ok(data)
+}
+
+fn decode_key<PublicKey: NewByteArray<32>>(
+    encoded: &str,
+    expected_hrp: &str,
+) -> Result<PublicKey, Error> {
+    let data = decode_expecting(encoded, expected_hrp)?;
+    validate!(32, 32, data.len(), "public_key");
+    let mut output = PublicKey::new_byte_array();
+    output.copy_from_slice(&data);
+    Ok(output)
+}
+
+/// Encodes an Ed25519 signing public key, such as [`crate::sign::PublicKey`],
+/// with the prefix [`SIGNING_PUBLIC_KEY_HRP`].
+pub fn encode_signing_public_key<PublicKey: ByteArray<CRYPTO_SIGN_PUBLICKEYBYTES>>(
+    public_key: &PublicKey,
+) -> String {
+    encode(SIGNING_PUBLIC_KEY_HRP, public_key.as_slice()).expect("key fits in maximum length")
+}
+
+/// Decodes an Ed25519 signing public key. Returns an error if the string has
+/// another prefix.
+pub fn decode_signing_public_key(encoded: &str) -> Result<crate::sign::PublicKey, Error> {
+    decode_key(encoded, SIGNING_PUBLIC_KEY_HRP)
+}
+
+/// Encodes an X25519 public key, such as [`crate::dryocbox::PublicKey`], with
+/// the prefix [`BOX_PUBLIC_KEY_HRP`].
+pub fn encode_box_public_key<PublicKey: ByteArray<CRYPTO_BOX_PUBLICKEYBYTES>>(
+    public_key: &PublicKey,
+) -> String {
+    encode(BOX_PUBLIC_KEY_HRP, public_key.as_slice()).expect("key fits in maximum length")
+}
+
+/// Decodes an X25519 public key. Returns an error if the string has another
+/// prefix.
+pub fn decode_box_public_key(encoded: &str) -> Result<crate::dryocbox::PublicKey, Error> {
+    decode_key(encoded, BOX_PUBLIC_KEY_HRP)
+}
+
+/// Returns the fingerprint of `public_key`: its [generic
+/// hash](crate::generichash), truncated to [`FINGERPRINT_BYTES`], encoded with
+/// the prefix [`FINGERPRINT_HRP`].
+///
+/// Fingerprints are meant to be compared by people, such as when verifying a
+/// key over the phone. Compare decoded fingerprints with
+/// [`decode_fingerprint`], which accepts either case.
+pub fn fingerprint<PublicKey: Bytes + ?Sized>(public_key: &PublicKey) -> String {
+    let digest = GenericHash::<CRYPTO_GENERICHASH_KEYBYTES, FINGERPRINT_BYTES>::digest(public_key);
+    encode(FINGERPRINT_HRP, &digest).expect("fingerprint fits in maximum length")
+}
+
+/// Decodes a fingerprint returned by [`fingerprint`]. Returns an error if the
+/// string has another prefix.
+pub fn decode_fingerprint(encoded: &str) -> Result<[u8; FINGERPRINT_BYTES], Error> {
+    let data = decode_expecting(encoded, FINGERPRINT_HRP)?;
+    validate!(
+        FINGERPRINT_BYTES,
+        FINGERPRINT_BYTES,
+        data.len(),
+        "fingerprint"
+    );
+    let mut output = [0u8; FINGERPRINT_BYTES];
+    output.copy_from_slice(&data);
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bip350_vectors() {
+        for valid in [
+            "A1LQFN3A",
+            "a1lqfn3a",
+            "an83characterlonghumanreadablepartthatcontainsthetheexcludedcharactersbioandnumber11sg7hg6",
+            "abcdef1l7aum6echk45nj3s0wdvt2fg8x9yrzpqzd3ryx",
+            "11llllllllllllllllllllllllllllllllllllllllllllllllllllllllllllllllllllllllllllllllllludsr8",
+            "split1checkupstagehandshakeupstreamerranterredcaperredlc445v",
+            "?1v759aa",
+        ] {
+            decode_values(valid).expect(valid);
+        }
+
+        // only data which is a whole number of bytes decodes
+        let (hrp, data) = decode("abcdef1l7aum6echk45nj3s0wdvt2fg8x9yrzpqzd3ryx").expect("decode");
+        assert_eq!(hrp, "abcdef");
+        assert_eq!(
+            encode(&hrp, &data).expect("encode"),
+            "abcdef1l7aum6echk45nj3s0wdvt2fg8x9yrzpqzd3ryx"
+        );
+        assert!(decode("split1checkupstagehandshakeupstreamerranterredcaperredlc445v").is_ok());
+
+        for invalid in [
+            "\x201xj0phk",
+            "an84characterslonghumanreadablepartthatcontainsthetheexcludedcharactersbioandnumber11d6pts4",
+            "qyrz8wqd2c9m",
+            "1qyrz8wqd2c9m",
+            "y1b0jsk6g",
+            "lt1igcx5c0",
+            "in1muywd",
+            "mm1crxm3i",
+            "au1s5cgom",
+            "M1VUXWEZ",
+            "16plkw9",
+            "1p2gdwpf",
+            // bech32 (not bech32m) checksum
+            "a12uel5l",
+            "Abcdef1l7aum6echk45nj3s0wdvt2fg8x9yrzpqzd3ryx",
+        ] {
+            assert!(decode(invalid).is_err(), "{:?}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_keys() {
+        let keypair = crate::dryocbox::KeyPair::gen();
+        let encoded = encode_box_public_key(&keypair.public_key);
+        assert_eq!(encoded.len(), BOX_PUBLIC_KEY_HRP.len() + 1 + 52 + 6);
+        assert_eq!(
+            decode_box_public_key(&encoded).expect("decode"),
+            keypair.public_key
+        );
+        assert!(decode_signing_public_key(&encoded).is_err());
+
+        // every single character substitution is detected
+        let encoded = encoded.into_bytes();
+        for i in BOX_PUBLIC_KEY_HRP.len() + 1..encoded.len() {
+            for &c in ALPHABET.iter().filter(|&&c| c != encoded[i]) {
+                let mut mistyped = encoded.clone();
+                mistyped[i] = c;
+                let mistyped = String::from_utf8(mistyped).expect("utf8");
+                assert!(decode_box_public_key(&mistyped).is_err());
+            }
+        }
+
+        let fingerprint = fingerprint(&keypair.public_key);
+        assert!(fingerprint.starts_with("fp1"));
+        assert_eq!(
+            decode_fingerprint(&fingerprint.to_uppercase()).expect("decode"),
+            decode_fingerprint(&fingerprint).expect("decode")
+        );
+    }
+}
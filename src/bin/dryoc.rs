@@ -0,0 +1,153 @@
+//! # dryoc command-line tool
+//!
+//! A small companion binary which exercises the library, intended for
+//! generating interop fixtures and inspecting serialized blobs. Keys, boxes,
+//! signed messages, and password hashes are read and written as JSON, using
+//! the same serde representations as the library. Sealing and verifying only
+//! need a public key, so they also accept the output of `dryoc pubkey`, which
+//! omits the secret key.
+//!
+//! Build with `cargo build --features cli`, then run `dryoc help` for usage.
+use std::io::{Read, Write};
+use std::process::ExitCode;
+
+use dryoc::dryocbox::{self, KeyPair, VecBox};
+use dryoc::generichash::Hasher;
+use dryoc::pwhash::VecPwHash;
+use dryoc::sign::{PublicKey, SecretKey, SigningKeyPair, VecSignedMessage};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+const USAGE: &str = "Usage: dryoc <command> [args]
+
+Messages and passwords are read from stdin, and results are written to stdout.
+
+Commands:
+  keygen box                   generate a box keypair
+  keygen sign                  generate a signing keypair
+  pubkey <keypair.json>        print a keypair's public key, without the secret key
+  seal <pubkey.json>           seal a message for a box public key or keypair
+  unseal <keypair.json>        unseal a sealed box with the keypair
+  sign <keypair.json>          sign a message with a signing keypair
+  verify <pubkey.json>         verify a signed message with a signing public key
+                               or keypair, printing the message
+  hash                         print the hex-encoded generic hash of the input
+  pwhash                       hash a password with the default parameters
+  pwhash-verify <hash.json>    verify a password against a password hash
+  help                         print this message";
+
+type CliResult = Result<(), Box<dyn std::error::Error>>;
+
+/// A public key file, as written by `dryoc pubkey`. Keypair files can be read
+/// as public key files too, as their secret key is ignored.
+#[derive(Serialize, Deserialize)]
+struct PublicKeyFile<Key> {
+    public_key: Key,
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+
+    let result = match args.as_slice() {
+        ["keygen", "box"] => write_json(&KeyPair::gen()),
+        ["keygen", "sign"] => write_json(&SigningKeyPair::gen_with_defaults()),
+        ["pubkey", keypair] => pubkey(keypair),
+        ["seal", public_key] => seal(public_key),
+        ["unseal", keypair] => unseal(keypair),
+        ["sign", keypair] => sign(keypair),
+        ["verify", public_key] => verify(public_key),
+        ["hash"] => hash(),
+        ["pwhash"] => pwhash(),
+        ["pwhash-verify", hash] => pwhash_verify(hash),
+        ["help"] | ["--help"] | ["-h"] => {
+            println!("{}", USAGE);
+            Ok(())
+        }
+        _ => {
+            eprintln!("{}", USAGE);
+            return ExitCode::from(2);
+        }
+    };
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("error: {}", err);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn read_stdin() -> Result<Vec<u8>, std::io::Error> {
+    let mut input = Vec::new();
+    std::io::stdin().read_to_end(&mut input)?;
+    Ok(input)
+}
+
+fn read_json<T: DeserializeOwned>(path: &str) -> Result<T, Box<dyn std::error::Error>> {
+    let file = std::fs::File::open(path)?;
+    Ok(serde_json::from_reader(file)?)
+}
+
+fn write_json<T: Serialize>(value: &T) -> CliResult {
+    let mut stdout = std::io::stdout();
+    serde_json::to_writer_pretty(&mut stdout, value)?;
+    writeln!(stdout)?;
+    Ok(())
+}
+
+fn pubkey(keypair: &str) -> CliResult {
+    // box and signing keypairs both have a public_key field
+    let keypair: PublicKeyFile<serde_json::Value> = read_json(keypair)?;
+    write_json(&keypair)
+}
+
+fn seal(public_key: &str) -> CliResult {
+    let public_key: PublicKeyFile<dryocbox::PublicKey> = read_json(public_key)?;
+    let message = read_stdin()?;
+    let dryocbox = VecBox::seal_to_vecbox(&message, &public_key.public_key)?;
+    write_json(&dryocbox)
+}
+
+fn unseal(keypair: &str) -> CliResult {
+    let keypair: KeyPair = read_json(keypair)?;
+    let dryocbox: VecBox = serde_json::from_slice(&read_stdin()?)?;
+    let message = dryocbox.unseal_to_vec(&keypair)?;
+    std::io::stdout().write_all(&message)?;
+    Ok(())
+}
+
+fn sign(keypair: &str) -> CliResult {
+    let keypair: SigningKeyPair<PublicKey, SecretKey> = read_json(keypair)?;
+    let signed: VecSignedMessage = keypair.sign_with_defaults(read_stdin()?)?;
+    write_json(&signed)
+}
+
+fn verify(public_key: &str) -> CliResult {
+    let public_key: PublicKeyFile<PublicKey> = read_json(public_key)?;
+    let signed: VecSignedMessage = serde_json::from_slice(&read_stdin()?)?;
+    signed.verify(&public_key.public_key)?;
+    let (_, message) = signed.into_parts();
+    std::io::stdout().write_all(&message)?;
+    Ok(())
+}
+
+fn hash() -> CliResult {
+    let hash = Hasher::<32>::digest(&read_stdin()?);
+    let hex: String = hash.iter().map(|b| format!("{:02x}", b)).collect();
+    println!("{}", hex);
+    Ok(())
+}
+
+fn pwhash() -> CliResult {
+    let password = read_stdin()?;
+    write_json(&VecPwHash::hash_with_defaults(&password)?)
+}
+
+fn pwhash_verify(hash: &str) -> CliResult {
+    let hash: VecPwHash = read_json(hash)?;
+    hash.verify(&read_stdin()?)?;
+    println!("ok");
+    Ok(())
+}
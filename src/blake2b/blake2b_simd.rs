@@ -421,6 +421,9 @@ impl State {
     fn init_param(params: &Params) -> Self {
         let mut state = Self::default();
         state.init0();
+        // Allocate the whole buffer up front, so that it's never reallocated
+        // (leaving unwiped copies of the input behind)
+        state.buf.reserve_exact(2 * BLOCKBYTES);
 
         let pslice = unsafe {
             std::slice::from_raw_parts(
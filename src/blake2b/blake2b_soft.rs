@@ -145,6 +145,9 @@ impl State {
     fn init_param(params: &Params) -> Self {
         let mut state = Self::default();
         state.init0();
+        // Allocate the whole buffer up front, so that it's never reallocated
+        // (leaving unwiped copies of the input behind)
+        state.buf.reserve_exact(2 * BLOCKBYTES);
 
         let pslice = unsafe {
             std::slice::from_raw_parts(
@@ -489,11 +492,11 @@ mod tests {
             let mut block = vec![0u8; i];
             copy_randombytes(&mut block);
 
-            unsafe { blake2b_update(&mut s, block.as_ptr() as *const u8, block.len() as u64) };
+            unsafe { blake2b_update(&mut s, block.as_ptr(), block.len() as u64) };
 
             state.update(&block);
 
-            unsafe { blake2b_update(&mut s, block.as_ptr() as *const u8, block.len() as u64) };
+            unsafe { blake2b_update(&mut s, block.as_ptr(), block.len() as u64) };
 
             state.update(&block);
 
@@ -503,7 +506,7 @@ mod tests {
             unsafe {
                 blake2b_final(
                     &mut s,
-                    so_output.as_mut_ptr() as *mut u8,
+                    so_output.as_mut_ptr(),
                     so_output.len() as u64,
                 )
             };
@@ -550,7 +553,7 @@ mod tests {
         unsafe {
             blake2b_final(
                 &mut s,
-                so_output.as_mut_ptr() as *mut u8,
+                so_output.as_mut_ptr(),
                 so_output.len() as u64,
             )
         };
@@ -574,9 +577,9 @@ mod tests {
 
             unsafe {
                 blake2b_long(
-                    so_output.as_mut_ptr() as *mut u8,
+                    so_output.as_mut_ptr(),
                     so_output.len() as u64,
-                    input.as_ptr() as *const u8,
+                    input.as_ptr(),
                     input.len() as u64,
                 )
             };
@@ -601,9 +604,9 @@ mod tests {
 
             unsafe {
                 blake2b_long(
-                    so_output.as_mut_ptr() as *mut u8,
+                    so_output.as_mut_ptr(),
                     so_output.len() as u64,
-                    input.as_ptr() as *const u8,
+                    input.as_ptr(),
                     input.len() as u64,
                 )
             };
@@ -0,0 +1,334 @@
+//! # Blind signatures
+//!
+//! A blind Schnorr signature scheme over ristretto255. The signer (server)
+//! signs a message chosen by the client (user) without learning the message,
+//! and without being able to link the resulting signature to the signing
+//! session that produced it. This is the building block for anonymous
+//! tokens: the server can check that a token was issued by it, but not to
+//! whom.
+//!
+//! Each signature takes one round trip:
+//!
+//! 1. The server calls [`SignerKeyPair::commit`], keeps the [`SignerSession`],
+//!    and sends the [`Commitment`] to the client.
+//! 2. The client calls [`BlindingClient::blind`] with the server's public key,
+//!    the message, and the commitment, keeps the [`BlindingClient`], and sends
+//!    the [`BlindedChallenge`] to the server.
+//! 3. The server calls [`SignerKeyPair::respond`] with the session and the
+//!    challenge, and sends the [`BlindResponse`] to the client.
+//! 4. The client calls [`BlindingClient::unblind`], which checks the response
+//!    and returns the [`Signature`].
+//!
+//! Anyone can then check the signature with [`verify`].
+//!
+//! ## Concurrent sessions
+//!
+//! Blind Schnorr signatures are only secure if the server doesn't have many
+//! signing sessions open at the same time: with enough concurrent sessions
+//! (on the order of the bit length of the group order), a client can forge
+//! one more signature than it was issued, using the ROS attack by Benhamouda
+//! et al. Servers should bound the number of open sessions per key, or
+//! complete each session before starting the next. Each [`SignerSession`] is
+//! consumed when responding, so it can't be used twice.
+//!
+//! If the `serde` feature is enabled, the [`serde::Deserialize`] and
+//! [`serde::Serialize`] traits will be implemented for the protocol messages
+//! and [`SignerKeyPair`].
+//!
+//! ## Example
+//!
+//! ```
+//! use dryoc::blindsig::*;
+//!
+//! let signer = SignerKeyPair::gen();
+//! let public_key = signer.public_key().clone();
+//! let message = b"token-1234";
+//!
+//! // Server
+//! let (session, commitment) = signer.commit();
+//!
+//! // Client
+//! let (client, challenge) =
+//!     BlindingClient::blind(&public_key, message, &commitment).expect("blind failed");
+//!
+//! // Server
+//! let response = signer.respond(session, &challenge).expect("respond failed");
+//!
+//! // Client
+//! let signature = client.unblind(&response).expect("unblind failed");
+//!
+//! verify(&signature, message, &public_key).expect("verify failed");
+//! ```
+use curve25519_dalek::constants::RISTRETTO_BASEPOINT_TABLE;
+use curve25519_dalek::scalar::Scalar;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha512};
+use zeroize::{Zeroize, ZeroizeOnDrop};
+
+use crate::error::Error;
+use crate::ristretto_util::{decode_element, decode_scalar, random_scalar};
+use crate::types::*;
+
+/// Length of a public key, an encoded ristretto255 group element.
+pub const BLINDSIG_PUBLICKEYBYTES: usize = 32;
+/// Length of a secret key, an encoded ristretto255 scalar.
+pub const BLINDSIG_SECRETKEYBYTES: usize = 32;
+/// Length of a signature.
+pub const BLINDSIG_BYTES: usize = 64;
+
+const DST_CHALLENGE: &[u8] = b"dryoc-blindsig-v1 Challenge";
+
+/// Public key, an encoded ristretto255 group element.
+pub type PublicKey = StackByteArray<BLINDSIG_PUBLICKEYBYTES>;
+/// Secret key, an encoded ristretto255 scalar.
+pub type SecretKey = StackByteArray<BLINDSIG_SECRETKEYBYTES>;
+/// The server's commitment, sent to the client at the start of a session.
+pub type Commitment = StackByteArray<32>;
+/// The client's blinded challenge, sent to the server.
+pub type BlindedChallenge = StackByteArray<32>;
+/// The server's response to a blinded challenge, sent to the client.
+pub type BlindResponse = StackByteArray<32>;
+/// Unblinded signature.
+pub type Signature = StackByteArray<BLINDSIG_BYTES>;
+
+#[cfg_attr(
+    feature = "serde",
+    derive(Zeroize, ZeroizeOnDrop, Clone, Serialize, Deserialize)
+)]
+#[cfg_attr(not(feature = "serde"), derive(Zeroize, ZeroizeOnDrop, Clone))]
+/// The server's signing keypair.
+pub struct SignerKeyPair {
+    public_key: PublicKey,
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::redact::serialize_secret")
+    )]
+    secret_key: SecretKey,
+}
+
+/// Server state for a single signing session, between
+/// [`SignerKeyPair::commit`] and [`SignerKeyPair::respond`].
+#[derive(Zeroize, ZeroizeOnDrop)]
+pub struct SignerSession {
+    nonce: Scalar,
+}
+
+/// Client state between [`BlindingClient::blind`] and
+/// [`BlindingClient::unblind`].
+#[derive(Zeroize, ZeroizeOnDrop)]
+pub struct BlindingClient {
+    public_key: PublicKey,
+    commitment: Commitment,
+    alpha: Scalar,
+    blinded_challenge: Scalar,
+    blinded_commitment: [u8; 32],
+}
+
+fn challenge(commitment: &[u8; 32], public_key: &[u8; 32], message: &[u8]) -> Scalar {
+    let hash: [u8; 64] = Sha512::new()
+        .chain_update(DST_CHALLENGE)
+        .chain_update(commitment)
+        .chain_update(public_key)
+        .chain_update(message)
+        .finalize()
+        .into();
+    Scalar::from_bytes_mod_order_wide(&hash)
+}
+
+impl SignerKeyPair {
+    /// Generates a new random signing keypair.
+    pub fn gen() -> Self {
+        Self::from_secret_scalar(random_scalar())
+    }
+
+    /// Loads a signing keypair from `secret_key`. Returns an error if the
+    /// secret key isn't a canonical, non-zero scalar.
+    pub fn from_secret_key<SK: ByteArray<BLINDSIG_SECRETKEYBYTES>>(
+        secret_key: &SK,
+    ) -> Result<Self, Error> {
+        let secret = decode_scalar(secret_key.as_array())?;
+        if secret == Scalar::ZERO {
+            return Err(dryoc_error!("invalid secret key"));
+        }
+        Ok(Self::from_secret_scalar(secret))
+    }
+
+    fn from_secret_scalar(mut secret: Scalar) -> Self {
+        let keypair = Self {
+            public_key: PublicKey::from(
+                (RISTRETTO_BASEPOINT_TABLE * &secret).compress().to_bytes(),
+            ),
+            secret_key: SecretKey::from(secret.to_bytes()),
+        };
+        secret.zeroize();
+        keypair
+    }
+
+    /// Returns the public key, which clients use to blind messages and verify
+    /// signatures.
+    pub fn public_key(&self) -> &PublicKey {
+        &self.public_key
+    }
+
+    /// Returns the secret key.
+    pub fn secret_key(&self) -> &SecretKey {
+        &self.secret_key
+    }
+
+    /// Starts a new signing session. Returns the session state, and the
+    /// commitment to send to the client.
+    pub fn commit(&self) -> (SignerSession, Commitment) {
+        let nonce = random_scalar();
+        let commitment =
+            Commitment::from((RISTRETTO_BASEPOINT_TABLE * &nonce).compress().to_bytes());
+        (SignerSession { nonce }, commitment)
+    }
+
+    /// Responds to the client's blinded `challenge`, consuming `session`.
+    /// Returns the response to send to the client.
+    pub fn respond(
+        &self,
+        session: SignerSession,
+        challenge: &BlindedChallenge,
+    ) -> Result<BlindResponse, Error> {
+        let challenge = decode_scalar(challenge.as_array())?;
+        let mut secret = decode_scalar(self.secret_key.as_array())?;
+        let mut response = session.nonce + challenge * secret;
+        secret.zeroize();
+        let encoded = BlindResponse::from(response.to_bytes());
+        response.zeroize();
+        Ok(encoded)
+    }
+}
+
+impl BlindingClient {
+    /// Blinds `message` for signing by the holder of `public_key`, using the
+    /// server's `commitment`. Returns the client's state, and the blinded
+    /// challenge to send to the server.
+    pub fn blind<PK: ByteArray<BLINDSIG_PUBLICKEYBYTES>>(
+        public_key: &PK,
+        message: &[u8],
+        commitment: &Commitment,
+    ) -> Result<(Self, BlindedChallenge), Error> {
+        let public_key_point = decode_element(public_key.as_array())?;
+        let commitment_point = decode_element(commitment.as_array())?;
+
+        let alpha = random_scalar();
+        let mut beta = random_scalar();
+        let blinded_commitment =
+            (commitment_point + RISTRETTO_BASEPOINT_TABLE * &alpha + beta * public_key_point)
+                .compress()
+                .to_bytes();
+        let challenge = challenge(&blinded_commitment, public_key.as_array(), message);
+        let blinded_challenge = challenge + beta;
+        beta.zeroize();
+
+        Ok((
+            Self {
+                public_key: PublicKey::from(*public_key.as_array()),
+                commitment: commitment.clone(),
+                alpha,
+                blinded_challenge,
+                blinded_commitment,
+            },
+            BlindedChallenge::from(blinded_challenge.to_bytes()),
+        ))
+    }
+
+    /// Unblinds the server's `response`, returning the signature. Returns an
+    /// error if the response isn't valid for the server's public key.
+    pub fn unblind(self, response: &BlindResponse) -> Result<Signature, Error> {
+        let response = decode_scalar(response.as_array())?;
+        let public_key = decode_element(self.public_key.as_array())?;
+        let commitment = decode_element(self.commitment.as_array())?;
+
+        if RISTRETTO_BASEPOINT_TABLE * &response != commitment + self.blinded_challenge * public_key
+        {
+            return Err(dryoc_error!("invalid response from signer"));
+        }
+
+        let s = response + self.alpha;
+        let mut signature = Signature::new_byte_array();
+        signature.as_mut_slice()[..32].copy_from_slice(&self.blinded_commitment);
+        signature.as_mut_slice()[32..].copy_from_slice(s.as_bytes());
+        Ok(signature)
+    }
+}
+
+/// Verifies that `signature` is a valid signature of `message` by the holder
+/// of `public_key`.
+pub fn verify<PK: ByteArray<BLINDSIG_PUBLICKEYBYTES>>(
+    signature: &Signature,
+    message: &[u8],
+    public_key: &PK,
+) -> Result<(), Error> {
+    let mut commitment = [0u8; 32];
+    let mut s = [0u8; 32];
+    commitment.copy_from_slice(&signature.as_slice()[..32]);
+    s.copy_from_slice(&signature.as_slice()[32..]);
+
+    let commitment_point = decode_element(&commitment)?;
+    let public_key_point = decode_element(public_key.as_array())?;
+    let s = decode_scalar(&s)?;
+    let c = challenge(&commitment, public_key.as_array(), message);
+
+    if RISTRETTO_BASEPOINT_TABLE * &s == commitment_point + c * public_key_point {
+        Ok(())
+    } else {
+        Err(dryoc_error!("signature mismatch"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn issue(signer: &SignerKeyPair, message: &[u8]) -> (BlindedChallenge, Signature) {
+        let (session, commitment) = signer.commit();
+        let (client, challenge) =
+            BlindingClient::blind(signer.public_key(), message, &commitment).expect("blind");
+        let response = signer.respond(session, &challenge).expect("respond");
+        (challenge, client.unblind(&response).expect("unblind"))
+    }
+
+    #[test]
+    fn test_blind_signature() {
+        let signer = SignerKeyPair::gen();
+        let (challenge, signature) = issue(&signer, b"message");
+        verify(&signature, b"message", signer.public_key()).expect("verify");
+        assert!(verify(&signature, b"other message", signer.public_key()).is_err());
+        assert!(verify(&signature, b"message", SignerKeyPair::gen().public_key()).is_err());
+
+        // the server never sees the signature's commitment or challenge
+        let (challenge2, signature2) = issue(&signer, b"message");
+        assert_ne!(signature, signature2);
+        assert_ne!(challenge, challenge2);
+
+        let loaded = SignerKeyPair::from_secret_key(signer.secret_key()).expect("load");
+        assert_eq!(loaded.public_key(), signer.public_key());
+        assert!(SignerKeyPair::from_secret_key(&SecretKey::default()).is_err());
+    }
+
+    #[test]
+    fn test_invalid_response() {
+        let signer = SignerKeyPair::gen();
+        let (session, commitment) = signer.commit();
+        let (client, challenge) =
+            BlindingClient::blind(signer.public_key(), b"message", &commitment).expect("blind");
+
+        // a response from a different session is detected by the client
+        let (other_session, _) = signer.commit();
+        let response = signer.respond(other_session, &challenge).expect("respond");
+        assert!(client.unblind(&response).is_err());
+
+        assert!(
+            signer
+                .respond(session, &BlindedChallenge::from([0xff; 32]))
+                .is_err()
+        );
+        assert!(
+            BlindingClient::blind(signer.public_key(), b"message", &Commitment::default()).is_err()
+        );
+    }
+}
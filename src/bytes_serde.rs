@@ -81,7 +81,7 @@ mod protected {
         where
             S: Serializer,
         {
-            serializer.serialize_bytes(self.as_slice())
+            crate::redact::serialize_secret(self, serializer)
         }
     }
 
@@ -99,7 +99,7 @@ mod protected {
         where
             S: Serializer,
         {
-            serializer.serialize_bytes(self.as_slice())
+            crate::redact::serialize_secret(self, serializer)
         }
     }
 
@@ -108,7 +108,7 @@ mod protected {
         where
             S: Serializer,
         {
-            serializer.serialize_bytes(self.as_slice())
+            crate::redact::serialize_secret(self, serializer)
         }
     }
 
@@ -0,0 +1,192 @@
+//! # Canonical serialization
+//!
+//! [`CanonicalEncode`] and [`CanonicalDecode`] provide a deterministic
+//! serialization profile for dryoc's signable and storable structures. Unlike
+//! the output of serde, which depends on the serializer, its version, and the
+//! order fields are declared in, a structure's canonical encoding is fixed,
+//! so it can safely be signed, hashed, or compared byte for byte.
+//!
+//! Structures are encoded using the core deterministic encoding of CBOR ([RFC
+//! 8949, section 4.2.1](https://www.rfc-editor.org/rfc/rfc8949#section-4.2.1)):
+//! integers and lengths always use their shortest form, and map entries are
+//! sorted by their encoded keys. Each structure is a map with small integer
+//! keys. Key `0` holds a text label naming the structure (such as
+//! `"dryoc/SignedMessage"`), so the encoding of one structure can't be
+//! mistaken for another, and keys from `1` onwards hold its fields. Nested
+//! records are encoded as arrays of their fields.
+//!
+//! Decoding is strict: any input which isn't exactly the canonical encoding
+//! of a structure is rejected, so each structure has exactly one encoding.
+//!
+//! [`Manifest`](crate::manifest::Manifest) has its own deterministic binary
+//! encoding, which these traits use as is.
+//!
+//! ## Example
+//!
+//! ```
+//! use dryoc::canonical::*;
+//! use dryoc::sign::{SigningKeyPair, VecSignedMessage};
+//!
+//! let keypair = SigningKeyPair::gen_with_defaults();
+//! let signed = keypair
+//!     .sign_with_defaults(b"message".to_vec())
+//!     .expect("sign failed");
+//!
+//! let bytes = signed.to_canonical_bytes();
+//! let decoded = VecSignedMessage::from_canonical_bytes(&bytes).expect("decode failed");
+//! decoded.verify(&keypair.public_key).expect("verify failed");
+//!
+//! // The encoding is stable, and round-trips exactly
+//! assert_eq!(decoded.to_canonical_bytes(), bytes);
+//! ```
+
+use crate::cbor::Value;
+use crate::error::Error;
+use crate::types::*;
+
+/// Structures which have a canonical, deterministic encoding.
+pub trait CanonicalEncode {
+    /// Returns the canonical encoding of this structure.
+    fn to_canonical_bytes(&self) -> Vec<u8>;
+}
+
+/// Structures which can be decoded from their canonical encoding.
+pub trait CanonicalDecode: Sized {
+    /// Decodes a structure from its canonical encoding, rejecting any input
+    /// which isn't canonically encoded.
+    fn from_canonical_bytes(bytes: &[u8]) -> Result<Self, Error>;
+}
+
+/// Encodes a structure labelled `label`, with `fields` numbered from 1.
+pub(crate) fn encode(label: &str, fields: Vec<Value>) -> Vec<u8> {
+    let entries = std::iter::once(Value::Text(label.to_string()))
+        .chain(fields)
+        .enumerate()
+        .map(|(key, value)| (Value::Unsigned(key as u64), value))
+        .collect();
+    Value::Map(entries).to_canonical_vec()
+}
+
+/// Decodes a structure labelled `label`, returning its `count` fields.
+pub(crate) fn decode(label: &str, bytes: &[u8], count: usize) -> Result<Vec<Value>, Error> {
+    let entries = match Value::from_canonical_slice(bytes)? {
+        Value::Map(entries) => entries,
+        _ => return Err(dryoc_error!("canonical encoding is not a map")),
+    };
+    if entries.len() != count + 1 {
+        return Err(dryoc_error!(format!(
+            "expected {} fields for {}, got {}",
+            count,
+            label,
+            entries.len().saturating_sub(1)
+        )));
+    }
+    let mut fields = Vec::with_capacity(count);
+    for (index, (key, value)) in entries.into_iter().enumerate() {
+        if key != Value::Unsigned(index as u64) {
+            return Err(dryoc_error!(format!("unexpected field in {}", label)));
+        }
+        if index == 0 {
+            if value.as_text() != Some(label) {
+                return Err(dryoc_error!(format!("expected {}", label)));
+            }
+        } else {
+            fields.push(value);
+        }
+    }
+    Ok(fields)
+}
+
+/// Returns the contents of a byte string field.
+pub(crate) fn bytes_field(value: &Value) -> Result<&[u8], Error> {
+    value
+        .as_bytes()
+        .ok_or_else(|| dryoc_error!("expected a byte string field"))
+}
+
+/// Returns a new fixed-length byte array, copied from a byte string field.
+pub(crate) fn byte_array_field<const LENGTH: usize, Output: NewByteArray<LENGTH>>(
+    value: &Value,
+) -> Result<Output, Error> {
+    let bytes = bytes_field(value)?;
+    if bytes.len() != LENGTH {
+        return Err(dryoc_error!(format!(
+            "expected a {} byte field, got {}",
+            LENGTH,
+            bytes.len()
+        )));
+    }
+    let mut output = Output::new_byte_array();
+    output.copy_from_slice(bytes);
+    Ok(output)
+}
+
+/// Returns a new byte array, copied from a byte string field.
+pub(crate) fn new_bytes_field<Output: NewBytes + ResizableBytes>(
+    value: &Value,
+) -> Result<Output, Error> {
+    let bytes = bytes_field(value)?;
+    let mut output = Output::new_bytes();
+    output.resize(bytes.len(), 0);
+    output.copy_from_slice(bytes);
+    Ok(output)
+}
+
+/// Returns the contents of a text string field.
+pub(crate) fn text_field(value: &Value) -> Result<&str, Error> {
+    value
+        .as_text()
+        .ok_or_else(|| dryoc_error!("expected a text string field"))
+}
+
+/// Returns the value of an unsigned integer field.
+pub(crate) fn unsigned_field(value: &Value) -> Result<u64, Error> {
+    value
+        .as_unsigned()
+        .ok_or_else(|| dryoc_error!("expected an unsigned integer field"))
+}
+
+/// Returns the fields of a nested record with `count` fields.
+pub(crate) fn record_field(value: &Value, count: usize) -> Result<&[Value], Error> {
+    match value.as_array() {
+        Some(fields) if fields.len() == count => Ok(fields),
+        _ => Err(dryoc_error!(format!(
+            "expected a record with {} fields",
+            count
+        ))),
+    }
+}
+
+/// Returns the items of an array field.
+pub(crate) fn array_field(value: &Value) -> Result<&[Value], Error> {
+    value
+        .as_array()
+        .ok_or_else(|| dryoc_error!("expected an array field"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_decode() {
+        let bytes = encode(
+            "dryoc/Test",
+            vec![Value::Bytes(vec![1, 2, 3]), Value::Unsigned(7)],
+        );
+        let fields = decode("dryoc/Test", &bytes, 2).expect("decode failed");
+        assert_eq!(bytes_field(&fields[0]).unwrap(), &[1, 2, 3]);
+        assert_eq!(unsigned_field(&fields[1]).unwrap(), 7);
+
+        decode("dryoc/Other", &bytes, 2).expect_err("label should mismatch");
+        decode("dryoc/Test", &bytes, 3).expect_err("count should mismatch");
+        text_field(&fields[0]).expect_err("type should mismatch");
+
+        // a non-canonical encoding of the same map
+        let mut unsorted = Vec::new();
+        unsorted.push(0xa3);
+        unsorted.extend_from_slice(&[0x02, 0x07]);
+        unsorted.extend_from_slice(&bytes[1..bytes.len() - 2]);
+        decode("dryoc/Test", &unsorted, 2).expect_err("should be rejected");
+    }
+}
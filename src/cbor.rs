@@ -0,0 +1,406 @@
+//! A minimal encoder and decoder for the subset of CBOR ([RFC
+//! 8949](https://www.rfc-editor.org/rfc/rfc8949)) needed for signed and
+//! encrypted structures: integers, byte and text strings, arrays, maps, tags,
+//! and the simple values `false`, `true`, and `null`.
+//!
+//! Encoding always uses the shortest form for lengths and integers, as
+//! required for deterministic encoding, and map entries are written in the
+//! order given. [`Value::to_canonical_vec`] additionally sorts map entries by
+//! their encoded keys, producing the core deterministic encoding of RFC 8949
+//! section 4.2.1. Decoding only accepts definite lengths, and rejects floats.
+//!
+//! This is used internally, and isn't exposed as part of the public API.
+use crate::error::Error;
+
+const MAX_DEPTH: usize = 16;
+
+const MAJOR_UNSIGNED: u8 = 0;
+const MAJOR_NEGATIVE: u8 = 1;
+const MAJOR_BYTES: u8 = 2;
+const MAJOR_TEXT: u8 = 3;
+const MAJOR_ARRAY: u8 = 4;
+const MAJOR_MAP: u8 = 5;
+const MAJOR_TAG: u8 = 6;
+const MAJOR_SIMPLE: u8 = 7;
+
+const SIMPLE_FALSE: u8 = 20;
+const SIMPLE_TRUE: u8 = 21;
+const SIMPLE_NULL: u8 = 22;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) enum Value {
+    Unsigned(u64),
+    /// A negative integer, with the value `-1 - n`
+    Negative(u64),
+    Bytes(Vec<u8>),
+    Text(String),
+    Array(Vec<Value>),
+    Map(Vec<(Value, Value)>),
+    Tag(u64, Box<Value>),
+    Bool(bool),
+    Null,
+}
+
+impl From<i64> for Value {
+    fn from(value: i64) -> Self {
+        if value < 0 {
+            Value::Negative(!value as u64)
+        } else {
+            Value::Unsigned(value as u64)
+        }
+    }
+}
+
+impl Value {
+    /// Returns the encoding of this value.
+    pub(crate) fn to_vec(&self) -> Vec<u8> {
+        let mut output = Vec::new();
+        self.encode(&mut output);
+        output
+    }
+
+    /// Decodes a single value, which must span all of `bytes`.
+    pub(crate) fn from_slice(bytes: &[u8]) -> Result<Self, Error> {
+        let mut decoder = Decoder { bytes, position: 0 };
+        let value = decoder.value(0)?;
+        if decoder.position != bytes.len() {
+            return Err(dryoc_error!("trailing bytes after CBOR value"));
+        }
+        Ok(value)
+    }
+
+    /// Returns the core deterministic encoding of this value, with map
+    /// entries sorted by the bytewise order of their encoded keys.
+    pub(crate) fn to_canonical_vec(&self) -> Vec<u8> {
+        let mut output = Vec::new();
+        self.encode_canonical(&mut output);
+        output
+    }
+
+    /// Decodes a single value, which must span all of `bytes` and must be in
+    /// core deterministic encoding, without duplicate map keys.
+    pub(crate) fn from_canonical_slice(bytes: &[u8]) -> Result<Self, Error> {
+        let value = Self::from_slice(bytes)?;
+        if value.to_canonical_vec() != bytes || value.has_duplicate_keys() {
+            return Err(dryoc_error!("CBOR value is not canonically encoded"));
+        }
+        Ok(value)
+    }
+
+    fn has_duplicate_keys(&self) -> bool {
+        match self {
+            Value::Array(items) => items.iter().any(Value::has_duplicate_keys),
+            Value::Map(entries) => {
+                entries
+                    .iter()
+                    .enumerate()
+                    .any(|(i, (key, _))| entries[..i].iter().any(|(other, _)| other == key))
+                    || entries
+                        .iter()
+                        .any(|(key, value)| key.has_duplicate_keys() || value.has_duplicate_keys())
+            }
+            Value::Tag(_, value) => value.has_duplicate_keys(),
+            _ => false,
+        }
+    }
+
+    fn encode_canonical(&self, output: &mut Vec<u8>) {
+        match self {
+            Value::Array(items) => {
+                encode_head(output, MAJOR_ARRAY, items.len() as u64);
+                items.iter().for_each(|item| item.encode_canonical(output));
+            }
+            Value::Map(entries) => {
+                let mut encoded: Vec<(Vec<u8>, Vec<u8>)> = entries
+                    .iter()
+                    .map(|(key, value)| (key.to_canonical_vec(), value.to_canonical_vec()))
+                    .collect();
+                encoded.sort();
+                encode_head(output, MAJOR_MAP, encoded.len() as u64);
+                for (key, value) in encoded {
+                    output.extend_from_slice(&key);
+                    output.extend_from_slice(&value);
+                }
+            }
+            Value::Tag(tag, value) => {
+                encode_head(output, MAJOR_TAG, *tag);
+                value.encode_canonical(output);
+            }
+            _ => self.encode(output),
+        }
+    }
+
+    fn encode(&self, output: &mut Vec<u8>) {
+        match self {
+            Value::Unsigned(n) => encode_head(output, MAJOR_UNSIGNED, *n),
+            Value::Negative(n) => encode_head(output, MAJOR_NEGATIVE, *n),
+            Value::Bytes(bytes) => {
+                encode_head(output, MAJOR_BYTES, bytes.len() as u64);
+                output.extend_from_slice(bytes);
+            }
+            Value::Text(text) => {
+                encode_head(output, MAJOR_TEXT, text.len() as u64);
+                output.extend_from_slice(text.as_bytes());
+            }
+            Value::Array(items) => {
+                encode_head(output, MAJOR_ARRAY, items.len() as u64);
+                items.iter().for_each(|item| item.encode(output));
+            }
+            Value::Map(entries) => {
+                encode_head(output, MAJOR_MAP, entries.len() as u64);
+                for (key, value) in entries {
+                    key.encode(output);
+                    value.encode(output);
+                }
+            }
+            Value::Tag(tag, value) => {
+                encode_head(output, MAJOR_TAG, *tag);
+                value.encode(output);
+            }
+            Value::Bool(false) => output.push(MAJOR_SIMPLE << 5 | SIMPLE_FALSE),
+            Value::Bool(true) => output.push(MAJOR_SIMPLE << 5 | SIMPLE_TRUE),
+            Value::Null => output.push(MAJOR_SIMPLE << 5 | SIMPLE_NULL),
+        }
+    }
+
+    /// Returns the value for integer `key`, if this is a map containing it.
+    pub(crate) fn get(&self, key: i64) -> Option<&Value> {
+        let key = Value::from(key);
+        match self {
+            Value::Map(entries) => entries.iter().find(|(k, _)| *k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn as_bytes(&self) -> Option<&[u8]> {
+        match self {
+            Value::Bytes(bytes) => Some(bytes),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn as_text(&self) -> Option<&str> {
+        match self {
+            Value::Text(text) => Some(text),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn as_unsigned(&self) -> Option<u64> {
+        match self {
+            Value::Unsigned(n) => Some(*n),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn as_integer(&self) -> Option<i64> {
+        match self {
+            Value::Unsigned(n) => i64::try_from(*n).ok(),
+            Value::Negative(n) => i64::try_from(*n).ok().map(|n| !n),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn as_array(&self) -> Option<&[Value]> {
+        match self {
+            Value::Array(items) => Some(items),
+            _ => None,
+        }
+    }
+}
+
+fn encode_head(output: &mut Vec<u8>, major: u8, n: u64) {
+    let major = major << 5;
+    if n < 24 {
+        output.push(major | n as u8);
+    } else if n <= u8::MAX as u64 {
+        output.push(major | 24);
+        output.push(n as u8);
+    } else if n <= u16::MAX as u64 {
+        output.push(major | 25);
+        output.extend_from_slice(&(n as u16).to_be_bytes());
+    } else if n <= u32::MAX as u64 {
+        output.push(major | 26);
+        output.extend_from_slice(&(n as u32).to_be_bytes());
+    } else {
+        output.push(major | 27);
+        output.extend_from_slice(&n.to_be_bytes());
+    }
+}
+
+struct Decoder<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Decoder<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], Error> {
+        if self.bytes.len() - self.position < len {
+            return Err(dryoc_error!("truncated CBOR value"));
+        }
+        let bytes = &self.bytes[self.position..self.position + len];
+        self.position += len;
+        Ok(bytes)
+    }
+
+    fn head(&mut self) -> Result<(u8, u8, u64), Error> {
+        let initial = self.take(1)?[0];
+        let (major, info) = (initial >> 5, initial & 0x1f);
+        let n = match info {
+            0..=23 => info as u64,
+            24 => self.take(1)?[0] as u64,
+            25 => u16::from_be_bytes([self.take(1)?[0], self.take(1)?[0]]) as u64,
+            26 => {
+                let mut n = [0u8; 4];
+                n.copy_from_slice(self.take(4)?);
+                u32::from_be_bytes(n) as u64
+            }
+            27 => {
+                let mut n = [0u8; 8];
+                n.copy_from_slice(self.take(8)?);
+                u64::from_be_bytes(n)
+            }
+            _ => return Err(dryoc_error!("unsupported CBOR length encoding")),
+        };
+        Ok((major, info, n))
+    }
+
+    fn length(&self, n: u64) -> Result<usize, Error> {
+        // Every item takes at least one byte, so any length greater than the
+        // remaining input is invalid
+        if n > (self.bytes.len() - self.position) as u64 {
+            Err(dryoc_error!("truncated CBOR value"))
+        } else {
+            Ok(n as usize)
+        }
+    }
+
+    fn value(&mut self, depth: usize) -> Result<Value, Error> {
+        if depth > MAX_DEPTH {
+            return Err(dryoc_error!("CBOR value nested too deeply"));
+        }
+
+        let (major, info, n) = self.head()?;
+        match major {
+            MAJOR_UNSIGNED => Ok(Value::Unsigned(n)),
+            MAJOR_NEGATIVE => Ok(Value::Negative(n)),
+            MAJOR_BYTES => {
+                let len = self.length(n)?;
+                Ok(Value::Bytes(self.take(len)?.to_vec()))
+            }
+            MAJOR_TEXT => {
+                let len = self.length(n)?;
+                String::from_utf8(self.take(len)?.to_vec())
+                    .map(Value::Text)
+                    .map_err(|_| dryoc_error!("invalid UTF-8 in CBOR text string"))
+            }
+            MAJOR_ARRAY => {
+                let len = self.length(n)?;
+                let mut items = Vec::with_capacity(len);
+                for _ in 0..len {
+                    items.push(self.value(depth + 1)?);
+                }
+                Ok(Value::Array(items))
+            }
+            MAJOR_MAP => {
+                let len = self.length(n)?;
+                let mut entries = Vec::with_capacity(len);
+                for _ in 0..len {
+                    let key = self.value(depth + 1)?;
+                    let value = self.value(depth + 1)?;
+                    entries.push((key, value));
+                }
+                Ok(Value::Map(entries))
+            }
+            MAJOR_TAG => Ok(Value::Tag(n, Box::new(self.value(depth + 1)?))),
+            _ => match info {
+                SIMPLE_FALSE => Ok(Value::Bool(false)),
+                SIMPLE_TRUE => Ok(Value::Bool(true)),
+                SIMPLE_NULL => Ok(Value::Null),
+                _ => Err(dryoc_error!("unsupported CBOR simple value")),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cbor() {
+        // RFC 8949, appendix A
+        let vectors: Vec<(Value, &str)> = vec![
+            (Value::from(0), "00"),
+            (Value::from(23), "17"),
+            (Value::from(24), "1818"),
+            (Value::from(1000), "1903e8"),
+            (Value::from(1000000), "1a000f4240"),
+            (Value::Unsigned(18446744073709551615), "1bffffffffffffffff"),
+            (Value::from(-1), "20"),
+            (Value::from(-1000), "3903e7"),
+            (Value::Bytes(vec![1, 2, 3, 4]), "4401020304"),
+            (Value::Text("IETF".into()), "6449455446"),
+            (Value::Text("\u{00fc}".into()), "62c3bc"),
+            (
+                Value::Array(vec![
+                    Value::from(1),
+                    Value::Array(vec![Value::from(2), Value::from(3)]),
+                ]),
+                "8201820203",
+            ),
+            (
+                Value::Map(vec![
+                    (Value::Text("a".into()), Value::from(1)),
+                    (
+                        Value::Text("b".into()),
+                        Value::Array(vec![Value::from(2), Value::from(3)]),
+                    ),
+                ]),
+                "a26161016162820203",
+            ),
+            (
+                Value::Tag(1, Box::new(Value::from(1363896240))),
+                "c11a514b67b0",
+            ),
+            (Value::Bool(false), "f4"),
+            (Value::Bool(true), "f5"),
+            (Value::Null, "f6"),
+        ];
+
+        for (value, encoded) in vectors {
+            assert_eq!(hex::encode(value.to_vec()), encoded);
+            assert_eq!(
+                Value::from_slice(&hex::decode(encoded).unwrap()).expect("decode"),
+                value
+            );
+        }
+
+        // trailing bytes, truncation, indefinite lengths, and floats
+        for invalid in ["0000", "44010203", "5f", "9f", "f93c00", "62c3"] {
+            assert!(Value::from_slice(&hex::decode(invalid).unwrap()).is_err());
+        }
+        // lengths exceeding the input
+        assert!(Value::from_slice(&hex::decode("9bffffffffffffffff").unwrap()).is_err());
+        // excessive nesting
+        assert!(Value::from_slice(&[0x81; 64]).is_err());
+    }
+
+    #[test]
+    fn test_canonical_cbor() {
+        let value = Value::Map(vec![
+            (Value::Text("b".into()), Value::from(1)),
+            (Value::from(10), Value::from(2)),
+            (Value::Text("a".into()), Value::from(3)),
+            (Value::from(-1), Value::from(4)),
+        ]);
+        let encoded = value.to_canonical_vec();
+        assert_eq!(hex::encode(&encoded), "a40a022004616103616201");
+        assert!(Value::from_canonical_slice(&encoded).is_ok());
+
+        // unsorted keys, non-shortest integers, and duplicate keys
+        for invalid in ["a2616201616103", "1817", "a201010102"] {
+            assert!(Value::from_canonical_slice(&hex::decode(invalid).unwrap()).is_err());
+        }
+    }
+}
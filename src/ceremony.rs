@@ -0,0 +1,546 @@
+//! # Key ceremonies
+//!
+//! A [`Ceremony`] generates a signing keypair from the entropy of several
+//! participants, such that no single participant controls the result. It uses
+//! a commit-then-reveal protocol:
+//!
+//! 1. each participant generates a [`Contribution`] and publishes its
+//!    commitment, a hash of their entropy bound to the ceremony label and
+//!    their name, with [`Ceremony::commit`]
+//! 2. once every participant has committed, each one reveals their
+//!    contribution with [`Ceremony::reveal`], which is checked against their
+//!    commitment
+//! 3. [`Ceremony::finish`] derives the keypair seed from the hash of the
+//!    commitments and every participant's entropy, returning the keypair and a
+//!    [`CeremonyTranscript`]
+//!
+//! Because commitments are fixed before any entropy is revealed, a participant
+//! can't choose their entropy after seeing anyone else's, so as long as one
+//! participant's entropy is random and kept secret, so is the seed. A
+//! participant can still abort the ceremony after seeing the other reveals,
+//! forcing a restart.
+//!
+//! The transcript records the ceremony label, the participants and their
+//! commitments, and the resulting public key, but not the revealed entropy, so
+//! it can be published. Auditors can check it was signed by the participants
+//! (or by the new key itself) with [`CeremonyTranscript::sign`] and
+//! [`CeremonyTranscript::verify`], and anyone holding the revealed
+//! contributions can recompute the public key with
+//! [`CeremonyTranscript::verify_contributions`].
+//!
+//! Note that whoever runs [`Ceremony::reveal`] sees every contribution, and
+//! thus learns the secret key. Run the final steps on a trusted machine, such
+//! as an air-gapped system.
+//!
+//! If the `serde` feature is enabled, the [`serde::Deserialize`] and
+//! [`serde::Serialize`] traits will be implemented for [`CeremonyTranscript`].
+//! Transcripts also implement [`CanonicalEncode`] and [`CanonicalDecode`].
+//!
+//! ## Example
+//!
+//! ```
+//! use dryoc::ceremony::*;
+//! use dryoc::sign::SigningKeyPair;
+//!
+//! let alice = Contribution::gen("root key 2026", "alice");
+//! let bob = Contribution::gen("root key 2026", "bob");
+//!
+//! let mut ceremony = Ceremony::new("root key 2026");
+//! ceremony.commit("alice", &alice.commitment()).expect("commit failed");
+//! ceremony.commit("bob", &bob.commitment()).expect("commit failed");
+//!
+//! ceremony.reveal(&alice).expect("reveal failed");
+//! ceremony.reveal(&bob).expect("reveal failed");
+//! let (keypair, transcript) = ceremony.finish().expect("finish failed");
+//! assert_eq!(transcript.public_key(), &keypair.public_key);
+//!
+//! // The new key signs the transcript, proving possession
+//! let signature = transcript.sign(&keypair).expect("sign failed");
+//! transcript
+//!     .verify(&keypair.public_key, &signature)
+//!     .expect("verify failed");
+//!
+//! // Auditors holding the contributions can recompute the public key
+//! transcript
+//!     .verify_contributions(&[alice, bob])
+//!     .expect("verify failed");
+//! ```
+
+use std::collections::BTreeMap;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use subtle::ConstantTimeEq;
+use zeroize::{Zeroize, ZeroizeOnDrop};
+
+use crate::canonical::{self, CanonicalDecode, CanonicalEncode};
+use crate::cbor::Value;
+use crate::constants::{
+    CRYPTO_GENERICHASH_BYTES, CRYPTO_GENERICHASH_KEYBYTES, CRYPTO_SIGN_SEEDBYTES,
+};
+use crate::error::Error;
+use crate::generichash::GenericHash;
+use crate::sign::{PublicKey, SigningKeyPair};
+use crate::sign::{Signer, Verifier};
+use crate::types::*;
+
+/// Length of a participant's entropy.
+pub const CEREMONY_ENTROPYBYTES: usize = 32;
+
+/// Stack-allocated participant entropy type alias.
+pub type Entropy = StackByteArray<CEREMONY_ENTROPYBYTES>;
+/// Stack-allocated commitment type alias.
+pub type Commitment = StackByteArray<CRYPTO_GENERICHASH_BYTES>;
+/// Stack-allocated transcript hash type alias.
+pub type TranscriptHash = StackByteArray<CRYPTO_GENERICHASH_BYTES>;
+
+/// Appends `value` to `input`, prefixed by its length.
+fn push_field(input: &mut Vec<u8>, value: &[u8]) {
+    input.extend_from_slice(&(value.len() as u64).to_le_bytes());
+    input.extend_from_slice(value);
+}
+
+/// Returns the unkeyed Blake2b hash of `input`.
+fn digest(input: &[u8]) -> StackByteArray<CRYPTO_GENERICHASH_BYTES> {
+    StackByteArray::from(GenericHash::<
+        CRYPTO_GENERICHASH_KEYBYTES,
+        CRYPTO_GENERICHASH_BYTES,
+    >::digest(input))
+}
+
+/// Returns the commitment to `entropy` for `participant` in ceremony `label`.
+fn commit(label: &str, participant: &str, entropy: &Entropy) -> Commitment {
+    let mut input = Vec::new();
+    push_field(&mut input, b"dryoc ceremony commitment");
+    push_field(&mut input, label.as_bytes());
+    push_field(&mut input, participant.as_bytes());
+    push_field(&mut input, entropy.as_slice());
+    let commitment = digest(&input);
+    input.zeroize();
+    commitment
+}
+
+/// Returns the hash of the label and the commitments, in participant order.
+fn commitments_hash(label: &str, commitments: &BTreeMap<String, Commitment>) -> TranscriptHash {
+    let mut input = Vec::new();
+    push_field(&mut input, b"dryoc ceremony commitments");
+    push_field(&mut input, label.as_bytes());
+    for (participant, commitment) in commitments {
+        push_field(&mut input, participant.as_bytes());
+        push_field(&mut input, commitment.as_slice());
+    }
+    digest(&input)
+}
+
+/// Derives the keypair from the commitments hash and each participant's
+/// entropy, in participant order.
+fn derive_keypair<'a>(
+    commitments_hash: &TranscriptHash,
+    entropy: impl Iterator<Item = &'a Entropy>,
+) -> Result<SigningKeyPair<PublicKey, crate::sign::SecretKey>, Error> {
+    let mut state: GenericHash<CRYPTO_GENERICHASH_BYTES, CRYPTO_SIGN_SEEDBYTES> =
+        GenericHash::new(Some(commitments_hash))?;
+    state.update(b"dryoc ceremony seed");
+    for entropy in entropy {
+        state.update(entropy);
+    }
+    let seed: StackByteArray<CRYPTO_SIGN_SEEDBYTES> = state.finalize()?;
+    Ok(SigningKeyPair::from_seed(&seed))
+}
+
+#[derive(Zeroize, ZeroizeOnDrop, Clone, Debug)]
+/// A participant's entropy contribution to a [`Ceremony`].
+///
+/// Refer to [crate::ceremony] for sample usage.
+pub struct Contribution {
+    label: String,
+    participant: String,
+    entropy: Entropy,
+}
+
+impl Contribution {
+    /// Returns a new contribution from `participant` to the ceremony named
+    /// `label`, with random entropy.
+    pub fn gen(label: &str, participant: &str) -> Self {
+        Self::from_entropy(label, participant, Entropy::gen())
+    }
+
+    /// Returns a contribution from `participant` to the ceremony named
+    /// `label` using `entropy`, such as entropy gathered from dice rolls or a
+    /// hardware source.
+    pub fn from_entropy(label: &str, participant: &str, entropy: Entropy) -> Self {
+        Self {
+            label: label.to_string(),
+            participant: participant.to_string(),
+            entropy,
+        }
+    }
+
+    /// Returns the participant's name.
+    pub fn participant(&self) -> &str {
+        &self.participant
+    }
+
+    /// Returns the participant's entropy.
+    pub fn entropy(&self) -> &Entropy {
+        &self.entropy
+    }
+
+    /// Returns the commitment to this contribution, which is published before
+    /// the contribution is revealed.
+    pub fn commitment(&self) -> Commitment {
+        commit(&self.label, &self.participant, &self.entropy)
+    }
+}
+
+/// A key ceremony in progress.
+///
+/// Refer to [crate::ceremony] for sample usage.
+pub struct Ceremony {
+    label: String,
+    commitments: BTreeMap<String, Commitment>,
+    revealed: BTreeMap<String, Entropy>,
+}
+
+impl Ceremony {
+    /// Returns a new ceremony named `label`. The label should be unique, such
+    /// as the key's purpose and the date of the ceremony.
+    pub fn new(label: &str) -> Self {
+        Self {
+            label: label.to_string(),
+            commitments: BTreeMap::new(),
+            revealed: BTreeMap::new(),
+        }
+    }
+
+    /// Returns the ceremony label.
+    pub fn label(&self) -> &str {
+        &self.label
+    }
+
+    /// Returns the hash of the label and all commitments made so far. Once
+    /// all participants have committed, participants should confirm they
+    /// agree on this hash before revealing their contributions.
+    pub fn commitments_hash(&self) -> TranscriptHash {
+        commitments_hash(&self.label, &self.commitments)
+    }
+
+    /// Records `participant`'s commitment. Fails if `participant` has
+    /// already committed, or if any contribution has already been revealed.
+    pub fn commit(&mut self, participant: &str, commitment: &Commitment) -> Result<(), Error> {
+        if !self.revealed.is_empty() {
+            return Err(dryoc_error!(
+                "can't commit after contributions have been revealed"
+            ));
+        }
+        if self.commitments.contains_key(participant) {
+            return Err(dryoc_error!(format!(
+                "participant {} has already committed",
+                participant
+            )));
+        }
+        self.commitments
+            .insert(participant.to_string(), commitment.clone());
+        Ok(())
+    }
+
+    /// Reveals `contribution`, checking it against the participant's
+    /// commitment. The first reveal closes the commitment phase.
+    pub fn reveal(&mut self, contribution: &Contribution) -> Result<(), Error> {
+        if contribution.label != self.label {
+            return Err(dryoc_error!(format!(
+                "contribution is for ceremony {}, not {}",
+                contribution.label, self.label
+            )));
+        }
+        let participant = contribution.participant();
+        let commitment = self
+            .commitments
+            .get(participant)
+            .ok_or_else(|| dryoc_error!(format!("participant {} hasn't committed", participant)))?;
+        if self.revealed.contains_key(participant) {
+            return Err(dryoc_error!(format!(
+                "participant {} has already revealed",
+                participant
+            )));
+        }
+        if contribution
+            .commitment()
+            .as_slice()
+            .ct_eq(commitment.as_slice())
+            .unwrap_u8()
+            == 0
+        {
+            return Err(dryoc_error!(format!(
+                "contribution from {} doesn't match its commitment",
+                participant
+            )));
+        }
+        self.revealed
+            .insert(participant.to_string(), contribution.entropy.clone());
+        Ok(())
+    }
+
+    /// Finishes the ceremony, returning the derived keypair and the
+    /// transcript. Fails unless every participant who committed has revealed
+    /// their contribution.
+    pub fn finish(
+        mut self,
+    ) -> Result<
+        (
+            SigningKeyPair<PublicKey, crate::sign::SecretKey>,
+            CeremonyTranscript,
+        ),
+        Error,
+    > {
+        if self.commitments.is_empty() {
+            return Err(dryoc_error!("ceremony has no participants"));
+        }
+        if let Some(participant) = self
+            .commitments
+            .keys()
+            .find(|participant| !self.revealed.contains_key(*participant))
+        {
+            return Err(dryoc_error!(format!(
+                "participant {} hasn't revealed",
+                participant
+            )));
+        }
+        let hash = self.commitments_hash();
+        let keypair = derive_keypair(&hash, self.revealed.values())?;
+        self.revealed.values_mut().for_each(Zeroize::zeroize);
+        let transcript = CeremonyTranscript {
+            label: std::mem::take(&mut self.label),
+            commitments: std::mem::take(&mut self.commitments),
+            public_key: keypair.public_key.clone(),
+        };
+        Ok((keypair, transcript))
+    }
+}
+
+#[cfg_attr(
+    feature = "serde",
+    derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)
+)]
+#[cfg_attr(not(feature = "serde"), derive(Clone, Debug, PartialEq, Eq))]
+/// The auditable record of a finished [`Ceremony`]: its label, each
+/// participant's commitment, and the resulting public key.
+///
+/// Refer to [crate::ceremony] for sample usage.
+pub struct CeremonyTranscript {
+    label: String,
+    commitments: BTreeMap<String, Commitment>,
+    public_key: PublicKey,
+}
+
+impl CeremonyTranscript {
+    /// Returns the ceremony label.
+    pub fn label(&self) -> &str {
+        &self.label
+    }
+
+    /// Returns the participants and their commitments, ordered by participant.
+    pub fn commitments(&self) -> &BTreeMap<String, Commitment> {
+        &self.commitments
+    }
+
+    /// Returns the public key generated by the ceremony.
+    pub fn public_key(&self) -> &PublicKey {
+        &self.public_key
+    }
+
+    /// Returns the hash of the transcript's canonical encoding.
+    pub fn hash(&self) -> TranscriptHash {
+        digest(&self.to_canonical_bytes())
+    }
+
+    /// Signs the transcript's canonical encoding with `signer`, returning the
+    /// detached signature. Participants, witnesses, and the new key itself
+    /// can each sign the transcript.
+    pub fn sign<S: Signer + ?Sized>(&self, signer: &S) -> Result<Vec<u8>, Error> {
+        signer.try_sign(&self.to_canonical_bytes())
+    }
+
+    /// Verifies a detached `signature` of the transcript with `verifier`.
+    pub fn verify<V: Verifier + ?Sized>(
+        &self,
+        verifier: &V,
+        signature: &[u8],
+    ) -> Result<(), Error> {
+        verifier.verify(&self.to_canonical_bytes(), signature)
+    }
+
+    /// Verifies that `contributions` match the transcript's commitments, and
+    /// that they derive the transcript's public key.
+    pub fn verify_contributions(&self, contributions: &[Contribution]) -> Result<(), Error> {
+        let mut ceremony = Ceremony::new(&self.label);
+        for (participant, commitment) in &self.commitments {
+            ceremony.commit(participant, commitment)?;
+        }
+        for contribution in contributions {
+            ceremony.reveal(contribution)?;
+        }
+        let (keypair, _) = ceremony.finish()?;
+        if keypair.public_key != self.public_key {
+            return Err(dryoc_error!(
+                "contributions don't derive the transcript's public key"
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl CanonicalEncode for CeremonyTranscript {
+    fn to_canonical_bytes(&self) -> Vec<u8> {
+        let commitments = self
+            .commitments
+            .iter()
+            .map(|(participant, commitment)| {
+                Value::Array(vec![
+                    Value::Text(participant.clone()),
+                    Value::Bytes(commitment.to_vec()),
+                ])
+            })
+            .collect();
+        canonical::encode(
+            "dryoc/CeremonyTranscript",
+            vec![
+                Value::Text(self.label.clone()),
+                Value::Array(commitments),
+                Value::Bytes(self.public_key.to_vec()),
+            ],
+        )
+    }
+}
+
+impl CanonicalDecode for CeremonyTranscript {
+    fn from_canonical_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        let fields = canonical::decode("dryoc/CeremonyTranscript", bytes, 3)?;
+        let mut commitments = BTreeMap::new();
+        for entry in canonical::array_field(&fields[1])? {
+            let entry = canonical::record_field(entry, 2)?;
+            let participant = canonical::text_field(&entry[0])?.to_string();
+            if commitments.keys().next_back() >= Some(&participant) {
+                return Err(dryoc_error!(
+                    "transcript participants are not in canonical order"
+                ));
+            }
+            commitments.insert(participant, canonical::byte_array_field(&entry[1])?);
+        }
+        Ok(Self {
+            label: canonical::text_field(&fields[0])?.to_string(),
+            commitments,
+            public_key: canonical::byte_array_field(&fields[2])?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(
+        contributions: &[Contribution],
+    ) -> Result<
+        (
+            SigningKeyPair<PublicKey, crate::sign::SecretKey>,
+            CeremonyTranscript,
+        ),
+        Error,
+    > {
+        let mut ceremony = Ceremony::new("test");
+        for contribution in contributions {
+            ceremony.commit(contribution.participant(), &contribution.commitment())?;
+        }
+        for contribution in contributions {
+            ceremony.reveal(contribution)?;
+        }
+        ceremony.finish()
+    }
+
+    #[test]
+    fn test_ceremony() {
+        let contributions: Vec<_> = ["carol", "alice", "bob"]
+            .iter()
+            .map(|name| Contribution::gen("test", name))
+            .collect();
+        let (keypair, transcript) = run(&contributions).expect("ceremony failed");
+        assert_eq!(
+            transcript.commitments().keys().collect::<Vec<_>>(),
+            vec!["alice", "bob", "carol"]
+        );
+
+        // the result is deterministic, and independent of the reveal order
+        let mut reversed = contributions.clone();
+        reversed.reverse();
+        let (again, _) = run(&reversed).expect("ceremony failed");
+        assert_eq!(keypair.public_key, again.public_key);
+        transcript
+            .verify_contributions(&reversed)
+            .expect("verify failed");
+
+        // every contribution affects the key
+        let mut changed = contributions.clone();
+        changed[1] = Contribution::gen("test", "alice");
+        transcript
+            .verify_contributions(&changed)
+            .expect_err("changed contribution");
+        transcript
+            .verify_contributions(&contributions[..2])
+            .expect_err("missing contribution");
+
+        let decoded = CeremonyTranscript::from_canonical_bytes(&transcript.to_canonical_bytes())
+            .expect("decode failed");
+        assert_eq!(decoded, transcript);
+        assert_eq!(decoded.hash(), transcript.hash());
+
+        let witness = SigningKeyPair::gen_with_defaults();
+        let signature = transcript.sign(&witness).expect("sign failed");
+        decoded
+            .verify(&witness.public_key, &signature)
+            .expect("verify failed");
+        decoded
+            .verify(&keypair.public_key, &signature)
+            .expect_err("wrong verifier");
+    }
+
+    #[test]
+    fn test_ceremony_phases() {
+        let alice = Contribution::gen("test", "alice");
+        let bob = Contribution::gen("test", "bob");
+        let mut ceremony = Ceremony::new("test");
+        ceremony
+            .commit("alice", &alice.commitment())
+            .expect("commit failed");
+        ceremony
+            .commit("alice", &alice.commitment())
+            .expect_err("duplicate commit");
+        ceremony.reveal(&bob).expect_err("bob hasn't committed");
+        ceremony
+            .reveal(&Contribution::gen("test", "alice"))
+            .expect_err("wrong entropy");
+        ceremony
+            .reveal(&Contribution::from_entropy(
+                "other",
+                "alice",
+                alice.entropy().clone(),
+            ))
+            .expect_err("wrong ceremony");
+        ceremony.reveal(&alice).expect("reveal failed");
+        ceremony
+            .commit("bob", &bob.commitment())
+            .expect_err("commit after reveal");
+        ceremony.finish().expect("finish failed");
+
+        let mut ceremony = Ceremony::new("test");
+        ceremony
+            .commit("alice", &alice.commitment())
+            .expect("commit failed");
+        ceremony
+            .commit("bob", &bob.commitment())
+            .expect("commit failed");
+        ceremony.reveal(&alice).expect("reveal failed");
+        assert!(ceremony.finish().is_err());
+        assert!(Ceremony::new("test").finish().is_err());
+    }
+}
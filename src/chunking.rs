@@ -0,0 +1,446 @@
+//! # Content-defined chunking
+//!
+//! [`Chunker`] splits data into variable-sized chunks using FastCDC, a gear
+//! hash based content-defined chunking algorithm. Chunk boundaries are chosen
+//! by the content itself rather than by fixed offsets, so inserting or
+//! removing bytes only changes the chunks around the edit, and the remaining
+//! chunks can be deduplicated against earlier versions of the data.
+//!
+//! Each chunk is returned as a [`ChunkBoundary`], with its offset, its length,
+//! and a keyed Blake2b hash of its contents. Chunk hashes are suitable as
+//! deduplication keys, and as inputs for convergent encryption of the chunk
+//! contents.
+//!
+//! Both the gear table (which decides where chunks are cut) and the chunk
+//! hashes are derived from the chunker's key. Without the key, an observer
+//! who can see chunk lengths or hashes can't use them to confirm guesses
+//! about the contents of the data. Chunkers must share a key in order to
+//! deduplicate each other's chunks.
+//!
+//! Chunk sizes are bounded by a [`ChunkerConfig`], which defaults to a
+//! minimum of 2 KiB, an average of 8 KiB, and a maximum of 64 KiB.
+//!
+//! ## Example
+//!
+//! ```
+//! use dryoc::chunking::*;
+//!
+//! let chunker = Chunker::new(Key::gen(), ChunkerConfig::default());
+//!
+//! let data: Vec<u8> = (0..100_000u32).map(|i| (i * 7 % 251) as u8).collect();
+//! let chunks: Vec<ChunkBoundary> = chunker.chunks(&data).collect();
+//!
+//! // The chunks cover the input without gaps
+//! let total: usize = chunks.iter().map(|c| c.length).sum();
+//! assert_eq!(total, data.len());
+//!
+//! // Data can also be chunked from a reader
+//! let from_reader: Vec<ChunkBoundary> = chunker
+//!     .chunk_reader(&data[..])
+//!     .map(|r| r.expect("read failed").0)
+//!     .collect();
+//! assert_eq!(chunks, from_reader);
+//! ```
+
+use std::io::Read;
+
+use crate::constants::{CRYPTO_GENERICHASH_BYTES, CRYPTO_GENERICHASH_KEYBYTES};
+use crate::error::Error;
+use crate::generichash::GenericHash;
+pub use crate::types::*;
+
+/// Smallest permitted minimum chunk size.
+pub const CHUNKER_MIN_SIZE_LIMIT: usize = 64;
+/// Largest permitted maximum chunk size.
+pub const CHUNKER_MAX_SIZE_LIMIT: usize = 1 << 30;
+
+/// Domain separation prefix used when deriving the gear table from a key.
+const GEAR_CONTEXT: &[u8] = b"dryoc cdc gear";
+
+/// Stack-allocated chunker key type alias.
+pub type Key = StackByteArray<CRYPTO_GENERICHASH_KEYBYTES>;
+/// Stack-allocated chunk hash type alias.
+pub type ChunkHash = StackByteArray<CRYPTO_GENERICHASH_BYTES>;
+
+/// Minimum, average, and maximum chunk sizes for a [`Chunker`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ChunkerConfig {
+    min_size: usize,
+    avg_size: usize,
+    max_size: usize,
+}
+
+impl ChunkerConfig {
+    /// Returns a new chunker config with the given sizes. The average size
+    /// must be a power of 2, and `min_size <= avg_size <= max_size` must
+    /// hold, within [`CHUNKER_MIN_SIZE_LIMIT`] and [`CHUNKER_MAX_SIZE_LIMIT`].
+    pub fn new(min_size: usize, avg_size: usize, max_size: usize) -> Result<Self, Error> {
+        if min_size < CHUNKER_MIN_SIZE_LIMIT {
+            Err(dryoc_error!(format!(
+                "minimum chunk size {} is less than {}",
+                min_size, CHUNKER_MIN_SIZE_LIMIT
+            )))
+        } else if max_size > CHUNKER_MAX_SIZE_LIMIT {
+            Err(dryoc_error!(format!(
+                "maximum chunk size {} is greater than {}",
+                max_size, CHUNKER_MAX_SIZE_LIMIT
+            )))
+        } else if !avg_size.is_power_of_two() {
+            Err(dryoc_error!(format!(
+                "average chunk size {} is not a power of 2",
+                avg_size
+            )))
+        } else if min_size > avg_size || avg_size > max_size {
+            Err(dryoc_error!(format!(
+                "chunk sizes must be ordered min <= avg <= max, got {} {} {}",
+                min_size, avg_size, max_size
+            )))
+        } else {
+            Ok(Self {
+                min_size,
+                avg_size,
+                max_size,
+            })
+        }
+    }
+
+    /// Returns the minimum chunk size. Only the final chunk may be smaller.
+    pub fn min_size(&self) -> usize {
+        self.min_size
+    }
+
+    /// Returns the target average chunk size.
+    pub fn avg_size(&self) -> usize {
+        self.avg_size
+    }
+
+    /// Returns the maximum chunk size.
+    pub fn max_size(&self) -> usize {
+        self.max_size
+    }
+}
+
+impl Default for ChunkerConfig {
+    fn default() -> Self {
+        Self {
+            min_size: 2 * 1024,
+            avg_size: 8 * 1024,
+            max_size: 64 * 1024,
+        }
+    }
+}
+
+/// A chunk emitted by a [`Chunker`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ChunkBoundary {
+    /// Offset of the chunk from the start of the input.
+    pub offset: u64,
+    /// Length of the chunk.
+    pub length: usize,
+    /// Keyed hash of the chunk contents.
+    pub hash: ChunkHash,
+}
+
+/// FastCDC content-defined chunker, with a key for the gear table and chunk
+/// hashes.
+///
+/// Refer to [crate::chunking] for sample usage.
+pub struct Chunker {
+    key: Key,
+    config: ChunkerConfig,
+    gear: [u64; 256],
+    mask_small: u64,
+    mask_large: u64,
+}
+
+impl Chunker {
+    /// Returns a new chunker for `key` with `config`.
+    pub fn new(key: Key, config: ChunkerConfig) -> Self {
+        let gear = derive_gear(&key);
+        // Normalized chunking: a stricter mask before the average size and a
+        // looser one after it narrows the chunk size distribution
+        let bits = config.avg_size.trailing_zeros();
+        let mask_small = top_bits_mask(bits + 2);
+        let mask_large = top_bits_mask(bits.saturating_sub(2));
+        Self {
+            key,
+            config,
+            gear,
+            mask_small,
+            mask_large,
+        }
+    }
+
+    /// Returns a reference to the key.
+    pub fn key(&self) -> &Key {
+        &self.key
+    }
+
+    /// Returns the chunker config.
+    pub fn config(&self) -> &ChunkerConfig {
+        &self.config
+    }
+
+    /// Returns the length of the first chunk of `data`, treating `data` as
+    /// the remainder of the input. Returns `data.len()` if no boundary is
+    /// found before the maximum chunk size.
+    pub fn cut_point(&self, data: &[u8]) -> usize {
+        if data.len() <= self.config.min_size {
+            return data.len();
+        }
+        let end = data.len().min(self.config.max_size);
+        let normal = end.min(self.config.avg_size);
+        let mut hash = 0u64;
+        let mut i = self.config.min_size;
+        while i < normal {
+            hash = (hash << 1).wrapping_add(self.gear[data[i] as usize]);
+            if hash & self.mask_small == 0 {
+                return i + 1;
+            }
+            i += 1;
+        }
+        while i < end {
+            hash = (hash << 1).wrapping_add(self.gear[data[i] as usize]);
+            if hash & self.mask_large == 0 {
+                return i + 1;
+            }
+            i += 1;
+        }
+        end
+    }
+
+    /// Returns the keyed hash of `chunk`.
+    pub fn hash_chunk(&self, chunk: &[u8]) -> ChunkHash {
+        GenericHash::hash_with_defaults(chunk, Some(&self.key))
+            .expect("key and output lengths are valid")
+    }
+
+    /// Returns an iterator over the chunks of `data`.
+    pub fn chunks<'a>(&'a self, data: &'a [u8]) -> Chunks<'a> {
+        Chunks {
+            chunker: self,
+            data,
+            offset: 0,
+        }
+    }
+
+    /// Returns an iterator over the chunks read from `reader`, yielding each
+    /// chunk's boundary along with its contents. At most one maximum-sized
+    /// chunk is buffered at a time.
+    pub fn chunk_reader<R: Read>(&self, reader: R) -> ReaderChunks<'_, R> {
+        ReaderChunks {
+            chunker: self,
+            reader,
+            buffer: Vec::with_capacity(self.config.max_size),
+            offset: 0,
+            eof: false,
+        }
+    }
+}
+
+/// Iterator over the chunks of a slice, returned by [`Chunker::chunks`].
+pub struct Chunks<'a> {
+    chunker: &'a Chunker,
+    data: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> Iterator for Chunks<'a> {
+    type Item = ChunkBoundary;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let remaining = &self.data[self.offset..];
+        if remaining.is_empty() {
+            return None;
+        }
+        let length = self.chunker.cut_point(remaining);
+        let boundary = ChunkBoundary {
+            offset: self.offset as u64,
+            length,
+            hash: self.chunker.hash_chunk(&remaining[..length]),
+        };
+        self.offset += length;
+        Some(boundary)
+    }
+}
+
+/// Iterator over the chunks read from a reader, returned by
+/// [`Chunker::chunk_reader`].
+pub struct ReaderChunks<'a, R: Read> {
+    chunker: &'a Chunker,
+    reader: R,
+    buffer: Vec<u8>,
+    offset: u64,
+    eof: bool,
+}
+
+impl<'a, R: Read> ReaderChunks<'a, R> {
+    fn fill(&mut self) -> Result<(), Error> {
+        let max_size = self.chunker.config.max_size;
+        while !self.eof && self.buffer.len() < max_size {
+            let len = self.buffer.len();
+            self.buffer.resize(max_size, 0);
+            match self.reader.read(&mut self.buffer[len..]) {
+                Ok(0) => {
+                    self.buffer.truncate(len);
+                    self.eof = true;
+                }
+                Ok(n) => self.buffer.truncate(len + n),
+                Err(err) if err.kind() == std::io::ErrorKind::Interrupted => {
+                    self.buffer.truncate(len)
+                }
+                Err(err) => {
+                    self.buffer.truncate(len);
+                    return Err(err.into());
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<'a, R: Read> Iterator for ReaderChunks<'a, R> {
+    type Item = Result<(ChunkBoundary, Vec<u8>), Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Err(err) = self.fill() {
+            return Some(Err(err));
+        }
+        if self.buffer.is_empty() {
+            return None;
+        }
+        let length = self.chunker.cut_point(&self.buffer);
+        let rest = self.buffer.split_off(length);
+        let data = std::mem::replace(&mut self.buffer, rest);
+        let boundary = ChunkBoundary {
+            offset: self.offset,
+            length,
+            hash: self.chunker.hash_chunk(&data),
+        };
+        self.offset += length as u64;
+        Some(Ok((boundary, data)))
+    }
+}
+
+fn top_bits_mask(bits: u32) -> u64 {
+    match bits {
+        0 => 0,
+        bits if bits >= 64 => u64::MAX,
+        bits => u64::MAX << (64 - bits),
+    }
+}
+
+fn derive_gear(key: &Key) -> [u64; 256] {
+    let mut gear = [0u64; 256];
+    for (block, entries) in gear.chunks_mut(8).enumerate() {
+        let mut state: GenericHash<CRYPTO_GENERICHASH_KEYBYTES, 64> =
+            GenericHash::new(Some(key)).expect("key length is valid");
+        state.update(GEAR_CONTEXT);
+        state.update(&[block as u8]);
+        let output: [u8; 64] = state.finalize().expect("output length is valid");
+        for (entry, bytes) in entries.iter_mut().zip(output.chunks_exact(8)) {
+            *entry = u64::from_le_bytes(bytes.try_into().expect("8 bytes"));
+        }
+    }
+    gear
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn data(len: usize) -> Vec<u8> {
+        crate::rng::randombytes_buf(len)
+    }
+
+    #[test]
+    fn test_chunk_sizes() {
+        let config = ChunkerConfig::default();
+        let chunker = Chunker::new(Key::gen(), config);
+        let data = data(1_000_000);
+
+        let chunks: Vec<ChunkBoundary> = chunker.chunks(&data).collect();
+        let mut offset = 0u64;
+        for (i, chunk) in chunks.iter().enumerate() {
+            assert_eq!(chunk.offset, offset);
+            assert!(chunk.length <= config.max_size());
+            if i + 1 < chunks.len() {
+                assert!(chunk.length >= config.min_size());
+            }
+            let start = chunk.offset as usize;
+            assert_eq!(
+                chunk.hash,
+                chunker.hash_chunk(&data[start..start + chunk.length])
+            );
+            offset += chunk.length as u64;
+        }
+        assert_eq!(offset, data.len() as u64);
+
+        let avg = data.len() / chunks.len();
+        assert!(avg > config.min_size() && avg < config.max_size());
+    }
+
+    #[test]
+    fn test_edit_resynchronizes() {
+        // Fixed inputs: with unlucky random ones, the edit can shift a
+        // max-length cut and disturb a few more chunks than asserted below
+        let chunker = Chunker::new(Key::from([1u8; 32]), ChunkerConfig::default());
+        let mut original = vec![0u8; 200_000];
+        crate::classic::crypto_stream::crypto_stream(&mut original, &[0u8; 24], &[2u8; 32]);
+        let mut edited = original.clone();
+        edited.splice(50_000..50_000, b"inserted bytes".iter().cloned());
+
+        let hashes = |data: &[u8]| -> Vec<ChunkHash> {
+            chunker.chunks(data).map(|c| c.hash).collect()
+        };
+        let before = hashes(&original);
+        let after = hashes(&edited);
+        let shared = after.iter().filter(|h| before.contains(h)).count();
+        assert!(shared + 3 >= before.len());
+    }
+
+    #[test]
+    fn test_reader_matches_slice() {
+        let chunker = Chunker::new(Key::gen(), ChunkerConfig::new(64, 256, 1024).unwrap());
+        let data = data(50_000);
+
+        let from_slice: Vec<ChunkBoundary> = chunker.chunks(&data).collect();
+        let from_reader: Vec<(ChunkBoundary, Vec<u8>)> = chunker
+            .chunk_reader(&data[..])
+            .collect::<Result<_, _>>()
+            .expect("read failed");
+        assert_eq!(from_slice.len(), from_reader.len());
+        for (a, (b, contents)) in from_slice.iter().zip(from_reader.iter()) {
+            assert_eq!(a, b);
+            assert_eq!(&data[b.offset as usize..][..b.length], &contents[..]);
+        }
+
+        assert_eq!(chunker.chunks(&[]).count(), 0);
+        assert_eq!(chunker.chunk_reader(&[][..]).count(), 0);
+    }
+
+    #[test]
+    fn test_keys_differ() {
+        let config = ChunkerConfig::new(64, 256, 1024).unwrap();
+        let a = Chunker::new(Key::gen(), config);
+        let b = Chunker::new(Key::gen(), config);
+        let data = data(20_000);
+
+        let a_chunks: Vec<ChunkBoundary> = a.chunks(&data).collect();
+        let b_chunks: Vec<ChunkBoundary> = b.chunks(&data).collect();
+        assert_ne!(a_chunks, b_chunks);
+
+        let again: Vec<ChunkBoundary> = Chunker::new(a.key().clone(), config)
+            .chunks(&data)
+            .collect();
+        assert_eq!(a_chunks, again);
+    }
+
+    #[test]
+    fn test_invalid_config() {
+        ChunkerConfig::new(16, 256, 1024).expect_err("min too small");
+        ChunkerConfig::new(64, 300, 1024).expect_err("avg not power of 2");
+        ChunkerConfig::new(512, 256, 1024).expect_err("min > avg");
+        ChunkerConfig::new(64, 2048, 1024).expect_err("avg > max");
+    }
+}
@@ -130,7 +130,9 @@ pub fn crypto_auth(mac: &mut Mac, message: &[u8], key: &Key) {
 ///
 /// Equivalent to libsodium's `crypto_auth_verify`.
 pub fn crypto_auth_verify(mac: &Mac, input: &[u8], key: &Key) -> Result<(), Error> {
-    crypto_auth_hmacsha512256_verify(mac, input, key)
+    with_context!("crypto_auth_verify", [input = input.len()], {
+        crypto_auth_hmacsha512256_verify(mac, input, key)
+    })
 }
 
 /// Internal state for [`crypto_auth`].
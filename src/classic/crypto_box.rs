@@ -184,7 +184,7 @@ pub fn crypto_box_easy(
         )))
     } else {
         let (mac, ciphertext) = ciphertext.split_at_mut(CRYPTO_BOX_MACBYTES);
-        let mac = mac.as_mut_array();
+        let mac = MutByteArray::as_mut_array(mac);
         crypto_box_detached(
             ciphertext,
             mac,
@@ -215,6 +215,51 @@ pub fn crypto_box_seal(
     ciphertext: &mut [u8],
     message: &[u8],
     recipient_public_key: &PublicKey,
+) -> Result<(), Error> {
+    let (mut epk, mut esk) = crypto_box_keypair();
+    let result = seal_with_ephemeral_keypair(ciphertext, message, recipient_public_key, &epk, &esk);
+
+    epk.zeroize();
+    esk.zeroize();
+
+    result
+}
+
+/// Identical to [`crypto_box_seal`], except that the ephemeral keypair
+/// (`ephemeral_public_key`, `ephemeral_secret_key`) is provided by the caller
+/// rather than generated. The sealed box nonce is derived from the ephemeral
+/// and recipient public keys, so the output is fully determined by the inputs,
+/// which makes golden-file tests of sealed boxes possible.
+///
+/// Don't use this outside of tests: an ephemeral keypair must never be used
+/// for more than one message.
+#[cfg(any(test, feature = "deterministic-testing"))]
+#[cfg_attr(
+    all(feature = "nightly", doc),
+    doc(cfg(feature = "deterministic-testing"))
+)]
+pub fn crypto_box_seal_with_ephemeral_keypair(
+    ciphertext: &mut [u8],
+    message: &[u8],
+    recipient_public_key: &PublicKey,
+    ephemeral_public_key: &PublicKey,
+    ephemeral_secret_key: &SecretKey,
+) -> Result<(), Error> {
+    seal_with_ephemeral_keypair(
+        ciphertext,
+        message,
+        recipient_public_key,
+        ephemeral_public_key,
+        ephemeral_secret_key,
+    )
+}
+
+fn seal_with_ephemeral_keypair(
+    ciphertext: &mut [u8],
+    message: &[u8],
+    recipient_public_key: &PublicKey,
+    epk: &PublicKey,
+    esk: &SecretKey,
 ) -> Result<(), Error> {
     if ciphertext.len() < message.len() + CRYPTO_BOX_SEALBYTES {
         Err(dryoc_error!(format!(
@@ -224,21 +269,18 @@ pub fn crypto_box_seal(
         )))
     } else {
         let mut nonce = Nonce::new_byte_array();
-        let (mut epk, mut esk) = crypto_box_keypair();
-        crypto_box_seal_nonce(&mut nonce, &epk, recipient_public_key);
+        crypto_box_seal_nonce(&mut nonce, epk, recipient_public_key);
 
         crypto_box_easy(
             &mut ciphertext[CRYPTO_BOX_PUBLICKEYBYTES..],
             message,
             &nonce,
             recipient_public_key,
-            &esk,
+            esk,
         )?;
 
-        ciphertext[..CRYPTO_BOX_PUBLICKEYBYTES].copy_from_slice(&epk);
+        ciphertext[..CRYPTO_BOX_PUBLICKEYBYTES].copy_from_slice(epk);
 
-        epk.zeroize();
-        esk.zeroize();
         nonce.zeroize();
 
         Ok(())
@@ -278,7 +320,7 @@ pub fn crypto_box_easy_inplace(
         data.rotate_right(CRYPTO_BOX_MACBYTES);
 
         let (mac, data) = data.split_at_mut(CRYPTO_BOX_MACBYTES);
-        let mac = mac.as_mut_array();
+        let mac = MutByteArray::as_mut_array(mac);
 
         crypto_box_detached_inplace(data, mac, nonce, recipient_public_key, sender_secret_key)?;
 
@@ -320,13 +362,19 @@ pub fn crypto_box_open_detached(
     recipient_public_key: &PublicKey,
     sender_secret_key: &SecretKey,
 ) -> Result<(), Error> {
-    let mut key = crypto_box_beforenm(recipient_public_key, sender_secret_key);
+    with_context!(
+        "crypto_box_open_detached",
+        [message = message.len(), ciphertext = ciphertext.len()],
+        {
+            let mut key = crypto_box_beforenm(recipient_public_key, sender_secret_key);
 
-    crypto_box_open_detached_afternm(message, mac, ciphertext, nonce, &key)?;
+            crypto_box_open_detached_afternm(message, mac, ciphertext, nonce, &key)?;
 
-    key.zeroize();
+            key.zeroize();
 
-    Ok(())
+            Ok(())
+        }
+    )
 }
 
 /// In-place variant of [`crypto_box_open_detached`].
@@ -337,13 +385,15 @@ pub fn crypto_box_open_detached_inplace(
     recipient_public_key: &PublicKey,
     sender_secret_key: &SecretKey,
 ) -> Result<(), Error> {
-    let mut key = crypto_box_beforenm(recipient_public_key, sender_secret_key);
+    with_context!("crypto_box_open_detached_inplace", [data = data.len()], {
+        let mut key = crypto_box_beforenm(recipient_public_key, sender_secret_key);
 
-    crypto_box_open_detached_afternm_inplace(data, mac, nonce, &key)?;
+        crypto_box_open_detached_afternm_inplace(data, mac, nonce, &key)?;
 
-    key.zeroize();
+        key.zeroize();
 
-    Ok(())
+        Ok(())
+    })
 }
 
 /// Decrypts `ciphertext` with recipient's secret key `recipient_secret_key` and
@@ -357,25 +407,31 @@ pub fn crypto_box_open_easy(
     sender_public_key: &PublicKey,
     recipient_secret_key: &SecretKey,
 ) -> Result<(), Error> {
-    if ciphertext.len() < CRYPTO_BOX_MACBYTES {
-        Err(dryoc_error!(format!(
-            "Impossibly small box ({} < {}",
-            ciphertext.len(),
-            CRYPTO_BOX_MACBYTES
-        )))
-    } else {
-        let (mac, ciphertext) = ciphertext.split_at(CRYPTO_BOX_MACBYTES);
-        let mac = mac.as_array();
-
-        crypto_box_open_detached(
-            message,
-            mac,
-            ciphertext,
-            nonce,
-            sender_public_key,
-            recipient_secret_key,
-        )
-    }
+    with_context!(
+        "crypto_box_open_easy",
+        [message = message.len(), ciphertext = ciphertext.len()],
+        {
+            if ciphertext.len() < CRYPTO_BOX_MACBYTES {
+                Err(dryoc_error!(format!(
+                    "Impossibly small box ({} < {}",
+                    ciphertext.len(),
+                    CRYPTO_BOX_MACBYTES
+                )))
+            } else {
+                let (mac, ciphertext) = ciphertext.split_at(CRYPTO_BOX_MACBYTES);
+                let mac = ByteArray::as_array(mac);
+
+                crypto_box_open_detached(
+                    message,
+                    mac,
+                    ciphertext,
+                    nonce,
+                    sender_public_key,
+                    recipient_secret_key,
+                )
+            }
+        }
+    )
 }
 
 /// Decrypts a sealed box from `ciphertext` with recipient's secret key
@@ -391,33 +447,39 @@ pub fn crypto_box_seal_open(
     recipient_public_key: &PublicKey,
     recipient_secret_key: &SecretKey,
 ) -> Result<(), Error> {
-    if ciphertext.len() < CRYPTO_BOX_SEALBYTES {
-        Err(dryoc_error!(format!(
-            "Impossibly small box ({} < {}",
-            ciphertext.len(),
-            CRYPTO_BOX_SEALBYTES,
-        )))
-    } else if message.len() != ciphertext.len() - CRYPTO_BOX_SEALBYTES {
-        Err(dryoc_error!(format!(
-            "message length invalid ({} != {}",
-            message.len(),
-            ciphertext.len() - CRYPTO_BOX_SEALBYTES,
-        )))
-    } else {
-        let mut nonce = Nonce::new_byte_array();
-        let mut epk = PublicKey::new_byte_array();
-        epk.copy_from_slice(&ciphertext[..CRYPTO_BOX_PUBLICKEYBYTES]);
-
-        crypto_box_seal_nonce(&mut nonce, &epk, recipient_public_key);
-
-        crypto_box_open_easy(
-            message,
-            &ciphertext[CRYPTO_BOX_PUBLICKEYBYTES..],
-            &nonce,
-            &epk,
-            recipient_secret_key,
-        )
-    }
+    with_context!(
+        "crypto_box_seal_open",
+        [message = message.len(), ciphertext = ciphertext.len()],
+        {
+            if ciphertext.len() < CRYPTO_BOX_SEALBYTES {
+                Err(dryoc_error!(format!(
+                    "Impossibly small box ({} < {}",
+                    ciphertext.len(),
+                    CRYPTO_BOX_SEALBYTES,
+                )))
+            } else if message.len() != ciphertext.len() - CRYPTO_BOX_SEALBYTES {
+                Err(dryoc_error!(format!(
+                    "message length invalid ({} != {}",
+                    message.len(),
+                    ciphertext.len() - CRYPTO_BOX_SEALBYTES,
+                )))
+            } else {
+                let mut nonce = Nonce::new_byte_array();
+                let mut epk = PublicKey::new_byte_array();
+                epk.copy_from_slice(&ciphertext[..CRYPTO_BOX_PUBLICKEYBYTES]);
+
+                crypto_box_seal_nonce(&mut nonce, &epk, recipient_public_key);
+
+                crypto_box_open_easy(
+                    message,
+                    &ciphertext[CRYPTO_BOX_PUBLICKEYBYTES..],
+                    &nonce,
+                    &epk,
+                    recipient_secret_key,
+                )
+            }
+        }
+    )
 }
 
 /// Decrypts `ciphertext` with recipient's secret key `recipient_secret_key` and
@@ -444,7 +506,7 @@ pub fn crypto_box_open_easy_inplace(
         )))
     } else {
         let (mac, d) = data.split_at_mut(CRYPTO_BOX_MACBYTES);
-        let mac = mac.as_array();
+        let mac = ByteArray::as_array(mac);
 
         crypto_box_open_detached_inplace(d, mac, nonce, sender_public_key, recipient_secret_key)?;
 
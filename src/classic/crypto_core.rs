@@ -59,9 +59,30 @@ fn chacha20_quarterround(a: &mut u32, b: &mut u32, c: &mut u32, d: &mut u32) {
     chacha20_round(c, d, b, 7);
 }
 
-/// Implements the HChaCha20 function.
+/// Implements the HChaCha20 function, which derives a 256-bit subkey
+/// `output` from a 256-bit `key` and 128-bit `input`.
+///
+/// HChaCha20 is the building block used to extend ChaCha20's nonce (i.e., in
+/// XChaCha20), and may be used to construct similar nonce-extension schemes.
+/// `constants` optionally overrides the ChaCha20 constants (`"expand 32-byte
+/// k"`), as 4 little-endian words.
 ///
 /// Compatible with libsodium's `crypto_core_hchacha20`.
+///
+/// ## Example
+///
+/// ```
+/// use dryoc::classic::crypto_core::*;
+/// use dryoc::types::*;
+///
+/// // Derive a subkey from a key and the first 16 bytes of a 24-byte nonce, as
+/// // XChaCha20 does.
+/// let key = HChaCha20Key::gen();
+/// let nonce = [0u8; 24];
+///
+/// let mut subkey = HChaCha20Output::default();
+/// crypto_core_hchacha20(&mut subkey, nonce[..16].try_into().unwrap(), &key, None);
+/// ```
 pub fn crypto_core_hchacha20(
     output: &mut HChaCha20Output,
     input: &HChaCha20Input,
@@ -128,7 +149,14 @@ fn salsa20_rotl32(x: u32, y: u32, rot: u32) -> u32 {
     x.wrapping_add(y).rotate_left(rot)
 }
 
-/// Implements the HSalsa20 function.
+/// Implements the HSalsa20 function, which derives a 256-bit subkey `output`
+/// from a 256-bit `key` and 128-bit `input`.
+///
+/// HSalsa20 is the building block used to extend Salsa20's nonce (i.e., in
+/// XSalsa20), and to derive shared keys in
+/// [`crypto_box`](crate::classic::crypto_box). `constants` optionally
+/// overrides the Salsa20 constants (`"expand 32-byte k"`), as 4 little-endian
+/// words.
 ///
 /// Compatible with libsodium's `crypto_core_hsalsa20`.
 pub fn crypto_core_hsalsa20(
@@ -333,4 +361,98 @@ mod tests {
             );
         }
     }
+
+    #[test]
+    fn test_crypto_core_hchacha20_vector() {
+        // Test vector from draft-irtf-cfrg-xchacha-03, section 2.2.1
+        let key: HChaCha20Key = hex::decode(
+            "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f",
+        )
+        .unwrap()
+        .try_into()
+        .unwrap();
+        let input: HChaCha20Input = hex::decode("000000090000004a0000000031415927")
+            .unwrap()
+            .try_into()
+            .unwrap();
+
+        let mut out = HChaCha20Output::default();
+        crypto_core_hchacha20(&mut out, &input, &key, None);
+
+        assert_eq!(
+            hex::encode(out),
+            "82413b4227b27bfed30e42508a877d73a0f9e4d58a74a853c12ec41326d3ecdc"
+        );
+    }
+
+    #[test]
+    fn test_crypto_core_hsalsa20_vector() {
+        // Test vector from NaCl's tests/core1.c
+        let key: HSalsa20Key = hex::decode(
+            "4a5d9d5ba4ce2de1728e3bf480350f25e07e21c947d19e3376f09b3c1e161742",
+        )
+        .unwrap()
+        .try_into()
+        .unwrap();
+        let input = HSalsa20Input::default();
+
+        let mut out = HSalsa20Output::default();
+        crypto_core_hsalsa20(&mut out, &input, &key, None);
+
+        assert_eq!(
+            hex::encode(out),
+            "1b27556473e985d462cd51197a9a46c76009549eac6474f206c4ee0844f68389"
+        );
+    }
+
+    #[test]
+    fn test_crypto_core_custom_constants() {
+        use libsodium_sys::{
+            crypto_core_hchacha20 as so_crypto_core_hchacha20,
+            crypto_core_hsalsa20 as so_crypto_core_hsalsa20,
+        };
+
+        use crate::rng::copy_randombytes;
+
+        for _ in 0..10 {
+            let key = HSalsa20Key::gen();
+            let input = HSalsa20Input::gen();
+            let mut c = [0u8; 16];
+            copy_randombytes(&mut c);
+            let constants = (
+                load_u32_le(&c[0..4]),
+                load_u32_le(&c[4..8]),
+                load_u32_le(&c[8..12]),
+                load_u32_le(&c[12..16]),
+            );
+
+            let mut out = HSalsa20Output::default();
+            crypto_core_hsalsa20(&mut out, &input, &key, Some(constants));
+            let mut so_out = [0u8; 32];
+            let ret = unsafe {
+                so_crypto_core_hsalsa20(
+                    so_out.as_mut_ptr(),
+                    input.as_ptr(),
+                    key.as_ptr(),
+                    c.as_ptr(),
+                )
+            };
+            assert_eq!(ret, 0);
+            assert_eq!(out, so_out);
+
+            let mut out = HChaCha20Output::default();
+            crypto_core_hchacha20(&mut out, &input, &key, Some(constants));
+            let mut so_out = [0u8; 32];
+            let ret = unsafe {
+                so_crypto_core_hchacha20(
+                    so_out.as_mut_ptr(),
+                    input.as_ptr(),
+                    key.as_ptr(),
+                    c.as_ptr(),
+                )
+            };
+            assert_eq!(ret, 0);
+            assert_eq!(out, so_out);
+        }
+    }
 }
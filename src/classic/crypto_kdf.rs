@@ -55,28 +55,30 @@ pub fn crypto_kdf_derive_from_key(
     context: &Context,
     main_key: &Key,
 ) -> Result<(), Error> {
-    if subkey.len() < CRYPTO_KDF_BLAKE2B_BYTES_MIN || subkey.len() > CRYPTO_KDF_BLAKE2B_BYTES_MAX {
-        Err(dryoc_error!(format!(
-            "invalid subkey length {}, should be at least {} and no more than {}",
-            subkey.len(),
-            CRYPTO_KDF_BLAKE2B_BYTES_MIN,
-            CRYPTO_KDF_BLAKE2B_BYTES_MAX
-        )))
-    } else {
-        let mut ctx_padded = [0u8; CRYPTO_GENERICHASH_BLAKE2B_PERSONALBYTES];
-        let mut salt = [0u8; CRYPTO_GENERICHASH_BLAKE2B_SALTBYTES];
+    with_context!("crypto_kdf_derive_from_key", [subkey = subkey.len()], {
+        if subkey.len() < CRYPTO_KDF_BLAKE2B_BYTES_MIN || subkey.len() > CRYPTO_KDF_BLAKE2B_BYTES_MAX {
+            Err(dryoc_error!(format!(
+                "invalid subkey length {}, should be at least {} and no more than {}",
+                subkey.len(),
+                CRYPTO_KDF_BLAKE2B_BYTES_MIN,
+                CRYPTO_KDF_BLAKE2B_BYTES_MAX
+            )))
+        } else {
+            let mut ctx_padded = [0u8; CRYPTO_GENERICHASH_BLAKE2B_PERSONALBYTES];
+            let mut salt = [0u8; CRYPTO_GENERICHASH_BLAKE2B_SALTBYTES];
 
-        ctx_padded[..CRYPTO_KDF_CONTEXTBYTES].copy_from_slice(context);
-        salt[..8].copy_from_slice(&subkey_id.to_le_bytes());
+            ctx_padded[..CRYPTO_KDF_CONTEXTBYTES].copy_from_slice(context);
+            salt[..8].copy_from_slice(&subkey_id.to_le_bytes());
 
-        let state = blake2b::State::init(
-            CRYPTO_KDF_KEYBYTES as u8,
-            Some(main_key),
-            Some(&salt),
-            Some(&ctx_padded),
-        )?;
-        state.finalize(subkey)
-    }
+            let state = blake2b::State::init(
+                CRYPTO_KDF_KEYBYTES as u8,
+                Some(main_key),
+                Some(&salt),
+                Some(&ctx_padded),
+            )?;
+            state.finalize(subkey)
+        }
+    })
 }
 
 #[cfg(test)]
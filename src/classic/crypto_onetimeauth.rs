@@ -112,7 +112,9 @@ pub fn crypto_onetimeauth(mac: &mut Mac, message: &[u8], key: &Key) {
 ///
 /// Equivalent to libsodium's `crypto_onetimeauth_verify`.
 pub fn crypto_onetimeauth_verify(mac: &Mac, input: &[u8], key: &Key) -> Result<(), Error> {
-    crypto_onetimeauth_poly1305_verify(mac, input, key)
+    with_context!("crypto_onetimeauth_verify", [input = input.len()], {
+        crypto_onetimeauth_poly1305_verify(mac, input, key)
+    })
 }
 
 /// Internal state for [`crypto_onetimeauth`].
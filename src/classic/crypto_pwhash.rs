@@ -57,9 +57,9 @@ pub(crate) const STR_HASHBYTES: usize = 32;
 
 #[cfg_attr(
     feature = "serde",
-    derive(Zeroize, Clone, Debug, Serialize, Deserialize)
+    derive(Zeroize, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)
 )]
-#[cfg_attr(not(feature = "serde"), derive(Zeroize, Clone, Debug))]
+#[cfg_attr(not(feature = "serde"), derive(Zeroize, Clone, Debug, PartialEq, Eq))]
 /// Password hash algorithm implementations.
 pub enum PasswordHashAlgorithm {
     /// Argon2i version 0x13 (v19)
@@ -119,6 +119,28 @@ pub fn crypto_pwhash(
     opslimit: u64,
     memlimit: usize,
     algorithm: PasswordHashAlgorithm,
+) -> Result<(), Error> {
+    crypto_pwhash_with_progress(
+        output,
+        password,
+        salt,
+        opslimit,
+        memlimit,
+        algorithm,
+        &mut |_, _| true,
+    )
+}
+
+/// Same as [`crypto_pwhash`], but calls `progress` as hashing proceeds, and
+/// aborts if it returns false.
+pub(crate) fn crypto_pwhash_with_progress(
+    output: &mut [u8],
+    password: &[u8],
+    salt: &[u8],
+    opslimit: u64,
+    memlimit: usize,
+    algorithm: PasswordHashAlgorithm,
+    progress: &mut dyn FnMut(u32, u32) -> bool,
 ) -> Result<(), Error> {
     validate!(
         CRYPTO_PWHASH_OPSLIMIT_MIN,
@@ -145,6 +167,7 @@ pub fn crypto_pwhash(
         None,
         output,
         algorithm.into(),
+        progress,
     )
 }
 
@@ -205,6 +228,7 @@ pub fn crypto_pwhash_str(password: &[u8], opslimit: u64, memlimit: usize) -> Res
         None,
         &mut hash,
         argon2::Argon2Type::Argon2id,
+        &mut |_, _| true,
     )?;
 
     let pw = pwhash_to_string(t_cost, m_cost, &salt, &hash);
@@ -224,15 +248,26 @@ pub(crate) struct Pwhash {
     pub(crate) version: Option<u32>,
 }
 
+/// Decodes unpadded standard base64, as used by PHC strings, in constant time
+/// with respect to the encoded data, so that decoding a stored hash doesn't
+/// leak it through timing. Non-canonical encodings (with padding, or non-zero
+/// trailing bits) are rejected.
+#[cfg(feature = "base64")]
+fn base64_decode_unpadded(encoded: &str) -> Option<Vec<u8>> {
+    use crate::utils::{base64_decode_into, base64_decoded_len};
+
+    if encoded.contains('=') {
+        return None;
+    }
+    let mut output = vec![0u8; base64_decoded_len(encoded).ok()?];
+    base64_decode_into(&mut output, encoded).ok()?;
+    Some(output)
+}
+
 #[cfg(feature = "base64")]
 impl Pwhash {
     pub(crate) fn parse_encoded_pwhash(hashed_password: &str) -> Result<Self, Error> {
-        use base64::Engine;
         let mut pwhash = Pwhash::default();
-        let base64_engine = base64::engine::general_purpose::GeneralPurpose::new(
-            &base64::alphabet::STANDARD,
-            base64::engine::general_purpose::NO_PAD,
-        );
 
         for s in hashed_password.split('$') {
             if s.is_empty() {
@@ -266,9 +301,9 @@ impl Pwhash {
                     }
                 }
             } else if pwhash.salt.is_none() {
-                pwhash.salt = base64_engine.decode(s).ok();
+                pwhash.salt = base64_decode_unpadded(s);
             } else if pwhash.pwhash.is_none() {
-                pwhash.pwhash = base64_engine.decode(s).ok();
+                pwhash.pwhash = base64_decode_unpadded(s);
             }
         }
 
@@ -302,27 +337,39 @@ impl Pwhash {
 #[cfg(any(feature = "base64", all(doc, not(doctest))))]
 #[cfg_attr(all(feature = "nightly", doc), doc(cfg(feature = "base64")))]
 pub fn crypto_pwhash_str_verify(hashed_password: &str, password: &[u8]) -> Result<(), Error> {
-    let mut hash = [0u8; STR_HASHBYTES];
-
-    let pwhash = Pwhash::parse_encoded_pwhash(hashed_password)?;
-
-    argon2_hash(
-        pwhash.t_cost.unwrap(),
-        pwhash.m_cost.unwrap(),
-        pwhash.parallelism.unwrap(),
-        password,
-        pwhash.salt.unwrap().as_ref(),
-        None,
-        None,
-        &mut hash,
-        pwhash.type_.unwrap().into(),
-    )?;
-
-    if hash.ct_eq(pwhash.pwhash.unwrap().as_ref()).unwrap_u8() == 1 {
-        Ok(())
-    } else {
-        Err(dryoc_error!("password hashes do not match"))
-    }
+    with_context!(
+        "crypto_pwhash_str_verify",
+        [hashed_password = hashed_password.len(), password = password.len()],
+        {
+            let mut hash = [0u8; STR_HASHBYTES];
+
+            let pwhash = Pwhash::parse_encoded_pwhash(hashed_password)?;
+            let mut stored = pwhash.pwhash.unwrap();
+
+            let result = argon2_hash(
+                pwhash.t_cost.unwrap(),
+                pwhash.m_cost.unwrap(),
+                pwhash.parallelism.unwrap(),
+                password,
+                pwhash.salt.unwrap().as_ref(),
+                None,
+                None,
+                &mut hash,
+                pwhash.type_.unwrap().into(),
+                &mut |_, _| true,
+            );
+            let matches = hash.ct_eq(&stored).unwrap_u8();
+            hash.zeroize();
+            stored.zeroize();
+            result?;
+
+            if matches == 1 {
+                Ok(())
+            } else {
+                Err(dryoc_error!("password hashes do not match"))
+            }
+        }
+    )
 }
 
 /// Checks if the parameters for `hashed_password` match those passed to the
@@ -412,6 +459,29 @@ mod tests {
         ));
     }
 
+    #[cfg(feature = "base64")]
+    #[test]
+    fn test_base64_decode_unpadded() {
+        use base64::engine::general_purpose;
+        use base64::Engine as _;
+
+        use crate::rng::randombytes_buf;
+
+        for len in 0..40 {
+            let data = randombytes_buf(len);
+            let encoded = general_purpose::STANDARD_NO_PAD.encode(&data);
+            assert_eq!(base64_decode_unpadded(&encoded), Some(data));
+        }
+        for invalid in ["A", "AB=", "AA==", "A-B_", "AB", "AAB", "ÀAAA"] {
+            assert_eq!(
+                base64_decode_unpadded(invalid),
+                general_purpose::STANDARD_NO_PAD.decode(invalid).ok(),
+                "{:?}",
+                invalid
+            );
+        }
+    }
+
     #[cfg(feature = "base64")]
     #[test]
     fn test_crypto_pwhash_str_verify() {
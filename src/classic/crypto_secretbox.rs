@@ -33,7 +33,8 @@
 
 use crate::classic::crypto_secretbox_impl::*;
 use crate::constants::{
-    CRYPTO_SECRETBOX_KEYBYTES, CRYPTO_SECRETBOX_MACBYTES, CRYPTO_SECRETBOX_NONCEBYTES,
+    CRYPTO_SECRETBOX_BOXZEROBYTES, CRYPTO_SECRETBOX_KEYBYTES, CRYPTO_SECRETBOX_MACBYTES,
+    CRYPTO_SECRETBOX_NONCEBYTES, CRYPTO_SECRETBOX_ZEROBYTES,
 };
 use crate::error::Error;
 use crate::rng::copy_randombytes;
@@ -81,9 +82,15 @@ pub fn crypto_secretbox_open_detached(
     nonce: &Nonce,
     key: &Key,
 ) -> Result<(), Error> {
-    let c_len = ciphertext.len();
-    message[..c_len].copy_from_slice(ciphertext);
-    crypto_secretbox_open_detached_inplace(message, mac, nonce, key)
+    with_context!(
+        "crypto_secretbox_open_detached",
+        [message = message.len(), ciphertext = ciphertext.len()],
+        {
+            let c_len = ciphertext.len();
+            message[..c_len].copy_from_slice(ciphertext);
+            crypto_secretbox_open_detached_inplace(message, mac, nonce, key)
+        }
+    )
 }
 
 /// Encrypts `message` with `nonce` and `key`.
@@ -118,17 +125,23 @@ pub fn crypto_secretbox_open_easy(
     nonce: &Nonce,
     key: &Key,
 ) -> Result<(), Error> {
-    if ciphertext.len() < CRYPTO_SECRETBOX_MACBYTES {
-        Err(dryoc_error!(format!(
-            "Impossibly small box ({} < {}",
-            ciphertext.len(),
-            CRYPTO_SECRETBOX_MACBYTES
-        )))
-    } else {
-        let (mac, ciphertext) = ciphertext.split_at(CRYPTO_SECRETBOX_MACBYTES);
-        let mac = mac.as_array();
-        crypto_secretbox_open_detached(message, mac, ciphertext, nonce, key)
-    }
+    with_context!(
+        "crypto_secretbox_open_easy",
+        [message = message.len(), ciphertext = ciphertext.len()],
+        {
+            if ciphertext.len() < CRYPTO_SECRETBOX_MACBYTES {
+                Err(dryoc_error!(format!(
+                    "Impossibly small box ({} < {}",
+                    ciphertext.len(),
+                    CRYPTO_SECRETBOX_MACBYTES
+                )))
+            } else {
+                let (mac, ciphertext) = ciphertext.split_at(CRYPTO_SECRETBOX_MACBYTES);
+                let mac = ByteArray::as_array(mac);
+                crypto_secretbox_open_detached(message, mac, ciphertext, nonce, key)
+            }
+        }
+    )
 }
 
 /// Encrypts `message` with `nonce` and `key` in-place, without allocating
@@ -140,7 +153,7 @@ pub fn crypto_secretbox_easy_inplace(
 ) -> Result<(), Error> {
     data.rotate_right(CRYPTO_SECRETBOX_MACBYTES);
     let (mac, data) = data.split_at_mut(CRYPTO_SECRETBOX_MACBYTES);
-    let mac = mac.as_mut_array();
+    let mac = MutByteArray::as_mut_array(mac);
 
     crypto_secretbox_detached_inplace(data, mac, nonce, key);
 
@@ -154,24 +167,127 @@ pub fn crypto_secretbox_open_easy_inplace(
     nonce: &Nonce,
     key: &Key,
 ) -> Result<(), Error> {
-    if ciphertext.len() < CRYPTO_SECRETBOX_MACBYTES {
+    with_context!("crypto_secretbox_open_easy_inplace", [ciphertext = ciphertext.len()], {
+        if ciphertext.len() < CRYPTO_SECRETBOX_MACBYTES {
+            Err(dryoc_error!(format!(
+                "Impossibly small box ({} < {}",
+                ciphertext.len(),
+                CRYPTO_SECRETBOX_MACBYTES
+            )))
+        } else {
+            let (mac, data) = ciphertext.split_at_mut(CRYPTO_SECRETBOX_MACBYTES);
+            let mac = ByteArray::as_array(mac);
+
+            crypto_secretbox_open_detached_inplace(data, mac, nonce, key)?;
+
+            ciphertext.rotate_left(CRYPTO_SECRETBOX_MACBYTES);
+
+            Ok(())
+        }
+    })
+}
+
+/// Encrypts `message` with `nonce` and `key`, using the legacy NaCl
+/// zero-padded layout.
+///
+/// `message` must begin with [`CRYPTO_SECRETBOX_ZEROBYTES`] zero bytes, and
+/// `ciphertext` must be the same length as `message`. Upon return, the first
+/// [`CRYPTO_SECRETBOX_BOXZEROBYTES`] bytes of `ciphertext` are zero, followed
+/// by the message authentication code and the encrypted message.
+///
+/// Provided for interoperability with NaCl-based systems. Prefer
+/// [`crypto_secretbox_easy`] or [`crypto_secretbox_detached`] otherwise.
+///
+/// Compatible with libsodium's `crypto_secretbox`.
+pub fn crypto_secretbox(
+    ciphertext: &mut [u8],
+    message: &[u8],
+    nonce: &Nonce,
+    key: &Key,
+) -> Result<(), Error> {
+    if message.len() < CRYPTO_SECRETBOX_ZEROBYTES {
+        Err(dryoc_error!(format!(
+            "message length {} less than minimum {}",
+            message.len(),
+            CRYPTO_SECRETBOX_ZEROBYTES
+        )))
+    } else if ciphertext.len() != message.len() {
         Err(dryoc_error!(format!(
-            "Impossibly small box ({} < {}",
+            "ciphertext length {} doesn't match message length {}",
             ciphertext.len(),
-            CRYPTO_SECRETBOX_MACBYTES
+            message.len()
+        )))
+    } else if message[..CRYPTO_SECRETBOX_ZEROBYTES]
+        .iter()
+        .any(|b| *b != 0)
+    {
+        Err(dryoc_error!(format!(
+            "message must begin with {} zero bytes",
+            CRYPTO_SECRETBOX_ZEROBYTES
         )))
     } else {
-        let (mac, data) = ciphertext.split_at_mut(CRYPTO_SECRETBOX_MACBYTES);
-        let mac = mac.as_array();
-
-        crypto_secretbox_open_detached_inplace(data, mac, nonce, key)?;
-
-        ciphertext.rotate_left(CRYPTO_SECRETBOX_MACBYTES);
+        let (padding, rest) = ciphertext.split_at_mut(CRYPTO_SECRETBOX_BOXZEROBYTES);
+        let (mac, data) = rest.split_at_mut(CRYPTO_SECRETBOX_MACBYTES);
+        padding.fill(0);
+        crypto_secretbox_detached(
+            data,
+            MutByteArray::as_mut_array(mac),
+            &message[CRYPTO_SECRETBOX_ZEROBYTES..],
+            nonce,
+            key,
+        );
 
         Ok(())
     }
 }
 
+/// Decrypts `ciphertext` with `nonce` and `key`, using the legacy NaCl
+/// zero-padded layout.
+///
+/// `ciphertext` must begin with [`CRYPTO_SECRETBOX_BOXZEROBYTES`] bytes of
+/// padding (which are ignored), and `message` must be the same length as
+/// `ciphertext`. Upon return, the first [`CRYPTO_SECRETBOX_ZEROBYTES`] bytes of
+/// `message` are zero, followed by the decrypted message.
+///
+/// Compatible with libsodium's `crypto_secretbox_open`.
+pub fn crypto_secretbox_open(
+    message: &mut [u8],
+    ciphertext: &[u8],
+    nonce: &Nonce,
+    key: &Key,
+) -> Result<(), Error> {
+    with_context!(
+        "crypto_secretbox_open",
+        [message = message.len(), ciphertext = ciphertext.len()],
+        {
+            if ciphertext.len() < CRYPTO_SECRETBOX_ZEROBYTES {
+                Err(dryoc_error!(format!(
+                    "ciphertext length {} less than minimum {}",
+                    ciphertext.len(),
+                    CRYPTO_SECRETBOX_ZEROBYTES
+                )))
+            } else if message.len() != ciphertext.len() {
+                Err(dryoc_error!(format!(
+                    "message length {} doesn't match ciphertext length {}",
+                    message.len(),
+                    ciphertext.len()
+                )))
+            } else {
+                let mac = ByteArray::as_array(&ciphertext[CRYPTO_SECRETBOX_BOXZEROBYTES..]);
+                let (padding, data) = message.split_at_mut(CRYPTO_SECRETBOX_ZEROBYTES);
+                padding.fill(0);
+                crypto_secretbox_open_detached(
+                    data,
+                    mac,
+                    &ciphertext[CRYPTO_SECRETBOX_ZEROBYTES..],
+                    nonce,
+                    key,
+                )
+            }
+        }
+    )
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -261,4 +377,92 @@ mod tests {
             assert_eq!(decrypted, so_decrypted);
         }
     }
+
+    #[test]
+    fn test_crypto_secretbox_nacl() {
+        use libsodium_sys::{
+            crypto_secretbox as so_crypto_secretbox,
+            crypto_secretbox_open as so_crypto_secretbox_open,
+        };
+
+        for i in 0..20 {
+            let key = crypto_secretbox_keygen();
+            let nonce = Nonce::gen();
+
+            let words = vec!["love Doge".to_string(); i];
+            let mut message = vec![0u8; CRYPTO_SECRETBOX_ZEROBYTES];
+            message.extend_from_slice(words.join(" <3 ").as_bytes());
+
+            let mut ciphertext = vec![0xffu8; message.len()];
+            crypto_secretbox(&mut ciphertext, &message, &nonce, &key).expect("encrypt failed");
+
+            let mut so_ciphertext = vec![0u8; message.len()];
+            let ret = unsafe {
+                so_crypto_secretbox(
+                    so_ciphertext.as_mut_ptr(),
+                    message.as_ptr(),
+                    message.len() as u64,
+                    nonce.as_ptr(),
+                    key.as_ptr(),
+                )
+            };
+            assert_eq!(ret, 0);
+            assert_eq!(ciphertext, so_ciphertext);
+
+            let mut decrypted = vec![0xffu8; ciphertext.len()];
+            crypto_secretbox_open(&mut decrypted, &ciphertext, &nonce, &key)
+                .expect("decrypt failed");
+            assert_eq!(decrypted, message);
+
+            let mut so_decrypted = vec![0u8; ciphertext.len()];
+            let ret = unsafe {
+                so_crypto_secretbox_open(
+                    so_decrypted.as_mut_ptr(),
+                    ciphertext.as_ptr(),
+                    ciphertext.len() as u64,
+                    nonce.as_ptr(),
+                    key.as_ptr(),
+                )
+            };
+            assert_eq!(ret, 0);
+            assert_eq!(so_decrypted, message);
+
+            ciphertext[CRYPTO_SECRETBOX_BOXZEROBYTES] ^= 1;
+            crypto_secretbox_open(&mut decrypted, &ciphertext, &nonce, &key)
+                .expect_err("decrypt should fail");
+        }
+
+        let key = crypto_secretbox_keygen();
+        let nonce = Nonce::gen();
+        let mut ciphertext = [0u8; CRYPTO_SECRETBOX_ZEROBYTES + 1];
+        crypto_secretbox(&mut ciphertext, &[1u8; CRYPTO_SECRETBOX_ZEROBYTES + 1], &nonce, &key)
+            .expect_err("non-zero padding should fail");
+        crypto_secretbox(&mut ciphertext, &[0u8; CRYPTO_SECRETBOX_ZEROBYTES], &nonce, &key)
+            .expect_err("length mismatch should fail");
+    }
+
+    #[cfg(feature = "rich-errors")]
+    #[test]
+    fn test_rich_errors() {
+        let key = crypto_secretbox_keygen();
+        let nonce = Nonce::default();
+        let ciphertext = [0u8; 32];
+        let mut message = [0u8; 16];
+
+        let err = crypto_secretbox_open_easy(&mut message, &ciphertext, &nonce, &key)
+            .expect_err("decrypt should fail");
+        let context = err.context().expect("missing context");
+        assert_eq!(context.operation, "crypto_secretbox_open_detached");
+        assert_eq!(context.sizes, vec![("message", 16), ("ciphertext", 16)]);
+        assert!(matches!(err.inner(), Error::Message(_)));
+
+        let err = crypto_secretbox_open_easy(&mut message, &ciphertext[..8], &nonce, &key)
+            .expect_err("decrypt should fail");
+        let context = err.context().expect("missing context");
+        assert_eq!(context.operation, "crypto_secretbox_open_easy");
+        assert_eq!(context.sizes, vec![("message", 16), ("ciphertext", 8)]);
+        assert!(err
+            .to_string()
+            .ends_with("in crypto_secretbox_open_easy (message=16, ciphertext=8)"));
+    }
 }
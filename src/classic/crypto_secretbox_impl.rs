@@ -1,24 +1,21 @@
 use generic_array::GenericArray;
 use salsa20::cipher::{KeyIvInit, StreamCipher};
-use salsa20::XSalsa20;
+use salsa20::{Salsa20, XSalsa20};
 use subtle::ConstantTimeEq;
 use zeroize::Zeroize;
 
 use crate::classic::crypto_secretbox::{Key, Mac, Nonce};
+use crate::constants::{CRYPTO_CORE_HSALSA20_OUTPUTBYTES, CRYPTO_STREAM_SALSA20_NONCEBYTES};
 use crate::error::Error;
 use crate::poly1305::Poly1305;
 
-pub(crate) fn crypto_secretbox_detached_inplace(
-    data: &mut [u8],
-    mac: &mut Mac,
-    nonce: &Nonce,
-    key: &Key,
-) {
-    let mut cipher = XSalsa20::new(
-        GenericArray::from_slice(key),
-        GenericArray::from_slice(nonce),
-    );
+/// Subkey derived with HSalsa20 from the key and the first 16 bytes of the
+/// nonce, which XSalsa20 uses to key Salsa20.
+pub(crate) type Subkey = [u8; CRYPTO_CORE_HSALSA20_OUTPUTBYTES];
+/// The last 8 bytes of the nonce, which XSalsa20 passes to Salsa20.
+pub(crate) type NonceSuffix = [u8; CRYPTO_STREAM_SALSA20_NONCEBYTES];
 
+fn seal_with_cipher<C: StreamCipher>(cipher: &mut C, data: &mut [u8], mac: &mut Mac) {
     let mut mac_key = crate::poly1305::Key::new();
     cipher.apply_keystream(&mut mac_key);
 
@@ -32,9 +29,33 @@ pub(crate) fn crypto_secretbox_detached_inplace(
     computed_mac.finalize(mac);
 }
 
-pub(crate) fn crypto_secretbox_open_detached_inplace(
+fn open_with_cipher<C: StreamCipher>(
+    cipher: &mut C,
     data: &mut [u8],
     mac: &Mac,
+) -> Result<(), Error> {
+    let mut mac_key = crate::poly1305::Key::new();
+    cipher.apply_keystream(&mut mac_key);
+
+    let mut computed_mac = Poly1305::new(&mac_key);
+    mac_key.zeroize();
+
+    computed_mac.update(data);
+    let computed_mac = computed_mac.finalize_to_array();
+
+    cipher.apply_keystream(data);
+
+    if mac.ct_eq(&computed_mac).unwrap_u8() == 1 {
+        Ok(())
+    } else {
+        Err(dryoc_error!("decryption error (authentication failure)"))
+    }
+}
+
+/// Verifies `mac` for `ciphertext`, without decrypting it.
+pub(crate) fn crypto_secretbox_verify_detached(
+    ciphertext: &[u8],
+    mac: &Mac,
     nonce: &Nonce,
     key: &Key,
 ) -> Result<(), Error> {
@@ -42,21 +63,74 @@ pub(crate) fn crypto_secretbox_open_detached_inplace(
         GenericArray::from_slice(key),
         GenericArray::from_slice(nonce),
     );
-
     let mut mac_key = crate::poly1305::Key::new();
     cipher.apply_keystream(&mut mac_key);
 
     let mut computed_mac = Poly1305::new(&mac_key);
     mac_key.zeroize();
 
-    computed_mac.update(data);
+    computed_mac.update(ciphertext);
     let computed_mac = computed_mac.finalize_to_array();
 
-    cipher.apply_keystream(data);
-
     if mac.ct_eq(&computed_mac).unwrap_u8() == 1 {
         Ok(())
     } else {
-        Err(dryoc_error!("decryption error (authentication failure)"))
+        Err(dryoc_error!("authentication failure"))
     }
 }
+
+pub(crate) fn crypto_secretbox_detached_inplace(
+    data: &mut [u8],
+    mac: &mut Mac,
+    nonce: &Nonce,
+    key: &Key,
+) {
+    let mut cipher = XSalsa20::new(
+        GenericArray::from_slice(key),
+        GenericArray::from_slice(nonce),
+    );
+    seal_with_cipher(&mut cipher, data, mac)
+}
+
+pub(crate) fn crypto_secretbox_open_detached_inplace(
+    data: &mut [u8],
+    mac: &Mac,
+    nonce: &Nonce,
+    key: &Key,
+) -> Result<(), Error> {
+    let mut cipher = XSalsa20::new(
+        GenericArray::from_slice(key),
+        GenericArray::from_slice(nonce),
+    );
+    open_with_cipher(&mut cipher, data, mac)
+}
+
+/// Same as [`crypto_secretbox_detached_inplace`], but with the HSalsa20
+/// subkey already derived from the key and nonce prefix.
+pub(crate) fn crypto_secretbox_detached_inplace_with_subkey(
+    data: &mut [u8],
+    mac: &mut Mac,
+    nonce_suffix: &NonceSuffix,
+    subkey: &Subkey,
+) {
+    let mut cipher = Salsa20::new(
+        GenericArray::from_slice(subkey),
+        GenericArray::from_slice(nonce_suffix),
+    );
+    seal_with_cipher(&mut cipher, data, mac)
+}
+
+/// Same as [`crypto_secretbox_open_detached_inplace`], but with the HSalsa20
+/// subkey already derived from the key and nonce prefix.
+pub(crate) fn crypto_secretbox_open_detached_inplace_with_subkey(
+    data: &mut [u8],
+    mac: &Mac,
+    nonce_suffix: &NonceSuffix,
+    subkey: &Subkey,
+) -> Result<(), Error> {
+    let mut cipher = Salsa20::new(
+        GenericArray::from_slice(subkey),
+        GenericArray::from_slice(nonce_suffix),
+    );
+    open_with_cipher(&mut cipher, data, mac)
+}
@@ -161,7 +161,7 @@ pub fn crypto_secretstream_xchacha20poly1305_init_push(
     copy_randombytes(header);
 
     let mut k = HChaCha20Key::default();
-    crypto_core_hchacha20(k.as_mut_array(), header[..16].as_array(), key, None);
+    crypto_core_hchacha20(k.as_mut_array(), ByteArray::as_array(&header[..16]), key, None);
     // Copy key into state
     state.k.copy_from_slice(&k);
     _crypto_secretstream_xchacha20poly1305_counter_reset(state);
@@ -186,7 +186,7 @@ pub fn crypto_secretstream_xchacha20poly1305_init_pull(
     key: &Key,
 ) {
     let mut k = HChaCha20Key::default();
-    crypto_core_hchacha20(k.as_mut_array(), header[0..16].as_array(), key, None);
+    crypto_core_hchacha20(k.as_mut_array(), ByteArray::as_array(&header[0..16]), key, None);
     state.k.copy_from_slice(&k);
 
     _crypto_secretstream_xchacha20poly1305_counter_reset(state);
@@ -281,7 +281,7 @@ pub fn crypto_secretstream_xchacha20poly1305_push(
     mac_key.zeroize();
 
     mac.update(associated_data);
-    mac.update(&_pad0[..((0x10 - associated_data.len()) & 0xf)]);
+    mac.update(&_pad0[..(0x10usize.wrapping_sub(associated_data.len()) & 0xf)]);
 
     let mut block = [0u8; 64];
     block[0] = tag;
@@ -349,95 +349,101 @@ pub fn crypto_secretstream_xchacha20poly1305_pull(
     ciphertext: &[u8],
     associated_data: Option<&[u8]>,
 ) -> Result<usize, Error> {
-    use chacha20::cipher::{KeyIvInit, StreamCipher, StreamCipherSeek};
-    use chacha20::{ChaCha20, Key, Nonce};
-
-    use crate::poly1305::Poly1305;
-
-    let _pad0 = [0u8; 16];
-
-    if message.len() < ciphertext.len() - CRYPTO_SECRETSTREAM_XCHACHA20POLY1305_ABYTES {
-        return Err(dryoc_error!(format!(
-            "Message length was {}, should be at least {}",
-            message.len(),
-            ciphertext.len() - CRYPTO_SECRETSTREAM_XCHACHA20POLY1305_ABYTES
-        )));
-    }
+    with_context!(
+        "crypto_secretstream_xchacha20poly1305_pull",
+        [message = message.len(), ciphertext = ciphertext.len(), associated_data = associated_data.map_or(0, <[u8]>::len)],
+        {
+            use chacha20::cipher::{KeyIvInit, StreamCipher, StreamCipherSeek};
+            use chacha20::{ChaCha20, Key, Nonce};
+
+            use crate::poly1305::Poly1305;
+
+            let _pad0 = [0u8; 16];
+
+            if message.len() < ciphertext.len() - CRYPTO_SECRETSTREAM_XCHACHA20POLY1305_ABYTES {
+                return Err(dryoc_error!(format!(
+                    "Message length was {}, should be at least {}",
+                    message.len(),
+                    ciphertext.len() - CRYPTO_SECRETSTREAM_XCHACHA20POLY1305_ABYTES
+                )));
+            }
 
-    if ciphertext.len() > CRYPTO_SECRETSTREAM_XCHACHA20POLY1305_MESSAGEBYTES_MAX {
-        return Err(dryoc_error!(format!(
-            "Message length {} exceeds max length {}",
-            ciphertext.len(),
-            CRYPTO_SECRETSTREAM_XCHACHA20POLY1305_MESSAGEBYTES_MAX
-        )));
-    }
+            if ciphertext.len() > CRYPTO_SECRETSTREAM_XCHACHA20POLY1305_MESSAGEBYTES_MAX {
+                return Err(dryoc_error!(format!(
+                    "Message length {} exceeds max length {}",
+                    ciphertext.len(),
+                    CRYPTO_SECRETSTREAM_XCHACHA20POLY1305_MESSAGEBYTES_MAX
+                )));
+            }
 
-    let associated_data = associated_data.unwrap_or(&[]);
+            let associated_data = associated_data.unwrap_or(&[]);
 
-    let mut mac_key = crate::poly1305::Key::new();
+            let mut mac_key = crate::poly1305::Key::new();
 
-    let key = Key::from_slice(&state.k);
-    let nonce = Nonce::from_slice(&state.nonce);
-    let mut cipher = ChaCha20::new(key, nonce);
+            let key = Key::from_slice(&state.k);
+            let nonce = Nonce::from_slice(&state.nonce);
+            let mut cipher = ChaCha20::new(key, nonce);
 
-    cipher.apply_keystream(&mut mac_key);
-    let mut mac = Poly1305::new(&mac_key);
-    mac_key.zeroize();
+            cipher.apply_keystream(&mut mac_key);
+            let mut mac = Poly1305::new(&mac_key);
+            mac_key.zeroize();
 
-    mac.update(associated_data);
-    mac.update(&_pad0[..((0x10 - associated_data.len()) & 0xf)]);
+            mac.update(associated_data);
+            mac.update(&_pad0[..(0x10usize.wrapping_sub(associated_data.len()) & 0xf)]);
 
-    let mut block = [0u8; 64];
-    block[0] = ciphertext[0];
+            let mut block = [0u8; 64];
+            block[0] = ciphertext[0];
 
-    cipher.seek(64);
-    cipher.apply_keystream(&mut block);
+            cipher.seek(64);
+            cipher.apply_keystream(&mut block);
 
-    *tag = block[0];
-    block[0] = ciphertext[0];
+            *tag = block[0];
+            block[0] = ciphertext[0];
 
-    mac.update(&block);
+            mac.update(&block);
 
-    let mlen = ciphertext.len() - CRYPTO_SECRETSTREAM_XCHACHA20POLY1305_ABYTES;
-    message[..mlen].copy_from_slice(&ciphertext[1..1 + mlen]);
+            let mlen = ciphertext.len() - CRYPTO_SECRETSTREAM_XCHACHA20POLY1305_ABYTES;
+            message[..mlen].copy_from_slice(&ciphertext[1..1 + mlen]);
 
-    // this is to workaround an unfortunate padding bug in libsodium, there's a
-    // note in commit 290197ba3ee72245fdab5e971c8de43a82b19874. There's no
-    // safety issue, so we can just pretend it's not a bug.
-    let buffer_mac_pad = ((0x10 - block.len() as i64 + mlen as i64) & 0xf) as usize;
-    mac.update(&message[..mlen]);
-    mac.update(&_pad0[..buffer_mac_pad]);
+            // this is to workaround an unfortunate padding bug in libsodium, there's a
+            // note in commit 290197ba3ee72245fdab5e971c8de43a82b19874. There's no
+            // safety issue, so we can just pretend it's not a bug.
+            let buffer_mac_pad = ((0x10 - block.len() as i64 + mlen as i64) & 0xf) as usize;
+            mac.update(&message[..mlen]);
+            mac.update(&_pad0[..buffer_mac_pad]);
 
-    let mut size_data = [0u8; 16];
-    size_data[..8].copy_from_slice(&associated_data.len().to_le_bytes());
-    size_data[8..16].copy_from_slice(&(block.len() + mlen).to_le_bytes());
-    mac.update(&size_data);
-    let mac = mac.finalize_to_array();
+            let mut size_data = [0u8; 16];
+            size_data[..8].copy_from_slice(&associated_data.len().to_le_bytes());
+            size_data[8..16].copy_from_slice(&(block.len() + mlen).to_le_bytes());
+            mac.update(&size_data);
+            let mac = mac.finalize_to_array();
 
-    cipher.seek(128);
-    cipher.apply_keystream(&mut message[..mlen]);
+            cipher.seek(128);
+            cipher.apply_keystream(&mut message[..mlen]);
 
-    if ciphertext[1 + mlen..].ct_eq(&mac).unwrap_u8() == 0 {
-        return Err(dryoc_error!("Message authentication mismatch"));
-    }
+            if ciphertext[1 + mlen..].ct_eq(&mac).unwrap_u8() == 0 {
+                return Err(dryoc_error!("Message authentication mismatch"));
+            }
 
-    let inonce = state_inonce(&mut state.nonce);
-    xor_buf(inonce, &mac);
+            let inonce = state_inonce(&mut state.nonce);
+            xor_buf(inonce, &mac);
 
-    let counter = state_counter(&mut state.nonce);
-    increment_bytes(counter);
+            let counter = state_counter(&mut state.nonce);
+            increment_bytes(counter);
 
-    if *tag & CRYPTO_SECRETSTREAM_XCHACHA20POLY1305_TAG_REKEY
-        == CRYPTO_SECRETSTREAM_XCHACHA20POLY1305_TAG_REKEY
-        || state_counter(&mut state.nonce)
-            .ct_eq(&[0u8; CRYPTO_SECRETSTREAM_XCHACHA20POLY1305_COUNTERBYTES])
-            .unwrap_u8()
-            == 1
-    {
-        crypto_secretstream_xchacha20poly1305_rekey(state);
-    }
+            if *tag & CRYPTO_SECRETSTREAM_XCHACHA20POLY1305_TAG_REKEY
+                == CRYPTO_SECRETSTREAM_XCHACHA20POLY1305_TAG_REKEY
+                || state_counter(&mut state.nonce)
+                    .ct_eq(&[0u8; CRYPTO_SECRETSTREAM_XCHACHA20POLY1305_COUNTERBYTES])
+                    .unwrap_u8()
+                    == 1
+            {
+                crypto_secretstream_xchacha20poly1305_rekey(state);
+            }
 
-    Ok(mlen)
+            Ok(mlen)
+        }
+    )
 }
 
 #[cfg(test)]
@@ -728,7 +734,7 @@ mod tests {
 
         for i in 0..100 {
             let message = format!("hello {}", i);
-            let aad = format!("aad {}", i);
+            let aad = format!("aad {}", i).repeat(i % 4 + 1);
             let tag = if i % 7 == 0 { Tag::REKEY } else { Tag::MESSAGE };
 
             let mut output =
@@ -891,7 +897,7 @@ mod tests {
 
         for i in 0..100 {
             let message = format!("hello {}", i);
-            let aad = format!("aad {}", i);
+            let aad = format!("aad {}", i).repeat(i % 4 + 1);
             let mut so_output =
                 vec![0u8; message.len() + CRYPTO_SECRETSTREAM_XCHACHA20POLY1305_ABYTES];
             let mut clen_p: c_ulonglong = 0;
@@ -117,21 +117,27 @@ pub fn crypto_sign_open(
     signed_message: &[u8],
     public_key: &PublicKey,
 ) -> Result<(), Error> {
-    if signed_message.len() < CRYPTO_SIGN_BYTES {
-        Err(dryoc_error!(format!(
-            "signed_message length invalid ({} < {})",
-            signed_message.len(),
-            CRYPTO_SIGN_BYTES,
-        )))
-    } else if message.len() != signed_message.len() - CRYPTO_SIGN_BYTES {
-        Err(dryoc_error!(format!(
-            "message length incorrect (expect {}, got {})",
-            signed_message.len() - CRYPTO_SIGN_BYTES,
-            message.len()
-        )))
-    } else {
-        crypto_sign_ed25519_open(message, signed_message, public_key)
-    }
+    with_context!(
+        "crypto_sign_open",
+        [message = message.len(), signed_message = signed_message.len()],
+        {
+            if signed_message.len() < CRYPTO_SIGN_BYTES {
+                Err(dryoc_error!(format!(
+                    "signed_message length invalid ({} < {})",
+                    signed_message.len(),
+                    CRYPTO_SIGN_BYTES,
+                )))
+            } else if message.len() != signed_message.len() - CRYPTO_SIGN_BYTES {
+                Err(dryoc_error!(format!(
+                    "message length incorrect (expect {}, got {})",
+                    signed_message.len() - CRYPTO_SIGN_BYTES,
+                    message.len()
+                )))
+            } else {
+                crypto_sign_ed25519_open(message, signed_message, public_key)
+            }
+        }
+    )
 }
 
 /// Signs `message`, placing the signature into `signature` upon success.
@@ -157,7 +163,9 @@ pub fn crypto_sign_verify_detached(
     message: &[u8],
     public_key: &PublicKey,
 ) -> Result<(), Error> {
-    crypto_sign_ed25519_verify_detached(signature, message, public_key)
+    with_context!("crypto_sign_verify_detached", [message = message.len()], {
+        crypto_sign_ed25519_verify_detached(signature, message, public_key)
+    })
 }
 
 /// State for incremental signing interface.
@@ -128,6 +128,30 @@ pub fn crypto_sign_ed25519_sk_to_curve25519(
     scalar.zeroize()
 }
 
+/// Copies the seed from `ed25519_secret_key` into `seed`.
+///
+/// Compatible with libsodium's `crypto_sign_ed25519_sk_to_seed`
+pub fn crypto_sign_ed25519_sk_to_seed(
+    seed: &mut [u8; CRYPTO_SIGN_ED25519_SEEDBYTES],
+    ed25519_secret_key: &SecretKey,
+) {
+    seed.copy_from_slice(&ed25519_secret_key[..CRYPTO_SIGN_ED25519_SEEDBYTES]);
+}
+
+/// Copies the public key from `ed25519_secret_key` into `ed25519_public_key`.
+///
+/// Note that this doesn't check that the public key matches the seed. Use
+/// [`crate::sign::SigningKeyPair::public_from_secret`] to recompute it
+/// instead.
+///
+/// Compatible with libsodium's `crypto_sign_ed25519_sk_to_pk`
+pub fn crypto_sign_ed25519_sk_to_pk(
+    ed25519_public_key: &mut PublicKey,
+    ed25519_secret_key: &SecretKey,
+) {
+    ed25519_public_key.copy_from_slice(&ed25519_secret_key[CRYPTO_SIGN_ED25519_SEEDBYTES..]);
+}
+
 pub(crate) fn crypto_sign_ed25519(
     signed_message: &mut [u8],
     message: &[u8],
@@ -386,4 +410,35 @@ mod tests {
             );
         }
     }
+
+    #[test]
+    fn test_sk_to_seed_and_pk() {
+        use libsodium_sys::{
+            crypto_sign_ed25519_sk_to_pk as so_crypto_sign_ed25519_sk_to_pk,
+            crypto_sign_ed25519_sk_to_seed as so_crypto_sign_ed25519_sk_to_seed,
+        };
+
+        for _ in 0..10 {
+            let mut seed = [0u8; CRYPTO_SIGN_ED25519_SEEDBYTES];
+            copy_randombytes(&mut seed);
+            let (pk, sk) = crypto_sign_ed25519_seed_keypair(&seed);
+
+            let mut extracted_seed = [0u8; CRYPTO_SIGN_ED25519_SEEDBYTES];
+            let mut extracted_pk = PublicKey::default();
+            crypto_sign_ed25519_sk_to_seed(&mut extracted_seed, &sk);
+            crypto_sign_ed25519_sk_to_pk(&mut extracted_pk, &sk);
+
+            let mut so_seed = [0u8; CRYPTO_SIGN_ED25519_SEEDBYTES];
+            let mut so_pk = PublicKey::default();
+            unsafe {
+                so_crypto_sign_ed25519_sk_to_seed(so_seed.as_mut_ptr(), sk.as_ptr());
+                so_crypto_sign_ed25519_sk_to_pk(so_pk.as_mut_ptr(), sk.as_ptr());
+            }
+
+            assert_eq!(extracted_seed, seed);
+            assert_eq!(extracted_seed, so_seed);
+            assert_eq!(extracted_pk, pk);
+            assert_eq!(extracted_pk, so_pk);
+        }
+    }
 }
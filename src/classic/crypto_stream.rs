@@ -0,0 +1,332 @@
+//! # Stream cipher functions
+//!
+//! Implements libsodium's raw stream cipher functions for Salsa20 and
+//! XSalsa20, which produce a keystream from a key and nonce, or XOR a message
+//! with that keystream.
+//!
+//! These functions provide no authentication whatsoever, and are provided for
+//! compatibility with legacy protocols only. For most purposes, you should use
+//! [`crypto_secretbox`](crate::classic::crypto_secretbox) or
+//! [`crypto_secretstream_xchacha20poly1305`](crate::classic::crypto_secretstream_xchacha20poly1305)
+//! instead.
+//!
+//! The `crypto_stream_*` functions without an algorithm name use XSalsa20, in
+//! the same way as libsodium.
+//!
+//! For details, refer to [libsodium docs](https://doc.libsodium.org/advanced/stream_ciphers/xsalsa20).
+//!
+//! ## Classic API example
+//!
+//! ```
+//! use dryoc::classic::crypto_stream::*;
+//! use dryoc::types::*;
+//!
+//! let key = crypto_stream_keygen();
+//! let nonce = XSalsa20Nonce::gen();
+//! let message = b"legacy protocol payload";
+//!
+//! // Mask the message with the keystream
+//! let mut masked = vec![0u8; message.len()];
+//! crypto_stream_xor(&mut masked, message, &nonce, &key).expect("xor failed");
+//!
+//! // Unmask it again by applying the same keystream
+//! let mut unmasked = vec![0u8; masked.len()];
+//! crypto_stream_xor(&mut unmasked, &masked, &nonce, &key).expect("xor failed");
+//!
+//! assert_eq!(&unmasked, message);
+//! ```
+
+use generic_array::GenericArray;
+use salsa20::cipher::{KeyIvInit, StreamCipher, StreamCipherSeek};
+use salsa20::{Salsa20, XSalsa20};
+
+use crate::constants::{
+    CRYPTO_STREAM_SALSA20_KEYBYTES, CRYPTO_STREAM_SALSA20_NONCEBYTES,
+    CRYPTO_STREAM_XSALSA20_KEYBYTES, CRYPTO_STREAM_XSALSA20_NONCEBYTES,
+};
+use crate::error::Error;
+use crate::types::*;
+
+/// Key for the Salsa20 stream cipher.
+pub type Salsa20Key = [u8; CRYPTO_STREAM_SALSA20_KEYBYTES];
+/// Nonce for the Salsa20 stream cipher.
+pub type Salsa20Nonce = [u8; CRYPTO_STREAM_SALSA20_NONCEBYTES];
+/// Key for the XSalsa20 stream cipher.
+pub type XSalsa20Key = [u8; CRYPTO_STREAM_XSALSA20_KEYBYTES];
+/// Nonce for the XSalsa20 stream cipher.
+pub type XSalsa20Nonce = [u8; CRYPTO_STREAM_XSALSA20_NONCEBYTES];
+/// Key for the default stream cipher (XSalsa20).
+pub type Key = XSalsa20Key;
+/// Nonce for the default stream cipher (XSalsa20).
+pub type Nonce = XSalsa20Nonce;
+
+/// Size of a Salsa20 block, which is the unit used for the initial counter in
+/// the `_xor_ic` functions.
+const SALSA20_BLOCKBYTES: u128 = 64;
+
+fn xor_ic<Cipher: StreamCipher + StreamCipherSeek>(
+    mut cipher: Cipher,
+    output: &mut [u8],
+    input: &[u8],
+    ic: u64,
+) -> Result<(), Error> {
+    if output.len() < input.len() {
+        return Err(dryoc_error!(format!(
+            "output length {} less than input length {}",
+            output.len(),
+            input.len()
+        )));
+    }
+    cipher
+        .try_seek(ic as u128 * SALSA20_BLOCKBYTES)
+        .map_err(|_| dryoc_error!("initial counter out of range"))?;
+    let output = &mut output[..input.len()];
+    output.copy_from_slice(input);
+    cipher
+        .try_apply_keystream(output)
+        .map_err(|_| dryoc_error!("keystream exhausted"))
+}
+
+/// Generates a random Salsa20 key using
+/// [`copy_randombytes`](crate::rng::copy_randombytes).
+///
+/// Compatible with libsodium's `crypto_stream_salsa20_keygen`.
+pub fn crypto_stream_salsa20_keygen() -> Salsa20Key {
+    Salsa20Key::gen()
+}
+
+/// Fills `output` with the Salsa20 keystream for `nonce` and `key`.
+///
+/// Compatible with libsodium's `crypto_stream_salsa20`.
+pub fn crypto_stream_salsa20(output: &mut [u8], nonce: &Salsa20Nonce, key: &Salsa20Key) {
+    output.fill(0);
+    let mut cipher = Salsa20::new(
+        GenericArray::from_slice(key),
+        GenericArray::from_slice(nonce),
+    );
+    cipher.apply_keystream(output);
+}
+
+/// Encrypts or decrypts `input` by XORing it with the Salsa20 keystream for
+/// `nonce` and `key`, placing the result into `output`.
+///
+/// Compatible with libsodium's `crypto_stream_salsa20_xor`.
+pub fn crypto_stream_salsa20_xor(
+    output: &mut [u8],
+    input: &[u8],
+    nonce: &Salsa20Nonce,
+    key: &Salsa20Key,
+) -> Result<(), Error> {
+    crypto_stream_salsa20_xor_ic(output, input, nonce, 0, key)
+}
+
+/// Equivalent to [`crypto_stream_salsa20_xor`], but starts the keystream at
+/// block `ic` (i.e., skips the first `ic * 64` bytes of keystream).
+///
+/// Compatible with libsodium's `crypto_stream_salsa20_xor_ic`.
+pub fn crypto_stream_salsa20_xor_ic(
+    output: &mut [u8],
+    input: &[u8],
+    nonce: &Salsa20Nonce,
+    ic: u64,
+    key: &Salsa20Key,
+) -> Result<(), Error> {
+    let cipher = Salsa20::new(
+        GenericArray::from_slice(key),
+        GenericArray::from_slice(nonce),
+    );
+    xor_ic(cipher, output, input, ic)
+}
+
+/// Generates a random XSalsa20 key using
+/// [`copy_randombytes`](crate::rng::copy_randombytes).
+///
+/// Compatible with libsodium's `crypto_stream_xsalsa20_keygen`.
+pub fn crypto_stream_xsalsa20_keygen() -> XSalsa20Key {
+    XSalsa20Key::gen()
+}
+
+/// Fills `output` with the XSalsa20 keystream for `nonce` and `key`.
+///
+/// Compatible with libsodium's `crypto_stream_xsalsa20`.
+pub fn crypto_stream_xsalsa20(output: &mut [u8], nonce: &XSalsa20Nonce, key: &XSalsa20Key) {
+    output.fill(0);
+    let mut cipher = XSalsa20::new(
+        GenericArray::from_slice(key),
+        GenericArray::from_slice(nonce),
+    );
+    cipher.apply_keystream(output);
+}
+
+/// Encrypts or decrypts `input` by XORing it with the XSalsa20 keystream for
+/// `nonce` and `key`, placing the result into `output`.
+///
+/// Compatible with libsodium's `crypto_stream_xsalsa20_xor`.
+pub fn crypto_stream_xsalsa20_xor(
+    output: &mut [u8],
+    input: &[u8],
+    nonce: &XSalsa20Nonce,
+    key: &XSalsa20Key,
+) -> Result<(), Error> {
+    crypto_stream_xsalsa20_xor_ic(output, input, nonce, 0, key)
+}
+
+/// Equivalent to [`crypto_stream_xsalsa20_xor`], but starts the keystream at
+/// block `ic` (i.e., skips the first `ic * 64` bytes of keystream).
+///
+/// Compatible with libsodium's `crypto_stream_xsalsa20_xor_ic`.
+pub fn crypto_stream_xsalsa20_xor_ic(
+    output: &mut [u8],
+    input: &[u8],
+    nonce: &XSalsa20Nonce,
+    ic: u64,
+    key: &XSalsa20Key,
+) -> Result<(), Error> {
+    let cipher = XSalsa20::new(
+        GenericArray::from_slice(key),
+        GenericArray::from_slice(nonce),
+    );
+    xor_ic(cipher, output, input, ic)
+}
+
+/// Generates a random key for the default stream cipher (XSalsa20).
+///
+/// Compatible with libsodium's `crypto_stream_keygen`.
+pub fn crypto_stream_keygen() -> Key {
+    crypto_stream_xsalsa20_keygen()
+}
+
+/// Fills `output` with the keystream of the default stream cipher (XSalsa20).
+///
+/// Compatible with libsodium's `crypto_stream`.
+pub fn crypto_stream(output: &mut [u8], nonce: &Nonce, key: &Key) {
+    crypto_stream_xsalsa20(output, nonce, key)
+}
+
+/// XORs `input` with the keystream of the default stream cipher (XSalsa20).
+///
+/// Compatible with libsodium's `crypto_stream_xor`.
+pub fn crypto_stream_xor(
+    output: &mut [u8],
+    input: &[u8],
+    nonce: &Nonce,
+    key: &Key,
+) -> Result<(), Error> {
+    crypto_stream_xsalsa20_xor(output, input, nonce, key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rng::copy_randombytes;
+
+    #[test]
+    fn test_crypto_stream_salsa20() {
+        use libsodium_sys::{
+            crypto_stream_salsa20 as so_crypto_stream_salsa20,
+            crypto_stream_salsa20_xor_ic as so_crypto_stream_salsa20_xor_ic,
+        };
+
+        for i in 0..20 {
+            let key = crypto_stream_salsa20_keygen();
+            let nonce = Salsa20Nonce::gen();
+            let len = i * 37;
+
+            let mut keystream = vec![0u8; len];
+            crypto_stream_salsa20(&mut keystream, &nonce, &key);
+            let mut so_keystream = vec![0u8; len];
+            unsafe {
+                so_crypto_stream_salsa20(
+                    so_keystream.as_mut_ptr(),
+                    len as u64,
+                    nonce.as_ptr(),
+                    key.as_ptr(),
+                )
+            };
+            assert_eq!(keystream, so_keystream);
+
+            let mut message = vec![0u8; len];
+            copy_randombytes(&mut message);
+            for ic in [0u64, 1, 7, u32::MAX as u64 + 3] {
+                let mut output = vec![0u8; len];
+                crypto_stream_salsa20_xor_ic(&mut output, &message, &nonce, ic, &key)
+                    .expect("xor failed");
+                let mut so_output = vec![0u8; len];
+                unsafe {
+                    so_crypto_stream_salsa20_xor_ic(
+                        so_output.as_mut_ptr(),
+                        message.as_ptr(),
+                        len as u64,
+                        nonce.as_ptr(),
+                        ic,
+                        key.as_ptr(),
+                    )
+                };
+                assert_eq!(output, so_output);
+            }
+
+            let mut output = vec![0u8; len];
+            crypto_stream_salsa20_xor(&mut output, &message, &nonce, &key).expect("xor failed");
+            for b in output.iter_mut().zip(keystream.iter()) {
+                *b.0 ^= b.1;
+            }
+            assert_eq!(output, message);
+        }
+    }
+
+    #[test]
+    fn test_crypto_stream_xsalsa20() {
+        use libsodium_sys::{
+            crypto_stream_xsalsa20 as so_crypto_stream_xsalsa20,
+            crypto_stream_xsalsa20_xor_ic as so_crypto_stream_xsalsa20_xor_ic,
+        };
+
+        for i in 0..20 {
+            let key = crypto_stream_keygen();
+            let nonce = Nonce::gen();
+            let len = i * 37;
+
+            let mut keystream = vec![0u8; len];
+            crypto_stream(&mut keystream, &nonce, &key);
+            let mut so_keystream = vec![0u8; len];
+            unsafe {
+                so_crypto_stream_xsalsa20(
+                    so_keystream.as_mut_ptr(),
+                    len as u64,
+                    nonce.as_ptr(),
+                    key.as_ptr(),
+                )
+            };
+            assert_eq!(keystream, so_keystream);
+
+            let mut message = vec![0u8; len];
+            copy_randombytes(&mut message);
+            for ic in [0u64, 1, 7, u32::MAX as u64 + 3] {
+                let mut output = vec![0u8; len];
+                crypto_stream_xsalsa20_xor_ic(&mut output, &message, &nonce, ic, &key)
+                    .expect("xor failed");
+                let mut so_output = vec![0u8; len];
+                unsafe {
+                    so_crypto_stream_xsalsa20_xor_ic(
+                        so_output.as_mut_ptr(),
+                        message.as_ptr(),
+                        len as u64,
+                        nonce.as_ptr(),
+                        ic,
+                        key.as_ptr(),
+                    )
+                };
+                assert_eq!(output, so_output);
+            }
+        }
+    }
+
+    #[test]
+    fn test_output_too_short() {
+        let key = crypto_stream_keygen();
+        let nonce = Nonce::gen();
+        let mut output = [0u8; 4];
+        crypto_stream_xor(&mut output, &[0u8; 5], &nonce, &key)
+            .expect_err("short output should fail");
+    }
+}
@@ -0,0 +1,945 @@
+//! # Strongly-typed Classic API
+//!
+//! The [Classic API](crate::classic) accepts keys, nonces, and other fixed
+//! length values as raw `[u8; N]` arrays, which means that arguments of the
+//! same length (such as a key and a MAC for
+//! [`crypto_auth`](crate::classic::crypto_auth)) can be swapped without any
+//! complaint from the compiler.
+//!
+//! This module provides distinct newtypes for each kind of value ([`Key`],
+//! [`Nonce`], [`Mac`], [`Header`], and [`Seed`]), along with versions of the
+//! Classic API functions which accept them, so that passing a nonce where a
+//! key is expected is a compile error. The functions are organized into
+//! submodules which mirror those of the Classic API, and they're otherwise
+//! identical to the raw-array versions, which remain available.
+//!
+//! The newtypes implement the [byte traits](crate::types), so they may also
+//! be used with the Rustaceous API.
+//!
+//! ## Example
+//!
+//! ```
+//! use dryoc::classic::typed::crypto_secretbox::*;
+//!
+//! let key = crypto_secretbox_keygen();
+//! let nonce = Nonce::gen();
+//! let message = b"I Love Doge!";
+//!
+//! let mut ciphertext = vec![0u8; message.len() + CRYPTO_SECRETBOX_MACBYTES];
+//! crypto_secretbox_easy(&mut ciphertext, message, &nonce, &key).expect("encrypt failed");
+//!
+//! // Swapping `nonce` and `key` here would not compile
+//! let mut decrypted = vec![0u8; message.len()];
+//! crypto_secretbox_open_easy(&mut decrypted, &ciphertext, &nonce, &key).expect("decrypt failed");
+//!
+//! assert_eq!(&decrypted, message);
+//! ```
+//!
+//! Raw arrays can be converted into the newtypes with [`From`], and back
+//! again with `as_array()`:
+//!
+//! ```
+//! use dryoc::classic::crypto_auth as raw;
+//! use dryoc::classic::typed::crypto_auth::*;
+//!
+//! let raw_key = raw::crypto_auth_keygen();
+//! let key = Key::from(raw_key);
+//!
+//! let mut mac = Mac::default();
+//! crypto_auth(&mut mac, b"Data to authenticate", &key);
+//!
+//! raw::crypto_auth_verify(mac.as_array(), b"Data to authenticate", key.as_array())
+//!     .expect("failed to authenticate");
+//! ```
+
+use zeroize::{Zeroize, ZeroizeOnDrop};
+
+use crate::error::Error;
+use crate::rng::copy_randombytes;
+use crate::types::*;
+
+macro_rules! impl_newtype {
+    ($(#[$meta:meta])* $name:ident) => {
+        $(#[$meta])*
+        #[derive(Zeroize, ZeroizeOnDrop, Debug, PartialEq, Eq, Clone)]
+        #[repr(transparent)]
+        pub struct $name<const LENGTH: usize>([u8; LENGTH]);
+
+        impl<const LENGTH: usize> $name<LENGTH> {
+            #[doc = concat!("Returns a new [`", stringify!($name), "`] filled with random data.")]
+            pub fn gen() -> Self {
+                let mut res = Self::default();
+                copy_randombytes(&mut res.0);
+                res
+            }
+
+            /// Returns a reference to the underlying array.
+            #[inline]
+            pub fn as_array(&self) -> &[u8; LENGTH] {
+                &self.0
+            }
+
+            /// Returns a mutable reference to the underlying array.
+            #[inline]
+            pub fn as_mut_array(&mut self) -> &mut [u8; LENGTH] {
+                &mut self.0
+            }
+        }
+
+        impl<const LENGTH: usize> Default for $name<LENGTH> {
+            fn default() -> Self {
+                Self([0u8; LENGTH])
+            }
+        }
+
+        impl<const LENGTH: usize> From<[u8; LENGTH]> for $name<LENGTH> {
+            fn from(mut src: [u8; LENGTH]) -> Self {
+                let res = Self(src);
+                src.zeroize();
+                res
+            }
+        }
+
+        impl<const LENGTH: usize> From<&[u8; LENGTH]> for $name<LENGTH> {
+            fn from(src: &[u8; LENGTH]) -> Self {
+                Self(*src)
+            }
+        }
+
+        impl<const LENGTH: usize> TryFrom<&[u8]> for $name<LENGTH> {
+            type Error = Error;
+
+            fn try_from(src: &[u8]) -> Result<Self, Self::Error> {
+                if src.len() != LENGTH {
+                    Err(dryoc_error!(format!(
+                        "Invalid size: expected {} found {}",
+                        LENGTH,
+                        src.len()
+                    )))
+                } else {
+                    let mut res = Self::default();
+                    res.0.copy_from_slice(src);
+                    Ok(res)
+                }
+            }
+        }
+
+        impl<const LENGTH: usize> AsRef<[u8]> for $name<LENGTH> {
+            fn as_ref(&self) -> &[u8] {
+                &self.0
+            }
+        }
+
+        impl<const LENGTH: usize> AsMut<[u8]> for $name<LENGTH> {
+            fn as_mut(&mut self) -> &mut [u8] {
+                &mut self.0
+            }
+        }
+
+        impl<const LENGTH: usize> Bytes for $name<LENGTH> {
+            #[inline]
+            fn as_slice(&self) -> &[u8] {
+                &self.0
+            }
+
+            #[inline]
+            fn len(&self) -> usize {
+                LENGTH
+            }
+
+            #[inline]
+            fn is_empty(&self) -> bool {
+                LENGTH == 0
+            }
+        }
+
+        impl<const LENGTH: usize> MutBytes for $name<LENGTH> {
+            #[inline]
+            fn as_mut_slice(&mut self) -> &mut [u8] {
+                &mut self.0
+            }
+
+            fn copy_from_slice(&mut self, other: &[u8]) {
+                self.0.copy_from_slice(other)
+            }
+        }
+
+        impl<const LENGTH: usize> NewBytes for $name<LENGTH> {
+            fn new_bytes() -> Self {
+                Self::default()
+            }
+        }
+
+        impl<const LENGTH: usize> ByteArray<LENGTH> for $name<LENGTH> {
+            #[inline]
+            fn as_array(&self) -> &[u8; LENGTH] {
+                &self.0
+            }
+        }
+
+        impl<const LENGTH: usize> MutByteArray<LENGTH> for $name<LENGTH> {
+            #[inline]
+            fn as_mut_array(&mut self) -> &mut [u8; LENGTH] {
+                &mut self.0
+            }
+        }
+
+        impl<const LENGTH: usize> NewByteArray<LENGTH> for $name<LENGTH> {
+            fn new_byte_array() -> Self {
+                Self::default()
+            }
+
+            fn gen() -> Self {
+                Self::gen()
+            }
+        }
+    };
+}
+
+impl_newtype!(
+    /// A secret key.
+    Key
+);
+impl_newtype!(
+    /// A nonce.
+    Nonce
+);
+impl_newtype!(
+    /// A message authentication code.
+    Mac
+);
+impl_newtype!(
+    /// A stream header.
+    Header
+);
+impl_newtype!(
+    /// A seed for deterministic key generation.
+    Seed
+);
+
+pub mod crypto_auth {
+    //! Strongly-typed version of [`crypto_auth`](crate::classic::crypto_auth).
+    use super::Error;
+    use crate::classic::crypto_auth as raw;
+    pub use crate::classic::crypto_auth::{crypto_auth_final, crypto_auth_update, AuthState};
+    pub use crate::constants::{CRYPTO_AUTH_BYTES, CRYPTO_AUTH_KEYBYTES};
+
+    /// Key for secret-key message authentication.
+    pub type Key = super::Key<CRYPTO_AUTH_KEYBYTES>;
+    /// Message authentication code type for use with secret-key authentication.
+    pub type Mac = super::Mac<CRYPTO_AUTH_BYTES>;
+
+    /// Typed version of [`raw::crypto_auth`].
+    pub fn crypto_auth(mac: &mut Mac, message: &[u8], key: &Key) {
+        raw::crypto_auth(mac.as_mut_array(), message, key.as_array())
+    }
+
+    /// Typed version of [`raw::crypto_auth_verify`].
+    pub fn crypto_auth_verify(mac: &Mac, input: &[u8], key: &Key) -> Result<(), Error> {
+        raw::crypto_auth_verify(mac.as_array(), input, key.as_array())
+    }
+
+    /// Typed version of [`raw::crypto_auth_keygen`].
+    pub fn crypto_auth_keygen() -> Key {
+        Key::gen()
+    }
+
+    /// Typed version of [`raw::crypto_auth_init`].
+    pub fn crypto_auth_init(key: &Key) -> AuthState {
+        raw::crypto_auth_init(key.as_array())
+    }
+}
+
+pub mod crypto_onetimeauth {
+    //! Strongly-typed version of
+    //! [`crypto_onetimeauth`](crate::classic::crypto_onetimeauth).
+    use super::Error;
+    use crate::classic::crypto_onetimeauth as raw;
+    pub use crate::classic::crypto_onetimeauth::{
+        crypto_onetimeauth_final, crypto_onetimeauth_update, OnetimeauthState,
+    };
+    pub use crate::constants::{CRYPTO_ONETIMEAUTH_BYTES, CRYPTO_ONETIMEAUTH_KEYBYTES};
+
+    /// Key for one-time authentication.
+    pub type Key = super::Key<CRYPTO_ONETIMEAUTH_KEYBYTES>;
+    /// Message authentication code type for use with one-time authentication.
+    pub type Mac = super::Mac<CRYPTO_ONETIMEAUTH_BYTES>;
+
+    /// Typed version of [`raw::crypto_onetimeauth`].
+    pub fn crypto_onetimeauth(mac: &mut Mac, message: &[u8], key: &Key) {
+        raw::crypto_onetimeauth(mac.as_mut_array(), message, key.as_array())
+    }
+
+    /// Typed version of [`raw::crypto_onetimeauth_verify`].
+    pub fn crypto_onetimeauth_verify(mac: &Mac, input: &[u8], key: &Key) -> Result<(), Error> {
+        raw::crypto_onetimeauth_verify(mac.as_array(), input, key.as_array())
+    }
+
+    /// Typed version of [`raw::crypto_onetimeauth_keygen`].
+    pub fn crypto_onetimeauth_keygen() -> Key {
+        Key::gen()
+    }
+
+    /// Typed version of [`raw::crypto_onetimeauth_init`].
+    pub fn crypto_onetimeauth_init(key: &Key) -> OnetimeauthState {
+        raw::crypto_onetimeauth_init(key.as_array())
+    }
+}
+
+pub mod crypto_secretbox {
+    //! Strongly-typed version of
+    //! [`crypto_secretbox`](crate::classic::crypto_secretbox).
+    use super::Error;
+    use crate::classic::crypto_secretbox as raw;
+    pub use crate::constants::{
+        CRYPTO_SECRETBOX_KEYBYTES, CRYPTO_SECRETBOX_MACBYTES, CRYPTO_SECRETBOX_NONCEBYTES,
+        CRYPTO_SECRETBOX_ZEROBYTES,
+    };
+
+    /// Secret box secret key.
+    pub type Key = super::Key<CRYPTO_SECRETBOX_KEYBYTES>;
+    /// Secret box nonce.
+    pub type Nonce = super::Nonce<CRYPTO_SECRETBOX_NONCEBYTES>;
+    /// Secret box message authentication code.
+    pub type Mac = super::Mac<CRYPTO_SECRETBOX_MACBYTES>;
+
+    /// Typed version of [`raw::crypto_secretbox_keygen`].
+    pub fn crypto_secretbox_keygen() -> Key {
+        Key::gen()
+    }
+
+    /// Typed version of [`raw::crypto_secretbox_detached`].
+    pub fn crypto_secretbox_detached(
+        ciphertext: &mut [u8],
+        mac: &mut Mac,
+        message: &[u8],
+        nonce: &Nonce,
+        key: &Key,
+    ) {
+        raw::crypto_secretbox_detached(
+            ciphertext,
+            mac.as_mut_array(),
+            message,
+            nonce.as_array(),
+            key.as_array(),
+        )
+    }
+
+    /// Typed version of [`raw::crypto_secretbox_open_detached`].
+    pub fn crypto_secretbox_open_detached(
+        message: &mut [u8],
+        mac: &Mac,
+        ciphertext: &[u8],
+        nonce: &Nonce,
+        key: &Key,
+    ) -> Result<(), Error> {
+        raw::crypto_secretbox_open_detached(
+            message,
+            mac.as_array(),
+            ciphertext,
+            nonce.as_array(),
+            key.as_array(),
+        )
+    }
+
+    /// Typed version of [`raw::crypto_secretbox_easy`].
+    pub fn crypto_secretbox_easy(
+        ciphertext: &mut [u8],
+        message: &[u8],
+        nonce: &Nonce,
+        key: &Key,
+    ) -> Result<(), Error> {
+        raw::crypto_secretbox_easy(ciphertext, message, nonce.as_array(), key.as_array())
+    }
+
+    /// Typed version of [`raw::crypto_secretbox_open_easy`].
+    pub fn crypto_secretbox_open_easy(
+        message: &mut [u8],
+        ciphertext: &[u8],
+        nonce: &Nonce,
+        key: &Key,
+    ) -> Result<(), Error> {
+        raw::crypto_secretbox_open_easy(message, ciphertext, nonce.as_array(), key.as_array())
+    }
+
+    /// Typed version of [`raw::crypto_secretbox_easy_inplace`].
+    pub fn crypto_secretbox_easy_inplace(
+        data: &mut [u8],
+        nonce: &Nonce,
+        key: &Key,
+    ) -> Result<(), Error> {
+        raw::crypto_secretbox_easy_inplace(data, nonce.as_array(), key.as_array())
+    }
+
+    /// Typed version of [`raw::crypto_secretbox_open_easy_inplace`].
+    pub fn crypto_secretbox_open_easy_inplace(
+        ciphertext: &mut [u8],
+        nonce: &Nonce,
+        key: &Key,
+    ) -> Result<(), Error> {
+        raw::crypto_secretbox_open_easy_inplace(ciphertext, nonce.as_array(), key.as_array())
+    }
+
+    /// Typed version of [`raw::crypto_secretbox`].
+    pub fn crypto_secretbox(
+        ciphertext: &mut [u8],
+        message: &[u8],
+        nonce: &Nonce,
+        key: &Key,
+    ) -> Result<(), Error> {
+        raw::crypto_secretbox(ciphertext, message, nonce.as_array(), key.as_array())
+    }
+
+    /// Typed version of [`raw::crypto_secretbox_open`].
+    pub fn crypto_secretbox_open(
+        message: &mut [u8],
+        ciphertext: &[u8],
+        nonce: &Nonce,
+        key: &Key,
+    ) -> Result<(), Error> {
+        raw::crypto_secretbox_open(message, ciphertext, nonce.as_array(), key.as_array())
+    }
+}
+
+pub mod crypto_box {
+    //! Strongly-typed version of [`crypto_box`](crate::classic::crypto_box).
+    //!
+    //! Public and secret keys are the same as those of the raw-array version.
+    use super::Error;
+    use crate::classic::crypto_box as raw;
+    pub use crate::classic::crypto_box::{
+        crypto_box_keypair, crypto_box_seal, crypto_box_seal_open, PublicKey, SecretKey,
+    };
+    pub use crate::constants::{
+        CRYPTO_BOX_BEFORENMBYTES, CRYPTO_BOX_MACBYTES, CRYPTO_BOX_NONCEBYTES,
+        CRYPTO_BOX_PUBLICKEYBYTES, CRYPTO_BOX_SEALBYTES, CRYPTO_BOX_SECRETKEYBYTES,
+        CRYPTO_BOX_SEEDBYTES,
+    };
+
+    /// Shared secret key, for use with the precalculation interface.
+    pub type Key = super::Key<CRYPTO_BOX_BEFORENMBYTES>;
+    /// Nonce for crypto boxes.
+    pub type Nonce = super::Nonce<CRYPTO_BOX_NONCEBYTES>;
+    /// Crypto box message authentication code.
+    pub type Mac = super::Mac<CRYPTO_BOX_MACBYTES>;
+    /// Seed for deterministic keypair generation.
+    pub type Seed = super::Seed<CRYPTO_BOX_SEEDBYTES>;
+
+    /// Typed version of [`raw::crypto_box_seed_keypair`].
+    pub fn crypto_box_seed_keypair(seed: &Seed) -> (PublicKey, SecretKey) {
+        raw::crypto_box_seed_keypair(seed.as_array())
+    }
+
+    /// Typed version of [`raw::crypto_box_beforenm`].
+    pub fn crypto_box_beforenm(public_key: &PublicKey, secret_key: &SecretKey) -> Key {
+        Key::from(raw::crypto_box_beforenm(public_key, secret_key))
+    }
+
+    /// Typed version of [`raw::crypto_box_detached_afternm`].
+    pub fn crypto_box_detached_afternm(
+        ciphertext: &mut [u8],
+        mac: &mut Mac,
+        message: &[u8],
+        nonce: &Nonce,
+        key: &Key,
+    ) {
+        raw::crypto_box_detached_afternm(
+            ciphertext,
+            mac.as_mut_array(),
+            message,
+            nonce.as_array(),
+            key.as_array(),
+        )
+    }
+
+    /// Typed version of [`raw::crypto_box_detached_afternm_inplace`].
+    pub fn crypto_box_detached_afternm_inplace(
+        ciphertext: &mut [u8],
+        mac: &mut Mac,
+        nonce: &Nonce,
+        key: &Key,
+    ) {
+        raw::crypto_box_detached_afternm_inplace(
+            ciphertext,
+            mac.as_mut_array(),
+            nonce.as_array(),
+            key.as_array(),
+        )
+    }
+
+    /// Typed version of [`raw::crypto_box_detached`].
+    pub fn crypto_box_detached(
+        ciphertext: &mut [u8],
+        mac: &mut Mac,
+        message: &[u8],
+        nonce: &Nonce,
+        recipient_public_key: &PublicKey,
+        sender_secret_key: &SecretKey,
+    ) {
+        raw::crypto_box_detached(
+            ciphertext,
+            mac.as_mut_array(),
+            message,
+            nonce.as_array(),
+            recipient_public_key,
+            sender_secret_key,
+        )
+    }
+
+    /// Typed version of [`raw::crypto_box_detached_inplace`].
+    pub fn crypto_box_detached_inplace(
+        message: &mut [u8],
+        mac: &mut Mac,
+        nonce: &Nonce,
+        recipient_public_key: &PublicKey,
+        sender_secret_key: &SecretKey,
+    ) -> Result<(), Error> {
+        raw::crypto_box_detached_inplace(
+            message,
+            mac.as_mut_array(),
+            nonce.as_array(),
+            recipient_public_key,
+            sender_secret_key,
+        )
+    }
+
+    /// Typed version of [`raw::crypto_box_easy`].
+    pub fn crypto_box_easy(
+        ciphertext: &mut [u8],
+        message: &[u8],
+        nonce: &Nonce,
+        recipient_public_key: &PublicKey,
+        sender_secret_key: &SecretKey,
+    ) -> Result<(), Error> {
+        raw::crypto_box_easy(
+            ciphertext,
+            message,
+            nonce.as_array(),
+            recipient_public_key,
+            sender_secret_key,
+        )
+    }
+
+    /// Typed version of [`raw::crypto_box_easy_inplace`].
+    pub fn crypto_box_easy_inplace(
+        data: &mut [u8],
+        nonce: &Nonce,
+        recipient_public_key: &PublicKey,
+        sender_secret_key: &SecretKey,
+    ) -> Result<(), Error> {
+        raw::crypto_box_easy_inplace(
+            data,
+            nonce.as_array(),
+            recipient_public_key,
+            sender_secret_key,
+        )
+    }
+
+    /// Typed version of [`raw::crypto_box_open_detached_afternm`].
+    pub fn crypto_box_open_detached_afternm(
+        message: &mut [u8],
+        mac: &Mac,
+        ciphertext: &[u8],
+        nonce: &Nonce,
+        key: &Key,
+    ) -> Result<(), Error> {
+        raw::crypto_box_open_detached_afternm(
+            message,
+            mac.as_array(),
+            ciphertext,
+            nonce.as_array(),
+            key.as_array(),
+        )
+    }
+
+    /// Typed version of [`raw::crypto_box_open_detached_afternm_inplace`].
+    pub fn crypto_box_open_detached_afternm_inplace(
+        data: &mut [u8],
+        mac: &Mac,
+        nonce: &Nonce,
+        key: &Key,
+    ) -> Result<(), Error> {
+        raw::crypto_box_open_detached_afternm_inplace(
+            data,
+            mac.as_array(),
+            nonce.as_array(),
+            key.as_array(),
+        )
+    }
+
+    /// Typed version of [`raw::crypto_box_open_detached`].
+    pub fn crypto_box_open_detached(
+        message: &mut [u8],
+        mac: &Mac,
+        ciphertext: &[u8],
+        nonce: &Nonce,
+        recipient_public_key: &PublicKey,
+        sender_secret_key: &SecretKey,
+    ) -> Result<(), Error> {
+        raw::crypto_box_open_detached(
+            message,
+            mac.as_array(),
+            ciphertext,
+            nonce.as_array(),
+            recipient_public_key,
+            sender_secret_key,
+        )
+    }
+
+    /// Typed version of [`raw::crypto_box_open_detached_inplace`].
+    pub fn crypto_box_open_detached_inplace(
+        data: &mut [u8],
+        mac: &Mac,
+        nonce: &Nonce,
+        recipient_public_key: &PublicKey,
+        sender_secret_key: &SecretKey,
+    ) -> Result<(), Error> {
+        raw::crypto_box_open_detached_inplace(
+            data,
+            mac.as_array(),
+            nonce.as_array(),
+            recipient_public_key,
+            sender_secret_key,
+        )
+    }
+
+    /// Typed version of [`raw::crypto_box_open_easy`].
+    pub fn crypto_box_open_easy(
+        message: &mut [u8],
+        ciphertext: &[u8],
+        nonce: &Nonce,
+        sender_public_key: &PublicKey,
+        recipient_secret_key: &SecretKey,
+    ) -> Result<(), Error> {
+        raw::crypto_box_open_easy(
+            message,
+            ciphertext,
+            nonce.as_array(),
+            sender_public_key,
+            recipient_secret_key,
+        )
+    }
+
+    /// Typed version of [`raw::crypto_box_open_easy_inplace`].
+    pub fn crypto_box_open_easy_inplace(
+        data: &mut [u8],
+        nonce: &Nonce,
+        sender_public_key: &PublicKey,
+        recipient_secret_key: &SecretKey,
+    ) -> Result<(), Error> {
+        raw::crypto_box_open_easy_inplace(
+            data,
+            nonce.as_array(),
+            sender_public_key,
+            recipient_secret_key,
+        )
+    }
+}
+
+pub mod crypto_secretstream_xchacha20poly1305 {
+    //! Strongly-typed version of
+    //! [`crypto_secretstream_xchacha20poly1305`](crate::classic::crypto_secretstream_xchacha20poly1305).
+    use crate::classic::crypto_secretstream_xchacha20poly1305 as raw;
+    pub use crate::classic::crypto_secretstream_xchacha20poly1305::{
+        crypto_secretstream_xchacha20poly1305_pull, crypto_secretstream_xchacha20poly1305_push,
+        crypto_secretstream_xchacha20poly1305_rekey, State,
+    };
+    pub use crate::constants::{
+        CRYPTO_SECRETSTREAM_XCHACHA20POLY1305_ABYTES,
+        CRYPTO_SECRETSTREAM_XCHACHA20POLY1305_HEADERBYTES,
+        CRYPTO_SECRETSTREAM_XCHACHA20POLY1305_KEYBYTES,
+        CRYPTO_SECRETSTREAM_XCHACHA20POLY1305_TAG_FINAL,
+        CRYPTO_SECRETSTREAM_XCHACHA20POLY1305_TAG_MESSAGE,
+        CRYPTO_SECRETSTREAM_XCHACHA20POLY1305_TAG_PUSH,
+        CRYPTO_SECRETSTREAM_XCHACHA20POLY1305_TAG_REKEY,
+    };
+
+    /// Secret stream secret key.
+    pub type Key = super::Key<CRYPTO_SECRETSTREAM_XCHACHA20POLY1305_KEYBYTES>;
+    /// Secret stream header.
+    pub type Header = super::Header<CRYPTO_SECRETSTREAM_XCHACHA20POLY1305_HEADERBYTES>;
+
+    /// Typed version of [`raw::crypto_secretstream_xchacha20poly1305_keygen`].
+    pub fn crypto_secretstream_xchacha20poly1305_keygen() -> Key {
+        Key::gen()
+    }
+
+    /// Typed version of
+    /// [`raw::crypto_secretstream_xchacha20poly1305_init_push`].
+    pub fn crypto_secretstream_xchacha20poly1305_init_push(
+        state: &mut State,
+        header: &mut Header,
+        key: &Key,
+    ) {
+        raw::crypto_secretstream_xchacha20poly1305_init_push(
+            state,
+            header.as_mut_array(),
+            key.as_array(),
+        )
+    }
+
+    /// Typed version of
+    /// [`raw::crypto_secretstream_xchacha20poly1305_init_pull`].
+    pub fn crypto_secretstream_xchacha20poly1305_init_pull(
+        state: &mut State,
+        header: &Header,
+        key: &Key,
+    ) {
+        raw::crypto_secretstream_xchacha20poly1305_init_pull(
+            state,
+            header.as_array(),
+            key.as_array(),
+        )
+    }
+}
+
+pub mod crypto_stream {
+    //! Strongly-typed version of
+    //! [`crypto_stream`](crate::classic::crypto_stream).
+    use super::Error;
+    use crate::classic::crypto_stream as raw;
+    pub use crate::constants::{
+        CRYPTO_STREAM_KEYBYTES, CRYPTO_STREAM_NONCEBYTES, CRYPTO_STREAM_SALSA20_KEYBYTES,
+        CRYPTO_STREAM_SALSA20_NONCEBYTES, CRYPTO_STREAM_XSALSA20_KEYBYTES,
+        CRYPTO_STREAM_XSALSA20_NONCEBYTES,
+    };
+
+    /// Salsa20 secret key.
+    pub type Salsa20Key = super::Key<CRYPTO_STREAM_SALSA20_KEYBYTES>;
+    /// Salsa20 nonce.
+    pub type Salsa20Nonce = super::Nonce<CRYPTO_STREAM_SALSA20_NONCEBYTES>;
+    /// XSalsa20 secret key.
+    pub type XSalsa20Key = super::Key<CRYPTO_STREAM_XSALSA20_KEYBYTES>;
+    /// XSalsa20 nonce.
+    pub type XSalsa20Nonce = super::Nonce<CRYPTO_STREAM_XSALSA20_NONCEBYTES>;
+    /// Secret key for the default stream cipher (XSalsa20).
+    pub type Key = XSalsa20Key;
+    /// Nonce for the default stream cipher (XSalsa20).
+    pub type Nonce = XSalsa20Nonce;
+
+    /// Typed version of [`raw::crypto_stream_salsa20_keygen`].
+    pub fn crypto_stream_salsa20_keygen() -> Salsa20Key {
+        Salsa20Key::gen()
+    }
+
+    /// Typed version of [`raw::crypto_stream_salsa20`].
+    pub fn crypto_stream_salsa20(output: &mut [u8], nonce: &Salsa20Nonce, key: &Salsa20Key) {
+        raw::crypto_stream_salsa20(output, nonce.as_array(), key.as_array())
+    }
+
+    /// Typed version of [`raw::crypto_stream_salsa20_xor`].
+    pub fn crypto_stream_salsa20_xor(
+        output: &mut [u8],
+        input: &[u8],
+        nonce: &Salsa20Nonce,
+        key: &Salsa20Key,
+    ) -> Result<(), Error> {
+        raw::crypto_stream_salsa20_xor(output, input, nonce.as_array(), key.as_array())
+    }
+
+    /// Typed version of [`raw::crypto_stream_salsa20_xor_ic`].
+    pub fn crypto_stream_salsa20_xor_ic(
+        output: &mut [u8],
+        input: &[u8],
+        nonce: &Salsa20Nonce,
+        ic: u64,
+        key: &Salsa20Key,
+    ) -> Result<(), Error> {
+        raw::crypto_stream_salsa20_xor_ic(output, input, nonce.as_array(), ic, key.as_array())
+    }
+
+    /// Typed version of [`raw::crypto_stream_xsalsa20_keygen`].
+    pub fn crypto_stream_xsalsa20_keygen() -> XSalsa20Key {
+        XSalsa20Key::gen()
+    }
+
+    /// Typed version of [`raw::crypto_stream_xsalsa20`].
+    pub fn crypto_stream_xsalsa20(output: &mut [u8], nonce: &XSalsa20Nonce, key: &XSalsa20Key) {
+        raw::crypto_stream_xsalsa20(output, nonce.as_array(), key.as_array())
+    }
+
+    /// Typed version of [`raw::crypto_stream_xsalsa20_xor`].
+    pub fn crypto_stream_xsalsa20_xor(
+        output: &mut [u8],
+        input: &[u8],
+        nonce: &XSalsa20Nonce,
+        key: &XSalsa20Key,
+    ) -> Result<(), Error> {
+        raw::crypto_stream_xsalsa20_xor(output, input, nonce.as_array(), key.as_array())
+    }
+
+    /// Typed version of [`raw::crypto_stream_xsalsa20_xor_ic`].
+    pub fn crypto_stream_xsalsa20_xor_ic(
+        output: &mut [u8],
+        input: &[u8],
+        nonce: &XSalsa20Nonce,
+        ic: u64,
+        key: &XSalsa20Key,
+    ) -> Result<(), Error> {
+        raw::crypto_stream_xsalsa20_xor_ic(output, input, nonce.as_array(), ic, key.as_array())
+    }
+
+    /// Typed version of [`raw::crypto_stream_keygen`].
+    pub fn crypto_stream_keygen() -> Key {
+        Key::gen()
+    }
+
+    /// Typed version of [`raw::crypto_stream`].
+    pub fn crypto_stream(output: &mut [u8], nonce: &Nonce, key: &Key) {
+        raw::crypto_stream(output, nonce.as_array(), key.as_array())
+    }
+
+    /// Typed version of [`raw::crypto_stream_xor`].
+    pub fn crypto_stream_xor(
+        output: &mut [u8],
+        input: &[u8],
+        nonce: &Nonce,
+        key: &Key,
+    ) -> Result<(), Error> {
+        raw::crypto_stream_xor(output, input, nonce.as_array(), key.as_array())
+    }
+}
+
+pub mod crypto_shorthash {
+    //! Strongly-typed version of
+    //! [`crypto_shorthash`](crate::classic::crypto_shorthash).
+    use crate::classic::crypto_shorthash as raw;
+    pub use crate::classic::crypto_shorthash::Hash;
+    pub use crate::constants::{CRYPTO_SHORTHASH_BYTES, CRYPTO_SHORTHASH_KEYBYTES};
+
+    /// Short-input hash secret key.
+    pub type Key = super::Key<CRYPTO_SHORTHASH_KEYBYTES>;
+
+    /// Typed version of [`raw::crypto_shorthash_keygen`].
+    pub fn crypto_shorthash_keygen() -> Key {
+        Key::gen()
+    }
+
+    /// Typed version of [`raw::crypto_shorthash`].
+    pub fn crypto_shorthash(output: &mut Hash, input: &[u8], key: &Key) {
+        raw::crypto_shorthash(output, input, key.as_array())
+    }
+}
+
+pub mod crypto_kdf {
+    //! Strongly-typed version of [`crypto_kdf`](crate::classic::crypto_kdf).
+    use super::Error;
+    use crate::classic::crypto_kdf as raw;
+    pub use crate::classic::crypto_kdf::Context;
+    pub use crate::constants::{CRYPTO_KDF_CONTEXTBYTES, CRYPTO_KDF_KEYBYTES};
+
+    /// Key derivation main key.
+    pub type Key = super::Key<CRYPTO_KDF_KEYBYTES>;
+
+    /// Typed version of [`raw::crypto_kdf_keygen`].
+    pub fn crypto_kdf_keygen() -> Key {
+        Key::gen()
+    }
+
+    /// Typed version of [`raw::crypto_kdf_derive_from_key`].
+    pub fn crypto_kdf_derive_from_key(
+        subkey: &mut [u8],
+        subkey_id: u64,
+        context: &Context,
+        main_key: &Key,
+    ) -> Result<(), Error> {
+        raw::crypto_kdf_derive_from_key(subkey, subkey_id, context, main_key.as_array())
+    }
+}
+
+pub mod crypto_kx {
+    //! Strongly-typed version of [`crypto_kx`](crate::classic::crypto_kx).
+    //!
+    //! Public, secret, and session keys are the same as those of the raw-array
+    //! version.
+    use super::Error;
+    use crate::classic::crypto_kx as raw;
+    pub use crate::classic::crypto_kx::{
+        crypto_kx_client_session_keys, crypto_kx_keypair, crypto_kx_server_session_keys,
+        PublicKey, SecretKey, SessionKey,
+    };
+    pub use crate::constants::CRYPTO_KX_SEEDBYTES;
+
+    /// Seed for deterministic keypair generation.
+    pub type Seed = super::Seed<CRYPTO_KX_SEEDBYTES>;
+
+    /// Typed version of [`raw::crypto_kx_seed_keypair`].
+    pub fn crypto_kx_seed_keypair(seed: &Seed) -> Result<(PublicKey, SecretKey), Error> {
+        raw::crypto_kx_seed_keypair(seed.as_array())
+    }
+}
+
+pub mod crypto_sign {
+    //! Strongly-typed version of [`crypto_sign`](crate::classic::crypto_sign).
+    //!
+    //! Public and secret keys, and signatures, are the same as those of the
+    //! raw-array version.
+    use crate::classic::crypto_sign as raw;
+    pub use crate::classic::crypto_sign::*;
+    pub use crate::constants::CRYPTO_SIGN_SEEDBYTES;
+
+    /// Seed for deterministic keypair generation.
+    pub type Seed = super::Seed<CRYPTO_SIGN_SEEDBYTES>;
+
+    /// Typed version of [`raw::crypto_sign_seed_keypair`].
+    pub fn crypto_sign_seed_keypair(seed: &Seed) -> (PublicKey, SecretKey) {
+        raw::crypto_sign_seed_keypair(seed.as_array())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_secretbox_matches_raw() {
+        use crate::classic::crypto_secretbox as raw;
+
+        let key = crypto_secretbox::crypto_secretbox_keygen();
+        let nonce = crypto_secretbox::Nonce::gen();
+        let message = b"hello";
+
+        let mut typed_ciphertext =
+            vec![0u8; message.len() + crypto_secretbox::CRYPTO_SECRETBOX_MACBYTES];
+        crypto_secretbox::crypto_secretbox_easy(&mut typed_ciphertext, message, &nonce, &key)
+            .expect("encrypt failed");
+
+        let mut raw_ciphertext = vec![0u8; typed_ciphertext.len()];
+        raw::crypto_secretbox_easy(
+            &mut raw_ciphertext,
+            message,
+            nonce.as_array(),
+            key.as_array(),
+        )
+        .expect("encrypt failed");
+        assert_eq!(typed_ciphertext, raw_ciphertext);
+
+        let mut decrypted = vec![0u8; message.len()];
+        crypto_secretbox::crypto_secretbox_open_easy(
+            &mut decrypted,
+            &typed_ciphertext,
+            &nonce,
+            &key,
+        )
+        .expect("decrypt failed");
+        assert_eq!(decrypted, message);
+    }
+
+    #[test]
+    fn test_newtype_conversions() {
+        let raw = [7u8; 32];
+        let key: Key<32> = raw.into();
+        assert_eq!(key.as_array(), &raw);
+        assert_eq!(Key::<32>::try_from(&raw[..]).expect("try_from failed"), key);
+        assert!(Nonce::<24>::try_from(&raw[..]).is_err());
+
+        // Newtypes work with the Rustaceous API too
+        use crate::dryocsecretbox::DryocSecretBox;
+        let nonce = Nonce::<24>::gen();
+        let dryocsecretbox = DryocSecretBox::encrypt_to_vecbox(b"hello", &nonce, &key);
+        let decrypted = dryocsecretbox
+            .decrypt_to_vec(&nonce, &key)
+            .expect("decrypt failed");
+        assert_eq!(decrypted, b"hello");
+    }
+}
@@ -0,0 +1,489 @@
+//! # Transport codecs
+//!
+//! [`StreamCodec`] encrypts a sequence of messages with a
+//! [`DryocStream`](crate::dryocstream::DryocStream), and places each
+//! ciphertext in a frame suitable for the underlying transport. The framing is
+//! chosen with the [`Framing`] trait, so the same session logic can be used
+//! across transports:
+//!
+//! * [`LengthPrefixed`] frames each ciphertext with a 32-bit big-endian length,
+//!   for byte-oriented transports such as TCP or Unix sockets, where frames can
+//!   arrive split or coalesced
+//! * [`Discrete`] uses the ciphertext as-is, for message-oriented transports
+//!   such as Kafka or NATS, where each payload already arrives whole
+//!
+//! The stream header is sent as a prefix of the first frame, so no separate
+//! handshake message is needed. Each side of a codec has its own stream, so
+//! one codec can be used to encode outgoing messages and decode incoming ones,
+//! provided each direction uses a different key.
+//!
+//! Messages are authenticated in order, and reordered, dropped, or replayed
+//! frames fail to decode. To detect truncation, end the session with
+//! [`StreamCodec::encode_final_frame`], and check
+//! [`StreamCodec::is_finished`] on the receiving side once the transport
+//! closes.
+//!
+//! With the `tokio-codec` feature, [`StreamCodec`] also implements
+//! `tokio_util::codec::Encoder` and `tokio_util::codec::Decoder`, so it
+//! can be used with `Framed`, `FramedRead`, and `FramedWrite`. Partially
+//! received frames are left in the read buffer until the rest arrives, and
+//! frames larger than [`LengthPrefixed::max_frame_size`] are rejected before
+//! they're buffered. Use [`LengthPrefixed`] framing with tokio's byte streams;
+//! [`Discrete`] framing expects each buffer to hold exactly one frame.
+//!
+//! ## Example
+//!
+//! ```
+//! use dryoc::codec::{Codec, LengthPrefixed, StreamCodec};
+//! use dryoc::dryocstream::Key;
+//! use dryoc::types::*;
+//!
+//! let key = Key::gen();
+//!
+//! let mut sender = StreamCodec::new(key.clone(), LengthPrefixed::default());
+//! let mut receiver = StreamCodec::new(key, LengthPrefixed::default());
+//!
+//! // Encode a couple of messages into a byte buffer, as if writing to a socket
+//! let mut wire = Vec::new();
+//! sender
+//!     .encode_frame(b"hello", &mut wire)
+//!     .expect("encode failed");
+//! sender
+//!     .encode_final_frame(b"goodbye", &mut wire)
+//!     .expect("encode failed");
+//!
+//! // Decode the frames back out of the buffer
+//! let (message, consumed) = receiver
+//!     .decode_frame(&wire)
+//!     .expect("decode failed")
+//!     .expect("incomplete frame");
+//! assert_eq!(message, b"hello");
+//! wire.drain(..consumed);
+//!
+//! let (message, consumed) = receiver
+//!     .decode_frame(&wire)
+//!     .expect("decode failed")
+//!     .expect("incomplete frame");
+//! assert_eq!(message, b"goodbye");
+//! wire.drain(..consumed);
+//!
+//! assert!(wire.is_empty());
+//! assert!(receiver.is_finished());
+//! ```
+
+use crate::constants::{
+    CRYPTO_SECRETSTREAM_XCHACHA20POLY1305_ABYTES, CRYPTO_SECRETSTREAM_XCHACHA20POLY1305_HEADERBYTES,
+};
+use crate::dryocstream::{DryocStream, Header, Key, Pull, Push, Tag};
+use crate::error::Error;
+use crate::types::*;
+
+/// Length in bytes of the frame length prefix used by [`LengthPrefixed`].
+pub const LENGTH_PREFIX_BYTES: usize = 4;
+/// Default maximum frame payload size for [`LengthPrefixed`], in bytes.
+pub const DEFAULT_MAX_FRAME_SIZE: usize = 16 * 1024 * 1024;
+
+/// Places payloads into frames, and extracts them again, for a particular
+/// transport.
+pub trait Framing {
+    /// Appends a frame containing `payload` to `output`.
+    fn write_frame(&self, payload: &[u8], output: &mut Vec<u8>) -> Result<(), Error>;
+
+    /// Reads a frame from the start of `input`, returning the payload and the
+    /// number of bytes of `input` consumed, or `None` if `input` doesn't
+    /// contain a complete frame yet.
+    fn read_frame<'a>(&self, input: &'a [u8]) -> Result<Option<(&'a [u8], usize)>, Error>;
+}
+
+/// Framing for byte-oriented transports, which prefixes each payload with its
+/// length as a 32-bit big-endian integer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LengthPrefixed {
+    max_frame_size: usize,
+}
+
+impl LengthPrefixed {
+    /// Returns a new length-prefixed framing, with a maximum frame payload
+    /// size of [`DEFAULT_MAX_FRAME_SIZE`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the maximum frame payload size, in bytes. Frames larger than this
+    /// are rejected when reading or writing, which bounds how much a peer can
+    /// make the receiver buffer.
+    pub fn with_max_frame_size(self, max_frame_size: usize) -> Self {
+        Self {
+            max_frame_size: max_frame_size.min(u32::MAX as usize),
+        }
+    }
+
+    /// Returns the maximum frame payload size, in bytes.
+    pub fn max_frame_size(&self) -> usize {
+        self.max_frame_size
+    }
+}
+
+impl Default for LengthPrefixed {
+    fn default() -> Self {
+        Self {
+            max_frame_size: DEFAULT_MAX_FRAME_SIZE,
+        }
+    }
+}
+
+impl Framing for LengthPrefixed {
+    fn write_frame(&self, payload: &[u8], output: &mut Vec<u8>) -> Result<(), Error> {
+        if payload.len() > self.max_frame_size {
+            return Err(dryoc_error!(format!(
+                "frame of {} bytes exceeds maximum frame size of {} bytes",
+                payload.len(),
+                self.max_frame_size
+            )));
+        }
+        output.reserve(LENGTH_PREFIX_BYTES + payload.len());
+        output.extend_from_slice(&(payload.len() as u32).to_be_bytes());
+        output.extend_from_slice(payload);
+        Ok(())
+    }
+
+    fn read_frame<'a>(&self, input: &'a [u8]) -> Result<Option<(&'a [u8], usize)>, Error> {
+        if input.len() < LENGTH_PREFIX_BYTES {
+            return Ok(None);
+        }
+        let mut prefix = [0u8; LENGTH_PREFIX_BYTES];
+        prefix.copy_from_slice(&input[..LENGTH_PREFIX_BYTES]);
+        let length = u32::from_be_bytes(prefix) as usize;
+        if length > self.max_frame_size {
+            return Err(dryoc_error!(format!(
+                "frame of {} bytes exceeds maximum frame size of {} bytes",
+                length, self.max_frame_size
+            )));
+        }
+        let end = LENGTH_PREFIX_BYTES + length;
+        if input.len() < end {
+            return Ok(None);
+        }
+        Ok(Some((&input[LENGTH_PREFIX_BYTES..end], end)))
+    }
+}
+
+/// Framing for message-oriented transports, where each transport message
+/// carries exactly one payload. The payload is used as the frame, unchanged.
+///
+/// Because the transport delimits messages, all of `input` is read as a
+/// single frame. Empty input is treated as no frame.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Discrete;
+
+impl Framing for Discrete {
+    fn write_frame(&self, payload: &[u8], output: &mut Vec<u8>) -> Result<(), Error> {
+        output.extend_from_slice(payload);
+        Ok(())
+    }
+
+    fn read_frame<'a>(&self, input: &'a [u8]) -> Result<Option<(&'a [u8], usize)>, Error> {
+        if input.is_empty() {
+            Ok(None)
+        } else {
+            Ok(Some((input, input.len())))
+        }
+    }
+}
+
+/// Encodes messages into encrypted frames, and decodes them again.
+pub trait Codec {
+    /// Encrypts `message`, and appends the resulting frame to `output`.
+    fn encode_frame(&mut self, message: &[u8], output: &mut Vec<u8>) -> Result<(), Error>;
+
+    /// Decodes and decrypts a frame from the start of `input`, returning the
+    /// message and the number of bytes of `input` consumed, or `None` if
+    /// `input` doesn't contain a complete frame yet.
+    fn decode_frame(&mut self, input: &[u8]) -> Result<Option<(Vec<u8>, usize)>, Error>;
+}
+
+/// A [`Codec`] which encrypts messages with a
+/// [`DryocStream`](crate::dryocstream::DryocStream), framed by `F`.
+///
+/// The push stream is initialized when the first frame is encoded, and the
+/// pull stream when the first frame is decoded.
+pub struct StreamCodec<F: Framing> {
+    key: Key,
+    framing: F,
+    push: Option<DryocStream<Push>>,
+    pull: Option<DryocStream<Pull>>,
+    sent_final: bool,
+    received_final: bool,
+}
+
+impl<F: Framing> StreamCodec<F> {
+    /// Returns a new codec using `key` for its streams, and `framing` for its
+    /// frames.
+    pub fn new(key: Key, framing: F) -> Self {
+        Self {
+            key,
+            framing,
+            push: None,
+            pull: None,
+            sent_final: false,
+            received_final: false,
+        }
+    }
+
+    /// Returns a reference to this codec's framing.
+    pub fn framing(&self) -> &F {
+        &self.framing
+    }
+
+    /// Encrypts `message` as the final message of the session, and appends
+    /// the resulting frame to `output`. No more frames can be encoded
+    /// afterwards.
+    pub fn encode_final_frame(
+        &mut self,
+        message: &[u8],
+        output: &mut Vec<u8>,
+    ) -> Result<(), Error> {
+        self.encode_with_tag(message, output, Tag::FINAL)?;
+        self.sent_final = true;
+        Ok(())
+    }
+
+    /// Returns true if the final frame of the session has been decoded, i.e.,
+    /// the sender ended the session with
+    /// [`StreamCodec::encode_final_frame`]. If the transport closes before
+    /// this returns true, the session may have been truncated.
+    pub fn is_finished(&self) -> bool {
+        self.received_final
+    }
+
+    fn encode_with_tag(
+        &mut self,
+        message: &[u8],
+        output: &mut Vec<u8>,
+        tag: Tag,
+    ) -> Result<(), Error> {
+        if self.sent_final {
+            return Err(dryoc_error!("session already finished"));
+        }
+        let mut payload = Vec::with_capacity(
+            CRYPTO_SECRETSTREAM_XCHACHA20POLY1305_HEADERBYTES
+                + message.len()
+                + CRYPTO_SECRETSTREAM_XCHACHA20POLY1305_ABYTES,
+        );
+        let push = match &mut self.push {
+            Some(push) => push,
+            None => {
+                let (push, header): (_, Header) = DryocStream::init_push(&self.key);
+                payload.extend_from_slice(header.as_slice());
+                self.push.insert(push)
+            }
+        };
+        let ciphertext = push.push_to_vec(&message, None, tag)?;
+        payload.extend_from_slice(&ciphertext);
+        self.framing.write_frame(&payload, output)
+    }
+}
+
+impl<F: Framing> Codec for StreamCodec<F> {
+    fn encode_frame(&mut self, message: &[u8], output: &mut Vec<u8>) -> Result<(), Error> {
+        self.encode_with_tag(message, output, Tag::MESSAGE)
+    }
+
+    fn decode_frame(&mut self, input: &[u8]) -> Result<Option<(Vec<u8>, usize)>, Error> {
+        let (mut payload, consumed) = match self.framing.read_frame(input)? {
+            Some(frame) => frame,
+            None => return Ok(None),
+        };
+        if self.received_final {
+            return Err(dryoc_error!("received frame after end of session"));
+        }
+        let pull = match &mut self.pull {
+            Some(pull) => pull,
+            None => {
+                if payload.len() < CRYPTO_SECRETSTREAM_XCHACHA20POLY1305_HEADERBYTES {
+                    return Err(dryoc_error!("first frame is too short for stream header"));
+                }
+                let (header, rest) =
+                    payload.split_at(CRYPTO_SECRETSTREAM_XCHACHA20POLY1305_HEADERBYTES);
+                let header = Header::try_from(header)?;
+                payload = rest;
+                self.pull.insert(DryocStream::init_pull(&self.key, &header))
+            }
+        };
+        if payload.len() < CRYPTO_SECRETSTREAM_XCHACHA20POLY1305_ABYTES {
+            return Err(dryoc_error!("frame is too short"));
+        }
+        let (message, tag) = pull.pull_to_vec(&payload, None)?;
+        if tag.contains(Tag::FINAL) {
+            self.received_final = true;
+        }
+        Ok(Some((message, consumed)))
+    }
+}
+
+#[cfg(feature = "tokio-codec")]
+#[cfg_attr(all(feature = "nightly", doc), doc(cfg(feature = "tokio-codec")))]
+impl<F: Framing, Input: Bytes> tokio_util::codec::Encoder<Input> for StreamCodec<F> {
+    type Error = Error;
+
+    fn encode(&mut self, item: Input, dst: &mut bytes::BytesMut) -> Result<(), Error> {
+        let mut frame = Vec::new();
+        self.encode_frame(item.as_slice(), &mut frame)?;
+        dst.extend_from_slice(&frame);
+        Ok(())
+    }
+}
+
+#[cfg(feature = "tokio-codec")]
+#[cfg_attr(all(feature = "nightly", doc), doc(cfg(feature = "tokio-codec")))]
+impl<F: Framing> tokio_util::codec::Decoder for StreamCodec<F> {
+    type Error = Error;
+    type Item = Vec<u8>;
+
+    fn decode(&mut self, src: &mut bytes::BytesMut) -> Result<Option<Vec<u8>>, Error> {
+        use bytes::Buf;
+
+        match self.decode_frame(src)? {
+            Some((message, consumed)) => {
+                src.advance(consumed);
+                Ok(Some(message))
+            }
+            None => Ok(None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn codec_pair<F: Framing + Clone>(framing: F) -> (StreamCodec<F>, StreamCodec<F>) {
+        let key = Key::gen();
+        (
+            StreamCodec::new(key.clone(), framing.clone()),
+            StreamCodec::new(key, framing),
+        )
+    }
+
+    #[test]
+    fn test_length_prefixed() {
+        let (mut sender, mut receiver) = codec_pair(LengthPrefixed::default());
+        let messages: [&[u8]; 3] = [b"first", b"", b"third message"];
+
+        let mut wire = Vec::new();
+        for message in &messages[..2] {
+            sender.encode_frame(message, &mut wire).expect("encode");
+        }
+        sender
+            .encode_final_frame(messages[2], &mut wire)
+            .expect("encode final");
+        assert!(sender.encode_frame(b"more", &mut wire).is_err());
+
+        // feed the bytes one at a time, as a socket might
+        let mut buffer = Vec::new();
+        let mut decoded = Vec::new();
+        for byte in wire {
+            buffer.push(byte);
+            if let Some((message, consumed)) = receiver.decode_frame(&buffer).expect("decode") {
+                buffer.drain(..consumed);
+                decoded.push(message);
+            }
+        }
+        assert!(buffer.is_empty());
+        assert_eq!(decoded, messages);
+        assert!(receiver.is_finished());
+    }
+
+    #[test]
+    fn test_length_prefixed_max_frame_size() {
+        let framing = LengthPrefixed::new().with_max_frame_size(64);
+        let (mut sender, mut receiver) = codec_pair(framing);
+
+        let mut wire = Vec::new();
+        assert!(sender.encode_frame(&[0u8; 64], &mut wire).is_err());
+        assert!(wire.is_empty());
+
+        let oversized = (65u32).to_be_bytes();
+        assert!(receiver.decode_frame(&oversized).is_err());
+    }
+
+    #[test]
+    fn test_discrete() {
+        let (mut sender, mut receiver) = codec_pair(Discrete);
+
+        let mut payloads = Vec::new();
+        for i in 0..3u8 {
+            let mut payload = Vec::new();
+            sender.encode_frame(&[i; 10], &mut payload).expect("encode");
+            payloads.push(payload);
+        }
+
+        assert!(receiver.decode_frame(&[]).expect("decode").is_none());
+        for (i, payload) in payloads.iter().enumerate() {
+            let (message, consumed) = receiver
+                .decode_frame(payload)
+                .expect("decode")
+                .expect("frame");
+            assert_eq!(consumed, payload.len());
+            assert_eq!(message, vec![i as u8; 10]);
+        }
+        assert!(!receiver.is_finished());
+    }
+
+    #[test]
+    fn test_tampered_and_reordered() {
+        let (mut sender, _) = codec_pair(Discrete);
+
+        let mut first = Vec::new();
+        let mut second = Vec::new();
+        sender.encode_frame(b"one", &mut first).expect("encode");
+        sender.encode_frame(b"two", &mut second).expect("encode");
+
+        let mut receiver = StreamCodec::new(sender.key.clone(), Discrete);
+        let mut tampered = first.clone();
+        let last = tampered.len() - 1;
+        tampered[last] ^= 1;
+        assert!(receiver.decode_frame(&tampered).is_err());
+
+        let mut receiver = StreamCodec::new(sender.key.clone(), Discrete);
+        receiver.decode_frame(&first).expect("decode");
+        assert!(receiver.decode_frame(&first).is_err());
+
+        let mut receiver = StreamCodec::new(sender.key.clone(), Discrete);
+        assert!(receiver.decode_frame(&first[..10]).is_err());
+    }
+
+    #[cfg(feature = "tokio-codec")]
+    #[test]
+    fn test_tokio_codec() {
+        use bytes::BytesMut;
+        use tokio_util::codec::{Decoder, Encoder};
+
+        let (mut sender, mut receiver) =
+            codec_pair(LengthPrefixed::new().with_max_frame_size(1024));
+
+        let mut wire = BytesMut::new();
+        sender.encode(b"first".to_vec(), &mut wire).expect("encode");
+        sender.encode(&b"second"[..], &mut wire).expect("encode");
+        assert!(sender.encode(vec![0u8; 2048], &mut wire).is_err());
+
+        // deliver the frames split at an arbitrary point
+        let mut src = BytesMut::new();
+        let split = wire.len() - 7;
+        src.extend_from_slice(&wire[..split]);
+        assert_eq!(
+            receiver.decode(&mut src).expect("decode"),
+            Some(b"first".to_vec())
+        );
+        assert_eq!(receiver.decode(&mut src).expect("decode"), None);
+        src.extend_from_slice(&wire[split..]);
+        assert_eq!(
+            receiver.decode(&mut src).expect("decode"),
+            Some(b"second".to_vec())
+        );
+        assert!(src.is_empty());
+
+        let mut oversized = BytesMut::from(&2048u32.to_be_bytes()[..]);
+        assert!(receiver.decode(&mut oversized).is_err());
+    }
+}
@@ -0,0 +1,340 @@
+//! # Database column encryption
+//!
+//! [`ColumnCipher`] encrypts and decrypts individual field values for storage
+//! in a database, with a separate key for each tenant. Keys are looked up by
+//! tenant ID with a [`TenantKeyResolver`], which can be a [`HashMap`], a
+//! closure, or your own type backed by a key management service.
+//!
+//! Each value is encrypted with XChaCha20-Poly1305 and a random nonce, and is
+//! bound to where it's stored with a [`ColumnBinding`]: the table, column,
+//! and tenant ID (and optionally the row ID) are authenticated as associated
+//! data. A value copied into a different column, row, or tenant fails to
+//! decrypt, even where the same key is used, which protects against an
+//! attacker with write access to the database swapping ciphertexts around.
+//!
+//! Encrypted values are stored as a version byte, followed by the 24 byte
+//! nonce, and the ciphertext with its 16 byte tag, adding
+//! [`COLUMN_OVERHEADBYTES`] to each value. With the `base64` feature,
+//! [`ColumnCipher::encrypt_to_base64`] and
+//! [`ColumnCipher::decrypt_from_base64`] encode values as text, for text
+//! columns.
+//!
+//! ## Example
+//!
+//! ```
+//! use std::collections::HashMap;
+//!
+//! use dryoc::column_crypto::*;
+//!
+//! let mut keys = HashMap::new();
+//! keys.insert("tenant-a".to_string(), Key::gen());
+//! keys.insert("tenant-b".to_string(), Key::gen());
+//!
+//! let cipher = ColumnCipher::new(keys);
+//!
+//! let binding = ColumnBinding::new("users", "email", "tenant-a").with_row_id(b"42");
+//! let value = cipher
+//!     .encrypt(&binding, b"alice@example.com")
+//!     .expect("encrypt failed");
+//!
+//! let plaintext = cipher.decrypt(&binding, &value).expect("decrypt failed");
+//! assert_eq!(plaintext, b"alice@example.com");
+//!
+//! // The value can't be moved to another column
+//! let other = ColumnBinding::new("users", "phone", "tenant-a").with_row_id(b"42");
+//! assert!(cipher.decrypt(&other, &value).is_err());
+//! ```
+
+use std::collections::HashMap;
+
+use crate::aead::{xchacha20poly1305_ietf_decrypt, xchacha20poly1305_ietf_encrypt};
+use crate::constants::{
+    CRYPTO_AEAD_XCHACHA20POLY1305_IETF_ABYTES, CRYPTO_AEAD_XCHACHA20POLY1305_IETF_KEYBYTES,
+    CRYPTO_AEAD_XCHACHA20POLY1305_IETF_NPUBBYTES,
+};
+use crate::error::Error;
+use crate::rng::copy_randombytes;
+pub use crate::types::*;
+
+/// Version byte which prefixes encrypted column values.
+pub const COLUMN_VERSION: u8 = 1;
+/// Number of bytes added to each value when it's encrypted.
+pub const COLUMN_OVERHEADBYTES: usize =
+    1 + CRYPTO_AEAD_XCHACHA20POLY1305_IETF_NPUBBYTES + CRYPTO_AEAD_XCHACHA20POLY1305_IETF_ABYTES;
+
+/// Domain separation prefix for the associated data of column values.
+const COLUMN_AAD_PREFIX: &[u8] = b"dryoc column";
+
+/// Stack-allocated tenant key type alias.
+pub type Key = StackByteArray<CRYPTO_AEAD_XCHACHA20POLY1305_IETF_KEYBYTES>;
+/// Stack-allocated nonce type alias.
+pub type Nonce = StackByteArray<CRYPTO_AEAD_XCHACHA20POLY1305_IETF_NPUBBYTES>;
+
+/// Resolves tenant IDs to tenant keys.
+pub trait TenantKeyResolver {
+    /// Returns the key for `tenant_id`, or an error if there's no key for the
+    /// tenant (or it can't be fetched).
+    fn resolve(&self, tenant_id: &str) -> Result<Key, Error>;
+}
+
+impl TenantKeyResolver for HashMap<String, Key> {
+    fn resolve(&self, tenant_id: &str) -> Result<Key, Error> {
+        self.get(tenant_id)
+            .cloned()
+            .ok_or_else(|| dryoc_error!(format!("no key for tenant {}", tenant_id)))
+    }
+}
+
+impl<F: Fn(&str) -> Result<Key, Error>> TenantKeyResolver for F {
+    fn resolve(&self, tenant_id: &str) -> Result<Key, Error> {
+        self(tenant_id)
+    }
+}
+
+/// Identifies where a value is stored, which the encrypted value is bound to.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ColumnBinding<'a> {
+    table: &'a str,
+    column: &'a str,
+    tenant_id: &'a str,
+    row_id: Option<&'a [u8]>,
+}
+
+impl<'a> ColumnBinding<'a> {
+    /// Returns a new binding for `column` of `table`, belonging to
+    /// `tenant_id`. The tenant ID also selects the key.
+    pub fn new(table: &'a str, column: &'a str, tenant_id: &'a str) -> Self {
+        Self {
+            table,
+            column,
+            tenant_id,
+            row_id: None,
+        }
+    }
+
+    /// Additionally binds the value to the row with `row_id`, i.e., its
+    /// primary key, so it can't be copied between rows. The row ID must not
+    /// change while the value is stored.
+    pub fn with_row_id(self, row_id: &'a [u8]) -> Self {
+        Self {
+            row_id: Some(row_id),
+            ..self
+        }
+    }
+
+    /// Returns the tenant ID.
+    pub fn tenant_id(&self) -> &str {
+        self.tenant_id
+    }
+
+    fn associated_data(&self) -> Vec<u8> {
+        let mut aad = Vec::new();
+        aad.extend_from_slice(COLUMN_AAD_PREFIX);
+        aad.push(COLUMN_VERSION);
+        for field in [
+            self.table.as_bytes(),
+            self.column.as_bytes(),
+            self.tenant_id.as_bytes(),
+        ] {
+            aad.extend_from_slice(&(field.len() as u64).to_be_bytes());
+            aad.extend_from_slice(field);
+        }
+        match self.row_id {
+            Some(row_id) => {
+                aad.push(1);
+                aad.extend_from_slice(&(row_id.len() as u64).to_be_bytes());
+                aad.extend_from_slice(row_id);
+            }
+            None => aad.push(0),
+        }
+        aad
+    }
+}
+
+/// Encrypts and decrypts column values with per-tenant keys. Refer to the
+/// [module documentation](self) for details.
+pub struct ColumnCipher<R: TenantKeyResolver> {
+    resolver: R,
+}
+
+impl<R: TenantKeyResolver> ColumnCipher<R> {
+    /// Returns a new column cipher, using `resolver` to look up tenant keys.
+    pub fn new(resolver: R) -> Self {
+        Self { resolver }
+    }
+
+    /// Returns a reference to the key resolver.
+    pub fn resolver(&self) -> &R {
+        &self.resolver
+    }
+
+    /// Encrypts `plaintext` for storage at `binding`, returning the encrypted
+    /// value.
+    pub fn encrypt(&self, binding: &ColumnBinding, plaintext: &[u8]) -> Result<Vec<u8>, Error> {
+        let key = self.resolver.resolve(binding.tenant_id)?;
+        let mut nonce = Nonce::default();
+        copy_randombytes(nonce.as_mut_slice());
+
+        let ciphertext = xchacha20poly1305_ietf_encrypt(
+            plaintext,
+            &binding.associated_data(),
+            nonce.as_array(),
+            key.as_array(),
+        );
+
+        let mut value = Vec::with_capacity(COLUMN_OVERHEADBYTES + plaintext.len());
+        value.push(COLUMN_VERSION);
+        value.extend_from_slice(nonce.as_slice());
+        value.extend_from_slice(&ciphertext);
+        Ok(value)
+    }
+
+    /// Decrypts `value`, which must have been encrypted for `binding`,
+    /// returning the plaintext.
+    pub fn decrypt(&self, binding: &ColumnBinding, value: &[u8]) -> Result<Vec<u8>, Error> {
+        if value.len() < COLUMN_OVERHEADBYTES {
+            return Err(dryoc_error!(format!(
+                "value length {} less than minimum {}",
+                value.len(),
+                COLUMN_OVERHEADBYTES
+            )));
+        }
+        if value[0] != COLUMN_VERSION {
+            return Err(dryoc_error!(format!(
+                "unsupported column value version {}",
+                value[0]
+            )));
+        }
+        let (nonce, ciphertext) = value[1..].split_at(CRYPTO_AEAD_XCHACHA20POLY1305_IETF_NPUBBYTES);
+        let nonce = Nonce::try_from(nonce)?;
+        let key = self.resolver.resolve(binding.tenant_id)?;
+
+        xchacha20poly1305_ietf_decrypt(
+            ciphertext,
+            &binding.associated_data(),
+            nonce.as_array(),
+            key.as_array(),
+        )
+    }
+
+    /// Encrypts `plaintext` for storage at `binding`, returning the encrypted
+    /// value encoded with standard base64.
+    #[cfg(feature = "base64")]
+    #[cfg_attr(all(feature = "nightly", doc), doc(cfg(feature = "base64")))]
+    pub fn encrypt_to_base64(
+        &self,
+        binding: &ColumnBinding,
+        plaintext: &[u8],
+    ) -> Result<String, Error> {
+        use base64::Engine as _;
+        use base64::engine::general_purpose::STANDARD;
+
+        Ok(STANDARD.encode(self.encrypt(binding, plaintext)?))
+    }
+
+    /// Decrypts `value`, encoded with standard base64, which must have been
+    /// encrypted for `binding`, returning the plaintext.
+    #[cfg(feature = "base64")]
+    #[cfg_attr(all(feature = "nightly", doc), doc(cfg(feature = "base64")))]
+    pub fn decrypt_from_base64(
+        &self,
+        binding: &ColumnBinding,
+        value: &str,
+    ) -> Result<Vec<u8>, Error> {
+        use base64::Engine as _;
+        use base64::engine::general_purpose::STANDARD;
+
+        let value = STANDARD
+            .decode(value)
+            .map_err(|err| dryoc_error!(format!("invalid base64: {}", err)))?;
+        self.decrypt(binding, &value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cipher() -> ColumnCipher<HashMap<String, Key>> {
+        let mut keys = HashMap::new();
+        keys.insert("a".to_string(), Key::gen());
+        keys.insert("b".to_string(), Key::gen());
+        ColumnCipher::new(keys)
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let cipher = cipher();
+        let binding = ColumnBinding::new("users", "email", "a");
+
+        let value = cipher.encrypt(&binding, b"hello").expect("encrypt");
+        assert_eq!(value.len(), COLUMN_OVERHEADBYTES + 5);
+        assert_eq!(value[0], COLUMN_VERSION);
+        assert_eq!(cipher.decrypt(&binding, &value).expect("decrypt"), b"hello");
+
+        let empty = cipher.encrypt(&binding, b"").expect("encrypt");
+        assert_eq!(cipher.decrypt(&binding, &empty).expect("decrypt"), b"");
+
+        // encryption is randomized
+        assert_ne!(value, cipher.encrypt(&binding, b"hello").expect("encrypt"));
+    }
+
+    #[test]
+    fn test_mis_binding() {
+        let cipher = cipher();
+        let binding = ColumnBinding::new("users", "email", "a").with_row_id(b"1");
+        let value = cipher.encrypt(&binding, b"hello").expect("encrypt");
+
+        for other in [
+            ColumnBinding::new("admins", "email", "a").with_row_id(b"1"),
+            ColumnBinding::new("users", "phone", "a").with_row_id(b"1"),
+            ColumnBinding::new("users", "email", "b").with_row_id(b"1"),
+            ColumnBinding::new("users", "email", "a").with_row_id(b"2"),
+            ColumnBinding::new("users", "email", "a"),
+            // field boundaries are unambiguous
+            ColumnBinding::new("usersemail", "", "a").with_row_id(b"1"),
+        ] {
+            assert!(cipher.decrypt(&other, &value).is_err());
+        }
+
+        let mut tampered = value.clone();
+        tampered[30] ^= 1;
+        assert!(cipher.decrypt(&binding, &tampered).is_err());
+        assert!(cipher.decrypt(&binding, &value[..10]).is_err());
+
+        let unknown = ColumnBinding::new("users", "email", "c");
+        assert!(cipher.encrypt(&unknown, b"hello").is_err());
+    }
+
+    #[test]
+    fn test_closure_resolver() {
+        let key = Key::gen();
+        let cipher = ColumnCipher::new(|tenant_id: &str| {
+            if tenant_id == "a" {
+                Ok(key.clone())
+            } else {
+                Err(dryoc_error!("unknown tenant"))
+            }
+        });
+        let binding = ColumnBinding::new("t", "c", "a");
+        let value = cipher.encrypt(&binding, b"data").expect("encrypt");
+        assert_eq!(cipher.decrypt(&binding, &value).expect("decrypt"), b"data");
+    }
+
+    #[cfg(feature = "base64")]
+    #[test]
+    fn test_base64() {
+        let cipher = cipher();
+        let binding = ColumnBinding::new("users", "email", "b");
+        let value = cipher
+            .encrypt_to_base64(&binding, b"hello")
+            .expect("encrypt");
+        assert_eq!(
+            cipher
+                .decrypt_from_base64(&binding, &value)
+                .expect("decrypt"),
+            b"hello"
+        );
+        assert!(cipher.decrypt_from_base64(&binding, "not base64!").is_err());
+    }
+}
@@ -0,0 +1,660 @@
+//! # sodiumoxide migration shim
+//!
+//! This module mirrors the most commonly used parts of
+//! [sodiumoxide](https://crates.io/crates/sodiumoxide)'s API, implemented with
+//! dryoc, to make it easy to move existing code off of sodiumoxide (which is
+//! no longer maintained). In most cases, migrating is a matter of replacing
+//! `sodiumoxide::crypto` with `dryoc::compat::sodiumoxide::crypto` in your
+//! imports.
+//!
+//! The function signatures, newtypes, and wire formats match sodiumoxide's, so
+//! data encrypted or signed with sodiumoxide can be opened or verified here,
+//! and vice versa. Like sodiumoxide, failures are reported as `Err(())`; for
+//! richer errors, and for protected memory support, use the Rustaceous API
+//! instead.
+//!
+//! The following modules are provided:
+//!
+//! * [`box_`]: public-key authenticated encryption
+//! * [`sealedbox`]: anonymous public-key encryption
+//! * [`secretbox`]: secret-key authenticated encryption
+//! * [`sign`]: public-key signatures
+//! * [`pwhash`]: password hashing and key derivation with Argon2id
+//! * [`randombytes`]: random data
+//!
+//! ## Example
+//!
+//! ```
+//! // previously: use sodiumoxide::crypto::secretbox;
+//! use dryoc::compat::sodiumoxide::crypto::secretbox;
+//!
+//! dryoc::compat::sodiumoxide::init().expect("init failed");
+//!
+//! let key = secretbox::gen_key();
+//! let nonce = secretbox::gen_nonce();
+//! let ciphertext = secretbox::seal(b"hello", &nonce, &key);
+//! let plaintext = secretbox::open(&ciphertext, &nonce, &key).expect("open failed");
+//! assert_eq!(plaintext, b"hello");
+//! ```
+#![allow(clippy::result_unit_err)]
+
+/// Initializes the library. Provided for compatibility: dryoc doesn't require
+/// initialization, so this always succeeds.
+pub fn init() -> Result<(), ()> {
+    Ok(())
+}
+
+/// Module paths matching `sodiumoxide::crypto`.
+pub mod crypto {
+    pub use super::{box_, pwhash, sealedbox, secretbox, sign};
+}
+
+macro_rules! new_type {
+    ($(#[$meta:meta])* public $name:ident($bytes:expr)) => {
+        $(#[$meta])*
+        #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+        pub struct $name(pub [u8; $bytes]);
+
+        new_type!(@impl $name($bytes));
+    };
+    ($(#[$meta:meta])* secret $name:ident($bytes:expr)) => {
+        $(#[$meta])*
+        #[derive(Clone, zeroize::Zeroize, zeroize::ZeroizeOnDrop)]
+        pub struct $name(pub [u8; $bytes]);
+
+        impl std::fmt::Debug for $name {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                write!(f, "{}(****)", stringify!($name))
+            }
+        }
+
+        impl PartialEq for $name {
+            fn eq(&self, other: &Self) -> bool {
+                use subtle::ConstantTimeEq;
+                self.0.ct_eq(&other.0).into()
+            }
+        }
+
+        impl Eq for $name {}
+
+        new_type!(@impl $name($bytes));
+    };
+    (@impl $name:ident($bytes:expr)) => {
+        impl $name {
+            /// Returns a new instance from `bytes`, or `None` if `bytes` has
+            /// the wrong length.
+            pub fn from_slice(bytes: &[u8]) -> Option<Self> {
+                <[u8; $bytes]>::try_from(bytes).ok().map(Self)
+            }
+        }
+
+        impl AsRef<[u8]> for $name {
+            fn as_ref(&self) -> &[u8] {
+                &self.0
+            }
+        }
+    };
+}
+
+/// Public-key authenticated encryption, matching `sodiumoxide::crypto::box_`.
+pub mod box_ {
+    use crate::classic::crypto_box::*;
+    use crate::constants::*;
+
+    /// Length of a public key
+    pub const PUBLICKEYBYTES: usize = CRYPTO_BOX_PUBLICKEYBYTES;
+    /// Length of a secret key
+    pub const SECRETKEYBYTES: usize = CRYPTO_BOX_SECRETKEYBYTES;
+    /// Length of a nonce
+    pub const NONCEBYTES: usize = CRYPTO_BOX_NONCEBYTES;
+    /// Length of a seed
+    pub const SEEDBYTES: usize = CRYPTO_BOX_SEEDBYTES;
+    /// Length of an authentication tag
+    pub const MACBYTES: usize = CRYPTO_BOX_MACBYTES;
+
+    new_type!(
+        /// Public key
+        public PublicKey(PUBLICKEYBYTES)
+    );
+    new_type!(
+        /// Secret key
+        secret SecretKey(SECRETKEYBYTES)
+    );
+    new_type!(
+        /// Seed for deterministic key generation
+        secret Seed(SEEDBYTES)
+    );
+    new_type!(
+        /// Nonce
+        public Nonce(NONCEBYTES)
+    );
+
+    impl SecretKey {
+        /// Computes the public key corresponding to this secret key.
+        pub fn public_key(&self) -> PublicKey {
+            let mut public_key = [0u8; PUBLICKEYBYTES];
+            crate::classic::crypto_core::crypto_scalarmult_base(&mut public_key, &self.0);
+            PublicKey(public_key)
+        }
+    }
+
+    /// Generates a random keypair.
+    pub fn gen_keypair() -> (PublicKey, SecretKey) {
+        let (public_key, secret_key) = crypto_box_keypair();
+        (PublicKey(public_key), SecretKey(secret_key))
+    }
+
+    /// Deterministically derives a keypair from `seed`.
+    pub fn keypair_from_seed(seed: &Seed) -> (PublicKey, SecretKey) {
+        let (public_key, secret_key) = crypto_box_seed_keypair(&seed.0);
+        (PublicKey(public_key), SecretKey(secret_key))
+    }
+
+    /// Generates a random nonce.
+    pub fn gen_nonce() -> Nonce {
+        let mut nonce = [0u8; NONCEBYTES];
+        crate::rng::copy_randombytes(&mut nonce);
+        Nonce(nonce)
+    }
+
+    /// Encrypts and authenticates `message` for `recipient_public_key`, using
+    /// `sender_secret_key`. Returns the tag followed by the ciphertext.
+    pub fn seal(
+        message: &[u8],
+        nonce: &Nonce,
+        recipient_public_key: &PublicKey,
+        sender_secret_key: &SecretKey,
+    ) -> Vec<u8> {
+        let mut ciphertext = vec![0u8; message.len() + MACBYTES];
+        crypto_box_easy(
+            &mut ciphertext,
+            message,
+            &nonce.0,
+            &recipient_public_key.0,
+            &sender_secret_key.0,
+        )
+        .expect("message too long");
+        ciphertext
+    }
+
+    /// Verifies and decrypts `ciphertext` from `sender_public_key`, using
+    /// `recipient_secret_key`.
+    pub fn open(
+        ciphertext: &[u8],
+        nonce: &Nonce,
+        sender_public_key: &PublicKey,
+        recipient_secret_key: &SecretKey,
+    ) -> Result<Vec<u8>, ()> {
+        if ciphertext.len() < MACBYTES {
+            return Err(());
+        }
+        let mut message = vec![0u8; ciphertext.len() - MACBYTES];
+        crypto_box_open_easy(
+            &mut message,
+            ciphertext,
+            &nonce.0,
+            &sender_public_key.0,
+            &recipient_secret_key.0,
+        )
+        .map_err(|_| ())?;
+        Ok(message)
+    }
+}
+
+/// Anonymous public-key encryption, matching
+/// `sodiumoxide::crypto::sealedbox`.
+pub mod sealedbox {
+    use super::box_::{PublicKey, SecretKey};
+    use crate::classic::crypto_box::{crypto_box_seal, crypto_box_seal_open};
+    use crate::constants::CRYPTO_BOX_SEALBYTES;
+
+    /// Overhead of a sealed box, in bytes
+    pub const SEALBYTES: usize = CRYPTO_BOX_SEALBYTES;
+
+    /// Encrypts `message` for `recipient_public_key`, using an ephemeral
+    /// keypair.
+    pub fn seal(message: &[u8], recipient_public_key: &PublicKey) -> Vec<u8> {
+        let mut ciphertext = vec![0u8; message.len() + SEALBYTES];
+        crypto_box_seal(&mut ciphertext, message, &recipient_public_key.0)
+            .expect("message too long");
+        ciphertext
+    }
+
+    /// Decrypts a sealed box with the recipient's keypair.
+    pub fn open(
+        ciphertext: &[u8],
+        recipient_public_key: &PublicKey,
+        recipient_secret_key: &SecretKey,
+    ) -> Result<Vec<u8>, ()> {
+        if ciphertext.len() < SEALBYTES {
+            return Err(());
+        }
+        let mut message = vec![0u8; ciphertext.len() - SEALBYTES];
+        crypto_box_seal_open(
+            &mut message,
+            ciphertext,
+            &recipient_public_key.0,
+            &recipient_secret_key.0,
+        )
+        .map_err(|_| ())?;
+        Ok(message)
+    }
+}
+
+/// Secret-key authenticated encryption, matching
+/// `sodiumoxide::crypto::secretbox`.
+pub mod secretbox {
+    use crate::classic::crypto_secretbox::*;
+    use crate::constants::*;
+
+    /// Length of a key
+    pub const KEYBYTES: usize = CRYPTO_SECRETBOX_KEYBYTES;
+    /// Length of a nonce
+    pub const NONCEBYTES: usize = CRYPTO_SECRETBOX_NONCEBYTES;
+    /// Length of an authentication tag
+    pub const MACBYTES: usize = CRYPTO_SECRETBOX_MACBYTES;
+
+    new_type!(
+        /// Secret key
+        secret Key(KEYBYTES)
+    );
+    new_type!(
+        /// Nonce
+        public Nonce(NONCEBYTES)
+    );
+
+    /// Generates a random key.
+    pub fn gen_key() -> Key {
+        Key(crypto_secretbox_keygen())
+    }
+
+    /// Generates a random nonce.
+    pub fn gen_nonce() -> Nonce {
+        let mut nonce = [0u8; NONCEBYTES];
+        crate::rng::copy_randombytes(&mut nonce);
+        Nonce(nonce)
+    }
+
+    /// Encrypts and authenticates `message` with `key`. Returns the tag
+    /// followed by the ciphertext.
+    pub fn seal(message: &[u8], nonce: &Nonce, key: &Key) -> Vec<u8> {
+        let mut ciphertext = vec![0u8; message.len() + MACBYTES];
+        crypto_secretbox_easy(&mut ciphertext, message, &nonce.0, &key.0)
+            .expect("message too long");
+        ciphertext
+    }
+
+    /// Verifies and decrypts `ciphertext` with `key`.
+    pub fn open(ciphertext: &[u8], nonce: &Nonce, key: &Key) -> Result<Vec<u8>, ()> {
+        if ciphertext.len() < MACBYTES {
+            return Err(());
+        }
+        let mut message = vec![0u8; ciphertext.len() - MACBYTES];
+        crypto_secretbox_open_easy(&mut message, ciphertext, &nonce.0, &key.0).map_err(|_| ())?;
+        Ok(message)
+    }
+}
+
+/// Public-key signatures, matching `sodiumoxide::crypto::sign`.
+pub mod sign {
+    use crate::classic::crypto_sign::*;
+    use crate::constants::*;
+
+    /// Length of a public key
+    pub const PUBLICKEYBYTES: usize = CRYPTO_SIGN_PUBLICKEYBYTES;
+    /// Length of a secret key
+    pub const SECRETKEYBYTES: usize = CRYPTO_SIGN_SECRETKEYBYTES;
+    /// Length of a seed
+    pub const SEEDBYTES: usize = CRYPTO_SIGN_SEEDBYTES;
+    /// Length of a signature
+    pub const SIGNATUREBYTES: usize = CRYPTO_SIGN_BYTES;
+
+    new_type!(
+        /// Public key
+        public PublicKey(PUBLICKEYBYTES)
+    );
+    new_type!(
+        /// Secret key
+        secret SecretKey(SECRETKEYBYTES)
+    );
+    new_type!(
+        /// Seed for deterministic key generation
+        secret Seed(SEEDBYTES)
+    );
+
+    /// Detached signature
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    pub struct Signature(pub [u8; SIGNATUREBYTES]);
+
+    impl Signature {
+        /// Returns a signature from `bytes`, or `None` if `bytes` has the wrong
+        /// length.
+        pub fn from_slice(bytes: &[u8]) -> Option<Self> {
+            <[u8; SIGNATUREBYTES]>::try_from(bytes).ok().map(Self)
+        }
+    }
+
+    impl AsRef<[u8]> for Signature {
+        fn as_ref(&self) -> &[u8] {
+            &self.0
+        }
+    }
+
+    impl SecretKey {
+        /// Returns the public key embedded in this secret key.
+        pub fn public_key(&self) -> PublicKey {
+            let mut public_key = [0u8; PUBLICKEYBYTES];
+            public_key.copy_from_slice(&self.0[SEEDBYTES..]);
+            PublicKey(public_key)
+        }
+    }
+
+    /// Generates a random keypair.
+    pub fn gen_keypair() -> (PublicKey, SecretKey) {
+        let (public_key, secret_key) = crypto_sign_keypair();
+        (PublicKey(public_key), SecretKey(secret_key))
+    }
+
+    /// Deterministically derives a keypair from `seed`.
+    pub fn keypair_from_seed(seed: &Seed) -> (PublicKey, SecretKey) {
+        let (public_key, secret_key) = crypto_sign_seed_keypair(&seed.0);
+        (PublicKey(public_key), SecretKey(secret_key))
+    }
+
+    /// Signs `message`, returning the signature followed by the message.
+    pub fn sign(message: &[u8], secret_key: &SecretKey) -> Vec<u8> {
+        let mut signed_message = vec![0u8; message.len() + SIGNATUREBYTES];
+        crypto_sign(&mut signed_message, message, &secret_key.0).expect("sign failed");
+        signed_message
+    }
+
+    /// Verifies `signed_message`, returning the message if the signature is
+    /// valid.
+    pub fn verify(signed_message: &[u8], public_key: &PublicKey) -> Result<Vec<u8>, ()> {
+        if signed_message.len() < SIGNATUREBYTES {
+            return Err(());
+        }
+        let mut message = vec![0u8; signed_message.len() - SIGNATUREBYTES];
+        crypto_sign_open(&mut message, signed_message, &public_key.0).map_err(|_| ())?;
+        Ok(message)
+    }
+
+    /// Signs `message`, returning a detached signature.
+    pub fn sign_detached(message: &[u8], secret_key: &SecretKey) -> Signature {
+        let mut signature = [0u8; SIGNATUREBYTES];
+        crypto_sign_detached(&mut signature, message, &secret_key.0).expect("sign failed");
+        Signature(signature)
+    }
+
+    /// Returns true if `signature` is a valid signature of `message` by
+    /// `public_key`.
+    pub fn verify_detached(signature: &Signature, message: &[u8], public_key: &PublicKey) -> bool {
+        crypto_sign_verify_detached(&signature.0, message, &public_key.0).is_ok()
+    }
+}
+
+/// Password hashing and key derivation with Argon2id, matching
+/// `sodiumoxide::crypto::pwhash::argon2id13`.
+pub mod pwhash {
+    use crate::classic::crypto_pwhash::*;
+    use crate::constants::*;
+
+    /// Module path matching `sodiumoxide::crypto::pwhash::argon2id13`.
+    pub mod argon2id13 {
+        pub use super::*;
+    }
+
+    /// Length of a salt
+    pub const SALTBYTES: usize = CRYPTO_PWHASH_SALTBYTES;
+    /// Length of a hashed password string, including padding
+    pub const HASHEDPASSWORDBYTES: usize = CRYPTO_PWHASH_STRBYTES;
+
+    /// Operations limit (number of passes)
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    pub struct OpsLimit(pub usize);
+
+    /// Memory limit, in bytes
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    pub struct MemLimit(pub usize);
+
+    /// Operations limit for interactive use
+    pub const OPSLIMIT_INTERACTIVE: OpsLimit =
+        OpsLimit(CRYPTO_PWHASH_OPSLIMIT_INTERACTIVE as usize);
+    /// Memory limit for interactive use
+    pub const MEMLIMIT_INTERACTIVE: MemLimit = MemLimit(CRYPTO_PWHASH_MEMLIMIT_INTERACTIVE);
+    /// Operations limit for moderately sensitive use
+    pub const OPSLIMIT_MODERATE: OpsLimit = OpsLimit(CRYPTO_PWHASH_OPSLIMIT_MODERATE as usize);
+    /// Memory limit for moderately sensitive use
+    pub const MEMLIMIT_MODERATE: MemLimit = MemLimit(CRYPTO_PWHASH_MEMLIMIT_MODERATE);
+    /// Operations limit for highly sensitive use
+    pub const OPSLIMIT_SENSITIVE: OpsLimit = OpsLimit(CRYPTO_PWHASH_OPSLIMIT_SENSITIVE as usize);
+    /// Memory limit for highly sensitive use
+    pub const MEMLIMIT_SENSITIVE: MemLimit = MemLimit(CRYPTO_PWHASH_MEMLIMIT_SENSITIVE);
+
+    new_type!(
+        /// Salt
+        public Salt(SALTBYTES)
+    );
+
+    /// A hashed password string, padded with zeros.
+    #[derive(Clone)]
+    pub struct HashedPassword(pub [u8; HASHEDPASSWORDBYTES]);
+
+    impl HashedPassword {
+        /// Returns a hashed password from `bytes`, or `None` if `bytes` has the
+        /// wrong length.
+        pub fn from_slice(bytes: &[u8]) -> Option<Self> {
+            <[u8; HASHEDPASSWORDBYTES]>::try_from(bytes).ok().map(Self)
+        }
+    }
+
+    impl AsRef<[u8]> for HashedPassword {
+        fn as_ref(&self) -> &[u8] {
+            &self.0
+        }
+    }
+
+    /// Generates a random salt.
+    pub fn gen_salt() -> Salt {
+        let mut salt = [0u8; SALTBYTES];
+        crate::rng::copy_randombytes(&mut salt);
+        Salt(salt)
+    }
+
+    /// Derives a key from `password` and `salt`, filling `key`.
+    pub fn derive_key<'a>(
+        key: &'a mut [u8],
+        password: &[u8],
+        salt: &Salt,
+        opslimit: OpsLimit,
+        memlimit: MemLimit,
+    ) -> Result<&'a [u8], ()> {
+        crypto_pwhash(
+            key,
+            password,
+            &salt.0,
+            opslimit.0 as u64,
+            memlimit.0,
+            PasswordHashAlgorithm::Argon2id13,
+        )
+        .map_err(|_| ())?;
+        Ok(key)
+    }
+
+    /// Hashes `password` with a random salt, returning a string suitable for
+    /// storage.
+    #[cfg(any(feature = "base64", all(doc, not(doctest))))]
+    #[cfg_attr(all(feature = "nightly", doc), doc(cfg(feature = "base64")))]
+    pub fn pwhash(
+        password: &[u8],
+        opslimit: OpsLimit,
+        memlimit: MemLimit,
+    ) -> Result<HashedPassword, ()> {
+        let hashed = crypto_pwhash_str(password, opslimit.0 as u64, memlimit.0).map_err(|_| ())?;
+        let mut hashed_password = [0u8; HASHEDPASSWORDBYTES];
+        hashed_password
+            .get_mut(..hashed.len())
+            .ok_or(())?
+            .copy_from_slice(hashed.as_bytes());
+        Ok(HashedPassword(hashed_password))
+    }
+
+    /// Returns true if `password` matches `hashed_password`.
+    #[cfg(any(feature = "base64", all(doc, not(doctest))))]
+    #[cfg_attr(all(feature = "nightly", doc), doc(cfg(feature = "base64")))]
+    pub fn pwhash_verify(hashed_password: &HashedPassword, password: &[u8]) -> bool {
+        let len = hashed_password
+            .0
+            .iter()
+            .position(|&b| b == 0)
+            .unwrap_or(HASHEDPASSWORDBYTES);
+        match std::str::from_utf8(&hashed_password.0[..len]) {
+            Ok(hashed) => crypto_pwhash_str_verify(hashed, password).is_ok(),
+            Err(_) => false,
+        }
+    }
+}
+
+/// Random data, matching `sodiumoxide::randombytes`.
+pub mod randombytes {
+    /// Returns `size` random bytes.
+    pub fn randombytes(size: usize) -> Vec<u8> {
+        crate::rng::randombytes_buf(size)
+    }
+
+    /// Fills `buf` with random bytes.
+    pub fn randombytes_into(buf: &mut [u8]) {
+        crate::rng::copy_randombytes(buf)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_box_interop() {
+        use sodiumoxide::crypto::box_ as so_box;
+
+        let (pk, sk) = box_::gen_keypair();
+        let (so_pk, so_sk) = so_box::gen_keypair();
+        let nonce = box_::gen_nonce();
+
+        let ciphertext = box_::seal(b"hello", &nonce, &box_::PublicKey(so_pk.0), &sk);
+        let plaintext = so_box::open(
+            &ciphertext,
+            &so_box::Nonce(nonce.0),
+            &so_box::PublicKey(pk.0),
+            &so_sk,
+        )
+        .expect("open failed");
+        assert_eq!(plaintext, b"hello");
+
+        let ciphertext = so_box::seal(
+            b"world",
+            &so_box::Nonce(nonce.0),
+            &so_box::PublicKey(pk.0),
+            &so_sk,
+        );
+        let plaintext =
+            box_::open(&ciphertext, &nonce, &box_::PublicKey(so_pk.0), &sk).expect("open failed");
+        assert_eq!(plaintext, b"world");
+        box_::open(&ciphertext[1..], &nonce, &box_::PublicKey(so_pk.0), &sk)
+            .expect_err("should fail");
+
+        let seed = box_::Seed([7u8; 32]);
+        let (pk, sk) = box_::keypair_from_seed(&seed);
+        let (so_pk, so_sk) = so_box::keypair_from_seed(&so_box::Seed([7u8; 32]));
+        assert_eq!(pk.0, so_pk.0);
+        assert_eq!(sk.0, so_sk.0);
+        assert_eq!(sk.public_key(), pk);
+
+        let sealed = sealedbox::seal(b"sealed", &pk);
+        assert_eq!(
+            sodiumoxide::crypto::sealedbox::open(&sealed, &so_pk, &so_sk).expect("open failed"),
+            b"sealed"
+        );
+    }
+
+    #[test]
+    fn test_secretbox_interop() {
+        use sodiumoxide::crypto::secretbox as so_secretbox;
+
+        let key = secretbox::gen_key();
+        let nonce = secretbox::gen_nonce();
+        let ciphertext = secretbox::seal(b"hello", &nonce, &key);
+        assert_eq!(
+            ciphertext,
+            so_secretbox::seal(
+                b"hello",
+                &so_secretbox::Nonce(nonce.0),
+                &so_secretbox::Key(key.0)
+            )
+        );
+        assert_eq!(
+            secretbox::open(&ciphertext, &nonce, &key).expect("open failed"),
+            b"hello"
+        );
+        secretbox::open(&ciphertext[..10], &nonce, &key).expect_err("should fail");
+        assert!(secretbox::Key::from_slice(&[0u8; 31]).is_none());
+    }
+
+    #[test]
+    fn test_sign_interop() {
+        use sodiumoxide::crypto::sign as so_sign;
+
+        let (pk, sk) = sign::keypair_from_seed(&sign::Seed([3u8; 32]));
+        let (so_pk, so_sk) = so_sign::keypair_from_seed(&so_sign::Seed([3u8; 32]));
+        assert_eq!(pk.0, so_pk.0);
+        assert_eq!(sk.public_key(), pk);
+
+        let signed = sign::sign(b"hello", &sk);
+        assert_eq!(signed, so_sign::sign(b"hello", &so_sk));
+        assert_eq!(sign::verify(&signed, &pk).expect("verify failed"), b"hello");
+
+        let signature = sign::sign_detached(b"hello", &sk);
+        assert!(sign::verify_detached(&signature, b"hello", &pk));
+        assert!(!sign::verify_detached(&signature, b"hellO", &pk));
+        assert!(so_sign::verify_detached(
+            &so_sign::Signature::from_bytes(&signature.0).expect("signature"),
+            b"hello",
+            &so_pk
+        ));
+    }
+
+    #[cfg(feature = "base64")]
+    #[test]
+    fn test_pwhash_interop() {
+        use sodiumoxide::crypto::pwhash::argon2id13 as so_pwhash;
+
+        let hashed = pwhash::pwhash(b"password", pwhash::OpsLimit(1), pwhash::MemLimit(8192))
+            .expect("pwhash failed");
+        assert!(pwhash::pwhash_verify(&hashed, b"password"));
+        assert!(!pwhash::pwhash_verify(&hashed, b"Password"));
+        assert!(so_pwhash::pwhash_verify(
+            &so_pwhash::HashedPassword(hashed.0),
+            b"password"
+        ));
+
+        let salt = pwhash::gen_salt();
+        let mut key = [0u8; 32];
+        let mut so_key = [0u8; 32];
+        pwhash::derive_key(
+            &mut key,
+            b"password",
+            &salt,
+            pwhash::OpsLimit(1),
+            pwhash::MemLimit(8192),
+        )
+        .expect("derive failed");
+        so_pwhash::derive_key(
+            &mut so_key,
+            b"password",
+            &so_pwhash::Salt(salt.0),
+            so_pwhash::OpsLimit(1),
+            so_pwhash::MemLimit(8192),
+        )
+        .expect("derive failed");
+        assert_eq!(key, so_key);
+    }
+}
@@ -38,6 +38,9 @@ pub const CRYPTO_SECRETBOX_XSALSA20POLY1305_KEYBYTES: usize = 32;
 pub const CRYPTO_SECRETBOX_XSALSA20POLY1305_NONCEBYTES: usize = 24;
 pub const CRYPTO_SECRETBOX_XSALSA20POLY1305_MACBYTES: usize = 16;
 pub const CRYPTO_SECRETBOX_XSALSA20POLY1305_MESSAGEBYTES_MAX: usize = SODIUM_SIZE_MAX;
+pub const CRYPTO_SECRETBOX_XSALSA20POLY1305_BOXZEROBYTES: usize = 16;
+pub const CRYPTO_SECRETBOX_XSALSA20POLY1305_ZEROBYTES: usize =
+    CRYPTO_SECRETBOX_XSALSA20POLY1305_BOXZEROBYTES + CRYPTO_SECRETBOX_XSALSA20POLY1305_MACBYTES;
 
 pub const CRYPTO_SECRETBOX_KEYBYTES: usize = CRYPTO_SECRETBOX_XSALSA20POLY1305_KEYBYTES;
 pub const CRYPTO_SECRETBOX_NONCEBYTES: usize = CRYPTO_SECRETBOX_XSALSA20POLY1305_NONCEBYTES;
@@ -45,6 +48,8 @@ pub const CRYPTO_SECRETBOX_MACBYTES: usize = CRYPTO_SECRETBOX_XSALSA20POLY1305_M
 pub const CRYPTO_SECRETBOX_PRIMITIVE: &str = "xsalsa20poly1305";
 pub const CRYPTO_SECRETBOX_MESSAGEBYTES_MAX: usize =
     CRYPTO_SECRETBOX_XSALSA20POLY1305_MESSAGEBYTES_MAX;
+pub const CRYPTO_SECRETBOX_BOXZEROBYTES: usize = CRYPTO_SECRETBOX_XSALSA20POLY1305_BOXZEROBYTES;
+pub const CRYPTO_SECRETBOX_ZEROBYTES: usize = CRYPTO_SECRETBOX_XSALSA20POLY1305_ZEROBYTES;
 
 pub const CRYPTO_AEAD_XCHACHA20POLY1305_IETF_KEYBYTES: usize = 32;
 pub const CRYPTO_AEAD_XCHACHA20POLY1305_IETF_NPUBBYTES: usize = 24;
@@ -65,6 +70,14 @@ pub const CRYPTO_SECRETSTREAM_XCHACHA20POLY1305_MESSAGEBYTES_MAX: usize = min(
     (64u64 * ((1u64 << 32) - 2u64)) as usize,
 );
 
+pub const CRYPTO_STREAM_SALSA20_KEYBYTES: usize = 32;
+pub const CRYPTO_STREAM_SALSA20_NONCEBYTES: usize = 8;
+pub const CRYPTO_STREAM_XSALSA20_KEYBYTES: usize = 32;
+pub const CRYPTO_STREAM_XSALSA20_NONCEBYTES: usize = 24;
+pub const CRYPTO_STREAM_KEYBYTES: usize = CRYPTO_STREAM_XSALSA20_KEYBYTES;
+pub const CRYPTO_STREAM_NONCEBYTES: usize = CRYPTO_STREAM_XSALSA20_NONCEBYTES;
+pub const CRYPTO_STREAM_PRIMITIVE: &str = "xsalsa20";
+
 pub const CRYPTO_STREAM_CHACHA20_IETF_KEYBYTES: usize = 32;
 pub const CRYPTO_STREAM_CHACHA20_IETF_NONCEBYTES: usize = 12;
 
@@ -223,3 +236,141 @@ pub const CRYPTO_PWHASH_SALTBYTES_MIN: usize = CRYPTO_PWHASH_ARGON2ID_SALTBYTES_
 pub const CRYPTO_PWHASH_SALTBYTES: usize = CRYPTO_PWHASH_ARGON2ID_SALTBYTES;
 pub const CRYPTO_PWHASH_STRBYTES: usize = CRYPTO_PWHASH_ARGON2ID_STRBYTES;
 pub const CRYPTO_PWHASH_STRPREFIX: &str = CRYPTO_PWHASH_ARGON2ID_STRPREFIX;
+
+// Compile-time checks for the relationships between constants which the
+// high-level types rely on. If any of these fail, a constant was changed
+// without updating the constants derived from it.
+const _: () = {
+    // crypto_box is crypto_secretbox keyed by an X25519 shared secret
+    assert!(CRYPTO_BOX_PUBLICKEYBYTES == CRYPTO_SCALARMULT_BYTES);
+    assert!(CRYPTO_BOX_SECRETKEYBYTES == CRYPTO_SCALARMULT_SCALARBYTES);
+    assert!(CRYPTO_BOX_BEFORENMBYTES == CRYPTO_SECRETBOX_KEYBYTES);
+    assert!(CRYPTO_BOX_BEFORENMBYTES == CRYPTO_CORE_HSALSA20_OUTPUTBYTES);
+    assert!(CRYPTO_BOX_MACBYTES == CRYPTO_SECRETBOX_MACBYTES);
+    assert!(CRYPTO_BOX_NONCEBYTES == CRYPTO_SECRETBOX_NONCEBYTES);
+    assert!(CRYPTO_BOX_SEALBYTES == CRYPTO_BOX_PUBLICKEYBYTES + CRYPTO_BOX_MACBYTES);
+
+    // crypto_secretbox is XSalsa20 with a Poly1305 tag
+    assert!(CRYPTO_SECRETBOX_KEYBYTES == CRYPTO_STREAM_XSALSA20_KEYBYTES);
+    assert!(CRYPTO_SECRETBOX_NONCEBYTES == CRYPTO_STREAM_XSALSA20_NONCEBYTES);
+    assert!(
+        CRYPTO_STREAM_XSALSA20_NONCEBYTES
+            == CRYPTO_CORE_HSALSA20_INPUTBYTES + CRYPTO_STREAM_SALSA20_NONCEBYTES
+    );
+    assert!(CRYPTO_SECRETBOX_MACBYTES == CRYPTO_ONETIMEAUTH_BYTES);
+    assert!(
+        CRYPTO_SECRETBOX_ZEROBYTES == CRYPTO_SECRETBOX_BOXZEROBYTES + CRYPTO_SECRETBOX_MACBYTES
+    );
+    assert!(CRYPTO_ONETIMEAUTH_KEYBYTES == CRYPTO_SECRETBOX_ZEROBYTES);
+
+    // crypto_secretstream is XChaCha20-Poly1305 with a 1 byte encrypted tag
+    assert!(
+        CRYPTO_SECRETSTREAM_XCHACHA20POLY1305_HEADERBYTES
+            == CRYPTO_CORE_HCHACHA20_INPUTBYTES + CRYPTO_SECRETSTREAM_XCHACHA20POLY1305_INONCEBYTES
+    );
+    assert!(
+        CRYPTO_STREAM_CHACHA20_IETF_NONCEBYTES
+            == CRYPTO_SECRETSTREAM_XCHACHA20POLY1305_COUNTERBYTES
+                + CRYPTO_SECRETSTREAM_XCHACHA20POLY1305_INONCEBYTES
+    );
+    assert!(CRYPTO_SECRETSTREAM_XCHACHA20POLY1305_KEYBYTES == CRYPTO_CORE_HCHACHA20_OUTPUTBYTES);
+    assert!(
+        CRYPTO_SECRETSTREAM_XCHACHA20POLY1305_ABYTES
+            == 1 + CRYPTO_AEAD_XCHACHA20POLY1305_IETF_ABYTES
+    );
+
+    // crypto_sign secret keys are the seed followed by the public key
+    assert!(CRYPTO_SIGN_SECRETKEYBYTES == CRYPTO_SIGN_SEEDBYTES + CRYPTO_SIGN_PUBLICKEYBYTES);
+
+    // crypto_kx and crypto_kdf are built on X25519 and Blake2b
+    assert!(CRYPTO_KX_PUBLICKEYBYTES == CRYPTO_SCALARMULT_BYTES);
+    assert!(CRYPTO_KX_SECRETKEYBYTES == CRYPTO_SCALARMULT_SCALARBYTES);
+    assert!(CRYPTO_KX_SESSIONKEYBYTES * 2 <= CRYPTO_GENERICHASH_BYTES_MAX);
+    assert!(CRYPTO_KDF_KEYBYTES >= CRYPTO_GENERICHASH_KEYBYTES_MIN);
+    assert!(CRYPTO_KDF_KEYBYTES <= CRYPTO_GENERICHASH_KEYBYTES_MAX);
+    assert!(CRYPTO_KDF_BLAKE2B_BYTES_MIN == CRYPTO_GENERICHASH_BYTES_MIN);
+    assert!(CRYPTO_KDF_BLAKE2B_BYTES_MAX == CRYPTO_GENERICHASH_BYTES_MAX);
+    assert!(CRYPTO_KDF_CONTEXTBYTES <= CRYPTO_GENERICHASH_BLAKE2B_PERSONALBYTES);
+};
+
+/// Checks at runtime that the serialized layouts of the high-level types
+/// match the constants they're described by, for example that a sealed
+/// [`DryocBox`](crate::dryocbox::DryocBox) is [`CRYPTO_BOX_SEALBYTES`] longer
+/// than its message.
+///
+/// The relationships between the constants themselves are checked at compile
+/// time. This function is a self-check for the types built on top of them,
+/// which can be run once at startup or from a test suite. Returns an error
+/// describing the first mismatch found.
+pub fn validate() -> Result<(), crate::error::Error> {
+    use crate::dryocbox::DryocBox;
+    use crate::dryocsecretbox::DryocSecretBox;
+    use crate::dryocstream::{DryocStream, Header, Tag};
+    use crate::keypair::KeyPair;
+    use crate::sign::SigningKeyPair;
+    use crate::types::*;
+
+    fn check(name: &str, actual: usize, expected: usize) -> Result<(), crate::error::Error> {
+        if actual == expected {
+            Ok(())
+        } else {
+            Err(dryoc_error!(format!(
+                "{} is {} bytes, expected {}",
+                name, actual, expected
+            )))
+        }
+    }
+
+    let message = b"validate";
+
+    let keypair = KeyPair::gen_with_defaults();
+    let sealed = DryocBox::seal_to_vecbox(message, &keypair.public_key)?;
+    check(
+        "sealed box",
+        sealed.to_vec().len(),
+        message.len() + CRYPTO_BOX_SEALBYTES,
+    )?;
+
+    let nonce = crate::dryocsecretbox::Nonce::gen();
+    let key = crate::dryocsecretbox::Key::gen();
+    let secretbox = DryocSecretBox::try_encrypt_to_vecbox(message, &nonce, &key)?;
+    check(
+        "secret box",
+        secretbox.to_vec().len(),
+        message.len() + CRYPTO_SECRETBOX_MACBYTES,
+    )?;
+
+    let key = crate::dryocstream::Key::gen();
+    let (mut stream, header): (_, Header) = DryocStream::init_push(&key);
+    check(
+        "stream header",
+        header.len(),
+        CRYPTO_SECRETSTREAM_XCHACHA20POLY1305_HEADERBYTES,
+    )?;
+    let ciphertext = stream.push_to_vec(message, None, Tag::MESSAGE)?;
+    check(
+        "stream message",
+        ciphertext.len(),
+        message.len() + CRYPTO_SECRETSTREAM_XCHACHA20POLY1305_ABYTES,
+    )?;
+
+    let signing_keypair = SigningKeyPair::gen_with_defaults();
+    let signed = signing_keypair.sign_with_defaults(message.to_vec())?;
+    check(
+        "signed message",
+        signed.to_vec().len(),
+        message.len() + CRYPTO_SIGN_BYTES,
+    )?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate() {
+        validate().expect("validate failed");
+    }
+}
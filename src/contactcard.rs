@@ -0,0 +1,245 @@
+//! # Contact cards
+//!
+//! [`ContactCard`] is a compact binary encoding of a device's public keys,
+//! small enough to fit comfortably in a QR code, for pairing flows where one
+//! device displays a code and the other scans it.
+//!
+//! A card contains a long-term identity key (an Ed25519
+//! [signing](crate::sign) public key), and optionally a prekey (an X25519
+//! [box](crate::dryocbox) or [key exchange](crate::kx) public key) which the
+//! scanning device can use to start a session right away. A card is laid out
+//! as:
+//!
+//! version (1, currently 1) ‖ flags (1) ‖ identity key (32) ‖ prekey (32, if
+//! flag bit 0 is set) ‖ checksum (4)
+//!
+//! where the checksum is the first 4 bytes of the 16 byte [generic
+//! hash](crate::generichash) of everything before it, so that an encoded card
+//! is at most [`MAX_CONTACT_CARD_BYTES`] (70) bytes.
+//!
+//! The checksum only detects misreads. A card isn't signed, so it's only as
+//! trustworthy as the channel it was obtained over. When it was received over
+//! an untrusted channel, compare its [fingerprint](ContactCard::fingerprint)
+//! with the one shown on the other device, using
+//! [`ContactCard::verify_fingerprint`].
+//!
+//! ## Example
+//!
+//! ```
+//! use dryoc::contactcard::ContactCard;
+//! use dryoc::keypair::StackKeyPair;
+//! use dryoc::sign::SigningKeyPair;
+//!
+//! let identity = SigningKeyPair::gen_with_defaults();
+//! let prekey = StackKeyPair::gen();
+//!
+//! // On the first device, encode a card and display it as a QR code
+//! let card = ContactCard::new(identity.public_key.clone(), Some(prekey.public_key.clone()));
+//! let encoded = card.to_bytes();
+//! assert_eq!(encoded.len(), 70);
+//! let fingerprint = card.fingerprint();
+//!
+//! // On the second device, decode the scanned card, and verify its
+//! // fingerprint against the one displayed by the first device
+//! let scanned = ContactCard::from_bytes(&encoded).expect("decode failed");
+//! scanned
+//!     .verify_fingerprint(&fingerprint.to_uppercase())
+//!     .expect("fingerprint mismatch");
+//! assert_eq!(scanned.prekey(), Some(&prekey.public_key));
+//! ```
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use subtle::ConstantTimeEq;
+
+use crate::bech32;
+use crate::constants::{
+    CRYPTO_BOX_PUBLICKEYBYTES, CRYPTO_GENERICHASH_BYTES_MIN, CRYPTO_GENERICHASH_KEYBYTES,
+    CRYPTO_SIGN_PUBLICKEYBYTES,
+};
+use crate::error::Error;
+use crate::generichash::GenericHash;
+use crate::types::*;
+
+/// Current contact card version.
+pub const CONTACT_CARD_VERSION: u8 = 1;
+/// Length of the checksum at the end of a contact card, in bytes.
+pub const CONTACT_CARD_CHECKSUM_BYTES: usize = 4;
+/// Maximum length of an encoded contact card, in bytes.
+pub const MAX_CONTACT_CARD_BYTES: usize =
+    2 + CRYPTO_SIGN_PUBLICKEYBYTES + CRYPTO_BOX_PUBLICKEYBYTES + CONTACT_CARD_CHECKSUM_BYTES;
+
+const FLAG_PREKEY: u8 = 0x01;
+
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)
+)]
+#[cfg_attr(not(feature = "serde"), derive(Clone, Debug, PartialEq, Eq))]
+/// A device's public identity key, and optional prekey, for pairing.
+///
+/// Refer to [crate::contactcard] for sample usage.
+pub struct ContactCard {
+    identity_key: crate::sign::PublicKey,
+    prekey: Option<crate::dryocbox::PublicKey>,
+}
+
+fn checksum(bytes: &[u8]) -> [u8; CONTACT_CARD_CHECKSUM_BYTES] {
+    let digest =
+        GenericHash::<CRYPTO_GENERICHASH_KEYBYTES, CRYPTO_GENERICHASH_BYTES_MIN>::digest(bytes);
+    let mut checksum = [0u8; CONTACT_CARD_CHECKSUM_BYTES];
+    checksum.copy_from_slice(&digest[..CONTACT_CARD_CHECKSUM_BYTES]);
+    checksum
+}
+
+impl ContactCard {
+    /// Returns a new contact card with `identity_key` and an optional
+    /// `prekey`.
+    pub fn new(
+        identity_key: crate::sign::PublicKey,
+        prekey: Option<crate::dryocbox::PublicKey>,
+    ) -> Self {
+        Self {
+            identity_key,
+            prekey,
+        }
+    }
+
+    /// Returns the identity key.
+    pub fn identity_key(&self) -> &crate::sign::PublicKey {
+        &self.identity_key
+    }
+
+    /// Returns the prekey, if any.
+    pub fn prekey(&self) -> Option<&crate::dryocbox::PublicKey> {
+        self.prekey.as_ref()
+    }
+
+    /// Encodes this card, as described in [crate::contactcard].
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(MAX_CONTACT_CARD_BYTES);
+        bytes.push(CONTACT_CARD_VERSION);
+        bytes.push(if self.prekey.is_some() {
+            FLAG_PREKEY
+        } else {
+            0
+        });
+        bytes.extend_from_slice(self.identity_key.as_slice());
+        if let Some(prekey) = &self.prekey {
+            bytes.extend_from_slice(prekey.as_slice());
+        }
+        let checksum = checksum(&bytes);
+        bytes.extend_from_slice(&checksum);
+        bytes
+    }
+
+    /// Decodes a card from `bytes`, as encoded by [`ContactCard::to_bytes`].
+    /// Returns an error if the card is truncated, damaged, or of an unknown
+    /// version.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        if bytes.len() < 2 + CRYPTO_SIGN_PUBLICKEYBYTES + CONTACT_CARD_CHECKSUM_BYTES {
+            return Err(dryoc_error!(format!(
+                "contact card too short: {} bytes",
+                bytes.len()
+            )));
+        }
+        let (body, expected) = bytes.split_at(bytes.len() - CONTACT_CARD_CHECKSUM_BYTES);
+        if checksum(body) != expected {
+            return Err(dryoc_error!("contact card checksum mismatch"));
+        }
+
+        if body[0] != CONTACT_CARD_VERSION {
+            return Err(dryoc_error!(format!(
+                "unsupported contact card version {}",
+                body[0]
+            )));
+        }
+        let flags = body[1];
+        if flags & !FLAG_PREKEY != 0 {
+            return Err(dryoc_error!(format!(
+                "unknown contact card flags {:#04x}",
+                flags
+            )));
+        }
+
+        let has_prekey = flags & FLAG_PREKEY != 0;
+        let expected_len = 2
+            + CRYPTO_SIGN_PUBLICKEYBYTES
+            + if has_prekey {
+                CRYPTO_BOX_PUBLICKEYBYTES
+            } else {
+                0
+            };
+        if body.len() != expected_len {
+            return Err(dryoc_error!(format!(
+                "invalid contact card length {}",
+                bytes.len()
+            )));
+        }
+
+        let (identity_key, prekey) = body[2..].split_at(CRYPTO_SIGN_PUBLICKEYBYTES);
+        let mut card = Self::new(crate::sign::PublicKey::try_from(identity_key)?, None);
+        if has_prekey {
+            card.prekey = Some(crate::dryocbox::PublicKey::try_from(prekey)?);
+        }
+        Ok(card)
+    }
+
+    /// Returns the [fingerprint](crate::bech32::fingerprint) of the identity
+    /// key, for a person to compare against the one displayed by the other
+    /// device.
+    pub fn fingerprint(&self) -> String {
+        bech32::fingerprint(&self.identity_key)
+    }
+
+    /// Verifies that `fingerprint`, in either case, matches the fingerprint of
+    /// this card's identity key. Returns an error if it doesn't, or if it can't
+    /// be decoded.
+    pub fn verify_fingerprint(&self, fingerprint: &str) -> Result<(), Error> {
+        let expected = bech32::decode_fingerprint(&self.fingerprint())?;
+        let actual = bech32::decode_fingerprint(fingerprint)?;
+        if expected.ct_eq(&actual).unwrap_u8() == 1 {
+            Ok(())
+        } else {
+            Err(dryoc_error!("fingerprint mismatch"))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keypair::StackKeyPair;
+    use crate::sign::SigningKeyPair;
+
+    #[test]
+    fn test_contact_card() {
+        let identity = SigningKeyPair::gen_with_defaults();
+        let card = ContactCard::new(identity.public_key.clone(), None);
+        let encoded = card.to_bytes();
+        assert_eq!(encoded.len(), 38);
+        assert_eq!(ContactCard::from_bytes(&encoded).expect("decode"), card);
+
+        let card = ContactCard::new(
+            identity.public_key.clone(),
+            Some(StackKeyPair::gen().public_key.clone()),
+        );
+        let encoded = card.to_bytes();
+        assert_eq!(encoded.len(), MAX_CONTACT_CARD_BYTES);
+        assert_eq!(ContactCard::from_bytes(&encoded).expect("decode"), card);
+
+        for i in 0..encoded.len() {
+            let mut damaged = encoded.clone();
+            damaged[i] ^= 0x20;
+            assert!(ContactCard::from_bytes(&damaged).is_err());
+        }
+        assert!(ContactCard::from_bytes(&encoded[..encoded.len() - 1]).is_err());
+
+        card.verify_fingerprint(&card.fingerprint())
+            .expect("verify");
+        let other = SigningKeyPair::gen_with_defaults();
+        let other = ContactCard::new(other.public_key.clone(), None);
+        assert!(card.verify_fingerprint(&other.fingerprint()).is_err());
+        assert!(card.verify_fingerprint("not a fingerprint").is_err());
+    }
+}
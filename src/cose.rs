@@ -0,0 +1,440 @@
+//! # COSE messages
+//!
+//! Encoding of signed and encrypted messages as [COSE](https://www.rfc-editor.org/rfc/rfc9052)
+//! structures, for interoperability with IoT and FIDO ecosystems which expect
+//! CBOR Object Signing and Encryption.
+//!
+//! * [`CoseSign1`] is a `COSE_Sign1` message, signed with Ed25519 (`EdDSA`,
+//!   algorithm -8), or any other algorithm implementing the
+//!   [`Signer`](crate::sign::Signer) and [`Verifier`](crate::sign::Verifier)
+//!   traits
+//! * [`CoseEncrypt0`] is a `COSE_Encrypt0` message, encrypted with
+//!   XChaCha20-Poly1305 (algorithm 24)
+//!
+//! Both include the algorithm in the protected header, and are encoded with
+//! their CBOR tag (18 and 16 respectively). Decoding accepts tagged or
+//! untagged messages, and rejects messages for any other algorithm.
+//!
+//! Both also accept external additional authenticated data, which is bound
+//! to the message but not included in it, as described in RFC 9052, section
+//! 4.3. Pass an empty slice if you don't need it.
+//!
+//! ## Example
+//!
+//! ```
+//! use dryoc::cose::*;
+//! use dryoc::sign::SigningKeyPair;
+//! use dryoc::types::*;
+//!
+//! let keypair = SigningKeyPair::gen_with_defaults();
+//! let signed = CoseSign1::sign(b"hello", b"", &keypair).expect("sign failed");
+//! let encoded = signed.to_cbor();
+//!
+//! let decoded = CoseSign1::from_cbor(&encoded).expect("decode failed");
+//! decoded
+//!     .verify(b"", &keypair.public_key)
+//!     .expect("verify failed");
+//! assert_eq!(decoded.payload(), b"hello");
+//!
+//! let key = Key::gen();
+//! let encrypted = CoseEncrypt0::encrypt(b"secret", b"", &key);
+//! let decoded = CoseEncrypt0::from_cbor(&encrypted.to_cbor()).expect("decode failed");
+//! let decrypted: Vec<u8> = decoded.decrypt(b"", &key).expect("decrypt failed");
+//! assert_eq!(decrypted, b"secret");
+//! ```
+use zeroize::Zeroize;
+
+use crate::aead::{xchacha20poly1305_ietf_decrypt, xchacha20poly1305_ietf_encrypt};
+use crate::cbor::Value;
+use crate::constants::{
+    CRYPTO_AEAD_XCHACHA20POLY1305_IETF_KEYBYTES, CRYPTO_AEAD_XCHACHA20POLY1305_IETF_NPUBBYTES,
+    CRYPTO_SIGN_PUBLICKEYBYTES, CRYPTO_SIGN_SECRETKEYBYTES,
+};
+use crate::error::Error;
+use crate::sign::{
+    PublicKey as Ed25519PublicKey, SignatureAlgorithm, Signer, SigningKeyPair, Verifier,
+};
+use crate::types::*;
+
+/// COSE algorithm identifier for EdDSA (Ed25519).
+pub const COSE_ALG_EDDSA: i64 = -8;
+/// COSE algorithm identifier for XChaCha20-Poly1305.
+pub const COSE_ALG_XCHACHA20POLY1305: i64 = 24;
+
+const TAG_ENCRYPT0: u64 = 16;
+const TAG_SIGN1: u64 = 18;
+
+const HEADER_ALG: i64 = 1;
+const HEADER_IV: i64 = 5;
+
+/// Key for [`CoseEncrypt0`].
+pub type Key = StackByteArray<CRYPTO_AEAD_XCHACHA20POLY1305_IETF_KEYBYTES>;
+/// Nonce (IV) for [`CoseEncrypt0`].
+pub type Nonce = StackByteArray<CRYPTO_AEAD_XCHACHA20POLY1305_IETF_NPUBBYTES>;
+
+/// A `COSE_Sign1` message, signed with Ed25519, or any other algorithm
+/// implementing the [`Signer`] and [`Verifier`] traits. Refer to
+/// [crate::cose] for details.
+#[derive(Clone, Debug)]
+pub struct CoseSign1 {
+    protected: Vec<u8>,
+    payload: Vec<u8>,
+    signature: Vec<u8>,
+}
+
+impl CoseSign1 {
+    /// Signs `payload` and `external_aad` with `keypair`.
+    pub fn sign<
+        PublicKey: ByteArray<CRYPTO_SIGN_PUBLICKEYBYTES> + Zeroize,
+        SecretKey: ByteArray<CRYPTO_SIGN_SECRETKEYBYTES> + Zeroize,
+    >(
+        payload: &[u8],
+        external_aad: &[u8],
+        keypair: &SigningKeyPair<PublicKey, SecretKey>,
+    ) -> Result<Self, Error> {
+        Self::sign_with(payload, external_aad, keypair)
+    }
+
+    /// Signs `payload` and `external_aad` with `signer`. The protected
+    /// header's algorithm is set from the signer's
+    /// [algorithm](Signer::algorithm).
+    pub fn sign_with<S: Signer + ?Sized>(
+        payload: &[u8],
+        external_aad: &[u8],
+        signer: &S,
+    ) -> Result<Self, Error> {
+        let protected = protected_header(signer.algorithm().cose_id());
+        let signature = signer.try_sign(&sig_structure(&protected, external_aad, payload))?;
+
+        Ok(Self {
+            protected,
+            payload: payload.to_vec(),
+            signature,
+        })
+    }
+
+    /// Verifies this message's signature with `public_key` and
+    /// `external_aad`.
+    pub fn verify<PublicKey: ByteArray<CRYPTO_SIGN_PUBLICKEYBYTES>>(
+        &self,
+        external_aad: &[u8],
+        public_key: &PublicKey,
+    ) -> Result<(), Error> {
+        self.verify_with(
+            external_aad,
+            &Ed25519PublicKey::from(*public_key.as_array()),
+        )
+    }
+
+    /// Verifies this message's signature with `verifier` and `external_aad`.
+    /// The protected header's algorithm must match the verifier's
+    /// [algorithm](Verifier::algorithm).
+    pub fn verify_with<V: Verifier + ?Sized>(
+        &self,
+        external_aad: &[u8],
+        verifier: &V,
+    ) -> Result<(), Error> {
+        check_algorithm(&self.protected, verifier.algorithm().cose_id())?;
+        verifier.verify(
+            &sig_structure(&self.protected, external_aad, &self.payload),
+            &self.signature,
+        )
+    }
+
+    /// Returns the payload of this message, which has not necessarily been
+    /// verified.
+    pub fn payload(&self) -> &[u8] {
+        &self.payload
+    }
+
+    /// Returns the signature of this message.
+    pub fn signature(&self) -> &[u8] {
+        &self.signature
+    }
+
+    /// Returns the tagged CBOR encoding of this message.
+    pub fn to_cbor(&self) -> Vec<u8> {
+        Value::Tag(
+            TAG_SIGN1,
+            Box::new(Value::Array(vec![
+                Value::Bytes(self.protected.clone()),
+                Value::Map(vec![]),
+                Value::Bytes(self.payload.clone()),
+                Value::Bytes(self.signature.clone()),
+            ])),
+        )
+        .to_vec()
+    }
+
+    /// Decodes a message from its tagged or untagged CBOR encoding. Returns an
+    /// error if the message isn't signed with EdDSA, or if the payload is
+    /// detached.
+    pub fn from_cbor(bytes: &[u8]) -> Result<Self, Error> {
+        Self::from_cbor_with_algorithm(bytes, SignatureAlgorithm::EdDSA)
+    }
+
+    /// Decodes a message from its tagged or untagged CBOR encoding. Returns an
+    /// error if the message isn't signed with `algorithm`, or if the payload
+    /// is detached.
+    pub fn from_cbor_with_algorithm(
+        bytes: &[u8],
+        algorithm: SignatureAlgorithm,
+    ) -> Result<Self, Error> {
+        match untag(Value::from_slice(bytes)?, TAG_SIGN1)?.as_array() {
+            Some(
+                [
+                    Value::Bytes(protected),
+                    Value::Map(_),
+                    Value::Bytes(payload),
+                    Value::Bytes(signature),
+                ],
+            ) => {
+                check_algorithm(protected, algorithm.cose_id())?;
+                Ok(Self {
+                    protected: protected.clone(),
+                    payload: payload.clone(),
+                    signature: signature.clone(),
+                })
+            }
+            _ => Err(dryoc_error!("invalid COSE_Sign1 structure")),
+        }
+    }
+}
+
+/// A `COSE_Encrypt0` message, encrypted with XChaCha20-Poly1305. Refer to
+/// [crate::cose] for details.
+#[derive(Clone, Debug)]
+pub struct CoseEncrypt0 {
+    protected: Vec<u8>,
+    nonce: Nonce,
+    ciphertext: Vec<u8>,
+}
+
+impl CoseEncrypt0 {
+    /// Encrypts `message` and `external_aad` with `key`, using a random
+    /// nonce.
+    pub fn encrypt<SecretKey: ByteArray<CRYPTO_AEAD_XCHACHA20POLY1305_IETF_KEYBYTES>>(
+        message: &[u8],
+        external_aad: &[u8],
+        key: &SecretKey,
+    ) -> Self {
+        Self::encrypt_with_nonce(message, external_aad, &Nonce::gen(), key)
+    }
+
+    /// Encrypts `message` and `external_aad` with `key` and `nonce`. The
+    /// nonce must never be reused with the same key.
+    pub fn encrypt_with_nonce<SecretKey: ByteArray<CRYPTO_AEAD_XCHACHA20POLY1305_IETF_KEYBYTES>>(
+        message: &[u8],
+        external_aad: &[u8],
+        nonce: &Nonce,
+        key: &SecretKey,
+    ) -> Self {
+        let protected = protected_header(COSE_ALG_XCHACHA20POLY1305);
+        let ciphertext = xchacha20poly1305_ietf_encrypt(
+            message,
+            &enc_structure(&protected, external_aad),
+            nonce.as_array(),
+            key.as_array(),
+        );
+
+        Self {
+            protected,
+            nonce: nonce.clone(),
+            ciphertext,
+        }
+    }
+
+    /// Decrypts this message with `external_aad` and `key`.
+    pub fn decrypt<
+        SecretKey: ByteArray<CRYPTO_AEAD_XCHACHA20POLY1305_IETF_KEYBYTES>,
+        Output: ResizableBytes + NewBytes,
+    >(
+        &self,
+        external_aad: &[u8],
+        key: &SecretKey,
+    ) -> Result<Output, Error> {
+        let mut message = xchacha20poly1305_ietf_decrypt(
+            &self.ciphertext,
+            &enc_structure(&self.protected, external_aad),
+            self.nonce.as_array(),
+            key.as_array(),
+        )?;
+
+        let mut output = Output::new_bytes();
+        output.resize(message.len(), 0);
+        output.as_mut_slice().copy_from_slice(&message);
+        message.zeroize();
+
+        Ok(output)
+    }
+
+    /// Returns the nonce of this message.
+    pub fn nonce(&self) -> &Nonce {
+        &self.nonce
+    }
+
+    /// Returns the ciphertext of this message, including the tag.
+    pub fn ciphertext(&self) -> &[u8] {
+        &self.ciphertext
+    }
+
+    /// Returns the tagged CBOR encoding of this message.
+    pub fn to_cbor(&self) -> Vec<u8> {
+        Value::Tag(
+            TAG_ENCRYPT0,
+            Box::new(Value::Array(vec![
+                Value::Bytes(self.protected.clone()),
+                Value::Map(vec![(
+                    Value::from(HEADER_IV),
+                    Value::Bytes(self.nonce.to_vec()),
+                )]),
+                Value::Bytes(self.ciphertext.clone()),
+            ])),
+        )
+        .to_vec()
+    }
+
+    /// Decodes a message from its tagged or untagged CBOR encoding. Returns an
+    /// error if the message isn't encrypted with XChaCha20-Poly1305.
+    pub fn from_cbor(bytes: &[u8]) -> Result<Self, Error> {
+        match untag(Value::from_slice(bytes)?, TAG_ENCRYPT0)?.as_array() {
+            Some(
+                [
+                    Value::Bytes(protected),
+                    unprotected,
+                    Value::Bytes(ciphertext),
+                ],
+            ) => {
+                check_algorithm(protected, COSE_ALG_XCHACHA20POLY1305)?;
+                let nonce = unprotected
+                    .get(HEADER_IV)
+                    .and_then(Value::as_bytes)
+                    .ok_or_else(|| dryoc_error!("missing or invalid IV"))?;
+                Ok(Self {
+                    protected: protected.clone(),
+                    nonce: Nonce::try_from(nonce)?,
+                    ciphertext: ciphertext.clone(),
+                })
+            }
+            _ => Err(dryoc_error!("invalid COSE_Encrypt0 structure")),
+        }
+    }
+}
+
+fn protected_header(algorithm: i64) -> Vec<u8> {
+    Value::Map(vec![(Value::from(HEADER_ALG), Value::from(algorithm))]).to_vec()
+}
+
+fn check_algorithm(protected: &[u8], algorithm: i64) -> Result<(), Error> {
+    match Value::from_slice(protected)?.get(HEADER_ALG) {
+        Some(value) if *value == Value::from(algorithm) => Ok(()),
+        Some(value) => Err(dryoc_error!(format!(
+            "unsupported algorithm {:?}, expected {}",
+            value, algorithm
+        ))),
+        None => Err(dryoc_error!("missing algorithm in protected header")),
+    }
+}
+
+fn untag(value: Value, expected: u64) -> Result<Value, Error> {
+    match value {
+        Value::Tag(tag, value) if tag == expected => Ok(*value),
+        Value::Tag(tag, _) => Err(dryoc_error!(format!(
+            "unexpected CBOR tag {}, expected {}",
+            tag, expected
+        ))),
+        value => Ok(value),
+    }
+}
+
+fn sig_structure(protected: &[u8], external_aad: &[u8], payload: &[u8]) -> Vec<u8> {
+    Value::Array(vec![
+        Value::Text("Signature1".into()),
+        Value::Bytes(protected.to_vec()),
+        Value::Bytes(external_aad.to_vec()),
+        Value::Bytes(payload.to_vec()),
+    ])
+    .to_vec()
+}
+
+fn enc_structure(protected: &[u8], external_aad: &[u8]) -> Vec<u8> {
+    Value::Array(vec![
+        Value::Text("Encrypt0".into()),
+        Value::Bytes(protected.to_vec()),
+        Value::Bytes(external_aad.to_vec()),
+    ])
+    .to_vec()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sign1() {
+        let keypair = SigningKeyPair::gen_with_defaults();
+        let signed = CoseSign1::sign(b"payload", b"aad", &keypair).expect("sign");
+        let encoded = signed.to_cbor();
+        assert!(hex::encode(&encoded).starts_with("d28443a10127a0477061796c6f61645840"));
+
+        let decoded = CoseSign1::from_cbor(&encoded).expect("decode");
+        decoded.verify(b"aad", &keypair.public_key).expect("verify");
+        assert!(decoded.verify(b"", &keypair.public_key).is_err());
+        assert!(
+            decoded
+                .verify(b"aad", &SigningKeyPair::gen_with_defaults().public_key)
+                .is_err()
+        );
+
+        // untagged messages are accepted
+        CoseSign1::from_cbor(&encoded[1..])
+            .expect("decode")
+            .verify(b"aad", &keypair.public_key)
+            .expect("verify");
+
+        // other algorithms are rejected
+        let mut es256 = encoded.clone();
+        es256[5] = 0x26;
+        assert!(CoseSign1::from_cbor(&es256).is_err());
+        assert!(CoseEncrypt0::from_cbor(&encoded).is_err());
+    }
+
+    #[test]
+    fn test_sign1_with() {
+        let keypair = SigningKeyPair::gen_with_defaults();
+        let signed = CoseSign1::sign_with(b"payload", b"", &keypair).expect("sign");
+        let encoded = signed.to_cbor();
+        assert_eq!(signed.signature().len(), 64);
+
+        let decoded = CoseSign1::from_cbor_with_algorithm(&encoded, SignatureAlgorithm::EdDSA)
+            .expect("decode");
+        decoded
+            .verify_with(b"", &keypair.public_key)
+            .expect("verify");
+        assert!(CoseSign1::from_cbor_with_algorithm(&encoded, SignatureAlgorithm::Es256K).is_err());
+    }
+
+    #[test]
+    fn test_encrypt0() {
+        let key = Key::gen();
+        let nonce = Nonce::from([2u8; CRYPTO_AEAD_XCHACHA20POLY1305_IETF_NPUBBYTES]);
+        let encrypted = CoseEncrypt0::encrypt_with_nonce(b"message", b"aad", &nonce, &key);
+        let encoded = encrypted.to_cbor();
+        assert!(hex::encode(&encoded).starts_with(&format!(
+            "d08344a1011818a1055818{}57",
+            "02".repeat(CRYPTO_AEAD_XCHACHA20POLY1305_IETF_NPUBBYTES)
+        )));
+
+        let decoded = CoseEncrypt0::from_cbor(&encoded).expect("decode");
+        let decrypted: Vec<u8> = decoded.decrypt(b"aad", &key).expect("decrypt");
+        assert_eq!(decrypted, b"message");
+        assert!(decoded.decrypt::<_, Vec<u8>>(b"", &key).is_err());
+        assert!(decoded.decrypt::<_, Vec<u8>>(b"aad", &Key::gen()).is_err());
+
+        let mut tampered = encoded.clone();
+        *tampered.last_mut().unwrap() ^= 1;
+        let tampered = CoseEncrypt0::from_cbor(&tampered).expect("decode");
+        assert!(tampered.decrypt::<_, Vec<u8>>(b"aad", &key).is_err());
+        assert!(CoseSign1::from_cbor(&encoded).is_err());
+    }
+}
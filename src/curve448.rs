@@ -0,0 +1,522 @@
+//! Arithmetic for Curve448 (the "Goldilocks" curve), used internally by
+//! [`crate::x448`] and [`crate::ed448`].
+//!
+//! Field elements are stored as 8 limbs of 56 bits, and all operations on
+//! secret data are constant-time. Points on Edwards448 use projective
+//! coordinates with the complete addition formulas from RFC 8032, section
+//! 5.2.4.
+use subtle::{Choice, ConditionallySelectable, ConstantTimeEq};
+use zeroize::Zeroize;
+
+const MASK: u64 = (1 << 56) - 1;
+
+/// p - 2, little-endian, for inversion by Fermat's little theorem.
+const P_MINUS_2: [u8; 56] = [
+    0xfd, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+    0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xfe, 0xff, 0xff, 0xff,
+    0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+    0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+];
+
+/// (p - 3) / 4, little-endian, for square roots.
+const P_MINUS_3_DIV_4: [u8; 56] = [
+    0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+    0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xbf, 0xff, 0xff, 0xff, 0xff,
+    0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+    0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x3f,
+];
+
+/// An element of the field GF(p), where p = 2^448 - 2^224 - 1.
+#[derive(Clone, Copy, Zeroize)]
+pub(crate) struct FieldElement([u64; 8]);
+
+impl ConditionallySelectable for FieldElement {
+    fn conditional_select(a: &Self, b: &Self, choice: Choice) -> Self {
+        let mut limbs = [0u64; 8];
+        for (i, limb) in limbs.iter_mut().enumerate() {
+            *limb = u64::conditional_select(&a.0[i], &b.0[i], choice);
+        }
+        Self(limbs)
+    }
+}
+
+impl FieldElement {
+    pub(crate) const ONE: Self = Self([1, 0, 0, 0, 0, 0, 0, 0]);
+    pub(crate) const ZERO: Self = Self([0; 8]);
+
+    /// Decodes a little-endian field element. Values which are not fully
+    /// reduced are accepted, and reduced by subsequent operations.
+    pub(crate) fn from_bytes(bytes: &[u8; 56]) -> Self {
+        let mut limbs = [0u64; 8];
+        for (i, limb) in limbs.iter_mut().enumerate() {
+            let mut word = [0u8; 8];
+            word[..7].copy_from_slice(&bytes[7 * i..7 * i + 7]);
+            *limb = u64::from_le_bytes(word);
+        }
+        Self(limbs)
+    }
+
+    /// Encodes this element in canonical (fully reduced) little-endian form.
+    pub(crate) fn to_bytes(self) -> [u8; 56] {
+        let mut limbs = self.0;
+        for _ in 0..3 {
+            carry(&mut limbs);
+        }
+
+        // subtract p, and keep the result if it didn't underflow
+        let mut reduced = [0u64; 8];
+        let mut borrow = 0u64;
+        for (i, limb) in reduced.iter_mut().enumerate() {
+            let p = if i == 4 { MASK - 1 } else { MASK };
+            let t = limbs[i].wrapping_sub(p).wrapping_sub(borrow);
+            borrow = t >> 63;
+            *limb = t & MASK;
+        }
+        let keep = Choice::from((borrow ^ 1) as u8);
+        for (limb, r) in limbs.iter_mut().zip(reduced) {
+            limb.conditional_assign(&r, keep);
+        }
+
+        let mut bytes = [0u8; 56];
+        for (i, limb) in limbs.iter().enumerate() {
+            bytes[7 * i..7 * i + 7].copy_from_slice(&limb.to_le_bytes()[..7]);
+        }
+        bytes
+    }
+
+    pub(crate) fn add(&self, other: &Self) -> Self {
+        let mut limbs = [0u64; 8];
+        for (i, limb) in limbs.iter_mut().enumerate() {
+            *limb = self.0[i] + other.0[i];
+        }
+        carry(&mut limbs);
+        Self(limbs)
+    }
+
+    pub(crate) fn sub(&self, other: &Self) -> Self {
+        // add 2p before subtracting, so the limbs never underflow
+        let mut limbs = [0u64; 8];
+        for (i, limb) in limbs.iter_mut().enumerate() {
+            let two_p = if i == 4 { 2 * MASK - 2 } else { 2 * MASK };
+            *limb = self.0[i] + two_p - other.0[i];
+        }
+        carry(&mut limbs);
+        Self(limbs)
+    }
+
+    pub(crate) fn neg(&self) -> Self {
+        Self::ZERO.sub(self)
+    }
+
+    pub(crate) fn mul(&self, other: &Self) -> Self {
+        let mut z = [0u128; 15];
+        for i in 0..8 {
+            for j in 0..8 {
+                z[i + j] += self.0[i] as u128 * other.0[j] as u128;
+            }
+        }
+        // 2^448 = 2^224 + 1 (mod p)
+        for k in (8..15).rev() {
+            z[k - 4] += z[k];
+            z[k - 8] += z[k];
+        }
+
+        let mut wide = [0u128; 8];
+        wide.copy_from_slice(&z[..8]);
+        for _ in 0..2 {
+            for i in 0..7 {
+                wide[i + 1] += wide[i] >> 56;
+                wide[i] &= MASK as u128;
+            }
+            let c = wide[7] >> 56;
+            wide[7] &= MASK as u128;
+            wide[0] += c;
+            wide[4] += c;
+        }
+
+        let mut limbs = [0u64; 8];
+        for (limb, w) in limbs.iter_mut().zip(wide) {
+            *limb = w as u64;
+        }
+        carry(&mut limbs);
+        Self(limbs)
+    }
+
+    pub(crate) fn square(&self) -> Self {
+        self.mul(self)
+    }
+
+    pub(crate) fn mul_small(&self, k: u32) -> Self {
+        self.mul(&Self([k as u64, 0, 0, 0, 0, 0, 0, 0]))
+    }
+
+    /// Raises this element to the power `exponent`, which is public.
+    fn pow(&self, exponent: &[u8; 56]) -> Self {
+        let mut result = Self::ONE;
+        for byte in exponent.iter().rev() {
+            for bit in (0..8).rev() {
+                result = result.square();
+                if (byte >> bit) & 1 == 1 {
+                    result = result.mul(self);
+                }
+            }
+        }
+        result
+    }
+
+    pub(crate) fn invert(&self) -> Self {
+        self.pow(&P_MINUS_2)
+    }
+
+    pub(crate) fn is_zero(&self) -> Choice {
+        self.to_bytes().ct_eq(&[0u8; 56])
+    }
+
+    pub(crate) fn is_negative(&self) -> Choice {
+        Choice::from(self.to_bytes()[0] & 1)
+    }
+
+    pub(crate) fn ct_eq(&self, other: &Self) -> Choice {
+        self.to_bytes().ct_eq(&other.to_bytes())
+    }
+
+    /// Returns the square root of `u / v`, if it exists.
+    pub(crate) fn sqrt_ratio(u: &Self, v: &Self) -> Option<Self> {
+        // x = u^3 v (u^5 v^3)^((p - 3) / 4), from RFC 8032, section 5.2.3
+        let u2 = u.square();
+        let u3 = u2.mul(u);
+        let u5 = u3.mul(&u2);
+        let v3 = v.square().mul(v);
+        let x = u3.mul(v).mul(&u5.mul(&v3).pow(&P_MINUS_3_DIV_4));
+        if bool::from(v.mul(&x.square()).ct_eq(u)) {
+            Some(x)
+        } else {
+            None
+        }
+    }
+}
+
+fn carry(limbs: &mut [u64; 8]) {
+    for i in 0..7 {
+        limbs[i + 1] += limbs[i] >> 56;
+        limbs[i] &= MASK;
+    }
+    let c = limbs[7] >> 56;
+    limbs[7] &= MASK;
+    limbs[0] += c;
+    limbs[4] += c;
+}
+
+/// Computes the X448 function from RFC 7748, section 5, returning the
+/// u-coordinate of `scalar` times the point with u-coordinate `u`. The scalar
+/// must already be clamped.
+pub(crate) fn x448(scalar: &[u8; 56], u: &[u8; 56]) -> [u8; 56] {
+    const A24: u32 = 39081;
+
+    let x1 = FieldElement::from_bytes(u);
+    let mut x2 = FieldElement::ONE;
+    let mut z2 = FieldElement::ZERO;
+    let mut x3 = x1;
+    let mut z3 = FieldElement::ONE;
+    let mut swap = Choice::from(0);
+
+    for t in (0..448).rev() {
+        let bit = Choice::from((scalar[t / 8] >> (t % 8)) & 1);
+        swap ^= bit;
+        FieldElement::conditional_swap(&mut x2, &mut x3, swap);
+        FieldElement::conditional_swap(&mut z2, &mut z3, swap);
+        swap = bit;
+
+        let a = x2.add(&z2);
+        let aa = a.square();
+        let b = x2.sub(&z2);
+        let bb = b.square();
+        let e = aa.sub(&bb);
+        let c = x3.add(&z3);
+        let d = x3.sub(&z3);
+        let da = d.mul(&a);
+        let cb = c.mul(&b);
+        x3 = da.add(&cb).square();
+        z3 = x1.mul(&da.sub(&cb).square());
+        x2 = aa.mul(&bb);
+        z2 = e.mul(&aa.add(&e.mul_small(A24)));
+    }
+    FieldElement::conditional_swap(&mut x2, &mut x3, swap);
+    FieldElement::conditional_swap(&mut z2, &mut z3, swap);
+
+    let result = x2.mul(&z2.invert()).to_bytes();
+    x2.zeroize();
+    x3.zeroize();
+    z2.zeroize();
+    z3.zeroize();
+    result
+}
+
+/// A point on Edwards448, in projective coordinates.
+#[derive(Clone, Copy)]
+pub(crate) struct EdwardsPoint {
+    x: FieldElement,
+    y: FieldElement,
+    z: FieldElement,
+}
+
+impl ConditionallySelectable for EdwardsPoint {
+    fn conditional_select(a: &Self, b: &Self, choice: Choice) -> Self {
+        Self {
+            x: FieldElement::conditional_select(&a.x, &b.x, choice),
+            y: FieldElement::conditional_select(&a.y, &b.y, choice),
+            z: FieldElement::conditional_select(&a.z, &b.z, choice),
+        }
+    }
+}
+
+impl EdwardsPoint {
+    const D: u32 = 39081;
+    pub(crate) const IDENTITY: Self = Self {
+        x: FieldElement::ZERO,
+        y: FieldElement::ONE,
+        z: FieldElement::ONE,
+    };
+
+    /// Returns the base point B, from RFC 8032, section 5.2.
+    pub(crate) fn base() -> Self {
+        const X: [u8; 56] = [
+            0x5e, 0xc0, 0x0c, 0xc7, 0x2b, 0xa8, 0x26, 0x26, 0x8e, 0x93, 0x00, 0x8b, 0xe1, 0x80,
+            0x3b, 0x43, 0x11, 0x65, 0xb6, 0x2a, 0xf7, 0x1a, 0xae, 0x12, 0x64, 0xa4, 0xd3, 0xa3,
+            0x24, 0xe3, 0x6d, 0xea, 0x67, 0x17, 0x0f, 0x47, 0x70, 0x65, 0x14, 0x9e, 0xda, 0x36,
+            0xbf, 0x22, 0xa6, 0x15, 0x1d, 0x22, 0xed, 0x0d, 0xed, 0x6b, 0xc6, 0x70, 0x19, 0x4f,
+        ];
+        const Y: [u8; 56] = [
+            0x14, 0xfa, 0x30, 0xf2, 0x5b, 0x79, 0x08, 0x98, 0xad, 0xc8, 0xd7, 0x4e, 0x2c, 0x13,
+            0xbd, 0xfd, 0xc4, 0x39, 0x7c, 0xe6, 0x1c, 0xff, 0xd3, 0x3a, 0xd7, 0xc2, 0xa0, 0x05,
+            0x1e, 0x9c, 0x78, 0x87, 0x40, 0x98, 0xa3, 0x6c, 0x73, 0x73, 0xea, 0x4b, 0x62, 0xc7,
+            0xc9, 0x56, 0x37, 0x20, 0x76, 0x88, 0x24, 0xbc, 0xb6, 0x6e, 0x71, 0x46, 0x3f, 0x69,
+        ];
+        Self {
+            x: FieldElement::from_bytes(&X),
+            y: FieldElement::from_bytes(&Y),
+            z: FieldElement::ONE,
+        }
+    }
+
+    pub(crate) fn add(&self, other: &Self) -> Self {
+        let a = self.z.mul(&other.z);
+        let b = a.square();
+        let c = self.x.mul(&other.x);
+        let d = self.y.mul(&other.y);
+        // d = -39081
+        let e = c.mul(&d).mul_small(Self::D).neg();
+        let f = b.sub(&e);
+        let g = b.add(&e);
+        let h = self.x.add(&self.y).mul(&other.x.add(&other.y));
+        Self {
+            x: a.mul(&f).mul(&h.sub(&c).sub(&d)),
+            y: a.mul(&g).mul(&d.sub(&c)),
+            z: f.mul(&g),
+        }
+    }
+
+    pub(crate) fn double(&self) -> Self {
+        let b = self.x.add(&self.y).square();
+        let c = self.x.square();
+        let d = self.y.square();
+        let e = c.add(&d);
+        let h = self.z.square();
+        let j = e.sub(&h.add(&h));
+        Self {
+            x: b.sub(&e).mul(&j),
+            y: e.mul(&c.sub(&d)),
+            z: e.mul(&j),
+        }
+    }
+
+    /// Multiplies this point by the little-endian `scalar`, of up to 448 bits,
+    /// in constant time.
+    pub(crate) fn mul(&self, scalar: &[u8; 57]) -> Self {
+        let mut result = Self::IDENTITY;
+        for t in (0..448).rev() {
+            result = result.double();
+            let sum = result.add(self);
+            let bit = Choice::from((scalar[t / 8] >> (t % 8)) & 1);
+            result.conditional_assign(&sum, bit);
+        }
+        result
+    }
+
+    /// Encodes this point, as described in RFC 8032, section 5.2.2.
+    pub(crate) fn to_bytes(self) -> [u8; 57] {
+        let z_inv = self.z.invert();
+        let x = self.x.mul(&z_inv);
+        let y = self.y.mul(&z_inv);
+        let mut bytes = [0u8; 57];
+        bytes[..56].copy_from_slice(&y.to_bytes());
+        bytes[56] = x.is_negative().unwrap_u8() << 7;
+        bytes
+    }
+
+    /// Decodes a point, as described in RFC 8032, section 5.2.3. Returns
+    /// `None` if the encoding is invalid.
+    pub(crate) fn from_bytes(bytes: &[u8; 57]) -> Option<Self> {
+        if bytes[56] & 0x7f != 0 {
+            return None;
+        }
+        let x_0 = bytes[56] >> 7;
+        let mut y_bytes = [0u8; 56];
+        y_bytes.copy_from_slice(&bytes[..56]);
+        let y = FieldElement::from_bytes(&y_bytes);
+        // reject non-canonical encodings of y
+        if y.to_bytes() != y_bytes {
+            return None;
+        }
+
+        // x^2 = (y^2 - 1) / (d y^2 - 1)
+        let y2 = y.square();
+        let u = y2.sub(&FieldElement::ONE);
+        let v = y2.mul_small(Self::D).neg().sub(&FieldElement::ONE);
+        let mut x = FieldElement::sqrt_ratio(&u, &v)?;
+        let x_is_zero = bool::from(x.is_zero());
+        if x_is_zero && x_0 == 1 {
+            return None;
+        }
+        if x.is_negative().unwrap_u8() != x_0 {
+            x = x.neg();
+        }
+
+        Some(Self {
+            x,
+            y,
+            z: FieldElement::ONE,
+        })
+    }
+
+    pub(crate) fn ct_eq(&self, other: &Self) -> Choice {
+        // compare x1/z1 = x2/z2 and y1/z1 = y2/z2
+        self.x.mul(&other.z).ct_eq(&other.x.mul(&self.z))
+            & self.y.mul(&other.z).ct_eq(&other.y.mul(&self.z))
+    }
+}
+
+/// The order of the prime-order subgroup, L = 2^446 - C, as 64-bit limbs.
+const L: [u64; 7] = [
+    0x2378c292ab5844f3,
+    0x216cc2728dc58f55,
+    0xc44edb49aed63690,
+    0xffffffff7cca23e9,
+    0xffffffffffffffff,
+    0xffffffffffffffff,
+    0x3fffffffffffffff,
+];
+
+/// C = 2^446 - L, as 64-bit limbs.
+const C: [u64; 4] = [
+    0xdc873d6d54a7bb0d,
+    0xde933d8d723a70aa,
+    0x3bb124b65129c96f,
+    0x000000008335dc16,
+];
+
+/// Reduces the little-endian integer in `x` modulo L, returning a 57-byte
+/// little-endian scalar.
+fn reduce(mut x: [u64; 16]) -> [u8; 57] {
+    // fold x = hi * 2^446 + lo into lo + hi * C, until hi is zero
+    for _ in 0..6 {
+        let mut hi = [0u64; 10];
+        for (i, limb) in hi.iter_mut().enumerate() {
+            let next = if 7 + i < 16 { x[7 + i] } else { 0 };
+            *limb = (x[6 + i] >> 62) | (next << 2);
+        }
+        let mut folded = [0u128; 17];
+        for (i, limb) in x.iter().take(7).enumerate() {
+            folded[i] = *limb as u128;
+        }
+        folded[6] &= (1 << 62) - 1;
+        for (i, h) in hi.iter().enumerate() {
+            for (j, c) in C.iter().enumerate() {
+                let product = *h as u128 * *c as u128;
+                folded[i + j] += product & u64::MAX as u128;
+                folded[i + j + 1] += product >> 64;
+            }
+        }
+        for i in 0..16 {
+            folded[i + 1] += folded[i] >> 64;
+            x[i] = folded[i] as u64;
+        }
+    }
+
+    // x < 2^446 < 2L, so subtract L at most once
+    let mut reduced = [0u64; 7];
+    let mut borrow = 0u64;
+    for (i, limb) in reduced.iter_mut().enumerate() {
+        let (t, b1) = x[i].overflowing_sub(L[i]);
+        let (t, b2) = t.overflowing_sub(borrow);
+        borrow = (b1 | b2) as u64;
+        *limb = t;
+    }
+    let keep = Choice::from((borrow ^ 1) as u8);
+    let mut bytes = [0u8; 57];
+    for i in 0..7 {
+        let limb = u64::conditional_select(&x[i], &reduced[i], keep);
+        bytes[8 * i..8 * i + 8].copy_from_slice(&limb.to_le_bytes());
+    }
+    x.zeroize();
+    bytes
+}
+
+fn to_limbs(bytes: &[u8]) -> [u64; 16] {
+    let mut limbs = [0u64; 16];
+    for (i, chunk) in bytes.chunks(8).enumerate() {
+        let mut word = [0u8; 8];
+        word[..chunk.len()].copy_from_slice(chunk);
+        limbs[i] = u64::from_le_bytes(word);
+    }
+    limbs
+}
+
+/// Reduces a little-endian integer of up to 114 bytes modulo L.
+pub(crate) fn scalar_reduce(bytes: &[u8]) -> [u8; 57] {
+    debug_assert!(bytes.len() <= 114);
+    let mut limbs = to_limbs(bytes);
+    let result = reduce(limbs);
+    limbs.zeroize();
+    result
+}
+
+/// Computes (a * b + c) mod L, for little-endian scalars of up to 57 bytes.
+pub(crate) fn scalar_mul_add(a: &[u8; 57], b: &[u8; 57], c: &[u8; 57]) -> [u8; 57] {
+    let mut a_limbs = to_limbs(a);
+    let mut b_limbs = to_limbs(b);
+    let mut wide = [0u128; 17];
+    for (i, limb) in to_limbs(c).iter().enumerate() {
+        wide[i] = *limb as u128;
+    }
+    for i in 0..8 {
+        for j in 0..8 {
+            let product = a_limbs[i] as u128 * b_limbs[j] as u128;
+            wide[i + j] += product & u64::MAX as u128;
+            wide[i + j + 1] += product >> 64;
+        }
+    }
+    let mut x = [0u64; 16];
+    for i in 0..16 {
+        wide[i + 1] += wide[i] >> 64;
+        x[i] = wide[i] as u64;
+    }
+    a_limbs.zeroize();
+    b_limbs.zeroize();
+    wide.zeroize();
+    reduce(x)
+}
+
+/// Returns `true` if the little-endian `scalar` is less than L.
+pub(crate) fn scalar_is_canonical(scalar: &[u8; 57]) -> bool {
+    let limbs = to_limbs(scalar);
+    if limbs[7] != 0 {
+        return false;
+    }
+    for i in (0..7).rev() {
+        if limbs[i] != L[i] {
+            return limbs[i] < L[i];
+        }
+    }
+    false
+}
@@ -82,6 +82,30 @@
 //! assert_eq!(message, decrypted.as_slice());
 //! ```
 //!
+//! ## Borrowed views with [`DryocBoxRef`]
+//!
+//! [`DryocBoxRef`] parses the boundaries of a box, sealed or otherwise, in a
+//! received buffer without copying it, and decrypts into a buffer supplied by
+//! the caller. Refer to
+//! [`DryocSecretBoxRef`](crate::dryocsecretbox::DryocSecretBoxRef) for
+//! details.
+//!
+//! ```
+//! use dryoc::dryocbox::*;
+//!
+//! let recipient_keypair = KeyPair::gen();
+//! let packet = DryocBox::seal_to_vecbox(b"ping", &recipient_keypair.public_key)
+//!     .expect("unable to seal")
+//!     .to_vec();
+//!
+//! let mut buffer = [0u8; 1500];
+//! let view = DryocBoxRef::from_sealed_bytes(&packet).expect("invalid packet");
+//! let len = view
+//!     .unseal_into(&mut buffer, &recipient_keypair)
+//!     .expect("unable to unseal");
+//! assert_eq!(&buffer[..len], b"ping");
+//! ```
+//!
 //! ## Additional resources
 //!
 //! * See <https://libsodium.gitbook.io/doc/public-key_cryptography/authenticated_encryption>
@@ -97,13 +121,20 @@ use serde::{Deserialize, Serialize};
 use subtle::ConstantTimeEq;
 use zeroize::Zeroize;
 
+use crate::canonical::{self, CanonicalDecode, CanonicalEncode};
+use crate::cbor::Value;
 use crate::constants::{
     CRYPTO_BOX_MACBYTES, CRYPTO_BOX_NONCEBYTES, CRYPTO_BOX_PUBLICKEYBYTES, CRYPTO_BOX_SEALBYTES,
     CRYPTO_BOX_SECRETKEYBYTES,
 };
 use crate::error::*;
+use crate::formats::{decode_envelope_of, encode_envelope, Format, Kind};
+use crate::policy::Algorithms;
 pub use crate::types::*;
 
+/// Algorithms used by boxes, checked against the [policy](crate::policy).
+const ALGORITHMS: Algorithms = Algorithms::X25519.union(Algorithms::XSALSA20POLY1305);
+
 /// Stack-allocated public key for authenticated public-key boxes.
 pub type PublicKey = StackByteArray<CRYPTO_BOX_PUBLICKEYBYTES>;
 /// Stack-allocated secret key for authenticated public-key boxes.
@@ -153,8 +184,8 @@ pub mod protected {
     //! .expect("encrypt failed");
     //!
     //! // Decrypt message into locked bytes.
-    //! let decrypted: LockedBytes = dryocbox
-    //!     .decrypt(
+    //! let decrypted = dryocbox
+    //!     .decrypt_to::<LockedBytes>(
     //!         &nonce,
     //!         &sender_keypair.public_key,
     //!         &recipient_keypair.secret_key,
@@ -231,6 +262,8 @@ impl<
     ) -> Result<Self, Error> {
         use crate::classic::crypto_box::crypto_box_detached;
 
+        crate::policy::require(ALGORITHMS)?;
+
         let mut dryocbox = Self {
             ephemeral_pk: None,
             tag: Mac::new_byte_array(),
@@ -268,16 +301,67 @@ impl<
         message: &Message,
         recipient_public_key: &RecipientPublicKey,
     ) -> Result<Self, Error> {
-        use crate::classic::crypto_box::{
-            crypto_box_detached, crypto_box_keypair, crypto_box_seal_nonce,
-        };
+        use crate::classic::crypto_box::crypto_box_keypair;
+
+        let (mut epk, mut esk) = crypto_box_keypair();
+        let dryocbox = Self::seal_with_keys(message, recipient_public_key, &epk, &esk);
+
+        epk.zeroize();
+        esk.zeroize();
+
+        dryocbox
+    }
+
+    /// Identical to [`DryocBox::seal`], except that the ephemeral keypair is
+    /// provided by the caller rather than generated. The sealed box nonce is
+    /// derived from the ephemeral and recipient public keys, so the resulting
+    /// box is fully determined by the inputs, which makes golden-file tests of
+    /// sealed boxes possible. For [`DryocBox::encrypt`], the nonce is already
+    /// provided by the caller.
+    ///
+    /// Don't use this outside of tests: an ephemeral keypair must never be
+    /// used for more than one message.
+    #[cfg(any(test, feature = "deterministic-testing"))]
+    #[cfg_attr(
+        all(feature = "nightly", doc),
+        doc(cfg(feature = "deterministic-testing"))
+    )]
+    pub fn seal_with_ephemeral_keypair<
+        Message: Bytes + ?Sized,
+        RecipientPublicKey: ByteArray<CRYPTO_BOX_PUBLICKEYBYTES>,
+        PublicKey: ByteArray<CRYPTO_BOX_PUBLICKEYBYTES> + Zeroize,
+        SecretKey: ByteArray<CRYPTO_BOX_SECRETKEYBYTES> + Zeroize,
+    >(
+        message: &Message,
+        recipient_public_key: &RecipientPublicKey,
+        ephemeral_keypair: &crate::keypair::KeyPair<PublicKey, SecretKey>,
+    ) -> Result<Self, Error> {
+        Self::seal_with_keys(
+            message,
+            recipient_public_key,
+            ephemeral_keypair.public_key.as_array(),
+            ephemeral_keypair.secret_key.as_array(),
+        )
+    }
+
+    fn seal_with_keys<
+        Message: Bytes + ?Sized,
+        RecipientPublicKey: ByteArray<CRYPTO_BOX_PUBLICKEYBYTES>,
+    >(
+        message: &Message,
+        recipient_public_key: &RecipientPublicKey,
+        epk: &[u8; CRYPTO_BOX_PUBLICKEYBYTES],
+        esk: &[u8; CRYPTO_BOX_SECRETKEYBYTES],
+    ) -> Result<Self, Error> {
+        use crate::classic::crypto_box::{crypto_box_detached, crypto_box_seal_nonce};
+
+        crate::policy::require(ALGORITHMS)?;
 
         let mut nonce = Nonce::new_byte_array();
-        let (epk, esk) = crypto_box_keypair();
-        crypto_box_seal_nonce(nonce.as_mut_array(), &epk, recipient_public_key.as_array());
+        crypto_box_seal_nonce(nonce.as_mut_array(), epk, recipient_public_key.as_array());
 
         let mut pk = EphemeralPublicKey::new_byte_array();
-        pk.copy_from_slice(&epk);
+        pk.copy_from_slice(epk);
 
         let mut dryocbox = Self {
             ephemeral_pk: Some(pk),
@@ -293,7 +377,7 @@ impl<
             message.as_slice(),
             nonce.as_array(),
             recipient_public_key.as_array(),
-            &esk,
+            esk,
         );
 
         Ok(dryocbox)
@@ -351,6 +435,16 @@ impl<
             })
         }
     }
+
+    /// Initializes a [`DryocBox`], sealed or otherwise, from a versioned
+    /// envelope, as produced by [`DryocBox::to_versioned_vec`]. Refer to
+    /// [crate::formats] for details.
+    pub fn from_versioned_bytes(bytes: &'a [u8]) -> Result<Self, Error> {
+        match decode_envelope_of(bytes, &[Kind::Box, Kind::SealedBox])? {
+            (Kind::SealedBox, payload) => Self::from_sealed_bytes(payload),
+            (_, payload) => Self::from_bytes(payload),
+        }
+    }
 }
 
 impl<
@@ -374,12 +468,49 @@ impl<
         self.to_bytes()
     }
 
+    /// Copies `self` into a new [`Vec`], wrapped in a versioned envelope with
+    /// the current [`Format`]. Refer to [crate::formats] for details.
+    pub fn to_versioned_vec(&self) -> Vec<u8> {
+        let kind = match self.ephemeral_pk {
+            Some(_) => Kind::SealedBox,
+            None => Kind::Box,
+        };
+        encode_envelope(Format::CURRENT, kind, &self.to_vec())
+    }
+
     /// Moves the tag, data, and (optional) ephemeral public key out of this
     /// instance, returning them as a tuple.
     pub fn into_parts(self) -> (Mac, Data, Option<EphemeralPublicKey>) {
         (self.tag, self.data, self.ephemeral_pk)
     }
 
+    /// Returns a reference to the message authentication tag.
+    pub fn tag(&self) -> &Mac {
+        &self.tag
+    }
+
+    /// Returns a reference to the ciphertext.
+    pub fn data(&self) -> &Data {
+        &self.data
+    }
+
+    /// Returns a reference to the ephemeral public key, if this is a sealed
+    /// box.
+    pub fn ephemeral_pk(&self) -> Option<&EphemeralPublicKey> {
+        self.ephemeral_pk.as_ref()
+    }
+
+    /// Returns the fields of this box for its canonical encoding.
+    pub(crate) fn canonical_fields(&self) -> Vec<Value> {
+        vec![
+            self.ephemeral_pk
+                .as_ref()
+                .map_or(Value::Null, |epk| Value::Bytes(epk.as_slice().to_vec())),
+            Value::Bytes(self.tag.as_slice().to_vec()),
+            Value::Bytes(self.data.as_slice().to_vec()),
+        ]
+    }
+
     /// Decrypts this box using `nonce`, `recipient_secret_key`, and
     /// `sender_public_key`, returning the decrypted message upon success.
     pub fn decrypt<
@@ -395,6 +526,8 @@ impl<
     ) -> Result<Output, Error> {
         use crate::classic::crypto_box::*;
 
+        crate::policy::require(ALGORITHMS)?;
+
         let mut message = Output::new_bytes();
         message.resize(self.data.as_slice().len(), 0);
 
@@ -410,6 +543,68 @@ impl<
         Ok(message)
     }
 
+    /// Verifies that this box is authentic for `nonce`,
+    /// `sender_public_key`, and `recipient_secret_key`, without decrypting
+    /// it. Nothing is allocated, and the message is never produced. Refer to
+    /// [`DryocSecretBox::verify_only`](crate::dryocsecretbox::DryocSecretBox::verify_only)
+    /// for details.
+    pub fn verify_only<
+        Nonce: ByteArray<CRYPTO_BOX_NONCEBYTES>,
+        SenderPublicKey: ByteArray<CRYPTO_BOX_PUBLICKEYBYTES>,
+        RecipientSecretKey: ByteArray<CRYPTO_BOX_SECRETKEYBYTES>,
+    >(
+        &self,
+        nonce: &Nonce,
+        sender_public_key: &SenderPublicKey,
+        recipient_secret_key: &RecipientSecretKey,
+    ) -> Result<(), Error> {
+        use crate::classic::crypto_box::crypto_box_beforenm;
+
+        crate::policy::require(ALGORITHMS)?;
+        use crate::classic::crypto_secretbox_impl::crypto_secretbox_verify_detached;
+
+        let mut key = crypto_box_beforenm(
+            sender_public_key.as_array(),
+            recipient_secret_key.as_array(),
+        );
+        let result = crypto_secretbox_verify_detached(
+            self.data.as_slice(),
+            self.tag.as_array(),
+            nonce.as_array(),
+            &key,
+        );
+        key.zeroize();
+
+        result
+    }
+
+    /// Decrypts this box like [`DryocBox::decrypt`], then re-encrypts the
+    /// decrypted message with the same nonce and keys, and checks that the
+    /// result matches this box before returning the message.
+    ///
+    /// This is a paranoid mode for long-running jobs, such as verifying
+    /// archives, where silent memory corruption of the keys or message would
+    /// otherwise go unnoticed. It roughly doubles the cost of decryption.
+    pub fn decrypt_self_checked<
+        Nonce: ByteArray<CRYPTO_BOX_NONCEBYTES>,
+        SenderPublicKey: ByteArray<CRYPTO_BOX_PUBLICKEYBYTES>,
+        RecipientSecretKey: ByteArray<CRYPTO_BOX_SECRETKEYBYTES>,
+        Output: ResizableBytes + NewBytes,
+    >(
+        &self,
+        nonce: &Nonce,
+        sender_public_key: &SenderPublicKey,
+        recipient_secret_key: &RecipientSecretKey,
+    ) -> Result<Output, Error> {
+        let message: Output = self.decrypt(nonce, sender_public_key, recipient_secret_key)?;
+        self.self_check(
+            message,
+            nonce.as_array(),
+            sender_public_key.as_array(),
+            recipient_secret_key.as_array(),
+        )
+    }
+
     /// Decrypts this sealed box using `recipient_secret_key`, and
     /// returning the decrypted message upon success.
     pub fn unseal<
@@ -422,6 +617,8 @@ impl<
     ) -> Result<Output, Error> {
         use crate::classic::crypto_box::*;
 
+        crate::policy::require(ALGORITHMS)?;
+
         match &self.ephemeral_pk {
             Some(epk) => {
                 let mut nonce = Nonce::new_byte_array();
@@ -451,6 +648,102 @@ impl<
         }
     }
 
+    /// Decrypts this sealed box like [`DryocBox::unseal`], then re-encrypts
+    /// the decrypted message and checks that the result matches this box
+    /// before returning the message. Refer to
+    /// [`DryocBox::decrypt_self_checked`] for details.
+    pub fn unseal_self_checked<
+        RecipientPublicKey: ByteArray<CRYPTO_BOX_PUBLICKEYBYTES> + Zeroize,
+        RecipientSecretKey: ByteArray<CRYPTO_BOX_SECRETKEYBYTES> + Zeroize,
+        Output: ResizableBytes + NewBytes + Zeroize,
+    >(
+        &self,
+        recipient_keypair: &crate::keypair::KeyPair<RecipientPublicKey, RecipientSecretKey>,
+    ) -> Result<Output, Error> {
+        use crate::classic::crypto_box::crypto_box_seal_nonce;
+
+        let message: Output = self.unseal(recipient_keypair)?;
+        let epk = self
+            .ephemeral_pk
+            .as_ref()
+            .ok_or_else(|| dryoc_error!("ephemeral public key is missing, cannot unseal"))?;
+        let mut nonce = Nonce::new_byte_array();
+        crypto_box_seal_nonce(
+            nonce.as_mut_array(),
+            epk.as_array(),
+            recipient_keypair.public_key.as_array(),
+        );
+
+        // The shared key from the ephemeral public key and the recipient's
+        // secret key is the same one the box was sealed with
+        self.self_check(
+            message,
+            nonce.as_array(),
+            epk.as_array(),
+            recipient_keypair.secret_key.as_array(),
+        )
+    }
+
+    fn self_check<Output: Bytes>(
+        &self,
+        message: Output,
+        nonce: &[u8; CRYPTO_BOX_NONCEBYTES],
+        public_key: &[u8; CRYPTO_BOX_PUBLICKEYBYTES],
+        secret_key: &[u8; CRYPTO_BOX_SECRETKEYBYTES],
+    ) -> Result<Output, Error> {
+        use crate::classic::crypto_box::crypto_box_detached;
+
+        let mut tag = [0u8; CRYPTO_BOX_MACBYTES];
+        let mut data = vec![0u8; message.as_slice().len()];
+        crypto_box_detached(
+            &mut data,
+            &mut tag,
+            message.as_slice(),
+            nonce,
+            public_key,
+            secret_key,
+        );
+
+        if (tag.ct_eq(self.tag.as_slice()) & data.ct_eq(self.data.as_slice())).unwrap_u8() == 0 {
+            return Err(dryoc_error!(
+                "self-check failed, re-encrypted message doesn't match ciphertext"
+            ));
+        }
+
+        Ok(message)
+    }
+
+    /// Decrypts this box using `nonce`, `recipient_secret_key`, and
+    /// `sender_public_key`, returning the decrypted message upon success.
+    ///
+    /// Same as [`DryocBox::decrypt`], except the output container is the only
+    /// type parameter, so it can be selected with a turbofish, i.e.,
+    /// `dryocbox.decrypt_to::<LockedBytes>(..)`.
+    pub fn decrypt_to<Output: ResizableBytes + NewBytes>(
+        &self,
+        nonce: &impl ByteArray<CRYPTO_BOX_NONCEBYTES>,
+        sender_public_key: &impl ByteArray<CRYPTO_BOX_PUBLICKEYBYTES>,
+        recipient_secret_key: &impl ByteArray<CRYPTO_BOX_SECRETKEYBYTES>,
+    ) -> Result<Output, Error> {
+        self.decrypt(nonce, sender_public_key, recipient_secret_key)
+    }
+
+    /// Decrypts this sealed box using `recipient_secret_key`, and
+    /// returning the decrypted message upon success.
+    ///
+    /// Same as [`DryocBox::unseal`], except the output container is the only
+    /// type parameter, so it can be selected with a turbofish, i.e.,
+    /// `dryocbox.unseal_to::<LockedBytes>(..)`.
+    pub fn unseal_to<Output: ResizableBytes + NewBytes + Zeroize>(
+        &self,
+        recipient_keypair: &crate::keypair::KeyPair<
+            impl ByteArray<CRYPTO_BOX_PUBLICKEYBYTES> + Zeroize,
+            impl ByteArray<CRYPTO_BOX_SECRETKEYBYTES> + Zeroize,
+        >,
+    ) -> Result<Output, Error> {
+        self.unseal(recipient_keypair)
+    }
+
     /// Copies `self` into the target. Can be used with protected memory.
     pub fn to_bytes<Bytes: NewBytes + ResizableBytes>(&self) -> Bytes {
         let mut data = Bytes::new_bytes();
@@ -474,6 +767,129 @@ impl<
     }
 }
 
+impl<
+    EphemeralPublicKey: MutByteArray<CRYPTO_BOX_PUBLICKEYBYTES> + Zeroize,
+    Mac: MutByteArray<CRYPTO_BOX_MACBYTES> + Zeroize,
+    Data: MutBytes + Zeroize,
+> DryocBox<EphemeralPublicKey, Mac, Data>
+{
+    /// Re-encrypts this box in place, from the old nonce and keys to the new
+    /// ones, for key rotation of stored boxes. Sealed boxes must be
+    /// re-encrypted with [`DryocBox::reseal`] instead.
+    ///
+    /// The message is decrypted into scratch memory (locked memory, with the
+    /// `nightly` feature), re-encrypted there, and zeroized, so the plaintext
+    /// is never exposed to the caller. If the box can't be decrypted, it's
+    /// left unchanged.
+    #[allow(clippy::too_many_arguments)]
+    pub fn reencrypt<
+        OldNonce: ByteArray<CRYPTO_BOX_NONCEBYTES>,
+        OldSenderPublicKey: ByteArray<CRYPTO_BOX_PUBLICKEYBYTES>,
+        OldRecipientSecretKey: ByteArray<CRYPTO_BOX_SECRETKEYBYTES>,
+        NewNonce: ByteArray<CRYPTO_BOX_NONCEBYTES>,
+        NewRecipientPublicKey: ByteArray<CRYPTO_BOX_PUBLICKEYBYTES>,
+        NewSenderSecretKey: ByteArray<CRYPTO_BOX_SECRETKEYBYTES>,
+    >(
+        &mut self,
+        old_nonce: &OldNonce,
+        old_sender_public_key: &OldSenderPublicKey,
+        old_recipient_secret_key: &OldRecipientSecretKey,
+        new_nonce: &NewNonce,
+        new_recipient_public_key: &NewRecipientPublicKey,
+        new_sender_secret_key: &NewSenderSecretKey,
+    ) -> Result<(), Error> {
+        use crate::classic::crypto_box::{
+            crypto_box_detached_inplace, crypto_box_open_detached_inplace,
+        };
+
+        crate::policy::require(ALGORITHMS)?;
+
+        if self.ephemeral_pk.is_some() {
+            return Err(dryoc_error!("box is sealed, use reseal() to re-encrypt it"));
+        }
+
+        let mut scratch = crate::utils::scratch_copy(self.data.as_slice())?;
+        crypto_box_open_detached_inplace(
+            scratch.as_mut_slice(),
+            self.tag.as_array(),
+            old_nonce.as_array(),
+            old_sender_public_key.as_array(),
+            old_recipient_secret_key.as_array(),
+        )?;
+        crypto_box_detached_inplace(
+            scratch.as_mut_slice(),
+            self.tag.as_mut_array(),
+            new_nonce.as_array(),
+            new_recipient_public_key.as_array(),
+            new_sender_secret_key.as_array(),
+        )?;
+        self.data.as_mut_slice().copy_from_slice(scratch.as_slice());
+
+        Ok(())
+    }
+
+    /// Re-seals this sealed box in place, from `recipient_keypair` to
+    /// `new_recipient_public_key`, with a new ephemeral keypair. The
+    /// plaintext is handled as with [`DryocBox::reencrypt`].
+    pub fn reseal<
+        RecipientPublicKey: ByteArray<CRYPTO_BOX_PUBLICKEYBYTES> + Zeroize,
+        RecipientSecretKey: ByteArray<CRYPTO_BOX_SECRETKEYBYTES> + Zeroize,
+        NewRecipientPublicKey: ByteArray<CRYPTO_BOX_PUBLICKEYBYTES>,
+    >(
+        &mut self,
+        recipient_keypair: &crate::keypair::KeyPair<RecipientPublicKey, RecipientSecretKey>,
+        new_recipient_public_key: &NewRecipientPublicKey,
+    ) -> Result<(), Error> {
+        use crate::classic::crypto_box::{
+            crypto_box_detached_inplace, crypto_box_keypair, crypto_box_open_detached_inplace,
+            crypto_box_seal_nonce,
+        };
+
+        crate::policy::require(ALGORITHMS)?;
+
+        let epk = self
+            .ephemeral_pk
+            .as_mut()
+            .ok_or_else(|| dryoc_error!("ephemeral public key is missing, cannot unseal"))?;
+
+        let mut nonce = Nonce::new_byte_array();
+        crypto_box_seal_nonce(
+            nonce.as_mut_array(),
+            epk.as_array(),
+            recipient_keypair.public_key.as_array(),
+        );
+        let mut scratch = crate::utils::scratch_copy(self.data.as_slice())?;
+        crypto_box_open_detached_inplace(
+            scratch.as_mut_slice(),
+            self.tag.as_array(),
+            nonce.as_array(),
+            epk.as_array(),
+            recipient_keypair.secret_key.as_array(),
+        )?;
+
+        let (new_epk, mut new_esk) = crypto_box_keypair();
+        crypto_box_seal_nonce(
+            nonce.as_mut_array(),
+            &new_epk,
+            new_recipient_public_key.as_array(),
+        );
+        let result = crypto_box_detached_inplace(
+            scratch.as_mut_slice(),
+            self.tag.as_mut_array(),
+            nonce.as_array(),
+            new_recipient_public_key.as_array(),
+            &new_esk,
+        );
+        new_esk.zeroize();
+        result?;
+
+        epk.copy_from_slice(&new_epk);
+        self.data.as_mut_slice().copy_from_slice(scratch.as_slice());
+
+        Ok(())
+    }
+}
+
 impl DryocBox<PublicKey, Mac, Vec<u8>> {
     /// Encrypts a message using `sender_secret_key` for `recipient_public_key`,
     /// and returns a new [DryocBox] with ciphertext and tag.
@@ -590,10 +1006,223 @@ impl<
     }
 }
 
+impl<
+    EphemeralPublicKey: ByteArray<CRYPTO_BOX_PUBLICKEYBYTES> + Zeroize,
+    Mac: ByteArray<CRYPTO_BOX_MACBYTES> + Zeroize,
+    Data: Bytes + Zeroize,
+> CanonicalEncode for DryocBox<EphemeralPublicKey, Mac, Data>
+{
+    fn to_canonical_bytes(&self) -> Vec<u8> {
+        canonical::encode("dryoc/DryocBox", self.canonical_fields())
+    }
+}
+
+impl<
+    EphemeralPublicKey: NewByteArray<CRYPTO_BOX_PUBLICKEYBYTES> + Zeroize,
+    Mac: NewByteArray<CRYPTO_BOX_MACBYTES> + Zeroize,
+    Data: NewBytes + ResizableBytes + Zeroize,
+> DryocBox<EphemeralPublicKey, Mac, Data>
+{
+    /// Returns a new box from the fields of its canonical encoding.
+    pub(crate) fn from_canonical_fields(fields: &[Value]) -> Result<Self, Error> {
+        let ephemeral_pk = match &fields[0] {
+            Value::Null => None,
+            epk => Some(canonical::byte_array_field(epk)?),
+        };
+        Ok(Self {
+            ephemeral_pk,
+            tag: canonical::byte_array_field(&fields[1])?,
+            data: canonical::new_bytes_field(&fields[2])?,
+        })
+    }
+}
+
+impl<
+    EphemeralPublicKey: NewByteArray<CRYPTO_BOX_PUBLICKEYBYTES> + Zeroize,
+    Mac: NewByteArray<CRYPTO_BOX_MACBYTES> + Zeroize,
+    Data: NewBytes + ResizableBytes + Zeroize,
+> CanonicalDecode for DryocBox<EphemeralPublicKey, Mac, Data>
+{
+    fn from_canonical_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        Self::from_canonical_fields(&canonical::decode("dryoc/DryocBox", bytes, 3)?)
+    }
+}
+
+/// A borrowed view of a serialized [`DryocBox`], sealed or otherwise, which
+/// references the ephemeral public key, tag, and ciphertext within a slice,
+/// without owning or copying them.
+///
+/// Refer to [crate::dryocbox] for sample usage.
+#[derive(Clone, Copy, Debug)]
+pub struct DryocBoxRef<'a> {
+    ephemeral_pk: Option<&'a [u8; CRYPTO_BOX_PUBLICKEYBYTES]>,
+    tag: &'a [u8; CRYPTO_BOX_MACBYTES],
+    data: &'a [u8],
+}
+
+impl<'a> DryocBoxRef<'a> {
+    /// Parses a view of a box from `bytes`, in the same layout as
+    /// [`DryocBox::from_bytes`].
+    pub fn from_bytes(bytes: &'a [u8]) -> Result<Self, Error> {
+        let (tag, data) = crate::utils::split_array_ref(bytes, "tag")?;
+        Ok(Self {
+            ephemeral_pk: None,
+            tag,
+            data,
+        })
+    }
+
+    /// Parses a view of a sealed box from `bytes`, in the same layout as
+    /// [`DryocBox::from_sealed_bytes`].
+    pub fn from_sealed_bytes(bytes: &'a [u8]) -> Result<Self, Error> {
+        let (epk, bytes) = crate::utils::split_array_ref(bytes, "ephemeral public key")?;
+        let (tag, data) = crate::utils::split_array_ref(bytes, "tag")?;
+        Ok(Self {
+            ephemeral_pk: Some(epk),
+            tag,
+            data,
+        })
+    }
+
+    /// Parses a nonce, followed by a view of a box, from `bytes`, returning
+    /// both.
+    pub fn from_nonce_prefixed_bytes(
+        bytes: &'a [u8],
+    ) -> Result<(&'a [u8; CRYPTO_BOX_NONCEBYTES], Self), Error> {
+        let (nonce, bytes) = crate::utils::split_array_ref(bytes, "nonce")?;
+        Ok((nonce, Self::from_bytes(bytes)?))
+    }
+
+    /// Returns the ephemeral public key, if this is a sealed box.
+    pub fn ephemeral_pk(&self) -> Option<&'a [u8; CRYPTO_BOX_PUBLICKEYBYTES]> {
+        self.ephemeral_pk
+    }
+
+    /// Returns the message authentication tag.
+    pub fn tag(&self) -> &'a [u8; CRYPTO_BOX_MACBYTES] {
+        self.tag
+    }
+
+    /// Returns the ciphertext.
+    pub fn data(&self) -> &'a [u8] {
+        self.data
+    }
+
+    /// Decrypts this box using `nonce`, `sender_public_key`, and
+    /// `recipient_secret_key` into the start of `output`, returning the length
+    /// of the message. `output` must be at least as long as the ciphertext.
+    pub fn decrypt_into<
+        Nonce: ByteArray<CRYPTO_BOX_NONCEBYTES> + ?Sized,
+        SenderPublicKey: ByteArray<CRYPTO_BOX_PUBLICKEYBYTES>,
+        RecipientSecretKey: ByteArray<CRYPTO_BOX_SECRETKEYBYTES>,
+    >(
+        &self,
+        output: &mut [u8],
+        nonce: &Nonce,
+        sender_public_key: &SenderPublicKey,
+        recipient_secret_key: &RecipientSecretKey,
+    ) -> Result<usize, Error> {
+        use crate::classic::crypto_box::crypto_box_open_detached;
+
+        crate::policy::require(ALGORITHMS)?;
+
+        crypto_box_open_detached(
+            crate::utils::output_prefix(output, self.data.len())?,
+            self.tag,
+            self.data,
+            nonce.as_array(),
+            sender_public_key.as_array(),
+            recipient_secret_key.as_array(),
+        )?;
+
+        Ok(self.data.len())
+    }
+
+    /// Decrypts this sealed box using `recipient_keypair` into the start of
+    /// `output`, returning the length of the message. `output` must be at
+    /// least as long as the ciphertext.
+    pub fn unseal_into<
+        RecipientPublicKey: ByteArray<CRYPTO_BOX_PUBLICKEYBYTES> + Zeroize,
+        RecipientSecretKey: ByteArray<CRYPTO_BOX_SECRETKEYBYTES> + Zeroize,
+    >(
+        &self,
+        output: &mut [u8],
+        recipient_keypair: &crate::keypair::KeyPair<RecipientPublicKey, RecipientSecretKey>,
+    ) -> Result<usize, Error> {
+        use crate::classic::crypto_box::crypto_box_seal_nonce;
+
+        let epk = self
+            .ephemeral_pk
+            .ok_or_else(|| dryoc_error!("ephemeral public key is missing, cannot unseal"))?;
+        let mut nonce = Nonce::new_byte_array();
+        crypto_box_seal_nonce(
+            nonce.as_mut_array(),
+            epk,
+            recipient_keypair.public_key.as_array(),
+        );
+
+        self.decrypt_into(output, &nonce, epk, &recipient_keypair.secret_key)
+    }
+
+    /// Verifies that this box is authentic for `nonce`, `sender_public_key`,
+    /// and `recipient_secret_key`, without decrypting it. Refer to
+    /// [`DryocBox::verify_only`] for details.
+    pub fn verify_only<
+        Nonce: ByteArray<CRYPTO_BOX_NONCEBYTES> + ?Sized,
+        SenderPublicKey: ByteArray<CRYPTO_BOX_PUBLICKEYBYTES>,
+        RecipientSecretKey: ByteArray<CRYPTO_BOX_SECRETKEYBYTES>,
+    >(
+        &self,
+        nonce: &Nonce,
+        sender_public_key: &SenderPublicKey,
+        recipient_secret_key: &RecipientSecretKey,
+    ) -> Result<(), Error> {
+        use crate::classic::crypto_box::crypto_box_beforenm;
+
+        crate::policy::require(ALGORITHMS)?;
+        use crate::classic::crypto_secretbox_impl::crypto_secretbox_verify_detached;
+
+        let mut key = crypto_box_beforenm(
+            sender_public_key.as_array(),
+            recipient_secret_key.as_array(),
+        );
+        let result = crypto_secretbox_verify_detached(self.data, self.tag, nonce.as_array(), &key);
+        key.zeroize();
+
+        result
+    }
+
+    /// Copies this view into a new [`VecBox`].
+    pub fn to_vecbox(&self) -> VecBox {
+        DryocBox::from_parts(
+            Mac::from(*self.tag),
+            self.data.to_vec(),
+            self.ephemeral_pk.map(|epk| PublicKey::from(*epk)),
+        )
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_canonical_box() {
+        let keypair = KeyPair::gen();
+        let sealed = DryocBox::seal_to_vecbox(b"canonical", &keypair.public_key).expect("seal");
+
+        let bytes = sealed.to_canonical_bytes();
+        let decoded = VecBox::from_canonical_bytes(&bytes).expect("decode failed");
+        assert_eq!(decoded.unseal_to_vec(&keypair).expect("unseal"), b"canonical");
+        assert_eq!(decoded.to_canonical_bytes(), bytes);
+
+        let (tag, data, _) = decoded.into_parts();
+        let unsealed: VecBox = DryocBox::from_parts(tag, data, None);
+        let decoded = VecBox::from_canonical_bytes(&unsealed.to_canonical_bytes())
+            .expect("decode failed");
+        assert!(decoded.into_parts().2.is_none());
+    }
+
     #[test]
     fn test_dryocbox_vecbox() {
         for i in 0..20 {
@@ -796,6 +1425,268 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_dryocbox_seal_with_ephemeral_keypair() {
+        use crate::classic::crypto_box::crypto_box_seal_with_ephemeral_keypair;
+
+        let recipient_keypair = KeyPair::from_seed(&[1u8; 32]);
+        let ephemeral_keypair = KeyPair::from_seed(&[2u8; 32]);
+        let message = b"reproducible sealed box";
+
+        let dryocbox: VecBox = DryocBox::seal_with_ephemeral_keypair(
+            message,
+            &recipient_keypair.public_key,
+            &ephemeral_keypair,
+        )
+        .expect("seal failed");
+        let again: VecBox = DryocBox::seal_with_ephemeral_keypair(
+            message,
+            &recipient_keypair.public_key,
+            &ephemeral_keypair,
+        )
+        .expect("seal failed");
+        assert_eq!(dryocbox.to_vec(), again.to_vec());
+
+        let mut ciphertext = vec![0u8; message.len() + CRYPTO_BOX_SEALBYTES];
+        crypto_box_seal_with_ephemeral_keypair(
+            &mut ciphertext,
+            message,
+            recipient_keypair.public_key.as_array(),
+            ephemeral_keypair.public_key.as_array(),
+            ephemeral_keypair.secret_key.as_array(),
+        )
+        .expect("seal failed");
+        assert_eq!(dryocbox.to_vec(), ciphertext);
+        assert_eq!(
+            hex::encode(&ciphertext),
+            "60346e7c911a5f6ba154129174cafe75b294ac3bbd5549632f48cec6266f8410\
+             69bc40e2952e3a81e01de7b6a268479586637fe35ca28b8472101bf56668743441e3959f4fa83c"
+        );
+
+        let m = dryocbox
+            .unseal_to_vec(&recipient_keypair)
+            .expect("unseal failed");
+        assert_eq!(m, message);
+    }
+
+    #[test]
+    fn test_self_checked() {
+        let sender_keypair = KeyPair::gen();
+        let recipient_keypair = KeyPair::gen();
+        let nonce = Nonce::gen();
+        let message = b"hello";
+
+        let dryocbox = DryocBox::encrypt_to_vecbox(
+            message,
+            &nonce,
+            &recipient_keypair.public_key,
+            &sender_keypair.secret_key,
+        )
+        .expect("encrypt failed");
+        let decrypted: Vec<u8> = dryocbox
+            .decrypt_self_checked(
+                &nonce,
+                &sender_keypair.public_key,
+                &recipient_keypair.secret_key,
+            )
+            .expect("decrypt failed");
+        assert_eq!(decrypted, message);
+
+        let sealed =
+            DryocBox::seal_to_vecbox(message, &recipient_keypair.public_key).expect("seal failed");
+        let unsealed: Vec<u8> = sealed
+            .unseal_self_checked(&recipient_keypair)
+            .expect("unseal failed");
+        assert_eq!(unsealed, message);
+
+        // Simulate the decrypted message being corrupted in memory before the
+        // check
+        let corrupted = dryocbox.self_check(
+            b"jello".to_vec(),
+            nonce.as_array(),
+            sender_keypair.public_key.as_array(),
+            recipient_keypair.secret_key.as_array(),
+        );
+        assert!(corrupted.is_err());
+    }
+
+    #[test]
+    fn test_reencrypt() {
+        let sender_keypair = KeyPair::gen();
+        let old_keypair = KeyPair::gen();
+        let new_keypair = KeyPair::gen();
+        let old_nonce = Nonce::gen();
+        let new_nonce = Nonce::gen();
+
+        let mut dryocbox = DryocBox::encrypt_to_vecbox(
+            b"hello",
+            &old_nonce,
+            &old_keypair.public_key,
+            &sender_keypair.secret_key,
+        )
+        .expect("encrypt failed");
+        let original = dryocbox.clone();
+        dryocbox
+            .reencrypt(
+                &old_nonce,
+                &sender_keypair.public_key,
+                &new_keypair.secret_key,
+                &new_nonce,
+                &new_keypair.public_key,
+                &sender_keypair.secret_key,
+            )
+            .expect_err("wrong key");
+        assert_eq!(dryocbox, original);
+
+        dryocbox
+            .reencrypt(
+                &old_nonce,
+                &sender_keypair.public_key,
+                &old_keypair.secret_key,
+                &new_nonce,
+                &new_keypair.public_key,
+                &sender_keypair.secret_key,
+            )
+            .expect("reencrypt failed");
+        let m = dryocbox
+            .decrypt_to_vec(&new_nonce, &sender_keypair.public_key, &new_keypair.secret_key)
+            .expect("decrypt failed");
+        assert_eq!(m, b"hello");
+
+        let mut sealed =
+            DryocBox::seal_to_vecbox(b"hello", &old_keypair.public_key).expect("seal failed");
+        sealed
+            .reencrypt(
+                &old_nonce,
+                &sender_keypair.public_key,
+                &old_keypair.secret_key,
+                &new_nonce,
+                &new_keypair.public_key,
+                &sender_keypair.secret_key,
+            )
+            .expect_err("sealed boxes must be resealed");
+        sealed
+            .reseal(&old_keypair, &new_keypair.public_key)
+            .expect("reseal failed");
+        assert!(sealed.unseal_to_vec(&old_keypair).is_err());
+        let m = sealed.unseal_to_vec(&new_keypair).expect("unseal failed");
+        assert_eq!(m, b"hello");
+        dryocbox
+            .reseal(&old_keypair, &new_keypair.public_key)
+            .expect_err("not sealed");
+    }
+
+    #[test]
+    fn test_box_ref() {
+        let sender_keypair = KeyPair::gen();
+        let recipient_keypair = KeyPair::gen();
+        let nonce = Nonce::gen();
+
+        let dryocbox = DryocBox::encrypt_to_vecbox(
+            b"hello",
+            &nonce,
+            &recipient_keypair.public_key,
+            &sender_keypair.secret_key,
+        )
+        .expect("encrypt failed");
+        let mut packet = nonce.to_vec();
+        packet.extend(dryocbox.to_vec());
+
+        let (parsed_nonce, view) =
+            DryocBoxRef::from_nonce_prefixed_bytes(&packet).expect("parse failed");
+        assert_eq!(parsed_nonce, nonce.as_array());
+        assert!(view.ephemeral_pk().is_none());
+        assert_eq!(view.to_vecbox(), dryocbox);
+        view.verify_only(
+            parsed_nonce,
+            &sender_keypair.public_key,
+            &recipient_keypair.secret_key,
+        )
+        .expect("verify failed");
+
+        let mut output = [0u8; 8];
+        let len = view
+            .decrypt_into(
+                &mut output,
+                parsed_nonce,
+                &sender_keypair.public_key,
+                &recipient_keypair.secret_key,
+            )
+            .expect("decrypt failed");
+        assert_eq!(&output[..len], b"hello");
+        view.decrypt_into(
+            &mut output[..4],
+            parsed_nonce,
+            &sender_keypair.public_key,
+            &recipient_keypair.secret_key,
+        )
+        .expect_err("output too short");
+        view.unseal_into(&mut output, &recipient_keypair)
+            .expect_err("not sealed");
+
+        let sealed = DryocBox::seal_to_vecbox(b"hello", &recipient_keypair.public_key)
+            .expect("seal failed");
+        let bytes = sealed.to_vec();
+        let view = DryocBoxRef::from_sealed_bytes(&bytes).expect("parse failed");
+        assert_eq!(view.to_vecbox(), sealed);
+        assert_eq!(view.tag(), sealed.tag().as_array());
+        assert_eq!(view.data(), sealed.data());
+        assert_eq!(
+            view.ephemeral_pk(),
+            sealed.ephemeral_pk().map(|epk| epk.as_array())
+        );
+        let len = view
+            .unseal_into(&mut output, &recipient_keypair)
+            .expect("unseal failed");
+        assert_eq!(&output[..len], b"hello");
+        view.unseal_into(&mut output, &sender_keypair)
+            .expect_err("wrong keypair");
+
+        DryocBoxRef::from_sealed_bytes(&bytes[..CRYPTO_BOX_SEALBYTES - 1])
+            .expect_err("too short");
+    }
+
+    #[test]
+    fn test_verify_only() {
+        let sender_keypair = KeyPair::gen();
+        let recipient_keypair = KeyPair::gen();
+        let nonce = Nonce::gen();
+
+        let dryocbox = DryocBox::encrypt_to_vecbox(
+            b"hello",
+            &nonce,
+            &recipient_keypair.public_key,
+            &sender_keypair.secret_key,
+        )
+        .expect("encrypt failed");
+        dryocbox
+            .verify_only(
+                &nonce,
+                &sender_keypair.public_key,
+                &recipient_keypair.secret_key,
+            )
+            .expect("verify failed");
+        // either party can verify
+        dryocbox
+            .verify_only(
+                &nonce,
+                &recipient_keypair.public_key,
+                &sender_keypair.secret_key,
+            )
+            .expect("verify failed");
+
+        let (tag, mut data, _) = dryocbox.into_parts();
+        data[0] ^= 1;
+        let tampered = VecBox::from_parts(tag, data, None);
+        tampered
+            .verify_only(
+                &nonce,
+                &sender_keypair.public_key,
+                &recipient_keypair.secret_key,
+            )
+            .expect_err("tampered");
+    }
+
     #[test]
     fn test_dryocbox_unseal_vecbox() {
         for i in 0..20 {
@@ -819,4 +1710,35 @@ mod tests {
             assert_eq!(m, message.as_bytes());
         }
     }
+
+    #[test]
+    fn test_decrypt_to() {
+        let sender_keypair = KeyPair::gen();
+        let recipient_keypair = KeyPair::gen();
+        let nonce = Nonce::gen();
+        let message = b"hello";
+
+        let dryocbox = DryocBox::encrypt_to_vecbox(
+            message,
+            &nonce,
+            &recipient_keypair.public_key,
+            &sender_keypair.secret_key,
+        )
+        .expect("encrypt failed");
+        let decrypted = dryocbox
+            .decrypt_to::<Vec<u8>>(
+                &nonce,
+                &sender_keypair.public_key,
+                &recipient_keypair.secret_key,
+            )
+            .expect("decrypt failed");
+        assert_eq!(decrypted, message);
+
+        let dryocbox = DryocBox::seal_to_vecbox(message, &recipient_keypair.public_key)
+            .expect("seal failed");
+        let unsealed = dryocbox
+            .unseal_to::<Vec<u8>>(&recipient_keypair)
+            .expect("unseal failed");
+        assert_eq!(unsealed, message);
+    }
 }
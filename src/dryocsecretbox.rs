@@ -44,6 +44,62 @@
 //! assert_eq!(message, decrypted.as_slice());
 //! ```
 //!
+//! ## Reusing the key setup with [`SecretBoxCipher`]
+//!
+//! XSalsa20 derives a subkey from the secret key and the first 16 bytes of
+//! the nonce with HSalsa20, then encrypts with Salsa20 using the subkey and
+//! the last 8 bytes of the nonce. When many messages are encrypted under the
+//! same key and nonce prefix, such as a high-rate stream of records with a
+//! per-record counter, [`SecretBoxCipher`] derives the subkey once and reuses
+//! it. Its boxes are identical to those made by [`DryocSecretBox::encrypt`]
+//! with the full nonce.
+//!
+//! ```
+//! use dryoc::dryocsecretbox::*;
+//!
+//! let secret_key = Key::gen();
+//! let nonce_prefix = NoncePrefix::gen();
+//! let cipher = SecretBoxCipher::new(&secret_key, &nonce_prefix);
+//!
+//! for counter in 0u64..4 {
+//!     let nonce_suffix = NonceSuffix::from(counter.to_le_bytes());
+//!     let dryocsecretbox = cipher.encrypt_to_vecbox(b"telemetry", &nonce_suffix);
+//!
+//!     // Equivalent to decrypting with the full nonce
+//!     let decrypted = dryocsecretbox
+//!         .decrypt_to_vec(&cipher.nonce(&nonce_suffix), &secret_key)
+//!         .expect("decrypt failed");
+//!     assert_eq!(decrypted, b"telemetry");
+//! }
+//! ```
+//!
+//! Each suffix must only be used once for a given key and prefix.
+//!
+//! ## Borrowed views with [`DryocSecretBoxRef`]
+//!
+//! For high-throughput packet processing, [`DryocSecretBoxRef`] parses the
+//! boundaries of a box in a received buffer without copying it, and decrypts
+//! into a buffer supplied by the caller.
+//!
+//! ```
+//! use dryoc::dryocsecretbox::*;
+//!
+//! let secret_key = Key::gen();
+//! let nonce = Nonce::gen();
+//!
+//! // A packet with the nonce, followed by the box
+//! let mut packet = nonce.to_vec();
+//! packet.extend(DryocSecretBox::encrypt_to_vecbox(b"ping", &nonce, &secret_key).to_vec());
+//!
+//! let mut buffer = [0u8; 1500];
+//! let (nonce, view) =
+//!     DryocSecretBoxRef::from_nonce_prefixed_bytes(&packet).expect("invalid packet");
+//! let len = view
+//!     .decrypt_into(&mut buffer, nonce, &secret_key)
+//!     .expect("decrypt failed");
+//! assert_eq!(&buffer[..len], b"ping");
+//! ```
+//!
 //! ## Additional resources
 //!
 //! * See <https://libsodium.gitbook.io/doc/secret-key_cryptography/secretbox>
@@ -56,12 +112,17 @@
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 use subtle::ConstantTimeEq;
-use zeroize::Zeroize;
+use zeroize::{Zeroize, ZeroizeOnDrop};
 
+use crate::canonical::{self, CanonicalDecode, CanonicalEncode};
+use crate::cbor::Value;
 use crate::constants::{
-    CRYPTO_SECRETBOX_KEYBYTES, CRYPTO_SECRETBOX_MACBYTES, CRYPTO_SECRETBOX_NONCEBYTES,
+    CRYPTO_CORE_HSALSA20_INPUTBYTES, CRYPTO_CORE_HSALSA20_OUTPUTBYTES, CRYPTO_SECRETBOX_KEYBYTES,
+    CRYPTO_SECRETBOX_MACBYTES, CRYPTO_SECRETBOX_NONCEBYTES, CRYPTO_STREAM_SALSA20_NONCEBYTES,
 };
 use crate::error::Error;
+use crate::formats::{decode_envelope_of, encode_envelope, Format, Kind};
+use crate::policy::Algorithms;
 pub use crate::types::*;
 
 /// Stack-allocated secret for authenticated secret box.
@@ -70,6 +131,12 @@ pub type Key = StackByteArray<CRYPTO_SECRETBOX_KEYBYTES>;
 pub type Nonce = StackByteArray<CRYPTO_SECRETBOX_NONCEBYTES>;
 /// Stack-allocated secret box message authentication code.
 pub type Mac = StackByteArray<CRYPTO_SECRETBOX_MACBYTES>;
+/// Stack-allocated nonce prefix for [`SecretBoxCipher`], the first 16 bytes of
+/// the nonce.
+pub type NoncePrefix = StackByteArray<CRYPTO_CORE_HSALSA20_INPUTBYTES>;
+/// Stack-allocated nonce suffix for [`SecretBoxCipher`], the last 8 bytes of
+/// the nonce.
+pub type NonceSuffix = StackByteArray<CRYPTO_STREAM_SALSA20_NONCEBYTES>;
 
 #[cfg(any(feature = "nightly", all(doc, not(doctest))))]
 #[cfg_attr(all(feature = "nightly", doc), doc(cfg(feature = "nightly")))]
@@ -101,8 +168,8 @@ pub mod protected {
     //! let dryocsecretbox: LockedBox = DryocSecretBox::encrypt(&message, &nonce, &secret_key);
     //!
     //! // Decrypt the message, placing the result into locked memory
-    //! let decrypted: LockedBytes = dryocsecretbox
-    //!     .decrypt(&nonce, &secret_key)
+    //! let decrypted = dryocsecretbox
+    //!     .decrypt_to::<LockedBytes>(&nonce, &secret_key)
     //!     .expect("decrypt failed");
     //!
     //! assert_eq!(message.as_slice(), decrypted.as_slice());
@@ -150,7 +217,9 @@ impl<
 > DryocSecretBox<Mac, Data>
 {
     /// Encrypts a message using `secret_key`, and returns a new
-    /// [DryocSecretBox] with ciphertext and tag
+    /// [DryocSecretBox] with ciphertext and tag. Panics if XSalsa20-Poly1305
+    /// is disabled by the [policy](crate::policy), see
+    /// [`DryocSecretBox::try_encrypt`].
     pub fn encrypt<
         Message: Bytes + ?Sized,
         Nonce: ByteArray<CRYPTO_SECRETBOX_NONCEBYTES>,
@@ -160,8 +229,24 @@ impl<
         nonce: &Nonce,
         secret_key: &SecretKey,
     ) -> Self {
+        Self::try_encrypt(message, nonce, secret_key).unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Same as [`DryocSecretBox::encrypt`], except an error is returned if
+    /// XSalsa20-Poly1305 is disabled by the [policy](crate::policy).
+    pub fn try_encrypt<
+        Message: Bytes + ?Sized,
+        Nonce: ByteArray<CRYPTO_SECRETBOX_NONCEBYTES>,
+        SecretKey: ByteArray<CRYPTO_SECRETBOX_KEYBYTES>,
+    >(
+        message: &Message,
+        nonce: &Nonce,
+        secret_key: &SecretKey,
+    ) -> Result<Self, Error> {
         use crate::classic::crypto_secretbox::crypto_secretbox_detached;
 
+        crate::policy::require(Algorithms::XSALSA20POLY1305)?;
+
         let mut new = Self {
             tag: Mac::new_byte_array(),
             data: Data::new_bytes(),
@@ -176,7 +261,7 @@ impl<
             secret_key.as_array(),
         );
 
-        new
+        Ok(new)
     }
 }
 
@@ -205,6 +290,14 @@ impl<
             })
         }
     }
+
+    /// Initializes a [`DryocSecretBox`] from a versioned envelope, as produced
+    /// by [`DryocSecretBox::to_versioned_vec`]. Refer to [crate::formats] for
+    /// details.
+    pub fn from_versioned_bytes(bytes: &'a [u8]) -> Result<Self, Error> {
+        let (_kind, payload) = decode_envelope_of(bytes, &[Kind::SecretBox])?;
+        Self::from_bytes(payload)
+    }
 }
 
 impl<Mac: ByteArray<CRYPTO_SECRETBOX_MACBYTES> + Zeroize, Data: Bytes + Zeroize>
@@ -220,10 +313,26 @@ impl<Mac: ByteArray<CRYPTO_SECRETBOX_MACBYTES> + Zeroize, Data: Bytes + Zeroize>
         self.to_bytes()
     }
 
+    /// Copies `self` into a new [`Vec`], wrapped in a versioned envelope with
+    /// the current [`Format`]. Refer to [crate::formats] for details.
+    pub fn to_versioned_vec(&self) -> Vec<u8> {
+        encode_envelope(Format::CURRENT, Kind::SecretBox, &self.to_vec())
+    }
+
     /// Moves the tag and data out of this instance, returning them as a tuple.
     pub fn into_parts(self) -> (Mac, Data) {
         (self.tag, self.data)
     }
+
+    /// Returns a reference to the message authentication tag.
+    pub fn tag(&self) -> &Mac {
+        &self.tag
+    }
+
+    /// Returns a reference to the ciphertext.
+    pub fn data(&self) -> &Data {
+        &self.data
+    }
 }
 
 impl<Mac: ByteArray<CRYPTO_SECRETBOX_MACBYTES> + Zeroize, Data: Bytes + Zeroize>
@@ -242,6 +351,8 @@ impl<Mac: ByteArray<CRYPTO_SECRETBOX_MACBYTES> + Zeroize, Data: Bytes + Zeroize>
     ) -> Result<Output, Error> {
         use crate::classic::crypto_secretbox::crypto_secretbox_open_detached;
 
+        crate::policy::require(Algorithms::XSALSA20POLY1305)?;
+
         let mut message = Output::new_bytes();
         message.resize(self.data.as_slice().len(), 0);
 
@@ -256,6 +367,85 @@ impl<Mac: ByteArray<CRYPTO_SECRETBOX_MACBYTES> + Zeroize, Data: Bytes + Zeroize>
         Ok(message)
     }
 
+    /// Verifies that this box is authentic for `nonce` and `secret_key`,
+    /// without decrypting it. Nothing is allocated, and the message is never
+    /// produced, so this is suitable for relays which must reject forged
+    /// messages, but don't need to read them.
+    ///
+    /// The tag is compared in constant time.
+    pub fn verify_only<
+        Nonce: ByteArray<CRYPTO_SECRETBOX_NONCEBYTES>,
+        SecretKey: ByteArray<CRYPTO_SECRETBOX_KEYBYTES>,
+    >(
+        &self,
+        nonce: &Nonce,
+        secret_key: &SecretKey,
+    ) -> Result<(), Error> {
+        use crate::classic::crypto_secretbox_impl::crypto_secretbox_verify_detached;
+
+        crate::policy::require(Algorithms::XSALSA20POLY1305)?;
+
+        crypto_secretbox_verify_detached(
+            self.data.as_slice(),
+            self.tag.as_array(),
+            nonce.as_array(),
+            secret_key.as_array(),
+        )
+    }
+
+    /// Decrypts this box like [`DryocSecretBox::decrypt`], then re-encrypts
+    /// the decrypted message with the same nonce and key, and checks that the
+    /// result matches this box before returning the message.
+    ///
+    /// This is a paranoid mode for long-running jobs, such as verifying
+    /// archives, where silent memory corruption of the key or message would
+    /// otherwise go unnoticed. It roughly doubles the cost of decryption.
+    pub fn decrypt_self_checked<
+        Output: ResizableBytes + NewBytes,
+        Nonce: ByteArray<CRYPTO_SECRETBOX_NONCEBYTES>,
+        SecretKey: ByteArray<CRYPTO_SECRETBOX_KEYBYTES>,
+    >(
+        &self,
+        nonce: &Nonce,
+        secret_key: &SecretKey,
+    ) -> Result<Output, Error> {
+        use crate::classic::crypto_secretbox::crypto_secretbox_detached;
+
+        let message: Output = self.decrypt(nonce, secret_key)?;
+
+        let mut tag = [0u8; CRYPTO_SECRETBOX_MACBYTES];
+        let mut data = vec![0u8; message.as_slice().len()];
+        crypto_secretbox_detached(
+            &mut data,
+            &mut tag,
+            message.as_slice(),
+            nonce.as_array(),
+            secret_key.as_array(),
+        );
+
+        if (tag.ct_eq(self.tag.as_slice()) & data.ct_eq(self.data.as_slice())).unwrap_u8() == 0 {
+            return Err(dryoc_error!(
+                "self-check failed, re-encrypted message doesn't match ciphertext"
+            ));
+        }
+
+        Ok(message)
+    }
+
+    /// Decrypts `ciphertext` using `secret_key`, returning a new
+    /// [DryocSecretBox] with decrypted message
+    ///
+    /// Same as [`DryocSecretBox::decrypt`], except the output container is the
+    /// only type parameter, so it can be selected with a turbofish, i.e.,
+    /// `dryocsecretbox.decrypt_to::<LockedBytes>(..)`.
+    pub fn decrypt_to<Output: ResizableBytes + NewBytes>(
+        &self,
+        nonce: &impl ByteArray<CRYPTO_SECRETBOX_NONCEBYTES>,
+        secret_key: &impl ByteArray<CRYPTO_SECRETBOX_KEYBYTES>,
+    ) -> Result<Output, Error> {
+        self.decrypt(nonce, secret_key)
+    }
+
     /// Copies `self` into the target. Can be used with protected memory.
     pub fn to_bytes<Bytes: NewBytes + ResizableBytes>(&self) -> Bytes {
         let mut data = Bytes::new_bytes();
@@ -267,6 +457,53 @@ impl<Mac: ByteArray<CRYPTO_SECRETBOX_MACBYTES> + Zeroize, Data: Bytes + Zeroize>
     }
 }
 
+impl<Mac: MutByteArray<CRYPTO_SECRETBOX_MACBYTES> + Zeroize, Data: MutBytes + Zeroize>
+    DryocSecretBox<Mac, Data>
+{
+    /// Re-encrypts this box in place, from `old_nonce` and `old_key` to
+    /// `new_nonce` and `new_key`, for key rotation of stored boxes.
+    ///
+    /// The message is decrypted into scratch memory (locked memory, with the
+    /// `nightly` feature), re-encrypted there, and zeroized, so the plaintext
+    /// is never exposed to the caller. If the box can't be decrypted, it's
+    /// left unchanged.
+    pub fn reencrypt<
+        OldNonce: ByteArray<CRYPTO_SECRETBOX_NONCEBYTES>,
+        OldKey: ByteArray<CRYPTO_SECRETBOX_KEYBYTES>,
+        NewNonce: ByteArray<CRYPTO_SECRETBOX_NONCEBYTES>,
+        NewKey: ByteArray<CRYPTO_SECRETBOX_KEYBYTES>,
+    >(
+        &mut self,
+        old_nonce: &OldNonce,
+        old_key: &OldKey,
+        new_nonce: &NewNonce,
+        new_key: &NewKey,
+    ) -> Result<(), Error> {
+        use crate::classic::crypto_secretbox_impl::{
+            crypto_secretbox_detached_inplace, crypto_secretbox_open_detached_inplace,
+        };
+
+        crate::policy::require(Algorithms::XSALSA20POLY1305)?;
+
+        let mut scratch = crate::utils::scratch_copy(self.data.as_slice())?;
+        crypto_secretbox_open_detached_inplace(
+            scratch.as_mut_slice(),
+            self.tag.as_array(),
+            old_nonce.as_array(),
+            old_key.as_array(),
+        )?;
+        crypto_secretbox_detached_inplace(
+            scratch.as_mut_slice(),
+            self.tag.as_mut_array(),
+            new_nonce.as_array(),
+            new_key.as_array(),
+        );
+        self.data.as_mut_slice().copy_from_slice(scratch.as_slice());
+
+        Ok(())
+    }
+}
+
 impl DryocSecretBox<Mac, Vec<u8>> {
     /// Encrypts a message using `secret_key`, and returns a new
     /// [DryocSecretBox] with ciphertext and tag
@@ -282,6 +519,21 @@ impl DryocSecretBox<Mac, Vec<u8>> {
         Self::encrypt(message, nonce, secret_key)
     }
 
+    /// Same as [`DryocSecretBox::encrypt_to_vecbox`], except an error is
+    /// returned if XSalsa20-Poly1305 is disabled by the
+    /// [policy](crate::policy).
+    pub fn try_encrypt_to_vecbox<
+        Message: Bytes + ?Sized,
+        Nonce: ByteArray<CRYPTO_SECRETBOX_NONCEBYTES>,
+        SecretKey: ByteArray<CRYPTO_SECRETBOX_KEYBYTES>,
+    >(
+        message: &Message,
+        nonce: &Nonce,
+        secret_key: &SecretKey,
+    ) -> Result<Self, Error> {
+        Self::try_encrypt(message, nonce, secret_key)
+    }
+
     /// Decrypts `ciphertext` using `secret_key`, returning a new
     /// [DryocSecretBox] with decrypted message
     pub fn decrypt_to_vec<
@@ -350,10 +602,311 @@ impl<Mac: ByteArray<CRYPTO_SECRETBOX_MACBYTES> + Zeroize, Data: Bytes + Zeroize>
     }
 }
 
+/// A borrowed view of a serialized [`DryocSecretBox`], which references the
+/// tag and ciphertext within a slice, without owning or copying them.
+///
+/// Refer to [crate::dryocsecretbox] for sample usage.
+#[derive(Clone, Copy, Debug)]
+pub struct DryocSecretBoxRef<'a> {
+    tag: &'a [u8; CRYPTO_SECRETBOX_MACBYTES],
+    data: &'a [u8],
+}
+
+impl<'a> DryocSecretBoxRef<'a> {
+    /// Parses a view of a box from `bytes`, in the same layout as
+    /// [`DryocSecretBox::from_bytes`].
+    pub fn from_bytes(bytes: &'a [u8]) -> Result<Self, Error> {
+        let (tag, data) = crate::utils::split_array_ref(bytes, "tag")?;
+        Ok(Self { tag, data })
+    }
+
+    /// Parses a nonce, followed by a view of a box, from `bytes`, returning
+    /// both.
+    pub fn from_nonce_prefixed_bytes(
+        bytes: &'a [u8],
+    ) -> Result<(&'a [u8; CRYPTO_SECRETBOX_NONCEBYTES], Self), Error> {
+        let (nonce, bytes) = crate::utils::split_array_ref(bytes, "nonce")?;
+        Ok((nonce, Self::from_bytes(bytes)?))
+    }
+
+    /// Returns the message authentication tag.
+    pub fn tag(&self) -> &'a [u8; CRYPTO_SECRETBOX_MACBYTES] {
+        self.tag
+    }
+
+    /// Returns the ciphertext.
+    pub fn data(&self) -> &'a [u8] {
+        self.data
+    }
+
+    /// Decrypts this box using `nonce` and `secret_key` into the start of
+    /// `output`, returning the length of the message. `output` must be at
+    /// least as long as the ciphertext.
+    pub fn decrypt_into<
+        Nonce: ByteArray<CRYPTO_SECRETBOX_NONCEBYTES> + ?Sized,
+        SecretKey: ByteArray<CRYPTO_SECRETBOX_KEYBYTES>,
+    >(
+        &self,
+        output: &mut [u8],
+        nonce: &Nonce,
+        secret_key: &SecretKey,
+    ) -> Result<usize, Error> {
+        use crate::classic::crypto_secretbox::crypto_secretbox_open_detached;
+
+        crate::policy::require(Algorithms::XSALSA20POLY1305)?;
+
+        crypto_secretbox_open_detached(
+            crate::utils::output_prefix(output, self.data.len())?,
+            self.tag,
+            self.data,
+            nonce.as_array(),
+            secret_key.as_array(),
+        )?;
+
+        Ok(self.data.len())
+    }
+
+    /// Verifies that this box is authentic for `nonce` and `secret_key`,
+    /// without decrypting it. Refer to [`DryocSecretBox::verify_only`] for
+    /// details.
+    pub fn verify_only<
+        Nonce: ByteArray<CRYPTO_SECRETBOX_NONCEBYTES> + ?Sized,
+        SecretKey: ByteArray<CRYPTO_SECRETBOX_KEYBYTES>,
+    >(
+        &self,
+        nonce: &Nonce,
+        secret_key: &SecretKey,
+    ) -> Result<(), Error> {
+        use crate::classic::crypto_secretbox_impl::crypto_secretbox_verify_detached;
+
+        crate::policy::require(Algorithms::XSALSA20POLY1305)?;
+
+        crypto_secretbox_verify_detached(
+            self.data,
+            self.tag,
+            nonce.as_array(),
+            secret_key.as_array(),
+        )
+    }
+
+    /// Copies this view into a new [`VecBox`].
+    pub fn to_vecbox(&self) -> VecBox {
+        DryocSecretBox::from_parts(Mac::from(*self.tag), self.data.to_vec())
+    }
+}
+
+/// Secret box cipher with the HSalsa20 subkey for a key and nonce prefix
+/// precomputed, for encrypting many messages with nonces that share the same
+/// first 16 bytes.
+///
+/// Refer to [crate::dryocsecretbox] for sample usage.
+#[derive(Zeroize, ZeroizeOnDrop, Clone)]
+pub struct SecretBoxCipher {
+    subkey: [u8; CRYPTO_CORE_HSALSA20_OUTPUTBYTES],
+    nonce_prefix: [u8; CRYPTO_CORE_HSALSA20_INPUTBYTES],
+}
+
+impl SecretBoxCipher {
+    /// Returns a new cipher for `secret_key` and `nonce_prefix`, deriving the
+    /// subkey. Panics if XSalsa20-Poly1305 is disabled by the
+    /// [policy](crate::policy), see [`SecretBoxCipher::try_new`].
+    pub fn new<
+        SecretKey: ByteArray<CRYPTO_SECRETBOX_KEYBYTES>,
+        Prefix: ByteArray<CRYPTO_CORE_HSALSA20_INPUTBYTES>,
+    >(
+        secret_key: &SecretKey,
+        nonce_prefix: &Prefix,
+    ) -> Self {
+        Self::try_new(secret_key, nonce_prefix).unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Same as [`SecretBoxCipher::new`], except an error is returned if
+    /// XSalsa20-Poly1305 is disabled by the [policy](crate::policy).
+    pub fn try_new<
+        SecretKey: ByteArray<CRYPTO_SECRETBOX_KEYBYTES>,
+        Prefix: ByteArray<CRYPTO_CORE_HSALSA20_INPUTBYTES>,
+    >(
+        secret_key: &SecretKey,
+        nonce_prefix: &Prefix,
+    ) -> Result<Self, Error> {
+        use crate::classic::crypto_core::crypto_core_hsalsa20;
+
+        crate::policy::require(Algorithms::XSALSA20POLY1305)?;
+
+        let mut subkey = [0u8; CRYPTO_CORE_HSALSA20_OUTPUTBYTES];
+        crypto_core_hsalsa20(
+            &mut subkey,
+            nonce_prefix.as_array(),
+            secret_key.as_array(),
+            None,
+        );
+        Ok(Self {
+            subkey,
+            nonce_prefix: *nonce_prefix.as_array(),
+        })
+    }
+
+    /// Returns the full nonce for `nonce_suffix`, which can be used with
+    /// [`DryocSecretBox::decrypt`] and the secret key.
+    pub fn nonce<Suffix: ByteArray<CRYPTO_STREAM_SALSA20_NONCEBYTES>>(
+        &self,
+        nonce_suffix: &Suffix,
+    ) -> Nonce {
+        let mut nonce = Nonce::new_byte_array();
+        nonce[..CRYPTO_CORE_HSALSA20_INPUTBYTES].copy_from_slice(&self.nonce_prefix);
+        nonce[CRYPTO_CORE_HSALSA20_INPUTBYTES..].copy_from_slice(nonce_suffix.as_slice());
+        nonce
+    }
+
+    /// Encrypts `message` with the nonce made from this cipher's prefix and
+    /// `nonce_suffix`, returning a new [`DryocSecretBox`].
+    pub fn encrypt<
+        Message: Bytes + ?Sized,
+        Suffix: ByteArray<CRYPTO_STREAM_SALSA20_NONCEBYTES>,
+        Mac: NewByteArray<CRYPTO_SECRETBOX_MACBYTES> + Zeroize,
+        Data: NewBytes + ResizableBytes + Zeroize,
+    >(
+        &self,
+        message: &Message,
+        nonce_suffix: &Suffix,
+    ) -> DryocSecretBox<Mac, Data> {
+        use crate::classic::crypto_secretbox_impl::crypto_secretbox_detached_inplace_with_subkey;
+
+        let mut tag = Mac::new_byte_array();
+        let mut data = Data::new_bytes();
+        data.resize(message.len(), 0);
+        data.as_mut_slice().copy_from_slice(message.as_slice());
+
+        crypto_secretbox_detached_inplace_with_subkey(
+            data.as_mut_slice(),
+            tag.as_mut_array(),
+            nonce_suffix.as_array(),
+            &self.subkey,
+        );
+
+        DryocSecretBox { tag, data }
+    }
+
+    /// Encrypts `message` like [`SecretBoxCipher::encrypt`], returning a
+    /// [`VecBox`]. Provided for convenience.
+    pub fn encrypt_to_vecbox<
+        Message: Bytes + ?Sized,
+        Suffix: ByteArray<CRYPTO_STREAM_SALSA20_NONCEBYTES>,
+    >(
+        &self,
+        message: &Message,
+        nonce_suffix: &Suffix,
+    ) -> VecBox {
+        self.encrypt(message, nonce_suffix)
+    }
+
+    /// Decrypts `dryocsecretbox` with the nonce made from this cipher's prefix
+    /// and `nonce_suffix`, returning the decrypted message.
+    pub fn decrypt<
+        Output: ResizableBytes + NewBytes,
+        Suffix: ByteArray<CRYPTO_STREAM_SALSA20_NONCEBYTES>,
+        Mac: ByteArray<CRYPTO_SECRETBOX_MACBYTES> + Zeroize,
+        Data: Bytes + Zeroize,
+    >(
+        &self,
+        dryocsecretbox: &DryocSecretBox<Mac, Data>,
+        nonce_suffix: &Suffix,
+    ) -> Result<Output, Error> {
+        use crate::classic::crypto_secretbox_impl::crypto_secretbox_open_detached_inplace_with_subkey;
+
+        crate::policy::require(Algorithms::XSALSA20POLY1305)?;
+
+        let mut message = Output::new_bytes();
+        message.resize(dryocsecretbox.data.len(), 0);
+        message
+            .as_mut_slice()
+            .copy_from_slice(dryocsecretbox.data.as_slice());
+
+        crypto_secretbox_open_detached_inplace_with_subkey(
+            message.as_mut_slice(),
+            dryocsecretbox.tag.as_array(),
+            nonce_suffix.as_array(),
+            &self.subkey,
+        )?;
+
+        Ok(message)
+    }
+
+    /// Decrypts `dryocsecretbox` like [`SecretBoxCipher::decrypt`], returning
+    /// the message as a [`Vec`]. Provided for convenience.
+    pub fn decrypt_to_vec<
+        Suffix: ByteArray<CRYPTO_STREAM_SALSA20_NONCEBYTES>,
+        Mac: ByteArray<CRYPTO_SECRETBOX_MACBYTES> + Zeroize,
+        Data: Bytes + Zeroize,
+    >(
+        &self,
+        dryocsecretbox: &DryocSecretBox<Mac, Data>,
+        nonce_suffix: &Suffix,
+    ) -> Result<Vec<u8>, Error> {
+        self.decrypt(dryocsecretbox, nonce_suffix)
+    }
+}
+
+impl<Mac: ByteArray<CRYPTO_SECRETBOX_MACBYTES> + Zeroize, Data: Bytes + Zeroize> CanonicalEncode
+    for DryocSecretBox<Mac, Data>
+{
+    fn to_canonical_bytes(&self) -> Vec<u8> {
+        canonical::encode(
+            "dryoc/DryocSecretBox",
+            vec![
+                Value::Bytes(self.tag.as_slice().to_vec()),
+                Value::Bytes(self.data.as_slice().to_vec()),
+            ],
+        )
+    }
+}
+
+impl<
+    Mac: NewByteArray<CRYPTO_SECRETBOX_MACBYTES> + Zeroize,
+    Data: NewBytes + ResizableBytes + Zeroize,
+> CanonicalDecode for DryocSecretBox<Mac, Data>
+{
+    fn from_canonical_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        let fields = canonical::decode("dryoc/DryocSecretBox", bytes, 2)?;
+        Ok(Self {
+            tag: canonical::byte_array_field(&fields[0])?,
+            data: canonical::new_bytes_field(&fields[1])?,
+        })
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_secretbox_cipher() {
+        let secret_key = Key::gen();
+        let nonce_prefix = NoncePrefix::gen();
+        let cipher = SecretBoxCipher::new(&secret_key, &nonce_prefix);
+
+        for i in 0..20usize {
+            let message = vec![i as u8; i * 7];
+            let nonce_suffix = NonceSuffix::from((i as u64).to_le_bytes());
+            let nonce = cipher.nonce(&nonce_suffix);
+            assert_eq!(&nonce[..16], nonce_prefix.as_slice());
+
+            let dryocsecretbox = cipher.encrypt_to_vecbox(&message, &nonce_suffix);
+            let expected: VecBox = DryocSecretBox::encrypt(&message, &nonce, &secret_key);
+            assert_eq!(dryocsecretbox.to_vec(), expected.to_vec());
+
+            let decrypted = cipher
+                .decrypt_to_vec(&expected, &nonce_suffix)
+                .expect("decrypt failed");
+            assert_eq!(decrypted, message);
+
+            let wrong_suffix = NonceSuffix::from((i as u64 + 1).to_le_bytes());
+            cipher
+                .decrypt_to_vec(&expected, &wrong_suffix)
+                .expect_err("decrypt should fail");
+        }
+    }
+
     #[test]
     fn test_dryocbox() {
         for i in 0..20 {
@@ -404,6 +957,112 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_decrypt_self_checked() {
+        let secret_key = Key::gen();
+        let nonce = Nonce::gen();
+        let dryocsecretbox: VecBox = DryocSecretBox::encrypt(b"hello", &nonce, &secret_key);
+
+        let m: Vec<u8> = dryocsecretbox
+            .decrypt_self_checked(&nonce, &secret_key)
+            .expect("decrypt failed");
+        assert_eq!(m, b"hello");
+        assert!(
+            dryocsecretbox
+                .decrypt_self_checked::<Vec<u8>, _, _>(&Nonce::gen(), &secret_key)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_reencrypt() {
+        let old_key = Key::gen();
+        let old_nonce = Nonce::gen();
+        let new_key = Key::gen();
+        let new_nonce = Nonce::gen();
+        let mut dryocsecretbox: VecBox = DryocSecretBox::encrypt(b"hello", &old_nonce, &old_key);
+        let original = dryocsecretbox.clone();
+
+        dryocsecretbox
+            .reencrypt(&new_nonce, &old_key, &new_nonce, &new_key)
+            .expect_err("wrong nonce");
+        assert_eq!(dryocsecretbox, original);
+
+        dryocsecretbox
+            .reencrypt(&old_nonce, &old_key, &new_nonce, &new_key)
+            .expect("reencrypt failed");
+        assert!(dryocsecretbox.decrypt_to_vec(&old_nonce, &old_key).is_err());
+        let m = dryocsecretbox
+            .decrypt_to_vec(&new_nonce, &new_key)
+            .expect("decrypt failed");
+        assert_eq!(m, b"hello");
+    }
+
+    #[test]
+    fn test_box_ref() {
+        let key = Key::gen();
+        let nonce = Nonce::gen();
+        let dryocsecretbox = DryocSecretBox::encrypt_to_vecbox(b"hello", &nonce, &key);
+        let bytes = dryocsecretbox.to_vec();
+
+        let view = DryocSecretBoxRef::from_bytes(&bytes).expect("parse failed");
+        assert_eq!(
+            view.data().as_ptr(),
+            bytes[CRYPTO_SECRETBOX_MACBYTES..].as_ptr()
+        );
+        assert_eq!(view.to_vecbox(), dryocsecretbox);
+        assert_eq!(view.tag(), dryocsecretbox.tag().as_array());
+        assert_eq!(view.data(), dryocsecretbox.data());
+        view.verify_only(&nonce, &key).expect("verify failed");
+
+        let mut output = [0u8; 8];
+        let len = view
+            .decrypt_into(&mut output, &nonce, &key)
+            .expect("decrypt failed");
+        assert_eq!(&output[..len], b"hello");
+        view.decrypt_into(&mut output[..4], &nonce, &key)
+            .expect_err("output too short");
+        view.decrypt_into(&mut output, &Nonce::gen(), &key)
+            .expect_err("wrong nonce");
+
+        let mut packet = nonce.to_vec();
+        packet.extend_from_slice(&bytes);
+        let (parsed_nonce, view) =
+            DryocSecretBoxRef::from_nonce_prefixed_bytes(&packet).expect("parse failed");
+        assert_eq!(parsed_nonce, nonce.as_array());
+        view.verify_only(parsed_nonce, &key).expect("verify failed");
+
+        DryocSecretBoxRef::from_bytes(&bytes[..CRYPTO_SECRETBOX_MACBYTES - 1])
+            .expect_err("too short");
+        DryocSecretBoxRef::from_nonce_prefixed_bytes(&packet[..CRYPTO_SECRETBOX_NONCEBYTES])
+            .expect_err("too short");
+    }
+
+    #[test]
+    fn test_verify_only() {
+        let secret_key = Key::gen();
+        let nonce = Nonce::gen();
+        let dryocsecretbox: VecBox = DryocSecretBox::encrypt(b"hello", &nonce, &secret_key);
+
+        dryocsecretbox
+            .verify_only(&nonce, &secret_key)
+            .expect("verify failed");
+        dryocsecretbox
+            .verify_only(&Nonce::gen(), &secret_key)
+            .expect_err("wrong nonce");
+        dryocsecretbox
+            .verify_only(&nonce, &Key::gen())
+            .expect_err("wrong key");
+
+        let mut bytes = dryocsecretbox.to_vec();
+        let last = bytes.len() - 1;
+        bytes[last] ^= 1;
+        VecBox::from_bytes(&bytes)
+            .expect("parse failed")
+            .verify_only(&nonce, &secret_key)
+            .expect_err("tampered");
+    }
+
     #[test]
     fn test_dryocbox_vec() {
         for i in 0..20 {
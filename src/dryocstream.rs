@@ -67,6 +67,10 @@
 //! * For public-key based encryption, see [`DryocBox`](crate::dryocbox)
 //! * For secret-key based encryption, see
 //!   [`DryocSecretBox`](crate::dryocsecretbox)
+//! * For streams signed by the sender, which the receiver can prove the origin
+//!   of, see [`SignedPushStream`]
+//! * To run several streams over a single [`Kx`](crate::kx) session, see
+//!   [`StreamMux`]
 //! * See the [protected] mod for an example using the protected memory features
 //!   with [`DryocStream`]
 
@@ -78,14 +82,23 @@ use crate::classic::crypto_secretstream_xchacha20poly1305::{
     crypto_secretstream_xchacha20poly1305_init_push, crypto_secretstream_xchacha20poly1305_pull,
     crypto_secretstream_xchacha20poly1305_push, crypto_secretstream_xchacha20poly1305_rekey, State,
 };
+use crate::classic::crypto_sign::{crypto_sign_detached, crypto_sign_verify_detached};
 use crate::constants::{
+    CRYPTO_GENERICHASH_BYTES, CRYPTO_GENERICHASH_BYTES_MAX, CRYPTO_GENERICHASH_KEYBYTES,
+    CRYPTO_KDF_CONTEXTBYTES, CRYPTO_KX_SESSIONKEYBYTES,
     CRYPTO_SECRETSTREAM_XCHACHA20POLY1305_HEADERBYTES,
     CRYPTO_SECRETSTREAM_XCHACHA20POLY1305_KEYBYTES,
     CRYPTO_SECRETSTREAM_XCHACHA20POLY1305_TAG_MESSAGE,
     CRYPTO_SECRETSTREAM_XCHACHA20POLY1305_TAG_PUSH,
-    CRYPTO_SECRETSTREAM_XCHACHA20POLY1305_TAG_REKEY, CRYPTO_STREAM_CHACHA20_IETF_NONCEBYTES,
+    CRYPTO_SECRETSTREAM_XCHACHA20POLY1305_TAG_REKEY, CRYPTO_SIGN_BYTES, CRYPTO_SIGN_PUBLICKEYBYTES,
+    CRYPTO_SIGN_SECRETKEYBYTES, CRYPTO_STREAM_CHACHA20_IETF_NONCEBYTES,
 };
 use crate::error::Error;
+use crate::generichash::GenericHash;
+use crate::kdf::{Context as KdfContext, Key as KdfKey, StackKdf};
+use crate::kx::Session;
+use crate::policy::Algorithms;
+use crate::sign::SigningKeyPair;
 pub use crate::types::*;
 
 /// Stream mode marker trait
@@ -147,9 +160,9 @@ pub mod protected {
     //! let mut pull_stream = DryocStream::init_pull(&key, &header);
     //!
     //! // Decrypt the set of messages, putting everything into locked memory
-    //! let (m1, tag1): (LockedBytes, Tag) = pull_stream.pull(&c1, None).expect("Decrypt failed");
-    //! let (m2, tag2): (LockedBytes, Tag) = pull_stream.pull(&c2, None).expect("Decrypt failed");
-    //! let (m3, tag3): (LockedBytes, Tag) = pull_stream.pull(&c3, None).expect("Decrypt failed");
+    //! let (m1, tag1) = pull_stream.pull_to::<LockedBytes>(&c1, None).expect("Decrypt failed");
+    //! let (m2, tag2) = pull_stream.pull_to::<LockedBytes>(&c2, None).expect("Decrypt failed");
+    //! let (m3, tag3) = pull_stream.pull_to::<LockedBytes>(&c3, None).expect("Decrypt failed");
     //!
     //! assert_eq!(message1.as_slice(), m1.as_slice());
     //! assert_eq!(message2.as_slice(), m2.as_slice());
@@ -257,6 +270,9 @@ impl DryocStream<Push> {
         tag: Tag,
     ) -> Result<Output, Error> {
         use crate::constants::CRYPTO_SECRETSTREAM_XCHACHA20POLY1305_ABYTES;
+
+        crate::policy::require(Algorithms::XCHACHA20POLY1305)?;
+
         let mut ciphertext = Output::new_bytes();
         ciphertext.resize(
             message.as_slice().len() + CRYPTO_SECRETSTREAM_XCHACHA20POLY1305_ABYTES,
@@ -313,6 +329,9 @@ impl DryocStream<Pull> {
         associated_data: Option<&Input>,
     ) -> Result<(Output, Tag), Error> {
         use crate::constants::CRYPTO_SECRETSTREAM_XCHACHA20POLY1305_ABYTES;
+
+        crate::policy::require(Algorithms::XCHACHA20POLY1305)?;
+
         let mut message = Output::default();
         message.resize(
             ciphertext.as_slice().len() - CRYPTO_SECRETSTREAM_XCHACHA20POLY1305_ABYTES,
@@ -330,6 +349,20 @@ impl DryocStream<Pull> {
         Ok((message, Tag::from_bits(tag).expect("invalid tag")))
     }
 
+    /// Decrypts `ciphertext` for this stream with `associated_data`, returning
+    /// the decrypted message and tag.
+    ///
+    /// Same as [`DryocStream::pull`], except the output container is the only
+    /// type parameter, so it can be selected with a turbofish, i.e.,
+    /// `stream.pull_to::<LockedBytes>(..)`.
+    pub fn pull_to<Output: MutBytes + Default + ResizableBytes>(
+        &mut self,
+        ciphertext: &impl Bytes,
+        associated_data: Option<&[u8]>,
+    ) -> Result<(Output, Tag), Error> {
+        self.pull(&ciphertext.as_slice(), associated_data.as_ref())
+    }
+
     /// Decrypts `ciphertext` for this stream with `associated_data`, returning
     /// the decrypted message and tag into a [`Vec`].
     pub fn pull_to_vec<Input: Bytes>(
@@ -341,6 +374,480 @@ impl DryocStream<Pull> {
     }
 }
 
+/// Digests computed by a [`HashingPushStream`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StreamDigests {
+    /// Hash of the plaintext, as the concatenation of every pushed message.
+    pub plaintext: crate::generichash::Hash,
+    /// Hash of the encrypted stream (the header followed by every
+    /// ciphertext), if requested.
+    pub ciphertext: Option<crate::generichash::Hash>,
+}
+
+/// A push stream which computes a [`GenericHash`](crate::generichash) of the
+/// plaintext (and optionally the ciphertext) while encrypting, so that data
+/// only needs to be read once to be both encrypted and fingerprinted.
+///
+/// Messages are hashed without their tags or associated data, so the
+/// plaintext digest matches the digest of the original, unsplit data.
+///
+/// ## Example
+///
+/// ```
+/// use dryoc::dryocstream::*;
+/// use dryoc::generichash::GenericHash;
+///
+/// let key = Key::gen();
+/// let (mut push_stream, header): (_, Header) =
+///     HashingPushStream::init_push(&key, None::<&dryoc::generichash::Key>, true)
+///         .expect("init failed");
+///
+/// let c1 = push_stream
+///     .push_to_vec(b"split into", None, Tag::MESSAGE)
+///     .expect("encrypt failed");
+/// let c2 = push_stream
+///     .push_to_vec(b"two messages", None, Tag::FINAL)
+///     .expect("encrypt failed");
+/// let digests = push_stream.finalize().expect("finalize failed");
+///
+/// let plaintext_hash: [u8; 32] = GenericHash::<32, 32>::digest(b"split intotwo messages");
+/// assert_eq!(digests.plaintext.as_slice(), &plaintext_hash);
+///
+/// let ciphertext_hash: [u8; 32] =
+///     GenericHash::<32, 32>::digest(&[header.to_vec(), c1, c2].concat());
+/// assert_eq!(digests.ciphertext.unwrap().as_slice(), &ciphertext_hash);
+/// ```
+pub struct HashingPushStream {
+    stream: DryocStream<Push>,
+    plaintext_hasher: crate::generichash::Hasher<CRYPTO_GENERICHASH_BYTES>,
+    ciphertext_hasher: Option<crate::generichash::Hasher<CRYPTO_GENERICHASH_BYTES>>,
+}
+
+impl HashingPushStream {
+    /// Returns a new hashing push stream, initialized from `key`. The
+    /// plaintext hash is keyed with `hash_key`, if provided, which prevents
+    /// the digest from revealing whether two streams contain the same
+    /// plaintext to anyone without the hash key. If `hash_ciphertext` is true,
+    /// the header and ciphertexts are also hashed (unkeyed).
+    pub fn init_push<
+        Key: ByteArray<CRYPTO_SECRETSTREAM_XCHACHA20POLY1305_KEYBYTES>,
+        HashKey: ByteArray<CRYPTO_GENERICHASH_KEYBYTES>,
+        Header: NewByteArray<CRYPTO_SECRETSTREAM_XCHACHA20POLY1305_HEADERBYTES>,
+    >(
+        key: &Key,
+        hash_key: Option<&HashKey>,
+        hash_ciphertext: bool,
+    ) -> Result<(Self, Header), Error> {
+        let (stream, header): (_, Header) = DryocStream::init_push(key);
+        let plaintext_hasher = GenericHash::new(hash_key)?;
+        let ciphertext_hasher = if hash_ciphertext {
+            let mut hasher = GenericHash::new::<crate::generichash::Key>(None)?;
+            hasher.update(header.as_slice());
+            Some(hasher)
+        } else {
+            None
+        };
+        Ok((
+            Self {
+                stream,
+                plaintext_hasher,
+                ciphertext_hasher,
+            },
+            header,
+        ))
+    }
+
+    /// Encrypts `message` for this stream with `associated_data` and `tag`,
+    /// returning the ciphertext, and updates the hashes.
+    pub fn push<Input: Bytes, Output: NewBytes + ResizableBytes>(
+        &mut self,
+        message: &Input,
+        associated_data: Option<&Input>,
+        tag: Tag,
+    ) -> Result<Output, Error> {
+        let ciphertext: Output = self.stream.push(message, associated_data, tag)?;
+        self.plaintext_hasher.update(message.as_slice());
+        if let Some(hasher) = self.ciphertext_hasher.as_mut() {
+            hasher.update(ciphertext.as_slice());
+        }
+        Ok(ciphertext)
+    }
+
+    /// Encrypts `message` for this stream with `associated_data` and `tag`,
+    /// returning the ciphertext, and updates the hashes.
+    pub fn push_to_vec<Input: Bytes>(
+        &mut self,
+        message: &Input,
+        associated_data: Option<&Input>,
+        tag: Tag,
+    ) -> Result<Vec<u8>, Error> {
+        self.push(message, associated_data, tag)
+    }
+
+    /// Manually rekeys the stream. See [`DryocStream::rekey`].
+    pub fn rekey(&mut self) {
+        self.stream.rekey()
+    }
+
+    /// Consumes the stream, returning the plaintext and ciphertext digests.
+    pub fn finalize(self) -> Result<StreamDigests, Error> {
+        Ok(StreamDigests {
+            plaintext: self.plaintext_hasher.finalize()?,
+            ciphertext: self
+                .ciphertext_hasher
+                .map(|hasher| hasher.finalize())
+                .transpose()?,
+        })
+    }
+}
+
+const SIGNED_STREAM_DOMAIN: &[u8] = b"dryoc signed secretstream v1";
+
+/// Running hash of a signed stream's transcript: the header, followed by the
+/// tag, associated data and plaintext of each message, with lengths prefixed
+/// so that message boundaries can't be moved.
+struct Transcript(crate::generichash::Hasher<CRYPTO_GENERICHASH_BYTES_MAX>);
+
+impl Transcript {
+    fn new(header: &[u8]) -> Result<Self, Error> {
+        let mut hasher = GenericHash::new::<crate::generichash::Key>(None)?;
+        hasher.update(SIGNED_STREAM_DOMAIN);
+        hasher.update(header);
+        Ok(Self(hasher))
+    }
+
+    fn update_prefixed(&mut self, data: &[u8]) {
+        self.0.update(&(data.len() as u64).to_le_bytes());
+        self.0.update(data);
+    }
+
+    fn update(&mut self, tag: Tag, associated_data: Option<&[u8]>, message: &[u8]) {
+        self.0.update(&[tag.bits()]);
+        self.update_prefixed(associated_data.unwrap_or_default());
+        self.update_prefixed(message);
+    }
+
+    fn finalize(mut self, associated_data: Option<&[u8]>) -> Result<Vec<u8>, Error> {
+        self.0.update(&[Tag::FINAL.bits()]);
+        self.update_prefixed(associated_data.unwrap_or_default());
+        self.0.finalize_to_vec()
+    }
+}
+
+/// A push stream whose final message carries an Ed25519 signature over a hash
+/// of the whole stream's transcript, so that the receiver can prove to a third
+/// party that the sender produced it (non-repudiation), without a separate
+/// signing pass over the data.
+///
+/// The transcript covers the header, and the tag, associated data and
+/// plaintext of every message, in order. Messages are pushed with any tag
+/// other than [`Tag::FINAL`]; the stream is ended with
+/// [`SignedPushStream::finalize`], which pushes the signature as the final
+/// message. Use [`SignedPullStream`] to decrypt and verify the stream.
+///
+/// ## Example
+///
+/// ```
+/// use dryoc::dryocstream::*;
+/// use dryoc::sign::SigningKeyPair;
+///
+/// let key = Key::gen();
+/// let sender = SigningKeyPair::gen_with_defaults();
+///
+/// let (mut push_stream, header): (_, Header) =
+///     SignedPushStream::init_push(&key).expect("init failed");
+/// let c1 = push_stream
+///     .push_to_vec(b"split into", None, Tag::MESSAGE)
+///     .expect("encrypt failed");
+/// let c2 = push_stream
+///     .push_to_vec(b"two messages", None, Tag::MESSAGE)
+///     .expect("encrypt failed");
+/// let c3: Vec<u8> = push_stream
+///     .finalize(None, &sender)
+///     .expect("finalize failed");
+///
+/// let mut pull_stream =
+///     SignedPullStream::init_pull(&key, &header, &sender.public_key).expect("init failed");
+/// let (m1, _) = pull_stream.pull_to_vec(&c1, None).expect("decrypt failed");
+/// let (m2, _) = pull_stream.pull_to_vec(&c2, None).expect("decrypt failed");
+/// // The final message is the verified signature, which can be archived
+/// // along with the stream as evidence of its origin
+/// let (signature, tag) = pull_stream.pull_to_vec(&c3, None).expect("verify failed");
+///
+/// assert_eq!([m1, m2].concat(), b"split intotwo messages");
+/// assert_eq!(tag, Tag::FINAL);
+/// assert_eq!(signature.len(), 64);
+/// ```
+pub struct SignedPushStream {
+    stream: DryocStream<Push>,
+    transcript: Transcript,
+}
+
+impl SignedPushStream {
+    /// Returns a new signed push stream, initialized from `key`.
+    pub fn init_push<
+        Key: ByteArray<CRYPTO_SECRETSTREAM_XCHACHA20POLY1305_KEYBYTES>,
+        Header: NewByteArray<CRYPTO_SECRETSTREAM_XCHACHA20POLY1305_HEADERBYTES>,
+    >(
+        key: &Key,
+    ) -> Result<(Self, Header), Error> {
+        let (stream, header): (_, Header) = DryocStream::init_push(key);
+        let transcript = Transcript::new(header.as_slice())?;
+        Ok((Self { stream, transcript }, header))
+    }
+
+    /// Encrypts `message` for this stream with `associated_data` and `tag`,
+    /// returning the ciphertext, and adds it to the transcript. Returns an
+    /// error if `tag` is [`Tag::FINAL`]; use [`SignedPushStream::finalize`] to
+    /// end the stream.
+    pub fn push<Input: Bytes, Output: NewBytes + ResizableBytes>(
+        &mut self,
+        message: &Input,
+        associated_data: Option<&Input>,
+        tag: Tag,
+    ) -> Result<Output, Error> {
+        if tag.contains(Tag::FINAL) {
+            return Err(dryoc_error!(
+                "signed streams must be ended with SignedPushStream::finalize"
+            ));
+        }
+        let ciphertext = self.stream.push(message, associated_data, tag)?;
+        self.transcript.update(
+            tag,
+            associated_data.map(|aad| aad.as_slice()),
+            message.as_slice(),
+        );
+        Ok(ciphertext)
+    }
+
+    /// Encrypts `message` for this stream with `associated_data` and `tag`,
+    /// returning the ciphertext, and adds it to the transcript.
+    pub fn push_to_vec<Input: Bytes>(
+        &mut self,
+        message: &Input,
+        associated_data: Option<&Input>,
+        tag: Tag,
+    ) -> Result<Vec<u8>, Error> {
+        self.push(message, associated_data, tag)
+    }
+
+    /// Manually rekeys the stream. See [`DryocStream::rekey`].
+    pub fn rekey(&mut self) {
+        self.stream.rekey()
+    }
+
+    /// Consumes the stream, signing the transcript (including
+    /// `associated_data`) with `signing_key_pair`, and returns the final
+    /// message, which contains the signature, tagged with [`Tag::FINAL`].
+    pub fn finalize<
+        Output: NewBytes + ResizableBytes,
+        PublicKey: ByteArray<CRYPTO_SIGN_PUBLICKEYBYTES> + Zeroize,
+        SecretKey: ByteArray<CRYPTO_SIGN_SECRETKEYBYTES> + Zeroize,
+    >(
+        mut self,
+        associated_data: Option<&[u8]>,
+        signing_key_pair: &SigningKeyPair<PublicKey, SecretKey>,
+    ) -> Result<Output, Error> {
+        let transcript = self.transcript.finalize(associated_data)?;
+        let mut signature = [0u8; CRYPTO_SIGN_BYTES];
+        crypto_sign_detached(
+            &mut signature,
+            &transcript,
+            signing_key_pair.secret_key.as_array(),
+        )?;
+        self.stream
+            .push(&signature.as_slice(), associated_data.as_ref(), Tag::FINAL)
+    }
+}
+
+/// The pull side of a [`SignedPushStream`], which verifies the sender's
+/// signature over the transcript when the final message is pulled.
+///
+/// Each message is authenticated with the stream key as it's pulled, as with
+/// [`DryocStream`], but the signature can only be verified at the end of the
+/// stream. Until the final message has been pulled successfully, treat the
+/// decrypted messages as coming from someone holding the stream key, not
+/// necessarily from the signer.
+///
+/// Refer to [`SignedPushStream`] for sample usage.
+pub struct SignedPullStream {
+    stream: DryocStream<Pull>,
+    transcript: Option<Transcript>,
+    public_key: crate::sign::PublicKey,
+}
+
+impl SignedPullStream {
+    /// Returns a new signed pull stream, initialized from `key` and `header`,
+    /// which verifies the stream's signature with the sender's `public_key`.
+    pub fn init_pull<
+        Key: ByteArray<CRYPTO_SECRETSTREAM_XCHACHA20POLY1305_KEYBYTES>,
+        Header: ByteArray<CRYPTO_SECRETSTREAM_XCHACHA20POLY1305_HEADERBYTES>,
+        PublicKey: ByteArray<CRYPTO_SIGN_PUBLICKEYBYTES>,
+    >(
+        key: &Key,
+        header: &Header,
+        public_key: &PublicKey,
+    ) -> Result<Self, Error> {
+        Ok(Self {
+            stream: DryocStream::init_pull(key, header),
+            transcript: Some(Transcript::new(header.as_slice())?),
+            public_key: crate::sign::PublicKey::from(public_key.as_array()),
+        })
+    }
+
+    /// Decrypts `ciphertext` for this stream with `associated_data`, returning
+    /// the decrypted message and tag. If the tag is [`Tag::FINAL`], the
+    /// message is the sender's signature, which has been verified over the
+    /// transcript. Returns an error if the signature is invalid, or if the
+    /// stream has already ended.
+    pub fn pull<Input: Bytes, Output: MutBytes + Default + ResizableBytes>(
+        &mut self,
+        ciphertext: &Input,
+        associated_data: Option<&Input>,
+    ) -> Result<(Output, Tag), Error> {
+        if self.transcript.is_none() {
+            return Err(dryoc_error!("signed stream has already ended"));
+        }
+        let (message, tag): (Output, Tag) = self.stream.pull(ciphertext, associated_data)?;
+        let associated_data = associated_data.map(|aad| aad.as_slice());
+        if !tag.contains(Tag::FINAL) {
+            if let Some(transcript) = self.transcript.as_mut() {
+                transcript.update(tag, associated_data, message.as_slice());
+            }
+            return Ok((message, tag));
+        }
+
+        let transcript = self
+            .transcript
+            .take()
+            .expect("transcript is present")
+            .finalize(associated_data)?;
+        let signature: &[u8; CRYPTO_SIGN_BYTES] = message
+            .as_slice()
+            .try_into()
+            .map_err(|_| dryoc_error!("invalid signature length"))?;
+        crypto_sign_verify_detached(signature, &transcript, self.public_key.as_array())?;
+        Ok((message, tag))
+    }
+
+    /// Decrypts `ciphertext` for this stream with `associated_data`, returning
+    /// the decrypted message and tag into a [`Vec`]. See
+    /// [`SignedPullStream::pull`].
+    pub fn pull_to_vec<Input: Bytes>(
+        &mut self,
+        ciphertext: &Input,
+        associated_data: Option<&Input>,
+    ) -> Result<(Vec<u8>, Tag), Error> {
+        self.pull(ciphertext, associated_data)
+    }
+
+    /// Manually rekeys the stream. See [`DryocStream::rekey`].
+    pub fn rekey(&mut self) {
+        self.stream.rekey()
+    }
+}
+
+/// Key derivation context for the channel keys of a [`StreamMux`].
+pub const STREAM_MUX_CONTEXT: &[u8; CRYPTO_KDF_CONTEXTBYTES] = b"dryocmux";
+
+/// Multiplexes several independent streams over a single [`kx`](crate::kx)
+/// session, such as a control channel and a bulk data channel.
+///
+/// Each channel is identified by a 64-bit ID, and uses its own stream key,
+/// derived with [`Kdf`] from the session's transmit key (for pushing) or
+/// receive key (for pulling), with the channel ID as the subkey ID. Because
+/// every channel has an independent key, each has its own nonce sequence, and
+/// streams on different channels can be used concurrently without any risk of
+/// nonce reuse across channels. A channel can also be reopened, because each
+/// new stream starts from a fresh random header.
+///
+/// Both peers must agree on the meaning of each channel ID. A ciphertext from
+/// one channel won't decrypt on another.
+///
+/// ```
+/// use dryoc::dryocstream::*;
+/// use dryoc::keypair::StackKeyPair;
+/// use dryoc::kx::Session;
+///
+/// const CONTROL: u64 = 0;
+/// const BULK: u64 = 1;
+///
+/// let client_keypair = StackKeyPair::gen();
+/// let server_keypair = StackKeyPair::gen();
+/// let client = Session::new_client_with_defaults(&client_keypair, &server_keypair.public_key)
+///     .expect("client session failed");
+/// let server = Session::new_server_with_defaults(&server_keypair, &client_keypair.public_key)
+///     .expect("server session failed");
+///
+/// let client_mux = StreamMux::new(&client);
+/// let server_mux = StreamMux::new(&server);
+///
+/// // The client opens a control channel and a bulk data channel
+/// let (mut control, control_header): (_, Header) =
+///     client_mux.init_push(CONTROL).expect("init failed");
+/// let (mut bulk, bulk_header): (_, Header) = client_mux.init_push(BULK).expect("init failed");
+///
+/// let c1 = control
+///     .push_to_vec(b"begin upload", None, Tag::MESSAGE)
+///     .expect("encrypt failed");
+/// let c2 = bulk
+///     .push_to_vec(b"lots of data", None, Tag::FINAL)
+///     .expect("encrypt failed");
+///
+/// // The server pulls each channel with the same channel ID
+/// let mut control = server_mux
+///     .init_pull(CONTROL, &control_header)
+///     .expect("init failed");
+/// let mut bulk = server_mux
+///     .init_pull(BULK, &bulk_header)
+///     .expect("init failed");
+///
+/// let (m1, _) = control.pull_to_vec(&c1, None).expect("decrypt failed");
+/// let (m2, _) = bulk.pull_to_vec(&c2, None).expect("decrypt failed");
+/// assert_eq!(m1, b"begin upload");
+/// assert_eq!(m2, b"lots of data");
+/// ```
+pub struct StreamMux {
+    tx: StackKdf,
+    rx: StackKdf,
+}
+
+impl StreamMux {
+    /// Returns a new multiplexer for `session`.
+    pub fn new<SessionKey: ByteArray<CRYPTO_KX_SESSIONKEYBYTES> + Zeroize>(
+        session: &Session<SessionKey>,
+    ) -> Self {
+        let kdf = |key: &[u8; CRYPTO_KX_SESSIONKEYBYTES]| {
+            StackKdf::from_parts(KdfKey::from(key), KdfContext::from(STREAM_MUX_CONTEXT))
+        };
+        Self {
+            tx: kdf(session.tx_as_array()),
+            rx: kdf(session.rx_as_array()),
+        }
+    }
+
+    /// Returns a new push stream for `channel`, keyed with the session's
+    /// transmit key, along with its header.
+    pub fn init_push<Header: NewByteArray<CRYPTO_SECRETSTREAM_XCHACHA20POLY1305_HEADERBYTES>>(
+        &self,
+        channel: u64,
+    ) -> Result<(DryocStream<Push>, Header), Error> {
+        let key: Key = self.tx.derive_subkey(channel)?;
+        Ok(DryocStream::init_push(&key))
+    }
+
+    /// Returns a new pull stream for `channel`, keyed with the session's
+    /// receive key, initialized from the `header` of the peer's push stream.
+    pub fn init_pull<Header: ByteArray<CRYPTO_SECRETSTREAM_XCHACHA20POLY1305_HEADERBYTES>>(
+        &self,
+        channel: u64,
+        header: &Header,
+    ) -> Result<DryocStream<Pull>, Error> {
+        let key: Key = self.rx.derive_subkey(channel)?;
+        Ok(DryocStream::init_pull(&key, header))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -495,4 +1002,107 @@ mod tests {
         assert_eq!(tag2, Tag::MESSAGE);
         assert_eq!(tag3, Tag::FINAL);
     }
+
+    #[test]
+    fn test_hashing_push_stream() {
+        let key = Key::gen();
+        let hash_key = crate::generichash::Key::gen();
+        let (mut push_stream, header): (_, Header) =
+            HashingPushStream::init_push(&key, Some(&hash_key), false).expect("init failed");
+
+        let c1 = push_stream
+            .push_to_vec(b"hello ", None, Tag::MESSAGE)
+            .expect("encrypt failed");
+        let c2 = push_stream
+            .push_to_vec(b"world", Some(b"aad12"), Tag::FINAL)
+            .expect("encrypt failed");
+        let digests = push_stream.finalize().expect("finalize failed");
+
+        let expected: [u8; 32] = GenericHash::<32, 32>::digest_keyed(b"hello world", &hash_key);
+        assert_eq!(digests.plaintext.as_slice(), &expected);
+        assert!(digests.ciphertext.is_none());
+
+        let mut pull_stream = DryocStream::init_pull(&key, &header);
+        let (m1, _) = pull_stream.pull_to_vec(&c1, None).expect("decrypt failed");
+        let (m2, tag) = pull_stream
+            .pull_to_vec(&c2, Some(&b"aad12".to_vec()))
+            .expect("decrypt failed");
+        assert_eq!([m1, m2].concat(), b"hello world");
+        assert_eq!(tag, Tag::FINAL);
+    }
+
+    #[test]
+    fn test_signed_stream() {
+        use crate::sign::SigningKeyPair;
+
+        let key = Key::gen();
+        let sender = SigningKeyPair::gen_with_defaults();
+        let (mut push_stream, header): (_, Header) =
+            SignedPushStream::init_push(&key).expect("init failed");
+
+        let c1 = push_stream
+            .push_to_vec(b"hello ", None, Tag::MESSAGE)
+            .expect("encrypt failed");
+        let c2 = push_stream
+            .push_to_vec(b"world", Some(b"aad12"), Tag::PUSH)
+            .expect("encrypt failed");
+        assert!(push_stream.push_to_vec(b"!", None, Tag::FINAL).is_err());
+        let c3: Vec<u8> = push_stream
+            .finalize(Some(b"done"), &sender)
+            .expect("finalize failed");
+
+        let pull = |public_key: &crate::sign::PublicKey, c2: &Vec<u8>, aad2: &[u8]| {
+            let mut pull_stream =
+                SignedPullStream::init_pull(&key, &header, public_key).expect("init failed");
+            let (m1, _) = pull_stream.pull_to_vec(&c1, None)?;
+            let (m2, tag) = pull_stream.pull_to_vec(c2, Some(&aad2.to_vec()))?;
+            assert_eq!(tag, Tag::PUSH);
+            assert_eq!([m1, m2].concat(), b"hello world");
+            let (signature, tag) = pull_stream.pull_to_vec(&c3, Some(&b"done".to_vec()))?;
+            assert_eq!(tag, Tag::FINAL);
+            assert!(pull_stream.pull_to_vec(&c3, None).is_err());
+            Ok::<_, Error>(signature)
+        };
+
+        let signature = pull(&sender.public_key, &c2, b"aad12").expect("pull failed");
+        assert_eq!(signature.len(), CRYPTO_SIGN_BYTES);
+
+        // Wrong signer
+        let other = SigningKeyPair::gen_with_defaults();
+        assert!(pull(&other.public_key, &c2, b"aad12").is_err());
+
+        // Tampered ciphertext
+        let mut tampered = c2.clone();
+        tampered[0] ^= 1;
+        assert!(pull(&sender.public_key, &tampered, b"aad12").is_err());
+    }
+
+    #[test]
+    fn test_stream_mux() {
+        use crate::keypair::StackKeyPair;
+
+        let client_keypair = StackKeyPair::gen();
+        let server_keypair = StackKeyPair::gen();
+        let client = Session::new_client_with_defaults(&client_keypair, &server_keypair.public_key)
+            .expect("client session failed");
+        let server = Session::new_server_with_defaults(&server_keypair, &client_keypair.public_key)
+            .expect("server session failed");
+        let client_mux = StreamMux::new(&client);
+        let server_mux = StreamMux::new(&server);
+
+        // Server to client
+        let (mut push, header): (_, Header) = server_mux.init_push(7).expect("init failed");
+        let c1 = push
+            .push_to_vec(b"hello", None, Tag::MESSAGE)
+            .expect("encrypt failed");
+        let mut pull = client_mux.init_pull(7, &header).expect("init failed");
+        let (m1, _) = pull.pull_to_vec(&c1, None).expect("decrypt failed");
+        assert_eq!(m1, b"hello");
+
+        // Other channels, and the other direction, use different keys
+        let mut pull = client_mux.init_pull(8, &header).expect("init failed");
+        assert!(pull.pull_to_vec(&c1, None).is_err());
+        let mut pull = server_mux.init_pull(7, &header).expect("init failed");
+        assert!(pull.pull_to_vec(&c1, None).is_err());
+    }
 }
@@ -0,0 +1,356 @@
+//! # Ed448 signatures
+//!
+//! This module implements the Ed448 signature scheme from
+//! [RFC 8032](https://www.rfc-editor.org/rfc/rfc8032), over Edwards448 (the
+//! "Goldilocks" curve). Ed448 provides a ~224-bit security level, compared to
+//! ~128 bits for Ed25519, and is intended for deployments where policy
+//! requires a stronger curve. For everything else, prefer
+//! [`sign`](crate::sign).
+//!
+//! Both pure Ed448 and Ed448 with a context string (up to 255 bytes) are
+//! supported. Signatures are deterministic, and verification uses the
+//! cofactored equation, as recommended by RFC 8032.
+//!
+//! The keys in this module are distinct from the Ed25519 keys used elsewhere
+//! in the crate, and can't be used interchangeably.
+//!
+//! This module requires the `curve448` feature.
+//!
+//! ## Example
+//!
+//! ```
+//! use dryoc::ed448::*;
+//!
+//! let keypair = SigningKeyPair::gen_with_defaults();
+//! let message = b"Now is the winter of our discontent";
+//!
+//! let signature: Signature = keypair.sign(message).expect("signing failed");
+//!
+//! verify(&signature, message, &keypair.public_key).expect("verification failed");
+//! ```
+//!
+//! Ed448 keys implement the [`Signer`] and [`Verifier`] traits, so they can
+//! also be used with the higher-level signed formats, such as JWS and COSE.
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use sha3::Shake256;
+use sha3::digest::{ExtendableOutput, Update, XofReader};
+use zeroize::{Zeroize, ZeroizeOnDrop};
+
+use crate::curve448::{scalar_is_canonical, scalar_mul_add, scalar_reduce, EdwardsPoint};
+use crate::error::Error;
+use crate::sign::{SignatureAlgorithm, Signer, Verifier};
+use crate::types::*;
+
+/// Length of an Ed448 public key, in bytes.
+pub const ED448_PUBLICKEYBYTES: usize = 57;
+/// Length of an Ed448 secret key, in bytes.
+pub const ED448_SECRETKEYBYTES: usize = 57;
+/// Length of an Ed448 signature, in bytes.
+pub const ED448_BYTES: usize = 114;
+/// Maximum length of an Ed448 context string, in bytes.
+pub const ED448_CONTEXTBYTES_MAX: usize = 255;
+
+/// Stack-allocated Ed448 public key.
+pub type PublicKey = StackByteArray<ED448_PUBLICKEYBYTES>;
+/// Stack-allocated Ed448 secret key.
+pub type SecretKey = StackByteArray<ED448_SECRETKEYBYTES>;
+/// Stack-allocated Ed448 signature.
+pub type Signature = StackByteArray<ED448_BYTES>;
+
+/// Computes SHAKE256(dom4(0, context) || parts), with a 114 byte output.
+fn hash(context: &[u8], parts: &[&[u8]]) -> [u8; 114] {
+    let mut hasher = Shake256::default();
+    hasher.update(b"SigEd448");
+    hasher.update(&[0, context.len() as u8]);
+    hasher.update(context);
+    for part in parts {
+        hasher.update(part);
+    }
+    let mut output = [0u8; 114];
+    hasher.finalize_xof().read(&mut output);
+    output
+}
+
+/// Expands `secret_key` into the clamped secret scalar and the nonce prefix.
+fn expand(secret_key: &[u8; ED448_SECRETKEYBYTES]) -> ([u8; 57], [u8; 57]) {
+    let mut hasher = Shake256::default();
+    hasher.update(secret_key);
+    let mut h = [0u8; 114];
+    hasher.finalize_xof().read(&mut h);
+
+    let mut scalar = [0u8; 57];
+    let mut prefix = [0u8; 57];
+    scalar.copy_from_slice(&h[..57]);
+    prefix.copy_from_slice(&h[57..]);
+    h.zeroize();
+    scalar[0] &= 0xfc;
+    scalar[55] |= 0x80;
+    scalar[56] = 0;
+    (scalar, prefix)
+}
+
+fn validate_context(context: &[u8]) -> Result<(), Error> {
+    if context.len() > ED448_CONTEXTBYTES_MAX {
+        Err(dryoc_error!(format!(
+            "context length {} exceeds maximum {}",
+            context.len(),
+            ED448_CONTEXTBYTES_MAX
+        )))
+    } else {
+        Ok(())
+    }
+}
+
+#[cfg_attr(
+    feature = "serde",
+    derive(Zeroize, ZeroizeOnDrop, Serialize, Deserialize, Debug, Clone)
+)]
+#[cfg_attr(not(feature = "serde"), derive(Zeroize, ZeroizeOnDrop, Debug, Clone))]
+/// An Ed448 keypair for public-key signatures.
+pub struct SigningKeyPair<
+    PublicKey: ByteArray<ED448_PUBLICKEYBYTES> + Zeroize,
+    SecretKey: ByteArray<ED448_SECRETKEYBYTES> + Zeroize,
+> {
+    /// Public key
+    pub public_key: PublicKey,
+    /// Secret key
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::redact::serialize_secret")
+    )]
+    pub secret_key: SecretKey,
+}
+
+impl<
+    PublicKey: NewByteArray<ED448_PUBLICKEYBYTES> + Zeroize,
+    SecretKey: NewByteArray<ED448_SECRETKEYBYTES> + Zeroize,
+> SigningKeyPair<PublicKey, SecretKey>
+{
+    /// Randomly generates a new signing keypair.
+    pub fn gen() -> Self {
+        Self::from_secret_key(SecretKey::gen())
+    }
+
+    /// Derives a signing keypair from `secret_key`, and consumes it, returning
+    /// a new keypair.
+    pub fn from_secret_key(secret_key: SecretKey) -> Self {
+        let (mut scalar, mut prefix) = expand(secret_key.as_array());
+        let mut public_key = PublicKey::new_byte_array();
+        public_key
+            .as_mut_array()
+            .copy_from_slice(&EdwardsPoint::base().mul(&scalar).to_bytes());
+        scalar.zeroize();
+        prefix.zeroize();
+        Self {
+            public_key,
+            secret_key,
+        }
+    }
+}
+
+impl<
+    PublicKey: ByteArray<ED448_PUBLICKEYBYTES> + Zeroize,
+    SecretKey: ByteArray<ED448_SECRETKEYBYTES> + Zeroize,
+> SigningKeyPair<PublicKey, SecretKey>
+{
+    /// Signs `message`, returning the signature.
+    pub fn sign<Signature: NewByteArray<ED448_BYTES>>(
+        &self,
+        message: &[u8],
+    ) -> Result<Signature, Error> {
+        self.sign_with_context(message, b"")
+    }
+
+    /// Signs `message` with the domain separation string `context`, which
+    /// must be at most [`ED448_CONTEXTBYTES_MAX`] bytes, returning the
+    /// signature. The same context must be used to verify the signature.
+    pub fn sign_with_context<Signature: NewByteArray<ED448_BYTES>>(
+        &self,
+        message: &[u8],
+        context: &[u8],
+    ) -> Result<Signature, Error> {
+        validate_context(context)?;
+
+        let (mut scalar, mut prefix) = expand(self.secret_key.as_array());
+        let mut r = scalar_reduce(&hash(context, &[&prefix, message]));
+        let big_r = EdwardsPoint::base().mul(&r).to_bytes();
+        let k = scalar_reduce(&hash(
+            context,
+            &[&big_r, self.public_key.as_slice(), message],
+        ));
+        let s = scalar_mul_add(&k, &scalar, &r);
+        scalar.zeroize();
+        prefix.zeroize();
+        r.zeroize();
+
+        let mut signature = Signature::new_byte_array();
+        signature.as_mut_slice()[..57].copy_from_slice(&big_r);
+        signature.as_mut_slice()[57..].copy_from_slice(&s);
+        Ok(signature)
+    }
+}
+
+impl SigningKeyPair<PublicKey, SecretKey> {
+    /// Randomly generates a new signing keypair, using default types
+    /// (stack-allocated byte arrays).
+    pub fn gen_with_defaults() -> Self {
+        Self::gen()
+    }
+}
+
+impl<
+    PublicKey: ByteArray<ED448_PUBLICKEYBYTES> + Zeroize,
+    SecretKey: ByteArray<ED448_SECRETKEYBYTES> + Zeroize,
+> Signer for SigningKeyPair<PublicKey, SecretKey>
+{
+    fn algorithm(&self) -> SignatureAlgorithm {
+        SignatureAlgorithm::EdDSA
+    }
+
+    fn try_sign(&self, message: &[u8]) -> Result<Vec<u8>, Error> {
+        self.sign::<Signature>(message)
+            .map(|signature| signature.to_vec())
+    }
+}
+
+impl Verifier for PublicKey {
+    fn algorithm(&self) -> SignatureAlgorithm {
+        SignatureAlgorithm::EdDSA
+    }
+
+    fn verify(&self, message: &[u8], signature: &[u8]) -> Result<(), Error> {
+        verify(&Signature::try_from(signature)?, message, self)
+    }
+}
+
+/// Verifies that `signature` is a valid signature of `message` by the holder
+/// of `public_key`.
+pub fn verify<Signature: ByteArray<ED448_BYTES>, PublicKey: ByteArray<ED448_PUBLICKEYBYTES>>(
+    signature: &Signature,
+    message: &[u8],
+    public_key: &PublicKey,
+) -> Result<(), Error> {
+    verify_with_context(signature, message, b"", public_key)
+}
+
+/// Verifies that `signature` is a valid signature of `message`, with the
+/// domain separation string `context`, by the holder of `public_key`.
+pub fn verify_with_context<
+    Signature: ByteArray<ED448_BYTES>,
+    PublicKey: ByteArray<ED448_PUBLICKEYBYTES>,
+>(
+    signature: &Signature,
+    message: &[u8],
+    context: &[u8],
+    public_key: &PublicKey,
+) -> Result<(), Error> {
+    validate_context(context)?;
+
+    let mut big_r_bytes = [0u8; 57];
+    let mut s = [0u8; 57];
+    big_r_bytes.copy_from_slice(&signature.as_slice()[..57]);
+    s.copy_from_slice(&signature.as_slice()[57..]);
+    if !scalar_is_canonical(&s) {
+        return Err(dryoc_error!("invalid signature"));
+    }
+    let a = EdwardsPoint::from_bytes(public_key.as_array())
+        .ok_or_else(|| dryoc_error!("invalid public key"))?;
+    let big_r =
+        EdwardsPoint::from_bytes(&big_r_bytes).ok_or_else(|| dryoc_error!("invalid signature"))?;
+
+    let k = scalar_reduce(&hash(
+        context,
+        &[&big_r_bytes, public_key.as_slice(), message],
+    ));
+
+    // [4][S]B = [4]R + [4][k]A
+    let lhs = EdwardsPoint::base().mul(&s).double().double();
+    let rhs = big_r.add(&a.mul(&k)).double().double();
+    if bool::from(lhs.ct_eq(&rhs)) {
+        Ok(())
+    } else {
+        Err(dryoc_error!("signature mismatch"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keypair(secret_key: &str) -> SigningKeyPair<PublicKey, SecretKey> {
+        SigningKeyPair::from_secret_key(
+            SecretKey::try_from(hex::decode(secret_key).expect("invalid hex").as_slice())
+                .expect("wrong length"),
+        )
+    }
+
+    #[test]
+    fn test_rfc8032_vectors() {
+        // RFC 8032, section 7.4, "Blank"
+        let keypair1 = keypair(
+            "6c82a562cb808d10d632be89c8513ebf6c929f34ddfa8c9f63c9960ef6e348a3528c8a3fcc2f044e39a3fc5b94492f8f032e7549a20098f95b",
+        );
+        assert_eq!(
+            hex::encode(keypair1.public_key.as_slice()),
+            "5fd7449b59b461fd2ce787ec616ad46a1da1342485a70e1f8a0ea75d80e96778edf124769b46c7061bd6783df1e50f6cd1fa1abeafe8256180"
+        );
+        let signature: Signature = keypair1.sign(b"").expect("signing failed");
+        assert_eq!(
+            hex::encode(signature.as_slice()),
+            "533a37f6bbe457251f023c0d88f976ae2dfb504a843e34d2074fd823d41a591f2b233f034f628281f2fd7a22ddd47d7828c59bd0a21bfd3980ff0d2028d4b18a9df63e006c5d1c2d345b925d8dc00b4104852db99ac5c7cdda8530a113a0f4dbb61149f05a7363268c71d95808ff2e652600"
+        );
+        verify(&signature, b"", &keypair1.public_key).expect("verification failed");
+
+        // RFC 8032, section 7.4, "1 octet"
+        let keypair2 = keypair(
+            "c4eab05d357007c632f3dbb48489924d552b08fe0c353a0d4a1f00acda2c463afbea67c5e8d2877c5e3bc397a659949ef8021e954e0a12274e",
+        );
+        assert_eq!(
+            hex::encode(keypair2.public_key.as_slice()),
+            "43ba28f430cdff456ae531545f7ecd0ac834a55d9358c0372bfa0c6c6798c0866aea01eb00742802b8438ea4cb82169c235160627b4c3a9480"
+        );
+        let signature: Signature = keypair2.sign(&[0x03]).expect("signing failed");
+        assert_eq!(
+            hex::encode(signature.as_slice()),
+            "26b8f91727bd62897af15e41eb43c377efb9c610d48f2335cb0bd0087810f4352541b143c4b981b7e18f62de8ccdf633fc1bf037ab7cd779805e0dbcc0aae1cbcee1afb2e027df36bc04dcecbf154336c19f0af7e0a6472905e799f1953d2a0ff3348ab21aa4adafd1d234441cf807c03a00"
+        );
+        verify(&signature, &[0x03], &keypair2.public_key).expect("verification failed");
+        verify(&signature, &[0x04], &keypair2.public_key).expect_err("should not verify");
+        verify(&signature, &[0x03], &keypair1.public_key).expect_err("should not verify");
+    }
+
+    #[test]
+    fn test_context() {
+        let keypair = SigningKeyPair::gen_with_defaults();
+        let message = b"To be, or not to be";
+
+        let signature: Signature = keypair
+            .sign_with_context(message, b"foo")
+            .expect("signing failed");
+        verify_with_context(&signature, message, b"foo", &keypair.public_key)
+            .expect("verification failed");
+        verify_with_context(&signature, message, b"bar", &keypair.public_key)
+            .expect_err("should not verify");
+        verify(&signature, message, &keypair.public_key).expect_err("should not verify");
+
+        keypair
+            .sign_with_context::<Signature>(message, &[0u8; 256])
+            .expect_err("context too long");
+
+        // Signer and Verifier produce and accept the same signatures
+        let signature = keypair.try_sign(message).expect("signing failed");
+        assert_eq!(signature.len(), ED448_BYTES);
+        Verifier::verify(&keypair.public_key, message, &signature).expect("verification failed");
+
+        // a non-canonical S is rejected
+        let signature: Signature = keypair
+            .sign_with_context(message, b"foo")
+            .expect("signing failed");
+        let mut tampered = signature.clone();
+        tampered.as_mut_slice()[113] = 0x01;
+        verify_with_context(&tampered, message, b"foo", &keypair.public_key)
+            .expect_err("should not verify");
+    }
+}
@@ -0,0 +1,1165 @@
+//! # Envelope encryption
+//!
+//! Implements envelope encryption, the pattern used by cloud key management
+//! services (KMS) for encrypting data at rest: each payload is encrypted with
+//! a random [`DataKey`], and the data key is then wrapped (encrypted) under
+//! one or more key encryption keys (KEKs). The wrapped data keys are stored
+//! alongside the ciphertext in a single [`Envelope`], so any one of the KEKs
+//! can later recover the data key and decrypt the payload.
+//!
+//! A KEK, described by a [`Kek`], may be:
+//!
+//! * a local secret key, which wraps the data key with a
+//!   [`DryocSecretBox`](crate::dryocsecretbox::DryocSecretBox)
+//! * a recipient's public key, which wraps the data key with a sealed
+//!   [`DryocBox`](crate::dryocbox::DryocBox)
+//! * an external KMS, via the [`KeyWrapper`] trait, in which case the KEK never
+//!   leaves the KMS
+//!
+//! Each KEK is identified by a key ID, which is stored in the envelope so the
+//! right wrapped data key can be found when opening it with an [`Unwrapper`].
+//!
+//! Payloads are encrypted with XChaCha20-Poly1305 and a random nonce, and
+//! optional associated data, which must be provided again to open the
+//! envelope.
+//!
+//! ## Provenance
+//!
+//! Envelopes sealed with [`Envelope::seal_with_provenance`] also carry
+//! [`Provenance`] metadata: the version of this crate which sealed them, the
+//! algorithms used, and any parameters the caller wants to record (such as how
+//! a KEK was derived). It's stored in the clear, so it can be inspected with
+//! [`Envelope::provenance`] without any keys, i.e., during incident response,
+//! to find out exactly how old envelopes were produced. It's also bound to the
+//! payload as associated data, so tampering with it, or removing it, causes
+//! [`Envelope::open`] to fail. Until an envelope has been opened, its
+//! provenance is unauthenticated.
+//!
+//! ```
+//! use dryoc::dryocsecretbox::Key;
+//! use dryoc::envelope::*;
+//! use dryoc::types::*;
+//!
+//! let kek = Key::gen();
+//! let envelope = Envelope::seal_with_provenance(
+//!     b"sensitive data",
+//!     b"",
+//!     &[Kek::Local {
+//!         key_id: "kek-2024",
+//!         key: &kek,
+//!     }],
+//!     Provenance::new().with_param("kek-source", "vault"),
+//! )
+//! .expect("seal failed");
+//! let stored = envelope.to_vec();
+//!
+//! // Inspect the provenance without decrypting
+//! let envelope = Envelope::from_bytes(&stored).expect("invalid envelope");
+//! let provenance = envelope.provenance().expect("missing provenance");
+//! assert_eq!(provenance.crate_version(), env!("CARGO_PKG_VERSION"));
+//! assert_eq!(
+//!     provenance.algorithms(),
+//!     ["xchacha20poly1305-ietf", "xsalsa20poly1305"]
+//! );
+//! assert_eq!(provenance.param("kek-source"), Some("vault"));
+//! ```
+//!
+//! ## Example
+//!
+//! ```
+//! use dryoc::dryocsecretbox::Key;
+//! use dryoc::envelope::*;
+//! use dryoc::keypair::StackKeyPair;
+//! use dryoc::types::*;
+//!
+//! let local_kek = Key::gen();
+//! let recipient = StackKeyPair::gen();
+//!
+//! let envelope = Envelope::seal(
+//!     b"sensitive data",
+//!     b"associated data",
+//!     &[
+//!         Kek::Local {
+//!             key_id: "backup-kek",
+//!             key: &local_kek,
+//!         },
+//!         Kek::Recipient {
+//!             key_id: "alice",
+//!             public_key: &recipient.public_key,
+//!         },
+//!     ],
+//! )
+//! .expect("seal failed");
+//!
+//! // Store the envelope, i.e., in a database or object store
+//! let stored = envelope.to_vec();
+//! let envelope = Envelope::from_bytes(&stored).expect("invalid envelope");
+//!
+//! // Either KEK can open it
+//! let plaintext = envelope
+//!     .open(
+//!         b"associated data",
+//!         &Unwrapper::Recipient {
+//!             key_id: "alice",
+//!             keypair: &recipient,
+//!         },
+//!     )
+//!     .expect("open failed");
+//! assert_eq!(plaintext, b"sensitive data");
+//! ```
+//!
+//! ## Layout
+//!
+//! [`Envelope::to_vec`] produces a [versioned envelope](crate::formats) of
+//! kind [`Kind::DataKeyEnvelope`], with the payload laid out as:
+//!
+//! wrapped key count (2, big-endian), then for each wrapped key: KEK type (1)
+//! ‖ key ID length (2, big-endian) ‖ key ID ‖ wrapped key length (4,
+//! big-endian) ‖ wrapped key; then nonce (24) ‖ ciphertext ‖ tag (16)
+//!
+//! Envelopes with provenance are of kind
+//! [`Kind::DataKeyEnvelopeWithProvenance`] instead, with the payload prefixed
+//! by provenance length (2, big-endian) ‖ provenance, where the provenance is
+//! its [canonical encoding](crate::canonical).
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use zeroize::{Zeroize, ZeroizeOnDrop, Zeroizing};
+
+use crate::aead::{xchacha20poly1305_ietf_decrypt, xchacha20poly1305_ietf_encrypt};
+use crate::canonical::{self, CanonicalDecode, CanonicalEncode};
+use crate::cbor::Value;
+use crate::constants::{
+    CRYPTO_AEAD_XCHACHA20POLY1305_IETF_ABYTES, CRYPTO_AEAD_XCHACHA20POLY1305_IETF_KEYBYTES,
+    CRYPTO_AEAD_XCHACHA20POLY1305_IETF_NPUBBYTES,
+};
+use crate::dryocbox::VecBox;
+use crate::dryocsecretbox;
+use crate::error::Error;
+use crate::formats::{decode_envelope_of, encode_envelope, Format, Kind};
+use crate::keypair::{PublicKey, StackKeyPair};
+pub use crate::kms::{AsyncKeyWrapper, KeyWrapper};
+use crate::kms::{secretbox_unwrap, secretbox_wrap};
+use crate::rng::copy_randombytes;
+use crate::types::*;
+
+/// Length of a data key, in bytes.
+pub const DATA_KEYBYTES: usize = CRYPTO_AEAD_XCHACHA20POLY1305_IETF_KEYBYTES;
+
+/// Domain separation prefix for the associated data of envelope payloads.
+const ENVELOPE_AAD_PREFIX: &[u8] = b"dryoc envelope";
+/// Domain separation prefix for the associated data of envelope payloads with
+/// provenance.
+const PROVENANCE_AAD_PREFIX: &[u8] = b"dryoc provenance envelope";
+/// Algorithm ID of envelope payloads, as recorded in their provenance.
+const PAYLOAD_ALGORITHM: &str = "xchacha20poly1305-ietf";
+
+/// A key encryption key, used to wrap a data key.
+#[derive(Clone, Copy)]
+pub enum Kek<'a> {
+    /// A local secret key, used to wrap the data key with a secret box.
+    Local {
+        /// ID of the key.
+        key_id: &'a str,
+        /// The key.
+        key: &'a dryocsecretbox::Key,
+    },
+    /// A recipient's public key, used to wrap the data key with a sealed box.
+    Recipient {
+        /// ID of the recipient's key.
+        key_id: &'a str,
+        /// The recipient's public key.
+        public_key: &'a PublicKey,
+    },
+    /// A key held by an external [`KeyWrapper`].
+    External {
+        /// ID of the key, as understood by `wrapper`.
+        key_id: &'a str,
+        /// The key wrapper.
+        wrapper: &'a dyn KeyWrapper,
+    },
+}
+
+impl<'a> Kek<'a> {
+    fn kek_type(&self) -> KekType {
+        match self {
+            Kek::Local { .. } => KekType::Local,
+            Kek::Recipient { .. } => KekType::Recipient,
+            Kek::External { .. } => KekType::External,
+        }
+    }
+
+    fn key_id(&self) -> &'a str {
+        match self {
+            Kek::Local { key_id, .. }
+            | Kek::Recipient { key_id, .. }
+            | Kek::External { key_id, .. } => key_id,
+        }
+    }
+}
+
+/// A key encryption key, used to unwrap a data key.
+#[derive(Clone, Copy)]
+pub enum Unwrapper<'a> {
+    /// A local secret key, which wrapped the data key with a secret box.
+    Local {
+        /// ID of the key.
+        key_id: &'a str,
+        /// The key.
+        key: &'a dryocsecretbox::Key,
+    },
+    /// A recipient's keypair, to whose public key the data key was sealed.
+    Recipient {
+        /// ID of the recipient's key.
+        key_id: &'a str,
+        /// The recipient's keypair.
+        keypair: &'a StackKeyPair,
+    },
+    /// A key held by an external [`KeyWrapper`].
+    External {
+        /// ID of the key, as understood by `wrapper`.
+        key_id: &'a str,
+        /// The key wrapper.
+        wrapper: &'a dyn KeyWrapper,
+    },
+}
+
+impl<'a> Unwrapper<'a> {
+    fn kek_type(&self) -> KekType {
+        match self {
+            Unwrapper::Local { .. } => KekType::Local,
+            Unwrapper::Recipient { .. } => KekType::Recipient,
+            Unwrapper::External { .. } => KekType::External,
+        }
+    }
+
+    fn key_id(&self) -> &'a str {
+        match self {
+            Unwrapper::Local { key_id, .. }
+            | Unwrapper::Recipient { key_id, .. }
+            | Unwrapper::External { key_id, .. } => key_id,
+        }
+    }
+}
+
+#[cfg_attr(
+    feature = "serde",
+    derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)
+)]
+#[cfg_attr(
+    not(feature = "serde"),
+    derive(Clone, Copy, Debug, PartialEq, Eq, Hash)
+)]
+/// Type of key encryption key which wrapped a data key.
+pub enum KekType {
+    /// A local secret key (see [`Kek::Local`]).
+    Local,
+    /// A recipient's public key (see [`Kek::Recipient`]).
+    Recipient,
+    /// An external [`KeyWrapper`] (see [`Kek::External`]).
+    External,
+}
+
+impl KekType {
+    fn id(self) -> u8 {
+        match self {
+            KekType::Local => 1,
+            KekType::Recipient => 2,
+            KekType::External => 3,
+        }
+    }
+
+    fn from_id(id: u8) -> Result<Self, Error> {
+        match id {
+            1 => Ok(KekType::Local),
+            2 => Ok(KekType::Recipient),
+            3 => Ok(KekType::External),
+            _ => Err(dryoc_error!(format!("unknown KEK type {}", id))),
+        }
+    }
+
+    /// Returns the ID of the algorithm used to wrap data keys, as recorded in
+    /// provenance.
+    fn algorithm(self) -> &'static str {
+        match self {
+            KekType::Local => "xsalsa20poly1305",
+            KekType::Recipient => "x25519-xsalsa20poly1305-sealed",
+            KekType::External => "external",
+        }
+    }
+}
+
+#[cfg_attr(
+    feature = "serde",
+    derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)
+)]
+#[cfg_attr(not(feature = "serde"), derive(Clone, Debug, PartialEq, Eq))]
+/// A data key, wrapped under a key encryption key.
+pub struct WrappedKey {
+    kek_type: KekType,
+    key_id: String,
+    wrapped_key: Vec<u8>,
+}
+
+impl WrappedKey {
+    /// Returns the type of KEK which wrapped the data key.
+    pub fn kek_type(&self) -> KekType {
+        self.kek_type
+    }
+
+    /// Returns the ID of the KEK which wrapped the data key.
+    pub fn key_id(&self) -> &str {
+        &self.key_id
+    }
+
+    /// Returns the wrapped data key.
+    pub fn wrapped_key(&self) -> &[u8] {
+        &self.wrapped_key
+    }
+}
+
+/// A random key used to encrypt a single payload, which is then wrapped under
+/// one or more key encryption keys.
+#[derive(Zeroize, ZeroizeOnDrop, Clone)]
+pub struct DataKey {
+    key: StackByteArray<DATA_KEYBYTES>,
+}
+
+impl DataKey {
+    /// Returns a new random data key.
+    pub fn gen() -> Self {
+        Self {
+            key: StackByteArray::gen(),
+        }
+    }
+
+    /// Encrypts `plaintext` with this key and `associated_data`, returning
+    /// nonce (24) ‖ ciphertext ‖ tag (16).
+    pub fn encrypt(&self, plaintext: &[u8], associated_data: &[u8]) -> Vec<u8> {
+        self.encrypt_payload(plaintext, &payload_aad(associated_data, None))
+    }
+
+    /// Decrypts `ciphertext`, as returned by [`DataKey::encrypt`], with this
+    /// key and `associated_data`, returning the plaintext.
+    pub fn decrypt(&self, ciphertext: &[u8], associated_data: &[u8]) -> Result<Vec<u8>, Error> {
+        self.decrypt_payload(ciphertext, &payload_aad(associated_data, None))
+    }
+
+    fn encrypt_payload(&self, plaintext: &[u8], aad: &[u8]) -> Vec<u8> {
+        let mut nonce = [0u8; CRYPTO_AEAD_XCHACHA20POLY1305_IETF_NPUBBYTES];
+        copy_randombytes(&mut nonce);
+        let ciphertext =
+            xchacha20poly1305_ietf_encrypt(plaintext, aad, &nonce, self.key.as_array());
+        let mut output = Vec::with_capacity(nonce.len() + ciphertext.len());
+        output.extend_from_slice(&nonce);
+        output.extend_from_slice(&ciphertext);
+        output
+    }
+
+    fn decrypt_payload(&self, ciphertext: &[u8], aad: &[u8]) -> Result<Vec<u8>, Error> {
+        if ciphertext.len()
+            < CRYPTO_AEAD_XCHACHA20POLY1305_IETF_NPUBBYTES
+                + CRYPTO_AEAD_XCHACHA20POLY1305_IETF_ABYTES
+        {
+            return Err(dryoc_error!("ciphertext too short"));
+        }
+        let (nonce, ciphertext) = ciphertext.split_at(CRYPTO_AEAD_XCHACHA20POLY1305_IETF_NPUBBYTES);
+        xchacha20poly1305_ietf_decrypt(
+            ciphertext,
+            aad,
+            ByteArray::as_array(nonce),
+            self.key.as_array(),
+        )
+    }
+
+    /// Wraps this key under `kek`.
+    pub fn wrap(&self, kek: &Kek) -> Result<WrappedKey, Error> {
+        let wrapped_key = match kek {
+            Kek::Local { key, .. } => secretbox_wrap(key, self.key.as_slice())?,
+            Kek::Recipient { public_key, .. } => {
+                VecBox::seal_to_vecbox(&self.key, public_key)?.to_vec()
+            }
+            Kek::External {
+                key_id, wrapper, ..
+            } => wrapper.wrap_key(key_id, self.key.as_slice())?,
+        };
+        Ok(WrappedKey {
+            kek_type: kek.kek_type(),
+            key_id: kek.key_id().into(),
+            wrapped_key,
+        })
+    }
+
+    /// Unwraps `wrapped` with `unwrapper`, returning the data key.
+    pub fn unwrap(wrapped: &WrappedKey, unwrapper: &Unwrapper) -> Result<Self, Error> {
+        if wrapped.kek_type != unwrapper.kek_type() || wrapped.key_id != unwrapper.key_id() {
+            return Err(dryoc_error!("wrapped key doesn't match unwrapper"));
+        }
+        let key = match unwrapper {
+            Unwrapper::Local { key, .. } => secretbox_unwrap(key, &wrapped.wrapped_key)?,
+            Unwrapper::Recipient { keypair, .. } => Zeroizing::new(
+                VecBox::from_sealed_bytes(&wrapped.wrapped_key)?.unseal_to_vec(*keypair)?,
+            ),
+            Unwrapper::External {
+                key_id, wrapper, ..
+            } => wrapper.unwrap_key(key_id, &wrapped.wrapped_key)?,
+        };
+        Self::from_unwrapped(key)
+    }
+
+    /// Wraps this key with the KEK `key_id` held by `wrapper`, which may be
+    /// async. The wrapped key is of type [`KekType::External`].
+    pub async fn wrap_async(
+        &self,
+        key_id: &str,
+        wrapper: &(dyn AsyncKeyWrapper + Sync),
+    ) -> Result<WrappedKey, Error> {
+        let wrapped_key = wrapper.wrap_key(key_id, self.key.as_slice()).await?;
+        Ok(WrappedKey {
+            kek_type: KekType::External,
+            key_id: key_id.into(),
+            wrapped_key,
+        })
+    }
+
+    /// Unwraps `wrapped` with `wrapper`, which may be async, returning the
+    /// data key. `wrapped` must be of type [`KekType::External`].
+    pub async fn unwrap_async(
+        wrapped: &WrappedKey,
+        wrapper: &(dyn AsyncKeyWrapper + Sync),
+    ) -> Result<Self, Error> {
+        if wrapped.kek_type != KekType::External {
+            return Err(dryoc_error!("wrapped key isn't from an external KEK"));
+        }
+        let key = wrapper
+            .unwrap_key(&wrapped.key_id, &wrapped.wrapped_key)
+            .await?;
+        Self::from_unwrapped(key)
+    }
+
+    fn from_unwrapped(key: Zeroizing<Vec<u8>>) -> Result<Self, Error> {
+        StackByteArray::try_from(key.as_slice())
+            .map(|key| Self { key })
+            .map_err(|_| dryoc_error!("unwrapped key has invalid length"))
+    }
+}
+
+fn payload_aad(associated_data: &[u8], provenance: Option<&Provenance>) -> Vec<u8> {
+    let mut aad = Vec::new();
+    match provenance {
+        Some(provenance) => {
+            let provenance = provenance.to_canonical_bytes();
+            aad.extend_from_slice(PROVENANCE_AAD_PREFIX);
+            aad.extend_from_slice(&(provenance.len() as u64).to_be_bytes());
+            aad.extend_from_slice(&provenance);
+        }
+        None => aad.extend_from_slice(ENVELOPE_AAD_PREFIX),
+    }
+    aad.extend_from_slice(associated_data);
+    aad
+}
+
+#[cfg_attr(
+    feature = "serde",
+    derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)
+)]
+#[cfg_attr(not(feature = "serde"), derive(Clone, Debug, PartialEq, Eq))]
+/// Metadata describing how an [`Envelope`] was produced.
+///
+/// Refer to [crate::envelope] for details.
+pub struct Provenance {
+    crate_version: String,
+    algorithms: Vec<String>,
+    params: Vec<(String, String)>,
+}
+
+impl Provenance {
+    /// Returns new provenance, with the version of this crate, and no
+    /// parameters. The algorithms are recorded when sealing an envelope.
+    pub fn new() -> Self {
+        Self {
+            crate_version: env!("CARGO_PKG_VERSION").into(),
+            algorithms: Vec::new(),
+            params: Vec::new(),
+        }
+    }
+
+    /// Returns this provenance with the parameter `name` set to `value`,
+    /// replacing any previous value.
+    pub fn with_param(mut self, name: &str, value: &str) -> Self {
+        self.params.retain(|(n, _)| n != name);
+        self.params.push((name.into(), value.into()));
+        self
+    }
+
+    /// Returns the version of this crate which sealed the envelope.
+    pub fn crate_version(&self) -> &str {
+        &self.crate_version
+    }
+
+    /// Returns the IDs of the algorithms used, starting with the payload's,
+    /// followed by those which wrapped its data key when it was sealed.
+    pub fn algorithms(&self) -> &[String] {
+        &self.algorithms
+    }
+
+    /// Returns the caller's parameters, as name and value pairs.
+    pub fn params(&self) -> &[(String, String)] {
+        &self.params
+    }
+
+    /// Returns the value of the parameter `name`, if it's set.
+    pub fn param(&self, name: &str) -> Option<&str> {
+        self.params
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    fn with_algorithms(mut self, keks: &[Kek]) -> Self {
+        self.algorithms = vec![PAYLOAD_ALGORITHM.into()];
+        for kek in keks {
+            let algorithm = kek.kek_type().algorithm();
+            if !self.algorithms.iter().any(|a| a == algorithm) {
+                self.algorithms.push(algorithm.into());
+            }
+        }
+        self
+    }
+}
+
+impl Default for Provenance {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CanonicalEncode for Provenance {
+    fn to_canonical_bytes(&self) -> Vec<u8> {
+        canonical::encode(
+            "dryoc/Provenance",
+            vec![
+                Value::Text(self.crate_version.clone()),
+                Value::Array(self.algorithms.iter().cloned().map(Value::Text).collect()),
+                Value::Array(
+                    self.params
+                        .iter()
+                        .map(|(name, value)| {
+                            Value::Array(vec![
+                                Value::Text(name.clone()),
+                                Value::Text(value.clone()),
+                            ])
+                        })
+                        .collect(),
+                ),
+            ],
+        )
+    }
+}
+
+impl CanonicalDecode for Provenance {
+    fn from_canonical_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        let fields = canonical::decode("dryoc/Provenance", bytes, 3)?;
+        let algorithms = canonical::array_field(&fields[1])?
+            .iter()
+            .map(|algorithm| Ok(canonical::text_field(algorithm)?.to_string()))
+            .collect::<Result<Vec<_>, Error>>()?;
+        let params = canonical::array_field(&fields[2])?
+            .iter()
+            .map(|param| {
+                let param = canonical::record_field(param, 2)?;
+                Ok((
+                    canonical::text_field(&param[0])?.to_string(),
+                    canonical::text_field(&param[1])?.to_string(),
+                ))
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+        Ok(Self {
+            crate_version: canonical::text_field(&fields[0])?.to_string(),
+            algorithms,
+            params,
+        })
+    }
+}
+
+#[cfg_attr(
+    feature = "serde",
+    derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)
+)]
+#[cfg_attr(not(feature = "serde"), derive(Clone, Debug, PartialEq, Eq))]
+/// An encrypted payload, along with its data key wrapped under one or more
+/// key encryption keys.
+pub struct Envelope {
+    wrapped_keys: Vec<WrappedKey>,
+    ciphertext: Vec<u8>,
+    provenance: Option<Provenance>,
+}
+
+impl Envelope {
+    /// Encrypts `plaintext` with `associated_data` under a new random data
+    /// key, and wraps the data key under each of `keks`.
+    pub fn seal(plaintext: &[u8], associated_data: &[u8], keks: &[Kek]) -> Result<Self, Error> {
+        Self::seal_with_data_key(&DataKey::gen(), plaintext, associated_data, keks)
+    }
+
+    /// Encrypts `plaintext` with `associated_data` under `data_key`, and wraps
+    /// the data key under each of `keks`. Data keys must not be reused for
+    /// large numbers of payloads.
+    pub fn seal_with_data_key(
+        data_key: &DataKey,
+        plaintext: &[u8],
+        associated_data: &[u8],
+        keks: &[Kek],
+    ) -> Result<Self, Error> {
+        Self::seal_inner(data_key, plaintext, associated_data, keks, None)
+    }
+
+    /// Same as [`Envelope::seal`], but also embeds `provenance`, with the
+    /// algorithms used recorded in it, and binds it to the payload. Refer to
+    /// [crate::envelope] for details.
+    pub fn seal_with_provenance(
+        plaintext: &[u8],
+        associated_data: &[u8],
+        keks: &[Kek],
+        provenance: Provenance,
+    ) -> Result<Self, Error> {
+        Self::seal_inner(
+            &DataKey::gen(),
+            plaintext,
+            associated_data,
+            keks,
+            Some(provenance.with_algorithms(keks)),
+        )
+    }
+
+    fn seal_inner(
+        data_key: &DataKey,
+        plaintext: &[u8],
+        associated_data: &[u8],
+        keks: &[Kek],
+        provenance: Option<Provenance>,
+    ) -> Result<Self, Error> {
+        if keks.is_empty() {
+            return Err(dryoc_error!("at least one KEK is required"));
+        }
+        let wrapped_keys = keks
+            .iter()
+            .map(|kek| data_key.wrap(kek))
+            .collect::<Result<Vec<_>, _>>()?;
+        let ciphertext = data_key.encrypt_payload(
+            plaintext,
+            &payload_aad(associated_data, provenance.as_ref()),
+        );
+        Ok(Self {
+            wrapped_keys,
+            ciphertext,
+            provenance,
+        })
+    }
+
+    /// Unwraps the data key with `unwrapper`, and decrypts the payload with
+    /// `associated_data`, returning the plaintext.
+    pub fn open(&self, associated_data: &[u8], unwrapper: &Unwrapper) -> Result<Vec<u8>, Error> {
+        self.data_key(unwrapper)?
+            .decrypt_payload(&self.ciphertext, &self.payload_aad(associated_data))
+    }
+
+    fn payload_aad(&self, associated_data: &[u8]) -> Vec<u8> {
+        payload_aad(associated_data, self.provenance.as_ref())
+    }
+
+    /// Unwraps and returns the data key with `unwrapper`.
+    pub fn data_key(&self, unwrapper: &Unwrapper) -> Result<DataKey, Error> {
+        let wrapped = self
+            .wrapped_keys
+            .iter()
+            .find(|wrapped| {
+                wrapped.kek_type == unwrapper.kek_type() && wrapped.key_id == unwrapper.key_id()
+            })
+            .ok_or_else(|| {
+                dryoc_error!(format!("no data key wrapped for {}", unwrapper.key_id()))
+            })?;
+        DataKey::unwrap(wrapped, unwrapper)
+    }
+
+    /// Unwraps the data key with `unwrapper`, and additionally wraps it under
+    /// `kek`, i.e., to grant access to another recipient, or when rotating
+    /// KEKs. The payload isn't re-encrypted.
+    pub fn add_kek(&mut self, unwrapper: &Unwrapper, kek: &Kek) -> Result<(), Error> {
+        let wrapped = self.data_key(unwrapper)?.wrap(kek)?;
+        self.wrapped_keys.push(wrapped);
+        Ok(())
+    }
+
+    /// Encrypts `plaintext` with `associated_data` under a new random data
+    /// key, and wraps the data key with the KEK `key_id` held by `wrapper`,
+    /// which may be async. More KEKs can be added with [`Envelope::add_kek`].
+    pub async fn seal_async(
+        plaintext: &[u8],
+        associated_data: &[u8],
+        key_id: &str,
+        wrapper: &(dyn AsyncKeyWrapper + Sync),
+    ) -> Result<Self, Error> {
+        let data_key = DataKey::gen();
+        let wrapped = data_key.wrap_async(key_id, wrapper).await?;
+        Ok(Self {
+            wrapped_keys: vec![wrapped],
+            ciphertext: data_key.encrypt(plaintext, associated_data),
+            provenance: None,
+        })
+    }
+
+    /// Unwraps the data key with the KEK `key_id` held by `wrapper`, which
+    /// may be async, and decrypts the payload with `associated_data`,
+    /// returning the plaintext.
+    pub async fn open_async(
+        &self,
+        associated_data: &[u8],
+        key_id: &str,
+        wrapper: &(dyn AsyncKeyWrapper + Sync),
+    ) -> Result<Vec<u8>, Error> {
+        let wrapped = self
+            .wrapped_keys
+            .iter()
+            .find(|wrapped| wrapped.kek_type == KekType::External && wrapped.key_id == key_id)
+            .ok_or_else(|| dryoc_error!(format!("no data key wrapped for {}", key_id)))?;
+        DataKey::unwrap_async(wrapped, wrapper)
+            .await?
+            .decrypt_payload(&self.ciphertext, &self.payload_aad(associated_data))
+    }
+
+    /// Removes the data keys wrapped under the KEK with `key_id`.
+    pub fn remove_kek(&mut self, key_id: &str) {
+        self.wrapped_keys.retain(|wrapped| wrapped.key_id != key_id)
+    }
+
+    /// Returns the wrapped data keys.
+    pub fn wrapped_keys(&self) -> &[WrappedKey] {
+        &self.wrapped_keys
+    }
+
+    /// Returns the encrypted payload, as nonce (24) ‖ ciphertext ‖ tag (16).
+    pub fn ciphertext(&self) -> &[u8] {
+        &self.ciphertext
+    }
+
+    /// Returns the envelope's provenance, if it was sealed with any. It's
+    /// only authenticated once the envelope has been opened.
+    pub fn provenance(&self) -> Option<&Provenance> {
+        self.provenance.as_ref()
+    }
+
+    /// Serializes this envelope into a new [`Vec`]. Refer to the [module
+    /// documentation](self) for the layout.
+    pub fn to_vec(&self) -> Vec<u8> {
+        let mut payload = Vec::new();
+        let kind = match &self.provenance {
+            Some(provenance) => {
+                let provenance = provenance.to_canonical_bytes();
+                payload.extend_from_slice(&(provenance.len() as u16).to_be_bytes());
+                payload.extend_from_slice(&provenance);
+                Kind::DataKeyEnvelopeWithProvenance
+            }
+            None => Kind::DataKeyEnvelope,
+        };
+        payload.extend_from_slice(&(self.wrapped_keys.len() as u16).to_be_bytes());
+        for wrapped in &self.wrapped_keys {
+            payload.push(wrapped.kek_type.id());
+            payload.extend_from_slice(&(wrapped.key_id.len() as u16).to_be_bytes());
+            payload.extend_from_slice(wrapped.key_id.as_bytes());
+            payload.extend_from_slice(&(wrapped.wrapped_key.len() as u32).to_be_bytes());
+            payload.extend_from_slice(&wrapped.wrapped_key);
+        }
+        payload.extend_from_slice(&self.ciphertext);
+        encode_envelope(Format::CURRENT, kind, &payload)
+    }
+
+    /// Deserializes an envelope from `bytes`, as produced by
+    /// [`Envelope::to_vec`].
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        let (kind, payload) = decode_envelope_of(
+            bytes,
+            &[Kind::DataKeyEnvelope, Kind::DataKeyEnvelopeWithProvenance],
+        )?;
+        let mut reader = Reader(payload);
+        let provenance = match kind {
+            Kind::DataKeyEnvelopeWithProvenance => {
+                let provenance_len = u16::from_be_bytes(reader.take_array()?) as usize;
+                Some(Provenance::from_canonical_bytes(
+                    reader.take(provenance_len)?,
+                )?)
+            }
+            _ => None,
+        };
+        let count = u16::from_be_bytes(reader.take_array()?);
+        let mut wrapped_keys = Vec::with_capacity(count as usize);
+        for _ in 0..count {
+            let kek_type = KekType::from_id(reader.take_array::<1>()?[0])?;
+            let key_id_len = u16::from_be_bytes(reader.take_array()?) as usize;
+            let key_id = String::from_utf8(reader.take(key_id_len)?.to_vec())
+                .map_err(|_| dryoc_error!("key ID isn't valid UTF-8"))?;
+            let wrapped_len = u32::from_be_bytes(reader.take_array()?) as usize;
+            let wrapped_key = reader.take(wrapped_len)?.to_vec();
+            wrapped_keys.push(WrappedKey {
+                kek_type,
+                key_id,
+                wrapped_key,
+            });
+        }
+        Ok(Self {
+            wrapped_keys,
+            ciphertext: reader.0.to_vec(),
+            provenance,
+        })
+    }
+}
+
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], Error> {
+        if self.0.len() < len {
+            return Err(dryoc_error!("envelope is truncated"));
+        }
+        let (head, tail) = self.0.split_at(len);
+        self.0 = tail;
+        Ok(head)
+    }
+
+    fn take_array<const N: usize>(&mut self) -> Result<[u8; N], Error> {
+        let mut array = [0u8; N];
+        array.copy_from_slice(self.take(N)?);
+        Ok(array)
+    }
+}
+
+impl CanonicalEncode for Envelope {
+    fn to_canonical_bytes(&self) -> Vec<u8> {
+        let wrapped_keys = self
+            .wrapped_keys
+            .iter()
+            .map(|wrapped| {
+                Value::Array(vec![
+                    Value::Unsigned(wrapped.kek_type.id() as u64),
+                    Value::Text(wrapped.key_id.clone()),
+                    Value::Bytes(wrapped.wrapped_key.clone()),
+                ])
+            })
+            .collect();
+        let mut fields = vec![
+            Value::Array(wrapped_keys),
+            Value::Bytes(self.ciphertext.clone()),
+        ];
+        if let Some(provenance) = &self.provenance {
+            fields.push(Value::Bytes(provenance.to_canonical_bytes()));
+        }
+        canonical::encode("dryoc/Envelope", fields)
+    }
+}
+
+impl CanonicalDecode for Envelope {
+    fn from_canonical_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        // envelopes with provenance have a third field
+        let fields = canonical::decode("dryoc/Envelope", bytes, 2)
+            .or_else(|_| canonical::decode("dryoc/Envelope", bytes, 3))?;
+        let wrapped_keys = canonical::array_field(&fields[0])?
+            .iter()
+            .map(|wrapped| {
+                let wrapped = canonical::record_field(wrapped, 3)?;
+                let kek_type = u8::try_from(canonical::unsigned_field(&wrapped[0])?)
+                    .map_err(|_| dryoc_error!("invalid KEK type"))?;
+                Ok(WrappedKey {
+                    kek_type: KekType::from_id(kek_type)?,
+                    key_id: canonical::text_field(&wrapped[1])?.to_string(),
+                    wrapped_key: canonical::bytes_field(&wrapped[2])?.to_vec(),
+                })
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+        let provenance = fields
+            .get(2)
+            .map(|provenance| {
+                canonical::bytes_field(provenance).and_then(Provenance::from_canonical_bytes)
+            })
+            .transpose()?;
+        Ok(Self {
+            wrapped_keys,
+            ciphertext: canonical::bytes_field(&fields[1])?.to_vec(),
+            provenance,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Wraps keys by XORing them with a fixed pad, standing in for a KMS.
+    struct XorWrapper;
+
+    impl KeyWrapper for XorWrapper {
+        fn wrap_key(&self, key_id: &str, key: &[u8]) -> Result<Vec<u8>, Error> {
+            if key_id != "kms-key" {
+                return Err(dryoc_error!("unknown key"));
+            }
+            Ok(key.iter().map(|b| b ^ 0x5a).collect())
+        }
+
+        fn unwrap_key(
+            &self,
+            key_id: &str,
+            wrapped_key: &[u8],
+        ) -> Result<Zeroizing<Vec<u8>>, Error> {
+            KeyWrapper::wrap_key(self, key_id, wrapped_key).map(Zeroizing::new)
+        }
+    }
+
+    #[test]
+    fn test_canonical_envelope() {
+        let local = dryocsecretbox::Key::gen();
+        let envelope = Envelope::seal(
+            b"payload",
+            b"aad",
+            &[
+                Kek::Local {
+                    key_id: "local",
+                    key: &local,
+                },
+                Kek::External {
+                    key_id: "kms-key",
+                    wrapper: &XorWrapper,
+                },
+            ],
+        )
+        .expect("seal");
+
+        let bytes = envelope.to_canonical_bytes();
+        let decoded = Envelope::from_canonical_bytes(&bytes).expect("decode failed");
+        assert_eq!(decoded, envelope);
+        assert_eq!(decoded.to_canonical_bytes(), bytes);
+        let unwrapper = Unwrapper::Local {
+            key_id: "local",
+            key: &local,
+        };
+        assert_eq!(decoded.open(b"aad", &unwrapper).expect("open"), b"payload");
+    }
+
+    #[test]
+    fn test_envelope() {
+        let local = dryocsecretbox::Key::gen();
+        let recipient = StackKeyPair::gen();
+
+        let envelope = Envelope::seal(
+            b"payload",
+            b"aad",
+            &[
+                Kek::Local {
+                    key_id: "local",
+                    key: &local,
+                },
+                Kek::Recipient {
+                    key_id: "recipient",
+                    public_key: &recipient.public_key,
+                },
+                Kek::External {
+                    key_id: "kms-key",
+                    wrapper: &XorWrapper,
+                },
+            ],
+        )
+        .expect("seal");
+        assert_eq!(envelope.wrapped_keys().len(), 3);
+
+        let envelope = Envelope::from_bytes(&envelope.to_vec()).expect("from bytes");
+
+        for unwrapper in [
+            Unwrapper::Local {
+                key_id: "local",
+                key: &local,
+            },
+            Unwrapper::Recipient {
+                key_id: "recipient",
+                keypair: &recipient,
+            },
+            Unwrapper::External {
+                key_id: "kms-key",
+                wrapper: &XorWrapper,
+            },
+        ] {
+            assert_eq!(envelope.open(b"aad", &unwrapper).expect("open"), b"payload");
+            assert!(envelope.open(b"other aad", &unwrapper).is_err());
+        }
+
+        let wrong_key = dryocsecretbox::Key::gen();
+        assert!(
+            envelope
+                .open(
+                    b"aad",
+                    &Unwrapper::Local {
+                        key_id: "local",
+                        key: &wrong_key,
+                    },
+                )
+                .is_err()
+        );
+        assert!(
+            envelope
+                .open(
+                    b"aad",
+                    &Unwrapper::Local {
+                        key_id: "missing",
+                        key: &local,
+                    },
+                )
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_add_remove_kek() {
+        let old = dryocsecretbox::Key::gen();
+        let new = dryocsecretbox::Key::gen();
+
+        let mut envelope = Envelope::seal(
+            b"payload",
+            b"",
+            &[Kek::Local {
+                key_id: "old",
+                key: &old,
+            }],
+        )
+        .expect("seal");
+        let ciphertext = envelope.ciphertext().to_vec();
+
+        envelope
+            .add_kek(
+                &Unwrapper::Local {
+                    key_id: "old",
+                    key: &old,
+                },
+                &Kek::Local {
+                    key_id: "new",
+                    key: &new,
+                },
+            )
+            .expect("add kek");
+        envelope.remove_kek("old");
+
+        assert_eq!(envelope.wrapped_keys().len(), 1);
+        assert_eq!(envelope.ciphertext(), ciphertext.as_slice());
+        let unwrapper = Unwrapper::Local {
+            key_id: "new",
+            key: &new,
+        };
+        assert_eq!(envelope.open(b"", &unwrapper).expect("open"), b"payload");
+    }
+
+    #[test]
+    fn test_async() {
+        use crate::kms::LocalKeyWrapper;
+        use crate::kms::tests::ready;
+
+        let kms = LocalKeyWrapper::new().with_key("kek", dryocsecretbox::Key::gen());
+        let envelope = ready(Envelope::seal_async(b"payload", b"aad", "kek", &kms)).expect("seal");
+
+        assert_eq!(
+            ready(envelope.open_async(b"aad", "kek", &kms)).expect("open"),
+            b"payload"
+        );
+        assert!(ready(envelope.open_async(b"aad", "other", &kms)).is_err());
+
+        // the sync API can open it too
+        let unwrapper = Unwrapper::External {
+            key_id: "kek",
+            wrapper: &kms,
+        };
+        assert_eq!(envelope.open(b"aad", &unwrapper).expect("open"), b"payload");
+    }
+
+    #[test]
+    fn test_provenance() {
+        let local = dryocsecretbox::Key::gen();
+        let recipient = StackKeyPair::gen();
+        let keks = [
+            Kek::Local {
+                key_id: "local",
+                key: &local,
+            },
+            Kek::Recipient {
+                key_id: "recipient",
+                public_key: &recipient.public_key,
+            },
+            Kek::Local {
+                key_id: "other",
+                key: &local,
+            },
+        ];
+        let envelope = Envelope::seal_with_provenance(
+            b"payload",
+            b"aad",
+            &keks,
+            Provenance::new()
+                .with_param("kdf", "argon2id")
+                .with_param("kdf", "scrypt"),
+        )
+        .expect("seal");
+        let unwrapper = Unwrapper::Local {
+            key_id: "local",
+            key: &local,
+        };
+
+        let bytes = envelope.to_vec();
+        assert_eq!(bytes[1], Kind::DataKeyEnvelopeWithProvenance.id());
+        let decoded = Envelope::from_bytes(&bytes).expect("from bytes");
+        assert_eq!(decoded, envelope);
+        let provenance = decoded.provenance().expect("provenance");
+        assert_eq!(provenance.crate_version(), env!("CARGO_PKG_VERSION"));
+        assert_eq!(
+            provenance.algorithms(),
+            [
+                "xchacha20poly1305-ietf",
+                "xsalsa20poly1305",
+                "x25519-xsalsa20poly1305-sealed"
+            ]
+        );
+        assert_eq!(provenance.params().len(), 1);
+        assert_eq!(provenance.param("kdf"), Some("scrypt"));
+        assert_eq!(decoded.open(b"aad", &unwrapper).expect("open"), b"payload");
+
+        let canonical =
+            Envelope::from_canonical_bytes(&envelope.to_canonical_bytes()).expect("decode failed");
+        assert_eq!(canonical, envelope);
+
+        // the provenance is authenticated, and can't be altered or removed
+        let mut tampered = envelope.clone();
+        tampered.provenance = Some(Provenance::new().with_param("kdf", "argon2id"));
+        assert!(tampered.open(b"aad", &unwrapper).is_err());
+        tampered.provenance = None;
+        assert!(tampered.open(b"aad", &unwrapper).is_err());
+
+        let plain = Envelope::seal(b"payload", b"aad", &keks).expect("seal");
+        assert!(plain.provenance().is_none());
+        assert_eq!(plain.to_vec()[1], Kind::DataKeyEnvelope.id());
+    }
+
+    #[test]
+    fn test_from_bytes_invalid() {
+        let key = dryocsecretbox::Key::gen();
+        let envelope = Envelope::seal(
+            b"payload",
+            b"",
+            &[Kek::Local {
+                key_id: "key",
+                key: &key,
+            }],
+        )
+        .expect("seal");
+        let bytes = envelope.to_vec();
+
+        assert!(Envelope::from_bytes(&bytes[..10]).is_err());
+        assert!(Envelope::seal(b"payload", b"", &[]).is_err());
+
+        let mut wrong_kind = bytes.clone();
+        wrong_kind[1] = Kind::SecretBox.id();
+        assert!(Envelope::from_bytes(&wrong_kind).is_err());
+    }
+}
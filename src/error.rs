@@ -14,6 +14,83 @@ pub enum Error {
 
     /// Unable to convert data from slice.
     FromSlice(core::array::TryFromSliceError),
+
+    /// A checksummed container (such as a persisted key) is truncated, or
+    /// failed its integrity check. Refer to [crate::formats] for details.
+    CorruptKeyFile(String),
+
+    /// A checksummed container uses a format version which isn't supported by
+    /// this version of the crate. Contains the version found.
+    WrongFormatVersion(u8),
+
+    /// An error with additional context about the operation which failed.
+    /// Only available with the `rich-errors` feature.
+    #[cfg(feature = "rich-errors")]
+    Context {
+        /// The underlying error.
+        error: Box<Error>,
+        /// Context for the operation which failed.
+        context: ErrorContext,
+    },
+}
+
+/// Context about a failed operation, attached to errors when the
+/// `rich-errors` feature is enabled. Allows failures to be aggregated by
+/// operation (and buffer sizes) without parsing error messages.
+#[cfg(feature = "rich-errors")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ErrorContext {
+    /// Name of the operation which failed, i.e., `crypto_box_open_easy`.
+    pub operation: &'static str,
+    /// Lengths of the buffers passed to the operation, as `(name, length)`
+    /// pairs.
+    pub sizes: Vec<(&'static str, usize)>,
+}
+
+#[cfg(feature = "rich-errors")]
+impl Display for ErrorContext {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.operation)?;
+        for (i, (name, len)) in self.sizes.iter().enumerate() {
+            write!(f, "{}{}={}", if i == 0 { " (" } else { ", " }, name, len)?;
+        }
+        if !self.sizes.is_empty() {
+            f.write_str(")")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "rich-errors")]
+impl Error {
+    /// Returns the operation context for this error, if any.
+    pub fn context(&self) -> Option<&ErrorContext> {
+        match self {
+            Error::Context { context, .. } => Some(context),
+            _ => None,
+        }
+    }
+
+    /// Returns the underlying error, without any operation context.
+    pub fn inner(&self) -> &Error {
+        match self {
+            Error::Context { error, .. } => error,
+            _ => self,
+        }
+    }
+
+    /// Attaches `operation` and `sizes` to this error as context. If the error
+    /// already has context, it's left unchanged, so the context always refers
+    /// to the innermost operation which failed.
+    pub fn with_context(self, operation: &'static str, sizes: Vec<(&'static str, usize)>) -> Self {
+        match self {
+            Error::Context { .. } => self,
+            _ => Error::Context {
+                error: Box::new(self),
+                context: ErrorContext { operation, sizes },
+            },
+        }
+    }
 }
 
 impl From<String> for Error {
@@ -46,6 +123,12 @@ impl Display for Error {
             Error::Message(message) => f.write_str(message),
             Error::Io(err) => write!(f, "I/O error: {}", err),
             Error::FromSlice(err) => write!(f, "From slice error: {}", err),
+            Error::CorruptKeyFile(reason) => write!(f, "corrupt key file: {}", reason),
+            Error::WrongFormatVersion(version) => {
+                write!(f, "unsupported format version {}", version)
+            }
+            #[cfg(feature = "rich-errors")]
+            Error::Context { error, context } => write!(f, "{} in {}", error, context),
         }
     }
 }
@@ -56,6 +139,9 @@ impl std::error::Error for Error {
             Error::Message(_) => None,
             Error::Io(err) => Some(err),
             Error::FromSlice(err) => Some(err),
+            Error::CorruptKeyFile(_) | Error::WrongFormatVersion(_) => None,
+            #[cfg(feature = "rich-errors")]
+            Error::Context { error, .. } => Some(error.as_ref()),
         }
     }
 }
@@ -79,3 +165,24 @@ macro_rules! validate {
         }
     };
 }
+
+/// Attaches operation context to the error of `$result` when the
+/// `rich-errors` feature is enabled. `$result` is evaluated within a closure,
+/// so that errors returned early with `?` also receive context. Buffer lengths
+/// are only evaluated on failure. Without the feature, evaluates to `$result`
+/// unchanged.
+macro_rules! with_context {
+    ($operation:literal, [$($name:ident = $len:expr),* $(,)?], $result:expr) => {{
+        #[cfg(feature = "rich-errors")]
+        {
+            #[allow(clippy::redundant_closure_call)]
+            (|| -> Result<_, crate::error::Error> { $result })().map_err(|err| {
+                err.with_context($operation, vec![$((stringify!($name), $len)),*])
+            })
+        }
+        #[cfg(not(feature = "rich-errors"))]
+        {
+            $result
+        }
+    }};
+}
@@ -0,0 +1,580 @@
+//! # Key escrow and recovery envelopes
+//!
+//! [`EscrowEnvelope`] encrypts a data key for a primary recipient, and
+//! additionally splits the same key into shares using Shamir's secret sharing
+//! over GF(2^8), encrypting each share to a recovery public key. Any
+//! `threshold` recovery key holders can cooperate to recover the data key,
+//! while fewer than `threshold` shares reveal nothing about it.
+//!
+//! The primary copy and each share are encrypted with a sealed
+//! [`DryocBox`](crate::dryocbox::DryocBox), so the party creating the
+//! envelope does not need a keypair of its own.
+//!
+//! You should use an [`EscrowEnvelope`] when you want to:
+//!
+//! * store a data key such that it can be recovered without the primary
+//!   recipient's secret key, as mandated by many enterprise deployments
+//! * require the cooperation of several recovery agents, rather than trusting a
+//!   single one
+//!
+//! If the `serde` feature is enabled, the [`serde::Deserialize`] and
+//! [`serde::Serialize`] traits will be implemented for [`EscrowEnvelope`] and
+//! [`RecoveryShare`].
+//!
+//! ## Rustaceous API example
+//!
+//! ```
+//! use dryoc::dryocbox::KeyPair;
+//! use dryoc::dryocsecretbox::Key;
+//! use dryoc::escrow::*;
+//! use dryoc::types::*;
+//!
+//! let data_key = Key::gen();
+//! let primary = KeyPair::gen();
+//! let recovery: Vec<KeyPair> = (0..3).map(|_| KeyPair::gen()).collect();
+//! let recovery_public_keys: Vec<_> = recovery.iter().map(|kp| kp.public_key.clone()).collect();
+//!
+//! // Any 2 of the 3 recovery agents can recover the key
+//! let envelope = EscrowEnvelope::seal(&data_key, &primary.public_key, &recovery_public_keys, 2)
+//!     .expect("seal failed");
+//!
+//! // The primary recipient can open the envelope directly
+//! let opened: Vec<u8> = envelope.open(&primary).expect("open failed");
+//! assert_eq!(opened, data_key.as_slice());
+//!
+//! // Recovery agents each decrypt their share, then combine them
+//! let shares = vec![
+//!     envelope.open_share(&recovery[0]).expect("share failed"),
+//!     envelope.open_share(&recovery[2]).expect("share failed"),
+//! ];
+//! let recovered: Vec<u8> = envelope.recover(&shares).expect("recover failed");
+//! assert_eq!(recovered, data_key.as_slice());
+//! ```
+//!
+//! ## Additional resources
+//!
+//! * See <https://en.wikipedia.org/wiki/Shamir%27s_secret_sharing> for details
+//!   on the secret sharing scheme
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use subtle::ConstantTimeEq;
+use zeroize::{Zeroize, ZeroizeOnDrop};
+
+use crate::canonical::{self, CanonicalDecode, CanonicalEncode};
+use crate::cbor::Value;
+use crate::constants::{CRYPTO_BOX_PUBLICKEYBYTES, CRYPTO_BOX_SECRETKEYBYTES};
+use crate::dryocbox::{DryocBox, PublicKey, VecBox};
+use crate::error::Error;
+use crate::keypair::KeyPair;
+use crate::rng::copy_randombytes;
+use crate::types::*;
+
+/// Maximum number of recovery recipients (shares) for an [`EscrowEnvelope`].
+pub const MAX_RECOVERY_RECIPIENTS: usize = 255;
+
+#[cfg_attr(
+    feature = "serde",
+    derive(Zeroize, ZeroizeOnDrop, Clone, Debug, Serialize, Deserialize)
+)]
+#[cfg_attr(not(feature = "serde"), derive(Zeroize, ZeroizeOnDrop, Clone, Debug))]
+/// A single decrypted share of an escrowed data key, as returned by
+/// [`EscrowEnvelope::open_share`].
+pub struct RecoveryShare {
+    index: u8,
+    value: Vec<u8>,
+}
+
+impl RecoveryShare {
+    /// Returns the share's index (x-coordinate), which is between 1 and
+    /// [`MAX_RECOVERY_RECIPIENTS`].
+    pub fn index(&self) -> u8 {
+        self.index
+    }
+
+    /// Returns the share's value.
+    pub fn value(&self) -> &[u8] {
+        &self.value
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(1 + self.value.len());
+        bytes.push(self.index);
+        bytes.extend_from_slice(&self.value);
+        bytes
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        match bytes.split_first() {
+            Some((&index, value)) if index != 0 => Ok(Self {
+                index,
+                value: value.to_vec(),
+            }),
+            _ => Err(dryoc_error!("invalid recovery share")),
+        }
+    }
+}
+
+#[cfg_attr(
+    feature = "serde",
+    derive(Zeroize, Clone, Debug, Serialize, Deserialize)
+)]
+#[cfg_attr(not(feature = "serde"), derive(Zeroize, Clone, Debug))]
+/// A recovery recipient within an [`EscrowEnvelope`]: the recipient's public
+/// key and their sealed share.
+pub struct RecoveryRecipient {
+    public_key: PublicKey,
+    sealed_share: VecBox,
+}
+
+impl RecoveryRecipient {
+    /// Returns the recovery recipient's public key.
+    pub fn public_key(&self) -> &PublicKey {
+        &self.public_key
+    }
+}
+
+#[cfg_attr(
+    feature = "serde",
+    derive(Zeroize, Clone, Debug, Serialize, Deserialize),
+    serde(try_from = "UncheckedEscrowEnvelope")
+)]
+#[cfg_attr(not(feature = "serde"), derive(Zeroize, Clone, Debug))]
+/// A data key encrypted for a primary recipient, with threshold recovery
+/// shares encrypted for one or more recovery recipients.
+///
+/// Refer to [crate::escrow] for sample usage.
+pub struct EscrowEnvelope {
+    primary: VecBox,
+    threshold: u8,
+    recovery: Vec<RecoveryRecipient>,
+}
+
+/// An envelope as deserialized, before its threshold is validated.
+#[cfg(feature = "serde")]
+#[derive(Deserialize)]
+struct UncheckedEscrowEnvelope {
+    primary: VecBox,
+    threshold: u8,
+    recovery: Vec<RecoveryRecipient>,
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<UncheckedEscrowEnvelope> for EscrowEnvelope {
+    type Error = Error;
+
+    fn try_from(envelope: UncheckedEscrowEnvelope) -> Result<Self, Error> {
+        let envelope = Self {
+            primary: envelope.primary,
+            threshold: envelope.threshold,
+            recovery: envelope.recovery,
+        };
+        envelope.validate()?;
+        Ok(envelope)
+    }
+}
+
+impl EscrowEnvelope {
+    /// Returns an error if the threshold isn't between 1 and the number of
+    /// recovery recipients, such as for a corrupt or tampered envelope.
+    fn validate(&self) -> Result<(), Error> {
+        if self.threshold == 0 || self.threshold as usize > self.recovery.len() {
+            Err(dryoc_error!(format!(
+                "invalid threshold {} for {} recovery recipients",
+                self.threshold,
+                self.recovery.len()
+            )))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Encrypts `data_key` for `primary_public_key`, and splits it into one
+    /// share per key in `recovery_public_keys`, such that any `threshold`
+    /// shares are sufficient to recover the data key.
+    pub fn seal<DataKey: Bytes + ?Sized>(
+        data_key: &DataKey,
+        primary_public_key: &PublicKey,
+        recovery_public_keys: &[PublicKey],
+        threshold: usize,
+    ) -> Result<Self, Error> {
+        if recovery_public_keys.is_empty() {
+            return Err(dryoc_error!("at least one recovery public key is required"));
+        }
+        validate!(
+            1,
+            MAX_RECOVERY_RECIPIENTS,
+            recovery_public_keys.len(),
+            "recovery recipients"
+        );
+        validate!(1, recovery_public_keys.len(), threshold, "threshold");
+
+        let primary = DryocBox::seal_to_vecbox(data_key, primary_public_key)?;
+
+        let shares = split(data_key.as_slice(), recovery_public_keys.len(), threshold);
+        let recovery = recovery_public_keys
+            .iter()
+            .zip(shares.iter())
+            .map(|(public_key, share)| {
+                let mut bytes = share.to_bytes();
+                let sealed_share = DryocBox::seal_to_vecbox(&bytes, public_key);
+                bytes.zeroize();
+                Ok(RecoveryRecipient {
+                    public_key: public_key.clone(),
+                    sealed_share: sealed_share?,
+                })
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+
+        Ok(Self {
+            primary,
+            threshold: threshold as u8,
+            recovery,
+        })
+    }
+
+    /// Returns the number of shares required to recover the data key.
+    pub fn threshold(&self) -> usize {
+        self.threshold as usize
+    }
+
+    /// Returns the recovery recipients for this envelope.
+    pub fn recovery_recipients(&self) -> &[RecoveryRecipient] {
+        &self.recovery
+    }
+
+    /// Decrypts the data key using the primary recipient's keypair.
+    pub fn open<
+        RecipientPublicKey: ByteArray<CRYPTO_BOX_PUBLICKEYBYTES> + Zeroize,
+        RecipientSecretKey: ByteArray<CRYPTO_BOX_SECRETKEYBYTES> + Zeroize,
+        Output: ResizableBytes + NewBytes + Zeroize,
+    >(
+        &self,
+        primary_keypair: &KeyPair<RecipientPublicKey, RecipientSecretKey>,
+    ) -> Result<Output, Error> {
+        self.primary.unseal(primary_keypair)
+    }
+
+    /// Decrypts the data key using the primary recipient's keypair.
+    ///
+    /// Same as [`EscrowEnvelope::open`], except the output container is the
+    /// only type parameter, so it can be selected with a turbofish, i.e.,
+    /// `envelope.open_to::<LockedBytes>(..)`.
+    pub fn open_to<Output: ResizableBytes + NewBytes + Zeroize>(
+        &self,
+        primary_keypair: &KeyPair<
+            impl ByteArray<CRYPTO_BOX_PUBLICKEYBYTES> + Zeroize,
+            impl ByteArray<CRYPTO_BOX_SECRETKEYBYTES> + Zeroize,
+        >,
+    ) -> Result<Output, Error> {
+        self.open(primary_keypair)
+    }
+
+    /// Decrypts the share belonging to `recovery_keypair`. Returns an error if
+    /// the keypair is not one of this envelope's recovery recipients.
+    pub fn open_share<
+        RecipientPublicKey: ByteArray<CRYPTO_BOX_PUBLICKEYBYTES> + Zeroize,
+        RecipientSecretKey: ByteArray<CRYPTO_BOX_SECRETKEYBYTES> + Zeroize,
+    >(
+        &self,
+        recovery_keypair: &KeyPair<RecipientPublicKey, RecipientSecretKey>,
+    ) -> Result<RecoveryShare, Error> {
+        let recipient = self
+            .recovery
+            .iter()
+            .find(|r| {
+                r.public_key
+                    .as_slice()
+                    .ct_eq(recovery_keypair.public_key.as_slice())
+                    .unwrap_u8()
+                    == 1
+            })
+            .ok_or_else(|| dryoc_error!("keypair is not a recovery recipient"))?;
+
+        let mut bytes: Vec<u8> = recipient.sealed_share.unseal(recovery_keypair)?;
+        let share = RecoveryShare::from_bytes(&bytes);
+        bytes.zeroize();
+        share
+    }
+
+    /// Combines at least [`threshold`](EscrowEnvelope::threshold) distinct
+    /// shares to recover the data key.
+    ///
+    /// Note that shares from a different envelope cannot be detected, and will
+    /// produce an incorrect key.
+    pub fn recover<Output: NewBytes + ResizableBytes>(
+        &self,
+        shares: &[RecoveryShare],
+    ) -> Result<Output, Error> {
+        self.validate()?;
+        if shares.len() < self.threshold() {
+            return Err(dryoc_error!(format!(
+                "{} shares provided, but at least {} are required",
+                shares.len(),
+                self.threshold()
+            )));
+        }
+        let shares = &shares[..self.threshold()];
+        let len = shares[0].value.len();
+        for (i, share) in shares.iter().enumerate() {
+            if share.value.len() != len {
+                return Err(dryoc_error!("recovery shares have mismatched lengths"));
+            }
+            if shares[..i].iter().any(|s| s.index == share.index) {
+                return Err(dryoc_error!(format!(
+                    "duplicate recovery share with index {}",
+                    share.index
+                )));
+            }
+        }
+
+        let mut output = Output::new_bytes();
+        output.resize(len, 0);
+        combine(output.as_mut_slice(), shares);
+        Ok(output)
+    }
+}
+
+/// Multiplication in GF(2^8) with the AES polynomial, in constant time.
+fn gf256_mul(mut a: u8, mut b: u8) -> u8 {
+    let mut r = 0u8;
+    for _ in 0..8 {
+        r ^= (b & 1).wrapping_neg() & a;
+        let carry = (a >> 7).wrapping_neg();
+        a = (a << 1) ^ (carry & 0x1b);
+        b >>= 1;
+    }
+    r
+}
+
+/// Multiplicative inverse in GF(2^8), computed as `a^254`.
+fn gf256_inv(a: u8) -> u8 {
+    let mut r = 1u8;
+    let mut base = a;
+    let mut exp = 254u8;
+    while exp > 0 {
+        if exp & 1 == 1 {
+            r = gf256_mul(r, base);
+        }
+        base = gf256_mul(base, base);
+        exp >>= 1;
+    }
+    r
+}
+
+fn split(secret: &[u8], count: usize, threshold: usize) -> Vec<RecoveryShare> {
+    let mut shares: Vec<RecoveryShare> = (1..=count)
+        .map(|x| RecoveryShare {
+            index: x as u8,
+            value: vec![0u8; secret.len()],
+        })
+        .collect();
+    let mut coefficients = vec![0u8; threshold];
+
+    for (i, byte) in secret.iter().enumerate() {
+        coefficients[0] = *byte;
+        copy_randombytes(&mut coefficients[1..]);
+        for share in shares.iter_mut() {
+            // evaluate the polynomial at x = share.index, using Horner's method
+            share.value[i] = coefficients
+                .iter()
+                .rev()
+                .fold(0u8, |acc, c| gf256_mul(acc, share.index) ^ c);
+        }
+    }
+
+    coefficients.zeroize();
+    shares
+}
+
+fn combine(output: &mut [u8], shares: &[RecoveryShare]) {
+    output.fill(0);
+    for (i, share) in shares.iter().enumerate() {
+        // Lagrange basis polynomial for this share, evaluated at x = 0
+        let mut basis = 1u8;
+        for (j, other) in shares.iter().enumerate() {
+            if i != j {
+                basis = gf256_mul(
+                    basis,
+                    gf256_mul(other.index, gf256_inv(other.index ^ share.index)),
+                );
+            }
+        }
+        for (out, y) in output.iter_mut().zip(share.value.iter()) {
+            *out ^= gf256_mul(*y, basis);
+        }
+    }
+}
+
+impl CanonicalEncode for EscrowEnvelope {
+    fn to_canonical_bytes(&self) -> Vec<u8> {
+        let recovery = self
+            .recovery
+            .iter()
+            .map(|recipient| {
+                Value::Array(vec![
+                    Value::Bytes(recipient.public_key.to_vec()),
+                    Value::Array(recipient.sealed_share.canonical_fields()),
+                ])
+            })
+            .collect();
+        canonical::encode(
+            "dryoc/EscrowEnvelope",
+            vec![
+                Value::Array(self.primary.canonical_fields()),
+                Value::Unsigned(self.threshold as u64),
+                Value::Array(recovery),
+            ],
+        )
+    }
+}
+
+impl CanonicalDecode for EscrowEnvelope {
+    fn from_canonical_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        let fields = canonical::decode("dryoc/EscrowEnvelope", bytes, 3)?;
+        let recovery = canonical::array_field(&fields[2])?
+            .iter()
+            .map(|recipient| {
+                let recipient = canonical::record_field(recipient, 2)?;
+                Ok(RecoveryRecipient {
+                    public_key: canonical::byte_array_field(&recipient[0])?,
+                    sealed_share: VecBox::from_canonical_fields(canonical::record_field(
+                        &recipient[1],
+                        3,
+                    )?)?,
+                })
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+        let envelope = Self {
+            primary: VecBox::from_canonical_fields(canonical::record_field(&fields[0], 3)?)?,
+            threshold: u8::try_from(canonical::unsigned_field(&fields[1])?)
+                .map_err(|_| dryoc_error!("invalid threshold"))?,
+            recovery,
+        };
+        envelope.validate()?;
+        Ok(envelope)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dryocbox::KeyPair;
+    use crate::dryocsecretbox::Key;
+
+    #[test]
+    fn test_gf256() {
+        for a in 1..=255u8 {
+            assert_eq!(gf256_mul(a, gf256_inv(a)), 1);
+        }
+        assert_eq!(gf256_mul(0x57, 0x83), 0xc1);
+    }
+
+    #[test]
+    fn test_split_combine() {
+        let secret = b"a secret of arbitrary length";
+        let shares = split(secret, 5, 3);
+
+        for a in 0..5 {
+            for b in 0..5 {
+                for c in 0..5 {
+                    if a == b || b == c || a == c {
+                        continue;
+                    }
+                    let subset = [shares[a].clone(), shares[b].clone(), shares[c].clone()];
+                    let mut output = vec![0u8; secret.len()];
+                    combine(&mut output, &subset);
+                    assert_eq!(&output, secret);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_canonical_envelope() {
+        let data_key = Key::gen();
+        let primary = KeyPair::gen();
+        let recovery: Vec<_> = (0..3).map(|_| KeyPair::gen().public_key.clone()).collect();
+        let envelope =
+            EscrowEnvelope::seal(&data_key, &primary.public_key, &recovery, 2).expect("seal");
+
+        let bytes = envelope.to_canonical_bytes();
+        let decoded = EscrowEnvelope::from_canonical_bytes(&bytes).expect("decode failed");
+        assert_eq!(decoded.to_canonical_bytes(), bytes);
+        assert_eq!(decoded.threshold(), 2);
+        assert_eq!(decoded.recovery_recipients().len(), 3);
+        let opened: Vec<u8> = decoded.open(&primary).expect("open failed");
+        assert_eq!(opened, data_key.as_slice());
+    }
+
+    #[test]
+    fn test_envelope() {
+        let data_key = Key::gen();
+        let primary = KeyPair::gen();
+        let recovery: Vec<KeyPair> = (0..4).map(|_| KeyPair::gen()).collect();
+        let recovery_public_keys: Vec<_> =
+            recovery.iter().map(|kp| kp.public_key.clone()).collect();
+
+        let envelope =
+            EscrowEnvelope::seal(&data_key, &primary.public_key, &recovery_public_keys, 3)
+                .expect("seal failed");
+        assert_eq!(envelope.threshold(), 3);
+        assert_eq!(envelope.recovery_recipients().len(), 4);
+
+        let opened: Vec<u8> = envelope.open(&primary).expect("open failed");
+        assert_eq!(opened, data_key.as_slice());
+
+        let shares: Vec<RecoveryShare> = recovery
+            .iter()
+            .map(|kp| envelope.open_share(kp).expect("share failed"))
+            .collect();
+
+        let recovered: Vec<u8> = envelope.recover(&shares[1..]).expect("recover failed");
+        assert_eq!(recovered, data_key.as_slice());
+
+        envelope
+            .recover::<Vec<u8>>(&shares[..2])
+            .expect_err("should fail with too few shares");
+        envelope
+            .recover::<Vec<u8>>(&[shares[0].clone(), shares[0].clone(), shares[1].clone()])
+            .expect_err("should fail with duplicate shares");
+        envelope
+            .open_share(&KeyPair::gen())
+            .expect_err("should fail for unknown recipient");
+        envelope
+            .open::<_, _, Vec<u8>>(&recovery[0])
+            .expect_err("should fail for wrong recipient");
+    }
+
+    #[test]
+    fn test_invalid_threshold() {
+        let primary = KeyPair::gen();
+        let recovery = vec![KeyPair::gen().public_key.clone()];
+
+        EscrowEnvelope::seal(&Key::gen(), &primary.public_key, &recovery, 0)
+            .expect_err("threshold of 0 should fail");
+        EscrowEnvelope::seal(&Key::gen(), &primary.public_key, &recovery, 2)
+            .expect_err("threshold above recipient count should fail");
+        EscrowEnvelope::seal(&Key::gen(), &primary.public_key, &[], 1)
+            .expect_err("no recipients should fail");
+
+        // stored envelopes with an invalid threshold are rejected when decoded
+        let mut envelope =
+            EscrowEnvelope::seal(&Key::gen(), &primary.public_key, &recovery, 1).expect("seal");
+        for threshold in [0, 2] {
+            envelope.threshold = threshold;
+            EscrowEnvelope::from_canonical_bytes(&envelope.to_canonical_bytes())
+                .expect_err("invalid threshold should fail to decode");
+            envelope
+                .recover::<Vec<u8>>(&[])
+                .expect_err("invalid threshold should fail to recover");
+
+            #[cfg(feature = "serde")]
+            {
+                let json = serde_json::to_string(&envelope).expect("serialize failed");
+                serde_json::from_str::<EscrowEnvelope>(&json)
+                    .expect_err("invalid threshold should fail to deserialize");
+            }
+        }
+    }
+}
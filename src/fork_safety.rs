@@ -0,0 +1,236 @@
+//! # Fork safety
+//!
+//! After `fork()`, the child process starts with a copy of the parent's memory,
+//! which includes any buffered random number generator state. If both
+//! processes continue drawing from the same state, they'll produce the same
+//! "random" values (such as nonces), which is catastrophic for most
+//! constructions. Additionally, memory locks are not inherited by the child,
+//! so any [protected](crate::protected) memory which was locked in the parent
+//! is no longer locked in the child.
+//!
+//! Calling [`install()`] registers `pthread_atfork()` handlers which increment
+//! the [fork generation](fork_generation) in the child, which causes any
+//! buffered random number generator state to be discarded and reseeded from
+//! the OS before its next use. Optionally, the handlers can also re-lock
+//! protected memory regions in the child, which you can check with
+//! [`verify()`].
+//!
+//! Installing the handlers is idempotent, and is a no-op on platforms without
+//! `fork()`.
+//!
+//! ## Example
+//!
+//! ```
+//! use dryoc::fork_safety;
+//!
+//! // Call once at startup, before forking any workers
+//! fork_safety::install(fork_safety::Config::default().with_relock_protected(true))
+//!     .expect("install failed");
+//!
+//! // ... then, in each worker after forking ...
+//! fork_safety::verify().expect("protected memory not re-locked");
+//! ```
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+
+use crate::error::Error;
+
+static FORK_GENERATION: AtomicU64 = AtomicU64::new(0);
+static RELOCK_PROTECTED: AtomicBool = AtomicBool::new(false);
+static RELOCK_FAILURES: AtomicUsize = AtomicUsize::new(0);
+static INSTALLED: Mutex<bool> = Mutex::new(false);
+
+/// Fork safety configuration, for use with [`install()`].
+#[derive(Clone, Debug, Default)]
+pub struct Config {
+    relock_protected: bool,
+}
+
+impl Config {
+    /// Returns this config with `relock_protected`, which re-locks any
+    /// locked [protected](crate::protected) memory regions in the child after
+    /// fork. Has no effect unless the `nightly` feature is enabled.
+    #[must_use]
+    pub fn with_relock_protected(self, relock_protected: bool) -> Self {
+        Self { relock_protected }
+    }
+}
+
+/// Installs the fork handlers described in the [module
+/// documentation](self). May be called more than once, in which case the most
+/// recent `config` takes effect, but the handlers are only registered once.
+pub fn install(config: Config) -> Result<(), Error> {
+    RELOCK_PROTECTED.store(config.relock_protected, Ordering::SeqCst);
+
+    let mut installed = INSTALLED
+        .lock()
+        .map_err(|err| dryoc_error!(format!("unable to acquire lock: {}", err)))?;
+    if !*installed {
+        #[cfg(unix)]
+        {
+            let ret = unsafe { libc::pthread_atfork(Some(prepare), Some(parent), Some(child)) };
+            if ret != 0 {
+                return Err(dryoc_error!(format!(
+                    "pthread_atfork failed: {}",
+                    std::io::Error::from_raw_os_error(ret)
+                )));
+            }
+        }
+        *installed = true;
+    }
+
+    Ok(())
+}
+
+/// Returns the number of times this process has been forked (from the
+/// perspective of the current process) since the handlers were
+/// [installed](install). Stateful generators compare this against the value
+/// observed when they were seeded, and reseed when it changes.
+pub fn fork_generation() -> u64 {
+    FORK_GENERATION.load(Ordering::SeqCst)
+}
+
+/// Returns an error if any protected memory regions could not be re-locked in
+/// this process after fork. Always succeeds in a process which hasn't been
+/// forked, or when re-locking is disabled.
+pub fn verify() -> Result<(), Error> {
+    match RELOCK_FAILURES.load(Ordering::SeqCst) {
+        0 => Ok(()),
+        failures => Err(dryoc_error!(format!(
+            "unable to re-lock {} protected memory region(s) after fork",
+            failures
+        ))),
+    }
+}
+
+#[cfg(unix)]
+extern "C" fn prepare() {
+    #[cfg(feature = "nightly")]
+    regions::acquire();
+}
+
+#[cfg(unix)]
+extern "C" fn parent() {
+    #[cfg(feature = "nightly")]
+    regions::release();
+}
+
+#[cfg(unix)]
+extern "C" fn child() {
+    FORK_GENERATION.fetch_add(1, Ordering::SeqCst);
+    #[cfg(feature = "nightly")]
+    {
+        if RELOCK_PROTECTED.load(Ordering::SeqCst) {
+            RELOCK_FAILURES.store(regions::relock(), Ordering::SeqCst);
+        }
+        regions::release();
+    }
+}
+
+#[cfg(feature = "nightly")]
+pub(crate) mod regions {
+    //! Registry of locked memory regions, so they can be re-locked after fork.
+    //! The registry is guarded by a spin lock rather than a mutex, because it
+    //! must be held across `fork()` by the atfork handlers.
+    use std::cell::UnsafeCell;
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    struct Registry {
+        busy: AtomicBool,
+        regions: UnsafeCell<Vec<(usize, usize)>>,
+    }
+
+    unsafe impl Sync for Registry {}
+
+    static REGISTRY: Registry = Registry {
+        busy: AtomicBool::new(false),
+        regions: UnsafeCell::new(Vec::new()),
+    };
+
+    pub(super) fn acquire() {
+        while REGISTRY
+            .busy
+            .compare_exchange_weak(false, true, Ordering::Acquire, Ordering::Relaxed)
+            .is_err()
+        {
+            std::hint::spin_loop();
+        }
+    }
+
+    pub(super) fn release() {
+        REGISTRY.busy.store(false, Ordering::Release);
+    }
+
+    fn with_regions<R>(f: impl FnOnce(&mut Vec<(usize, usize)>) -> R) -> R {
+        acquire();
+        let result = f(unsafe { &mut *REGISTRY.regions.get() });
+        release();
+        result
+    }
+
+    /// Records that `data` has been locked.
+    pub(crate) fn insert(data: &[u8]) {
+        with_regions(|regions| regions.push((data.as_ptr() as usize, data.len())));
+    }
+
+    /// Records that `data` has been unlocked.
+    pub(crate) fn remove(data: &[u8]) {
+        let region = (data.as_ptr() as usize, data.len());
+        with_regions(|regions| {
+            if let Some(pos) = regions.iter().position(|r| *r == region) {
+                regions.swap_remove(pos);
+            }
+        });
+    }
+
+    /// Re-locks every registered region, returning the number of failures.
+    /// Must be called with the registry already acquired, and must not
+    /// allocate, as it runs in the child immediately after fork.
+    #[cfg(unix)]
+    pub(super) fn relock() -> usize {
+        let regions = unsafe { &*REGISTRY.regions.get() };
+        regions
+            .iter()
+            .filter(|(ptr, len)| unsafe { libc::mlock(*ptr as *const libc::c_void, *len) } != 0)
+            .count()
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    fn in_child(f: impl FnOnce() -> bool) -> bool {
+        let pid = unsafe { libc::fork() };
+        if pid == 0 {
+            let ok = f();
+            unsafe { libc::_exit(if ok { 0 } else { 1 }) };
+        }
+        assert!(pid > 0, "fork failed");
+        let mut status = 0;
+        unsafe { libc::waitpid(pid, &mut status, 0) };
+        libc::WIFEXITED(status) && libc::WEXITSTATUS(status) == 0
+    }
+
+    #[test]
+    fn test_fork_generation() {
+        install(Config::default()).expect("install failed");
+        install(Config::default()).expect("second install failed");
+
+        let generation = fork_generation();
+        assert!(in_child(|| fork_generation() == generation + 1));
+        assert_eq!(fork_generation(), generation);
+        verify().expect("verify failed");
+    }
+
+    #[cfg(feature = "nightly")]
+    #[test]
+    fn test_relock_protected() {
+        use crate::protected::*;
+
+        install(Config::default().with_relock_protected(true)).expect("install failed");
+
+        let key = HeapByteArray::<32>::gen_locked().expect("lock failed");
+        assert!(in_child(|| verify().is_ok() && key.as_slice().len() == 32));
+    }
+}
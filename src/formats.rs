@@ -0,0 +1,558 @@
+//! # Wire formats
+//!
+//! This module documents the exact byte layouts produced by the byte
+//! serialization methods (`to_vec()`, `to_bytes()`, and their `from_bytes()`
+//! counterparts) of each high-level type, and provides a small versioned
+//! envelope for persisting them.
+//!
+//! The layouts below are stable: they're covered by snapshot tests, and any
+//! incompatible change will be accompanied by a new [`Format`] version.
+//! Multi-byte lengths are shown in bytes.
+//!
+//! | Type | Layout |
+//! |-|-|
+//! | [`DryocSecretBox`](crate::dryocsecretbox::DryocSecretBox) | tag (16) ‖ ciphertext |
+//! | [`DryocBox`](crate::dryocbox::DryocBox) | tag (16) ‖ ciphertext |
+//! | Sealed [`DryocBox`](crate::dryocbox::DryocBox) | ephemeral public key (32) ‖ tag (16) ‖ ciphertext |
+//! | [`SignedMessage`](crate::sign::SignedMessage) | signature (64) ‖ message |
+//! | [`DryocStream`](crate::dryocstream::DryocStream) | header (24), then for each message: encrypted tag (1) ‖ ciphertext ‖ tag (16) |
+//! | [`PwHash`](crate::pwhash::PwHash) string | PHC string, i.e., `$argon2id$v=19$m=<memlimit KiB>,t=<opslimit>,p=1$<salt>$<hash>` |
+//!
+//! Nonces are never included in the layouts above, and must be stored or
+//! transmitted separately.
+//!
+//! ## Versioned envelopes
+//!
+//! Because the layouts above don't identify themselves, data which is persisted
+//! (and may be read by a later version of this crate) should be wrapped in a
+//! versioned envelope, using the `to_versioned_vec()` and
+//! `from_versioned_bytes()` methods on each type. An envelope is laid out as:
+//!
+//! format version (1) ‖ [`Kind`] (1) ‖ payload
+//!
+//! where the payload is the layout of the type, as described above, for the
+//! given format version.
+//!
+//! ## Checksummed containers
+//!
+//! Keys and other data persisted to disk can also be wrapped in a checksummed
+//! container, using [`encode_checksummed`] and [`decode_checksummed`], or the
+//! `to_checksummed_bytes()` and `from_checksummed_bytes()` methods on
+//! [`KeyPair`](crate::keypair::KeyPair) and
+//! [`SigningKeyPair`](crate::sign::SigningKeyPair). A container is laid out
+//! as:
+//!
+//! magic (4, `DRYC`) ‖ format version (1) ‖ [`Kind`] (1) ‖ payload length (4,
+//! big-endian) ‖ payload ‖ checksum (32)
+//!
+//! where the checksum is the unkeyed 32 byte [generic
+//! hash](crate::generichash) (BLAKE2b) of everything before it. Decoding
+//! distinguishes truncated or damaged files ([`Error::CorruptKeyFile`]) from
+//! files written by a newer version of this crate
+//! ([`Error::WrongFormatVersion`]), so operators get an actionable error rather
+//! than a generic decryption failure. The checksum only detects accidental
+//! damage: it's not a MAC, and doesn't protect against deliberate tampering.
+//!
+//! ## Example
+//!
+//! ```
+//! use dryoc::dryocsecretbox::*;
+//! use dryoc::formats::{Format, Kind};
+//!
+//! let secret_key = Key::gen();
+//! let nonce = Nonce::gen();
+//!
+//! let dryocsecretbox = DryocSecretBox::encrypt_to_vecbox(b"hello", &nonce, &secret_key);
+//! let persisted = dryocsecretbox.to_versioned_vec();
+//!
+//! let (format, kind, _) = dryoc::formats::decode_envelope(&persisted).expect("decode failed");
+//! assert_eq!(format, Format::V1);
+//! assert_eq!(kind, Kind::SecretBox);
+//!
+//! let restored = VecBox::from_versioned_bytes(&persisted).expect("decode failed");
+//! let decrypted = restored
+//!     .decrypt_to_vec(&nonce, &secret_key)
+//!     .expect("decrypt failed");
+//! assert_eq!(decrypted, b"hello");
+//! ```
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use subtle::ConstantTimeEq;
+
+use crate::constants::{CRYPTO_GENERICHASH_BYTES, CRYPTO_GENERICHASH_KEYBYTES};
+use crate::error::Error;
+use crate::generichash::GenericHash;
+use crate::types::*;
+
+/// Length of the versioned envelope header, in bytes.
+pub const ENVELOPE_HEADER_BYTES: usize = 2;
+/// Magic bytes at the start of a checksummed container.
+pub const CHECKSUMMED_MAGIC: [u8; 4] = *b"DRYC";
+/// Length of the checksummed container header, in bytes.
+pub const CHECKSUMMED_HEADER_BYTES: usize = 10;
+/// Length of the checksum at the end of a checksummed container, in bytes.
+pub const CHECKSUM_BYTES: usize = CRYPTO_GENERICHASH_BYTES;
+
+#[cfg_attr(
+    feature = "serde",
+    derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)
+)]
+#[cfg_attr(
+    not(feature = "serde"),
+    derive(Clone, Copy, Debug, PartialEq, Eq, Hash)
+)]
+#[non_exhaustive]
+/// Wire format version.
+pub enum Format {
+    /// The initial wire format, as documented in [crate::formats].
+    V1,
+}
+
+impl Format {
+    /// The format used when serializing.
+    pub const CURRENT: Format = Format::V1;
+
+    /// Returns the version number of this format, as encoded in envelopes.
+    pub fn version(self) -> u8 {
+        match self {
+            Format::V1 => 1,
+        }
+    }
+
+    /// Returns the format for `version`, or an error if the version is unknown
+    /// to this version of the crate.
+    pub fn from_version(version: u8) -> Result<Self, Error> {
+        match version {
+            1 => Ok(Format::V1),
+            _ => Err(dryoc_error!(format!(
+                "unsupported format version {}",
+                version
+            ))),
+        }
+    }
+}
+
+#[cfg_attr(
+    feature = "serde",
+    derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)
+)]
+#[cfg_attr(
+    not(feature = "serde"),
+    derive(Clone, Copy, Debug, PartialEq, Eq, Hash)
+)]
+#[non_exhaustive]
+/// Type of the payload within a versioned envelope.
+pub enum Kind {
+    /// A [`DryocSecretBox`](crate::dryocsecretbox::DryocSecretBox)
+    SecretBox,
+    /// A [`DryocBox`](crate::dryocbox::DryocBox)
+    Box,
+    /// A sealed [`DryocBox`](crate::dryocbox::DryocBox)
+    SealedBox,
+    /// A [`SignedMessage`](crate::sign::SignedMessage)
+    SignedMessage,
+    /// A [`KeyPair`](crate::keypair::KeyPair), as public key (32) ‖ secret key
+    /// (32)
+    KeyPair,
+    /// A [`SigningKeyPair`](crate::sign::SigningKeyPair), as its secret key
+    /// (64), which contains the public key
+    SigningKeyPair,
+    /// A symmetric secret key, such as a
+    /// [`dryocsecretbox::Key`](crate::dryocsecretbox::Key)
+    SecretKey,
+    /// An [`Envelope`](crate::envelope::Envelope), with its payload laid out
+    /// as described in [crate::envelope]
+    DataKeyEnvelope,
+    /// An [`Envelope`](crate::envelope::Envelope) with
+    /// [`Provenance`](crate::envelope::Provenance), with its payload laid out
+    /// as described in [crate::envelope]
+    DataKeyEnvelopeWithProvenance,
+}
+
+impl Kind {
+    /// Returns the identifier of this kind, as encoded in envelopes.
+    pub fn id(self) -> u8 {
+        match self {
+            Kind::SecretBox => 1,
+            Kind::Box => 2,
+            Kind::SealedBox => 3,
+            Kind::SignedMessage => 4,
+            Kind::KeyPair => 5,
+            Kind::SigningKeyPair => 6,
+            Kind::SecretKey => 7,
+            Kind::DataKeyEnvelope => 8,
+            Kind::DataKeyEnvelopeWithProvenance => 9,
+        }
+    }
+
+    /// Returns the kind for `id`, or an error if the identifier is unknown.
+    pub fn from_id(id: u8) -> Result<Self, Error> {
+        match id {
+            1 => Ok(Kind::SecretBox),
+            2 => Ok(Kind::Box),
+            3 => Ok(Kind::SealedBox),
+            4 => Ok(Kind::SignedMessage),
+            5 => Ok(Kind::KeyPair),
+            6 => Ok(Kind::SigningKeyPair),
+            7 => Ok(Kind::SecretKey),
+            8 => Ok(Kind::DataKeyEnvelope),
+            9 => Ok(Kind::DataKeyEnvelopeWithProvenance),
+            _ => Err(dryoc_error!(format!("unknown payload kind {}", id))),
+        }
+    }
+}
+
+/// Wraps `payload` in a versioned envelope with `format` and `kind`.
+pub fn encode_envelope<Output: NewBytes + ResizableBytes>(
+    format: Format,
+    kind: Kind,
+    payload: &[u8],
+) -> Output {
+    let mut output = Output::new_bytes();
+    output.resize(ENVELOPE_HEADER_BYTES + payload.len(), 0);
+    let s = output.as_mut_slice();
+    s[0] = format.version();
+    s[1] = kind.id();
+    s[ENVELOPE_HEADER_BYTES..].copy_from_slice(payload);
+    output
+}
+
+/// Unwraps a versioned envelope, returning its format, kind, and payload.
+pub fn decode_envelope(bytes: &[u8]) -> Result<(Format, Kind, &[u8]), Error> {
+    if bytes.len() < ENVELOPE_HEADER_BYTES {
+        return Err(dryoc_error!(format!(
+            "bytes of len {} less than expected minimum of {}",
+            bytes.len(),
+            ENVELOPE_HEADER_BYTES
+        )));
+    }
+    let format = Format::from_version(bytes[0])?;
+    let kind = Kind::from_id(bytes[1])?;
+    Ok((format, kind, &bytes[ENVELOPE_HEADER_BYTES..]))
+}
+
+/// Unwraps a versioned envelope, returning the payload if the envelope
+/// contains one of `expected` kinds.
+pub(crate) fn decode_envelope_of<'a>(
+    bytes: &'a [u8],
+    expected: &[Kind],
+) -> Result<(Kind, &'a [u8]), Error> {
+    let (_format, kind, payload) = decode_envelope(bytes)?;
+    if expected.contains(&kind) {
+        Ok((kind, payload))
+    } else {
+        Err(dryoc_error!(format!(
+            "unexpected payload kind {:?}, expected one of {:?}",
+            kind, expected
+        )))
+    }
+}
+
+fn checksum(bytes: &[u8]) -> [u8; CHECKSUM_BYTES] {
+    GenericHash::<CRYPTO_GENERICHASH_KEYBYTES, CHECKSUM_BYTES>::digest(bytes)
+}
+
+/// Wraps `payload` in a checksummed container with `format` and `kind`.
+pub fn encode_checksummed<Output: NewBytes + ResizableBytes>(
+    format: Format,
+    kind: Kind,
+    payload: &[u8],
+) -> Output {
+    let body_len = CHECKSUMMED_HEADER_BYTES + payload.len();
+    let mut output = Output::new_bytes();
+    output.resize(body_len + CHECKSUM_BYTES, 0);
+    let s = output.as_mut_slice();
+    s[..4].copy_from_slice(&CHECKSUMMED_MAGIC);
+    s[4] = format.version();
+    s[5] = kind.id();
+    s[6..CHECKSUMMED_HEADER_BYTES].copy_from_slice(&(payload.len() as u32).to_be_bytes());
+    s[CHECKSUMMED_HEADER_BYTES..body_len].copy_from_slice(payload);
+    let checksum = checksum(&s[..body_len]);
+    s[body_len..].copy_from_slice(&checksum);
+    output
+}
+
+/// Unwraps a checksummed container, returning its format, kind, and payload.
+///
+/// Returns [`Error::CorruptKeyFile`] if `bytes` isn't a checksummed
+/// container, is truncated, or fails its checksum, and
+/// [`Error::WrongFormatVersion`] if it was written with an unsupported
+/// format version.
+pub fn decode_checksummed(bytes: &[u8]) -> Result<(Format, Kind, &[u8]), Error> {
+    if bytes.len() < CHECKSUMMED_MAGIC.len() || bytes[..4] != CHECKSUMMED_MAGIC {
+        return Err(Error::CorruptKeyFile(
+            "missing magic bytes, not a checksummed container".into(),
+        ));
+    }
+    if bytes.len() < CHECKSUMMED_HEADER_BYTES {
+        return Err(Error::CorruptKeyFile(format!(
+            "truncated header, {} bytes is less than the minimum of {}",
+            bytes.len(),
+            CHECKSUMMED_HEADER_BYTES
+        )));
+    }
+    let format = Format::from_version(bytes[4]).map_err(|_| Error::WrongFormatVersion(bytes[4]))?;
+
+    let mut payload_len = [0u8; 4];
+    payload_len.copy_from_slice(&bytes[6..CHECKSUMMED_HEADER_BYTES]);
+    let body_len = CHECKSUMMED_HEADER_BYTES + u32::from_be_bytes(payload_len) as usize;
+    if bytes.len() != body_len + CHECKSUM_BYTES {
+        return Err(Error::CorruptKeyFile(format!(
+            "expected {} bytes, found {}, file may be truncated",
+            body_len + CHECKSUM_BYTES,
+            bytes.len()
+        )));
+    }
+    if checksum(&bytes[..body_len])
+        .ct_eq(&bytes[body_len..])
+        .unwrap_u8()
+        == 0
+    {
+        return Err(Error::CorruptKeyFile("checksum mismatch".into()));
+    }
+
+    let kind = Kind::from_id(bytes[5])
+        .map_err(|_| Error::CorruptKeyFile(format!("unknown payload kind {}", bytes[5])))?;
+    Ok((format, kind, &bytes[CHECKSUMMED_HEADER_BYTES..body_len]))
+}
+
+/// Unwraps a checksummed container, returning the payload if the container
+/// contains the `expected` kind.
+pub(crate) fn decode_checksummed_of(bytes: &[u8], expected: Kind) -> Result<&[u8], Error> {
+    let (_format, kind, payload) = decode_checksummed(bytes)?;
+    if kind == expected {
+        Ok(payload)
+    } else {
+        Err(dryoc_error!(format!(
+            "unexpected payload kind {:?}, expected {:?}",
+            kind, expected
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unhex(s: &str) -> Vec<u8> {
+        hex::decode(s).expect("invalid hex")
+    }
+
+    #[test]
+    fn test_envelope() {
+        let envelope: Vec<u8> = encode_envelope(Format::V1, Kind::SignedMessage, b"payload");
+        assert_eq!(envelope, b"\x01\x04payload");
+
+        let (format, kind, payload) = decode_envelope(&envelope).expect("decode failed");
+        assert_eq!(format, Format::V1);
+        assert_eq!(kind, Kind::SignedMessage);
+        assert_eq!(payload, b"payload");
+
+        decode_envelope(b"\x01").expect_err("too short");
+        decode_envelope(b"\x02\x01").expect_err("unknown version");
+        decode_envelope(b"\x01\x00").expect_err("unknown kind");
+        decode_envelope_of(&envelope, &[Kind::Box]).expect_err("wrong kind");
+    }
+
+    #[test]
+    fn test_checksummed() {
+        let container: Vec<u8> = encode_checksummed(Format::V1, Kind::SecretKey, b"payload");
+        assert_eq!(
+            &container[..CHECKSUMMED_HEADER_BYTES],
+            b"DRYC\x01\x07\0\0\0\x07"
+        );
+        assert_eq!(
+            container.len(),
+            CHECKSUMMED_HEADER_BYTES + 7 + CHECKSUM_BYTES
+        );
+
+        let (format, kind, payload) = decode_checksummed(&container).expect("decode failed");
+        assert_eq!(format, Format::V1);
+        assert_eq!(kind, Kind::SecretKey);
+        assert_eq!(payload, b"payload");
+
+        for len in 0..container.len() {
+            assert!(matches!(
+                decode_checksummed(&container[..len]),
+                Err(Error::CorruptKeyFile(_))
+            ));
+        }
+        for i in 0..container.len() {
+            let mut corrupted = container.clone();
+            corrupted[i] ^= 0x10;
+            let result = decode_checksummed(&corrupted);
+            if i == 4 {
+                assert!(matches!(result, Err(Error::WrongFormatVersion(0x11))));
+            } else {
+                assert!(matches!(result, Err(Error::CorruptKeyFile(_))), "{}", i);
+            }
+        }
+        decode_checksummed_of(&container, Kind::KeyPair).expect_err("wrong kind");
+    }
+
+    #[test]
+    fn test_checksummed_keypairs() {
+        use crate::keypair::StackKeyPair;
+        use crate::sign::{PublicKey, SecretKey, SigningKeyPair};
+
+        let keypair = StackKeyPair::gen();
+        let container: Vec<u8> = keypair.to_checksummed_bytes();
+        let restored = StackKeyPair::from_checksummed_bytes(&container).expect("decode failed");
+        assert_eq!(restored, keypair);
+
+        let signing_keypair = SigningKeyPair::gen_with_defaults();
+        let container: Vec<u8> = signing_keypair.to_checksummed_bytes();
+        let restored = SigningKeyPair::<PublicKey, SecretKey>::from_checksummed_bytes(&container)
+            .expect("decode failed");
+        assert_eq!(restored.public_key, signing_keypair.public_key);
+        assert_eq!(restored.secret_key, signing_keypair.secret_key);
+        StackKeyPair::from_checksummed_bytes(&container).expect_err("wrong kind");
+    }
+
+    #[test]
+    fn test_secretbox_snapshot() {
+        use crate::dryocsecretbox::*;
+
+        let key = Key::from(&[1u8; 32]);
+        let nonce = Nonce::from(&[2u8; 24]);
+        let dryocsecretbox = DryocSecretBox::encrypt_to_vecbox(b"dryoc", &nonce, &key);
+
+        let persisted = dryocsecretbox.to_versioned_vec();
+        // matches libsodium's crypto_secretbox_easy(), with the envelope header
+        assert_eq!(
+            persisted,
+            unhex("01017c820e5461284808f207e5637f935bd99917a7921d")
+        );
+
+        let restored = VecBox::from_versioned_bytes(&persisted).expect("decode failed");
+        assert_eq!(restored.to_vec(), dryocsecretbox.to_vec());
+        assert_eq!(
+            restored
+                .decrypt_to_vec(&nonce, &key)
+                .expect("decrypt failed"),
+            b"dryoc"
+        );
+    }
+
+    #[test]
+    fn test_box_snapshot() {
+        use crate::dryocbox::*;
+
+        let sender = KeyPair::from_seed(&[1u8; 32]);
+        let recipient = KeyPair::from_seed(&[2u8; 32]);
+        let nonce = Nonce::from(&[3u8; 24]);
+        let dryocbox = DryocBox::encrypt_to_vecbox(
+            b"dryoc",
+            &nonce,
+            &recipient.public_key,
+            &sender.secret_key,
+        )
+        .expect("encrypt failed");
+
+        let persisted = dryocbox.to_versioned_vec();
+        assert_eq!(
+            persisted,
+            unhex("01025345db49c7728e2b25efd836d5e718a0700aeaca1a")
+        );
+
+        let restored = VecBox::from_versioned_bytes(&persisted).expect("decode failed");
+        assert_eq!(
+            restored
+                .decrypt_to_vec(&nonce, &sender.public_key, &recipient.secret_key)
+                .expect("decrypt failed"),
+            b"dryoc"
+        );
+
+        // sealed boxes are randomized, so only check that they round trip
+        let sealed = VecBox::seal_to_vecbox(b"dryoc", &recipient.public_key).expect("seal failed");
+        let persisted = sealed.to_versioned_vec();
+        assert_eq!(&persisted[..2], &[1, 3]);
+        let restored = VecBox::from_versioned_bytes(&persisted).expect("decode failed");
+        assert_eq!(
+            restored.unseal_to_vec(&recipient).expect("unseal failed"),
+            b"dryoc"
+        );
+    }
+
+    #[test]
+    fn test_signed_message_snapshot() {
+        use crate::sign::*;
+
+        let keypair = SigningKeyPair::<PublicKey, SecretKey>::from_seed(&[1u8; 32]);
+        let signed = keypair.sign_with_defaults(b"dryoc").expect("sign failed");
+
+        let persisted = signed.to_versioned_vec();
+        assert_eq!(
+            persisted,
+            unhex(
+                "0104cea04b1357ffdfe92e752eb87c3a47045a89dce82119f5517b8ad28a9a64aead77bf155423ed85847e0fcf9a634ee1c294400b474800934de7a7d3a6e76d67056472796f63"
+            )
+        );
+
+        let restored = VecSignedMessage::from_versioned_bytes(&persisted).expect("decode failed");
+        restored.verify(&keypair.public_key).expect("verify failed");
+
+        VecSignedMessage::from_versioned_bytes(&encode_envelope::<Vec<u8>>(
+            Format::V1,
+            Kind::SecretBox,
+            &persisted[2..],
+        ))
+        .expect_err("wrong kind");
+    }
+
+    #[test]
+    fn test_stream_snapshot() {
+        use crate::dryocstream::*;
+
+        let key = Key::from(&[1u8; 32]);
+
+        // headers are random, so check the layout of a new stream, then that a
+        // persisted stream still decrypts
+        let (mut push, header): (_, Header) = DryocStream::init_push(&key);
+        let first = push
+            .push_to_vec(b"dry", None, Tag::MESSAGE)
+            .expect("push failed");
+        let last = push
+            .push_to_vec(b"oc", None, Tag::FINAL)
+            .expect("push failed");
+        assert_eq!(header.len(), 24);
+        assert_eq!(first.len(), 1 + 3 + 16);
+        assert_eq!(last.len(), 1 + 2 + 16);
+
+        let persisted = unhex(
+            "a275a175b43b969ca12c8dbf782b09714aafd880bca2cf7d053b55932616df943f660f9edeb198957b67b88638d492085ac3f1e03b4a278f5ba31e1d294ac1",
+        );
+        let (header, messages) = persisted.split_at(24);
+        let (first, last) = messages.split_at(1 + 3 + 16);
+        let mut pull = DryocStream::init_pull(&key, &Header::try_from(header).expect("header"));
+        assert_eq!(
+            pull.pull_to_vec(&first, None).expect("pull failed"),
+            (b"dry".to_vec(), Tag::MESSAGE)
+        );
+        assert_eq!(
+            pull.pull_to_vec(&last, None).expect("pull failed"),
+            (b"oc".to_vec(), Tag::FINAL)
+        );
+    }
+
+    #[cfg(feature = "base64")]
+    #[test]
+    fn test_pwhash_snapshot() {
+        use crate::pwhash::*;
+
+        let config = Config::interactive().with_opslimit(1).with_memlimit(8192);
+        let pwhash =
+            VecPwHash::hash_with_salt(b"dryoc", vec![1u8; 16], config).expect("hash failed");
+
+        let persisted = pwhash.to_string();
+        assert_eq!(
+            persisted,
+            "$argon2id$v=19$m=8,t=1,\
+             p=1$AQEBAQEBAQEBAQEBAQEBAQ$p0V+qFpgxmD6X4O5pZ0adZSh5VQolgh6OwssgY9CpG0"
+        );
+
+        let restored = VecPwHash::from_string(&persisted).expect("parse failed");
+        restored.verify(b"dryoc").expect("verify failed");
+    }
+}
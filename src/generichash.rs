@@ -21,6 +21,28 @@
 //! );
 //! ```
 //!
+//! # Rustaceous API example, fixed-size output
+//!
+//! The output length of [`GenericHash`] is a const generic parameter, and is
+//! checked at compile time. [`Hasher`] is provided as a shorthand for hashing
+//! into arrays of any valid length.
+//!
+//! ```
+//! use dryoc::generichash::Hasher;
+//!
+//! let hash: [u8; 16] = Hasher::<16>::digest(b"hello");
+//! let wide_hash: [u8; 64] = Hasher::<64>::digest(b"hello");
+//! ```
+//!
+//! Using an output length outside of the range supported by Blake2b (16 to 64
+//! bytes) fails to compile:
+//!
+//! ```compile_fail
+//! use dryoc::generichash::Hasher;
+//!
+//! let hash: [u8; 8] = Hasher::<8>::digest(b"hello");
+//! ```
+//!
 //! # Rustaceous API example, incremental interface
 //!
 //! ```
@@ -43,7 +65,10 @@ use crate::classic::crypto_generichash::{
     crypto_generichash, crypto_generichash_final, crypto_generichash_init,
     crypto_generichash_update, GenericHashState,
 };
-use crate::constants::{CRYPTO_GENERICHASH_BYTES, CRYPTO_GENERICHASH_KEYBYTES};
+use crate::constants::{
+    CRYPTO_GENERICHASH_BYTES, CRYPTO_GENERICHASH_BYTES_MAX, CRYPTO_GENERICHASH_BYTES_MIN,
+    CRYPTO_GENERICHASH_KEYBYTES, CRYPTO_GENERICHASH_KEYBYTES_MAX, CRYPTO_GENERICHASH_KEYBYTES_MIN,
+};
 use crate::error::Error;
 pub use crate::types::*;
 
@@ -51,6 +76,10 @@ pub use crate::types::*;
 pub type Hash = StackByteArray<CRYPTO_GENERICHASH_BYTES>;
 /// Stack-allocated secret key for use with the generic hash algorithm.
 pub type Key = StackByteArray<CRYPTO_GENERICHASH_KEYBYTES>;
+/// [`GenericHash`] with the default key length and an `OUTPUT_LENGTH`-byte
+/// output. Provided for convenience.
+pub type Hasher<const OUTPUT_LENGTH: usize> =
+    GenericHash<CRYPTO_GENERICHASH_KEYBYTES, OUTPUT_LENGTH>;
 
 #[cfg(any(feature = "nightly", all(doc, not(doctest))))]
 #[cfg_attr(all(feature = "nightly", doc), doc(cfg(feature = "nightly")))]
@@ -73,6 +102,24 @@ pub mod protected {
     //!     HeapBytes::from_slice_into_readonly_locked(b"super secret input").expect("input failed");
     //! let hash: Locked<Hash> = GenericHash::hash(&input, Some(&key)).expect("hash failed");
     //! ```
+    //!
+    //! The incremental interface can also finalize directly into locked memory,
+    //! so keyed hashes used as derived secrets never leave protected memory:
+    //!
+    //! ```
+    //! use dryoc::constants::{CRYPTO_GENERICHASH_BYTES, CRYPTO_GENERICHASH_KEYBYTES};
+    //! use dryoc::generichash::protected::*;
+    //! use dryoc::generichash::GenericHash;
+    //!
+    //! let key = Key::gen_readonly_locked().expect("gen failed");
+    //! let mut hasher =
+    //!     GenericHash::<CRYPTO_GENERICHASH_KEYBYTES, CRYPTO_GENERICHASH_BYTES>::new(Some(&key))
+    //!         .expect("new failed");
+    //! hasher.update(b"derived secret context");
+    //! let hash = hasher
+    //!     .finalize_into_readonly_locked()
+    //!     .expect("finalize failed");
+    //! ```
     use super::*;
     pub use crate::protected::*;
 
@@ -82,6 +129,24 @@ pub mod protected {
     /// Heap-allocated, page-aligned hash output for the generic hash algorithm,
     /// for use with protected memory.
     pub type Hash = HeapByteArray<CRYPTO_GENERICHASH_BYTES>;
+
+    impl<const KEY_LENGTH: usize, const OUTPUT_LENGTH: usize> GenericHash<KEY_LENGTH, OUTPUT_LENGTH> {
+        /// Computes the final hash value, writing it directly into a new
+        /// locked byte array.
+        pub fn finalize_into_locked(self) -> Result<Locked<HeapByteArray<OUTPUT_LENGTH>>, Error> {
+            let mut output = HeapByteArray::<OUTPUT_LENGTH>::new_locked()?;
+            self.finalize_into(&mut output)?;
+            Ok(output)
+        }
+
+        /// Computes the final hash value, writing it directly into a new
+        /// locked byte array, which is then protected as read-only.
+        pub fn finalize_into_readonly_locked(
+            self,
+        ) -> Result<LockedRO<HeapByteArray<OUTPUT_LENGTH>>, Error> {
+            self.finalize_into_locked()?.mprotect_readonly()
+        }
+    }
 }
 
 /// Provides a generic hash function implementation based on Blake2b. Compatible
@@ -91,8 +156,20 @@ pub struct GenericHash<const KEY_LENGTH: usize, const OUTPUT_LENGTH: usize> {
 }
 
 impl<const KEY_LENGTH: usize, const OUTPUT_LENGTH: usize> GenericHash<KEY_LENGTH, OUTPUT_LENGTH> {
+    pub(crate) const VALID_KEY_LENGTH: () = assert!(
+        KEY_LENGTH >= CRYPTO_GENERICHASH_KEYBYTES_MIN
+            && KEY_LENGTH <= CRYPTO_GENERICHASH_KEYBYTES_MAX,
+        "generic hash key length must be between 16 and 64 bytes"
+    );
+    const VALID_OUTPUT_LENGTH: () = assert!(
+        OUTPUT_LENGTH >= CRYPTO_GENERICHASH_BYTES_MIN
+            && OUTPUT_LENGTH <= CRYPTO_GENERICHASH_BYTES_MAX,
+        "generic hash output length must be between 16 and 64 bytes"
+    );
+
     /// Returns a new hasher instance, with `key`.
     pub fn new<Key: ByteArray<KEY_LENGTH>>(key: Option<&Key>) -> Result<Self, Error> {
+        let () = Self::VALID_OUTPUT_LENGTH;
         Ok(Self {
             state: crypto_generichash_init(key.map(|k| k.as_slice()), OUTPUT_LENGTH)?,
         })
@@ -107,11 +184,20 @@ impl<const KEY_LENGTH: usize, const OUTPUT_LENGTH: usize> GenericHash<KEY_LENGTH
     pub fn finalize<Output: NewByteArray<OUTPUT_LENGTH>>(self) -> Result<Output, Error> {
         let mut output = Output::new_byte_array();
 
-        crypto_generichash_final(self.state, output.as_mut_slice())?;
+        self.finalize_into(&mut output)?;
 
         Ok(output)
     }
 
+    /// Computes the final hash value, writing it into `output`. Useful when
+    /// `output` is a preallocated buffer, such as a locked byte array.
+    pub fn finalize_into<Output: MutByteArray<OUTPUT_LENGTH>>(
+        self,
+        output: &mut Output,
+    ) -> Result<(), Error> {
+        crypto_generichash_final(self.state, output.as_mut_slice())
+    }
+
     /// Computes and returns the final hash value as a [`Vec`]. Provided for
     /// convenience.
     pub fn finalize_to_vec(self) -> Result<Vec<u8>, Error> {
@@ -145,6 +231,7 @@ impl<const KEY_LENGTH: usize, const OUTPUT_LENGTH: usize> GenericHash<KEY_LENGTH
         input: &Input,
         key: Option<&Key>,
     ) -> Result<Output, Error> {
+        let () = Self::VALID_OUTPUT_LENGTH;
         let mut output = Output::new_byte_array();
         crypto_generichash(
             output.as_mut_slice(),
@@ -154,6 +241,35 @@ impl<const KEY_LENGTH: usize, const OUTPUT_LENGTH: usize> GenericHash<KEY_LENGTH
         Ok(output)
     }
 
+    /// Computes the unkeyed hash of `input`, returning an array of
+    /// `OUTPUT_LENGTH` bytes.
+    ///
+    /// Unlike [`GenericHash::hash`], this can't fail at runtime: an invalid
+    /// output length is a compile-time error.
+    pub fn digest<Input: Bytes + ?Sized>(input: &Input) -> [u8; OUTPUT_LENGTH] {
+        let () = Self::VALID_OUTPUT_LENGTH;
+        let mut output = [0u8; OUTPUT_LENGTH];
+        crypto_generichash(&mut output, input.as_slice(), None)
+            .expect("output length is validated at compile time");
+        output
+    }
+
+    /// Computes the hash of `input` with `key`, returning an array of
+    /// `OUTPUT_LENGTH` bytes.
+    ///
+    /// Unlike [`GenericHash::hash`], this can't fail at runtime: invalid output
+    /// or key lengths are compile-time errors.
+    pub fn digest_keyed<Input: Bytes + ?Sized, Key: ByteArray<KEY_LENGTH>>(
+        input: &Input,
+        key: &Key,
+    ) -> [u8; OUTPUT_LENGTH] {
+        let ((), ()) = (Self::VALID_OUTPUT_LENGTH, Self::VALID_KEY_LENGTH);
+        let mut output = [0u8; OUTPUT_LENGTH];
+        crypto_generichash(&mut output, input.as_slice(), Some(key.as_slice()))
+            .expect("output and key lengths are validated at compile time");
+        output
+    }
+
     /// Convenience wrapper for [`GenericHash::hash`].
     pub fn hash_to_vec<Input: Bytes, Key: ByteArray<KEY_LENGTH>>(
         input: &Input,
@@ -230,6 +346,37 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_generichash_finalize_into() {
+        let key = Key::gen();
+        let mut hasher = GenericHash::new_with_defaults(Some(&key)).expect("new hash failed");
+        hasher.update(b"hello");
+
+        let mut output = Hash::default();
+        hasher.finalize_into(&mut output).expect("finalize failed");
+
+        let expected: Hash =
+            GenericHash::hash_with_defaults(b"hello", Some(&key)).expect("hash failed");
+        assert_eq!(output, expected);
+    }
+
+    #[test]
+    fn test_generichash_digest() {
+        let hash: [u8; 32] = Hasher::<32>::digest(b"hello");
+        let expected: Hash =
+            GenericHash::hash_with_defaults::<_, Key, _>(b"hello", None).expect("hash failed");
+        assert_eq!(&hash, expected.as_array());
+
+        let key = Key::gen();
+        let hash: [u8; 64] = Hasher::<64>::digest_keyed(b"hello", &key);
+        let expected: Vec<u8> =
+            GenericHash::<32, 64>::hash(b"hello", Some(&key)).expect("hash failed");
+        assert_eq!(hash.to_vec(), expected);
+
+        let hash: [u8; 16] = GenericHash::<16, 16>::digest_keyed(b"hello", &[7u8; 16]);
+        assert_ne!(hash, Hasher::<16>::digest(b"hello"));
+    }
+
     #[test]
     fn test_generichash_onetime() {
         use base64::engine::general_purpose;
@@ -0,0 +1,319 @@
+//! # Rate limiting hooks for decryption and verification
+//!
+//! Servers which decrypt or verify attacker-supplied input on request can be
+//! used as an oracle: an attacker submits many forgeries, and learns from
+//! which ones fail (or how long they take to fail). Authenticated encryption
+//! makes this much less useful, but it's still wise to limit how many failures
+//! each key or peer is allowed.
+//!
+//! A [`DecryptGuard`] is a policy which is consulted before each decryption or
+//! verification, and told its outcome afterwards. [`guarded`] wraps any
+//! decryption in a guard, and [`GuardedVerifier`] wraps a [`Verifier`], so it
+//! can be used with any of the signed formats.
+//!
+//! [`FailureLimiter`] is a guard which counts failures for each ID (such as a
+//! key ID, or a peer's address), and once an ID has failed too many times in a
+//! row, refuses further attempts for an exponentially increasing backoff
+//! period. A successful attempt resets the count.
+//!
+//! ## Example
+//!
+//! ```
+//! use std::time::Duration;
+//!
+//! use dryoc::dryocsecretbox::*;
+//! use dryoc::guard::*;
+//!
+//! let limiter = FailureLimiter::new(3, Duration::from_secs(1))
+//!     .with_backoff_callback(|id, failures, delay| {
+//!         eprintln!("{:?} failed {} times, blocked for {:?}", id, failures, delay)
+//!     });
+//!
+//! let key = Key::gen();
+//! let forged = DryocSecretBox::from_bytes(&[0u8; 64]).expect("parse failed");
+//! let nonce = Nonce::gen();
+//!
+//! for _ in 0..3 {
+//!     let result: Result<Vec<u8>, _> =
+//!         guarded(&limiter, b"peer-1", || forged.decrypt_to_vec(&nonce, &key));
+//!     assert!(result.is_err());
+//! }
+//!
+//! // The peer is now refused without attempting decryption
+//! assert!(limiter.check(b"peer-1").is_err());
+//! // Other peers are unaffected
+//! assert!(limiter.check(b"peer-2").is_ok());
+//! ```
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::error::Error;
+use crate::sign::{SignatureAlgorithm, Verifier};
+
+/// A policy which is consulted before, and told the outcome of, each
+/// decryption or verification.
+///
+/// Implementations must be safe to share between threads, as a single guard
+/// is typically shared by all of a server's request handlers.
+pub trait DecryptGuard: Send + Sync {
+    /// Called before an attempt for `id`. Returns an error to refuse the
+    /// attempt.
+    fn check(&self, id: &[u8]) -> Result<(), Error>;
+
+    /// Called after an attempt for `id`, with whether it succeeded.
+    fn record(&self, id: &[u8], success: bool);
+}
+
+/// Runs `operation` for `id` if `guard` allows it, then records the outcome.
+pub fn guarded<G: DecryptGuard + ?Sized, T>(
+    guard: &G,
+    id: &[u8],
+    operation: impl FnOnce() -> Result<T, Error>,
+) -> Result<T, Error> {
+    guard.check(id)?;
+    let result = operation();
+    guard.record(id, result.is_ok());
+    result
+}
+
+/// A [`Verifier`] which consults a [`DecryptGuard`] before each verification.
+///
+/// Refer to [crate::guard] for details.
+pub struct GuardedVerifier<'a, V: Verifier + ?Sized, G: DecryptGuard + ?Sized> {
+    verifier: &'a V,
+    guard: &'a G,
+    id: Vec<u8>,
+}
+
+impl<'a, V: Verifier + ?Sized, G: DecryptGuard + ?Sized> GuardedVerifier<'a, V, G> {
+    /// Returns a new verifier which verifies with `verifier`, and records
+    /// attempts for `id` with `guard`.
+    pub fn new(verifier: &'a V, guard: &'a G, id: &[u8]) -> Self {
+        Self {
+            verifier,
+            guard,
+            id: id.to_vec(),
+        }
+    }
+}
+
+impl<V: Verifier + ?Sized, G: DecryptGuard + ?Sized> Verifier for GuardedVerifier<'_, V, G> {
+    fn algorithm(&self) -> SignatureAlgorithm {
+        self.verifier.algorithm()
+    }
+
+    fn verify(&self, message: &[u8], signature: &[u8]) -> Result<(), Error> {
+        guarded(self.guard, &self.id, || {
+            self.verifier.verify(message, signature)
+        })
+    }
+}
+
+type BackoffCallback = Box<dyn Fn(&[u8], u32, Duration) + Send + Sync>;
+
+struct FailureState {
+    failures: u32,
+    blocked_until: Option<Instant>,
+}
+
+/// A [`DecryptGuard`] which blocks IDs after repeated failures, with
+/// exponential backoff.
+///
+/// After `max_failures` consecutive failures, an ID is blocked for the base
+/// delay, and each further failure doubles the delay, up to the maximum delay
+/// (one hour, by default). A success resets the ID's count.
+///
+/// State is kept for each ID which has failed at least once, until it
+/// succeeds, or until [`FailureLimiter::forget`] or
+/// [`FailureLimiter::prune`] is called.
+///
+/// Refer to [crate::guard] for sample usage.
+pub struct FailureLimiter {
+    max_failures: u32,
+    base_delay: Duration,
+    max_delay: Duration,
+    on_backoff: Option<BackoffCallback>,
+    state: Mutex<HashMap<Vec<u8>, FailureState>>,
+}
+
+impl FailureLimiter {
+    /// Returns a new limiter which blocks an ID for `base_delay` after
+    /// `max_failures` consecutive failures.
+    pub fn new(max_failures: u32, base_delay: Duration) -> Self {
+        Self {
+            max_failures: max_failures.max(1),
+            base_delay,
+            max_delay: Duration::from_secs(3600),
+            on_backoff: None,
+            state: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Returns this limiter with a maximum backoff of `max_delay`.
+    #[must_use]
+    pub fn with_max_delay(self, max_delay: Duration) -> Self {
+        Self { max_delay, ..self }
+    }
+
+    /// Returns this limiter with `callback`, which is called with the ID, its
+    /// number of consecutive failures, and the backoff delay each time an ID
+    /// is blocked. Use this to log or alert on suspected attacks.
+    #[must_use]
+    pub fn with_backoff_callback(
+        self,
+        callback: impl Fn(&[u8], u32, Duration) + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            on_backoff: Some(Box::new(callback)),
+            ..self
+        }
+    }
+
+    /// Returns the number of consecutive failures recorded for `id`.
+    pub fn failures(&self, id: &[u8]) -> u32 {
+        self.lock().get(id).map_or(0, |state| state.failures)
+    }
+
+    /// Forgets all failures recorded for `id`, unblocking it.
+    pub fn forget(&self, id: &[u8]) {
+        self.lock().remove(id);
+    }
+
+    /// Forgets the state of IDs which aren't currently blocked, to bound
+    /// memory use. Their failure counts are reset.
+    pub fn prune(&self) {
+        let now = Instant::now();
+        self.lock()
+            .retain(|_, state| state.blocked_until.map_or(false, |until| until > now));
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<Vec<u8>, FailureState>> {
+        // The state is always consistent, so a panic while it's held is
+        // harmless
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn delay(&self, failures: u32) -> Duration {
+        let doublings = failures - self.max_failures;
+        2u32.checked_pow(doublings)
+            .and_then(|factor| self.base_delay.checked_mul(factor))
+            .map_or(self.max_delay, |delay| delay.min(self.max_delay))
+    }
+}
+
+impl DecryptGuard for FailureLimiter {
+    fn check(&self, id: &[u8]) -> Result<(), Error> {
+        let now = Instant::now();
+        match self.lock().get(id).and_then(|state| state.blocked_until) {
+            Some(until) if until > now => Err(dryoc_error!(format!(
+                "too many failed attempts, retry in {:?}",
+                until - now
+            ))),
+            _ => Ok(()),
+        }
+    }
+
+    fn record(&self, id: &[u8], success: bool) {
+        if success {
+            self.forget(id);
+            return;
+        }
+        let mut state = self.lock();
+        let entry = state.entry(id.to_vec()).or_insert(FailureState {
+            failures: 0,
+            blocked_until: None,
+        });
+        entry.failures = entry.failures.saturating_add(1);
+        if entry.failures >= self.max_failures {
+            let delay = self.delay(entry.failures);
+            entry.blocked_until = Some(Instant::now() + delay);
+            let failures = entry.failures;
+            drop(state);
+            if let Some(callback) = &self.on_backoff {
+                callback(id, failures, delay);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    use super::*;
+    use crate::sign::{Signer, SigningKeyPair};
+
+    fn fail() -> Result<(), Error> {
+        Err(dryoc_error!("failed"))
+    }
+
+    #[test]
+    fn test_failure_limiter() {
+        let blocked = Arc::new(AtomicU32::new(0));
+        let counter = blocked.clone();
+        let limiter = FailureLimiter::new(2, Duration::from_secs(60))
+            .with_backoff_callback(move |id, failures, delay| {
+                assert_eq!(id, b"a");
+                assert_eq!(delay, Duration::from_secs(60) * 2u32.pow(failures - 2));
+                counter.fetch_add(1, Ordering::SeqCst);
+            });
+
+        guarded(&limiter, b"a", fail).expect_err("failed");
+        guarded(&limiter, b"a", || Ok(())).expect("allowed");
+        assert_eq!(limiter.failures(b"a"), 0);
+
+        guarded(&limiter, b"a", fail).expect_err("failed");
+        guarded(&limiter, b"a", fail).expect_err("failed");
+        assert_eq!(blocked.load(Ordering::SeqCst), 1);
+        assert_eq!(limiter.failures(b"a"), 2);
+
+        // refused without running the operation
+        let mut ran = false;
+        guarded(&limiter, b"a", || {
+            ran = true;
+            Ok(())
+        })
+        .expect_err("blocked");
+        assert!(!ran);
+        limiter.check(b"b").expect("other IDs are unaffected");
+
+        limiter.prune();
+        assert_eq!(limiter.failures(b"a"), 2);
+        limiter.forget(b"a");
+        limiter.check(b"a").expect("unblocked");
+    }
+
+    #[test]
+    fn test_failure_limiter_delay() {
+        let limiter = FailureLimiter::new(3, Duration::from_secs(1))
+            .with_max_delay(Duration::from_secs(10));
+        assert_eq!(limiter.delay(3), Duration::from_secs(1));
+        assert_eq!(limiter.delay(4), Duration::from_secs(2));
+        assert_eq!(limiter.delay(6), Duration::from_secs(8));
+        assert_eq!(limiter.delay(7), Duration::from_secs(10));
+        assert_eq!(limiter.delay(u32::MAX), Duration::from_secs(10));
+    }
+
+    #[test]
+    fn test_guarded_verifier() {
+        let keypair = SigningKeyPair::gen_with_defaults();
+        let signature = keypair.try_sign(b"message").expect("sign failed");
+        let limiter = FailureLimiter::new(1, Duration::from_secs(60));
+        let verifier = GuardedVerifier::new(&keypair.public_key, &limiter, b"peer");
+
+        verifier
+            .verify(b"message", &signature)
+            .expect("verify failed");
+        verifier
+            .verify(b"forged", &signature)
+            .expect_err("should fail");
+        verifier
+            .verify(b"message", &signature)
+            .expect_err("peer is blocked");
+    }
+}
@@ -0,0 +1,180 @@
+//! HMAC-based extract-and-expand key derivation function (HKDF), as described
+//! in [RFC 5869](https://www.rfc-editor.org/rfc/rfc5869), generic over the
+//! SHA-1 and SHA-2 hash functions.
+//!
+//! This is used internally to derive keys which are bound to a label and
+//! context (and for one-time passwords, see [`crate::totp`]), and isn't
+//! exposed as part of the public API. The labeled variants from
+//! [RFC 9180](https://www.rfc-editor.org/rfc/rfc9180), section 4, are shared by
+//! [`crate::kem`] and [`crate::hpke`].
+use sha2::digest::core_api::BlockSizeUser;
+use sha2::digest::{Digest, Output};
+use sha2::Sha256;
+use zeroize::Zeroize;
+
+use crate::error::Error;
+
+/// Computes HMAC over the concatenation of each slice in `message`, using
+/// `key`.
+pub(crate) fn hmac<D: Digest + BlockSizeUser>(key: &[u8], message: &[&[u8]]) -> Output<D> {
+    let mut pad = vec![0u8; D::block_size()];
+    if key.len() > pad.len() {
+        let mut hashed_key = D::digest(key);
+        pad[..hashed_key.len()].copy_from_slice(&hashed_key);
+        hashed_key.as_mut_slice().zeroize();
+    } else {
+        pad[..key.len()].copy_from_slice(key);
+    }
+
+    pad.iter_mut().for_each(|b| *b ^= 0x36);
+    let mut inner = D::new();
+    inner.update(&pad);
+    for part in message {
+        inner.update(part);
+    }
+    let mut inner_hash = inner.finalize();
+
+    pad.iter_mut().for_each(|b| *b ^= 0x36 ^ 0x5c);
+    let mut outer = D::new();
+    outer.update(&pad);
+    outer.update(&inner_hash);
+
+    pad.zeroize();
+    inner_hash.as_mut_slice().zeroize();
+
+    outer.finalize()
+}
+
+/// HKDF-Extract: computes a pseudorandom key from `salt` and the input keying
+/// material `ikm`.
+pub(crate) fn extract<D: Digest + BlockSizeUser>(salt: &[u8], ikm: &[u8]) -> Output<D> {
+    hmac::<D>(salt, &[ikm])
+}
+
+/// HKDF-Expand: fills `output` with keying material derived from the
+/// pseudorandom key `prk`, bound to the concatenation of each slice in `info`.
+///
+/// The output may be at most 255 times the hash function's output length.
+pub(crate) fn expand<D: Digest + BlockSizeUser>(
+    output: &mut [u8],
+    prk: &[u8],
+    info: &[&[u8]],
+) -> Result<(), Error> {
+    let hash_len = <D as Digest>::output_size();
+    if output.len() > 255 * hash_len {
+        return Err(dryoc_error!(format!(
+            "output length of {} greater than maximum {}",
+            output.len(),
+            255 * hash_len
+        )));
+    }
+
+    let mut block = Output::<D>::default();
+    for (i, chunk) in output.chunks_mut(hash_len).enumerate() {
+        let counter = [(i + 1) as u8];
+        let previous: &[u8] = if i == 0 { &[] } else { &block };
+        let mut message: Vec<&[u8]> = Vec::with_capacity(info.len() + 2);
+        message.push(previous);
+        message.extend_from_slice(info);
+        message.push(&counter);
+        block = hmac::<D>(prk, &message);
+        chunk.copy_from_slice(&block[..chunk.len()]);
+    }
+    block.as_mut_slice().zeroize();
+
+    Ok(())
+}
+
+/// LabeledExtract from RFC 9180: HKDF-SHA256 extract, with the input keying
+/// material `ikm` bound to `suite_id` and `label`.
+pub(crate) fn labeled_extract(suite_id: &[u8], salt: &[u8], label: &[u8], ikm: &[u8]) -> [u8; 32] {
+    let mut prk = hmac::<Sha256>(salt, &[b"HPKE-v1", suite_id, label, ikm]);
+    let mut out = [0u8; 32];
+    out.copy_from_slice(&prk);
+    prk.as_mut_slice().zeroize();
+    out
+}
+
+/// LabeledExpand from RFC 9180: HKDF-SHA256 expand, with `info` bound to
+/// `suite_id`, `label`, and the length of `output`, which must fit in 2 bytes.
+pub(crate) fn labeled_expand(
+    output: &mut [u8],
+    suite_id: &[u8],
+    prk: &[u8],
+    label: &[u8],
+    info: &[u8],
+) -> Result<(), Error> {
+    if output.len() > u16::MAX as usize {
+        return Err(dryoc_error!(format!(
+            "output length of {} greater than maximum {}",
+            output.len(),
+            u16::MAX
+        )));
+    }
+    let length = (output.len() as u16).to_be_bytes();
+    expand::<Sha256>(output, prk, &[&length, b"HPKE-v1", suite_id, label, info])
+}
+
+#[cfg(test)]
+mod tests {
+    use sha2::{Sha256, Sha512};
+
+    use super::*;
+
+    fn unhex(s: &str) -> Vec<u8> {
+        hex::decode(s).expect("invalid hex")
+    }
+
+    #[test]
+    fn test_hkdf_sha256_rfc5869() {
+        // RFC 5869, appendix A.1
+        let ikm = unhex("0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b");
+        let salt = unhex("000102030405060708090a0b0c");
+        let info = unhex("f0f1f2f3f4f5f6f7f8f9");
+
+        let prk = extract::<Sha256>(&salt, &ikm);
+        assert_eq!(
+            prk.as_slice(),
+            unhex("077709362c2e32df0ddc3f0dc47bba6390b6c73bb50f9c3122ec844ad7c2b3e5")
+        );
+
+        let mut okm = vec![0u8; 42];
+        expand::<Sha256>(&mut okm, &prk, &[&info[..5], &info[5..]]).expect("expand failed");
+        assert_eq!(
+            okm,
+            unhex(
+                "3cb25f25faacd57a90434f64d0362f2a2d2d0a90cf1a5a4c5db02d56ecc4c5bf34007208d5b887185865"
+            )
+        );
+    }
+
+    #[test]
+    fn test_hkdf_sha512() {
+        let ikm = unhex("0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b");
+        let salt = unhex("000102030405060708090a0b0c");
+        let info = unhex("f0f1f2f3f4f5f6f7f8f9");
+
+        let prk = extract::<Sha512>(&salt, &ikm);
+        let mut okm = vec![0u8; 100];
+        expand::<Sha512>(&mut okm, &prk, &[&info]).expect("expand failed");
+        assert_eq!(
+            okm,
+            unhex(
+                "832390086cda71fb47625bb5ceb168e4c8e26a1a16ed34d9fc7fe92c1481579338da362cb8d9f925d7cbcce0dff7098769cf15959867d571c1715450cb530137be3fb62f3cf32b84feba8f1eb1b563e20d9749b8640b8264c4b69b14ad5199115e1d609c"
+            )
+        );
+
+        // keys longer than the block size are hashed first
+        let long_key = vec![0xaau8; 131];
+        assert_eq!(
+            hmac::<Sha512>(&long_key, &[b"Test Using Larger Than Block-Size Key - Hash Key First"])
+                .as_slice(),
+            unhex(
+                "80b24263c7c1a3ebb71493c1dd7be8b49b46d1f41b4aeec1121b013783f8f3526b56d037e05f2598bd0fd2215d6a1e5295e64f73f63f0aec8b915a985d786598"
+            )
+        );
+
+        let mut too_long = vec![0u8; 255 * 64 + 1];
+        expand::<Sha512>(&mut too_long, &prk, &[&info]).expect_err("should fail");
+    }
+}
@@ -0,0 +1,918 @@
+//! # Hybrid public key encryption (HPKE)
+//!
+//! Implements HPKE, as described in
+//! [RFC 9180](https://www.rfc-editor.org/rfc/rfc9180), with HKDF-SHA256 as the
+//! KDF and ChaCha20-Poly1305 as the AEAD. HPKE is generic over the [`Kem`];
+//! use [`DhKemX25519`](crate::kem::DhKemX25519) for the DHKEM(X25519,
+//! HKDF-SHA256) ciphersuite required by protocols such as MLS, ECH, and
+//! Oblivious HTTP.
+//!
+//! All four modes are supported:
+//!
+//! * base mode, which encrypts to the recipient's public key
+//! * PSK mode, which additionally authenticates the sender with a pre-shared
+//!   key (a [`Psk`])
+//! * auth mode, which additionally authenticates the sender with their static
+//!   keypair (requires an [`AuthKem`])
+//! * auth PSK mode, which combines both
+//!
+//! Each mode has a pair of setup functions, which return a [`SenderContext`]
+//! or [`RecipientContext`] for encrypting a stream of messages, and a pair of
+//! single-shot functions for encrypting one message.
+//!
+//! ## Single-shot example
+//!
+//! ```
+//! use dryoc::hpke;
+//! use dryoc::kem::*;
+//!
+//! let recipient = DhKemX25519::gen_keypair();
+//!
+//! let (enc, ciphertext) = hpke::seal_base::<DhKemX25519>(
+//!     &recipient.public_key,
+//!     b"application info",
+//!     b"associated data",
+//!     b"hello",
+//! )
+//! .expect("seal failed");
+//!
+//! let plaintext = hpke::open_base::<DhKemX25519>(
+//!     &enc,
+//!     &recipient,
+//!     b"application info",
+//!     b"associated data",
+//!     &ciphertext,
+//! )
+//! .expect("open failed");
+//!
+//! assert_eq!(plaintext, b"hello");
+//! ```
+//!
+//! ## Streaming example
+//!
+//! ```
+//! use dryoc::hpke;
+//! use dryoc::kem::*;
+//!
+//! let recipient = DhKemX25519::gen_keypair();
+//!
+//! let (enc, mut sender) = hpke::setup_base_sender::<DhKemX25519>(&recipient.public_key, b"info")
+//!     .expect("setup failed");
+//! let first = sender.seal(b"", b"first").expect("seal failed");
+//! let second = sender.seal(b"", b"second").expect("seal failed");
+//!
+//! let mut recipient =
+//!     hpke::setup_base_recipient::<DhKemX25519>(&enc, &recipient, b"info").expect("setup failed");
+//! assert_eq!(recipient.open(b"", &first).expect("open failed"), b"first");
+//! assert_eq!(
+//!     recipient.open(b"", &second).expect("open failed"),
+//!     b"second"
+//! );
+//! ```
+use zeroize::{Zeroize, ZeroizeOnDrop};
+
+use crate::aead::{chacha20poly1305_ietf_decrypt, chacha20poly1305_ietf_encrypt};
+use crate::error::Error;
+use crate::hkdf::{labeled_expand, labeled_extract};
+use crate::kem::{AuthKem, Kem};
+use crate::types::*;
+
+/// HPKE KDF identifier for HKDF-SHA256.
+pub const HPKE_KDF_HKDF_SHA256: u16 = 0x0001;
+/// HPKE AEAD identifier for ChaCha20-Poly1305.
+pub const HPKE_AEAD_CHACHA20POLY1305: u16 = 0x0003;
+/// Length of the AEAD key.
+pub const HPKE_KEYBYTES: usize = 32;
+/// Length of the AEAD nonce.
+pub const HPKE_NONCEBYTES: usize = 12;
+/// Length of the AEAD authentication tag.
+pub const HPKE_TAGBYTES: usize = 16;
+
+const HPKE_EXPORTER_SECRETBYTES: usize = 32;
+
+#[derive(Clone, Copy)]
+enum Mode {
+    Base    = 0x00,
+    Psk     = 0x01,
+    Auth    = 0x02,
+    AuthPsk = 0x03,
+}
+
+/// A pre-shared key, along with its identifier, for use with the PSK modes.
+#[derive(Clone, Copy, Debug)]
+pub struct Psk<'a> {
+    psk: &'a [u8],
+    psk_id: &'a [u8],
+}
+
+impl<'a> Psk<'a> {
+    /// Returns a new pre-shared key. `psk` should contain at least 32 bytes
+    /// of entropy, and `psk_id` must be non-empty.
+    pub fn new(psk: &'a [u8], psk_id: &'a [u8]) -> Self {
+        Self { psk, psk_id }
+    }
+}
+
+#[derive(Zeroize, ZeroizeOnDrop)]
+struct Context {
+    key: [u8; HPKE_KEYBYTES],
+    base_nonce: [u8; HPKE_NONCEBYTES],
+    exporter_secret: [u8; HPKE_EXPORTER_SECRETBYTES],
+    seq: u64,
+}
+
+impl Context {
+    fn suite_id<K: Kem>() -> [u8; 10] {
+        let mut suite_id = [0u8; 10];
+        suite_id[..4].copy_from_slice(b"HPKE");
+        suite_id[4..6].copy_from_slice(&K::ID.to_be_bytes());
+        suite_id[6..8].copy_from_slice(&HPKE_KDF_HKDF_SHA256.to_be_bytes());
+        suite_id[8..].copy_from_slice(&HPKE_AEAD_CHACHA20POLY1305.to_be_bytes());
+        suite_id
+    }
+
+    fn key_schedule<K: Kem>(
+        mode: Mode,
+        shared_secret: &K::SharedSecret,
+        info: &[u8],
+        psk: Option<&Psk>,
+    ) -> Result<Self, Error> {
+        let (psk, psk_id) = psk.map(|psk| (psk.psk, psk.psk_id)).unwrap_or_default();
+        match mode {
+            Mode::Base | Mode::Auth => (),
+            Mode::Psk | Mode::AuthPsk => {
+                if psk.is_empty() || psk_id.is_empty() {
+                    return Err(dryoc_error!("PSK and PSK ID must both be non-empty"));
+                }
+            }
+        }
+
+        let suite_id = Self::suite_id::<K>();
+        let psk_id_hash = labeled_extract(&suite_id, b"", b"psk_id_hash", psk_id);
+        let info_hash = labeled_extract(&suite_id, b"", b"info_hash", info);
+        let mut key_schedule_context = [0u8; 65];
+        key_schedule_context[0] = mode as u8;
+        key_schedule_context[1..33].copy_from_slice(&psk_id_hash);
+        key_schedule_context[33..].copy_from_slice(&info_hash);
+
+        let mut secret = labeled_extract(&suite_id, shared_secret.as_slice(), b"secret", psk);
+        let mut context = Self {
+            key: [0u8; HPKE_KEYBYTES],
+            base_nonce: [0u8; HPKE_NONCEBYTES],
+            exporter_secret: [0u8; HPKE_EXPORTER_SECRETBYTES],
+            seq: 0,
+        };
+        let result = labeled_expand(
+            &mut context.key,
+            &suite_id,
+            &secret,
+            b"key",
+            &key_schedule_context,
+        )
+        .and_then(|_| {
+            labeled_expand(
+                &mut context.base_nonce,
+                &suite_id,
+                &secret,
+                b"base_nonce",
+                &key_schedule_context,
+            )
+        })
+        .and_then(|_| {
+            labeled_expand(
+                &mut context.exporter_secret,
+                &suite_id,
+                &secret,
+                b"exp",
+                &key_schedule_context,
+            )
+        });
+        secret.zeroize();
+
+        result.map(|_| context)
+    }
+
+    fn next_nonce(&mut self) -> Result<[u8; HPKE_NONCEBYTES], Error> {
+        if self.seq == u64::MAX {
+            return Err(dryoc_error!("message limit reached"));
+        }
+        let mut nonce = self.base_nonce;
+        nonce[HPKE_NONCEBYTES - 8..]
+            .iter_mut()
+            .zip(self.seq.to_be_bytes())
+            .for_each(|(n, s)| *n ^= s);
+        Ok(nonce)
+    }
+
+    fn export<K: Kem>(&self, exporter_context: &[u8], output: &mut [u8]) -> Result<(), Error> {
+        labeled_expand(
+            output,
+            &Self::suite_id::<K>(),
+            &self.exporter_secret,
+            b"sec",
+            exporter_context,
+        )
+    }
+}
+
+/// The sender's HPKE context, returned by the `setup_*_sender` functions,
+/// which encrypts a sequence of messages for the recipient.
+pub struct SenderContext<K: Kem> {
+    context: Context,
+    kem: std::marker::PhantomData<K>,
+}
+
+impl<K: Kem> SenderContext<K> {
+    fn new(context: Context) -> Self {
+        Self {
+            context,
+            kem: std::marker::PhantomData,
+        }
+    }
+
+    /// Encrypts `plaintext` with `aad` as associated data, returning the
+    /// ciphertext. Messages must be opened by the recipient in the same order
+    /// they were sealed.
+    pub fn seal(&mut self, aad: &[u8], plaintext: &[u8]) -> Result<Vec<u8>, Error> {
+        let nonce = self.context.next_nonce()?;
+        let ciphertext = chacha20poly1305_ietf_encrypt(plaintext, aad, &nonce, &self.context.key);
+        self.context.seq += 1;
+        Ok(ciphertext)
+    }
+
+    /// Derives a secret of length `output.len()` from this context, bound to
+    /// `exporter_context`. The recipient derives the same secret.
+    pub fn export(&self, exporter_context: &[u8], output: &mut [u8]) -> Result<(), Error> {
+        self.context.export::<K>(exporter_context, output)
+    }
+}
+
+/// The recipient's HPKE context, returned by the `setup_*_recipient`
+/// functions, which decrypts a sequence of messages from the sender.
+pub struct RecipientContext<K: Kem> {
+    context: Context,
+    kem: std::marker::PhantomData<K>,
+}
+
+impl<K: Kem> RecipientContext<K> {
+    fn new(context: Context) -> Self {
+        Self {
+            context,
+            kem: std::marker::PhantomData,
+        }
+    }
+
+    /// Decrypts `ciphertext` with `aad` as associated data, returning the
+    /// plaintext.
+    pub fn open(&mut self, aad: &[u8], ciphertext: &[u8]) -> Result<Vec<u8>, Error> {
+        let nonce = self.context.next_nonce()?;
+        let plaintext = chacha20poly1305_ietf_decrypt(ciphertext, aad, &nonce, &self.context.key)?;
+        self.context.seq += 1;
+        Ok(plaintext)
+    }
+
+    /// Derives a secret of length `output.len()` from this context, bound to
+    /// `exporter_context`. The sender derives the same secret.
+    pub fn export(&self, exporter_context: &[u8], output: &mut [u8]) -> Result<(), Error> {
+        self.context.export::<K>(exporter_context, output)
+    }
+}
+
+fn setup_sender<K: Kem>(
+    mode: Mode,
+    encapsulated: (K::SharedSecret, K::Ciphertext),
+    info: &[u8],
+    psk: Option<&Psk>,
+) -> Result<(K::Ciphertext, SenderContext<K>), Error> {
+    let (mut shared_secret, enc) = encapsulated;
+    let context = Context::key_schedule::<K>(mode, &shared_secret, info, psk);
+    shared_secret.zeroize();
+    Ok((enc, SenderContext::new(context?)))
+}
+
+fn setup_recipient<K: Kem>(
+    mode: Mode,
+    mut shared_secret: K::SharedSecret,
+    info: &[u8],
+    psk: Option<&Psk>,
+) -> Result<RecipientContext<K>, Error> {
+    let context = Context::key_schedule::<K>(mode, &shared_secret, info, psk);
+    shared_secret.zeroize();
+    Ok(RecipientContext::new(context?))
+}
+
+/// Sets up a base mode sender context for `recipient_public_key`, returning
+/// the encapsulated key to send to the recipient along with the context.
+pub fn setup_base_sender<K: Kem>(
+    recipient_public_key: &K::PublicKey,
+    info: &[u8],
+) -> Result<(K::Ciphertext, SenderContext<K>), Error> {
+    setup_sender(
+        Mode::Base,
+        K::encapsulate(recipient_public_key)?,
+        info,
+        None,
+    )
+}
+
+/// Sets up a base mode recipient context from the encapsulated key `enc`.
+pub fn setup_base_recipient<K: Kem>(
+    enc: &K::Ciphertext,
+    recipient_keypair: &K::KeyPair,
+    info: &[u8],
+) -> Result<RecipientContext<K>, Error> {
+    setup_recipient(
+        Mode::Base,
+        K::decapsulate(enc, recipient_keypair)?,
+        info,
+        None,
+    )
+}
+
+/// Sets up a PSK mode sender context for `recipient_public_key`, returning the
+/// encapsulated key to send to the recipient along with the context.
+pub fn setup_psk_sender<K: Kem>(
+    recipient_public_key: &K::PublicKey,
+    info: &[u8],
+    psk: &Psk,
+) -> Result<(K::Ciphertext, SenderContext<K>), Error> {
+    setup_sender(
+        Mode::Psk,
+        K::encapsulate(recipient_public_key)?,
+        info,
+        Some(psk),
+    )
+}
+
+/// Sets up a PSK mode recipient context from the encapsulated key `enc`.
+pub fn setup_psk_recipient<K: Kem>(
+    enc: &K::Ciphertext,
+    recipient_keypair: &K::KeyPair,
+    info: &[u8],
+    psk: &Psk,
+) -> Result<RecipientContext<K>, Error> {
+    setup_recipient(
+        Mode::Psk,
+        K::decapsulate(enc, recipient_keypair)?,
+        info,
+        Some(psk),
+    )
+}
+
+/// Sets up an auth mode sender context for `recipient_public_key`, returning
+/// the encapsulated key to send to the recipient along with the context.
+pub fn setup_auth_sender<K: AuthKem>(
+    recipient_public_key: &K::PublicKey,
+    info: &[u8],
+    sender_keypair: &K::KeyPair,
+) -> Result<(K::Ciphertext, SenderContext<K>), Error> {
+    setup_sender(
+        Mode::Auth,
+        K::auth_encapsulate(recipient_public_key, sender_keypair)?,
+        info,
+        None,
+    )
+}
+
+/// Sets up an auth mode recipient context from the encapsulated key `enc`,
+/// authenticating the sender with `sender_public_key`.
+pub fn setup_auth_recipient<K: AuthKem>(
+    enc: &K::Ciphertext,
+    recipient_keypair: &K::KeyPair,
+    info: &[u8],
+    sender_public_key: &K::PublicKey,
+) -> Result<RecipientContext<K>, Error> {
+    setup_recipient(
+        Mode::Auth,
+        K::auth_decapsulate(enc, recipient_keypair, sender_public_key)?,
+        info,
+        None,
+    )
+}
+
+/// Sets up an auth PSK mode sender context for `recipient_public_key`,
+/// returning the encapsulated key to send to the recipient along with the
+/// context.
+pub fn setup_auth_psk_sender<K: AuthKem>(
+    recipient_public_key: &K::PublicKey,
+    info: &[u8],
+    psk: &Psk,
+    sender_keypair: &K::KeyPair,
+) -> Result<(K::Ciphertext, SenderContext<K>), Error> {
+    setup_sender(
+        Mode::AuthPsk,
+        K::auth_encapsulate(recipient_public_key, sender_keypair)?,
+        info,
+        Some(psk),
+    )
+}
+
+/// Sets up an auth PSK mode recipient context from the encapsulated key
+/// `enc`, authenticating the sender with `sender_public_key`.
+pub fn setup_auth_psk_recipient<K: AuthKem>(
+    enc: &K::Ciphertext,
+    recipient_keypair: &K::KeyPair,
+    info: &[u8],
+    psk: &Psk,
+    sender_public_key: &K::PublicKey,
+) -> Result<RecipientContext<K>, Error> {
+    setup_recipient(
+        Mode::AuthPsk,
+        K::auth_decapsulate(enc, recipient_keypair, sender_public_key)?,
+        info,
+        Some(psk),
+    )
+}
+
+/// Encrypts a single message in base mode, returning the encapsulated key
+/// and the ciphertext.
+pub fn seal_base<K: Kem>(
+    recipient_public_key: &K::PublicKey,
+    info: &[u8],
+    aad: &[u8],
+    plaintext: &[u8],
+) -> Result<(K::Ciphertext, Vec<u8>), Error> {
+    let (enc, mut context) = setup_base_sender::<K>(recipient_public_key, info)?;
+    Ok((enc, context.seal(aad, plaintext)?))
+}
+
+/// Decrypts a single message in base mode.
+pub fn open_base<K: Kem>(
+    enc: &K::Ciphertext,
+    recipient_keypair: &K::KeyPair,
+    info: &[u8],
+    aad: &[u8],
+    ciphertext: &[u8],
+) -> Result<Vec<u8>, Error> {
+    setup_base_recipient::<K>(enc, recipient_keypair, info)?.open(aad, ciphertext)
+}
+
+/// Encrypts a single message in PSK mode, returning the encapsulated key and
+/// the ciphertext.
+pub fn seal_psk<K: Kem>(
+    recipient_public_key: &K::PublicKey,
+    info: &[u8],
+    aad: &[u8],
+    plaintext: &[u8],
+    psk: &Psk,
+) -> Result<(K::Ciphertext, Vec<u8>), Error> {
+    let (enc, mut context) = setup_psk_sender::<K>(recipient_public_key, info, psk)?;
+    Ok((enc, context.seal(aad, plaintext)?))
+}
+
+/// Decrypts a single message in PSK mode.
+pub fn open_psk<K: Kem>(
+    enc: &K::Ciphertext,
+    recipient_keypair: &K::KeyPair,
+    info: &[u8],
+    aad: &[u8],
+    ciphertext: &[u8],
+    psk: &Psk,
+) -> Result<Vec<u8>, Error> {
+    setup_psk_recipient::<K>(enc, recipient_keypair, info, psk)?.open(aad, ciphertext)
+}
+
+/// Encrypts a single message in auth mode, returning the encapsulated key and
+/// the ciphertext.
+pub fn seal_auth<K: AuthKem>(
+    recipient_public_key: &K::PublicKey,
+    info: &[u8],
+    aad: &[u8],
+    plaintext: &[u8],
+    sender_keypair: &K::KeyPair,
+) -> Result<(K::Ciphertext, Vec<u8>), Error> {
+    let (enc, mut context) = setup_auth_sender::<K>(recipient_public_key, info, sender_keypair)?;
+    Ok((enc, context.seal(aad, plaintext)?))
+}
+
+/// Decrypts a single message in auth mode.
+pub fn open_auth<K: AuthKem>(
+    enc: &K::Ciphertext,
+    recipient_keypair: &K::KeyPair,
+    info: &[u8],
+    aad: &[u8],
+    ciphertext: &[u8],
+    sender_public_key: &K::PublicKey,
+) -> Result<Vec<u8>, Error> {
+    setup_auth_recipient::<K>(enc, recipient_keypair, info, sender_public_key)?
+        .open(aad, ciphertext)
+}
+
+/// Encrypts a single message in auth PSK mode, returning the encapsulated
+/// key and the ciphertext.
+pub fn seal_auth_psk<K: AuthKem>(
+    recipient_public_key: &K::PublicKey,
+    info: &[u8],
+    aad: &[u8],
+    plaintext: &[u8],
+    psk: &Psk,
+    sender_keypair: &K::KeyPair,
+) -> Result<(K::Ciphertext, Vec<u8>), Error> {
+    let (enc, mut context) =
+        setup_auth_psk_sender::<K>(recipient_public_key, info, psk, sender_keypair)?;
+    Ok((enc, context.seal(aad, plaintext)?))
+}
+
+/// Decrypts a single message in auth PSK mode.
+pub fn open_auth_psk<K: AuthKem>(
+    enc: &K::Ciphertext,
+    recipient_keypair: &K::KeyPair,
+    info: &[u8],
+    aad: &[u8],
+    ciphertext: &[u8],
+    psk: &Psk,
+    sender_public_key: &K::PublicKey,
+) -> Result<Vec<u8>, Error> {
+    setup_auth_psk_recipient::<K>(enc, recipient_keypair, info, psk, sender_public_key)?
+        .open(aad, ciphertext)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kem::{Ciphertext, DhKemX25519};
+    use crate::keypair::{SecretKey, StackKeyPair};
+
+    #[test]
+    fn test_open_base_interop() {
+        // produced by pyca/cryptography's HPKE implementation, with the same
+        // ciphersuite
+        let recipient = StackKeyPair::from_secret_key(
+            SecretKey::try_from(
+                hex::decode("4612c550263fc8ad58375df3f557aac531d26850903e55a9f23f21d8534e8ac8")
+                    .expect("invalid hex")
+                    .as_slice(),
+            )
+            .expect("invalid key"),
+        );
+        let message = hex::decode(
+            "5d4d16bea84f18fd95e2ca987e6d15f373cea1745b867950d90eb6369693be765a387f75b92843c7647\
+             3c9b6b5d89f87aa6b22bfe1221643b036",
+        )
+        .expect("invalid hex");
+        let (enc, ciphertext) = message.split_at(32);
+        let enc = Ciphertext::try_from(enc).expect("invalid enc");
+
+        let plaintext = open_base::<DhKemX25519>(
+            &enc,
+            &recipient,
+            b"dryoc hpke test",
+            b"associated data",
+            ciphertext,
+        )
+        .expect("open failed");
+        assert_eq!(plaintext, b"hello hpke");
+
+        open_base::<DhKemX25519>(
+            &enc,
+            &recipient,
+            b"other info",
+            b"associated data",
+            ciphertext,
+        )
+        .expect_err("should fail with different info");
+    }
+
+    /// Test vector from RFC 9180, appendix A.2, for DHKEM(X25519,
+    /// HKDF-SHA256), HKDF-SHA256, and ChaCha20-Poly1305.
+    struct Rfc9180Vector {
+        mode: Mode,
+        ikm_e: &'static str,
+        ikm_r: &'static str,
+        ikm_s: Option<&'static str>,
+        psk: bool,
+        enc: &'static str,
+        shared_secret: &'static str,
+        key: &'static str,
+        base_nonce: &'static str,
+        exporter_secret: &'static str,
+        /// Sequence numbers and ciphertexts of the plaintext, with the
+        /// associated data "Count-<sequence number>"
+        encryptions: &'static [(u64, &'static str)],
+        /// 32 byte exports for the contexts "", 0x00, and "TestContext"
+        exports: [&'static str; 3],
+    }
+
+    fn unhex(s: &str) -> Vec<u8> {
+        hex::decode(s).expect("invalid hex")
+    }
+
+    fn check_rfc9180_vector(vector: &Rfc9180Vector) {
+        let info = unhex("4f6465206f6e2061204772656369616e2055726e");
+        let psk_bytes = unhex("0247fd33b913760fa1fa51e1892d9f307fbe65eb171e8132c2af18555a738b82");
+        let psk_id = unhex("456e6e796e20447572696e206172616e204d6f726961");
+        let psk = Psk::new(&psk_bytes, &psk_id);
+        let psk = vector.psk.then_some(&psk);
+        let plaintext = unhex("4265617574792069732074727574682c20747275746820626561757479");
+
+        let ephemeral = DhKemX25519::derive_keypair(&unhex(vector.ikm_e)).expect("derive failed");
+        let recipient = DhKemX25519::derive_keypair(&unhex(vector.ikm_r)).expect("derive failed");
+        let sender = vector
+            .ikm_s
+            .map(|ikm| DhKemX25519::derive_keypair(&unhex(ikm)).expect("derive failed"));
+
+        let (shared_secret, enc) = match &sender {
+            Some(sender) => DhKemX25519::auth_encapsulate_with_ephemeral_keypair(
+                &recipient.public_key,
+                sender,
+                &ephemeral,
+            ),
+            None => {
+                DhKemX25519::encapsulate_with_ephemeral_keypair(&recipient.public_key, &ephemeral)
+            }
+        }
+        .expect("encapsulate failed");
+        assert_eq!(hex::encode(&enc), vector.enc);
+        assert_eq!(hex::encode(&shared_secret), vector.shared_secret);
+
+        let recipient_shared_secret = match &sender {
+            Some(sender) => DhKemX25519::auth_decapsulate(&enc, &recipient, &sender.public_key),
+            None => DhKemX25519::decapsulate(&enc, &recipient),
+        }
+        .expect("decapsulate failed");
+        let mut receiver =
+            setup_recipient::<DhKemX25519>(vector.mode, recipient_shared_secret, &info, psk)
+                .expect("setup failed");
+        let (_, mut sender) =
+            setup_sender::<DhKemX25519>(vector.mode, (shared_secret, enc), &info, psk)
+                .expect("setup failed");
+        assert_eq!(hex::encode(sender.context.key), vector.key);
+        assert_eq!(hex::encode(sender.context.base_nonce), vector.base_nonce);
+        assert_eq!(
+            hex::encode(sender.context.exporter_secret),
+            vector.exporter_secret
+        );
+
+        for (seq, ciphertext) in vector.encryptions {
+            let aad = format!("Count-{}", seq);
+            sender.context.seq = *seq;
+            let sealed = sender
+                .seal(aad.as_bytes(), &plaintext)
+                .expect("seal failed");
+            assert_eq!(hex::encode(&sealed), *ciphertext);
+            receiver.context.seq = *seq;
+            assert_eq!(
+                receiver.open(aad.as_bytes(), &sealed).expect("open failed"),
+                plaintext
+            );
+        }
+
+        for (context, expected) in ["", "00", "54657374436f6e74657874"]
+            .iter()
+            .zip(vector.exports)
+        {
+            let mut exported = [0u8; 32];
+            sender
+                .export(&unhex(context), &mut exported)
+                .expect("export failed");
+            assert_eq!(hex::encode(exported), expected);
+            receiver
+                .export(&unhex(context), &mut exported)
+                .expect("export failed");
+            assert_eq!(hex::encode(exported), expected);
+        }
+    }
+
+    #[test]
+    fn test_rfc9180_vectors() {
+        // RFC 9180, appendix A.2.1
+        check_rfc9180_vector(&Rfc9180Vector {
+            mode: Mode::Base,
+            ikm_e: "909a9b35d3dc4713a5e72a4da274b55d3d3821a37e5d099e74a647db583a904b",
+            ikm_r: "1ac01f181fdf9f352797655161c58b75c656a6cc2716dcb66372da835542e1df",
+            ikm_s: None,
+            psk: false,
+            enc: "1afa08d3dec047a643885163f1180476fa7ddb54c6a8029ea33f95796bf2ac4a",
+            shared_secret: "0bbe78490412b4bbea4812666f7916932b828bba79942424abb65244930d69a7",
+            key: "ad2744de8e17f4ebba575b3f5f5a8fa1f69c2a07f6e7500bc60ca6e3e3ec1c91",
+            base_nonce: "5c4d98150661b848853b547f",
+            exporter_secret: "a3b010d4994890e2c6968a36f64470d3c824c8f5029942feb11e7a74b2921922",
+            encryptions: &[
+                (
+                    0,
+                    "1c5250d8034ec2b784ba2cfd69dbdb8af406cfe3ff938e131f0def8c8b60b4db21993c62ce81883d\
+                     2dd1b51a28",
+                ),
+                (
+                    1,
+                    "6b53c051e4199c518de79594e1c4ab18b96f081549d45ce015be002090bb119e85285337cc95ba5f\
+                     59992dc98c",
+                ),
+                (
+                    2,
+                    "71146bd6795ccc9c49ce25dda112a48f202ad220559502cef1f34271e0cb4b02b4f10ecac6f48c32\
+                     f878fae86b",
+                ),
+                (
+                    4,
+                    "63357a2aa291f5a4e5f27db6baa2af8cf77427c7c1a909e0b37214dd47db122bb153495ff0b02e9e\
+                     54a50dbe16",
+                ),
+                (
+                    255,
+                    "18ab939d63ddec9f6ac2b60d61d36a7375d2070c9b683861110757062c52b8880a5f6b3936da9cd6\
+                     c23ef2a95c",
+                ),
+                (
+                    256,
+                    "7a4a13e9ef23978e2c520fd4d2e757514ae160cd0cd05e556ef692370ca53076214c0c40d4c728d6\
+                     ed9e727a5b",
+                ),
+            ],
+            exports: [
+                "4bbd6243b8bb54cec311fac9df81841b6fd61f56538a775e7c80a9f40160606e",
+                "8c1df14732580e5501b00f82b10a1647b40713191b7c1240ac80e2b68808ba69",
+                "5acb09211139c43b3090489a9da433e8a30ee7188ba8b0a9a1ccf0c229283e53",
+            ],
+        });
+
+        // RFC 9180, appendix A.2.2
+        check_rfc9180_vector(&Rfc9180Vector {
+            mode: Mode::Psk,
+            ikm_e: "35706a0b09fb26fb45c39c2f5079c709c7cf98e43afa973f14d88ece7e29c2e3",
+            ikm_r: "26b923eade72941c8a85b09986cdfa3f1296852261adedc52d58d2930269812b",
+            ikm_s: None,
+            psk: true,
+            enc: "2261299c3f40a9afc133b969a97f05e95be2c514e54f3de26cbe5644ac735b04",
+            shared_secret: "4be079c5e77779d0215b3f689595d59e3e9b0455d55662d1f3666ec606e50ea7",
+            key: "600d2fdb0313a7e5c86a9ce9221cd95bed069862421744cfb4ab9d7203a9c019",
+            base_nonce: "112e0465562045b7368653e7",
+            exporter_secret: "73b506dc8b6b4269027f80b0362def5cbb57ee50eed0c2873dac9181f453c5ac",
+            encryptions: &[(
+                0,
+                "4a177f9c0d6f15cfdf533fb65bf84aecdc6ab16b8b85b4cf65a370e07fc1d78d28fb073214525276f4\
+                 a89608ff",
+            )],
+            exports: [
+                "813c1bfc516c99076ae0f466671f0ba5ff244a41699f7b2417e4c59d46d39f40",
+                "2745cf3d5bb65c333658732954ee7af49eb895ce77f8022873a62a13c94cb4e1",
+                "ad40e3ae14f21c99bfdebc20ae14ab86f4ca2dc9a4799d200f43a25f99fa78ae",
+            ],
+        });
+
+        // RFC 9180, appendix A.2.3
+        check_rfc9180_vector(&Rfc9180Vector {
+            mode: Mode::Auth,
+            ikm_e: "938d3daa5a8904540bc24f48ae90eed3f4f7f11839560597b55e7c9598c996c0",
+            ikm_r: "64835d5ee64aa7aad57c6f2e4f758f7696617f8829e70bc9ac7a5ef95d1c756c",
+            ikm_s: Some("9d8f94537d5a3ddef71234c0baedfad4ca6861634d0b94c3007fed557ad17df6"),
+            psk: false,
+            enc: "f7674cc8cd7baa5872d1f33dbaffe3314239f6197ddf5ded1746760bfc847e0e",
+            shared_secret: "d2d67828c8bc9fa661cf15a31b3ebf1febe0cafef7abfaaca580aaf6d471e3eb",
+            key: "b071fd1136680600eb447a845a967d35e9db20749cdf9ce098bcc4deef4b1356",
+            base_nonce: "d20577dff16d7cea2c4bf780",
+            exporter_secret: "be2d93b82071318cdb88510037cf504344151f2f9b9da8ab48974d40a2251dd7",
+            encryptions: &[(
+                0,
+                "ab1a13c9d4f01a87ec3440dbd756e2677bd2ecf9df0ce7ed73869b98e00c09be111cb9fdf077347aeb\
+                 88e61bdf",
+            )],
+            exports: [
+                "070cffafd89b67b7f0eeb800235303a223e6ff9d1e774dce8eac585c8688c872",
+                "2852e728568d40ddb0edde284d36a4359c56558bb2fb8837cd3d92e46a3a14a8",
+                "1df39dc5dd60edcbf5f9ae804e15ada66e885b28ed7929116f768369a3f950ee",
+            ],
+        });
+
+        // RFC 9180, appendix A.2.4
+        check_rfc9180_vector(&Rfc9180Vector {
+            mode: Mode::AuthPsk,
+            ikm_e: "49d6eac8c6c558c953a0a252929a818745bb08cd3d29e15f9f5db5eb2e7d4b84",
+            ikm_r: "f3304ddcf15848488271f12b75ecaf72301faabf6ad283654a14c398832eb184",
+            ikm_s: Some("20ade1d5203de1aadfb261c4700b6432e260d0d317be6ebbb8d7fffb1f86ad9d"),
+            psk: true,
+            enc: "656a2e00dc9990fd189e6e473459392df556e9a2758754a09db3f51179a3fc02",
+            shared_secret: "86a6c0ed17714f11d2951747e660857a5fd7616c933ef03207808b7a7123fe67",
+            key: "49c7e6d7d2d257aded2a746fe6a9bf12d4de8007c4862b1fdffe8c35fb65054c",
+            base_nonce: "abac79931e8c1bcb8a23960a",
+            exporter_secret: "7c6cc1bb98993cd93e2599322247a58fd41fdecd3db895fb4c5fd8d6bbe606b5",
+            encryptions: &[(
+                0,
+                "9aa52e29274fc6172e38a4461361d2342585d3aeec67fb3b721ecd63f059577c7fe886be0ede01456e\
+                 bc67d597",
+            )],
+            exports: [
+                "c23ebd4e7a0ad06a5dddf779f65004ce9481069ce0f0e6dd51a04539ddcbd5cd",
+                "ed7ff5ca40a3d84561067ebc8e01702bc36cf1eb99d42a92004642b9dfaadd37",
+                "d3bae066aa8da27d527d85c040f7dd6ccb60221c902ee36a82f70bcd62a60ee4",
+            ],
+        });
+    }
+
+    #[test]
+    fn test_modes() {
+        let recipient = DhKemX25519::gen_keypair();
+        let sender = DhKemX25519::gen_keypair();
+        let psk = Psk::new(b"0123456789abcdef0123456789abcdef", b"psk id");
+
+        let (enc, ciphertext) =
+            seal_psk::<DhKemX25519>(&recipient.public_key, b"info", b"aad", b"psk", &psk)
+                .expect("seal failed");
+        assert_eq!(
+            open_psk::<DhKemX25519>(&enc, &recipient, b"info", b"aad", &ciphertext, &psk)
+                .expect("open failed"),
+            b"psk"
+        );
+        let wrong_psk = Psk::new(b"fedcba9876543210fedcba9876543210", b"psk id");
+        open_psk::<DhKemX25519>(&enc, &recipient, b"info", b"aad", &ciphertext, &wrong_psk)
+            .expect_err("should fail with wrong psk");
+        open_base::<DhKemX25519>(&enc, &recipient, b"info", b"aad", &ciphertext)
+            .expect_err("should fail in base mode");
+
+        let (enc, ciphertext) =
+            seal_auth::<DhKemX25519>(&recipient.public_key, b"info", b"aad", b"auth", &sender)
+                .expect("seal failed");
+        assert_eq!(
+            open_auth::<DhKemX25519>(
+                &enc,
+                &recipient,
+                b"info",
+                b"aad",
+                &ciphertext,
+                &sender.public_key
+            )
+            .expect("open failed"),
+            b"auth"
+        );
+        open_auth::<DhKemX25519>(
+            &enc,
+            &recipient,
+            b"info",
+            b"aad",
+            &ciphertext,
+            &recipient.public_key,
+        )
+        .expect_err("should fail with wrong sender");
+
+        let (enc, ciphertext) = seal_auth_psk::<DhKemX25519>(
+            &recipient.public_key,
+            b"info",
+            b"aad",
+            b"auth psk",
+            &psk,
+            &sender,
+        )
+        .expect("seal failed");
+        assert_eq!(
+            open_auth_psk::<DhKemX25519>(
+                &enc,
+                &recipient,
+                b"info",
+                b"aad",
+                &ciphertext,
+                &psk,
+                &sender.public_key
+            )
+            .expect("open failed"),
+            b"auth psk"
+        );
+
+        seal_psk::<DhKemX25519>(
+            &recipient.public_key,
+            b"info",
+            b"aad",
+            b"psk",
+            &Psk::new(b"", b"psk id"),
+        )
+        .expect_err("empty psk should be rejected");
+    }
+
+    #[test]
+    fn test_context() {
+        let recipient = DhKemX25519::gen_keypair();
+        let (enc, mut sender) =
+            setup_base_sender::<DhKemX25519>(&recipient.public_key, b"info").expect("setup");
+        let mut receiver =
+            setup_base_recipient::<DhKemX25519>(&enc, &recipient, b"info").expect("setup");
+
+        let messages: Vec<Vec<u8>> = (0..3)
+            .map(|i| sender.seal(b"aad", &[i; 8]).expect("seal failed"))
+            .collect();
+        assert_ne!(messages[0], messages[1]);
+
+        // messages must be opened in order
+        receiver
+            .open(b"aad", &messages[1])
+            .expect_err("should fail out of order");
+        for (i, message) in messages.iter().enumerate() {
+            assert_eq!(
+                receiver.open(b"aad", message).expect("open failed"),
+                [i as u8; 8]
+            );
+        }
+
+        let mut sender_secret = [0u8; 48];
+        let mut receiver_secret = [0u8; 48];
+        sender
+            .export(b"exporter", &mut sender_secret)
+            .expect("export failed");
+        receiver
+            .export(b"exporter", &mut receiver_secret)
+            .expect("export failed");
+        assert_eq!(sender_secret, receiver_secret);
+        receiver
+            .export(b"other", &mut receiver_secret)
+            .expect("export failed");
+        assert_ne!(sender_secret, receiver_secret);
+    }
+}
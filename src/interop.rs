@@ -0,0 +1,252 @@
+//! Conversions between dryoc's key and signature types and those of the
+//! RustCrypto and dalek crates, enabled with the `x25519-dalek`,
+//! `ed25519-dalek`, and `chacha20poly1305` features respectively.
+//!
+//! The byte representations are identical, so these conversions simply copy
+//! bytes across, validating them where the target type requires it.
+#[cfg(feature = "chacha20poly1305")]
+mod chacha20poly1305_interop {
+    use chacha20poly1305::{Key, Nonce, Tag, XNonce};
+
+    use crate::types::*;
+
+    impl From<&StackByteArray<32>> for Key {
+        fn from(key: &StackByteArray<32>) -> Self {
+            Key::clone_from_slice(key.as_slice())
+        }
+    }
+
+    impl From<&Key> for StackByteArray<32> {
+        fn from(key: &Key) -> Self {
+            Self::from(<[u8; 32]>::from(*key))
+        }
+    }
+
+    impl From<&StackByteArray<24>> for XNonce {
+        fn from(nonce: &StackByteArray<24>) -> Self {
+            XNonce::clone_from_slice(nonce.as_slice())
+        }
+    }
+
+    impl From<&XNonce> for StackByteArray<24> {
+        fn from(nonce: &XNonce) -> Self {
+            Self::from(<[u8; 24]>::from(*nonce))
+        }
+    }
+
+    impl From<&StackByteArray<12>> for Nonce {
+        fn from(nonce: &StackByteArray<12>) -> Self {
+            Nonce::clone_from_slice(nonce.as_slice())
+        }
+    }
+
+    impl From<&Nonce> for StackByteArray<12> {
+        fn from(nonce: &Nonce) -> Self {
+            Self::from(<[u8; 12]>::from(*nonce))
+        }
+    }
+
+    impl From<&Tag> for StackByteArray<16> {
+        fn from(tag: &Tag) -> Self {
+            Self::from(<[u8; 16]>::from(*tag))
+        }
+    }
+}
+
+#[cfg(feature = "ed25519-dalek")]
+mod ed25519_interop {
+    use ed25519_dalek::{Signature, SigningKey, VerifyingKey};
+
+    use crate::error::Error;
+    use crate::sign::{PublicKey, SecretKey, SigningKeyPair};
+    use crate::types::*;
+
+    impl From<&VerifyingKey> for StackByteArray<32> {
+        fn from(public_key: &VerifyingKey) -> Self {
+            Self::from(public_key.to_bytes())
+        }
+    }
+
+    impl TryFrom<&StackByteArray<32>> for VerifyingKey {
+        type Error = Error;
+
+        fn try_from(public_key: &StackByteArray<32>) -> Result<Self, Self::Error> {
+            VerifyingKey::from_bytes(public_key.as_array())
+                .map_err(|_| dryoc_error!("invalid Ed25519 public key"))
+        }
+    }
+
+    impl From<&SigningKey> for StackByteArray<64> {
+        fn from(secret_key: &SigningKey) -> Self {
+            Self::from(secret_key.to_keypair_bytes())
+        }
+    }
+
+    impl TryFrom<&StackByteArray<64>> for SigningKey {
+        type Error = Error;
+
+        fn try_from(secret_key: &StackByteArray<64>) -> Result<Self, Self::Error> {
+            SigningKey::from_keypair_bytes(secret_key.as_array())
+                .map_err(|_| dryoc_error!("invalid Ed25519 secret key"))
+        }
+    }
+
+    impl From<&Signature> for StackByteArray<64> {
+        fn from(signature: &Signature) -> Self {
+            Self::from(signature.to_bytes())
+        }
+    }
+
+    impl From<&StackByteArray<64>> for Signature {
+        fn from(signature: &StackByteArray<64>) -> Self {
+            Signature::from_bytes(signature.as_array())
+        }
+    }
+
+    impl From<&SigningKey> for SigningKeyPair<PublicKey, SecretKey> {
+        fn from(secret_key: &SigningKey) -> Self {
+            Self::from_secret_key(secret_key.into())
+        }
+    }
+
+    impl TryFrom<&SigningKeyPair<PublicKey, SecretKey>> for SigningKey {
+        type Error = Error;
+
+        fn try_from(keypair: &SigningKeyPair<PublicKey, SecretKey>) -> Result<Self, Self::Error> {
+            SigningKey::try_from(&keypair.secret_key)
+        }
+    }
+}
+
+#[cfg(feature = "x25519-dalek")]
+mod x25519_interop {
+    use x25519_dalek::{PublicKey, StaticSecret};
+
+    use crate::keypair::StackKeyPair;
+    use crate::types::*;
+
+    impl From<&PublicKey> for StackByteArray<32> {
+        fn from(public_key: &PublicKey) -> Self {
+            Self::from(public_key.to_bytes())
+        }
+    }
+
+    impl From<&StackByteArray<32>> for PublicKey {
+        fn from(public_key: &StackByteArray<32>) -> Self {
+            PublicKey::from(*public_key.as_array())
+        }
+    }
+
+    impl From<&StaticSecret> for StackByteArray<32> {
+        fn from(secret_key: &StaticSecret) -> Self {
+            Self::from(secret_key.to_bytes())
+        }
+    }
+
+    impl From<&StackByteArray<32>> for StaticSecret {
+        fn from(secret_key: &StackByteArray<32>) -> Self {
+            StaticSecret::from(*secret_key.as_array())
+        }
+    }
+
+    impl From<&StaticSecret> for StackKeyPair {
+        fn from(secret_key: &StaticSecret) -> Self {
+            Self {
+                public_key: (&PublicKey::from(secret_key)).into(),
+                secret_key: secret_key.into(),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(feature = "x25519-dalek")]
+    #[test]
+    fn test_x25519_dalek() {
+        use crate::dryocbox::*;
+
+        let keypair = KeyPair::gen();
+        let secret = x25519_dalek::StaticSecret::from(&keypair.secret_key);
+        let public = x25519_dalek::PublicKey::from(&keypair.public_key);
+        assert_eq!(x25519_dalek::PublicKey::from(&secret), public);
+
+        let converted = KeyPair::from(&secret);
+        assert_eq!(converted.public_key, keypair.public_key);
+        assert_eq!(converted.secret_key, keypair.secret_key);
+
+        // a shared secret computed on the dalek side matches crypto_box's
+        let other = x25519_dalek::StaticSecret::random_from_rng(rand_core::OsRng);
+        let other_public = x25519_dalek::PublicKey::from(&other);
+        let nonce = Nonce::gen();
+        let dryocbox = DryocBox::encrypt_to_vecbox(
+            b"hello",
+            &nonce,
+            &PublicKey::from(&other_public),
+            &keypair.secret_key,
+        )
+        .expect("encrypt failed");
+        let decrypted = dryocbox
+            .decrypt_to_vec(&nonce, &keypair.public_key, &SecretKey::from(&other))
+            .expect("decrypt failed");
+        assert_eq!(decrypted, b"hello");
+    }
+
+    #[cfg(feature = "ed25519-dalek")]
+    #[test]
+    fn test_ed25519_dalek() {
+        use ed25519_dalek::{Signer, Verifier};
+
+        use crate::sign::*;
+
+        let keypair = SigningKeyPair::gen_with_defaults();
+        let signing_key = ed25519_dalek::SigningKey::try_from(&keypair).expect("invalid key");
+        let verifying_key =
+            ed25519_dalek::VerifyingKey::try_from(&keypair.public_key).expect("invalid key");
+        assert_eq!(signing_key.verifying_key(), verifying_key);
+
+        let signed = keypair.sign_with_defaults(b"hello").expect("sign failed");
+        let (signature, message) = signed.into_parts();
+        verifying_key
+            .verify(&message, &ed25519_dalek::Signature::from(&signature))
+            .expect("verify failed");
+
+        let dalek_signature = signing_key.sign(b"hello");
+        assert_eq!(Signature::from(&dalek_signature), signature);
+
+        let converted = SigningKeyPair::from(&signing_key);
+        assert_eq!(converted.public_key, keypair.public_key);
+
+        let mut mismatched = keypair.secret_key.clone();
+        mismatched[63] ^= 1;
+        assert!(ed25519_dalek::SigningKey::try_from(&mismatched).is_err());
+    }
+
+    #[cfg(feature = "chacha20poly1305")]
+    #[test]
+    fn test_chacha20poly1305() {
+        use chacha20poly1305::aead::{Aead, KeyInit};
+        use chacha20poly1305::XChaCha20Poly1305;
+
+        use crate::types::*;
+
+        let key = StackByteArray::<32>::gen();
+        let nonce = StackByteArray::<24>::gen();
+        let cipher = XChaCha20Poly1305::new(&(&key).into());
+        let ciphertext = cipher
+            .encrypt(&(&nonce).into(), b"hello".as_slice())
+            .expect("encrypt failed");
+        assert_eq!(
+            cipher
+                .decrypt(
+                    &chacha20poly1305::XNonce::from(&nonce),
+                    ciphertext.as_slice()
+                )
+                .expect("decrypt failed"),
+            b"hello"
+        );
+
+        let dalek_key = chacha20poly1305::Key::from(&key);
+        assert_eq!(StackByteArray::<32>::from(&dalek_key), key);
+    }
+}
@@ -0,0 +1,585 @@
+//! # JOSE tokens
+//!
+//! Minimal support for JSON Web Signatures ([RFC
+//! 7515](https://www.rfc-editor.org/rfc/rfc7515)) and JSON Web Encryption
+//! ([RFC 7516](https://www.rfc-editor.org/rfc/rfc7516)) in the compact
+//! serialization, for issuing and consuming standards-compliant tokens
+//! without a full JOSE implementation.
+//!
+//! * [`jws`] signs and verifies with Ed25519 (`EdDSA`, as described in [RFC 8037](https://www.rfc-editor.org/rfc/rfc8037)),
+//!   or any other algorithm implementing the [`Signer`](crate::sign::Signer)
+//!   and [`Verifier`](crate::sign::Verifier) traits
+//! * [`jwe`] encrypts and decrypts with direct X25519 key agreement (`ECDH-ES`,
+//!   also from RFC 8037), and ChaCha20-Poly1305 (`C20P`) or XChaCha20-Poly1305
+//!   (`XC20P`) content encryption
+//!
+//! Only these algorithms are accepted when verifying or decrypting, so
+//! tokens can't be downgraded to `none` or some other algorithm. When
+//! verifying with a [`Verifier`](crate::sign::Verifier), the token's `alg`
+//! must match the verifier's algorithm. Tokens with
+//! a `crit` header parameter are rejected, as no extensions are supported.
+//!
+//! ## Example
+//!
+//! ```
+//! use dryoc::dryocbox::KeyPair;
+//! use dryoc::jose::*;
+//! use dryoc::sign::SigningKeyPair;
+//!
+//! let signing_keypair = SigningKeyPair::gen_with_defaults();
+//! let token = jws::sign(br#"{"sub":"alice"}"#, &signing_keypair).expect("sign failed");
+//! let claims = jws::verify(&token, &signing_keypair.public_key).expect("verify failed");
+//! assert_eq!(claims, br#"{"sub":"alice"}"#);
+//!
+//! let recipient = KeyPair::gen();
+//! let token = jwe::encrypt(b"secret", ContentEncryption::XC20P, &recipient.public_key)
+//!     .expect("encrypt failed");
+//! let decrypted: Vec<u8> = jwe::decrypt(&token, &recipient).expect("decrypt failed");
+//! assert_eq!(decrypted, b"secret");
+//! ```
+use base64::Engine as _;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use serde::{Deserialize, Serialize};
+
+use crate::error::Error;
+
+/// Content encryption algorithm for [`jwe`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ContentEncryption {
+    /// ChaCha20-Poly1305 with a 96 bit nonce
+    C20P,
+    /// XChaCha20-Poly1305 with a 192 bit nonce
+    XC20P,
+}
+
+impl ContentEncryption {
+    /// Returns the `enc` header value for this algorithm.
+    pub fn name(&self) -> &'static str {
+        match self {
+            ContentEncryption::C20P => "C20P",
+            ContentEncryption::XC20P => "XC20P",
+        }
+    }
+
+    fn from_name(name: &str) -> Result<Self, Error> {
+        match name {
+            "C20P" => Ok(ContentEncryption::C20P),
+            "XC20P" => Ok(ContentEncryption::XC20P),
+            _ => Err(dryoc_error!(format!(
+                "unsupported content encryption {}",
+                name
+            ))),
+        }
+    }
+
+    fn nonce_length(&self) -> usize {
+        match self {
+            ContentEncryption::C20P => crate::constants::CRYPTO_STREAM_CHACHA20_IETF_NONCEBYTES,
+            ContentEncryption::XC20P => {
+                crate::constants::CRYPTO_AEAD_XCHACHA20POLY1305_IETF_NPUBBYTES
+            }
+        }
+    }
+}
+
+/// A public JSON Web Key for an X25519 key, as used for the ephemeral key in
+/// [`jwe`] tokens.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Jwk {
+    /// Key type, `OKP`
+    pub kty: String,
+    /// Curve, `X25519`
+    pub crv: String,
+    /// Base64url-encoded public key
+    pub x: String,
+}
+
+/// The JOSE header parameters understood by this module.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Header {
+    /// Algorithm
+    pub alg: String,
+    /// Content encryption algorithm, for JWE
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub enc: Option<String>,
+    /// Ephemeral public key, for JWE
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub epk: Option<Jwk>,
+    /// Media type of the token
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub typ: Option<String>,
+    /// Key ID
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub kid: Option<String>,
+    /// Critical extensions, which aren't supported
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub crit: Option<Vec<String>>,
+}
+
+impl Header {
+    /// Decodes the header of the compact serialized `token`, without verifying
+    /// or decrypting it. This can be used to look up the key with the header's
+    /// `kid`.
+    pub fn from_token(token: &str) -> Result<Self, Error> {
+        let encoded = token
+            .split('.')
+            .next()
+            .ok_or_else(|| dryoc_error!("empty token"))?;
+        let header: Self = serde_json::from_slice(&decode(encoded)?)
+            .map_err(|err| dryoc_error!(format!("invalid JOSE header: {}", err)))?;
+        if header.crit.is_some() {
+            return Err(dryoc_error!("critical header extensions are not supported"));
+        }
+        Ok(header)
+    }
+
+    fn encode(&self) -> String {
+        URL_SAFE_NO_PAD.encode(serde_json::to_vec(self).expect("header serialization failed"))
+    }
+}
+
+fn decode(encoded: &str) -> Result<Vec<u8>, Error> {
+    URL_SAFE_NO_PAD
+        .decode(encoded)
+        .map_err(|err| dryoc_error!(format!("invalid base64url: {}", err)))
+}
+
+fn split<const PARTS: usize>(token: &str) -> Result<[&str; PARTS], Error> {
+    let mut parts = [""; PARTS];
+    let mut split = token.split('.');
+    for part in parts.iter_mut() {
+        *part = split
+            .next()
+            .ok_or_else(|| dryoc_error!(format!("token must have {} parts", PARTS)))?;
+    }
+    if split.next().is_some() {
+        return Err(dryoc_error!(format!("token must have {} parts", PARTS)));
+    }
+    Ok(parts)
+}
+
+/// # JSON Web Signatures with EdDSA
+///
+/// [`sign`] and [`verify`] use Ed25519. Other signature algorithms can be
+/// used by passing a [`Signer`] to [`sign_with`], and a [`Verifier`] to
+/// [`verify_with`].
+pub mod jws {
+    use zeroize::Zeroize;
+
+    use super::*;
+    use crate::constants::{CRYPTO_SIGN_PUBLICKEYBYTES, CRYPTO_SIGN_SECRETKEYBYTES};
+    use crate::sign::{PublicKey as Ed25519PublicKey, Signer, SigningKeyPair, Verifier};
+    use crate::types::*;
+
+    /// Signs `payload` with `keypair`, returning the compact serialized JWS.
+    pub fn sign<
+        PublicKey: ByteArray<CRYPTO_SIGN_PUBLICKEYBYTES> + Zeroize,
+        SecretKey: ByteArray<CRYPTO_SIGN_SECRETKEYBYTES> + Zeroize,
+    >(
+        payload: &[u8],
+        keypair: &SigningKeyPair<PublicKey, SecretKey>,
+    ) -> Result<String, Error> {
+        sign_with(payload, Header::default(), keypair)
+    }
+
+    /// Signs `payload` with `keypair`, including the `typ` and `kid`
+    /// parameters from `header`. The `alg` parameter is always set to
+    /// `EdDSA`.
+    pub fn sign_with_header<
+        PublicKey: ByteArray<CRYPTO_SIGN_PUBLICKEYBYTES> + Zeroize,
+        SecretKey: ByteArray<CRYPTO_SIGN_SECRETKEYBYTES> + Zeroize,
+    >(
+        payload: &[u8],
+        header: Header,
+        keypair: &SigningKeyPair<PublicKey, SecretKey>,
+    ) -> Result<String, Error> {
+        sign_with(payload, header, keypair)
+    }
+
+    /// Signs `payload` with `signer`, including the `typ` and `kid`
+    /// parameters from `header`. The `alg` parameter is set from the
+    /// signer's [algorithm](Signer::algorithm).
+    pub fn sign_with<S: Signer + ?Sized>(
+        payload: &[u8],
+        header: Header,
+        signer: &S,
+    ) -> Result<String, Error> {
+        let header = Header {
+            alg: signer.algorithm().jws_name().into(),
+            typ: header.typ,
+            kid: header.kid,
+            ..Default::default()
+        };
+
+        let mut token = header.encode();
+        token.push('.');
+        token.push_str(&URL_SAFE_NO_PAD.encode(payload));
+
+        let signature = signer.try_sign(token.as_bytes())?;
+        token.push('.');
+        token.push_str(&URL_SAFE_NO_PAD.encode(signature));
+
+        Ok(token)
+    }
+
+    /// Verifies the compact serialized JWS `token` with `public_key`, and
+    /// returns its payload.
+    pub fn verify<PublicKey: ByteArray<CRYPTO_SIGN_PUBLICKEYBYTES>>(
+        token: &str,
+        public_key: &PublicKey,
+    ) -> Result<Vec<u8>, Error> {
+        verify_with(token, &Ed25519PublicKey::from(*public_key.as_array()))
+    }
+
+    /// Verifies the compact serialized JWS `token` with `verifier`, and
+    /// returns its payload. The token's `alg` parameter must match the
+    /// verifier's [algorithm](Verifier::algorithm).
+    pub fn verify_with<V: Verifier + ?Sized>(token: &str, verifier: &V) -> Result<Vec<u8>, Error> {
+        let [header, payload, signature] = split::<3>(token)?;
+        if Header::from_token(header)?.alg != verifier.algorithm().jws_name() {
+            return Err(dryoc_error!("unsupported JWS algorithm"));
+        }
+
+        let signing_input = &token[..header.len() + 1 + payload.len()];
+        verifier.verify(signing_input.as_bytes(), &decode(signature)?)?;
+
+        decode(payload)
+    }
+}
+
+/// # JSON Web Encryption with ECDH-ES and (X)ChaCha20-Poly1305
+pub mod jwe {
+    use sha2::{Digest, Sha256};
+    use subtle::ConstantTimeEq;
+    use zeroize::Zeroize;
+
+    use super::*;
+    use crate::aead::{
+        chacha20poly1305_ietf_decrypt, chacha20poly1305_ietf_encrypt,
+        xchacha20poly1305_ietf_decrypt, xchacha20poly1305_ietf_encrypt,
+    };
+    use crate::classic::crypto_core::crypto_scalarmult;
+    use crate::classic::crypto_kx::crypto_kx_keypair;
+    use crate::constants::{
+        CRYPTO_AEAD_XCHACHA20POLY1305_IETF_ABYTES, CRYPTO_BOX_PUBLICKEYBYTES,
+        CRYPTO_BOX_SECRETKEYBYTES, CRYPTO_SCALARMULT_BYTES,
+    };
+    use crate::keypair::KeyPair;
+    use crate::rng::randombytes_buf;
+    use crate::types::*;
+
+    const ALG: &str = "ECDH-ES";
+    const KTY: &str = "OKP";
+    const CRV: &str = "X25519";
+
+    /// Encrypts `plaintext` for `recipient_public_key` with `enc`, returning
+    /// the compact serialized JWE.
+    pub fn encrypt<RecipientPublicKey: ByteArray<CRYPTO_BOX_PUBLICKEYBYTES>>(
+        plaintext: &[u8],
+        enc: ContentEncryption,
+        recipient_public_key: &RecipientPublicKey,
+    ) -> Result<String, Error> {
+        let (ephemeral_public_key, mut ephemeral_secret_key) = crypto_kx_keypair();
+        let cek = derive_key(
+            ephemeral_secret_key.as_array(),
+            recipient_public_key.as_array(),
+            enc,
+        );
+        ephemeral_secret_key.zeroize();
+        let mut cek = cek?;
+
+        let header = Header {
+            alg: ALG.into(),
+            enc: Some(enc.name().into()),
+            epk: Some(Jwk {
+                kty: KTY.into(),
+                crv: CRV.into(),
+                x: URL_SAFE_NO_PAD.encode(ephemeral_public_key.as_slice()),
+            }),
+            ..Default::default()
+        };
+
+        let encoded_header = header.encode();
+        let nonce = randombytes_buf(enc.nonce_length());
+        let mut ciphertext = match enc {
+            ContentEncryption::C20P => chacha20poly1305_ietf_encrypt(
+                plaintext,
+                encoded_header.as_bytes(),
+                ByteArray::as_array(&nonce[..]),
+                &cek,
+            ),
+            ContentEncryption::XC20P => xchacha20poly1305_ietf_encrypt(
+                plaintext,
+                encoded_header.as_bytes(),
+                ByteArray::as_array(&nonce[..]),
+                &cek,
+            ),
+        };
+        cek.zeroize();
+        let tag =
+            ciphertext.split_off(ciphertext.len() - CRYPTO_AEAD_XCHACHA20POLY1305_IETF_ABYTES);
+
+        Ok([
+            encoded_header.as_str(),
+            "",
+            &URL_SAFE_NO_PAD.encode(nonce),
+            &URL_SAFE_NO_PAD.encode(ciphertext),
+            &URL_SAFE_NO_PAD.encode(tag),
+        ]
+        .join("."))
+    }
+
+    /// Decrypts the compact serialized JWE `token` with `recipient_keypair`.
+    pub fn decrypt<
+        RecipientPublicKey: ByteArray<CRYPTO_BOX_PUBLICKEYBYTES> + Zeroize,
+        RecipientSecretKey: ByteArray<CRYPTO_BOX_SECRETKEYBYTES> + Zeroize,
+        Output: ResizableBytes + NewBytes,
+    >(
+        token: &str,
+        recipient_keypair: &KeyPair<RecipientPublicKey, RecipientSecretKey>,
+    ) -> Result<Output, Error> {
+        let [encoded_header, encrypted_key, nonce, ciphertext, tag] = split::<5>(token)?;
+        let header = Header::from_token(encoded_header)?;
+        if header.alg != ALG {
+            return Err(dryoc_error!("unsupported JWE algorithm"));
+        }
+        if !encrypted_key.is_empty() {
+            return Err(dryoc_error!("encrypted key must be empty for ECDH-ES"));
+        }
+        let enc = ContentEncryption::from_name(
+            header
+                .enc
+                .as_deref()
+                .ok_or_else(|| dryoc_error!("missing content encryption"))?,
+        )?;
+        let epk = header
+            .epk
+            .ok_or_else(|| dryoc_error!("missing ephemeral public key"))?;
+        if epk.kty != KTY || epk.crv != CRV {
+            return Err(dryoc_error!("ephemeral public key must be an X25519 key"));
+        }
+        let ephemeral_public_key = decode(&epk.x)?;
+        if ephemeral_public_key.len() != CRYPTO_SCALARMULT_BYTES {
+            return Err(dryoc_error!("invalid ephemeral public key length"));
+        }
+        let nonce = decode(nonce)?;
+        if nonce.len() != enc.nonce_length() {
+            return Err(dryoc_error!("invalid nonce length"));
+        }
+        let mut ciphertext = decode(ciphertext)?;
+        ciphertext.extend_from_slice(&decode(tag)?);
+
+        let mut cek = derive_key(
+            recipient_keypair.secret_key.as_array(),
+            ByteArray::as_array(&ephemeral_public_key[..]),
+            enc,
+        )?;
+        let plaintext = match enc {
+            ContentEncryption::C20P => chacha20poly1305_ietf_decrypt(
+                &ciphertext,
+                encoded_header.as_bytes(),
+                ByteArray::as_array(&nonce[..]),
+                &cek,
+            ),
+            ContentEncryption::XC20P => xchacha20poly1305_ietf_decrypt(
+                &ciphertext,
+                encoded_header.as_bytes(),
+                ByteArray::as_array(&nonce[..]),
+                &cek,
+            ),
+        };
+        cek.zeroize();
+        let mut plaintext = plaintext?;
+
+        let mut output = Output::new_bytes();
+        output.resize(plaintext.len(), 0);
+        output.as_mut_slice().copy_from_slice(&plaintext);
+        plaintext.zeroize();
+
+        Ok(output)
+    }
+
+    /// Computes the X25519 shared secret, and derives the content encryption
+    /// key from it with the Concat KDF, as described in RFC 7518, section
+    /// 4.6.2.
+    fn derive_key(
+        secret_key: &[u8; CRYPTO_BOX_SECRETKEYBYTES],
+        public_key: &[u8; CRYPTO_BOX_PUBLICKEYBYTES],
+        enc: ContentEncryption,
+    ) -> Result<[u8; 32], Error> {
+        let mut shared_secret = [0u8; CRYPTO_SCALARMULT_BYTES];
+        crypto_scalarmult(&mut shared_secret, secret_key, public_key);
+        // RFC 8037, section 3.2.2: reject the all-zero shared secret
+        if shared_secret
+            .ct_eq(&[0u8; CRYPTO_SCALARMULT_BYTES])
+            .unwrap_u8()
+            == 1
+        {
+            return Err(dryoc_error!("invalid public key"));
+        }
+
+        let mut key = [0u8; 32];
+        concat_kdf(&mut key, &shared_secret, enc.name().as_bytes(), b"", b"");
+        shared_secret.zeroize();
+        Ok(key)
+    }
+
+    pub(super) fn concat_kdf(
+        output: &mut [u8],
+        shared_secret: &[u8],
+        algorithm_id: &[u8],
+        party_u_info: &[u8],
+        party_v_info: &[u8],
+    ) {
+        let bits = (output.len() * 8) as u32;
+        for (i, chunk) in output.chunks_mut(32).enumerate() {
+            let mut hash = Sha256::new()
+                .chain_update(((i + 1) as u32).to_be_bytes())
+                .chain_update(shared_secret)
+                .chain_update((algorithm_id.len() as u32).to_be_bytes())
+                .chain_update(algorithm_id)
+                .chain_update((party_u_info.len() as u32).to_be_bytes())
+                .chain_update(party_u_info)
+                .chain_update((party_v_info.len() as u32).to_be_bytes())
+                .chain_update(party_v_info)
+                .chain_update(bits.to_be_bytes())
+                .finalize();
+            chunk.copy_from_slice(&hash[..chunk.len()]);
+            hash.as_mut_slice().zeroize();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dryocbox::KeyPair;
+    use crate::sign::{SecretKey, SigningKeyPair};
+    use crate::types::*;
+
+    #[test]
+    fn test_jws_rfc8037() {
+        // RFC 8037, appendix A.4
+        let seed = decode("nWGxne_9WmC6hEr0kuwsxERJxWl7MmkZcDusAxyuf2A").unwrap();
+        let public_key = decode("11qYAYKxCrfVS_7TyWQHOg7hcvPapiMlrwIaaPcHURo").unwrap();
+        let mut secret_key = seed.clone();
+        secret_key.extend_from_slice(&public_key);
+        let keypair: SigningKeyPair<crate::sign::PublicKey, SecretKey> =
+            SigningKeyPair::from_secret_key(
+                SecretKey::try_from(secret_key.as_slice()).expect("secret key"),
+            );
+        assert_eq!(keypair.public_key.as_slice(), public_key);
+
+        let token = jws::sign(b"Example of Ed25519 signing", &keypair).expect("sign");
+        assert_eq!(
+            token,
+            "eyJhbGciOiJFZERTQSJ9.RXhhbXBsZSBvZiBFZDI1NTE5IHNpZ25pbmc.hgyY0il_MGCjP0JzlnLWG1PPOt7-09PGcvMg3AIbQR6dWbhijcNR4ki4iylGjg5BhVsPt9g7sVvpAr_MuM0KAg"
+        );
+        assert_eq!(
+            jws::verify(&token, &keypair.public_key).expect("verify"),
+            b"Example of Ed25519 signing"
+        );
+
+        // algorithm substitution and tampering
+        let none = format!("eyJhbGciOiJub25lIn0{}", &token[20..]);
+        assert!(jws::verify(&none, &keypair.public_key).is_err());
+        let tampered = token.replace("RXhhbXBsZSBvZ", "RXhhbXBsZSBvZy");
+        assert!(jws::verify(&tampered, &keypair.public_key).is_err());
+        assert!(jws::verify(&token[..token.len() - 2], &keypair.public_key).is_err());
+
+        let header = Header {
+            typ: Some("JWT".into()),
+            kid: Some("key-1".into()),
+            ..Default::default()
+        };
+        let token = jws::sign_with_header(b"{}", header, &keypair).expect("sign");
+        let header = Header::from_token(&token).expect("header");
+        assert_eq!(header.alg, "EdDSA");
+        assert_eq!(header.kid.as_deref(), Some("key-1"));
+        jws::verify(&token, &keypair.public_key).expect("verify");
+    }
+
+    #[test]
+    fn test_jws_external_signer() {
+        use crate::sign::{SignatureAlgorithm, Signer, Verifier};
+
+        // stands in for an external ES256 implementation
+        struct Es256(SigningKeyPair<crate::sign::PublicKey, SecretKey>);
+
+        impl Signer for Es256 {
+            fn algorithm(&self) -> SignatureAlgorithm {
+                SignatureAlgorithm::Es256
+            }
+
+            fn try_sign(&self, message: &[u8]) -> Result<Vec<u8>, Error> {
+                self.0.try_sign(message)
+            }
+        }
+
+        impl Verifier for Es256 {
+            fn algorithm(&self) -> SignatureAlgorithm {
+                SignatureAlgorithm::Es256
+            }
+
+            fn verify(&self, message: &[u8], signature: &[u8]) -> Result<(), Error> {
+                self.0.public_key.verify(message, signature)
+            }
+        }
+
+        let signer = Es256(SigningKeyPair::gen_with_defaults());
+        let token = jws::sign_with(b"{}", Header::default(), &signer).expect("sign");
+        assert_eq!(Header::from_token(&token).expect("header").alg, "ES256");
+        assert_eq!(jws::verify_with(&token, &signer).expect("verify"), b"{}");
+
+        // the algorithm must match the verifier
+        assert!(jws::verify(&token, &signer.0.public_key).is_err());
+        let token = jws::sign(b"{}", &signer.0).expect("sign");
+        assert!(jws::verify_with(&token, &signer).is_err());
+    }
+
+    #[test]
+    fn test_concat_kdf_rfc7518() {
+        // RFC 7518, appendix C
+        let z = [
+            158, 86, 217, 29, 129, 113, 53, 211, 114, 131, 66, 131, 191, 132, 38, 156, 251, 49,
+            110, 163, 218, 128, 106, 72, 246, 218, 167, 121, 140, 254, 144, 196,
+        ];
+        let mut key = [0u8; 16];
+        jwe::concat_kdf(&mut key, &z, b"A128GCM", b"Alice", b"Bob");
+        assert_eq!(URL_SAFE_NO_PAD.encode(key), "VqqN6vgjbSBcIijNcacQGg");
+    }
+
+    #[test]
+    fn test_jwe() {
+        let recipient = KeyPair::gen();
+        for enc in [ContentEncryption::C20P, ContentEncryption::XC20P] {
+            let token =
+                jwe::encrypt(b"attack at dawn", enc, &recipient.public_key).expect("encrypt");
+            assert_eq!(token.split('.').nth(1), Some(""));
+            let header = Header::from_token(&token).expect("header");
+            assert_eq!(header.alg, "ECDH-ES");
+            assert_eq!(header.enc.as_deref(), Some(enc.name()));
+
+            let decrypted: Vec<u8> = jwe::decrypt(&token, &recipient).expect("decrypt");
+            assert_eq!(decrypted, b"attack at dawn");
+
+            assert!(jwe::decrypt::<_, _, Vec<u8>>(&token, &KeyPair::gen()).is_err());
+            let mut parts: Vec<String> = token.split('.').map(String::from).collect();
+            let mut ciphertext = decode(&parts[3]).unwrap();
+            ciphertext[0] ^= 1;
+            parts[3] = URL_SAFE_NO_PAD.encode(ciphertext);
+            let tampered = parts.join(".");
+            assert!(jwe::decrypt::<_, _, Vec<u8>>(&tampered, &recipient).is_err());
+        }
+
+        // low order public keys are rejected
+        assert!(
+            jwe::encrypt(
+                b"",
+                ContentEncryption::C20P,
+                &crate::dryocbox::PublicKey::default()
+            )
+            .is_err()
+        );
+    }
+}
@@ -60,6 +60,10 @@ pub struct Kdf<
     Key: ByteArray<CRYPTO_KDF_KEYBYTES> + Zeroize,
     Context: ByteArray<CRYPTO_KDF_CONTEXTBYTES> + Zeroize,
 > {
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::redact::serialize_secret")
+    )]
     main_key: Key,
     context: Context,
 }
@@ -0,0 +1,422 @@
+//! # Key encapsulation mechanisms
+//!
+//! [`Kem`] is a generic interface to key encapsulation mechanisms (KEMs). The
+//! sender uses the recipient's public key to generate a random shared secret,
+//! along with a ciphertext (the encapsulated key) from which only the
+//! recipient can recover the same shared secret. Higher level constructions,
+//! such as hybrid encryption and envelope encryption, can be written
+//! generically over any KEM.
+//!
+//! [`DhKemX25519`] implements DHKEM(X25519, HKDF-SHA256), as described in
+//! [RFC 9180, section 4.1](https://www.rfc-editor.org/rfc/rfc9180#section-4.1).
+//! It also implements [`AuthKem`], which additionally authenticates the sender
+//! using their static keypair.
+//!
+//! ## Example
+//!
+//! ```
+//! use dryoc::kem::*;
+//!
+//! let recipient = DhKemX25519::gen_keypair();
+//!
+//! // The sender generates a shared secret, and sends the ciphertext
+//! let (shared_secret, ciphertext) =
+//!     DhKemX25519::encapsulate(&recipient.public_key).expect("encapsulate failed");
+//!
+//! // The recipient recovers the same shared secret
+//! let recovered = DhKemX25519::decapsulate(&ciphertext, &recipient).expect("decapsulate failed");
+//!
+//! assert_eq!(shared_secret, recovered);
+//! ```
+use subtle::ConstantTimeEq;
+use zeroize::Zeroize;
+
+use crate::classic::crypto_core::crypto_scalarmult;
+use crate::constants::{CRYPTO_BOX_PUBLICKEYBYTES, CRYPTO_SCALARMULT_BYTES};
+use crate::error::Error;
+use crate::hkdf::{labeled_expand, labeled_extract};
+use crate::keypair::{PublicKey, SecretKey, StackKeyPair};
+use crate::types::*;
+
+/// A key encapsulation mechanism.
+pub trait Kem {
+    /// KEM identifier, as assigned in the IANA HPKE KEM identifiers registry.
+    const ID: u16;
+    /// Length of the shared secret, in bytes.
+    const SHARED_SECRET_BYTES: usize;
+
+    /// Public key type.
+    type PublicKey: Bytes + for<'a> TryFrom<&'a [u8], Error = Error>;
+    /// Keypair type, which contains both the public and secret keys.
+    type KeyPair;
+    /// Ciphertext (encapsulated key) type.
+    type Ciphertext: Bytes + for<'a> TryFrom<&'a [u8], Error = Error>;
+    /// Shared secret type.
+    type SharedSecret: Bytes + Zeroize;
+
+    /// Randomly generates a new keypair.
+    fn gen_keypair() -> Self::KeyPair;
+
+    /// Deterministically derives a keypair from the input keying material
+    /// `ikm`, which should have at least as much entropy as the secret key.
+    fn derive_keypair(ikm: &[u8]) -> Result<Self::KeyPair, Error>;
+
+    /// Returns the public key of `keypair`.
+    fn public_key(keypair: &Self::KeyPair) -> &Self::PublicKey;
+
+    /// Generates a random shared secret for `recipient_public_key`, returning
+    /// the shared secret and the ciphertext to send to the recipient.
+    fn encapsulate(
+        recipient_public_key: &Self::PublicKey,
+    ) -> Result<(Self::SharedSecret, Self::Ciphertext), Error>;
+
+    /// Recovers the shared secret from `ciphertext`, using the recipient's
+    /// keypair.
+    fn decapsulate(
+        ciphertext: &Self::Ciphertext,
+        recipient_keypair: &Self::KeyPair,
+    ) -> Result<Self::SharedSecret, Error>;
+
+    /// Encapsulates with the given ephemeral keypair, rather than a randomly
+    /// generated one. This is only useful for testing against known vectors.
+    /// Reusing an ephemeral keypair breaks the security of the KEM.
+    ///
+    /// Returns an error unless implemented by the KEM.
+    #[cfg(any(test, feature = "deterministic-testing"))]
+    #[cfg_attr(
+        all(feature = "nightly", doc),
+        doc(cfg(feature = "deterministic-testing"))
+    )]
+    fn encapsulate_with_ephemeral_keypair(
+        _recipient_public_key: &Self::PublicKey,
+        _ephemeral_keypair: &Self::KeyPair,
+    ) -> Result<(Self::SharedSecret, Self::Ciphertext), Error> {
+        Err(dryoc_error!(
+            "encapsulation with an ephemeral keypair is not supported"
+        ))
+    }
+}
+
+/// A key encapsulation mechanism which can additionally authenticate the
+/// sender, using their static keypair.
+pub trait AuthKem: Kem {
+    /// Generates a random shared secret for `recipient_public_key`, bound to
+    /// the sender's keypair, returning the shared secret and the ciphertext
+    /// to send to the recipient.
+    fn auth_encapsulate(
+        recipient_public_key: &Self::PublicKey,
+        sender_keypair: &Self::KeyPair,
+    ) -> Result<(Self::SharedSecret, Self::Ciphertext), Error>;
+
+    /// Recovers the shared secret from `ciphertext`, using the recipient's
+    /// keypair, and verifying that it was encapsulated by the holder of
+    /// `sender_public_key`. Verification is implicit: if the sender doesn't
+    /// match, a different shared secret is returned.
+    fn auth_decapsulate(
+        ciphertext: &Self::Ciphertext,
+        recipient_keypair: &Self::KeyPair,
+        sender_public_key: &Self::PublicKey,
+    ) -> Result<Self::SharedSecret, Error>;
+
+    /// Authenticated encapsulation with the given ephemeral keypair. This is
+    /// only useful for testing against known vectors.
+    ///
+    /// Returns an error unless implemented by the KEM.
+    #[cfg(any(test, feature = "deterministic-testing"))]
+    #[cfg_attr(
+        all(feature = "nightly", doc),
+        doc(cfg(feature = "deterministic-testing"))
+    )]
+    fn auth_encapsulate_with_ephemeral_keypair(
+        _recipient_public_key: &Self::PublicKey,
+        _sender_keypair: &Self::KeyPair,
+        _ephemeral_keypair: &Self::KeyPair,
+    ) -> Result<(Self::SharedSecret, Self::Ciphertext), Error> {
+        Err(dryoc_error!(
+            "authenticated encapsulation with an ephemeral keypair is not supported"
+        ))
+    }
+}
+
+/// Length of the DHKEM(X25519, HKDF-SHA256) shared secret.
+pub const DHKEM_X25519_SHARED_SECRET_BYTES: usize = 32;
+
+/// DHKEM(X25519, HKDF-SHA256) ciphertext, which is the sender's ephemeral
+/// public key.
+pub type Ciphertext = StackByteArray<CRYPTO_BOX_PUBLICKEYBYTES>;
+/// DHKEM(X25519, HKDF-SHA256) shared secret.
+pub type SharedSecret = StackByteArray<DHKEM_X25519_SHARED_SECRET_BYTES>;
+
+/// DHKEM(X25519, HKDF-SHA256), from RFC 9180. Uses the same keypairs as
+/// [`DryocBox`](crate::dryocbox::DryocBox).
+pub struct DhKemX25519;
+
+impl DhKemX25519 {
+    const SUITE_ID: [u8; 5] = [b'K', b'E', b'M', 0x00, 0x20];
+
+    fn dh(output: &mut [u8], secret_key: &SecretKey, public_key: &PublicKey) -> Result<(), Error> {
+        let mut shared = [0u8; CRYPTO_SCALARMULT_BYTES];
+        crypto_scalarmult(&mut shared, secret_key.as_array(), public_key.as_array());
+        let is_zero: bool = shared.ct_eq(&[0u8; CRYPTO_SCALARMULT_BYTES]).into();
+        output.copy_from_slice(&shared);
+        shared.zeroize();
+        if is_zero {
+            Err(dryoc_error!("invalid public key, shared secret is zero"))
+        } else {
+            Ok(())
+        }
+    }
+
+    fn extract_and_expand(dh: &[u8], kem_context: &[&[u8]]) -> Result<SharedSecret, Error> {
+        let mut prk = labeled_extract(&Self::SUITE_ID, b"", b"eae_prk", dh);
+        let mut shared_secret = SharedSecret::new_byte_array();
+        let result = labeled_expand(
+            shared_secret.as_mut_slice(),
+            &Self::SUITE_ID,
+            &prk,
+            b"shared_secret",
+            &kem_context.concat(),
+        );
+        prk.zeroize();
+        result.map(|_| shared_secret)
+    }
+
+    fn encap(
+        recipient_public_key: &PublicKey,
+        sender_keypair: Option<&StackKeyPair>,
+        ephemeral_keypair: &StackKeyPair,
+    ) -> Result<(SharedSecret, Ciphertext), Error> {
+        let mut dh = [0u8; 2 * CRYPTO_SCALARMULT_BYTES];
+        let result = Self::dh(
+            &mut dh[..CRYPTO_SCALARMULT_BYTES],
+            &ephemeral_keypair.secret_key,
+            recipient_public_key,
+        )
+        .and_then(|_| match sender_keypair {
+            Some(sender_keypair) => {
+                Self::dh(
+                    &mut dh[CRYPTO_SCALARMULT_BYTES..],
+                    &sender_keypair.secret_key,
+                    recipient_public_key,
+                )?;
+                Self::extract_and_expand(
+                    &dh,
+                    &[
+                        ephemeral_keypair.public_key.as_slice(),
+                        recipient_public_key.as_slice(),
+                        sender_keypair.public_key.as_slice(),
+                    ],
+                )
+            }
+            None => Self::extract_and_expand(
+                &dh[..CRYPTO_SCALARMULT_BYTES],
+                &[
+                    ephemeral_keypair.public_key.as_slice(),
+                    recipient_public_key.as_slice(),
+                ],
+            ),
+        });
+        dh.zeroize();
+
+        Ok((result?, ephemeral_keypair.public_key.clone()))
+    }
+
+    fn decap(
+        ciphertext: &Ciphertext,
+        recipient_keypair: &StackKeyPair,
+        sender_public_key: Option<&PublicKey>,
+    ) -> Result<SharedSecret, Error> {
+        let mut dh = [0u8; 2 * CRYPTO_SCALARMULT_BYTES];
+        let result = Self::dh(
+            &mut dh[..CRYPTO_SCALARMULT_BYTES],
+            &recipient_keypair.secret_key,
+            ciphertext,
+        )
+        .and_then(|_| match sender_public_key {
+            Some(sender_public_key) => {
+                Self::dh(
+                    &mut dh[CRYPTO_SCALARMULT_BYTES..],
+                    &recipient_keypair.secret_key,
+                    sender_public_key,
+                )?;
+                Self::extract_and_expand(
+                    &dh,
+                    &[
+                        ciphertext.as_slice(),
+                        recipient_keypair.public_key.as_slice(),
+                        sender_public_key.as_slice(),
+                    ],
+                )
+            }
+            None => Self::extract_and_expand(
+                &dh[..CRYPTO_SCALARMULT_BYTES],
+                &[
+                    ciphertext.as_slice(),
+                    recipient_keypair.public_key.as_slice(),
+                ],
+            ),
+        });
+        dh.zeroize();
+
+        result
+    }
+}
+
+impl Kem for DhKemX25519 {
+    type Ciphertext = Ciphertext;
+    type KeyPair = StackKeyPair;
+    type PublicKey = PublicKey;
+    type SharedSecret = SharedSecret;
+
+    const ID: u16 = 0x0020;
+    const SHARED_SECRET_BYTES: usize = DHKEM_X25519_SHARED_SECRET_BYTES;
+
+    fn gen_keypair() -> StackKeyPair {
+        StackKeyPair::gen()
+    }
+
+    fn derive_keypair(ikm: &[u8]) -> Result<StackKeyPair, Error> {
+        let mut prk = labeled_extract(&Self::SUITE_ID, b"", b"dkp_prk", ikm);
+        let mut secret_key = SecretKey::new_byte_array();
+        let result = labeled_expand(secret_key.as_mut_slice(), &Self::SUITE_ID, &prk, b"sk", b"");
+        prk.zeroize();
+        result.map(|_| StackKeyPair::from_secret_key(secret_key))
+    }
+
+    fn public_key(keypair: &StackKeyPair) -> &PublicKey {
+        &keypair.public_key
+    }
+
+    fn encapsulate(recipient_public_key: &PublicKey) -> Result<(SharedSecret, Ciphertext), Error> {
+        Self::encap(recipient_public_key, None, &StackKeyPair::gen())
+    }
+
+    fn decapsulate(
+        ciphertext: &Ciphertext,
+        recipient_keypair: &StackKeyPair,
+    ) -> Result<SharedSecret, Error> {
+        Self::decap(ciphertext, recipient_keypair, None)
+    }
+
+    #[cfg(any(test, feature = "deterministic-testing"))]
+    fn encapsulate_with_ephemeral_keypair(
+        recipient_public_key: &PublicKey,
+        ephemeral_keypair: &StackKeyPair,
+    ) -> Result<(SharedSecret, Ciphertext), Error> {
+        Self::encap(recipient_public_key, None, ephemeral_keypair)
+    }
+}
+
+impl AuthKem for DhKemX25519 {
+    fn auth_encapsulate(
+        recipient_public_key: &PublicKey,
+        sender_keypair: &StackKeyPair,
+    ) -> Result<(SharedSecret, Ciphertext), Error> {
+        Self::encap(
+            recipient_public_key,
+            Some(sender_keypair),
+            &StackKeyPair::gen(),
+        )
+    }
+
+    fn auth_decapsulate(
+        ciphertext: &Ciphertext,
+        recipient_keypair: &StackKeyPair,
+        sender_public_key: &PublicKey,
+    ) -> Result<SharedSecret, Error> {
+        Self::decap(ciphertext, recipient_keypair, Some(sender_public_key))
+    }
+
+    #[cfg(any(test, feature = "deterministic-testing"))]
+    fn auth_encapsulate_with_ephemeral_keypair(
+        recipient_public_key: &PublicKey,
+        sender_keypair: &StackKeyPair,
+        ephemeral_keypair: &StackKeyPair,
+    ) -> Result<(SharedSecret, Ciphertext), Error> {
+        Self::encap(
+            recipient_public_key,
+            Some(sender_keypair),
+            ephemeral_keypair,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unhex(s: &str) -> Vec<u8> {
+        hex::decode(s).expect("invalid hex")
+    }
+
+    #[test]
+    fn test_rfc9180_vector() {
+        // RFC 9180, appendix A.1.1
+        let ephemeral = DhKemX25519::derive_keypair(&unhex(
+            "7268600d403fce431561aef583ee1613527cff655c1343f29812e66706df3234",
+        ))
+        .expect("derive failed");
+        assert_eq!(
+            ephemeral.secret_key.as_slice(),
+            unhex("52c4a758a802cd8b936eceea314432798d5baf2d7e9235dc084ab1b9cfa2f736")
+        );
+        let recipient = StackKeyPair::from_secret_key(
+            SecretKey::try_from(
+                unhex("4612c550263fc8ad58375df3f557aac531d26850903e55a9f23f21d8534e8ac8")
+                    .as_slice(),
+            )
+            .expect("invalid key"),
+        );
+        assert_eq!(
+            recipient.public_key.as_slice(),
+            unhex("3948cfe0ad1ddb695d780e59077195da6c56506b027329794ab02bca80815c4d")
+        );
+
+        let (shared_secret, ciphertext) =
+            DhKemX25519::encapsulate_with_ephemeral_keypair(&recipient.public_key, &ephemeral)
+                .expect("encapsulate failed");
+        assert_eq!(
+            ciphertext.as_slice(),
+            unhex("37fda3567bdbd628e88668c3c8d7e97d1d1253b6d4ea6d44c150f741f1bf4431")
+        );
+        assert_eq!(
+            shared_secret.as_slice(),
+            unhex("fe0e18c9f024ce43799ae393c7e8fe8fce9d218875e8227b0187c04e7d2ea1fc")
+        );
+        assert_eq!(
+            DhKemX25519::decapsulate(&ciphertext, &recipient).expect("decapsulate failed"),
+            shared_secret
+        );
+    }
+
+    #[test]
+    fn test_auth_kem() {
+        let recipient = DhKemX25519::gen_keypair();
+        let sender = DhKemX25519::gen_keypair();
+
+        let (shared_secret, ciphertext) =
+            DhKemX25519::auth_encapsulate(&recipient.public_key, &sender)
+                .expect("encapsulate failed");
+        assert_eq!(
+            DhKemX25519::auth_decapsulate(&ciphertext, &recipient, &sender.public_key)
+                .expect("decapsulate failed"),
+            shared_secret
+        );
+
+        // a different sender, or no sender, results in a different secret
+        let other = DhKemX25519::gen_keypair();
+        assert_ne!(
+            DhKemX25519::auth_decapsulate(&ciphertext, &recipient, &other.public_key)
+                .expect("decapsulate failed"),
+            shared_secret
+        );
+        assert_ne!(
+            DhKemX25519::decapsulate(&ciphertext, &recipient).expect("decapsulate failed"),
+            shared_secret
+        );
+
+        // low order points are rejected
+        DhKemX25519::decapsulate(&Ciphertext::default(), &recipient)
+            .expect_err("should reject zero point");
+    }
+}
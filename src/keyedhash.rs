@@ -0,0 +1,331 @@
+//! # Keyed hashing
+//!
+//! [`KeyedHash`] computes message authentication codes using keyed Blake2b,
+//! with keys between 16 and 64 bytes long. Blake2b isn't vulnerable to length
+//! extension attacks, so unlike Merkle–Damgård hashes such as SHA-2 it doesn't
+//! need to be wrapped in HMAC to be used as a MAC: hashing with a key is
+//! sufficient.
+//!
+//! You should use [`KeyedHash`] when you want to:
+//!
+//! * authenticate messages with a pre-shared key, and you'd otherwise reach for
+//!   HMAC
+//! * use keys that aren't exactly 32 bytes long
+//! * verify authentication codes in constant time
+//!
+//! If the `serde` feature is enabled, the [`serde::Deserialize`] and
+//! [`serde::Serialize`] traits will be implemented for [`KeyedHash`].
+//!
+//! # Rustaceous API example
+//!
+//! ```
+//! use dryoc::keyedhash::*;
+//!
+//! // Generate a random 32-byte key
+//! let keyed_hash = StackKeyedHash::gen();
+//!
+//! let mac: Mac = keyed_hash.compute(b"Data to authenticate");
+//! keyed_hash
+//!     .verify(b"Data to authenticate", &mac)
+//!     .expect("verify failed");
+//! keyed_hash
+//!     .verify(b"Other data", &mac)
+//!     .expect_err("verify should have failed");
+//!
+//! // Keys may be between 16 and 64 bytes long
+//! let keyed_hash = KeyedHash::from_key([7u8; 64]);
+//!
+//! // Multi-part messages can be authenticated incrementally
+//! let mut state = keyed_hash.begin();
+//! state.update(b"Multi-part ");
+//! state.update(b"data");
+//! let mac = state.finalize_to_vec();
+//! assert_eq!(mac, keyed_hash.compute_to_vec(b"Multi-part data"));
+//! ```
+//!
+//! Keys with an unsupported length fail to compile:
+//!
+//! ```compile_fail
+//! use dryoc::keyedhash::*;
+//!
+//! let keyed_hash = KeyedHash::from_key([7u8; 8]);
+//! let mac: Mac = keyed_hash.compute(b"Data to authenticate");
+//! ```
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use subtle::ConstantTimeEq;
+use zeroize::Zeroize;
+
+use crate::constants::{CRYPTO_GENERICHASH_BYTES, CRYPTO_GENERICHASH_KEYBYTES};
+use crate::error::Error;
+use crate::generichash::GenericHash;
+use crate::types::*;
+
+/// Stack-allocated key for keyed hashing, with the recommended length.
+pub type Key = StackByteArray<CRYPTO_GENERICHASH_KEYBYTES>;
+/// Stack-allocated message authentication code for keyed hashing.
+pub type Mac = StackByteArray<CRYPTO_GENERICHASH_BYTES>;
+
+#[cfg_attr(
+    feature = "serde",
+    derive(Zeroize, Clone, Debug, Serialize, Deserialize),
+    serde(try_from = "UncheckedKeyedHash<KEY_LENGTH, Key>")
+)]
+#[cfg_attr(not(feature = "serde"), derive(Zeroize, Clone, Debug))]
+/// Keyed Blake2b message authentication, with a `KEY_LENGTH`-byte key.
+///
+/// Refer to [crate::keyedhash] for sample usage.
+pub struct KeyedHash<const KEY_LENGTH: usize, Key: ByteArray<KEY_LENGTH> + Zeroize> {
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::redact::serialize_secret")
+    )]
+    key: Key,
+}
+
+/// A keyed hash as deserialized, before its key length is validated. The
+/// length is checked at runtime, because deserializing doesn't go through
+/// [`KeyedHash::from_key`].
+#[cfg(feature = "serde")]
+#[derive(Deserialize)]
+struct UncheckedKeyedHash<const KEY_LENGTH: usize, Key> {
+    key: Key,
+}
+
+#[cfg(feature = "serde")]
+impl<const KEY_LENGTH: usize, Key: ByteArray<KEY_LENGTH> + Zeroize>
+    TryFrom<UncheckedKeyedHash<KEY_LENGTH, Key>> for KeyedHash<KEY_LENGTH, Key>
+{
+    type Error = Error;
+
+    fn try_from(keyed_hash: UncheckedKeyedHash<KEY_LENGTH, Key>) -> Result<Self, Error> {
+        use crate::constants::{CRYPTO_GENERICHASH_KEYBYTES_MAX, CRYPTO_GENERICHASH_KEYBYTES_MIN};
+
+        validate!(
+            CRYPTO_GENERICHASH_KEYBYTES_MIN,
+            CRYPTO_GENERICHASH_KEYBYTES_MAX,
+            KEY_LENGTH,
+            "key length"
+        );
+        Ok(Self {
+            key: keyed_hash.key,
+        })
+    }
+}
+
+/// Stack-allocated type alias for [`KeyedHash`], with the recommended key
+/// length. Provided for convenience.
+pub type StackKeyedHash = KeyedHash<CRYPTO_GENERICHASH_KEYBYTES, Key>;
+
+#[cfg(any(feature = "nightly", all(doc, not(doctest))))]
+#[cfg_attr(all(feature = "nightly", doc), doc(cfg(feature = "nightly")))]
+pub mod protected {
+    //! #  Protected memory type aliases for [`KeyedHash`]
+    //!
+    //! This mod provides re-exports of type aliases for protected memory usage
+    //! with [`KeyedHash`]. These type aliases are provided for
+    //! convenience.
+    //!
+    //! ## Example
+    //!
+    //! ```
+    //! use dryoc::keyedhash::protected::*;
+    //! use dryoc::keyedhash::KeyedHash;
+    //!
+    //! // Create a randomly generated key, lock it, protect it as read-only
+    //! let keyed_hash: LockedKeyedHash =
+    //!     KeyedHash::from_key(Key::gen_readonly_locked().expect("gen failed"));
+    //! let input =
+    //!     HeapBytes::from_slice_into_readonly_locked(b"super secret input").expect("input failed");
+    //!
+    //! let mac: Locked<Mac> = keyed_hash.compute(&input);
+    //! keyed_hash.verify(&input, &mac).expect("verify failed");
+    //! ```
+    use super::*;
+    pub use crate::protected::*;
+
+    /// Heap-allocated, page-aligned key for keyed hashing, for use with
+    /// protected memory.
+    pub type Key = HeapByteArray<CRYPTO_GENERICHASH_KEYBYTES>;
+    /// Heap-allocated, page-aligned message authentication code for keyed
+    /// hashing, for use with protected memory.
+    pub type Mac = HeapByteArray<CRYPTO_GENERICHASH_BYTES>;
+
+    /// Locked, read-only [`KeyedHash`], provided as a type alias for
+    /// convenience.
+    pub type LockedKeyedHash = KeyedHash<CRYPTO_GENERICHASH_KEYBYTES, LockedRO<Key>>;
+}
+
+impl<const KEY_LENGTH: usize, Key: NewByteArray<KEY_LENGTH> + Zeroize> KeyedHash<KEY_LENGTH, Key> {
+    /// Returns a new keyed hash with a randomly generated key.
+    pub fn gen() -> Self {
+        Self::from_key(Key::gen())
+    }
+}
+
+impl<const KEY_LENGTH: usize, Key: ByteArray<KEY_LENGTH> + Zeroize> KeyedHash<KEY_LENGTH, Key> {
+    /// Returns a new keyed hash for `key`, consuming it. The key length is
+    /// checked at compile time.
+    pub fn from_key(key: Key) -> Self {
+        let () = GenericHash::<KEY_LENGTH, CRYPTO_GENERICHASH_BYTES>::VALID_KEY_LENGTH;
+        Self { key }
+    }
+
+    /// Returns a reference to the key.
+    pub fn key(&self) -> &Key {
+        &self.key
+    }
+
+    /// Moves the key out of this instance, returning it.
+    pub fn into_key(self) -> Key {
+        self.key
+    }
+
+    /// Computes and returns the message authentication code for `input`.
+    pub fn compute<Input: Bytes + ?Sized, Output: NewByteArray<CRYPTO_GENERICHASH_BYTES>>(
+        &self,
+        input: &Input,
+    ) -> Output {
+        let mut state = self.begin();
+        state.update(input);
+        state.finalize()
+    }
+
+    /// Computes and returns the message authentication code for `input` as a
+    /// [`Vec`]. Provided for convenience.
+    pub fn compute_to_vec<Input: Bytes + ?Sized>(&self, input: &Input) -> Vec<u8> {
+        self.compute(input)
+    }
+
+    /// Verifies that `mac` is the message authentication code for `input`,
+    /// using a constant-time comparison.
+    pub fn verify<Input: Bytes + ?Sized, OtherMac: ByteArray<CRYPTO_GENERICHASH_BYTES>>(
+        &self,
+        input: &Input,
+        mac: &OtherMac,
+    ) -> Result<(), Error> {
+        let mut state = self.begin();
+        state.update(input);
+        state.verify(mac)
+    }
+
+    /// Returns a new incremental keyed hash state, for authenticating
+    /// multi-part messages.
+    pub fn begin(&self) -> KeyedHashState<KEY_LENGTH> {
+        KeyedHashState {
+            hasher: GenericHash::new(Some(&self.key))
+                .expect("key length is validated at compile time"),
+        }
+    }
+}
+
+/// Incremental keyed hash state, returned by [`KeyedHash::begin`].
+pub struct KeyedHashState<const KEY_LENGTH: usize> {
+    hasher: GenericHash<KEY_LENGTH, CRYPTO_GENERICHASH_BYTES>,
+}
+
+impl<const KEY_LENGTH: usize> KeyedHashState<KEY_LENGTH> {
+    /// Updates the keyed hash state with `input`.
+    pub fn update<Input: Bytes + ?Sized>(&mut self, input: &Input) {
+        self.hasher.update(input)
+    }
+
+    /// Finalizes the keyed hash state, returning the message authentication
+    /// code.
+    pub fn finalize<Output: NewByteArray<CRYPTO_GENERICHASH_BYTES>>(self) -> Output {
+        self.hasher
+            .finalize()
+            .expect("output length is validated at compile time")
+    }
+
+    /// Finalizes the keyed hash state, returning the message authentication
+    /// code as a [`Vec`]. Provided for convenience.
+    pub fn finalize_to_vec(self) -> Vec<u8> {
+        self.finalize()
+    }
+
+    /// Finalizes the keyed hash state, and verifies that the computed code
+    /// matches `other_mac` using a constant-time comparison.
+    pub fn verify<OtherMac: ByteArray<CRYPTO_GENERICHASH_BYTES>>(
+        self,
+        other_mac: &OtherMac,
+    ) -> Result<(), Error> {
+        let computed_mac: Mac = self.finalize();
+
+        if other_mac
+            .as_array()
+            .ct_eq(computed_mac.as_array())
+            .unwrap_u8()
+            == 1
+        {
+            Ok(())
+        } else {
+            Err(dryoc_error!("authentication codes do not match"))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_keyed_hash() {
+        let keyed_hash = StackKeyedHash::gen();
+
+        let mac: Mac = keyed_hash.compute(b"Data to authenticate");
+        keyed_hash
+            .verify(b"Data to authenticate", &mac)
+            .expect("verify failed");
+        keyed_hash
+            .verify(b"Data to authenticat", &mac)
+            .expect_err("verify should have failed");
+        StackKeyedHash::gen()
+            .verify(b"Data to authenticate", &mac)
+            .expect_err("verify should have failed");
+
+        let expected: Mac =
+            GenericHash::hash_with_defaults(b"Data to authenticate", Some(keyed_hash.key()))
+                .expect("hash failed");
+        assert_eq!(mac, expected);
+
+        let mut state = keyed_hash.begin();
+        state.update(b"Data to ");
+        state.update(b"authenticate");
+        state.verify(&mac).expect("verify failed");
+    }
+
+    #[test]
+    fn test_key_lengths() {
+        let short = KeyedHash::from_key([1u8; 16]);
+        let long = KeyedHash::from_key([1u8; 64]);
+
+        let short_mac: Mac = short.compute(b"input");
+        let long_mac: Mac = long.compute(b"input");
+        assert_ne!(short_mac, long_mac);
+
+        let expected: Vec<u8> =
+            GenericHash::<64, 32>::hash(b"input", Some(&[1u8; 64])).expect("hash failed");
+        assert_eq!(long_mac.to_vec(), expected);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde() {
+        let keyed_hash = StackKeyedHash::gen();
+        let json = serde_json::to_string(&keyed_hash).expect("serialize failed");
+        let decoded: StackKeyedHash = serde_json::from_str(&json).expect("deserialize failed");
+
+        let mac: Mac = keyed_hash.compute(b"input");
+        decoded.verify(b"input", &mac).expect("verify failed");
+
+        // key lengths are only checked at compile time by from_key
+        let err = serde_json::from_str::<KeyedHash<8, [u8; 8]>>(r#"{"key":[1,2,3,4,5,6,7,8]}"#)
+            .expect_err("short key should fail to deserialize");
+        assert!(
+            err.to_string()
+                .starts_with("key length value of 8 less than minimum 16")
+        );
+    }
+}
@@ -11,11 +11,15 @@ use subtle::ConstantTimeEq;
 use zeroize::{Zeroize, ZeroizeOnDrop};
 
 use crate::classic::crypto_box::crypto_box_seed_keypair_inplace;
+use crate::classic::crypto_kdf::crypto_kdf_derive_from_key;
 use crate::constants::{
-    CRYPTO_BOX_PUBLICKEYBYTES, CRYPTO_BOX_SECRETKEYBYTES, CRYPTO_KX_SESSIONKEYBYTES,
+    CRYPTO_BOX_PUBLICKEYBYTES, CRYPTO_BOX_SECRETKEYBYTES, CRYPTO_BOX_SEEDBYTES,
+    CRYPTO_KDF_CONTEXTBYTES, CRYPTO_KDF_KEYBYTES, CRYPTO_KX_SESSIONKEYBYTES,
 };
 use crate::error::Error;
+use crate::formats::{decode_checksummed_of, encode_checksummed, Format, Kind};
 use crate::kx;
+use crate::pwhash::{derive_seed, Config};
 use crate::types::*;
 
 /// Stack-allocated public key type alias.
@@ -39,6 +43,10 @@ pub struct KeyPair<
     /// Public key
     pub public_key: PublicKey,
     /// Secret key
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::redact::serialize_secret")
+    )]
     pub secret_key: SecretKey,
 }
 
@@ -100,6 +108,81 @@ impl<
             secret_key,
         }
     }
+
+    /// Deterministically derives a keypair from `main_key`, `context`, and
+    /// `subkey_id`, using the same derivation as [`Kdf`](crate::kdf::Kdf).
+    /// The derived subkey is used as the seed for [`KeyPair::from_seed`].
+    ///
+    /// This allows many keypairs to be recreated from a single stored main
+    /// key, so only the main key needs to be backed up. Different contexts
+    /// or subkey IDs give unrelated keypairs.
+    pub fn derive<
+        MainKey: ByteArray<CRYPTO_KDF_KEYBYTES>,
+        Context: ByteArray<CRYPTO_KDF_CONTEXTBYTES>,
+    >(
+        main_key: &MainKey,
+        context: &Context,
+        subkey_id: u64,
+    ) -> Result<Self, Error> {
+        let mut seed = [0u8; CRYPTO_KDF_KEYBYTES];
+        crypto_kdf_derive_from_key(
+            &mut seed,
+            subkey_id,
+            context.as_array(),
+            main_key.as_array(),
+        )?;
+        let keypair = Self::from_seed(&seed);
+        seed.zeroize();
+        Ok(keypair)
+    }
+
+    /// Deterministically derives a keypair from `passphrase` and `salt`,
+    /// using Argon2id with `config` to derive the seed for
+    /// [`KeyPair::from_seed`]. The same passphrase, salt, and config always
+    /// give the same keypair, so the keypair can be recovered from the
+    /// passphrase alone, as long as the salt and config are stored alongside
+    /// the public key.
+    ///
+    /// The salt must be at least
+    /// [`CRYPTO_PWHASH_SALTBYTES`](crate::constants::CRYPTO_PWHASH_SALTBYTES)
+    /// bytes, and should be unique to each identity (such as a random value,
+    /// or a hash of the user's name and the application).
+    ///
+    /// The seed is zeroized after use. To keep it in locked memory, use
+    /// `from_passphrase_locked` from the [protected] mod.
+    pub fn from_passphrase<Passphrase: Bytes + ?Sized>(
+        passphrase: &Passphrase,
+        salt: &[u8],
+        config: &Config,
+    ) -> Result<Self, Error> {
+        let mut seed = [0u8; CRYPTO_BOX_SEEDBYTES];
+        let result = derive_seed(&mut seed, passphrase.as_slice(), salt, config)
+            .map(|()| Self::from_seed(&seed));
+        seed.zeroize();
+        result
+    }
+
+    /// Deserializes a keypair from a checksummed container, as produced by
+    /// [`KeyPair::to_checksummed_bytes`]. Refer to [crate::formats] for
+    /// details.
+    pub fn from_checksummed_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        let payload = decode_checksummed_of(bytes, Kind::KeyPair)?;
+        validate!(
+            CRYPTO_BOX_PUBLICKEYBYTES + CRYPTO_BOX_SECRETKEYBYTES,
+            CRYPTO_BOX_PUBLICKEYBYTES + CRYPTO_BOX_SECRETKEYBYTES,
+            payload.len(),
+            "payload"
+        );
+
+        let mut keypair = Self::new();
+        keypair
+            .public_key
+            .copy_from_slice(&payload[..CRYPTO_BOX_PUBLICKEYBYTES]);
+        keypair
+            .secret_key
+            .copy_from_slice(&payload[CRYPTO_BOX_PUBLICKEYBYTES..]);
+        Ok(keypair)
+    }
 }
 
 impl KeyPair<StackByteArray<CRYPTO_BOX_PUBLICKEYBYTES>, StackByteArray<CRYPTO_BOX_SECRETKEYBYTES>> {
@@ -150,6 +233,19 @@ impl<
     ) -> Result<kx::Session<SessionKey>, Error> {
         kx::Session::new_server(self, client_public_key)
     }
+
+    /// Serializes this keypair into a checksummed container, which detects
+    /// truncation and corruption when it's loaded with
+    /// [`KeyPair::from_checksummed_bytes`]. Refer to [crate::formats] for
+    /// details. Can be used with protected memory.
+    pub fn to_checksummed_bytes<Output: NewBytes + ResizableBytes>(&self) -> Output {
+        let mut payload = [0u8; CRYPTO_BOX_PUBLICKEYBYTES + CRYPTO_BOX_SECRETKEYBYTES];
+        payload[..CRYPTO_BOX_PUBLICKEYBYTES].copy_from_slice(self.public_key.as_slice());
+        payload[CRYPTO_BOX_PUBLICKEYBYTES..].copy_from_slice(self.secret_key.as_slice());
+        let output = encode_checksummed(Format::CURRENT, Kind::KeyPair, &payload);
+        payload.zeroize();
+        output
+    }
 }
 
 impl<
@@ -177,7 +273,7 @@ pub mod protected {
         >
     {
         /// Returns a new locked keypair.
-        pub fn new_locked_keypair() -> Result<Self, std::io::Error> {
+        pub fn new_locked_keypair() -> Result<Self, crate::error::Error> {
             Ok(Self {
                 public_key: HeapByteArray::<CRYPTO_BOX_PUBLICKEYBYTES>::new_locked()?,
                 secret_key: HeapByteArray::<CRYPTO_BOX_SECRETKEYBYTES>::new_locked()?,
@@ -185,7 +281,7 @@ pub mod protected {
         }
 
         /// Returns a new randomly generated locked keypair.
-        pub fn gen_locked_keypair() -> Result<Self, std::io::Error> {
+        pub fn gen_locked_keypair() -> Result<Self, crate::error::Error> {
             let mut res = Self::new_locked_keypair()?;
 
             crypto_box_keypair_inplace(
@@ -195,6 +291,28 @@ pub mod protected {
 
             Ok(res)
         }
+
+        /// Deterministically derives a locked keypair from `passphrase` and
+        /// `salt`. The seed is derived into locked memory, and is zeroized
+        /// when it's dropped. Refer to [`KeyPair::from_passphrase`] for
+        /// details.
+        pub fn from_passphrase_locked<Passphrase: Bytes + ?Sized>(
+            passphrase: &Passphrase,
+            salt: &[u8],
+            config: &Config,
+        ) -> Result<Self, crate::error::Error> {
+            let mut seed = HeapByteArray::<CRYPTO_BOX_SEEDBYTES>::new_locked()?;
+            derive_seed(seed.as_mut_slice(), passphrase.as_slice(), salt, config)?;
+
+            let mut res = Self::new_locked_keypair()?;
+            crypto_box_seed_keypair_inplace(
+                res.public_key.as_mut_array(),
+                res.secret_key.as_mut_array(),
+                seed.as_slice(),
+            );
+
+            Ok(res)
+        }
     }
 
     impl
@@ -204,7 +322,7 @@ pub mod protected {
         >
     {
         /// Returns a new randomly generated locked, read-only keypair.
-        pub fn gen_readonly_locked_keypair() -> Result<Self, std::io::Error> {
+        pub fn gen_readonly_locked_keypair() -> Result<Self, crate::error::Error> {
             let mut public_key = HeapByteArray::<CRYPTO_BOX_PUBLICKEYBYTES>::new_locked()?;
             let mut secret_key = HeapByteArray::<CRYPTO_BOX_SECRETKEYBYTES>::new_locked()?;
 
@@ -219,6 +337,50 @@ pub mod protected {
             })
         }
     }
+
+    impl StackKeyPair {
+        /// Consumes this keypair, and moves it into a single locked region of
+        /// memory. Both keys share one allocation, which uses fewer locked
+        /// pages than allocating each key separately, as
+        /// [`KeyPair::gen_locked_keypair`] does.
+        ///
+        /// Moving the keypair may leave copies of it on the stack; use
+        /// [`StackKeyPair::gen_locked_box`] to generate a keypair in place.
+        pub fn mlock(self) -> Result<LockedBox<Self>, crate::error::Error> {
+            ProtectedBox::new_locked(self)
+        }
+
+        /// Consumes this keypair, and moves it into a single locked,
+        /// read-only region of memory. See [`StackKeyPair::mlock`].
+        pub fn mlock_readonly(self) -> Result<LockedROBox<Self>, crate::error::Error> {
+            ProtectedBox::new_readonly_locked(self)
+        }
+
+        /// Consumes this keypair, and moves it into a single no-access region
+        /// of memory, which can't be read until it's made readable again with
+        /// [`ProtectedBox::mprotect_readonly`]. See [`StackKeyPair::mlock`].
+        pub fn mprotect_noaccess(self) -> Result<NoAccessBox<Self>, crate::error::Error> {
+            ProtectedBox::new(self).mprotect_noaccess()
+        }
+
+        /// Returns a new randomly generated keypair, generated in place in a
+        /// single locked region of memory.
+        pub fn gen_locked_box() -> Result<LockedBox<Self>, crate::error::Error> {
+            let mut res = ProtectedBox::new_locked(Self::new())?;
+            let keypair = &mut *res;
+            crypto_box_keypair_inplace(
+                keypair.public_key.as_mut_array(),
+                keypair.secret_key.as_mut_array(),
+            );
+            Ok(res)
+        }
+
+        /// Returns a new randomly generated keypair in a single locked,
+        /// read-only region of memory.
+        pub fn gen_readonly_locked_box() -> Result<LockedROBox<Self>, crate::error::Error> {
+            Self::gen_locked_box()?.mprotect_readonly()
+        }
+    }
 }
 
 impl<
@@ -296,6 +458,29 @@ mod tests {
         assert_eq!(ge.as_ref(), public_key);
     }
 
+    #[cfg(feature = "nightly")]
+    #[test]
+    fn test_locked_box() {
+        let keypair = StackKeyPair::gen();
+        let expected = keypair.clone();
+
+        let locked = keypair.mlock().expect("lock failed");
+        assert_eq!(*locked, expected);
+        let noaccess = locked
+            .munlock()
+            .and_then(|k| k.mprotect_noaccess())
+            .expect("mprotect failed");
+        let readonly = noaccess.mprotect_readonly().expect("mprotect failed");
+        assert_eq!(*readonly, expected);
+
+        let readonly = StackKeyPair::gen_readonly_locked_box().expect("gen failed");
+        assert_eq!(
+            *readonly,
+            StackKeyPair::from_secret_key(readonly.secret_key.clone())
+        );
+        let _noaccess = expected.mprotect_noaccess().expect("mprotect failed");
+    }
+
     #[test]
     fn test_from_secret_key() {
         let keypair_1 = KeyPair::<
@@ -306,4 +491,55 @@ mod tests {
 
         assert_eq!(keypair_1.public_key, keypair_2.public_key);
     }
+
+    #[test]
+    fn test_derive() {
+        use crate::kdf::Kdf;
+
+        let kdf = Kdf::gen_with_defaults();
+        let (main_key, context) = kdf.clone().into_parts();
+
+        let keypair_1 = StackKeyPair::derive(&main_key, &context, 1).expect("derive failed");
+        let keypair_2 = StackKeyPair::derive(&main_key, &context, 1).expect("derive failed");
+        let keypair_3 = StackKeyPair::derive(&main_key, &context, 2).expect("derive failed");
+
+        assert_eq!(keypair_1.public_key, keypair_2.public_key);
+        assert_eq!(keypair_1.secret_key, keypair_2.secret_key);
+        assert_ne!(keypair_1.public_key, keypair_3.public_key);
+
+        let seed: StackByteArray<CRYPTO_KDF_KEYBYTES> = kdf.derive_subkey(1).expect("kdf failed");
+        assert_eq!(
+            StackKeyPair::from_seed(&seed).public_key,
+            keypair_1.public_key
+        );
+    }
+
+    #[test]
+    fn test_from_passphrase() {
+        use crate::constants::CRYPTO_PWHASH_SALTBYTES;
+
+        let config = Config::interactive().with_opslimit(1).with_memlimit(8192);
+        let salt = [7u8; CRYPTO_PWHASH_SALTBYTES];
+
+        let keypair_1 = StackKeyPair::from_passphrase(b"correct horse", &salt, &config)
+            .expect("derive failed");
+        let keypair_2 = StackKeyPair::from_passphrase(b"correct horse", &salt, &config)
+            .expect("derive failed");
+        assert_eq!(keypair_1, keypair_2);
+        assert_eq!(
+            StackKeyPair::from_secret_key(keypair_1.secret_key.clone()).public_key,
+            keypair_1.public_key
+        );
+
+        let other_salt = [8u8; CRYPTO_PWHASH_SALTBYTES];
+        let keypair_3 = StackKeyPair::from_passphrase(b"correct horse", &other_salt, &config)
+            .expect("derive failed");
+        let keypair_4 = StackKeyPair::from_passphrase(b"battery staple", &salt, &config)
+            .expect("derive failed");
+        assert_ne!(keypair_1.public_key, keypair_3.public_key);
+        assert_ne!(keypair_1.public_key, keypair_4.public_key);
+
+        StackKeyPair::from_passphrase(b"correct horse", &salt[..8], &config)
+            .expect_err("salt too short");
+    }
 }
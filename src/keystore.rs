@@ -0,0 +1,405 @@
+//! # Protected keystore
+//!
+//! [`Keystore`] is a collection of named keys held in locked memory, which can
+//! be exported to (and imported from) a single password-protected backup blob.
+//!
+//! Backups are encrypted with a key derived from the password with Argon2id
+//! (see [`crate::pwhash`]), and the entries are written as a secret stream (see
+//! [`crate::dryocstream`]), so they can't be reordered, truncated, or modified
+//! without detection. Decrypted entries are only ever written to locked
+//! memory.
+//!
+//! ## Backup format
+//!
+//! A backup blob is laid out as:
+//!
+//! magic (`DRYOCKS`, 7) ‖ version (1) ‖ opslimit (u64 LE) ‖ memlimit (u64 LE)
+//! ‖ salt (16) ‖ stream header (24) ‖ entries
+//!
+//! where each entry is a ciphertext length (u32 LE) followed by the
+//! encryption of name length (u32 LE) ‖ name ‖ key length (u32 LE) ‖ key.
+//! The final entry is empty and tagged with
+//! [`Tag::FINAL`](crate::dryocstream::Tag::FINAL). Every entry authenticates
+//! the fields preceding the stream header as associated data.
+//!
+//! ## Example
+//!
+//! ```
+//! use dryoc::keystore::Keystore;
+//! use dryoc::pwhash::Config;
+//! use dryoc::types::*;
+//!
+//! let mut keystore = Keystore::new();
+//! keystore
+//!     .insert_from_slice("signing", &[1u8; 64])
+//!     .expect("insert failed");
+//! keystore
+//!     .insert_from_slice("encryption", &[2u8; 32])
+//!     .expect("insert failed");
+//!
+//! # let config = Config::interactive().with_memlimit(8192).with_opslimit(1);
+//! # /*
+//! let config = Config::default();
+//! # */
+//! let backup = keystore
+//!     .export_encrypted_with_config(b"correct horse", config)
+//!     .expect("export failed");
+//!
+//! let restored = Keystore::import_encrypted(&backup, b"correct horse").expect("import failed");
+//! assert_eq!(restored.len(), 2);
+//! assert_eq!(
+//!     restored.get("encryption").expect("missing").as_slice(),
+//!     &[2u8; 32]
+//! );
+//!
+//! assert!(Keystore::import_encrypted(&backup, b"wrong password").is_err());
+//! ```
+use std::collections::BTreeMap;
+
+use crate::classic::crypto_pwhash::{crypto_pwhash, PasswordHashAlgorithm};
+use crate::classic::crypto_secretstream_xchacha20poly1305::{
+    crypto_secretstream_xchacha20poly1305_init_pull,
+    crypto_secretstream_xchacha20poly1305_init_push, crypto_secretstream_xchacha20poly1305_pull,
+    crypto_secretstream_xchacha20poly1305_push, Header, State,
+};
+use crate::constants::{
+    CRYPTO_PWHASH_SALTBYTES, CRYPTO_SECRETSTREAM_XCHACHA20POLY1305_ABYTES,
+    CRYPTO_SECRETSTREAM_XCHACHA20POLY1305_HEADERBYTES,
+    CRYPTO_SECRETSTREAM_XCHACHA20POLY1305_KEYBYTES,
+    CRYPTO_SECRETSTREAM_XCHACHA20POLY1305_TAG_FINAL,
+    CRYPTO_SECRETSTREAM_XCHACHA20POLY1305_TAG_MESSAGE,
+};
+use crate::error::Error;
+use crate::protected::*;
+use crate::pwhash::Config;
+
+const MAGIC_BYTES: usize = 7;
+const MAGIC: &[u8; MAGIC_BYTES] = b"DRYOCKS";
+const VERSION: u8 = 1;
+const PREAMBLE_BYTES: usize = MAGIC_BYTES + 1 + 8 + 8 + CRYPTO_PWHASH_SALTBYTES;
+
+/// A collection of named keys held in locked memory. Refer to
+/// [crate::keystore] for details.
+#[derive(Default)]
+pub struct Keystore {
+    keys: BTreeMap<String, LockedBytes>,
+}
+
+impl Keystore {
+    /// Returns a new, empty keystore.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Inserts `key` with `name`, replacing (and dropping) any existing key
+    /// with the same name.
+    pub fn insert<Name: Into<String>>(&mut self, name: Name, key: LockedBytes) {
+        self.keys.insert(name.into(), key);
+    }
+
+    /// Copies `key` into locked memory, and inserts it with `name`.
+    pub fn insert_from_slice<Name: Into<String>>(
+        &mut self,
+        name: Name,
+        key: &[u8],
+    ) -> Result<(), Error> {
+        self.insert(name, HeapBytes::from_slice_into_locked(key)?);
+        Ok(())
+    }
+
+    /// Returns the key named `name`, if present.
+    pub fn get(&self, name: &str) -> Option<&LockedBytes> {
+        self.keys.get(name)
+    }
+
+    /// Removes the key named `name`, returning true if it was present.
+    pub fn remove(&mut self, name: &str) -> bool {
+        self.keys.remove(name).is_some()
+    }
+
+    /// Returns an iterator over the names of the keys, in sorted order.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.keys.keys().map(String::as_str)
+    }
+
+    /// Returns the number of keys in this keystore.
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    /// Returns true if this keystore is empty.
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    /// Exports every key in this keystore to an encrypted backup blob, using a
+    /// key derived from `password` with the default password hashing
+    /// parameters.
+    pub fn export_encrypted(&self, password: &[u8]) -> Result<Vec<u8>, Error> {
+        self.export_encrypted_with_config(password, Config::default())
+    }
+
+    /// Exports every key in this keystore to an encrypted backup blob, using a
+    /// key derived from `password` with `config`'s password hashing
+    /// parameters.
+    pub fn export_encrypted_with_config(
+        &self,
+        password: &[u8],
+        config: Config,
+    ) -> Result<Vec<u8>, Error> {
+        let mut blob = Vec::with_capacity(PREAMBLE_BYTES);
+        blob.extend_from_slice(MAGIC);
+        blob.push(VERSION);
+        blob.extend_from_slice(&config.opslimit().to_le_bytes());
+        blob.extend_from_slice(&(config.memlimit() as u64).to_le_bytes());
+        let mut salt = [0u8; CRYPTO_PWHASH_SALTBYTES];
+        crate::rng::copy_randombytes(&mut salt);
+        blob.extend_from_slice(&salt);
+
+        let key = derive_key(password, &salt, config.opslimit(), config.memlimit())?;
+        let mut header = Header::default();
+        let mut state = State::new();
+        crypto_secretstream_xchacha20poly1305_init_push(&mut state, &mut header, key.as_array());
+        blob.extend_from_slice(&header);
+
+        let preamble = blob[..PREAMBLE_BYTES].to_vec();
+        for (name, key) in &self.keys {
+            let mut entry = HeapBytes::new_locked()?;
+            entry.resize(4 + name.len() + 4 + key.len(), 0);
+            let s = entry.as_mut_slice();
+            let (name_len, rest) = s.split_at_mut(4);
+            name_len.copy_from_slice(&length_prefix(name.len())?);
+            let (name_bytes, rest) = rest.split_at_mut(name.len());
+            name_bytes.copy_from_slice(name.as_bytes());
+            let (key_len, key_bytes) = rest.split_at_mut(4);
+            key_len.copy_from_slice(&length_prefix(key.len())?);
+            key_bytes.copy_from_slice(key.as_slice());
+
+            push_entry(
+                &mut state,
+                &mut blob,
+                entry.as_slice(),
+                &preamble,
+                CRYPTO_SECRETSTREAM_XCHACHA20POLY1305_TAG_MESSAGE,
+            )?;
+        }
+        push_entry(
+            &mut state,
+            &mut blob,
+            &[],
+            &preamble,
+            CRYPTO_SECRETSTREAM_XCHACHA20POLY1305_TAG_FINAL,
+        )?;
+
+        Ok(blob)
+    }
+
+    /// Imports the keys from an encrypted backup blob produced by
+    /// [`Keystore::export_encrypted`], using `password`. Returns an error if
+    /// the password is wrong, or the blob has been modified or truncated.
+    pub fn import_encrypted(blob: &[u8], password: &[u8]) -> Result<Self, Error> {
+        if blob.len() < PREAMBLE_BYTES + CRYPTO_SECRETSTREAM_XCHACHA20POLY1305_HEADERBYTES {
+            return Err(dryoc_error!("keystore backup is too short"));
+        }
+        let (preamble, rest) = blob.split_at(PREAMBLE_BYTES);
+        if &preamble[..MAGIC_BYTES] != MAGIC {
+            return Err(dryoc_error!("not a keystore backup"));
+        }
+        if preamble[MAGIC_BYTES] != VERSION {
+            return Err(dryoc_error!(format!(
+                "unsupported keystore backup version {}",
+                preamble[MAGIC_BYTES]
+            )));
+        }
+        let params = &preamble[MAGIC_BYTES + 1..];
+        let opslimit = u64::from_le_bytes(ByteArray::as_array(&params[..8]).to_owned());
+        let memlimit = u64::from_le_bytes(ByteArray::as_array(&params[8..16]).to_owned());
+        let memlimit = usize::try_from(memlimit).map_err(|_| dryoc_error!("invalid memlimit"))?;
+        let salt = &params[16..];
+
+        let (header, mut entries) =
+            rest.split_at(CRYPTO_SECRETSTREAM_XCHACHA20POLY1305_HEADERBYTES);
+        let key = derive_key(password, salt, opslimit, memlimit)?;
+        let mut state = State::new();
+        crypto_secretstream_xchacha20poly1305_init_pull(
+            &mut state,
+            ByteArray::as_array(header),
+            key.as_array(),
+        );
+
+        let mut keystore = Self::new();
+        loop {
+            let (entry, tag, remaining) = pull_entry(&mut state, entries, preamble)?;
+            entries = remaining;
+            if tag == CRYPTO_SECRETSTREAM_XCHACHA20POLY1305_TAG_FINAL {
+                if !entry.is_empty() || !entries.is_empty() {
+                    return Err(dryoc_error!("unexpected data after final entry"));
+                }
+                return Ok(keystore);
+            }
+
+            let (name, key) = parse_entry(entry.as_slice())?;
+            keystore.insert_from_slice(name, key)?;
+        }
+    }
+}
+
+fn derive_key(
+    password: &[u8],
+    salt: &[u8],
+    opslimit: u64,
+    memlimit: usize,
+) -> Result<LockedRO<HeapByteArray<CRYPTO_SECRETSTREAM_XCHACHA20POLY1305_KEYBYTES>>, Error> {
+    let mut key = HeapByteArray::<CRYPTO_SECRETSTREAM_XCHACHA20POLY1305_KEYBYTES>::new_locked()?;
+    crypto_pwhash(
+        key.as_mut_slice(),
+        password,
+        salt,
+        opslimit,
+        memlimit,
+        PasswordHashAlgorithm::Argon2id13,
+    )?;
+    key.mprotect_readonly()
+}
+
+fn length_prefix(len: usize) -> Result<[u8; 4], Error> {
+    u32::try_from(len)
+        .map(u32::to_le_bytes)
+        .map_err(|_| dryoc_error!("keystore entry too long"))
+}
+
+fn push_entry(
+    state: &mut State,
+    blob: &mut Vec<u8>,
+    entry: &[u8],
+    preamble: &[u8],
+    tag: u8,
+) -> Result<(), Error> {
+    let ciphertext_len = entry.len() + CRYPTO_SECRETSTREAM_XCHACHA20POLY1305_ABYTES;
+    blob.extend_from_slice(&length_prefix(ciphertext_len)?);
+    let start = blob.len();
+    blob.resize(start + ciphertext_len, 0);
+    crypto_secretstream_xchacha20poly1305_push(
+        state,
+        &mut blob[start..],
+        entry,
+        Some(preamble),
+        tag,
+    )
+}
+
+fn pull_entry<'a>(
+    state: &mut State,
+    entries: &'a [u8],
+    preamble: &[u8],
+) -> Result<(LockedBytes, u8, &'a [u8]), Error> {
+    let (len, rest) = split_length_prefixed(entries)?;
+    if len < CRYPTO_SECRETSTREAM_XCHACHA20POLY1305_ABYTES {
+        return Err(dryoc_error!("invalid keystore entry length"));
+    }
+    let (ciphertext, remaining) = rest.split_at(len);
+
+    let mut entry = HeapBytes::new_locked()?;
+    entry.resize(len - CRYPTO_SECRETSTREAM_XCHACHA20POLY1305_ABYTES, 0);
+    let mut tag = 0u8;
+    crypto_secretstream_xchacha20poly1305_pull(
+        state,
+        entry.as_mut_slice(),
+        &mut tag,
+        ciphertext,
+        Some(preamble),
+    )?;
+    Ok((entry, tag, remaining))
+}
+
+fn parse_entry(entry: &[u8]) -> Result<(String, &[u8]), Error> {
+    let (name_len, rest) = split_length_prefixed(entry)?;
+    let (name, rest) = rest.split_at(name_len);
+    let name = std::str::from_utf8(name)
+        .map_err(|_| dryoc_error!("invalid keystore entry name"))?
+        .to_string();
+    let (key_len, key) = split_length_prefixed(rest)?;
+    if key_len != key.len() {
+        return Err(dryoc_error!("invalid keystore entry key length"));
+    }
+    Ok((name, key))
+}
+
+/// Reads a u32 LE length prefix from `bytes`, returning it along with the
+/// remaining bytes, and checks that at least that many bytes remain.
+fn split_length_prefixed(bytes: &[u8]) -> Result<(usize, &[u8]), Error> {
+    if bytes.len() < 4 {
+        return Err(dryoc_error!("truncated keystore backup"));
+    }
+    let (len, rest) = bytes.split_at(4);
+    let len = u32::from_le_bytes(ByteArray::as_array(len).to_owned()) as usize;
+    if len > rest.len() {
+        return Err(dryoc_error!("truncated keystore backup"));
+    }
+    Ok((len, rest))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fast_config() -> Config {
+        Config::interactive().with_memlimit(8192).with_opslimit(1)
+    }
+
+    #[test]
+    fn test_keystore_backup() {
+        let mut keystore = Keystore::new();
+        keystore
+            .insert_from_slice("a", &[1u8; 32])
+            .expect("insert failed");
+        keystore
+            .insert_from_slice("b", &[2u8; 64])
+            .expect("insert failed");
+        keystore
+            .insert_from_slice("empty", &[])
+            .expect("insert failed");
+        assert_eq!(keystore.names().collect::<Vec<_>>(), ["a", "b", "empty"]);
+
+        let backup = keystore
+            .export_encrypted_with_config(b"password", fast_config())
+            .expect("export failed");
+        let restored = Keystore::import_encrypted(&backup, b"password").expect("import failed");
+        assert_eq!(restored.names().collect::<Vec<_>>(), ["a", "b", "empty"]);
+        for name in keystore.names() {
+            assert_eq!(
+                restored.get(name).expect("missing").as_slice(),
+                keystore.get(name).expect("missing").as_slice()
+            );
+        }
+
+        assert!(Keystore::import_encrypted(&backup, b"Password").is_err());
+        // truncation, at an entry boundary or otherwise
+        let final_entry_len = 4 + CRYPTO_SECRETSTREAM_XCHACHA20POLY1305_ABYTES;
+        assert!(
+            Keystore::import_encrypted(&backup[..backup.len() - final_entry_len], b"password")
+                .is_err()
+        );
+        assert!(Keystore::import_encrypted(&backup[..backup.len() - 1], b"password").is_err());
+        // tampering with the parameters or ciphertext
+        let mut tampered = backup.clone();
+        tampered[PREAMBLE_BYTES - 1] ^= 1;
+        assert!(Keystore::import_encrypted(&tampered, b"password").is_err());
+        let mut tampered = backup.clone();
+        let last = tampered.len() - 1;
+        tampered[last] ^= 1;
+        assert!(Keystore::import_encrypted(&tampered, b"password").is_err());
+        let mut tampered = backup;
+        tampered[0] = b'X';
+        assert!(Keystore::import_encrypted(&tampered, b"password").is_err());
+
+        let empty = Keystore::new()
+            .export_encrypted_with_config(b"password", fast_config())
+            .expect("export failed");
+        assert!(
+            Keystore::import_encrypted(&empty, b"password")
+                .expect("import failed")
+                .is_empty()
+        );
+    }
+}
@@ -0,0 +1,238 @@
+//! # Key management service integration
+//!
+//! Defines the [`KeyWrapper`] and [`AsyncKeyWrapper`] traits, which wrap and
+//! unwrap data keys with a key encryption key (KEK) identified by a key ID.
+//! [Envelope encryption](crate::envelope) uses these traits to wrap data keys
+//! under KEKs which never leave a key management service (KMS) or HSM, so
+//! integrations with cloud providers can be implemented outside of this crate
+//! and plugged in.
+//!
+//! Implement [`KeyWrapper`] for blocking clients, or [`AsyncKeyWrapper`] for
+//! async clients. Every [`KeyWrapper`] which is [`Sync`] is also an
+//! [`AsyncKeyWrapper`], so the async APIs accept either.
+//!
+//! Unwrapped keys are returned in [`Zeroizing`] vectors, which are wiped when
+//! they're dropped.
+//!
+//! [`LocalKeyWrapper`] implements both traits with a set of local secret
+//! keys, using [`DryocSecretBox`], for testing, development, or deployments
+//! without a KMS.
+//!
+//! ## Example
+//!
+//! ```
+//! use dryoc::dryocsecretbox::Key;
+//! use dryoc::envelope::*;
+//! use dryoc::kms::LocalKeyWrapper;
+//! use dryoc::types::*;
+//!
+//! let kms = LocalKeyWrapper::new().with_key("kek-1", Key::gen());
+//!
+//! let envelope = Envelope::seal(
+//!     b"sensitive data",
+//!     b"",
+//!     &[Kek::External {
+//!         key_id: "kek-1",
+//!         wrapper: &kms,
+//!     }],
+//! )
+//! .expect("seal failed");
+//!
+//! let plaintext = envelope
+//!     .open(
+//!         b"",
+//!         &Unwrapper::External {
+//!             key_id: "kek-1",
+//!             wrapper: &kms,
+//!         },
+//!     )
+//!     .expect("open failed");
+//! assert_eq!(plaintext, b"sensitive data");
+//! ```
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+
+use zeroize::Zeroizing;
+
+use crate::constants::CRYPTO_SECRETBOX_NONCEBYTES;
+use crate::dryocsecretbox::{DryocSecretBox, Key, Nonce};
+use crate::error::Error;
+use crate::types::*;
+
+/// Boxed future returned by [`AsyncKeyWrapper`] methods.
+pub type KeyWrapperFuture<'a, T> = Pin<Box<dyn Future<Output = Result<T, Error>> + Send + 'a>>;
+
+/// Wraps and unwraps data keys with a key encryption key held elsewhere, such
+/// as in a cloud KMS or HSM.
+pub trait KeyWrapper {
+    /// Wraps `key` with the KEK identified by `key_id`, returning the wrapped
+    /// key.
+    fn wrap_key(&self, key_id: &str, key: &[u8]) -> Result<Vec<u8>, Error>;
+
+    /// Unwraps `wrapped_key` with the KEK identified by `key_id`, returning
+    /// the key.
+    fn unwrap_key(&self, key_id: &str, wrapped_key: &[u8]) -> Result<Zeroizing<Vec<u8>>, Error>;
+}
+
+/// Async version of [`KeyWrapper`], for KMS clients which make network
+/// requests. Methods return boxed futures, so the trait can be used as a
+/// trait object.
+pub trait AsyncKeyWrapper {
+    /// Wraps `key` with the KEK identified by `key_id`, returning the wrapped
+    /// key.
+    fn wrap_key<'a>(&'a self, key_id: &'a str, key: &'a [u8]) -> KeyWrapperFuture<'a, Vec<u8>>;
+
+    /// Unwraps `wrapped_key` with the KEK identified by `key_id`, returning
+    /// the key.
+    fn unwrap_key<'a>(
+        &'a self,
+        key_id: &'a str,
+        wrapped_key: &'a [u8],
+    ) -> KeyWrapperFuture<'a, Zeroizing<Vec<u8>>>;
+}
+
+impl<W: KeyWrapper + Sync> AsyncKeyWrapper for W {
+    fn wrap_key<'a>(&'a self, key_id: &'a str, key: &'a [u8]) -> KeyWrapperFuture<'a, Vec<u8>> {
+        Box::pin(async move { KeyWrapper::wrap_key(self, key_id, key) })
+    }
+
+    fn unwrap_key<'a>(
+        &'a self,
+        key_id: &'a str,
+        wrapped_key: &'a [u8],
+    ) -> KeyWrapperFuture<'a, Zeroizing<Vec<u8>>> {
+        Box::pin(async move { KeyWrapper::unwrap_key(self, key_id, wrapped_key) })
+    }
+}
+
+/// A [`KeyWrapper`] with local secret keys, which wraps keys with a
+/// [`DryocSecretBox`] and a random nonce. Wrapped keys are laid out as nonce
+/// (24) ‖ tag (16) ‖ ciphertext.
+#[derive(Clone, Default)]
+pub struct LocalKeyWrapper {
+    keys: HashMap<String, Key>,
+}
+
+impl LocalKeyWrapper {
+    /// Returns a new key wrapper without any keys.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds `key` with `key_id`.
+    pub fn with_key(mut self, key_id: &str, key: Key) -> Self {
+        self.keys.insert(key_id.into(), key);
+        self
+    }
+
+    /// Generates a new random key with `key_id`, replacing any existing key
+    /// with the same ID.
+    pub fn gen_key(&mut self, key_id: &str) {
+        self.keys.insert(key_id.into(), Key::gen());
+    }
+
+    /// Removes the key with `key_id`, returning it if it existed.
+    pub fn remove_key(&mut self, key_id: &str) -> Option<Key> {
+        self.keys.remove(key_id)
+    }
+
+    fn key(&self, key_id: &str) -> Result<&Key, Error> {
+        self.keys
+            .get(key_id)
+            .ok_or_else(|| dryoc_error!(format!("unknown key ID {}", key_id)))
+    }
+}
+
+impl KeyWrapper for LocalKeyWrapper {
+    fn wrap_key(&self, key_id: &str, key: &[u8]) -> Result<Vec<u8>, Error> {
+        secretbox_wrap(self.key(key_id)?, key)
+    }
+
+    fn unwrap_key(&self, key_id: &str, wrapped_key: &[u8]) -> Result<Zeroizing<Vec<u8>>, Error> {
+        secretbox_unwrap(self.key(key_id)?, wrapped_key)
+    }
+}
+
+/// Wraps `key` with `kek`, returning nonce (24) ‖ tag (16) ‖ ciphertext.
+pub(crate) fn secretbox_wrap(kek: &Key, key: &[u8]) -> Result<Vec<u8>, Error> {
+    let nonce = Nonce::gen();
+    let secretbox = DryocSecretBox::try_encrypt_to_vecbox(key, &nonce, kek)?;
+    let mut wrapped = nonce.to_vec();
+    wrapped.extend_from_slice(&secretbox.to_vec());
+    Ok(wrapped)
+}
+
+/// Unwraps `wrapped_key`, as returned by [`secretbox_wrap`], with `kek`.
+pub(crate) fn secretbox_unwrap(kek: &Key, wrapped_key: &[u8]) -> Result<Zeroizing<Vec<u8>>, Error> {
+    if wrapped_key.len() < CRYPTO_SECRETBOX_NONCEBYTES {
+        return Err(dryoc_error!("wrapped key too short"));
+    }
+    let (nonce, secretbox) = wrapped_key.split_at(CRYPTO_SECRETBOX_NONCEBYTES);
+    let nonce = Nonce::try_from(nonce)?;
+    DryocSecretBox::from_bytes(secretbox)?
+        .decrypt_to_vec(&nonce, kek)
+        .map(Zeroizing::new)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::task::{Context, Poll, RawWaker, RawWakerVTable, Waker};
+
+    use super::*;
+
+    /// Polls a future which is expected to be ready immediately.
+    pub(crate) fn ready<F: Future>(future: F) -> F::Output {
+        fn noop_raw_waker() -> RawWaker {
+            fn clone(_: *const ()) -> RawWaker {
+                noop_raw_waker()
+            }
+            fn noop(_: *const ()) {}
+            static VTABLE: RawWakerVTable = RawWakerVTable::new(clone, noop, noop, noop);
+            RawWaker::new(std::ptr::null(), &VTABLE)
+        }
+        let waker = unsafe { Waker::from_raw(noop_raw_waker()) };
+        let mut cx = Context::from_waker(&waker);
+        let mut future = Box::pin(future);
+        match future.as_mut().poll(&mut cx) {
+            Poll::Ready(output) => output,
+            Poll::Pending => panic!("future not ready"),
+        }
+    }
+
+    #[test]
+    fn test_local_key_wrapper() {
+        let mut kms = LocalKeyWrapper::new().with_key("a", Key::gen());
+        kms.gen_key("b");
+
+        let wrapped = KeyWrapper::wrap_key(&kms, "a", b"data key").expect("wrap");
+        assert_eq!(
+            KeyWrapper::unwrap_key(&kms, "a", &wrapped)
+                .expect("unwrap")
+                .as_slice(),
+            b"data key"
+        );
+        assert!(KeyWrapper::unwrap_key(&kms, "b", &wrapped).is_err());
+        assert!(KeyWrapper::unwrap_key(&kms, "c", &wrapped).is_err());
+        assert!(KeyWrapper::unwrap_key(&kms, "a", &wrapped[..20]).is_err());
+
+        kms.remove_key("a");
+        assert!(KeyWrapper::wrap_key(&kms, "a", b"data key").is_err());
+    }
+
+    #[test]
+    fn test_async_key_wrapper() {
+        let kms = LocalKeyWrapper::new().with_key("a", Key::gen());
+        let wrapper: &dyn AsyncKeyWrapper = &kms;
+
+        let wrapped = ready(wrapper.wrap_key("a", b"data key")).expect("wrap");
+        assert_eq!(
+            ready(wrapper.unwrap_key("a", &wrapped))
+                .expect("unwrap")
+                .as_slice(),
+            b"data key"
+        );
+        assert!(ready(wrapper.unwrap_key("b", &wrapped)).is_err());
+    }
+}
@@ -40,6 +40,72 @@
 //! assert_eq!(client_tx, server_rx);
 //! ```
 //!
+//! ## Exporting keying material
+//!
+//! Additional keys bound to a session can be derived with
+//! [`Session::export_keying_material`], similar to TLS exporters. Both parties
+//! derive the same output for the same label and context.
+//!
+//! ```
+//! use dryoc::kx::*;
+//!
+//! let client_keypair = KeyPair::gen();
+//! let server_keypair = KeyPair::gen();
+//!
+//! let client = Session::new_client_with_defaults(&client_keypair, &server_keypair.public_key)
+//!     .expect("compute client failed");
+//! let server = Session::new_server_with_defaults(&server_keypair, &client_keypair.public_key)
+//!     .expect("compute server failed");
+//!
+//! // Derive a key for a separate datagram channel
+//! let client_key = client
+//!     .export_keying_material_to_vec(b"datagram channel", b"", 32)
+//!     .expect("export failed");
+//! let server_key = server
+//!     .export_keying_material_to_vec(b"datagram channel", b"", 32)
+//!     .expect("export failed");
+//!
+//! assert_eq!(client_key, server_key);
+//! ```
+//!
+//! ## Updating session keys
+//!
+//! Long-lived sessions can periodically replace their keys with
+//! [`Session::rekey_with`], using a two-message exchange of fresh ephemeral
+//! public keys. The new keys are chained with the old session keys, so
+//! compromising the new ephemeral keys alone isn't enough to derive them, and
+//! compromising the new session keys reveals nothing about the old ones.
+//!
+//! ```
+//! use dryoc::kx::*;
+//!
+//! # let client_keypair = KeyPair::gen();
+//! # let server_keypair = KeyPair::gen();
+//! # let client = Session::new_client_with_defaults(&client_keypair, &server_keypair.public_key)
+//! #     .expect("compute client failed");
+//! # let server = Session::new_server_with_defaults(&server_keypair, &client_keypair.public_key)
+//! #     .expect("compute server failed");
+//! // Message 1: the client sends its ephemeral public key to the server
+//! let client_update = KeyUpdate::gen();
+//! let client_update_pk = client_update.public_key().clone();
+//!
+//! // Message 2: the server replies with its own ephemeral public key, and
+//! // updates its session
+//! let server_update = KeyUpdate::gen();
+//! let server_update_pk = server_update.public_key().clone();
+//! let server = server
+//!     .rekey_with(server_update, &client_update_pk)
+//!     .expect("server rekey failed");
+//!
+//! // The client updates its session upon receiving message 2
+//! let client = client
+//!     .rekey_with(client_update, &server_update_pk)
+//!     .expect("client rekey failed");
+//!
+//! assert_eq!(client.rx_as_slice(), server.tx_as_slice());
+//! assert_eq!(client.tx_as_slice(), server.rx_as_slice());
+//! ```
+//!
 //! ## Additional resources
 //!
 //! * See <https://doc.libsodium.org/key_exchange> for additional details on key
@@ -47,15 +113,30 @@
 
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
+use sha2::Sha512;
+use subtle::ConstantTimeEq;
 use zeroize::Zeroize;
 
+use crate::classic::crypto_core::crypto_scalarmult;
 use crate::classic::crypto_kx::{crypto_kx_client_session_keys, crypto_kx_server_session_keys};
 use crate::constants::{
     CRYPTO_KX_PUBLICKEYBYTES, CRYPTO_KX_SECRETKEYBYTES, CRYPTO_KX_SESSIONKEYBYTES,
+    CRYPTO_SCALARMULT_BYTES,
 };
 use crate::error::Error;
+use crate::hkdf;
+use crate::policy::Algorithms;
 use crate::types::*;
 
+/// Length of a channel binding token, as returned by
+/// [`Session::channel_binding`].
+pub const CHANNEL_BINDING_BYTES: usize = 32;
+/// Stack-allocated channel binding token type alias
+pub type ChannelBinding = StackByteArray<CHANNEL_BINDING_BYTES>;
+/// Maximum number of bytes which can be exported with
+/// [`Session::export_keying_material`].
+pub const MAX_EXPORTED_KEYING_MATERIAL_BYTES: usize = 255 * 64;
+
 /// Stack-allocated session key type alias
 pub type SessionKey = StackByteArray<CRYPTO_KX_SESSIONKEYBYTES>;
 /// Stack-allocated public key type alias
@@ -73,13 +154,46 @@ pub type KeyPair = crate::keypair::KeyPair<PublicKey, SecretKey>;
 /// Key derivation implemantation based on Curve25519, Diffie-Hellman, and
 /// Blake2b. Compatible with libsodium's `crypto_kx_*` functions.
 pub struct Session<SessionKey: ByteArray<CRYPTO_KX_SESSIONKEYBYTES> + Zeroize> {
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::redact::serialize_secret")
+    )]
     rx_key: SessionKey,
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::redact::serialize_secret")
+    )]
     tx_key: SessionKey,
 }
 
 /// Stack-allocated type alias for [`Session`]. Provided for convenience.
 pub type StackSession = Session<SessionKey>;
 
+/// Ephemeral keypair for one side of a session key update, consumed by
+/// [`Session::rekey_with`].
+///
+/// Each party generates a new [`KeyUpdate`] and sends its
+/// [`public_key`](KeyUpdate::public_key) to the other party, preferably over
+/// the existing session's channel. A [`KeyUpdate`] can only be used once.
+pub struct KeyUpdate {
+    keypair: KeyPair,
+}
+
+impl KeyUpdate {
+    /// Generates a new random ephemeral keypair for a key update.
+    pub fn gen() -> Self {
+        Self {
+            keypair: KeyPair::gen(),
+        }
+    }
+
+    /// Returns the ephemeral public key, which must be sent to the other
+    /// party.
+    pub fn public_key(&self) -> &PublicKey {
+        &self.keypair.public_key
+    }
+}
+
 #[cfg(any(feature = "nightly", all(doc, not(doctest))))]
 #[cfg_attr(all(feature = "nightly", doc), doc(cfg(feature = "nightly")))]
 pub mod protected {
@@ -152,6 +266,8 @@ impl<SessionKey: NewByteArray<CRYPTO_KX_SESSIONKEYBYTES> + Zeroize> Session<Sess
         client_keypair: &crate::keypair::KeyPair<PublicKey, SecretKey>,
         server_public_key: &PublicKey,
     ) -> Result<Self, Error> {
+        crate::policy::require(Algorithms::X25519)?;
+
         let mut rx_key = SessionKey::new_byte_array();
         let mut tx_key = SessionKey::new_byte_array();
 
@@ -175,6 +291,8 @@ impl<SessionKey: NewByteArray<CRYPTO_KX_SESSIONKEYBYTES> + Zeroize> Session<Sess
         server_keypair: &crate::keypair::KeyPair<PublicKey, SecretKey>,
         client_public_key: &PublicKey,
     ) -> Result<Self, Error> {
+        crate::policy::require(Algorithms::X25519)?;
+
         let mut rx_key = SessionKey::new_byte_array();
         let mut tx_key = SessionKey::new_byte_array();
 
@@ -188,6 +306,64 @@ impl<SessionKey: NewByteArray<CRYPTO_KX_SESSIONKEYBYTES> + Zeroize> Session<Sess
 
         Ok(Self { rx_key, tx_key })
     }
+
+    /// Computes new session keys from this session and a key update, given
+    /// the local `key_update` and the other party's ephemeral
+    /// `new_remote_public_key`, returning the new session upon success.
+    ///
+    /// Each new key is derived with HKDF-SHA512 from the corresponding old
+    /// key and the X25519 shared secret of the ephemeral keys, so both parties
+    /// must hold the same session and exchange ephemeral public keys. Refer
+    /// to [crate::kx] for the full exchange.
+    pub fn rekey_with<RemotePublicKey: ByteArray<CRYPTO_KX_PUBLICKEYBYTES>>(
+        &self,
+        key_update: KeyUpdate,
+        new_remote_public_key: &RemotePublicKey,
+    ) -> Result<Self, Error> {
+        let local_public_key = key_update.keypair.public_key.as_array();
+        let remote_public_key = new_remote_public_key.as_array();
+
+        let mut shared_secret = [0u8; CRYPTO_SCALARMULT_BYTES];
+        crypto_scalarmult(
+            &mut shared_secret,
+            key_update.keypair.secret_key.as_array(),
+            remote_public_key,
+        );
+        if shared_secret
+            .ct_eq(&[0u8; CRYPTO_SCALARMULT_BYTES])
+            .unwrap_u8()
+            == 1
+        {
+            return Err(dryoc_error!("invalid remote public key"));
+        }
+
+        // Order the ephemeral public keys so both parties agree on them
+        let (first, second) = if local_public_key < remote_public_key {
+            (local_public_key, remote_public_key)
+        } else {
+            (remote_public_key, local_public_key)
+        };
+
+        let rekey = |old_key: &[u8]| -> Result<SessionKey, Error> {
+            let mut prk = hkdf::extract::<Sha512>(old_key, &shared_secret);
+            let mut new_key = SessionKey::new_byte_array();
+            let result = hkdf::expand::<Sha512>(
+                new_key.as_mut_slice(),
+                &prk,
+                &[b"dryoc kx key update", first, second],
+            );
+            prk.as_mut_slice().zeroize();
+            result.map(|_| new_key)
+        };
+        let rx_key = rekey(self.rx_key.as_slice());
+        let tx_key = rekey(self.tx_key.as_slice());
+        shared_secret.zeroize();
+
+        Ok(Self {
+            rx_key: rx_key?,
+            tx_key: tx_key?,
+        })
+    }
 }
 
 impl Session<SessionKey> {
@@ -219,6 +395,12 @@ impl Session<SessionKey> {
 }
 
 impl<SessionKey: ByteArray<CRYPTO_KX_SESSIONKEYBYTES> + Zeroize> Session<SessionKey> {
+    /// Returns a session with `rx_key` and `tx_key`, for sessions established
+    /// by other key exchanges, such as [crate::pairing].
+    pub(crate) fn from_parts(rx_key: SessionKey, tx_key: SessionKey) -> Self {
+        Self { rx_key, tx_key }
+    }
+
     /// Moves the rx_key and tx_key out of this instance, returning them as a
     /// tuple with `(rx_key, tx_key)`.
     pub fn into_parts(self) -> (SessionKey, SessionKey) {
@@ -248,6 +430,143 @@ impl<SessionKey: ByteArray<CRYPTO_KX_SESSIONKEYBYTES> + Zeroize> Session<Session
     pub fn tx_as_array(&self) -> &[u8; CRYPTO_KX_SESSIONKEYBYTES] {
         self.tx_key.as_array()
     }
+
+    /// Derives `len` bytes of keying material bound to this session, the
+    /// application-specific `label`, and an optional `context`, similar to
+    /// TLS exporters ([RFC 8446, section
+    /// 7.5](https://www.rfc-editor.org/rfc/rfc8446#section-7.5)).
+    ///
+    /// The output is derived with HKDF-SHA512 from both session keys, and is
+    /// the same for the client and the server. Distinct labels, contexts, or
+    /// lengths produce unrelated outputs, and the exported material can't be
+    /// used to recover the session keys.
+    ///
+    /// `len` must be between 1 and [`MAX_EXPORTED_KEYING_MATERIAL_BYTES`].
+    pub fn export_keying_material<Output: NewBytes + ResizableBytes>(
+        &self,
+        label: &[u8],
+        context: &[u8],
+        len: usize,
+    ) -> Result<Output, Error> {
+        let mut output = Output::new_bytes();
+        output.resize(len, 0);
+        self.export_into(output.as_mut_slice(), label, context)?;
+        Ok(output)
+    }
+
+    /// Wrapper around [`Session::export_keying_material`], returning a
+    /// [`Vec`]. Provided for convenience.
+    pub fn export_keying_material_to_vec(
+        &self,
+        label: &[u8],
+        context: &[u8],
+        len: usize,
+    ) -> Result<Vec<u8>, Error> {
+        self.export_keying_material(label, context, len)
+    }
+
+    /// Derives a channel binding token for this session, given the
+    /// `client_public_key` and `server_public_key` used to establish it.
+    ///
+    /// The token commits to the key exchange transcript (both public keys)
+    /// and to the session keys, and is the same for the client and the
+    /// server. Applications can sign the token, or include it in an
+    /// application-layer authentication exchange, to prove that the
+    /// authentication took place over this particular session. A relay which
+    /// terminates two separate sessions ends up with two different tokens, so
+    /// it can't forward the authentication from one to the other.
+    ///
+    /// Like the TLS exporter-based channel binding described in [RFC
+    /// 9266](https://www.rfc-editor.org/rfc/rfc9266), the token is exported
+    /// keying material with a fixed label.
+    ///
+    /// ```
+    /// use dryoc::kx::*;
+    /// use dryoc::sign::SigningKeyPair;
+    ///
+    /// let client_keypair = KeyPair::gen();
+    /// let server_keypair = KeyPair::gen();
+    ///
+    /// let client = Session::new_client_with_defaults(&client_keypair, &server_keypair.public_key)
+    ///     .expect("compute client failed");
+    /// let server = Session::new_server_with_defaults(&server_keypair, &client_keypair.public_key)
+    ///     .expect("compute server failed");
+    ///
+    /// // The client signs the token with its long-term identity key
+    /// let identity = SigningKeyPair::gen_with_defaults();
+    /// let token: ChannelBinding = client
+    ///     .channel_binding(&client_keypair.public_key, &server_keypair.public_key)
+    ///     .expect("channel binding failed");
+    /// let signed = identity
+    ///     .sign_with_defaults(token.to_vec())
+    ///     .expect("sign failed");
+    ///
+    /// // The server verifies the signature over its own copy of the token
+    /// let expected: ChannelBinding = server
+    ///     .channel_binding(&client_keypair.public_key, &server_keypair.public_key)
+    ///     .expect("channel binding failed");
+    /// signed.verify(&identity.public_key).expect("verify failed");
+    /// let (_, message) = signed.into_parts();
+    /// assert_eq!(message, expected.to_vec());
+    /// ```
+    pub fn channel_binding<
+        PublicKey: ByteArray<CRYPTO_KX_PUBLICKEYBYTES>,
+        Output: NewByteArray<CHANNEL_BINDING_BYTES>,
+    >(
+        &self,
+        client_public_key: &PublicKey,
+        server_public_key: &PublicKey,
+    ) -> Result<Output, Error> {
+        let mut transcript = [0u8; 2 * CRYPTO_KX_PUBLICKEYBYTES];
+        transcript[..CRYPTO_KX_PUBLICKEYBYTES].copy_from_slice(client_public_key.as_slice());
+        transcript[CRYPTO_KX_PUBLICKEYBYTES..].copy_from_slice(server_public_key.as_slice());
+
+        let mut output = Output::new_byte_array();
+        self.export_into(
+            output.as_mut_slice(),
+            b"dryoc kx channel binding",
+            &transcript,
+        )?;
+        Ok(output)
+    }
+
+    fn export_into(&self, output: &mut [u8], label: &[u8], context: &[u8]) -> Result<(), Error> {
+        let len = output.len();
+        validate!(
+            1,
+            MAX_EXPORTED_KEYING_MATERIAL_BYTES,
+            len,
+            "exported keying material length"
+        );
+
+        // The client's rx key is the server's tx key (and vice versa), so
+        // combine them in a way that doesn't depend on the role
+        let mut ikm = [0u8; CRYPTO_KX_SESSIONKEYBYTES];
+        for ((k, rx), tx) in ikm
+            .iter_mut()
+            .zip(self.rx_key.as_slice())
+            .zip(self.tx_key.as_slice())
+        {
+            *k = rx ^ tx;
+        }
+        let mut prk = hkdf::extract::<Sha512>(b"dryoc kx exporter", &ikm);
+        ikm.zeroize();
+
+        let result = hkdf::expand::<Sha512>(
+            output,
+            &prk,
+            &[
+                &(label.len() as u64).to_le_bytes(),
+                label,
+                &(context.len() as u64).to_le_bytes(),
+                context,
+                &(len as u64).to_le_bytes(),
+            ],
+        );
+        prk.as_mut_slice().zeroize();
+
+        result
+    }
 }
 
 #[cfg(test)]
@@ -273,4 +592,133 @@ mod tests {
         assert_eq!(client_rx, server_tx);
         assert_eq!(client_tx, server_rx);
     }
+
+    #[test]
+    fn test_export_keying_material() {
+        let client_keypair = KeyPair::gen();
+        let server_keypair = KeyPair::gen();
+
+        let client = Session::new_client_with_defaults(&client_keypair, &server_keypair.public_key)
+            .expect("compute client failed");
+        let server = Session::new_server_with_defaults(&server_keypair, &client_keypair.public_key)
+            .expect("compute server failed");
+
+        let client_key = client
+            .export_keying_material_to_vec(b"label", b"context", 32)
+            .expect("export failed");
+        let server_key = server
+            .export_keying_material_to_vec(b"label", b"context", 32)
+            .expect("export failed");
+        assert_eq!(client_key, server_key);
+        assert_ne!(client_key.as_slice(), client.rx_as_slice());
+        assert_ne!(client_key.as_slice(), client.tx_as_slice());
+
+        for (label, context, len) in [
+            (&b"other label"[..], &b"context"[..], 32),
+            (b"label", b"other context", 32),
+            (b"label", b"", 32),
+            (b"labelcontext", b"", 32),
+            (b"label", b"context", 33),
+        ] {
+            let other = client
+                .export_keying_material_to_vec(label, context, len)
+                .expect("export failed");
+            assert_ne!(client_key, other[..32]);
+        }
+
+        let long: Vec<u8> = server
+            .export_keying_material(b"label", b"", MAX_EXPORTED_KEYING_MATERIAL_BYTES)
+            .expect("export failed");
+        assert_eq!(long.len(), MAX_EXPORTED_KEYING_MATERIAL_BYTES);
+
+        client
+            .export_keying_material_to_vec(b"label", b"", 0)
+            .expect_err("zero length should fail");
+        client
+            .export_keying_material_to_vec(b"label", b"", MAX_EXPORTED_KEYING_MATERIAL_BYTES + 1)
+            .expect_err("too long should fail");
+    }
+
+    #[test]
+    fn test_rekey() {
+        let client_keypair = KeyPair::gen();
+        let server_keypair = KeyPair::gen();
+
+        let client = Session::new_client_with_defaults(&client_keypair, &server_keypair.public_key)
+            .expect("compute client failed");
+        let server = Session::new_server_with_defaults(&server_keypair, &client_keypair.public_key)
+            .expect("compute server failed");
+
+        let client_update = KeyUpdate::gen();
+        let server_update = KeyUpdate::gen();
+        let client_update_pk = client_update.public_key().clone();
+        let server_update_pk = server_update.public_key().clone();
+
+        let new_client = client
+            .rekey_with(client_update, &server_update_pk)
+            .expect("client rekey failed");
+        let new_server = server
+            .rekey_with(server_update, &client_update_pk)
+            .expect("server rekey failed");
+
+        assert_eq!(new_client.rx_as_slice(), new_server.tx_as_slice());
+        assert_eq!(new_client.tx_as_slice(), new_server.rx_as_slice());
+        assert_ne!(new_client.rx_as_slice(), client.rx_as_slice());
+        assert_ne!(new_client.tx_as_slice(), client.tx_as_slice());
+        assert_ne!(new_client.rx_as_slice(), new_client.tx_as_slice());
+
+        // a party without the old session can't derive the new keys
+        let other_keypair = KeyPair::gen();
+        let other = Session::new_client_with_defaults(&other_keypair, &server_keypair.public_key)
+            .expect("compute client failed");
+        let mitm_update = KeyUpdate::gen();
+        let mitm_update_pk = mitm_update.public_key().clone();
+        let server_update = KeyUpdate::gen();
+        let server_update_pk = server_update.public_key().clone();
+        let mitm = other
+            .rekey_with(mitm_update, &server_update_pk)
+            .expect("rekey failed");
+        let new_server = new_server
+            .rekey_with(server_update, &mitm_update_pk)
+            .expect("rekey failed");
+        assert_ne!(mitm.rx_as_slice(), new_server.tx_as_slice());
+
+        client
+            .rekey_with(KeyUpdate::gen(), &PublicKey::default())
+            .expect_err("low order point should fail");
+    }
+
+    #[test]
+    fn test_channel_binding() {
+        let client_keypair = KeyPair::gen();
+        let server_keypair = KeyPair::gen();
+        let relay_keypair = KeyPair::gen();
+
+        let client = Session::new_client_with_defaults(&client_keypair, &server_keypair.public_key)
+            .expect("compute client failed");
+        let server = Session::new_server_with_defaults(&server_keypair, &client_keypair.public_key)
+            .expect("compute server failed");
+
+        let client_token: ChannelBinding = client
+            .channel_binding(&client_keypair.public_key, &server_keypair.public_key)
+            .expect("channel binding failed");
+        let server_token: ChannelBinding = server
+            .channel_binding(&client_keypair.public_key, &server_keypair.public_key)
+            .expect("channel binding failed");
+        assert_eq!(client_token, server_token);
+
+        let swapped: ChannelBinding = client
+            .channel_binding(&server_keypair.public_key, &client_keypair.public_key)
+            .expect("channel binding failed");
+        assert_ne!(client_token, swapped);
+
+        // a relay terminating two sessions sees two different tokens
+        let relay_to_server =
+            Session::new_client_with_defaults(&relay_keypair, &server_keypair.public_key)
+                .expect("compute client failed");
+        let relay_token: ChannelBinding = relay_to_server
+            .channel_binding(&relay_keypair.public_key, &server_keypair.public_key)
+            .expect("channel binding failed");
+        assert_ne!(client_token, relay_token);
+    }
 }
@@ -33,6 +33,30 @@
 //! * Protected memory handling (`mprotect()` + `mlock()`, along with Windows
 //!   equivalents)
 //! * [Serde](https://serde.rs/) support (with `features = ["serde"]`)
+//! * [`bytes`](https://crates.io/crates/bytes) support for the byte traits in
+//!   [types] (with `features = ["bytes"]`)
+//! * A companion `dryoc` command-line tool for key generation, sealing,
+//!   signing, hashing, and password hashing with the library's serde
+//!   representations (with `features = ["cli"]`)
+//! * Conversions to and from the key and signature types of
+//!   [`x25519-dalek`](https://crates.io/crates/x25519-dalek),
+//!   [`ed25519-dalek`](https://crates.io/crates/ed25519-dalek), and
+//!   [`chacha20poly1305`](https://crates.io/crates/chacha20poly1305) (with
+//!   `features = ["x25519-dalek"]`, `features = ["ed25519-dalek"]`, or
+//!   `features = ["chacha20poly1305"]`)
+//! * [COSE](https://www.rfc-editor.org/rfc/rfc9052) encoding of signed and
+//!   encrypted messages, with [cose] (with `features = ["cose"]`)
+//! * [JOSE](https://www.rfc-editor.org/rfc/rfc7515) compact serialization of
+//!   EdDSA signed and ECDH-ES encrypted tokens, with [jose] (with `features =
+//!   ["jose"]`)
+//! * Caller-provided ephemeral keys for sealed boxes, so tests of sealed
+//!   outputs are reproducible (with `features = ["deterministic-testing"]`)
+//! * Operation context (such as the failed primitive and buffer sizes) on
+//!   errors, for aggregating failures (with `features = ["rich-errors"]`)
+//! * A [zeroizing global allocator](zeroizing_alloc), which wipes freed and
+//!   reallocated memory, including the crate's own temporary buffers (with
+//!   `features = ["zeroizing-alloc"]`, or `features =
+//!   ["global-zeroizing-alloc"]` to install it)
 //! * [_Portable_ SIMD](https://doc.rust-lang.org/std/simd/index.html)
 //!   implementation for Blake2b (used by generic hashing, password hashing, and
 //!   key derivation) on nightly, with `features = ["simd_backend", "nightly"]`
@@ -81,6 +105,7 @@
 //! | Secret-key authenticated boxes | [`DryocSecretBox`](dryocsecretbox) | [`crypto_secretbox`](classic::crypto_secretbox) | [Link](https://libsodium.gitbook.io/doc/secret-key_cryptography/secretbox) |
 //! | Streaming encryption | [`DryocStream`](dryocstream) | [`crypto_secretstream_xchacha20poly1305`](classic::crypto_secretstream_xchacha20poly1305) | [Link](https://libsodium.gitbook.io/doc/secret-key_cryptography/secretstream) |
 //! | Generic hashing, HMAC | [`GenericHash`](generichash) | [`crypto_generichash`](classic::crypto_generichash) | [Link](https://doc.libsodium.org/hashing/generic_hashing) |
+//! | Keyed hashing, MAC | [`KeyedHash`](keyedhash) | [`crypto_generichash`](classic::crypto_generichash) | [Link](https://doc.libsodium.org/hashing/generic_hashing) |
 //! | Secret-key authentication | [`Auth`](auth) | [`crypto_auth`](classic::crypto_auth) | [Link](https://doc.libsodium.org/secret-key_cryptography/secret-key_authentication) |
 //! | One-time authentication | [`OnetimeAuth`](onetimeauth) | [`crypto_onetimeauth`](classic::crypto_onetimeauth) | [Link](https://doc.libsodium.org/advanced/poly1305) |
 //! | Key derivation | [`Kdf`](kdf) | [`crypto_kdf`](classic::crypto_kdf) | [Link](https://doc.libsodium.org/key_derivation) |
@@ -89,6 +114,7 @@
 //! | Password hashing | [`PwHash`](pwhash) | [`crypto_pwhash`](classic::crypto_pwhash) | [Link](https://libsodium.gitbook.io/doc/password_hashing/default_phf) |
 //! | Protected memory[^4] | [protected] | N/A | [Link](https://doc.libsodium.org/memory_management) |
 //! | Short-input hashing | N/A | [`crypto_shorthash`](classic::crypto_shorthash) | [Link](https://libsodium.gitbook.io/doc/hashing/short-input_hashing) |
+//! | Stream ciphers | N/A | [`crypto_stream`](classic::crypto_stream) | [Link](https://doc.libsodium.org/advanced/stream_ciphers/xsalsa20) |
 //!
 //! ## Using Serde
 //!
@@ -148,11 +174,18 @@ mod error;
 #[macro_use]
 pub mod protected;
 
+mod aead;
 mod argon2;
 mod blake2b;
 #[cfg(feature = "serde")]
 mod bytes_serde;
+mod cbor;
+#[cfg(feature = "curve448")]
+mod curve448;
+mod hkdf;
+mod interop;
 mod poly1305;
+mod ristretto_util;
 mod scalarmult_curve25519;
 mod siphash24;
 
@@ -164,8 +197,11 @@ pub mod classic {
     //! switch code from using libsodium directly over to dryoc, and also to
     //! provide a familiar interface for anyone already comfortable with
     //! libsodium.
+    //!
+    //! Versions of the Classic API functions which accept distinct key, nonce,
+    //! and MAC types (rather than raw arrays) are provided in [typed].
     mod crypto_box_impl;
-    mod crypto_secretbox_impl;
+    pub(crate) mod crypto_secretbox_impl;
     mod generichash_blake2b;
 
     pub mod crypto_auth;
@@ -182,32 +218,118 @@ pub mod classic {
     pub mod crypto_secretbox;
     pub mod crypto_secretstream_xchacha20poly1305;
     pub mod crypto_shorthash;
+    pub mod crypto_stream;
     pub mod crypto_sign;
     pub mod crypto_sign_ed25519;
+    pub mod typed;
 }
 
+pub mod attestation;
 pub mod auth;
+pub mod authenticator;
+pub mod bech32;
+pub mod blindsig;
+pub mod canonical;
+pub mod ceremony;
+pub mod chunking;
+pub mod codec;
+pub mod column_crypto;
+pub mod compat {
+    //! # Compatibility shims
+    //!
+    //! Modules which mirror the APIs of other libraries, implemented with
+    //! dryoc, to ease migrating existing code.
+    pub mod sodiumoxide;
+}
 /// # Constant value definitions
 pub mod constants;
+pub mod contactcard;
+#[cfg(feature = "cose")]
+#[cfg_attr(all(feature = "nightly", doc), doc(cfg(feature = "cose")))]
+pub mod cose;
 pub mod dryocbox;
 pub mod dryocsecretbox;
 pub mod dryocstream;
+pub mod envelope;
+#[cfg(feature = "curve448")]
+#[cfg_attr(all(feature = "nightly", doc), doc(cfg(feature = "curve448")))]
+pub mod ed448;
+pub mod escrow;
+pub mod fork_safety;
+pub mod formats;
 pub mod generichash;
+pub mod guard;
+pub mod hpke;
+#[cfg(feature = "jose")]
+#[cfg_attr(all(feature = "nightly", doc), doc(cfg(feature = "jose")))]
+pub mod jose;
 pub mod kdf;
+pub mod kem;
+pub mod keyedhash;
 pub mod keypair;
+#[cfg(any(feature = "nightly", all(doc, not(doctest))))]
+#[cfg_attr(all(feature = "nightly", doc), doc(cfg(feature = "nightly")))]
+pub mod keystore;
+pub mod kms;
 pub mod kx;
+pub mod libp2p;
+pub mod logfile;
+#[cfg(any(feature = "nightly", all(doc, not(doctest))))]
+#[cfg_attr(all(feature = "nightly", doc), doc(cfg(feature = "nightly")))]
+pub mod loaders;
+pub mod manifest;
+#[cfg(feature = "tower")]
+#[cfg_attr(all(feature = "nightly", doc), doc(cfg(feature = "tower")))]
+pub mod middleware;
+pub mod multiformats;
+pub mod musig;
 pub mod onetimeauth;
+pub mod oprf;
+pub mod pagecrypt;
+pub mod pake;
+pub mod pairing;
+pub mod policy;
+pub mod prelude;
+pub mod privacypass;
 pub mod pwhash;
+#[cfg(feature = "serde")]
+#[cfg_attr(all(feature = "nightly", doc), doc(cfg(feature = "serde")))]
+pub mod redact;
+pub mod reencrypt;
+pub mod replay;
 /// # Random number generation utilities
 pub mod rng;
+pub mod secretcache;
 pub mod sha512;
 pub mod sign;
+#[cfg(any(feature = "nightly", all(doc, not(doctest))))]
+#[cfg_attr(all(feature = "nightly", doc), doc(cfg(feature = "nightly")))]
+pub mod spool;
+pub mod timelock;
+#[cfg(any(feature = "nightly", all(doc, not(doctest))))]
+#[cfg_attr(all(feature = "nightly", doc), doc(cfg(feature = "nightly")))]
+pub mod totp;
+pub mod transcript;
+pub mod treekem;
 /// # Base type definitions
 pub mod types;
 /// # Various utility functions
 pub mod utils;
+#[cfg(feature = "curve448")]
+#[cfg_attr(all(feature = "nightly", doc), doc(cfg(feature = "curve448")))]
+pub mod x448;
+#[cfg(feature = "zeroizing-alloc")]
+#[cfg_attr(all(feature = "nightly", doc), doc(cfg(feature = "zeroizing-alloc")))]
+pub mod zeroizing_alloc;
+
+#[cfg(feature = "global-zeroizing-alloc")]
+#[global_allocator]
+static ALLOCATOR: zeroizing_alloc::ZeroizingAllocator =
+    zeroizing_alloc::ZeroizingAllocator::system();
 
 pub use error::Error;
+#[cfg(feature = "rich-errors")]
+pub use error::ErrorContext;
 
 #[cfg(test)]
 mod tests {
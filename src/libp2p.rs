@@ -0,0 +1,868 @@
+//! # libp2p identities and Noise handshakes
+//!
+//! Helpers for peer-to-peer applications which speak
+//! [libp2p](https://libp2p.io/) protocols, so they can use dryoc's keys
+//! instead of a second cryptography stack:
+//!
+//! * [`PeerId`] derives a libp2p peer ID (such as `12D3KooW...`) from an
+//!   Ed25519 [signing public key](crate::sign::PublicKey), as described in the
+//!   [peer ID
+//!   specification](https://github.com/libp2p/specs/blob/master/peer-ids/peer-ids.md)
+//! * [`NoiseHandshake`] runs the `Noise_XX_25519_ChaChaPoly_SHA256` handshake
+//!   used by libp2p's [Noise
+//!   transport](https://github.com/libp2p/specs/blob/master/noise/README.md),
+//!   authenticating each peer's static key with its identity key, and returns
+//!   a [`NoiseTransport`] for encrypting and decrypting messages afterwards
+//!
+//! The handshake and transport operate on individual Noise messages, and
+//! leave I/O to the caller. On the wire, libp2p prefixes each message with its
+//! length as a 2 byte big-endian integer.
+//!
+//! Only Ed25519 identity keys are supported.
+//!
+//! ## Example
+//!
+//! ```
+//! use dryoc::libp2p::*;
+//! use dryoc::sign::SigningKeyPair;
+//!
+//! let alice_identity = SigningKeyPair::gen_with_defaults();
+//! let bob_identity = SigningKeyPair::gen_with_defaults();
+//!
+//! let mut alice = NoiseHandshake::initiator(&alice_identity).expect("initiator failed");
+//! let mut bob = NoiseHandshake::responder(&bob_identity).expect("responder failed");
+//!
+//! // -> e
+//! bob.read_message(&alice.write_message().expect("write failed"))
+//!     .expect("read failed");
+//! // <- e, ee, s, es
+//! alice
+//!     .read_message(&bob.write_message().expect("write failed"))
+//!     .expect("read failed");
+//! // -> s, se
+//! bob.read_message(&alice.write_message().expect("write failed"))
+//!     .expect("read failed");
+//!
+//! let mut alice = alice.into_transport().expect("handshake incomplete");
+//! let mut bob = bob.into_transport().expect("handshake incomplete");
+//! assert_eq!(
+//!     alice.remote_peer_id(),
+//!     &PeerId::from_public_key(&bob_identity.public_key)
+//! );
+//!
+//! let message = alice.encrypt(b"hello bob").expect("encrypt failed");
+//! assert_eq!(bob.decrypt(&message).expect("decrypt failed"), b"hello bob");
+//! ```
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::str::FromStr;
+
+use sha2::{Digest, Sha256};
+use subtle::ConstantTimeEq;
+use zeroize::Zeroize;
+
+use crate::aead::{chacha20poly1305_ietf_decrypt, chacha20poly1305_ietf_encrypt};
+use crate::classic::crypto_core::crypto_scalarmult;
+use crate::classic::crypto_sign::{crypto_sign_detached, crypto_sign_verify_detached};
+use crate::constants::{
+    CRYPTO_AEAD_XCHACHA20POLY1305_IETF_ABYTES, CRYPTO_SCALARMULT_BYTES, CRYPTO_SIGN_BYTES,
+    CRYPTO_SIGN_PUBLICKEYBYTES, CRYPTO_SIGN_SECRETKEYBYTES, CRYPTO_STREAM_CHACHA20_IETF_NONCEBYTES,
+};
+use crate::error::Error;
+use crate::kx;
+use crate::multiformats::multibase::{self, Base};
+use crate::sign::{PublicKey, Signature, SigningKeyPair};
+use crate::types::*;
+
+/// Protobuf `KeyType` of Ed25519 keys.
+const KEY_TYPE_ED25519: u64 = 1;
+/// Multihash code of the identity hash, used for peer IDs of keys up to 42
+/// bytes long.
+const MULTIHASH_IDENTITY: u8 = 0x00;
+
+const PROTOCOL_NAME: &[u8] = b"Noise_XX_25519_ChaChaPoly_SHA256";
+const STATIC_KEY_SIGNATURE_PREFIX: &[u8] = b"noise-libp2p-static-key:";
+/// Maximum length of a Noise message.
+pub const NOISE_MAX_MESSAGE_BYTES: usize = 65535;
+const HASHLEN: usize = 32;
+const TAGLEN: usize = CRYPTO_AEAD_XCHACHA20POLY1305_IETF_ABYTES;
+const DHLEN: usize = CRYPTO_SCALARMULT_BYTES;
+
+/// Protobuf field numbers of `PublicKey` and `NoiseHandshakePayload`.
+const FIELD_KEY_TYPE: u64 = 1;
+const FIELD_KEY_DATA: u64 = 2;
+const FIELD_IDENTITY_KEY: u64 = 1;
+const FIELD_IDENTITY_SIG: u64 = 2;
+
+const WIRE_VARINT: u64 = 0;
+const WIRE_LEN: u64 = 2;
+
+fn write_varint(output: &mut Vec<u8>, mut n: u64) {
+    while n >= 0x80 {
+        output.push((n as u8) | 0x80);
+        n >>= 7;
+    }
+    output.push(n as u8);
+}
+
+fn write_bytes_field(output: &mut Vec<u8>, field: u64, bytes: &[u8]) {
+    write_varint(output, field << 3 | WIRE_LEN);
+    write_varint(output, bytes.len() as u64);
+    output.extend_from_slice(bytes);
+}
+
+/// A protobuf field value, as far as libp2p's messages need.
+enum Field<'a> {
+    Varint(u64),
+    Bytes(&'a [u8]),
+}
+
+/// Decodes the fields of a protobuf message, returning field numbers and
+/// values in the order they appear.
+fn read_fields(mut input: &[u8]) -> Result<Vec<(u64, Field<'_>)>, Error> {
+    fn varint(input: &mut &[u8]) -> Result<u64, Error> {
+        let mut n = 0u64;
+        for i in 0..10 {
+            let byte = *input
+                .get(i)
+                .ok_or_else(|| dryoc_error!("truncated protobuf varint"))?;
+            n |= ((byte & 0x7f) as u64) << (7 * i);
+            if byte & 0x80 == 0 {
+                *input = &input[i + 1..];
+                return Ok(n);
+            }
+        }
+        Err(dryoc_error!("protobuf varint too long"))
+    }
+
+    let mut fields = Vec::new();
+    while !input.is_empty() {
+        let key = varint(&mut input)?;
+        let value = match key & 0x7 {
+            WIRE_VARINT => Field::Varint(varint(&mut input)?),
+            WIRE_LEN => {
+                let len = varint(&mut input)?;
+                if len > input.len() as u64 {
+                    return Err(dryoc_error!("truncated protobuf field"));
+                }
+                let (bytes, rest) = input.split_at(len as usize);
+                input = rest;
+                Field::Bytes(bytes)
+            }
+            _ => return Err(dryoc_error!("unsupported protobuf wire type")),
+        };
+        fields.push((key >> 3, value));
+    }
+    Ok(fields)
+}
+
+/// A libp2p peer ID, derived from an Ed25519 public key.
+///
+/// The peer ID is the identity multihash of the protobuf encoded public key,
+/// and its string form is base58btc, without a multibase prefix.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PeerId {
+    public_key: PublicKey,
+}
+
+impl PeerId {
+    /// Returns the peer ID of the Ed25519 `public_key`.
+    pub fn from_public_key<PublicKey: ByteArray<CRYPTO_SIGN_PUBLICKEYBYTES>>(
+        public_key: &PublicKey,
+    ) -> Self {
+        Self {
+            public_key: (*public_key.as_array()).into(),
+        }
+    }
+
+    /// Returns the public key this peer ID was derived from.
+    pub fn public_key(&self) -> &PublicKey {
+        &self.public_key
+    }
+
+    /// Returns the protobuf encoding of the public key, as used in libp2p's
+    /// messages.
+    pub fn to_protobuf(&self) -> Vec<u8> {
+        let mut output = Vec::with_capacity(4 + CRYPTO_SIGN_PUBLICKEYBYTES);
+        write_varint(&mut output, FIELD_KEY_TYPE << 3 | WIRE_VARINT);
+        write_varint(&mut output, KEY_TYPE_ED25519);
+        write_bytes_field(&mut output, FIELD_KEY_DATA, self.public_key.as_slice());
+        output
+    }
+
+    /// Decodes a protobuf encoded public key. Returns an error if it isn't an
+    /// Ed25519 key.
+    pub fn from_protobuf(bytes: &[u8]) -> Result<Self, Error> {
+        let mut key_type = None;
+        let mut data = None;
+        for (field, value) in read_fields(bytes)? {
+            match (field, value) {
+                (FIELD_KEY_TYPE, Field::Varint(n)) => key_type = Some(n),
+                (FIELD_KEY_DATA, Field::Bytes(bytes)) => data = Some(bytes),
+                _ => return Err(dryoc_error!("invalid public key encoding")),
+            }
+        }
+        if key_type != Some(KEY_TYPE_ED25519) {
+            return Err(dryoc_error!("only Ed25519 public keys are supported"));
+        }
+        let data = data.ok_or_else(|| dryoc_error!("missing public key data"))?;
+        Ok(Self {
+            public_key: PublicKey::try_from(data)?,
+        })
+    }
+
+    /// Returns the binary form of this peer ID: an identity multihash of the
+    /// protobuf encoded public key.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let protobuf = self.to_protobuf();
+        let mut output = Vec::with_capacity(2 + protobuf.len());
+        output.push(MULTIHASH_IDENTITY);
+        output.push(protobuf.len() as u8);
+        output.extend_from_slice(&protobuf);
+        output
+    }
+
+    /// Decodes a peer ID from its binary form.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        match bytes {
+            [MULTIHASH_IDENTITY, len, protobuf @ ..] if *len as usize == protobuf.len() => {
+                Self::from_protobuf(protobuf)
+            }
+            _ => Err(dryoc_error!(
+                "peer ID must be an identity multihash of an Ed25519 key"
+            )),
+        }
+    }
+}
+
+impl Hash for PeerId {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.public_key.as_slice().hash(state)
+    }
+}
+
+impl fmt::Display for PeerId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Strip the multibase prefix
+        f.write_str(&multibase::encode(Base::Base58Btc, &self.to_bytes())[1..])
+    }
+}
+
+impl FromStr for PeerId {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (_, bytes) = multibase::decode(&format!("{}{}", Base::Base58Btc.prefix(), s))?;
+        Self::from_bytes(&bytes)
+    }
+}
+
+fn dh(secret_key: &[u8; DHLEN], public_key: &[u8; DHLEN]) -> Result<[u8; DHLEN], Error> {
+    let mut shared_secret = [0u8; DHLEN];
+    crypto_scalarmult(&mut shared_secret, secret_key, public_key);
+    if shared_secret.ct_eq(&[0u8; DHLEN]).unwrap_u8() == 1 {
+        return Err(dryoc_error!("invalid public key"));
+    }
+    Ok(shared_secret)
+}
+
+/// A Noise `CipherState`.
+#[derive(Zeroize)]
+#[zeroize(drop)]
+struct CipherState {
+    key: Option<[u8; 32]>,
+    nonce: u64,
+}
+
+impl CipherState {
+    fn new(key: Option<[u8; 32]>) -> Self {
+        Self { key, nonce: 0 }
+    }
+
+    fn next_nonce(&mut self) -> Result<[u8; CRYPTO_STREAM_CHACHA20_IETF_NONCEBYTES], Error> {
+        // The maximum nonce is reserved
+        if self.nonce == u64::MAX {
+            return Err(dryoc_error!("nonces exhausted"));
+        }
+        let mut nonce = [0u8; CRYPTO_STREAM_CHACHA20_IETF_NONCEBYTES];
+        nonce[4..].copy_from_slice(&self.nonce.to_le_bytes());
+        self.nonce += 1;
+        Ok(nonce)
+    }
+
+    fn encrypt_with_ad(&mut self, ad: &[u8], plaintext: &[u8]) -> Result<Vec<u8>, Error> {
+        match self.key {
+            Some(key) => {
+                let nonce = self.next_nonce()?;
+                Ok(chacha20poly1305_ietf_encrypt(plaintext, ad, &nonce, &key))
+            }
+            None => Ok(plaintext.to_vec()),
+        }
+    }
+
+    fn decrypt_with_ad(&mut self, ad: &[u8], ciphertext: &[u8]) -> Result<Vec<u8>, Error> {
+        match self.key {
+            Some(key) => {
+                let nonce = self.next_nonce()?;
+                let plaintext = chacha20poly1305_ietf_decrypt(ciphertext, ad, &nonce, &key);
+                if plaintext.is_err() {
+                    // The nonce is only consumed by successful decryption
+                    self.nonce -= 1;
+                }
+                plaintext
+            }
+            None => Ok(ciphertext.to_vec()),
+        }
+    }
+}
+
+/// A Noise `SymmetricState`.
+#[derive(Zeroize)]
+#[zeroize(drop)]
+struct SymmetricState {
+    cipher: CipherState,
+    ck: [u8; HASHLEN],
+    h: [u8; HASHLEN],
+}
+
+impl SymmetricState {
+    fn new() -> Self {
+        // The protocol name is exactly HASHLEN bytes, so it's used as is
+        let mut h = [0u8; HASHLEN];
+        h.copy_from_slice(PROTOCOL_NAME);
+        let mut state = Self {
+            cipher: CipherState::new(None),
+            ck: h,
+            h,
+        };
+        // Empty prologue
+        state.mix_hash(&[]);
+        state
+    }
+
+    fn hkdf(&self, ikm: &[u8]) -> ([u8; HASHLEN], [u8; HASHLEN]) {
+        let mut prk = crate::hkdf::extract::<Sha256>(&self.ck, ikm);
+        let mut output = [0u8; 2 * HASHLEN];
+        crate::hkdf::expand::<Sha256>(&mut output, &prk, &[])
+            .expect("output length is less than the maximum");
+        prk.as_mut_slice().zeroize();
+
+        let (mut first, mut second) = ([0u8; HASHLEN], [0u8; HASHLEN]);
+        first.copy_from_slice(&output[..HASHLEN]);
+        second.copy_from_slice(&output[HASHLEN..]);
+        output.zeroize();
+        (first, second)
+    }
+
+    fn mix_key(&mut self, mut ikm: [u8; DHLEN]) {
+        let (ck, key) = self.hkdf(&ikm);
+        ikm.zeroize();
+        self.ck = ck;
+        self.cipher = CipherState::new(Some(key));
+    }
+
+    fn mix_hash(&mut self, data: &[u8]) {
+        self.h = Sha256::new()
+            .chain_update(self.h)
+            .chain_update(data)
+            .finalize()
+            .into();
+    }
+
+    fn encrypt_and_hash(&mut self, plaintext: &[u8]) -> Result<Vec<u8>, Error> {
+        let ciphertext = self.cipher.encrypt_with_ad(&self.h, plaintext)?;
+        self.mix_hash(&ciphertext);
+        Ok(ciphertext)
+    }
+
+    fn decrypt_and_hash(&mut self, ciphertext: &[u8]) -> Result<Vec<u8>, Error> {
+        let plaintext = self.cipher.decrypt_with_ad(&self.h, ciphertext)?;
+        self.mix_hash(ciphertext);
+        Ok(plaintext)
+    }
+
+    fn split(&self) -> (CipherState, CipherState) {
+        let (first, second) = self.hkdf(&[]);
+        (
+            CipherState::new(Some(first)),
+            CipherState::new(Some(second)),
+        )
+    }
+}
+
+/// State of a libp2p Noise XX handshake.
+///
+/// The initiator writes the first and third messages, and reads the second.
+/// The responder reads the first and third messages, and writes the second.
+/// Once all three messages have been exchanged, call
+/// [`NoiseHandshake::into_transport`] to get the transport state.
+///
+/// Refer to [crate::libp2p] for sample usage.
+pub struct NoiseHandshake {
+    initiator: bool,
+    message: usize,
+    state: SymmetricState,
+    static_keypair: kx::KeyPair,
+    ephemeral_keypair: Option<kx::KeyPair>,
+    remote_static: Option<[u8; DHLEN]>,
+    remote_ephemeral: Option<[u8; DHLEN]>,
+    remote_peer_id: Option<PeerId>,
+    payload: Vec<u8>,
+}
+
+impl NoiseHandshake {
+    /// Returns a new handshake for the peer which opened the connection, with
+    /// the identity `keypair`.
+    pub fn initiator<
+        PublicKey: ByteArray<CRYPTO_SIGN_PUBLICKEYBYTES> + Zeroize,
+        SecretKey: ByteArray<CRYPTO_SIGN_SECRETKEYBYTES> + Zeroize,
+    >(
+        keypair: &SigningKeyPair<PublicKey, SecretKey>,
+    ) -> Result<Self, Error> {
+        Self::new(true, keypair)
+    }
+
+    /// Returns a new handshake for the peer which accepted the connection,
+    /// with the identity `keypair`.
+    pub fn responder<
+        PublicKey: ByteArray<CRYPTO_SIGN_PUBLICKEYBYTES> + Zeroize,
+        SecretKey: ByteArray<CRYPTO_SIGN_SECRETKEYBYTES> + Zeroize,
+    >(
+        keypair: &SigningKeyPair<PublicKey, SecretKey>,
+    ) -> Result<Self, Error> {
+        Self::new(false, keypair)
+    }
+
+    fn new<
+        PublicKey: ByteArray<CRYPTO_SIGN_PUBLICKEYBYTES> + Zeroize,
+        SecretKey: ByteArray<CRYPTO_SIGN_SECRETKEYBYTES> + Zeroize,
+    >(
+        initiator: bool,
+        keypair: &SigningKeyPair<PublicKey, SecretKey>,
+    ) -> Result<Self, Error> {
+        let static_keypair = kx::KeyPair::gen();
+
+        // The static key is only used for this handshake, so the payload
+        // authenticating it with the identity key can be computed up front
+        let mut message = STATIC_KEY_SIGNATURE_PREFIX.to_vec();
+        message.extend_from_slice(static_keypair.public_key.as_slice());
+        let mut signature = Signature::new_byte_array();
+        crypto_sign_detached(
+            signature.as_mut_array(),
+            &message,
+            keypair.secret_key.as_array(),
+        )?;
+
+        let mut payload = Vec::new();
+        write_bytes_field(
+            &mut payload,
+            FIELD_IDENTITY_KEY,
+            &PeerId::from_public_key(&keypair.public_key).to_protobuf(),
+        );
+        write_bytes_field(&mut payload, FIELD_IDENTITY_SIG, signature.as_slice());
+
+        Ok(Self {
+            initiator,
+            message: 0,
+            state: SymmetricState::new(),
+            static_keypair,
+            ephemeral_keypair: None,
+            remote_static: None,
+            remote_ephemeral: None,
+            remote_peer_id: None,
+            payload,
+        })
+    }
+
+    /// Returns true if it's this peer's turn to write a message.
+    pub fn is_my_turn(&self) -> bool {
+        !self.is_finished() && (self.message % 2 == 0) == self.initiator
+    }
+
+    /// Returns true once all handshake messages have been exchanged.
+    pub fn is_finished(&self) -> bool {
+        self.message == 3
+    }
+
+    /// Returns the remote peer's ID, once it has been authenticated.
+    pub fn remote_peer_id(&self) -> Option<&PeerId> {
+        self.remote_peer_id.as_ref()
+    }
+
+    /// Writes the next handshake message, which should be sent to the remote
+    /// peer.
+    pub fn write_message(&mut self) -> Result<Vec<u8>, Error> {
+        if !self.is_my_turn() {
+            return Err(dryoc_error!("not expecting to write a handshake message"));
+        }
+
+        let mut output = Vec::new();
+        match self.message {
+            // -> e
+            0 => {
+                self.write_ephemeral(&mut output);
+                output.extend(self.state.encrypt_and_hash(&[])?);
+            }
+            // <- e, ee, s, es
+            1 => {
+                self.write_ephemeral(&mut output);
+                self.mix_ee()?;
+                self.write_static(&mut output)?;
+                self.mix_static()?;
+                output.extend(self.state.encrypt_and_hash(&self.payload)?);
+            }
+            // -> s, se
+            _ => {
+                self.write_static(&mut output)?;
+                self.mix_static()?;
+                output.extend(self.state.encrypt_and_hash(&self.payload)?);
+            }
+        }
+        self.message += 1;
+
+        Ok(output)
+    }
+
+    /// Reads the next handshake message, received from the remote peer. If
+    /// the message contains the remote peer's identity, it's authenticated
+    /// before returning.
+    pub fn read_message(&mut self, message: &[u8]) -> Result<(), Error> {
+        if self.is_finished() || self.is_my_turn() {
+            return Err(dryoc_error!("not expecting to read a handshake message"));
+        }
+        if message.len() > NOISE_MAX_MESSAGE_BYTES {
+            return Err(dryoc_error!("handshake message too long"));
+        }
+
+        let mut message = message;
+        match self.message {
+            // -> e
+            0 => {
+                self.read_ephemeral(&mut message)?;
+                self.state.decrypt_and_hash(message)?;
+            }
+            // <- e, ee, s, es
+            1 => {
+                self.read_ephemeral(&mut message)?;
+                self.mix_ee()?;
+                self.read_static(&mut message)?;
+                self.mix_static()?;
+                let payload = self.state.decrypt_and_hash(message)?;
+                self.verify_payload(&payload)?;
+            }
+            // -> s, se
+            _ => {
+                self.read_static(&mut message)?;
+                self.mix_static()?;
+                let payload = self.state.decrypt_and_hash(message)?;
+                self.verify_payload(&payload)?;
+            }
+        }
+        self.message += 1;
+
+        Ok(())
+    }
+
+    /// Consumes the finished handshake, returning the transport state.
+    pub fn into_transport(self) -> Result<NoiseTransport, Error> {
+        let remote_peer_id = match (&self.remote_peer_id, self.is_finished()) {
+            (Some(remote_peer_id), true) => remote_peer_id.clone(),
+            _ => return Err(dryoc_error!("handshake isn't finished")),
+        };
+        let (initiator_cipher, responder_cipher) = self.state.split();
+        let (send, receive) = if self.initiator {
+            (initiator_cipher, responder_cipher)
+        } else {
+            (responder_cipher, initiator_cipher)
+        };
+
+        Ok(NoiseTransport {
+            send,
+            receive,
+            handshake_hash: self.state.h,
+            remote_peer_id,
+        })
+    }
+
+    fn write_ephemeral(&mut self, output: &mut Vec<u8>) {
+        let keypair = kx::KeyPair::gen();
+        output.extend_from_slice(keypair.public_key.as_slice());
+        self.state.mix_hash(keypair.public_key.as_slice());
+        self.ephemeral_keypair = Some(keypair);
+    }
+
+    fn write_static(&mut self, output: &mut Vec<u8>) -> Result<(), Error> {
+        let public_key = *self.static_keypair.public_key.as_array();
+        output.extend(self.state.encrypt_and_hash(&public_key)?);
+        Ok(())
+    }
+
+    fn read_ephemeral(&mut self, message: &mut &[u8]) -> Result<(), Error> {
+        if message.len() < DHLEN {
+            return Err(dryoc_error!("handshake message too short"));
+        }
+        let (public_key, rest) = message.split_at(DHLEN);
+        self.state.mix_hash(public_key);
+        self.remote_ephemeral = Some(*ByteArray::as_array(public_key));
+        *message = rest;
+        Ok(())
+    }
+
+    fn read_static(&mut self, message: &mut &[u8]) -> Result<(), Error> {
+        if message.len() < DHLEN + TAGLEN {
+            return Err(dryoc_error!("handshake message too short"));
+        }
+        let (ciphertext, rest) = message.split_at(DHLEN + TAGLEN);
+        let public_key = self.state.decrypt_and_hash(ciphertext)?;
+        self.remote_static = Some(*ByteArray::as_array(&public_key[..]));
+        *message = rest;
+        Ok(())
+    }
+
+    /// Mixes the `ee` DH result into the state.
+    fn mix_ee(&mut self) -> Result<(), Error> {
+        let shared_secret = match (&self.ephemeral_keypair, &self.remote_ephemeral) {
+            (Some(ephemeral_keypair), Some(remote_ephemeral)) => {
+                dh(ephemeral_keypair.secret_key.as_array(), remote_ephemeral)?
+            }
+            _ => return Err(dryoc_error!("missing handshake key")),
+        };
+        self.state.mix_key(shared_secret);
+        Ok(())
+    }
+
+    /// Mixes the `es` DH result into the state while processing the second
+    /// message, or the `se` result while processing the third message. Both
+    /// combine the static key of the peer which wrote the message with the
+    /// ephemeral key of the other peer.
+    fn mix_static(&mut self) -> Result<(), Error> {
+        let writer_is_initiator = self.message == 2;
+        let shared_secret = if writer_is_initiator == self.initiator {
+            match &self.remote_ephemeral {
+                Some(remote_ephemeral) => {
+                    dh(self.static_keypair.secret_key.as_array(), remote_ephemeral)?
+                }
+                None => return Err(dryoc_error!("missing handshake key")),
+            }
+        } else {
+            match (&self.ephemeral_keypair, &self.remote_static) {
+                (Some(ephemeral_keypair), Some(remote_static)) => {
+                    dh(ephemeral_keypair.secret_key.as_array(), remote_static)?
+                }
+                _ => return Err(dryoc_error!("missing handshake key")),
+            }
+        };
+        self.state.mix_key(shared_secret);
+        Ok(())
+    }
+
+    fn verify_payload(&mut self, payload: &[u8]) -> Result<(), Error> {
+        let mut identity_key = None;
+        let mut identity_sig = None;
+        for (field, value) in read_fields(payload)? {
+            match (field, value) {
+                (FIELD_IDENTITY_KEY, Field::Bytes(bytes)) => identity_key = Some(bytes),
+                (FIELD_IDENTITY_SIG, Field::Bytes(bytes)) => identity_sig = Some(bytes),
+                // Ignore extensions and unknown fields
+                _ => (),
+            }
+        }
+        let peer_id = PeerId::from_protobuf(
+            identity_key.ok_or_else(|| dryoc_error!("missing identity key"))?,
+        )?;
+        let identity_sig =
+            identity_sig.ok_or_else(|| dryoc_error!("missing identity signature"))?;
+        validate!(
+            CRYPTO_SIGN_BYTES,
+            CRYPTO_SIGN_BYTES,
+            identity_sig.len(),
+            "identity_sig"
+        );
+
+        let remote_static = self
+            .remote_static
+            .ok_or_else(|| dryoc_error!("missing remote static key"))?;
+        let mut message = STATIC_KEY_SIGNATURE_PREFIX.to_vec();
+        message.extend_from_slice(&remote_static);
+        crypto_sign_verify_detached(
+            ByteArray::as_array(identity_sig),
+            &message,
+            peer_id.public_key.as_array(),
+        )?;
+
+        self.remote_peer_id = Some(peer_id);
+        Ok(())
+    }
+}
+
+/// Transport state after a libp2p Noise handshake, for encrypting and
+/// decrypting messages in each direction.
+///
+/// Refer to [crate::libp2p] for sample usage.
+pub struct NoiseTransport {
+    send: CipherState,
+    receive: CipherState,
+    handshake_hash: [u8; HASHLEN],
+    remote_peer_id: PeerId,
+}
+
+impl NoiseTransport {
+    /// Returns the authenticated ID of the remote peer.
+    pub fn remote_peer_id(&self) -> &PeerId {
+        &self.remote_peer_id
+    }
+
+    /// Returns the handshake hash, which uniquely identifies this session, and
+    /// can be used for channel binding.
+    pub fn handshake_hash(&self) -> &[u8; HASHLEN] {
+        &self.handshake_hash
+    }
+
+    /// Encrypts `plaintext` as the next message to the remote peer. The
+    /// encrypted message can be at most [`NOISE_MAX_MESSAGE_BYTES`] long.
+    pub fn encrypt(&mut self, plaintext: &[u8]) -> Result<Vec<u8>, Error> {
+        if plaintext.len() > NOISE_MAX_MESSAGE_BYTES - TAGLEN {
+            return Err(dryoc_error!(format!(
+                "plaintext length {} greater than maximum {}",
+                plaintext.len(),
+                NOISE_MAX_MESSAGE_BYTES - TAGLEN
+            )));
+        }
+        self.send.encrypt_with_ad(&[], plaintext)
+    }
+
+    /// Decrypts the next message from the remote peer. Messages must be
+    /// decrypted in the order they were encrypted.
+    pub fn decrypt(&mut self, ciphertext: &[u8]) -> Result<Vec<u8>, Error> {
+        validate!(
+            TAGLEN,
+            NOISE_MAX_MESSAGE_BYTES,
+            ciphertext.len(),
+            "ciphertext"
+        );
+        self.receive.decrypt_with_ad(&[], ciphertext)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn handshake(
+        alice_identity: &SigningKeyPair<PublicKey, crate::sign::SecretKey>,
+        bob_identity: &SigningKeyPair<PublicKey, crate::sign::SecretKey>,
+    ) -> (NoiseTransport, NoiseTransport) {
+        let mut alice = NoiseHandshake::initiator(alice_identity).expect("initiator");
+        let mut bob = NoiseHandshake::responder(bob_identity).expect("responder");
+
+        assert!(alice.is_my_turn());
+        assert!(bob.write_message().is_err());
+        bob.read_message(&alice.write_message().expect("write"))
+            .expect("read");
+        let message = bob.write_message().expect("write");
+        assert_eq!(
+            message.len(),
+            DHLEN + DHLEN + TAGLEN + 2 + 36 + 2 + 64 + TAGLEN
+        );
+        alice.read_message(&message).expect("read");
+        assert_eq!(
+            alice.remote_peer_id(),
+            Some(&PeerId::from_public_key(&bob_identity.public_key))
+        );
+        bob.read_message(&alice.write_message().expect("write"))
+            .expect("read");
+        assert!(alice.is_finished() && bob.is_finished());
+
+        (
+            alice.into_transport().expect("transport"),
+            bob.into_transport().expect("transport"),
+        )
+    }
+
+    #[test]
+    fn test_peer_id() {
+        // https://github.com/libp2p/specs/blob/master/peer-ids/peer-ids.md#test-vectors
+        let seed = hex::decode("7e0830617c4a7de83925dfb2694556b12936c477a0e1feb2e148ec9da60fee7d")
+            .unwrap();
+        let keypair: SigningKeyPair<PublicKey, crate::sign::SecretKey> =
+            SigningKeyPair::from_seed(&StackByteArray::<32>::try_from(&seed[..]).unwrap());
+        let peer_id = PeerId::from_public_key(&keypair.public_key);
+        assert_eq!(
+            hex::encode(peer_id.to_protobuf()),
+            "080112201ed1e8fae2c4a144b8be8fd4b47bf3d3b34b871c3cacf6010f0e42d474fce27e"
+        );
+        assert_eq!(
+            peer_id.to_string(),
+            "12D3KooWBtg3aaRMjxwedh83aGiUkwSxDwUZkzuJcfaqUmo7R3pq"
+        );
+        assert_eq!(
+            "12D3KooWBtg3aaRMjxwedh83aGiUkwSxDwUZkzuJcfaqUmo7R3pq"
+                .parse::<PeerId>()
+                .expect("parse"),
+            peer_id
+        );
+        assert_eq!(
+            PeerId::from_bytes(&peer_id.to_bytes()).expect("decode"),
+            peer_id
+        );
+
+        // RSA and secp256k1 keys aren't supported
+        assert!(PeerId::from_protobuf(&[0x08, 0x00, 0x12, 0x00]).is_err());
+        assert!(
+            "QmYyQSo1c1Ym7orWxLYvCrM2EmxFTANf8wXmmE7DWjhx5N"
+                .parse::<PeerId>()
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_noise_handshake() {
+        let alice_identity = SigningKeyPair::gen_with_defaults();
+        let bob_identity = SigningKeyPair::gen_with_defaults();
+        let (mut alice, mut bob) = handshake(&alice_identity, &bob_identity);
+
+        assert_eq!(alice.handshake_hash(), bob.handshake_hash());
+        assert_eq!(
+            bob.remote_peer_id(),
+            &PeerId::from_public_key(&alice_identity.public_key)
+        );
+
+        for i in 0..10 {
+            let message = vec![i as u8; i * 100];
+            let encrypted = alice.encrypt(&message).expect("encrypt");
+            assert_eq!(bob.decrypt(&encrypted).expect("decrypt"), message);
+            let encrypted = bob.encrypt(&message).expect("encrypt");
+            assert_eq!(alice.decrypt(&encrypted).expect("decrypt"), message);
+        }
+
+        // replayed, reordered, and tampered messages
+        let first = alice.encrypt(b"first").expect("encrypt");
+        let second = alice.encrypt(b"second").expect("encrypt");
+        assert!(bob.decrypt(&second).is_err());
+        let mut tampered = first.clone();
+        tampered[0] ^= 1;
+        assert!(bob.decrypt(&tampered).is_err());
+        assert_eq!(bob.decrypt(&first).expect("decrypt"), b"first");
+        assert!(bob.decrypt(&first).is_err());
+        assert_eq!(bob.decrypt(&second).expect("decrypt"), b"second");
+
+        assert!(alice.encrypt(&vec![0u8; NOISE_MAX_MESSAGE_BYTES]).is_err());
+    }
+
+    #[test]
+    fn test_noise_handshake_tampering() {
+        let alice_identity = SigningKeyPair::gen_with_defaults();
+        let bob_identity = SigningKeyPair::gen_with_defaults();
+
+        for position in [0, DHLEN, DHLEN * 2 + 8, DHLEN * 2 + TAGLEN + 20] {
+            let mut alice = NoiseHandshake::initiator(&alice_identity).expect("initiator");
+            let mut bob = NoiseHandshake::responder(&bob_identity).expect("responder");
+            bob.read_message(&alice.write_message().expect("write"))
+                .expect("read");
+            let mut message = bob.write_message().expect("write");
+            message[position] ^= 1;
+            assert!(alice.read_message(&message).is_err());
+            assert!(alice.into_transport().is_err());
+        }
+    }
+}
@@ -0,0 +1,365 @@
+//! # Secret loaders
+//!
+//! Helpers for loading secrets from the process environment directly into
+//! locked memory, for deployments which inject keys with environment
+//! variables (as is common with 12-factor apps).
+//!
+//! [`secret_from_env`] reads and decodes the variable, copies the decoded
+//! secret into locked memory, wipes any intermediate buffers, and removes the
+//! variable from the environment. On Linux, the variable's value is also
+//! overwritten in place before it's removed, which scrubs it from
+//! `/proc/<pid>/environ`.
+//!
+//! Modifying the environment isn't thread-safe, so secrets should be loaded
+//! early, before any other threads are spawned.
+//!
+//! For secrets stored in files or passed on stdin, [`SecretReader`] reads
+//! from any [`Read`] source directly into locked memory.
+//!
+//! ## Example
+//!
+//! ```
+//! use dryoc::dryocsecretbox::protected::*;
+//! use dryoc::dryocsecretbox::DryocSecretBox;
+//! use dryoc::loaders::*;
+//!
+//! # std::env::set_var(
+//! #     "MY_APP_SECRET_KEY",
+//! #     "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f",
+//! # );
+//! let secret_key: Locked<Key> =
+//!     secret_array_from_env("MY_APP_SECRET_KEY", SecretEncoding::Hex).expect("load failed");
+//! assert!(std::env::var_os("MY_APP_SECRET_KEY").is_none());
+//!
+//! let nonce = Nonce::gen_locked().expect("nonce failed");
+//! let sealed: LockedBox = DryocSecretBox::encrypt(b"hello", &nonce, &secret_key);
+//! ```
+//!
+//! Reading a key file, or a secret piped in on stdin:
+//!
+//! ```
+//! use dryoc::dryocsecretbox::protected::*;
+//! use dryoc::loaders::*;
+//!
+//! # let key_file: &[u8] = &[7u8; 32];
+//! // let key_file = std::fs::File::open("secret.key").expect("open failed");
+//! let secret_key: Locked<Key> = SecretReader::new(key_file)
+//!     .read_secret_array()
+//!     .expect("read failed");
+//!
+//! # let stdin: &[u8] = b"correct horse battery staple\n";
+//! // let stdin = std::io::stdin().lock();
+//! let passphrase = SecretReader::new(stdin).read_secret().expect("read failed");
+//! assert_eq!(passphrase.as_slice(), b"correct horse battery staple");
+//! ```
+
+use std::io::{ErrorKind, Read};
+
+use zeroize::Zeroize;
+
+use crate::error::Error;
+use crate::protected::*;
+
+/// Encoding of a secret stored in an environment variable.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SecretEncoding {
+    /// Hexadecimal, upper or lower case
+    Hex,
+    /// Base64 with the standard alphabet, padding optional
+    Base64,
+    /// The raw bytes of the variable's value
+    Raw,
+}
+
+/// Loads the secret stored in the environment variable `var` with
+/// `encoding`, decoding it directly into locked memory, and removes the
+/// variable from the environment.
+///
+/// Refer to [crate::loaders] for details.
+pub fn secret_from_env(var: &str, encoding: SecretEncoding) -> Result<LockedBytes, Error> {
+    let mut value = take_env(var)?;
+    let result = match encoding {
+        SecretEncoding::Hex => as_str(&value).and_then(LockedBytes::from_hex),
+        SecretEncoding::Base64 => as_str(&value).and_then(LockedBytes::from_base64),
+        SecretEncoding::Raw => HeapBytes::from_slice_into_locked(&value),
+    };
+    value.zeroize();
+    result
+}
+
+/// Loads the fixed-length secret stored in the environment variable `var`
+/// with `encoding`, decoding it directly into a locked byte array, and removes
+/// the variable from the environment. Returns an error if the decoded length
+/// doesn't match `LENGTH`.
+///
+/// Refer to [crate::loaders] for details.
+pub fn secret_array_from_env<const LENGTH: usize>(
+    var: &str,
+    encoding: SecretEncoding,
+) -> Result<Locked<HeapByteArray<LENGTH>>, Error> {
+    let mut value = take_env(var)?;
+    let result = match encoding {
+        SecretEncoding::Hex => as_str(&value).and_then(Locked::<HeapByteArray<LENGTH>>::from_hex),
+        SecretEncoding::Base64 => {
+            as_str(&value).and_then(Locked::<HeapByteArray<LENGTH>>::from_base64)
+        }
+        SecretEncoding::Raw => HeapByteArray::<LENGTH>::from_slice_into_locked(&value),
+    };
+    value.zeroize();
+    result
+}
+
+/// Default chunk size for [`SecretReader`], in bytes.
+pub const SECRET_READER_CHUNK_SIZE: usize = 64;
+/// Default maximum secret length for [`SecretReader`], in bytes.
+pub const SECRET_READER_MAX_LENGTH: usize = 64 * 1024;
+
+/// Reads a secret from a [`Read`] source, such as a key file or stdin,
+/// directly into locked memory.
+///
+/// Data is read in chunks of at most
+/// [`with_chunk_size`](SecretReader::with_chunk_size) bytes straight into a
+/// locked buffer, so the secret is never held in unlocked memory by this
+/// reader. When the buffer has to grow, the secret is copied into a new
+/// locked region, and the old region is zeroized and unlocked. Trailing
+/// newlines (`\n` or `\r\n`) are trimmed by default, as most editors and
+/// `echo` add one.
+///
+/// Any buffering done by the source itself, such as by
+/// [`BufReader`](std::io::BufReader) or [`Stdin`](std::io::Stdin), is outside
+/// of this reader's control.
+pub struct SecretReader<R: Read> {
+    reader: R,
+    chunk_size: usize,
+    max_length: usize,
+    trim_newlines: bool,
+}
+
+impl<R: Read> SecretReader<R> {
+    /// Returns a new reader for `reader`, with the default chunk size and
+    /// maximum length, which trims trailing newlines.
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            chunk_size: SECRET_READER_CHUNK_SIZE,
+            max_length: SECRET_READER_MAX_LENGTH,
+            trim_newlines: true,
+        }
+    }
+
+    /// Sets the maximum number of bytes read from the source at once.
+    pub fn with_chunk_size(self, chunk_size: usize) -> Self {
+        Self {
+            chunk_size: chunk_size.max(1),
+            ..self
+        }
+    }
+
+    /// Sets the maximum length of the secret, before newlines are trimmed.
+    /// Reading a longer secret returns an error.
+    pub fn with_max_length(self, max_length: usize) -> Self {
+        Self { max_length, ..self }
+    }
+
+    /// Sets whether trailing newlines are trimmed from the secret.
+    pub fn with_trim_newlines(self, trim_newlines: bool) -> Self {
+        Self {
+            trim_newlines,
+            ..self
+        }
+    }
+
+    /// Reads the source until EOF, returning the secret in locked memory.
+    pub fn read_secret(mut self) -> Result<LockedBytes, Error> {
+        let mut secret = HeapBytes::new_locked()?;
+        let mut len = 0;
+        loop {
+            if len == self.max_length {
+                // make sure the source is exhausted
+                let mut probe = [0u8; 1];
+                let read = read_retrying(&mut self.reader, &mut probe);
+                probe.zeroize();
+                match read? {
+                    0 => break,
+                    _ => {
+                        return Err(dryoc_error!(format!(
+                            "secret is longer than {} bytes",
+                            self.max_length
+                        )));
+                    }
+                }
+            }
+
+            let chunk = self.chunk_size.min(self.max_length - len);
+            if secret.len() < len + chunk {
+                let capacity = (secret.len() * 2).max(len + chunk).min(self.max_length);
+                secret.resize(capacity, 0);
+            }
+            match read_retrying(
+                &mut self.reader,
+                &mut secret.as_mut_slice()[len..len + chunk],
+            )? {
+                0 => break,
+                n => len += n,
+            }
+        }
+
+        if self.trim_newlines {
+            while len > 0 && matches!(secret.as_slice()[len - 1], b'\n' | b'\r') {
+                len -= 1;
+            }
+        }
+        secret.resize(len, 0);
+        Ok(secret)
+    }
+
+    /// Reads the source until EOF, returning the secret in a locked byte
+    /// array. Returns an error if the secret's length doesn't match `LENGTH`.
+    pub fn read_secret_array<const LENGTH: usize>(
+        self,
+    ) -> Result<Locked<HeapByteArray<LENGTH>>, Error> {
+        let secret = self.with_max_length(LENGTH + 2).read_secret()?;
+        if secret.len() != LENGTH {
+            return Err(dryoc_error!(format!(
+                "secret length {} doesn't match expected length {}",
+                secret.len(),
+                LENGTH
+            )));
+        }
+        HeapByteArray::<LENGTH>::from_slice_into_locked(secret.as_slice())
+    }
+}
+
+fn read_retrying<R: Read>(reader: &mut R, buf: &mut [u8]) -> Result<usize, Error> {
+    loop {
+        match reader.read(buf) {
+            Err(err) if err.kind() == ErrorKind::Interrupted => continue,
+            result => return Ok(result?),
+        }
+    }
+}
+
+fn as_str(value: &[u8]) -> Result<&str, Error> {
+    std::str::from_utf8(value).map_err(|_| dryoc_error!("secret is not valid UTF-8"))
+}
+
+/// Reads `var`, scrubs its value from the environment, and removes it.
+fn take_env(var: &str) -> Result<Vec<u8>, Error> {
+    let value = std::env::var_os(var)
+        .ok_or_else(|| dryoc_error!(format!("environment variable {} is not set", var)))?;
+
+    #[cfg(unix)]
+    let value = {
+        use std::os::unix::ffi::OsStringExt;
+        value.into_vec()
+    };
+    #[cfg(not(unix))]
+    let value = value
+        .into_string()
+        .map(String::into_bytes)
+        .map_err(|_| dryoc_error!("secret is not valid unicode"));
+
+    #[cfg(target_os = "linux")]
+    scrub_environ(var);
+    std::env::remove_var(var);
+
+    #[cfg(unix)]
+    return Ok(value);
+    #[cfg(not(unix))]
+    return value;
+}
+
+/// Overwrites the value of `var` in the process environment block with
+/// zeros, so that it doesn't linger in memory (or in `/proc/<pid>/environ`)
+/// after the variable is removed.
+#[cfg(target_os = "linux")]
+fn scrub_environ(var: &str) {
+    extern "C" {
+        static mut environ: *mut *mut std::os::raw::c_char;
+    }
+
+    unsafe {
+        let mut entry = environ;
+        while !entry.is_null() && !(*entry).is_null() {
+            let bytes = std::slice::from_raw_parts_mut(*entry as *mut u8, libc::strlen(*entry));
+            if bytes.len() > var.len()
+                && bytes.starts_with(var.as_bytes())
+                && bytes[var.len()] == b'='
+            {
+                bytes[var.len() + 1..].zeroize();
+            }
+            entry = entry.add(1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_secret_from_env() {
+        std::env::set_var("DRYOC_TEST_SECRET_HEX", "deadbeef");
+        let secret =
+            secret_from_env("DRYOC_TEST_SECRET_HEX", SecretEncoding::Hex).expect("load failed");
+        assert_eq!(secret.as_slice(), &[0xde, 0xad, 0xbe, 0xef]);
+        assert!(std::env::var_os("DRYOC_TEST_SECRET_HEX").is_none());
+
+        std::env::set_var("DRYOC_TEST_SECRET_BASE64", "3q2+7w==");
+        let secret: Locked<HeapByteArray<4>> =
+            secret_array_from_env("DRYOC_TEST_SECRET_BASE64", SecretEncoding::Base64)
+                .expect("load failed");
+        assert_eq!(secret.as_slice(), &[0xde, 0xad, 0xbe, 0xef]);
+        assert!(std::env::var_os("DRYOC_TEST_SECRET_BASE64").is_none());
+
+        std::env::set_var("DRYOC_TEST_SECRET_RAW", "raw secret");
+        let secret =
+            secret_from_env("DRYOC_TEST_SECRET_RAW", SecretEncoding::Raw).expect("load failed");
+        assert_eq!(secret.as_slice(), b"raw secret");
+
+        assert!(secret_from_env("DRYOC_TEST_SECRET_RAW", SecretEncoding::Raw).is_err());
+
+        std::env::set_var("DRYOC_TEST_SECRET_BAD", "not hex");
+        assert!(secret_from_env("DRYOC_TEST_SECRET_BAD", SecretEncoding::Hex).is_err());
+        assert!(std::env::var_os("DRYOC_TEST_SECRET_BAD").is_none());
+    }
+
+    #[test]
+    fn test_secret_reader() {
+        let secret = SecretReader::new(&b"hunter2\r\n"[..])
+            .with_chunk_size(3)
+            .read_secret()
+            .expect("read failed");
+        assert_eq!(secret.as_slice(), b"hunter2");
+
+        let secret = SecretReader::new(&b"hunter2\n"[..])
+            .with_trim_newlines(false)
+            .read_secret()
+            .expect("read failed");
+        assert_eq!(secret.as_slice(), b"hunter2\n");
+
+        let data = vec![0x5a; 1000];
+        let secret = SecretReader::new(data.as_slice())
+            .with_chunk_size(7)
+            .read_secret()
+            .expect("read failed");
+        assert_eq!(secret.as_slice(), data.as_slice());
+
+        assert!(
+            SecretReader::new(data.as_slice())
+                .with_max_length(999)
+                .read_secret()
+                .is_err()
+        );
+
+        let secret: Locked<HeapByteArray<4>> = SecretReader::new(&b"\xde\xad\xbe\xef\n"[..])
+            .read_secret_array()
+            .expect("read failed");
+        assert_eq!(secret.as_slice(), &[0xde, 0xad, 0xbe, 0xef]);
+        assert!(
+            SecretReader::new(&b"abc"[..])
+                .read_secret_array::<4>()
+                .is_err()
+        );
+    }
+}
@@ -0,0 +1,567 @@
+//! # Append-only encrypted log files
+//!
+//! [`LogWriter`] appends encrypted records to a log file, and [`LogReader`]
+//! reads them back in any order. Each record is encrypted as its own
+//! secret stream segment (see [`crate::dryocstream`]), with the log's random
+//! file ID and the record's number authenticated as associated data, so
+//! records can't be reordered, or moved between logs written with the same
+//! key.
+//!
+//! The writer also maintains an index of record offsets. A [`LogIndex`] is
+//! signed by any [`Signer`], such as a
+//! [`SigningKeyPair`](crate::sign::SigningKeyPair), and is typically stored
+//! in a sidecar file next to the log. Readers verify the index before
+//! reading, which gives:
+//!
+//! * O(1) seeking to any record by its number
+//! * detection of truncation: a log which is shorter than its index, or
+//!   which doesn't begin with the index's file ID, is rejected when opened
+//!
+//! Records appended after the index was signed aren't visible to readers
+//! until a new index is signed. An existing log can be appended to with
+//! [`LogWriter::resume`].
+//!
+//! ## Layout
+//!
+//! The log begins with the magic bytes `DRYOCLG1` and the 16 byte file ID,
+//! followed by each record as header (24) ‖ ciphertext ‖ tag (17).
+//!
+//! ## Example
+//!
+//! ```
+//! use std::io::Cursor;
+//!
+//! use dryoc::logfile::*;
+//! use dryoc::sign::SigningKeyPair;
+//!
+//! let key = Key::gen();
+//! let signing_keypair = SigningKeyPair::gen_with_defaults();
+//!
+//! let mut writer = LogWriter::new(Vec::new(), &key).expect("new failed");
+//! for i in 0..10u32 {
+//!     writer
+//!         .append(format!("record {}", i).as_bytes())
+//!         .expect("append failed");
+//! }
+//! let index = writer.sign_index(&signing_keypair).expect("sign failed");
+//! let log = writer.into_inner();
+//!
+//! // Store the index alongside the log, then read it back
+//! let index = LogIndex::from_bytes(&index.to_vec()).expect("invalid index");
+//! let mut reader = LogReader::new(Cursor::new(&log), &key, index, &signing_keypair.public_key)
+//!     .expect("open failed");
+//! assert_eq!(reader.len(), 10);
+//! assert_eq!(reader.read(7).expect("read failed"), b"record 7");
+//!
+//! // Truncated logs are detected
+//! let truncated = &log[..log.len() - 1];
+//! let index = LogIndex::from_bytes(&reader.index().to_vec()).unwrap();
+//! assert!(
+//!     LogReader::new(Cursor::new(truncated), &key, index, &signing_keypair.public_key).is_err()
+//! );
+//! ```
+use std::io::{Read, Seek, SeekFrom, Write};
+
+use crate::classic::crypto_secretstream_xchacha20poly1305::{
+    crypto_secretstream_xchacha20poly1305_init_pull,
+    crypto_secretstream_xchacha20poly1305_init_push, crypto_secretstream_xchacha20poly1305_pull,
+    crypto_secretstream_xchacha20poly1305_push, Header, State,
+};
+use crate::constants::{
+    CRYPTO_SECRETSTREAM_XCHACHA20POLY1305_ABYTES,
+    CRYPTO_SECRETSTREAM_XCHACHA20POLY1305_HEADERBYTES,
+    CRYPTO_SECRETSTREAM_XCHACHA20POLY1305_KEYBYTES,
+    CRYPTO_SECRETSTREAM_XCHACHA20POLY1305_TAG_FINAL,
+};
+use crate::error::Error;
+use crate::rng::copy_randombytes;
+use crate::sign::{SignatureAlgorithm, Signer, Verifier};
+pub use crate::types::*;
+
+/// Magic bytes which begin a log file.
+pub const LOG_MAGIC: &[u8; 8] = b"DRYOCLG1";
+/// Length of a log's random file ID.
+pub const LOG_FILE_IDBYTES: usize = 16;
+/// Length of the log file header.
+pub const LOG_HEADERBYTES: usize = 8 + LOG_FILE_IDBYTES;
+/// Number of bytes added to each record when it's encrypted.
+pub const LOG_RECORD_OVERHEADBYTES: usize =
+    CRYPTO_SECRETSTREAM_XCHACHA20POLY1305_HEADERBYTES + CRYPTO_SECRETSTREAM_XCHACHA20POLY1305_ABYTES;
+
+/// Domain separation prefix for log index signatures.
+const LOG_INDEX_SIGN_CONTEXT: &[u8] = b"dryoc log index";
+
+/// Stack-allocated log key type alias.
+pub type Key = StackByteArray<CRYPTO_SECRETSTREAM_XCHACHA20POLY1305_KEYBYTES>;
+/// Stack-allocated log file ID type alias.
+pub type FileId = StackByteArray<LOG_FILE_IDBYTES>;
+
+fn record_ad(file_id: &FileId, record: u64) -> [u8; LOG_FILE_IDBYTES + 8] {
+    let mut ad = [0u8; LOG_FILE_IDBYTES + 8];
+    ad[..LOG_FILE_IDBYTES].copy_from_slice(file_id.as_slice());
+    ad[LOG_FILE_IDBYTES..].copy_from_slice(&record.to_le_bytes());
+    ad
+}
+
+/// Signed index of the records in a log, produced by
+/// [`LogWriter::sign_index`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LogIndex {
+    file_id: FileId,
+    offsets: Vec<u64>,
+    end: u64,
+    algorithm: SignatureAlgorithm,
+    signature: Vec<u8>,
+}
+
+impl LogIndex {
+    /// Returns the log's file ID.
+    pub fn file_id(&self) -> &FileId {
+        &self.file_id
+    }
+
+    /// Returns the number of records in the index.
+    pub fn len(&self) -> u64 {
+        self.offsets.len() as u64
+    }
+
+    /// Returns true if the index has no records.
+    pub fn is_empty(&self) -> bool {
+        self.offsets.is_empty()
+    }
+
+    /// Returns the length of the log covered by this index, in bytes.
+    pub fn log_len(&self) -> u64 {
+        self.end
+    }
+
+    fn signing_input(
+        algorithm: SignatureAlgorithm,
+        file_id: &FileId,
+        offsets: &[u64],
+        end: u64,
+    ) -> Vec<u8> {
+        let mut input = LOG_INDEX_SIGN_CONTEXT.to_vec();
+        input.extend_from_slice(&algorithm.cose_id().to_le_bytes());
+        input.extend_from_slice(&Self::encode_body(file_id, offsets, end));
+        input
+    }
+
+    fn encode_body(file_id: &FileId, offsets: &[u64], end: u64) -> Vec<u8> {
+        let mut body = Vec::with_capacity(LOG_FILE_IDBYTES + 16 + offsets.len() * 8);
+        body.extend_from_slice(file_id.as_slice());
+        body.extend_from_slice(&end.to_le_bytes());
+        body.extend_from_slice(&(offsets.len() as u64).to_le_bytes());
+        offsets
+            .iter()
+            .for_each(|offset| body.extend_from_slice(&offset.to_le_bytes()));
+        body
+    }
+
+    /// Verifies the signature with `verifier`.
+    pub fn verify<V: Verifier + ?Sized>(&self, verifier: &V) -> Result<(), Error> {
+        if verifier.algorithm() != self.algorithm {
+            return Err(dryoc_error!(format!(
+                "log index is signed with {}, but verifier uses {}",
+                self.algorithm.jws_name(),
+                verifier.algorithm().jws_name()
+            )));
+        }
+        verifier.verify(
+            &Self::signing_input(self.algorithm, &self.file_id, &self.offsets, self.end),
+            &self.signature,
+        )
+    }
+
+    /// Serializes this index into a new [`Vec`], as: the algorithm's COSE
+    /// identifier (8) ‖ signature length (4) ‖ signature ‖ file ID (16) ‖ log
+    /// length (8) ‖ record count (8) ‖ record offsets (8 each). Integers are
+    /// little-endian.
+    pub fn to_vec(&self) -> Vec<u8> {
+        let body = Self::encode_body(&self.file_id, &self.offsets, self.end);
+        let mut bytes = Vec::with_capacity(12 + self.signature.len() + body.len());
+        bytes.extend_from_slice(&self.algorithm.cose_id().to_le_bytes());
+        bytes.extend_from_slice(&(self.signature.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(&self.signature);
+        bytes.extend_from_slice(&body);
+        bytes
+    }
+
+    /// Parses an index produced by [`LogIndex::to_vec`]. The signature isn't
+    /// verified.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        let take = |bytes: &mut &[u8], len: usize| -> Result<Vec<u8>, Error> {
+            if bytes.len() < len {
+                return Err(dryoc_error!("log index is truncated"));
+            }
+            let (head, tail) = bytes.split_at(len);
+            *bytes = tail;
+            Ok(head.to_vec())
+        };
+        let mut bytes = bytes;
+        let cose_id = i64::from_le_bytes(take(&mut bytes, 8)?.as_slice().try_into()?);
+        let algorithm = SignatureAlgorithm::from_cose_id(cose_id)?;
+        let signature_len = u32::from_le_bytes(take(&mut bytes, 4)?.as_slice().try_into()?);
+        let signature = take(&mut bytes, signature_len as usize)?;
+        let file_id = FileId::try_from(take(&mut bytes, LOG_FILE_IDBYTES)?.as_slice())?;
+        let end = u64::from_le_bytes(take(&mut bytes, 8)?.as_slice().try_into()?);
+        let count = u64::from_le_bytes(take(&mut bytes, 8)?.as_slice().try_into()?);
+        if count.checked_mul(8) != Some(bytes.len() as u64) {
+            return Err(dryoc_error!("invalid log index length"));
+        }
+        let offsets: Vec<u64> = bytes
+            .chunks_exact(8)
+            .map(|offset| u64::from_le_bytes(offset.try_into().expect("8 bytes")))
+            .collect();
+        let mut minimum = LOG_HEADERBYTES as u64;
+        for offset in &offsets {
+            if *offset < minimum {
+                return Err(dryoc_error!("log index offsets are out of order"));
+            }
+            minimum = offset
+                .checked_add(LOG_RECORD_OVERHEADBYTES as u64)
+                .ok_or_else(|| dryoc_error!("invalid log index offset"))?;
+        }
+        if end < minimum {
+            return Err(dryoc_error!("invalid log index length"));
+        }
+        Ok(Self {
+            file_id,
+            offsets,
+            end,
+            algorithm,
+            signature,
+        })
+    }
+}
+
+/// Appends encrypted records to a log.
+///
+/// Refer to [crate::logfile] for sample usage.
+pub struct LogWriter<W: Write> {
+    writer: W,
+    key: Key,
+    file_id: FileId,
+    offsets: Vec<u64>,
+    position: u64,
+}
+
+impl<W: Write> LogWriter<W> {
+    /// Starts a new log with a random file ID, writing its header to
+    /// `writer`.
+    pub fn new(mut writer: W, key: &Key) -> Result<Self, Error> {
+        let mut file_id = FileId::default();
+        copy_randombytes(file_id.as_mut_slice());
+        writer.write_all(LOG_MAGIC)?;
+        writer.write_all(file_id.as_slice())?;
+        Ok(Self {
+            writer,
+            key: key.clone(),
+            file_id,
+            offsets: Vec::new(),
+            position: LOG_HEADERBYTES as u64,
+        })
+    }
+
+    /// Returns the log's file ID.
+    pub fn file_id(&self) -> &FileId {
+        &self.file_id
+    }
+
+    /// Returns the number of records in the log.
+    pub fn len(&self) -> u64 {
+        self.offsets.len() as u64
+    }
+
+    /// Returns true if the log has no records.
+    pub fn is_empty(&self) -> bool {
+        self.offsets.is_empty()
+    }
+
+    /// Encrypts and appends `record`, returning its record number.
+    pub fn append(&mut self, record: &[u8]) -> Result<u64, Error> {
+        let number = self.offsets.len() as u64;
+        let mut segment = vec![0u8; LOG_RECORD_OVERHEADBYTES + record.len()];
+        let (header, ciphertext) =
+            segment.split_at_mut(CRYPTO_SECRETSTREAM_XCHACHA20POLY1305_HEADERBYTES);
+        let mut state = State::new();
+        crypto_secretstream_xchacha20poly1305_init_push(
+            &mut state,
+            <&mut Header>::try_from(header)?,
+            self.key.as_array(),
+        );
+        crypto_secretstream_xchacha20poly1305_push(
+            &mut state,
+            ciphertext,
+            record,
+            Some(&record_ad(&self.file_id, number)),
+            CRYPTO_SECRETSTREAM_XCHACHA20POLY1305_TAG_FINAL,
+        )?;
+        self.writer.write_all(&segment)?;
+        self.offsets.push(self.position);
+        self.position += segment.len() as u64;
+        Ok(number)
+    }
+
+    /// Flushes the underlying writer.
+    pub fn flush(&mut self) -> Result<(), Error> {
+        Ok(self.writer.flush()?)
+    }
+
+    /// Flushes the underlying writer, then returns an index of all the
+    /// records appended so far, signed with `signer`.
+    pub fn sign_index<S: Signer + ?Sized>(&mut self, signer: &S) -> Result<LogIndex, Error> {
+        self.flush()?;
+        let algorithm = signer.algorithm();
+        let signature = signer.try_sign(&LogIndex::signing_input(
+            algorithm,
+            &self.file_id,
+            &self.offsets,
+            self.position,
+        ))?;
+        Ok(LogIndex {
+            file_id: self.file_id.clone(),
+            offsets: self.offsets.clone(),
+            end: self.position,
+            algorithm,
+            signature,
+        })
+    }
+
+    /// Returns the underlying writer, consuming this log writer.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+impl<W: Read + Write + Seek> LogWriter<W> {
+    /// Resumes appending to an existing log in `file`, after verifying
+    /// `index` with `verifier`. Any data following the end of the indexed
+    /// records, such as records which were never indexed, is overwritten.
+    pub fn resume<V: Verifier + ?Sized>(
+        mut file: W,
+        key: &Key,
+        index: LogIndex,
+        verifier: &V,
+    ) -> Result<Self, Error> {
+        index.verify(verifier)?;
+        check_log(&mut file, &index)?;
+        file.seek(SeekFrom::Start(index.end))?;
+        Ok(Self {
+            writer: file,
+            key: key.clone(),
+            file_id: index.file_id,
+            offsets: index.offsets,
+            position: index.end,
+        })
+    }
+}
+
+fn check_log<R: Read + Seek>(reader: &mut R, index: &LogIndex) -> Result<(), Error> {
+    let len = reader.seek(SeekFrom::End(0))?;
+    if len < index.end {
+        return Err(dryoc_error!(format!(
+            "log is truncated: expected at least {} bytes, found {}",
+            index.end, len
+        )));
+    }
+    let mut header = [0u8; LOG_HEADERBYTES];
+    reader.seek(SeekFrom::Start(0))?;
+    reader.read_exact(&mut header)?;
+    if &header[..LOG_MAGIC.len()] != LOG_MAGIC
+        || &header[LOG_MAGIC.len()..] != index.file_id.as_slice()
+    {
+        return Err(dryoc_error!("log header doesn't match index"));
+    }
+    Ok(())
+}
+
+/// Reads encrypted records from a log, using a verified [`LogIndex`].
+///
+/// Refer to [crate::logfile] for sample usage.
+pub struct LogReader<R: Read + Seek> {
+    reader: R,
+    key: Key,
+    index: LogIndex,
+}
+
+impl<R: Read + Seek> LogReader<R> {
+    /// Opens the log in `reader`, after verifying `index` with `verifier`,
+    /// and checking that the log matches the index and isn't truncated.
+    pub fn new<V: Verifier + ?Sized>(
+        mut reader: R,
+        key: &Key,
+        index: LogIndex,
+        verifier: &V,
+    ) -> Result<Self, Error> {
+        index.verify(verifier)?;
+        check_log(&mut reader, &index)?;
+        Ok(Self {
+            reader,
+            key: key.clone(),
+            index,
+        })
+    }
+
+    /// Returns the verified index.
+    pub fn index(&self) -> &LogIndex {
+        &self.index
+    }
+
+    /// Returns the number of records in the index.
+    pub fn len(&self) -> u64 {
+        self.index.len()
+    }
+
+    /// Returns true if the index has no records.
+    pub fn is_empty(&self) -> bool {
+        self.index.is_empty()
+    }
+
+    /// Reads and decrypts record number `record`.
+    pub fn read(&mut self, record: u64) -> Result<Vec<u8>, Error> {
+        let start = *self
+            .index
+            .offsets
+            .get(record as usize)
+            .ok_or_else(|| dryoc_error!(format!("no record {}", record)))?;
+        let end = self
+            .index
+            .offsets
+            .get(record as usize + 1)
+            .copied()
+            .unwrap_or(self.index.end);
+
+        let mut segment = vec![0u8; (end - start) as usize];
+        self.reader.seek(SeekFrom::Start(start))?;
+        self.reader.read_exact(&mut segment)?;
+
+        let (header, ciphertext) =
+            segment.split_at(CRYPTO_SECRETSTREAM_XCHACHA20POLY1305_HEADERBYTES);
+        let mut output = vec![0u8; ciphertext.len() - CRYPTO_SECRETSTREAM_XCHACHA20POLY1305_ABYTES];
+        let mut state = State::new();
+        crypto_secretstream_xchacha20poly1305_init_pull(
+            &mut state,
+            <&Header>::try_from(header)?,
+            self.key.as_array(),
+        );
+        let mut tag = 0u8;
+        crypto_secretstream_xchacha20poly1305_pull(
+            &mut state,
+            &mut output,
+            &mut tag,
+            ciphertext,
+            Some(&record_ad(&self.index.file_id, record)),
+        )?;
+        if tag != CRYPTO_SECRETSTREAM_XCHACHA20POLY1305_TAG_FINAL {
+            return Err(dryoc_error!("invalid log record tag"));
+        }
+        Ok(output)
+    }
+
+    /// Returns an iterator which reads and decrypts each record in order.
+    pub fn records(&mut self) -> impl Iterator<Item = Result<Vec<u8>, Error>> + '_ {
+        (0..self.len()).map(move |record| self.read(record))
+    }
+
+    /// Returns the underlying reader, consuming this log reader.
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::sign::SigningKeyPair;
+
+    fn write_log(key: &Key, signer: &dyn Signer, count: u32) -> (Vec<u8>, LogIndex) {
+        let mut writer = LogWriter::new(Vec::new(), key).expect("new failed");
+        for i in 0..count {
+            writer
+                .append(&vec![i as u8; i as usize * 3])
+                .expect("append failed");
+        }
+        let index = writer.sign_index(signer).expect("sign failed");
+        (writer.into_inner(), index)
+    }
+
+    #[test]
+    fn test_log_round_trip() {
+        let key = Key::gen();
+        let signer = SigningKeyPair::gen_with_defaults();
+        let (log, index) = write_log(&key, &signer, 20);
+        let index = LogIndex::from_bytes(&index.to_vec()).expect("parse failed");
+
+        let mut reader =
+            LogReader::new(Cursor::new(&log), &key, index, &signer.public_key).expect("open");
+        for i in (0..20u32).rev() {
+            assert_eq!(reader.read(i as u64).expect("read"), vec![i as u8; i as usize * 3]);
+        }
+        assert_eq!(reader.records().count(), 20);
+        reader.read(20).expect_err("no such record");
+
+        // records are bound to their position and file
+        let (other_log, _) = write_log(&key, &signer, 20);
+        let mut mixed = log.clone();
+        let start = reader.index().offsets[3] as usize;
+        mixed[start..start + 20].copy_from_slice(&other_log[start..start + 20]);
+        let index = reader.index().clone();
+        let mut reader =
+            LogReader::new(Cursor::new(&mixed), &key, index, &signer.public_key).expect("open");
+        reader.read(3).expect_err("record from other log should fail");
+        reader.read(4).expect("other records are intact");
+    }
+
+    #[test]
+    fn test_log_tampering() {
+        let key = Key::gen();
+        let signer = SigningKeyPair::gen_with_defaults();
+        let (log, index) = write_log(&key, &signer, 5);
+
+        LogReader::new(
+            Cursor::new(&log[..log.len() - 1]),
+            &key,
+            index.clone(),
+            &signer.public_key,
+        )
+        .err()
+        .expect("truncation should be detected");
+
+        let other = SigningKeyPair::gen_with_defaults();
+        LogReader::new(Cursor::new(&log), &key, index.clone(), &other.public_key)
+            .err()
+            .expect("wrong signer should fail");
+
+        // move the last record's offset by one byte
+        let mut bytes = index.to_vec();
+        let last_offset = bytes.len() - 8;
+        bytes[last_offset] ^= 1;
+        let tampered = LogIndex::from_bytes(&bytes).expect("parse failed");
+        LogReader::new(Cursor::new(&log), &key, tampered, &signer.public_key)
+            .err()
+            .expect("tampered index should fail");
+    }
+
+    #[test]
+    fn test_log_resume() {
+        let key = Key::gen();
+        let signer = SigningKeyPair::gen_with_defaults();
+        let (log, index) = write_log(&key, &signer, 3);
+
+        let mut cursor = Cursor::new(log);
+        cursor.get_mut().extend_from_slice(b"unindexed garbage");
+        let mut writer =
+            LogWriter::resume(cursor, &key, index, &signer.public_key).expect("resume");
+        assert_eq!(writer.append(b"appended").expect("append"), 3);
+        let index = writer.sign_index(&signer).expect("sign failed");
+
+        let mut reader =
+            LogReader::new(writer.into_inner(), &key, index, &signer.public_key).expect("open");
+        assert_eq!(reader.len(), 4);
+        assert_eq!(reader.read(3).expect("read"), b"appended");
+        assert_eq!(reader.read(2).expect("read"), vec![2u8; 6]);
+    }
+}
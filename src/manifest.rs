@@ -0,0 +1,514 @@
+//! # Signed manifests of directory trees
+//!
+//! [`Manifest`] maps the relative paths of files in a directory tree to the
+//! Blake2b digests of their contents, and [`SignedManifest`] bundles a
+//! manifest with a signature over its canonical encoding. Signed manifests
+//! can be used to distribute software, or to verify that a restored backup
+//! matches what was originally backed up.
+//!
+//! Paths are stored relative to the root of the tree, with `/` separators on
+//! all platforms, and must be valid UTF-8. Only regular files are included;
+//! directories are walked recursively, and anything else (such as a symbolic
+//! link) is rejected.
+//!
+//! The canonical encoding orders entries by path, so the same tree always
+//! produces the same bytes. Signatures are produced by any [`Signer`], such as
+//! an Ed25519 [`SigningKeyPair`](crate::sign::SigningKeyPair), and the
+//! signature algorithm is covered by the signature.
+//!
+//! ## Example
+//!
+//! ```
+//! use dryoc::manifest::*;
+//! use dryoc::sign::SigningKeyPair;
+//!
+//! let dir = std::env::temp_dir().join(format!("dryoc-manifest-doc-{}", std::process::id()));
+//! std::fs::create_dir_all(dir.join("bin")).expect("mkdir failed");
+//! std::fs::write(dir.join("README"), b"hello").expect("write failed");
+//! std::fs::write(dir.join("bin/tool"), b"\x7fELF").expect("write failed");
+//!
+//! let keypair = SigningKeyPair::gen_with_defaults();
+//!
+//! let manifest = Manifest::from_dir(&dir).expect("manifest failed");
+//! assert_eq!(manifest.len(), 2);
+//! let signed = manifest.sign(&keypair).expect("sign failed");
+//!
+//! // Distribute the bundle alongside the files, then verify them
+//! let bundle = signed.to_bytes();
+//! let signed = SignedManifest::from_bytes(&bundle).expect("parse failed");
+//! signed
+//!     .verify_dir(&keypair.public_key, &dir)
+//!     .expect("verify failed");
+//!
+//! // Modified files are detected
+//! std::fs::write(dir.join("README"), b"goodbye").expect("write failed");
+//! assert!(signed.verify_dir(&keypair.public_key, &dir).is_err());
+//! # std::fs::remove_dir_all(&dir).ok();
+//! ```
+
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::{Component, Path};
+
+use crate::canonical::{CanonicalDecode, CanonicalEncode};
+use crate::constants::CRYPTO_GENERICHASH_BYTES;
+use crate::error::Error;
+use crate::generichash::GenericHash;
+use crate::sign::{SignatureAlgorithm, Signer, Verifier};
+pub use crate::types::*;
+
+/// Magic bytes which prefix the canonical encoding of a manifest.
+pub const MANIFEST_MAGIC: &[u8; 8] = b"DRYOCMF1";
+
+/// Domain separation prefix for manifest signatures.
+const MANIFEST_SIGN_CONTEXT: &[u8] = b"dryoc manifest signature";
+
+/// Stack-allocated file digest type alias.
+pub type Digest = StackByteArray<CRYPTO_GENERICHASH_BYTES>;
+
+/// A difference between a manifest and a directory tree (or another
+/// manifest), returned by [`Manifest::diff`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ManifestDifference {
+    /// The file is listed in the manifest, but is missing.
+    Missing(String),
+    /// The file is present, but isn't listed in the manifest.
+    Unexpected(String),
+    /// The file's digest doesn't match the manifest.
+    Modified(String),
+}
+
+/// A mapping of relative file paths to content digests.
+///
+/// Refer to [crate::manifest] for sample usage.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Manifest {
+    entries: BTreeMap<String, Digest>,
+}
+
+impl Manifest {
+    /// Returns a new, empty manifest.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns a new manifest of all the regular files below `root`.
+    pub fn from_dir<P: AsRef<Path>>(root: P) -> Result<Self, Error> {
+        let mut manifest = Self::new();
+        manifest.add_dir(root.as_ref(), "")?;
+        Ok(manifest)
+    }
+
+    fn add_dir(&mut self, dir: &Path, prefix: &str) -> Result<(), Error> {
+        for entry in fs::read_dir(dir)? {
+            let entry = entry?;
+            let name = entry.file_name().into_string().map_err(|name| {
+                dryoc_error!(format!("path is not valid UTF-8: {:?}", name))
+            })?;
+            let path = format!("{}{}", prefix, name);
+            let file_type = entry.file_type()?;
+            if file_type.is_dir() {
+                self.add_dir(&entry.path(), &format!("{}/", path))?;
+            } else if file_type.is_file() {
+                let digest = digest_file(&entry.path())?;
+                self.entries.insert(path, digest);
+            } else {
+                return Err(dryoc_error!(format!(
+                    "unsupported file type for {}",
+                    path
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Inserts `path` with `digest`, replacing any existing entry. `path` must
+    /// be relative, `/`-separated, and must not contain `.` or `..`
+    /// components.
+    pub fn insert(&mut self, path: &str, digest: Digest) -> Result<(), Error> {
+        validate_path(path)?;
+        self.entries.insert(path.to_string(), digest);
+        Ok(())
+    }
+
+    /// Returns the digest for `path`, if it's listed.
+    pub fn get(&self, path: &str) -> Option<&Digest> {
+        self.entries.get(path)
+    }
+
+    /// Returns an iterator over the entries, ordered by path.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Digest)> {
+        self.entries.iter().map(|(path, digest)| (path.as_str(), digest))
+    }
+
+    /// Returns the number of entries.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns true if the manifest has no entries.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Returns the differences between this manifest and `actual`, ordered by
+    /// path. Returns an empty [`Vec`] if they match.
+    pub fn diff(&self, actual: &Manifest) -> Vec<ManifestDifference> {
+        let mut differences = Vec::new();
+        for (path, digest) in &self.entries {
+            match actual.entries.get(path) {
+                None => differences.push(ManifestDifference::Missing(path.clone())),
+                Some(other) if other != digest => {
+                    differences.push(ManifestDifference::Modified(path.clone()))
+                }
+                Some(_) => (),
+            }
+        }
+        for path in actual.entries.keys() {
+            if !self.entries.contains_key(path) {
+                differences.push(ManifestDifference::Unexpected(path.clone()));
+            }
+        }
+        differences.sort_by(|a, b| difference_path(a).cmp(difference_path(b)));
+        differences
+    }
+
+    /// Returns the canonical encoding of this manifest: the magic bytes, the
+    /// entry count, then each entry ordered by path, as a length-prefixed
+    /// path followed by its digest. Integers are little-endian.
+    pub fn to_canonical_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(
+            MANIFEST_MAGIC.len()
+                + 4
+                + self
+                    .entries
+                    .keys()
+                    .map(|path| 4 + path.len() + CRYPTO_GENERICHASH_BYTES)
+                    .sum::<usize>(),
+        );
+        bytes.extend_from_slice(MANIFEST_MAGIC);
+        bytes.extend_from_slice(&(self.entries.len() as u32).to_le_bytes());
+        for (path, digest) in &self.entries {
+            bytes.extend_from_slice(&(path.len() as u32).to_le_bytes());
+            bytes.extend_from_slice(path.as_bytes());
+            bytes.extend_from_slice(digest.as_slice());
+        }
+        bytes
+    }
+
+    /// Parses a manifest from its canonical encoding. Entries must be
+    /// strictly ordered by path, so each manifest has exactly one encoding.
+    pub fn from_canonical_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        let mut reader = ByteReader { bytes };
+        if reader.take(MANIFEST_MAGIC.len())? != MANIFEST_MAGIC {
+            return Err(dryoc_error!("invalid manifest magic"));
+        }
+        let count = reader.take_u32()?;
+        let mut entries = BTreeMap::new();
+        let mut last: Option<String> = None;
+        for _ in 0..count {
+            let len = reader.take_u32()? as usize;
+            let path = std::str::from_utf8(reader.take(len)?)
+                .map_err(|_| dryoc_error!("manifest path is not valid UTF-8"))?
+                .to_string();
+            validate_path(&path)?;
+            if last.as_ref().map_or(false, |last| *last >= path) {
+                return Err(dryoc_error!("manifest entries are not in canonical order"));
+            }
+            let digest = Digest::try_from(reader.take(CRYPTO_GENERICHASH_BYTES)?)?;
+            last = Some(path.clone());
+            entries.insert(path, digest);
+        }
+        if !reader.bytes.is_empty() {
+            return Err(dryoc_error!("trailing bytes after manifest"));
+        }
+        Ok(Self { entries })
+    }
+
+    /// Signs this manifest with `signer`, returning the signed manifest.
+    pub fn sign<S: Signer + ?Sized>(self, signer: &S) -> Result<SignedManifest, Error> {
+        let algorithm = signer.algorithm();
+        let signature = signer.try_sign(&signing_input(algorithm, &self))?;
+        Ok(SignedManifest {
+            manifest: self,
+            algorithm,
+            signature,
+        })
+    }
+}
+
+/// A [`Manifest`] along with a signature over its canonical encoding.
+///
+/// Refer to [crate::manifest] for sample usage.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SignedManifest {
+    manifest: Manifest,
+    algorithm: SignatureAlgorithm,
+    signature: Vec<u8>,
+}
+
+impl SignedManifest {
+    /// Returns the manifest, without verifying the signature.
+    pub fn manifest_unverified(&self) -> &Manifest {
+        &self.manifest
+    }
+
+    /// Returns the signature algorithm.
+    pub fn algorithm(&self) -> SignatureAlgorithm {
+        self.algorithm
+    }
+
+    /// Returns the signature.
+    pub fn signature(&self) -> &[u8] {
+        &self.signature
+    }
+
+    /// Verifies the signature with `verifier`, returning the manifest if it's
+    /// valid.
+    pub fn verify<V: Verifier + ?Sized>(&self, verifier: &V) -> Result<&Manifest, Error> {
+        if verifier.algorithm() != self.algorithm {
+            return Err(dryoc_error!(format!(
+                "manifest is signed with {}, but verifier uses {}",
+                self.algorithm.jws_name(),
+                verifier.algorithm().jws_name()
+            )));
+        }
+        verifier.verify(
+            &signing_input(self.algorithm, &self.manifest),
+            &self.signature,
+        )?;
+        Ok(&self.manifest)
+    }
+
+    /// Verifies the signature with `verifier`, then verifies that the files
+    /// below `root` match the manifest exactly.
+    pub fn verify_dir<V: Verifier + ?Sized, P: AsRef<Path>>(
+        &self,
+        verifier: &V,
+        root: P,
+    ) -> Result<(), Error> {
+        let manifest = self.verify(verifier)?;
+        let differences = manifest.diff(&Manifest::from_dir(root)?);
+        match differences.first() {
+            None => Ok(()),
+            Some(first) => Err(dryoc_error!(format!(
+                "directory doesn't match manifest ({} differences, first: {:?})",
+                differences.len(),
+                first
+            ))),
+        }
+    }
+
+    /// Returns the serialized bundle: the algorithm's COSE identifier as an
+    /// 8-byte integer, the length-prefixed signature, then the canonical
+    /// encoding of the manifest.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let manifest = self.manifest.to_canonical_bytes();
+        let mut bytes = Vec::with_capacity(12 + self.signature.len() + manifest.len());
+        bytes.extend_from_slice(&self.algorithm.cose_id().to_le_bytes());
+        bytes.extend_from_slice(&(self.signature.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(&self.signature);
+        bytes.extend_from_slice(&manifest);
+        bytes
+    }
+
+    /// Parses a bundle produced by [`SignedManifest::to_bytes`]. The
+    /// signature isn't verified.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        let mut reader = ByteReader { bytes };
+        let cose_id = i64::from_le_bytes(reader.take(8)?.try_into()?);
+        let algorithm = SignatureAlgorithm::from_cose_id(cose_id)?;
+        let len = reader.take_u32()? as usize;
+        let signature = reader.take(len)?.to_vec();
+        let manifest = Manifest::from_canonical_bytes(reader.bytes)?;
+        Ok(Self {
+            manifest,
+            algorithm,
+            signature,
+        })
+    }
+}
+
+struct ByteReader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> ByteReader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], Error> {
+        if self.bytes.len() < len {
+            return Err(dryoc_error!("manifest is truncated"));
+        }
+        let (head, tail) = self.bytes.split_at(len);
+        self.bytes = tail;
+        Ok(head)
+    }
+
+    fn take_u32(&mut self) -> Result<u32, Error> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into()?))
+    }
+}
+
+fn signing_input(algorithm: SignatureAlgorithm, manifest: &Manifest) -> Vec<u8> {
+    let mut input = MANIFEST_SIGN_CONTEXT.to_vec();
+    input.extend_from_slice(&algorithm.cose_id().to_le_bytes());
+    input.extend_from_slice(&manifest.to_canonical_bytes());
+    input
+}
+
+fn validate_path(path: &str) -> Result<(), Error> {
+    let valid = !path.is_empty()
+        && !path.contains('\\')
+        && path.split('/').all(|part| !part.is_empty())
+        && Path::new(path)
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)));
+    if valid {
+        Ok(())
+    } else {
+        Err(dryoc_error!(format!("invalid manifest path {:?}", path)))
+    }
+}
+
+fn difference_path(difference: &ManifestDifference) -> &str {
+    match difference {
+        ManifestDifference::Missing(path)
+        | ManifestDifference::Unexpected(path)
+        | ManifestDifference::Modified(path) => path,
+    }
+}
+
+fn digest_file(path: &Path) -> Result<Digest, Error> {
+    let mut file = File::open(path)?;
+    let mut state = GenericHash::new_with_defaults::<Digest>(None)?;
+    let mut buf = vec![0u8; 64 * 1024];
+    loop {
+        let n = file.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        state.update(&buf[..n]);
+    }
+    state.finalize()
+}
+
+impl CanonicalEncode for Manifest {
+    fn to_canonical_bytes(&self) -> Vec<u8> {
+        Manifest::to_canonical_bytes(self)
+    }
+}
+
+impl CanonicalDecode for Manifest {
+    fn from_canonical_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        Manifest::from_canonical_bytes(bytes)
+    }
+}
+
+impl CanonicalEncode for SignedManifest {
+    fn to_canonical_bytes(&self) -> Vec<u8> {
+        self.to_bytes()
+    }
+}
+
+impl CanonicalDecode for SignedManifest {
+    fn from_canonical_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        Self::from_bytes(bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sign::SigningKeyPair;
+
+    fn temp_tree(name: &str) -> std::path::PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("dryoc-manifest-{}-{}", name, std::process::id()));
+        fs::remove_dir_all(&dir).ok();
+        fs::create_dir_all(dir.join("a/b")).expect("mkdir failed");
+        fs::write(dir.join("top"), b"top").expect("write failed");
+        fs::write(dir.join("a/b/deep"), b"deep").expect("write failed");
+        dir
+    }
+
+    #[test]
+    fn test_manifest_round_trip() {
+        let dir = temp_tree("round-trip");
+        let manifest = Manifest::from_dir(&dir).expect("manifest failed");
+        let paths: Vec<&str> = manifest.iter().map(|(path, _)| path).collect();
+        assert_eq!(paths, vec!["a/b/deep", "top"]);
+        assert_eq!(
+            manifest.get("top"),
+            Some(&GenericHash::hash_with_defaults(b"top", None::<&Digest>).unwrap())
+        );
+
+        let bytes = manifest.to_canonical_bytes();
+        assert_eq!(
+            Manifest::from_canonical_bytes(&bytes).expect("parse failed"),
+            manifest
+        );
+
+        let keypair = SigningKeyPair::gen_with_defaults();
+        let signed = manifest.sign(&keypair).expect("sign failed");
+        let parsed = SignedManifest::from_bytes(&signed.to_bytes()).expect("parse failed");
+        assert_eq!(parsed, signed);
+        parsed
+            .verify_dir(&keypair.public_key, &dir)
+            .expect("verify failed");
+
+        let other = SigningKeyPair::gen_with_defaults();
+        parsed
+            .verify(&other.public_key)
+            .expect_err("verify should fail");
+
+        let mut tampered = signed.to_bytes();
+        let last = tampered.len() - 1;
+        tampered[last] ^= 1;
+        SignedManifest::from_bytes(&tampered)
+            .expect("parse failed")
+            .verify(&keypair.public_key)
+            .expect_err("verify should fail");
+
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_manifest_diff() {
+        let dir = temp_tree("diff");
+        let manifest = Manifest::from_dir(&dir).expect("manifest failed");
+
+        fs::write(dir.join("top"), b"changed").expect("write failed");
+        fs::remove_file(dir.join("a/b/deep")).expect("remove failed");
+        fs::write(dir.join("a/new"), b"new").expect("write failed");
+
+        let actual = Manifest::from_dir(&dir).expect("manifest failed");
+        assert_eq!(
+            manifest.diff(&actual),
+            vec![
+                ManifestDifference::Missing("a/b/deep".into()),
+                ManifestDifference::Unexpected("a/new".into()),
+                ManifestDifference::Modified("top".into()),
+            ]
+        );
+        assert!(manifest.diff(&manifest).is_empty());
+
+        fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_invalid_paths() {
+        let mut manifest = Manifest::new();
+        for path in ["", "/abs", "../up", "a/../b", "./a", "a//b", "a/", "a\\b"] {
+            manifest
+                .insert(path, Digest::default())
+                .expect_err("insert should fail");
+        }
+        manifest
+            .insert("a/b.txt", Digest::default())
+            .expect("insert failed");
+
+        let mut bytes = manifest.to_canonical_bytes();
+        bytes.push(0);
+        Manifest::from_canonical_bytes(&bytes).expect_err("parse should fail");
+    }
+}
@@ -0,0 +1,625 @@
+//! # HTTP body encryption middleware
+//!
+//! [`SealedBodyLayer`] is a [`tower_layer::Layer`] which transparently seals
+//! and unseals HTTP request and response bodies, for payload encryption
+//! between services independent of the transport (i.e., end-to-end through
+//! proxies which terminate TLS). Bodies are encrypted with either sealed
+//! boxes (see [`DryocBox::seal`](crate::dryocbox::DryocBox::seal)) or
+//! [HPKE](crate::hpke) with [`DhKemX25519`], selected with [`Scheme`].
+//!
+//! Keys are looked up by ID with a [`KeyResolver`], so they can be rotated or
+//! fetched from elsewhere without rebuilding the service. Each party has an
+//! X25519 keypair, and knows the public keys of the parties it talks to:
+//!
+//! * A client layer seals each request body to the server's public key, naming
+//!   that key in the [`KEY_ID_HEADER`] header, and names its own key in the
+//!   [`REPLY_KEY_ID_HEADER`] header. Response bodies are unsealed with the
+//!   client's keypair, and responses which aren't sealed are rejected with an
+//!   error.
+//! * A server layer unseals each request body with the keypair named in
+//!   [`KEY_ID_HEADER`], and seals the response body to the public key named in
+//!   [`REPLY_KEY_ID_HEADER`], if present. Requests which can't be unsealed, or
+//!   which name an unknown reply key, are rejected with `400 Bad Request`
+//!   without reaching the inner service.
+//!
+//! Bodies are encrypted in one piece, so services must use buffered
+//! [`bytes::Bytes`] bodies (i.e., `http::Request<Bytes>`); collect streaming
+//! bodies before this layer. The `Content-Length` header is updated to match
+//! the transformed body, and other headers are passed through unchanged.
+//!
+//! Sealed boxes and HPKE in base mode don't authenticate the sender, so this
+//! provides confidentiality for bodies, but not authentication of the peer.
+//!
+//! Requires the `tower` feature.
+//!
+//! ## Example
+//!
+//! ```
+//! use bytes::Bytes;
+//! use dryoc::keypair::StackKeyPair;
+//! use dryoc::middleware::*;
+//! use tower_layer::Layer;
+//!
+//! let server_keypair = StackKeyPair::gen();
+//! let client_keypair = StackKeyPair::gen();
+//!
+//! // The server knows its own keypair, and the client's public key
+//! let server_keys = StaticKeyResolver::new()
+//!     .with_keypair("server", server_keypair.clone())
+//!     .with_public_key("client", client_keypair.public_key.clone());
+//! let server_layer = SealedBodyLayer::server(server_keys).with_scheme(Scheme::Hpke);
+//!
+//! // The client knows its own keypair, and the server's public key
+//! let client_keys = StaticKeyResolver::new()
+//!     .with_keypair("client", client_keypair)
+//!     .with_public_key("server", server_keypair.public_key.clone());
+//! let client_layer =
+//!     SealedBodyLayer::client(client_keys, "server", "client").with_scheme(Scheme::Hpke);
+//!
+//! // Stack the layers around a service, i.e.:
+//! # #[derive(Clone)]
+//! # struct Echo;
+//! # impl tower_service::Service<http::Request<Bytes>> for Echo {
+//! #     type Response = http::Response<Bytes>;
+//! #     type Error = std::convert::Infallible;
+//! #     type Future = std::future::Ready<Result<Self::Response, Self::Error>>;
+//! #     fn poll_ready(
+//! #         &mut self,
+//! #         _cx: &mut std::task::Context<'_>,
+//! #     ) -> std::task::Poll<Result<(), Self::Error>> {
+//! #         std::task::Poll::Ready(Ok(()))
+//! #     }
+//! #     fn call(&mut self, request: http::Request<Bytes>) -> Self::Future {
+//! #         std::future::ready(Ok(http::Response::new(request.into_body())))
+//! #     }
+//! # }
+//! let server = server_layer.layer(Echo);
+//! let client = client_layer.layer(server);
+//! ```
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use bytes::Bytes;
+use http::header::{HeaderValue, CONTENT_LENGTH};
+use http::{Request, Response, StatusCode};
+use tower_layer::Layer;
+use tower_service::Service;
+
+use crate::constants::CRYPTO_BOX_PUBLICKEYBYTES;
+use crate::dryocbox::VecBox;
+use crate::error::Error;
+use crate::hpke;
+use crate::kem::{Ciphertext, DhKemX25519};
+use crate::keypair::{PublicKey, StackKeyPair};
+
+/// Header naming the key a body is sealed to.
+pub const KEY_ID_HEADER: &str = "dryoc-key-id";
+/// Request header naming the key the response body should be sealed to.
+pub const REPLY_KEY_ID_HEADER: &str = "dryoc-reply-key-id";
+/// HPKE `info` parameter used for sealing bodies.
+pub const HPKE_INFO: &[u8] = b"dryoc http body";
+
+/// Boxed error type returned by [`SealedBody`], as used by tower.
+pub type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+/// Boxed future type returned by [`SealedBody`].
+pub type BoxFuture<T> = Pin<Box<dyn Future<Output = Result<T, BoxError>> + Send>>;
+
+/// Resolves key IDs to keys, for sealing and unsealing bodies.
+pub trait KeyResolver {
+    /// Returns the keypair with `key_id`, used to unseal bodies sealed to it.
+    fn keypair(&self, key_id: &str) -> Option<StackKeyPair>;
+
+    /// Returns the public key with `key_id`, used to seal bodies to it.
+    fn public_key(&self, key_id: &str) -> Option<PublicKey>;
+}
+
+/// A [`KeyResolver`] with a fixed set of keys.
+#[derive(Clone, Default)]
+pub struct StaticKeyResolver {
+    keypairs: HashMap<String, StackKeyPair>,
+    public_keys: HashMap<String, PublicKey>,
+}
+
+impl StaticKeyResolver {
+    /// Returns a new resolver without any keys.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds `keypair` with `key_id`. The keypair's public key is also added
+    /// with the same ID.
+    pub fn with_keypair(mut self, key_id: &str, keypair: StackKeyPair) -> Self {
+        self.public_keys
+            .insert(key_id.into(), keypair.public_key.clone());
+        self.keypairs.insert(key_id.into(), keypair);
+        self
+    }
+
+    /// Adds `public_key` with `key_id`.
+    pub fn with_public_key(mut self, key_id: &str, public_key: PublicKey) -> Self {
+        self.public_keys.insert(key_id.into(), public_key);
+        self
+    }
+}
+
+impl KeyResolver for StaticKeyResolver {
+    fn keypair(&self, key_id: &str) -> Option<StackKeyPair> {
+        self.keypairs.get(key_id).cloned()
+    }
+
+    fn public_key(&self, key_id: &str) -> Option<PublicKey> {
+        self.public_keys.get(key_id).cloned()
+    }
+}
+
+/// Encryption scheme used for bodies. Both parties must use the same scheme.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Scheme {
+    /// Sealed boxes, compatible with libsodium's `crypto_box_seal`.
+    SealedBox,
+    /// HPKE in base mode, with DHKEM(X25519, HKDF-SHA256),
+    /// HKDF-SHA256 and ChaCha20-Poly1305. The body is the encapsulated key
+    /// followed by the ciphertext. The direction of the message (request or
+    /// response) is authenticated as associated data, so a sealed request
+    /// body can't be reflected back as a response.
+    Hpke,
+}
+
+#[derive(Clone, Copy)]
+enum Direction {
+    Request,
+    Response,
+}
+
+impl Direction {
+    fn aad(self) -> &'static [u8] {
+        match self {
+            Direction::Request => b"request",
+            Direction::Response => b"response",
+        }
+    }
+}
+
+impl Scheme {
+    fn seal(
+        self,
+        body: &[u8],
+        public_key: &PublicKey,
+        direction: Direction,
+    ) -> Result<Vec<u8>, Error> {
+        match self {
+            Scheme::SealedBox => Ok(VecBox::seal_to_vecbox(body, public_key)?.to_vec()),
+            Scheme::Hpke => {
+                let (enc, ciphertext) =
+                    hpke::seal_base::<DhKemX25519>(public_key, HPKE_INFO, direction.aad(), body)?;
+                let mut sealed = Vec::with_capacity(enc.len() + ciphertext.len());
+                sealed.extend_from_slice(crate::types::Bytes::as_slice(&enc));
+                sealed.extend_from_slice(&ciphertext);
+                Ok(sealed)
+            }
+        }
+    }
+
+    fn open(
+        self,
+        body: &[u8],
+        keypair: &StackKeyPair,
+        direction: Direction,
+    ) -> Result<Vec<u8>, Error> {
+        match self {
+            Scheme::SealedBox => VecBox::from_sealed_bytes(body)?.unseal_to_vec(keypair),
+            Scheme::Hpke => {
+                if body.len() < CRYPTO_BOX_PUBLICKEYBYTES {
+                    return Err(dryoc_error!("sealed body too short"));
+                }
+                let (enc, ciphertext) = body.split_at(CRYPTO_BOX_PUBLICKEYBYTES);
+                let enc = Ciphertext::try_from(enc)?;
+                hpke::open_base::<DhKemX25519>(
+                    &enc,
+                    keypair,
+                    HPKE_INFO,
+                    direction.aad(),
+                    ciphertext,
+                )
+            }
+        }
+    }
+}
+
+#[derive(Clone)]
+enum Role {
+    Client {
+        peer_key_id: String,
+        own_key_id: String,
+    },
+    Server,
+}
+
+/// A [`tower_layer::Layer`] which seals and unseals HTTP bodies. Refer to the
+/// [module documentation](self) for details.
+pub struct SealedBodyLayer<R> {
+    resolver: Arc<R>,
+    scheme: Scheme,
+    role: Role,
+}
+
+impl<R> Clone for SealedBodyLayer<R> {
+    fn clone(&self) -> Self {
+        Self {
+            resolver: self.resolver.clone(),
+            scheme: self.scheme,
+            role: self.role.clone(),
+        }
+    }
+}
+
+impl<R: KeyResolver> SealedBodyLayer<R> {
+    /// Returns a new server layer, which unseals request bodies and seals
+    /// response bodies, using keys from `resolver`. Uses
+    /// [`Scheme::SealedBox`] by default.
+    pub fn server(resolver: R) -> Self {
+        Self {
+            resolver: Arc::new(resolver),
+            scheme: Scheme::SealedBox,
+            role: Role::Server,
+        }
+    }
+
+    /// Returns a new client layer, which seals request bodies to the public
+    /// key `peer_key_id`, and unseals response bodies with the keypair
+    /// `own_key_id`, using keys from `resolver`. Uses [`Scheme::SealedBox`]
+    /// by default.
+    pub fn client(resolver: R, peer_key_id: &str, own_key_id: &str) -> Self {
+        Self {
+            resolver: Arc::new(resolver),
+            scheme: Scheme::SealedBox,
+            role: Role::Client {
+                peer_key_id: peer_key_id.into(),
+                own_key_id: own_key_id.into(),
+            },
+        }
+    }
+
+    /// Sets the encryption scheme used for bodies.
+    pub fn with_scheme(self, scheme: Scheme) -> Self {
+        Self { scheme, ..self }
+    }
+}
+
+impl<S, R> Layer<S> for SealedBodyLayer<R> {
+    type Service = SealedBody<S, R>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        SealedBody {
+            inner,
+            layer: self.clone(),
+        }
+    }
+}
+
+/// A [`tower_service::Service`] which seals and unseals HTTP bodies, created
+/// by [`SealedBodyLayer`].
+pub struct SealedBody<S, R> {
+    inner: S,
+    layer: SealedBodyLayer<R>,
+}
+
+impl<S: Clone, R> Clone for SealedBody<S, R> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            layer: self.layer.clone(),
+        }
+    }
+}
+
+fn header_str<'a>(headers: &'a http::HeaderMap, name: &str) -> Option<&'a str> {
+    headers.get(name).and_then(|value| value.to_str().ok())
+}
+
+fn set_content_length(headers: &mut http::HeaderMap, length: usize) {
+    headers.insert(CONTENT_LENGTH, HeaderValue::from(length));
+}
+
+fn key_id_value(key_id: &str) -> Result<HeaderValue, Error> {
+    HeaderValue::from_str(key_id).map_err(|_| dryoc_error!("invalid key ID"))
+}
+
+fn bad_request(message: &'static str) -> Response<Bytes> {
+    let mut response = Response::new(Bytes::from_static(message.as_bytes()));
+    *response.status_mut() = StatusCode::BAD_REQUEST;
+    set_content_length(response.headers_mut(), message.len());
+    response
+}
+
+impl<S, R> SealedBody<S, R>
+where
+    R: KeyResolver,
+{
+    fn unseal_request(
+        &self,
+        request: Request<Bytes>,
+    ) -> Result<(Request<Bytes>, Option<PublicKey>), &'static str> {
+        let (mut parts, body) = request.into_parts();
+        let key_id =
+            header_str(&parts.headers, KEY_ID_HEADER).ok_or("missing sealed body key ID")?;
+        let keypair = self
+            .layer
+            .resolver
+            .keypair(key_id)
+            .ok_or("unknown sealed body key ID")?;
+        let reply_key = match header_str(&parts.headers, REPLY_KEY_ID_HEADER) {
+            Some(reply_key_id) => Some(
+                self.layer
+                    .resolver
+                    .public_key(reply_key_id)
+                    .ok_or("unknown reply key ID")?,
+            ),
+            None => None,
+        };
+        let body = self
+            .layer
+            .scheme
+            .open(&body, &keypair, Direction::Request)
+            .map_err(|_| "unable to unseal body")?;
+
+        parts.headers.remove(KEY_ID_HEADER);
+        set_content_length(&mut parts.headers, body.len());
+        Ok((Request::from_parts(parts, Bytes::from(body)), reply_key))
+    }
+
+    fn seal_request(
+        &self,
+        request: Request<Bytes>,
+        peer_key_id: &str,
+        own_key_id: &str,
+    ) -> Result<Request<Bytes>, Error> {
+        let (mut parts, body) = request.into_parts();
+        let public_key = self
+            .layer
+            .resolver
+            .public_key(peer_key_id)
+            .ok_or_else(|| dryoc_error!(format!("unknown key ID {}", peer_key_id)))?;
+        let body = self
+            .layer
+            .scheme
+            .seal(&body, &public_key, Direction::Request)?;
+
+        parts
+            .headers
+            .insert(KEY_ID_HEADER, key_id_value(peer_key_id)?);
+        parts
+            .headers
+            .insert(REPLY_KEY_ID_HEADER, key_id_value(own_key_id)?);
+        set_content_length(&mut parts.headers, body.len());
+        Ok(Request::from_parts(parts, Bytes::from(body)))
+    }
+}
+
+fn seal_response(
+    response: Response<Bytes>,
+    scheme: Scheme,
+    public_key: &PublicKey,
+    key_id: HeaderValue,
+) -> Result<Response<Bytes>, Error> {
+    let (mut parts, body) = response.into_parts();
+    let body = scheme.seal(&body, public_key, Direction::Response)?;
+    parts.headers.insert(KEY_ID_HEADER, key_id);
+    set_content_length(&mut parts.headers, body.len());
+    Ok(Response::from_parts(parts, Bytes::from(body)))
+}
+
+fn unseal_response<R: KeyResolver>(
+    response: Response<Bytes>,
+    scheme: Scheme,
+    resolver: &R,
+) -> Result<Response<Bytes>, Error> {
+    let (mut parts, body) = response.into_parts();
+    let key_id = header_str(&parts.headers, KEY_ID_HEADER)
+        .ok_or_else(|| dryoc_error!("response body isn't sealed"))?;
+    let keypair = resolver
+        .keypair(key_id)
+        .ok_or_else(|| dryoc_error!(format!("unknown key ID {}", key_id)))?;
+    let body = scheme.open(&body, &keypair, Direction::Response)?;
+    parts.headers.remove(KEY_ID_HEADER);
+    set_content_length(&mut parts.headers, body.len());
+    Ok(Response::from_parts(parts, Bytes::from(body)))
+}
+
+impl<S, R> Service<Request<Bytes>> for SealedBody<S, R>
+where
+    S: Service<Request<Bytes>, Response = Response<Bytes>>,
+    S::Error: Into<BoxError>,
+    S::Future: Send + 'static,
+    R: KeyResolver + Send + Sync + 'static,
+{
+    type Error = BoxError;
+    type Future = BoxFuture<Response<Bytes>>;
+    type Response = Response<Bytes>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), BoxError>> {
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, request: Request<Bytes>) -> Self::Future {
+        let scheme = self.layer.scheme;
+        match self.layer.role.clone() {
+            Role::Server => {
+                let (request, reply_key) = match self.unseal_request(request) {
+                    Ok(unsealed) => unsealed,
+                    Err(message) => return Box::pin(async move { Ok(bad_request(message)) }),
+                };
+                let reply_key_id = request.headers().get(REPLY_KEY_ID_HEADER).cloned();
+                let future = self.inner.call(request);
+                Box::pin(async move {
+                    let response = future.await.map_err(Into::into)?;
+                    match (reply_key, reply_key_id) {
+                        (Some(public_key), Some(key_id)) => {
+                            Ok(seal_response(response, scheme, &public_key, key_id)?)
+                        }
+                        _ => Ok(response),
+                    }
+                })
+            }
+            Role::Client {
+                peer_key_id,
+                own_key_id,
+            } => {
+                let request = match self.seal_request(request, &peer_key_id, &own_key_id) {
+                    Ok(request) => request,
+                    Err(err) => return Box::pin(async move { Err(err.into()) }),
+                };
+                let resolver = self.layer.resolver.clone();
+                let future = self.inner.call(request);
+                Box::pin(async move {
+                    let response = future.await.map_err(Into::into)?;
+                    Ok(unseal_response(response, scheme, resolver.as_ref())?)
+                })
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::Infallible;
+    use std::future::Ready;
+    use std::sync::Mutex;
+    use std::task::{RawWaker, RawWakerVTable, Waker};
+
+    use super::*;
+
+    /// Echoes the request body back, and records the body it received.
+    #[derive(Clone, Default)]
+    struct Echo {
+        received: Arc<Mutex<Vec<Bytes>>>,
+    }
+
+    impl Service<Request<Bytes>> for Echo {
+        type Error = Infallible;
+        type Future = Ready<Result<Response<Bytes>, Infallible>>;
+        type Response = Response<Bytes>;
+
+        fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Infallible>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, request: Request<Bytes>) -> Self::Future {
+            self.received.lock().unwrap().push(request.body().clone());
+            std::future::ready(Ok(Response::new(request.into_body())))
+        }
+    }
+
+    /// Polls a future which is expected to be ready immediately.
+    fn ready<F: Future>(future: F) -> F::Output {
+        fn noop_raw_waker() -> RawWaker {
+            fn clone(_: *const ()) -> RawWaker {
+                noop_raw_waker()
+            }
+            fn noop(_: *const ()) {}
+            static VTABLE: RawWakerVTable = RawWakerVTable::new(clone, noop, noop, noop);
+            RawWaker::new(std::ptr::null(), &VTABLE)
+        }
+        let waker = unsafe { Waker::from_raw(noop_raw_waker()) };
+        let mut cx = Context::from_waker(&waker);
+        let mut future = Box::pin(future);
+        match future.as_mut().poll(&mut cx) {
+            Poll::Ready(output) => output,
+            Poll::Pending => panic!("future not ready"),
+        }
+    }
+
+    fn layers(
+        scheme: Scheme,
+    ) -> (
+        SealedBodyLayer<StaticKeyResolver>,
+        SealedBodyLayer<StaticKeyResolver>,
+    ) {
+        let server_keypair = StackKeyPair::gen();
+        let client_keypair = StackKeyPair::gen();
+        let server_keys = StaticKeyResolver::new()
+            .with_keypair("server", server_keypair.clone())
+            .with_public_key("client", client_keypair.public_key.clone());
+        let client_keys = StaticKeyResolver::new()
+            .with_keypair("client", client_keypair)
+            .with_public_key("server", server_keypair.public_key.clone());
+        (
+            SealedBodyLayer::client(client_keys, "server", "client").with_scheme(scheme),
+            SealedBodyLayer::server(server_keys).with_scheme(scheme),
+        )
+    }
+
+    #[test]
+    fn test_round_trip() {
+        for scheme in [Scheme::SealedBox, Scheme::Hpke] {
+            let (client_layer, server_layer) = layers(scheme);
+            let echo = Echo::default();
+            let mut client = client_layer.layer(server_layer.layer(echo.clone()));
+
+            let request = Request::new(Bytes::from_static(b"secret payload"));
+            let response = ready(client.call(request)).expect("call failed");
+
+            assert_eq!(response.status(), StatusCode::OK);
+            assert_eq!(response.body(), &Bytes::from_static(b"secret payload"));
+            assert_eq!(
+                response.headers().get(CONTENT_LENGTH).unwrap(),
+                &HeaderValue::from(14usize)
+            );
+            assert_eq!(
+                echo.received.lock().unwrap().as_slice(),
+                &[Bytes::from_static(b"secret payload")]
+            );
+        }
+    }
+
+    #[test]
+    fn test_sealed_on_the_wire() {
+        let (client_layer, _) = layers(Scheme::Hpke);
+        let echo = Echo::default();
+        let mut client = client_layer.layer(echo.clone());
+
+        let request = Request::new(Bytes::from_static(b"secret payload"));
+        // the echoed body is sealed to the server, and the response isn't
+        // sealed, so it's rejected
+        assert!(ready(client.call(request)).is_err());
+
+        let received = echo.received.lock().unwrap();
+        assert_eq!(received.len(), 1);
+        assert_ne!(received[0], Bytes::from_static(b"secret payload"));
+    }
+
+    #[test]
+    fn test_server_rejects() {
+        let (_, server_layer) = layers(Scheme::SealedBox);
+        let echo = Echo::default();
+        let mut server = server_layer.layer(echo.clone());
+
+        // plaintext request
+        let request = Request::new(Bytes::from_static(b"plaintext"));
+        let response = ready(server.call(request)).expect("call failed");
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        // tampered request
+        let request = Request::builder()
+            .header(KEY_ID_HEADER, "server")
+            .body(Bytes::from(vec![0u8; 64]))
+            .unwrap();
+        let response = ready(server.call(request)).expect("call failed");
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        // unknown key
+        let request = Request::builder()
+            .header(KEY_ID_HEADER, "someone else")
+            .body(Bytes::from(vec![0u8; 64]))
+            .unwrap();
+        let response = ready(server.call(request)).expect("call failed");
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        assert!(echo.received.lock().unwrap().is_empty());
+    }
+}
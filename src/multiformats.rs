@@ -0,0 +1,565 @@
+//! # Multiformats
+//!
+//! Encoding and decoding of [multiformats](https://multiformats.io/), for
+//! interoperability with IPFS, libp2p, and other systems which identify keys
+//! and digests with self-describing prefixes:
+//!
+//! * [`multibase`] prefixes a string encoding with a character identifying the
+//!   base, such as `z` for base58btc
+//! * [`multihash`] prefixes a digest with varints identifying the hash function
+//!   and digest length, such as `0xb220` for 32 byte [generic
+//!   hashes](crate::generichash) (BLAKE2b-256)
+//! * [`multikey`] prefixes a public key with a varint identifying the key type,
+//!   and encodes it with base58btc, as used by `did:key` identifiers
+//!
+//! Only the lowercase variants of the base16 and base32 encodings, and the
+//! hash functions and key types implemented by dryoc, are supported.
+//!
+//! ## Example
+//!
+//! ```
+//! use dryoc::multiformats::*;
+//! use dryoc::sign::SigningKeyPair;
+//!
+//! let keypair = SigningKeyPair::gen_with_defaults();
+//! let encoded = multikey::encode_signing_public_key(&keypair.public_key);
+//! assert!(encoded.starts_with("z6Mk"));
+//! let public_key = multikey::decode_signing_public_key(&encoded).expect("decode failed");
+//! assert_eq!(public_key, keypair.public_key);
+//!
+//! let hash = multihash::Multihash::generichash(b"hello world");
+//! let encoded = multibase::encode(multibase::Base::Base32, &hash.to_bytes());
+//! let decoded = multihash::Multihash::from_multibase(&encoded).expect("decode failed");
+//! assert_eq!(decoded, hash);
+//! ```
+use crate::error::Error;
+
+/// Maximum length of an unsigned varint, as specified by multiformats.
+const VARINT_MAX_BYTES: usize = 9;
+
+fn write_varint(output: &mut Vec<u8>, mut n: u64) {
+    while n >= 0x80 {
+        output.push((n as u8) | 0x80);
+        n >>= 7;
+    }
+    output.push(n as u8);
+}
+
+/// Reads a minimally encoded unsigned varint, returning it and the remaining
+/// input.
+fn read_varint(input: &[u8]) -> Result<(u64, &[u8]), Error> {
+    let mut n = 0u64;
+    for (i, byte) in input.iter().take(VARINT_MAX_BYTES).enumerate() {
+        n |= ((byte & 0x7f) as u64) << (7 * i);
+        if byte & 0x80 == 0 {
+            if *byte == 0 && i > 0 {
+                return Err(dryoc_error!("varint isn't minimally encoded"));
+            }
+            return Ok((n, &input[i + 1..]));
+        }
+    }
+    Err(dryoc_error!("truncated or overlong varint"))
+}
+
+/// # Multibase string encodings
+pub mod multibase {
+    use super::*;
+
+    const BASE16_ALPHABET: &[u8] = b"0123456789abcdef";
+    const BASE32_ALPHABET: &[u8] = b"abcdefghijklmnopqrstuvwxyz234567";
+    const BASE58_ALPHABET: &[u8] = b"123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+    const BASE64URL_ALPHABET: &[u8] =
+        b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+    /// Multibase encodings.
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    pub enum Base {
+        /// Lowercase hexadecimal, with the prefix `f`
+        Base16,
+        /// Lowercase RFC 4648 base32 without padding, with the prefix `b`
+        Base32,
+        /// Bitcoin's base58 alphabet, with the prefix `z`
+        Base58Btc,
+        /// RFC 4648 URL-safe base64 without padding, with the prefix `u`
+        Base64Url,
+    }
+
+    impl Base {
+        /// Returns the multibase prefix character for this encoding.
+        pub fn prefix(&self) -> char {
+            match self {
+                Base::Base16 => 'f',
+                Base::Base32 => 'b',
+                Base::Base58Btc => 'z',
+                Base::Base64Url => 'u',
+            }
+        }
+
+        fn from_prefix(prefix: char) -> Result<Self, Error> {
+            match prefix {
+                'f' => Ok(Base::Base16),
+                'b' => Ok(Base::Base32),
+                'z' => Ok(Base::Base58Btc),
+                'u' => Ok(Base::Base64Url),
+                _ => Err(dryoc_error!(format!(
+                    "unsupported multibase prefix {:?}",
+                    prefix
+                ))),
+            }
+        }
+    }
+
+    /// Encodes `data` with `base`, including the multibase prefix.
+    pub fn encode(base: Base, data: &[u8]) -> String {
+        let mut output = String::new();
+        output.push(base.prefix());
+        match base {
+            Base::Base16 => encode_bits(&mut output, data, BASE16_ALPHABET, 4),
+            Base::Base32 => encode_bits(&mut output, data, BASE32_ALPHABET, 5),
+            Base::Base58Btc => encode_base58(&mut output, data),
+            Base::Base64Url => encode_bits(&mut output, data, BASE64URL_ALPHABET, 6),
+        }
+        output
+    }
+
+    /// Decodes the multibase string `encoded`, returning the encoding it used
+    /// and the decoded data.
+    pub fn decode(encoded: &str) -> Result<(Base, Vec<u8>), Error> {
+        let mut chars = encoded.chars();
+        let base = Base::from_prefix(
+            chars
+                .next()
+                .ok_or_else(|| dryoc_error!("empty multibase string"))?,
+        )?;
+        let encoded = chars.as_str().as_bytes();
+        let data = match base {
+            Base::Base16 => decode_bits(encoded, BASE16_ALPHABET, 4)?,
+            Base::Base32 => decode_bits(encoded, BASE32_ALPHABET, 5)?,
+            Base::Base58Btc => decode_base58(encoded)?,
+            Base::Base64Url => decode_bits(encoded, BASE64URL_ALPHABET, 6)?,
+        };
+        Ok((base, data))
+    }
+
+    fn position(alphabet: &[u8], c: u8) -> Result<u32, Error> {
+        alphabet
+            .iter()
+            .position(|a| *a == c)
+            .map(|p| p as u32)
+            .ok_or_else(|| dryoc_error!(format!("invalid character {:?}", c as char)))
+    }
+
+    fn encode_bits(output: &mut String, data: &[u8], alphabet: &[u8], bits: u32) {
+        let mask = (1u32 << bits) - 1;
+        let (mut buffer, mut count) = (0u32, 0u32);
+        for byte in data {
+            buffer = (buffer << 8) | *byte as u32;
+            count += 8;
+            while count >= bits {
+                count -= bits;
+                output.push(alphabet[((buffer >> count) & mask) as usize] as char);
+            }
+            buffer &= (1 << count) - 1;
+        }
+        if count > 0 {
+            output.push(alphabet[((buffer << (bits - count)) & mask) as usize] as char);
+        }
+    }
+
+    fn decode_bits(encoded: &[u8], alphabet: &[u8], bits: u32) -> Result<Vec<u8>, Error> {
+        let mut output = Vec::with_capacity(encoded.len() * bits as usize / 8);
+        let (mut buffer, mut count) = (0u32, 0u32);
+        for c in encoded {
+            buffer = (buffer << bits) | position(alphabet, *c)?;
+            count += bits;
+            if count >= 8 {
+                count -= 8;
+                output.push((buffer >> count) as u8);
+            }
+            buffer &= (1 << count) - 1;
+        }
+        // Reject trailing characters which don't complete a byte, and
+        // non-zero padding bits, so each input has one encoding
+        if count >= bits || buffer != 0 {
+            return Err(dryoc_error!("invalid encoding length or padding"));
+        }
+        Ok(output)
+    }
+
+    fn encode_base58(output: &mut String, data: &[u8]) {
+        let zeros = data.iter().take_while(|b| **b == 0).count();
+        // Little-endian base58 digits
+        let mut digits: Vec<u8> = Vec::with_capacity(data.len() * 138 / 100 + 1);
+        for byte in &data[zeros..] {
+            let mut carry = *byte as u32;
+            for digit in digits.iter_mut() {
+                carry += (*digit as u32) << 8;
+                *digit = (carry % 58) as u8;
+                carry /= 58;
+            }
+            while carry > 0 {
+                digits.push((carry % 58) as u8);
+                carry /= 58;
+            }
+        }
+        output.extend(std::iter::repeat('1').take(zeros));
+        output.extend(
+            digits
+                .iter()
+                .rev()
+                .map(|d| BASE58_ALPHABET[*d as usize] as char),
+        );
+    }
+
+    fn decode_base58(encoded: &[u8]) -> Result<Vec<u8>, Error> {
+        let zeros = encoded.iter().take_while(|c| **c == b'1').count();
+        // Little-endian bytes
+        let mut bytes: Vec<u8> = Vec::with_capacity(encoded.len() * 733 / 1000 + 1);
+        for c in &encoded[zeros..] {
+            let mut carry = position(BASE58_ALPHABET, *c)?;
+            for byte in bytes.iter_mut() {
+                carry += (*byte as u32) * 58;
+                *byte = carry as u8;
+                carry >>= 8;
+            }
+            while carry > 0 {
+                bytes.push(carry as u8);
+                carry >>= 8;
+            }
+        }
+        let mut output = vec![0u8; zeros];
+        output.extend(bytes.iter().rev());
+        Ok(output)
+    }
+}
+
+/// # Self-describing digests
+pub mod multihash {
+    use super::*;
+    use crate::constants::{
+        CRYPTO_GENERICHASH_BYTES, CRYPTO_GENERICHASH_BYTES_MAX, CRYPTO_GENERICHASH_BYTES_MIN,
+        CRYPTO_GENERICHASH_KEYBYTES,
+    };
+    use crate::generichash::GenericHash;
+
+    /// Multicodec code for the identity "hash", which contains the input
+    /// itself.
+    pub const IDENTITY: u64 = 0x00;
+    /// Multicodec code for SHA-256.
+    pub const SHA2_256: u64 = 0x12;
+    /// Multicodec code for SHA-512.
+    pub const SHA2_512: u64 = 0x13;
+    /// Multicodec code for BLAKE2b-256, the default [generic
+    /// hash](crate::generichash).
+    pub const BLAKE2B_256: u64 = 0xb220;
+    /// Multicodec code for BLAKE2b-512.
+    pub const BLAKE2B_512: u64 = 0xb240;
+
+    /// Multicodec code for BLAKE2b-8. The code for BLAKE2b with a digest of
+    /// `n` bytes is this code plus `n - 1`.
+    const BLAKE2B_8: u64 = 0xb201;
+
+    /// A digest, along with the code of the hash function which produced it.
+    #[derive(Clone, Debug, PartialEq, Eq)]
+    pub struct Multihash {
+        code: u64,
+        digest: Vec<u8>,
+    }
+
+    impl Multihash {
+        /// Returns a new multihash for `digest`, produced by the hash function
+        /// with multicodec `code`. Returns an error if the digest length
+        /// doesn't match a hash function known to dryoc.
+        pub fn new(code: u64, digest: &[u8]) -> Result<Self, Error> {
+            let expected = match code {
+                SHA2_256 => Some(32),
+                SHA2_512 => Some(64),
+                code if (BLAKE2B_8..BLAKE2B_8 + 64).contains(&code) => {
+                    Some((code - BLAKE2B_8 + 1) as usize)
+                }
+                _ => None,
+            };
+            if let Some(expected) = expected {
+                validate!(expected, expected, digest.len(), "digest");
+            }
+            Ok(Self {
+                code,
+                digest: digest.to_vec(),
+            })
+        }
+
+        /// Returns a multihash for a [generic hash](crate::generichash)
+        /// digest, with the BLAKE2b code matching its length.
+        pub fn from_generichash(digest: &[u8]) -> Result<Self, Error> {
+            validate!(
+                CRYPTO_GENERICHASH_BYTES_MIN,
+                CRYPTO_GENERICHASH_BYTES_MAX,
+                digest.len(),
+                "digest"
+            );
+            Self::new(BLAKE2B_8 + digest.len() as u64 - 1, digest)
+        }
+
+        /// Computes the default unkeyed [generic hash](crate::generichash) of
+        /// `input`, and returns it as a BLAKE2b-256 multihash.
+        pub fn generichash(input: &[u8]) -> Self {
+            Self {
+                code: BLAKE2B_256,
+                digest:
+                    GenericHash::<CRYPTO_GENERICHASH_KEYBYTES, CRYPTO_GENERICHASH_BYTES>::digest(
+                        input,
+                    )
+                    .to_vec(),
+            }
+        }
+
+        /// Returns the multicodec code of the hash function.
+        pub fn code(&self) -> u64 {
+            self.code
+        }
+
+        /// Returns the digest.
+        pub fn digest(&self) -> &[u8] {
+            &self.digest
+        }
+
+        /// Returns the binary encoding of this multihash: the code and digest
+        /// length as varints, followed by the digest.
+        pub fn to_bytes(&self) -> Vec<u8> {
+            let mut output = Vec::with_capacity(2 * VARINT_MAX_BYTES + self.digest.len());
+            write_varint(&mut output, self.code);
+            write_varint(&mut output, self.digest.len() as u64);
+            output.extend_from_slice(&self.digest);
+            output
+        }
+
+        /// Decodes a multihash from its binary encoding.
+        pub fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+            let (code, rest) = read_varint(bytes)?;
+            let (len, digest) = read_varint(rest)?;
+            if len != digest.len() as u64 {
+                return Err(dryoc_error!(format!(
+                    "multihash length {} doesn't match digest length {}",
+                    len,
+                    digest.len()
+                )));
+            }
+            Self::new(code, digest)
+        }
+
+        /// Decodes a multihash from a multibase string, in any supported
+        /// encoding.
+        pub fn from_multibase(encoded: &str) -> Result<Self, Error> {
+            let (_, bytes) = multibase::decode(encoded)?;
+            Self::from_bytes(&bytes)
+        }
+    }
+}
+
+/// # Self-describing public keys
+pub mod multikey {
+    use super::*;
+    use crate::constants::{CRYPTO_BOX_PUBLICKEYBYTES, CRYPTO_SIGN_PUBLICKEYBYTES};
+    use crate::types::*;
+
+    /// Public key types.
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    pub enum KeyType {
+        /// An Ed25519 public key, as used by [crate::sign]
+        Ed25519,
+        /// An X25519 public key, as used by [crate::dryocbox] and [crate::kx]
+        X25519,
+    }
+
+    impl KeyType {
+        /// Returns the multicodec code for this key type.
+        pub fn code(&self) -> u64 {
+            match self {
+                KeyType::Ed25519 => 0xed,
+                KeyType::X25519 => 0xec,
+            }
+        }
+
+        fn from_code(code: u64) -> Result<Self, Error> {
+            match code {
+                0xed => Ok(KeyType::Ed25519),
+                0xec => Ok(KeyType::X25519),
+                _ => Err(dryoc_error!(format!(
+                    "unsupported multikey code {:#x}",
+                    code
+                ))),
+            }
+        }
+    }
+
+    /// Encodes `public_key` of type `key_type` as a base58btc multibase
+    /// string.
+    pub fn encode(key_type: KeyType, public_key: &[u8]) -> String {
+        let mut bytes = Vec::with_capacity(VARINT_MAX_BYTES + public_key.len());
+        write_varint(&mut bytes, key_type.code());
+        bytes.extend_from_slice(public_key);
+        multibase::encode(multibase::Base::Base58Btc, &bytes)
+    }
+
+    /// Decodes a multikey from a multibase string, returning the key type and
+    /// the public key.
+    pub fn decode(encoded: &str) -> Result<(KeyType, Vec<u8>), Error> {
+        let (_, bytes) = multibase::decode(encoded)?;
+        let (code, public_key) = read_varint(&bytes)?;
+        let key_type = KeyType::from_code(code)?;
+        validate!(32, 32, public_key.len(), "public_key");
+        Ok((key_type, public_key.to_vec()))
+    }
+
+    /// Encodes an Ed25519 signing public key, such as
+    /// [`crate::sign::PublicKey`].
+    pub fn encode_signing_public_key<PublicKey: ByteArray<CRYPTO_SIGN_PUBLICKEYBYTES>>(
+        public_key: &PublicKey,
+    ) -> String {
+        encode(KeyType::Ed25519, public_key.as_slice())
+    }
+
+    /// Decodes an Ed25519 signing public key. Returns an error if the multikey
+    /// is of another type.
+    pub fn decode_signing_public_key(encoded: &str) -> Result<crate::sign::PublicKey, Error> {
+        decode_key(encoded, KeyType::Ed25519)
+    }
+
+    /// Encodes an X25519 public key, such as [`crate::dryocbox::PublicKey`].
+    pub fn encode_box_public_key<PublicKey: ByteArray<CRYPTO_BOX_PUBLICKEYBYTES>>(
+        public_key: &PublicKey,
+    ) -> String {
+        encode(KeyType::X25519, public_key.as_slice())
+    }
+
+    /// Decodes an X25519 public key. Returns an error if the multikey is of
+    /// another type.
+    pub fn decode_box_public_key(encoded: &str) -> Result<crate::dryocbox::PublicKey, Error> {
+        decode_key(encoded, KeyType::X25519)
+    }
+
+    fn decode_key<PublicKey: NewByteArray<32>>(
+        encoded: &str,
+        expected: KeyType,
+    ) -> Result<PublicKey, Error> {
+        let (key_type, public_key) = decode(encoded)?;
+        if key_type != expected {
+            return Err(dryoc_error!(format!(
+                "expected {:?} multikey, got {:?}",
+                expected, key_type
+            )));
+        }
+        let mut output = PublicKey::new_byte_array();
+        output.copy_from_slice(&public_key);
+        Ok(output)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::multibase::Base;
+    use super::multihash::*;
+    use super::*;
+
+    #[test]
+    fn test_multibase() {
+        // https://github.com/multiformats/multibase/tree/master/tests
+        let data = b"yes mani !";
+        for (base, encoded) in [
+            (Base::Base16, "f796573206d616e692021"),
+            (Base::Base32, "bpfsxgidnmfxgsibb"),
+            (Base::Base58Btc, "z7paNL19xttacUY"),
+            (Base::Base64Url, "ueWVzIG1hbmkgIQ"),
+        ] {
+            assert_eq!(multibase::encode(base, data), encoded);
+            assert_eq!(
+                multibase::decode(encoded).expect("decode"),
+                (base, data.to_vec())
+            );
+        }
+
+        assert_eq!(
+            multibase::encode(Base::Base58Btc, b"\0\0yes mani !"),
+            "z117paNL19xttacUY"
+        );
+        for base in [Base::Base16, Base::Base32, Base::Base58Btc, Base::Base64Url] {
+            for len in 0..40 {
+                let data = crate::rng::randombytes_buf(len);
+                let encoded = multibase::encode(base, &data);
+                assert_eq!(multibase::decode(&encoded).expect("decode").1, data);
+            }
+        }
+
+        for invalid in [
+            "",
+            "Q",
+            "f7",
+            "f0G",
+            "bpfsxgidnmfxgsibba",
+            "bmf",
+            "z0",
+            "ueWVzIG1hbmkgIR",
+        ] {
+            assert!(multibase::decode(invalid).is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_multihash() {
+        let hash = Multihash::generichash(b"hello world");
+        assert_eq!(hash.code(), BLAKE2B_256);
+        assert_eq!(
+            hex::encode(hash.to_bytes()),
+            "a0e40220256c83b297114d201b30179f3f0ef0cace9783622da5974326b436178aeef610"
+        );
+        assert_eq!(
+            Multihash::from_bytes(&hash.to_bytes()).expect("decode"),
+            hash
+        );
+        assert_eq!(
+            Multihash::from_generichash(hash.digest()).expect("multihash"),
+            hash
+        );
+        assert_eq!(
+            Multihash::from_generichash(&[0u8; 64])
+                .expect("multihash")
+                .code(),
+            BLAKE2B_512
+        );
+
+        let sha256 =
+            hex::decode("1220b94d27b9934d3e08a52e52d7da7dabfac484efe37a5380ee9088f7ace2efcde9")
+                .unwrap();
+        let hash = Multihash::from_bytes(&sha256).expect("decode");
+        assert_eq!(hash.code(), SHA2_256);
+        assert_eq!(hash.to_bytes(), sha256);
+
+        // wrong lengths, truncation, and non-minimal varints
+        assert!(Multihash::new(SHA2_256, &[0u8; 31]).is_err());
+        assert!(Multihash::from_bytes(&sha256[..sha256.len() - 1]).is_err());
+        assert!(Multihash::from_bytes(&[0x92, 0x00, 0x00]).is_err());
+        assert!(Multihash::from_bytes(&[0xff; 12]).is_err());
+    }
+
+    #[test]
+    fn test_multikey() {
+        let key: Vec<u8> = (0..32).collect();
+        assert_eq!(
+            multikey::encode(multikey::KeyType::Ed25519, &key),
+            "z6MkeTGwHmLmuCmgg4ABYhzWVh6ZX7hTwWt8gguAretUfc9c"
+        );
+        assert_eq!(
+            multikey::encode(multikey::KeyType::X25519, &key),
+            "z6LSbgC4DpuCf7zxewhFPnYcyBm3YgxjEEovsehvWqZzTm8z"
+        );
+
+        let keypair = crate::dryocbox::KeyPair::gen();
+        let encoded = multikey::encode_box_public_key(&keypair.public_key);
+        assert_eq!(
+            multikey::decode_box_public_key(&encoded).expect("decode"),
+            keypair.public_key
+        );
+        assert!(multikey::decode_signing_public_key(&encoded).is_err());
+        assert!(multikey::decode(&encoded[..encoded.len() - 1]).is_err());
+    }
+}
@@ -0,0 +1,526 @@
+//! # Multi-signatures
+//!
+//! An n-of-n multi-signature scheme for Ed25519, in the style of
+//! [MuSig2](https://eprint.iacr.org/2020/1261). A group of signers, each with
+//! their own [`SigningKeyPair`], jointly produce a single 64-byte signature
+//! which is a regular Ed25519 signature for the group's aggregate public key.
+//! Verifiers don't need to know that the signature was produced by more than
+//! one party: it can be checked with [`crypto_sign_verify_detached`] or any
+//! other Ed25519 implementation.
+//!
+//! The aggregate public key is a weighted sum of the signers' public keys,
+//! where each weight is derived from the entire set of keys. This prevents
+//! rogue-key attacks, in which a malicious signer chooses their public key as
+//! a function of the others' keys to control the aggregate key.
+//!
+//! Signing takes two rounds:
+//!
+//! 1. Each signer calls [`gen_nonce`], keeps the [`SecretNonce`], and sends the
+//!    [`PublicNonce`] to the other signers (or to an untrusted aggregator).
+//! 2. Once all public nonces are known, each signer creates a
+//!    [`SigningSession`] with the same keys, nonces, and message, and calls
+//!    [`SigningSession::partial_sign`], sending the [`PartialSignature`] to the
+//!    aggregator. The aggregator combines the partial signatures with
+//!    [`SigningSession::aggregate`].
+//!
+//! The first round doesn't depend on the message, so nonces can be exchanged
+//! ahead of time. A secret nonce must never be used for more than one
+//! signature, as that reveals the signer's secret key. [`SecretNonce`] can't
+//! be cloned or serialized, and is consumed when signing.
+//!
+//! All parties must use the public keys and public nonces in the same order.
+//!
+//! ## Example
+//!
+//! ```
+//! use dryoc::classic::crypto_sign::crypto_sign_verify_detached;
+//! use dryoc::musig::*;
+//! use dryoc::sign::SigningKeyPair;
+//! use dryoc::types::*;
+//!
+//! let alice = SigningKeyPair::gen_with_defaults();
+//! let bob = SigningKeyPair::gen_with_defaults();
+//! let message = b"We, the undersigned";
+//!
+//! let context = KeyAggContext::new(&[alice.public_key.clone(), bob.public_key.clone()])
+//!     .expect("invalid keys");
+//!
+//! // Round 1: exchange public nonces
+//! let (alice_secret_nonce, alice_public_nonce) = gen_nonce(&alice);
+//! let (bob_secret_nonce, bob_public_nonce) = gen_nonce(&bob);
+//! let public_nonces = [alice_public_nonce, bob_public_nonce];
+//!
+//! // Round 2: exchange partial signatures
+//! let session = SigningSession::new(&context, &public_nonces, message).expect("invalid nonces");
+//! let alice_partial = session
+//!     .partial_sign(alice_secret_nonce, &alice)
+//!     .expect("signing failed");
+//! let bob_partial = session
+//!     .partial_sign(bob_secret_nonce, &bob)
+//!     .expect("signing failed");
+//!
+//! let signature = session
+//!     .aggregate(&[alice_partial, bob_partial])
+//!     .expect("aggregation failed");
+//!
+//! // The result is a regular Ed25519 signature
+//! crypto_sign_verify_detached(
+//!     signature.as_array(),
+//!     message,
+//!     context.aggregate_public_key().as_array(),
+//! )
+//! .expect("verification failed");
+//! ```
+//!
+//! [`crypto_sign_verify_detached`]: crate::classic::crypto_sign::crypto_sign_verify_detached
+use curve25519_dalek::constants::ED25519_BASEPOINT_TABLE;
+use curve25519_dalek::edwards::{CompressedEdwardsY, EdwardsPoint};
+use curve25519_dalek::scalar::Scalar;
+use curve25519_dalek::traits::Identity;
+use sha2::{Digest, Sha512};
+use zeroize::{Zeroize, ZeroizeOnDrop};
+
+use crate::constants::{CRYPTO_SIGN_PUBLICKEYBYTES, CRYPTO_SIGN_SECRETKEYBYTES};
+use crate::error::Error;
+use crate::rng::copy_randombytes;
+use crate::sign::{PublicKey, Signature, SigningKeyPair};
+use crate::types::*;
+
+/// Length of a public nonce, which is two encoded Edwards points.
+pub const MUSIG_PUBLICNONCEBYTES: usize = 64;
+/// Length of a partial signature, which is an encoded scalar.
+pub const MUSIG_PARTIALSIGNATUREBYTES: usize = 32;
+
+const DST_KEYAGG_LIST: &[u8] = b"dryoc-musig-v1 KeyAgg list";
+const DST_KEYAGG_COEFFICIENT: &[u8] = b"dryoc-musig-v1 KeyAgg coefficient";
+const DST_NONCE: &[u8] = b"dryoc-musig-v1 nonce";
+const DST_NONCE_COEFFICIENT: &[u8] = b"dryoc-musig-v1 nonce coefficient";
+
+/// Public nonce, sent to the other signers in the first round.
+pub type PublicNonce = StackByteArray<MUSIG_PUBLICNONCEBYTES>;
+/// Partial signature, sent to the aggregator in the second round.
+pub type PartialSignature = StackByteArray<MUSIG_PARTIALSIGNATUREBYTES>;
+
+/// Secret nonce, kept by a signer between the first and second rounds. It's
+/// consumed by [`SigningSession::partial_sign`], and must never be reused.
+#[derive(Zeroize, ZeroizeOnDrop)]
+pub struct SecretNonce {
+    r1: Scalar,
+    r2: Scalar,
+    public_key: [u8; CRYPTO_SIGN_PUBLICKEYBYTES],
+}
+
+/// The set of signers' public keys, and the aggregate public key derived from
+/// them.
+#[derive(Clone, Debug)]
+pub struct KeyAggContext {
+    public_keys: Vec<PublicKey>,
+    coefficients: Vec<Scalar>,
+    points: Vec<EdwardsPoint>,
+    aggregate_public_key: PublicKey,
+}
+
+/// A signing session for a single message, created once all public nonces
+/// are known.
+pub struct SigningSession<'a> {
+    context: &'a KeyAggContext,
+    public_nonces: Vec<(EdwardsPoint, EdwardsPoint)>,
+    nonce_coefficient: Scalar,
+    challenge: Scalar,
+    aggregate_nonce: [u8; 32],
+}
+
+fn decode_point(bytes: &[u8]) -> Result<EdwardsPoint, Error> {
+    let mut array = [0u8; 32];
+    array.copy_from_slice(bytes);
+    CompressedEdwardsY(array)
+        .decompress()
+        .filter(|point| !point.is_small_order() && point.is_torsion_free())
+        .ok_or_else(|| dryoc_error!("invalid point"))
+}
+
+fn hash_to_scalar(parts: &[&[u8]]) -> Scalar {
+    let mut hasher = Sha512::new();
+    for part in parts {
+        hasher.update(part);
+    }
+    let mut wide: [u8; 64] = hasher.finalize().into();
+    let scalar = Scalar::from_bytes_mod_order_wide(&wide);
+    wide.zeroize();
+    scalar
+}
+
+/// Returns the Ed25519 secret scalar for `secret_key`, as used by
+/// [`crypto_sign_detached`](crate::classic::crypto_sign::crypto_sign_detached).
+fn secret_scalar(secret_key: &[u8; CRYPTO_SIGN_SECRETKEYBYTES]) -> Scalar {
+    let mut hash: [u8; 64] = Sha512::digest(&secret_key[..32]).into();
+    hash[0] &= 248;
+    hash[31] &= 127;
+    hash[31] |= 64;
+    let mut bytes = [0u8; 32];
+    bytes.copy_from_slice(&hash[..32]);
+    let scalar = Scalar::from_bytes_mod_order(bytes);
+    hash.zeroize();
+    bytes.zeroize();
+    scalar
+}
+
+impl KeyAggContext {
+    /// Creates a new context for the signers' `public_keys`, which must be
+    /// distinct, and in the same order for all signers.
+    pub fn new<PK: ByteArray<CRYPTO_SIGN_PUBLICKEYBYTES>>(
+        public_keys: &[PK],
+    ) -> Result<Self, Error> {
+        if public_keys.is_empty() {
+            return Err(dryoc_error!("at least one public key is required"));
+        }
+
+        let public_keys: Vec<PublicKey> = public_keys
+            .iter()
+            .map(|public_key| PublicKey::from(*public_key.as_array()))
+            .collect();
+        for (i, public_key) in public_keys.iter().enumerate() {
+            if public_keys[..i].contains(public_key) {
+                return Err(dryoc_error!("duplicate public key"));
+            }
+        }
+
+        let mut list = Sha512::new();
+        list.update(DST_KEYAGG_LIST);
+        for public_key in &public_keys {
+            list.update(public_key.as_slice());
+        }
+        let list = list.finalize();
+
+        let points = public_keys
+            .iter()
+            .map(|public_key| decode_point(public_key.as_slice()))
+            .collect::<Result<Vec<_>, _>>()?;
+        let coefficients: Vec<Scalar> = public_keys
+            .iter()
+            .map(|public_key| {
+                hash_to_scalar(&[DST_KEYAGG_COEFFICIENT, &list, public_key.as_slice()])
+            })
+            .collect();
+        let aggregate = points
+            .iter()
+            .zip(&coefficients)
+            .fold(EdwardsPoint::identity(), |acc, (point, coefficient)| {
+                acc + coefficient * point
+            });
+
+        Ok(Self {
+            public_keys,
+            coefficients,
+            points,
+            aggregate_public_key: PublicKey::from(aggregate.compress().to_bytes()),
+        })
+    }
+
+    /// Returns the aggregate public key, which verifies the signatures
+    /// produced by the group.
+    pub fn aggregate_public_key(&self) -> &PublicKey {
+        &self.aggregate_public_key
+    }
+
+    /// Returns the signers' public keys.
+    pub fn public_keys(&self) -> &[PublicKey] {
+        &self.public_keys
+    }
+}
+
+/// Generates a new secret and public nonce pair for the holder of `keypair`.
+/// The public nonce is sent to the other signers, and the secret nonce is
+/// kept for [`SigningSession::partial_sign`].
+///
+/// Nonces are generated randomly, and also bound to the secret key, so that
+/// a weak random number generator alone doesn't leak the key.
+pub fn gen_nonce<
+    PK: ByteArray<CRYPTO_SIGN_PUBLICKEYBYTES> + Zeroize,
+    SK: ByteArray<CRYPTO_SIGN_SECRETKEYBYTES> + Zeroize,
+>(
+    keypair: &SigningKeyPair<PK, SK>,
+) -> (SecretNonce, PublicNonce) {
+    let mut rand = [0u8; 32];
+    copy_randombytes(&mut rand);
+    let r1 = hash_to_scalar(&[DST_NONCE, &rand, keypair.secret_key.as_slice(), &[1]]);
+    let r2 = hash_to_scalar(&[DST_NONCE, &rand, keypair.secret_key.as_slice(), &[2]]);
+    rand.zeroize();
+
+    let mut public_nonce = PublicNonce::new_byte_array();
+    public_nonce.as_mut_slice()[..32]
+        .copy_from_slice((ED25519_BASEPOINT_TABLE * &r1).compress().as_bytes());
+    public_nonce.as_mut_slice()[32..]
+        .copy_from_slice((ED25519_BASEPOINT_TABLE * &r2).compress().as_bytes());
+
+    (
+        SecretNonce {
+            r1,
+            r2,
+            public_key: *keypair.public_key.as_array(),
+        },
+        public_nonce,
+    )
+}
+
+impl<'a> SigningSession<'a> {
+    /// Creates a signing session for `message`, with the signers' public
+    /// nonces from the first round, in the same order as the public keys in
+    /// `context`.
+    pub fn new(
+        context: &'a KeyAggContext,
+        public_nonces: &[PublicNonce],
+        message: &[u8],
+    ) -> Result<Self, Error> {
+        if public_nonces.len() != context.public_keys.len() {
+            return Err(dryoc_error!(format!(
+                "expected {} public nonces, got {}",
+                context.public_keys.len(),
+                public_nonces.len()
+            )));
+        }
+
+        let public_nonces = public_nonces
+            .iter()
+            .map(|nonce| {
+                Ok((
+                    decode_point(&nonce.as_slice()[..32])?,
+                    decode_point(&nonce.as_slice()[32..])?,
+                ))
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+        let (r1, r2) = public_nonces.iter().fold(
+            (EdwardsPoint::identity(), EdwardsPoint::identity()),
+            |(r1, r2), (nonce1, nonce2)| (r1 + nonce1, r2 + nonce2),
+        );
+
+        let nonce_coefficient = hash_to_scalar(&[
+            DST_NONCE_COEFFICIENT,
+            context.aggregate_public_key.as_slice(),
+            r1.compress().as_bytes(),
+            r2.compress().as_bytes(),
+            message,
+        ]);
+        let aggregate_nonce = (r1 + nonce_coefficient * r2).compress().to_bytes();
+        // the Ed25519 challenge, H(R || A || M)
+        let challenge = hash_to_scalar(&[
+            &aggregate_nonce,
+            context.aggregate_public_key.as_slice(),
+            message,
+        ]);
+
+        Ok(Self {
+            context,
+            public_nonces,
+            nonce_coefficient,
+            challenge,
+            aggregate_nonce,
+        })
+    }
+
+    /// Creates the partial signature for the holder of `keypair`, consuming
+    /// their `secret_nonce` from the first round.
+    pub fn partial_sign<
+        PK: ByteArray<CRYPTO_SIGN_PUBLICKEYBYTES> + Zeroize,
+        SK: ByteArray<CRYPTO_SIGN_SECRETKEYBYTES> + Zeroize,
+    >(
+        &self,
+        secret_nonce: SecretNonce,
+        keypair: &SigningKeyPair<PK, SK>,
+    ) -> Result<PartialSignature, Error> {
+        if &secret_nonce.public_key != keypair.public_key.as_array() {
+            return Err(dryoc_error!("secret nonce belongs to a different signer"));
+        }
+        let index = self.index_of(keypair.public_key.as_array())?;
+
+        let mut secret = secret_scalar(keypair.secret_key.as_array());
+        if ED25519_BASEPOINT_TABLE * &secret != self.context.points[index] {
+            secret.zeroize();
+            return Err(dryoc_error!("secret key doesn't match public key"));
+        }
+        let (expected1, expected2) = &self.public_nonces[index];
+        if ED25519_BASEPOINT_TABLE * &secret_nonce.r1 != *expected1
+            || ED25519_BASEPOINT_TABLE * &secret_nonce.r2 != *expected2
+        {
+            secret.zeroize();
+            return Err(dryoc_error!("secret nonce doesn't match public nonce"));
+        }
+
+        let mut partial = secret_nonce.r1
+            + self.nonce_coefficient * secret_nonce.r2
+            + self.challenge * self.context.coefficients[index] * secret;
+        secret.zeroize();
+        let partial_signature = PartialSignature::from(partial.to_bytes());
+        partial.zeroize();
+
+        Ok(partial_signature)
+    }
+
+    /// Verifies the partial signature of the signer with `public_key`, which
+    /// lets the aggregator identify a misbehaving signer.
+    pub fn verify_partial<PK: ByteArray<CRYPTO_SIGN_PUBLICKEYBYTES>>(
+        &self,
+        public_key: &PK,
+        partial_signature: &PartialSignature,
+    ) -> Result<(), Error> {
+        let index = self.index_of(public_key.as_array())?;
+        let partial =
+            Option::<Scalar>::from(Scalar::from_canonical_bytes(*partial_signature.as_array()))
+                .ok_or_else(|| dryoc_error!("invalid partial signature"))?;
+
+        let (nonce1, nonce2) = &self.public_nonces[index];
+        let expected = nonce1
+            + self.nonce_coefficient * nonce2
+            + (self.challenge * self.context.coefficients[index]) * self.context.points[index];
+        if ED25519_BASEPOINT_TABLE * &partial == expected {
+            Ok(())
+        } else {
+            Err(dryoc_error!("partial signature mismatch"))
+        }
+    }
+
+    /// Combines the signers' partial signatures, in the same order as the
+    /// public keys, into an Ed25519 signature for the aggregate public key.
+    /// Use [`SigningSession::verify_partial`] to find out which signer is at
+    /// fault if the signature doesn't verify.
+    pub fn aggregate(&self, partial_signatures: &[PartialSignature]) -> Result<Signature, Error> {
+        if partial_signatures.len() != self.context.public_keys.len() {
+            return Err(dryoc_error!(format!(
+                "expected {} partial signatures, got {}",
+                self.context.public_keys.len(),
+                partial_signatures.len()
+            )));
+        }
+
+        let mut s = Scalar::ZERO;
+        for partial_signature in partial_signatures {
+            s +=
+                Option::<Scalar>::from(Scalar::from_canonical_bytes(*partial_signature.as_array()))
+                    .ok_or_else(|| dryoc_error!("invalid partial signature"))?;
+        }
+
+        let mut signature = Signature::new_byte_array();
+        signature.as_mut_slice()[..32].copy_from_slice(&self.aggregate_nonce);
+        signature.as_mut_slice()[32..].copy_from_slice(s.as_bytes());
+
+        Ok(signature)
+    }
+
+    fn index_of(&self, public_key: &[u8; CRYPTO_SIGN_PUBLICKEYBYTES]) -> Result<usize, Error> {
+        self.context
+            .public_keys
+            .iter()
+            .position(|key| key.as_array() == public_key)
+            .ok_or_else(|| dryoc_error!("public key isn't part of this signing session"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::classic::crypto_sign::crypto_sign_verify_detached;
+
+    fn sign_with(
+        keypairs: &[SigningKeyPair<PublicKey, crate::sign::SecretKey>],
+        message: &[u8],
+    ) -> (KeyAggContext, Signature) {
+        let public_keys: Vec<PublicKey> = keypairs.iter().map(|k| k.public_key.clone()).collect();
+        let context = KeyAggContext::new(&public_keys).expect("invalid keys");
+        let (secret_nonces, public_nonces): (Vec<_>, Vec<_>) =
+            keypairs.iter().map(gen_nonce).unzip();
+        let session =
+            SigningSession::new(&context, &public_nonces, message).expect("invalid nonces");
+        let partials: Vec<PartialSignature> = secret_nonces
+            .into_iter()
+            .zip(keypairs)
+            .map(|(nonce, keypair)| session.partial_sign(nonce, keypair).expect("sign"))
+            .collect();
+        for (keypair, partial) in keypairs.iter().zip(&partials) {
+            session
+                .verify_partial(&keypair.public_key, partial)
+                .expect("partial signature");
+        }
+        let signature = session.aggregate(&partials).expect("aggregate");
+        (context, signature)
+    }
+
+    #[test]
+    fn test_musig() {
+        for n in [1, 2, 5] {
+            let keypairs: Vec<_> = (0..n)
+                .map(|_| SigningKeyPair::gen_with_defaults())
+                .collect();
+            let (context, signature) = sign_with(&keypairs, b"attestation");
+            crypto_sign_verify_detached(
+                signature.as_array(),
+                b"attestation",
+                context.aggregate_public_key().as_array(),
+            )
+            .expect("verify");
+            assert!(
+                crypto_sign_verify_detached(
+                    signature.as_array(),
+                    b"something else",
+                    context.aggregate_public_key().as_array(),
+                )
+                .is_err()
+            );
+        }
+
+        // a single signer's aggregate key still differs from their own key
+        let keypair = SigningKeyPair::gen_with_defaults();
+        let context =
+            KeyAggContext::new(std::slice::from_ref(&keypair.public_key)).expect("invalid key");
+        assert_ne!(context.aggregate_public_key(), &keypair.public_key);
+    }
+
+    #[test]
+    fn test_musig_errors() {
+        let alice = SigningKeyPair::gen_with_defaults();
+        let bob = SigningKeyPair::gen_with_defaults();
+        let mallory = SigningKeyPair::gen_with_defaults();
+
+        assert!(KeyAggContext::new::<PublicKey>(&[]).is_err());
+        assert!(KeyAggContext::new(&[alice.public_key.clone(), alice.public_key.clone()]).is_err());
+
+        // the key order affects the aggregate key
+        let context = KeyAggContext::new(&[alice.public_key.clone(), bob.public_key.clone()])
+            .expect("invalid keys");
+        let reversed = KeyAggContext::new(&[bob.public_key.clone(), alice.public_key.clone()])
+            .expect("invalid keys");
+        assert_ne!(
+            context.aggregate_public_key(),
+            reversed.aggregate_public_key()
+        );
+
+        let (alice_secret, alice_public) = gen_nonce(&alice);
+        let (bob_secret, bob_public) = gen_nonce(&bob);
+        let (mallory_secret, _) = gen_nonce(&mallory);
+        assert!(SigningSession::new(&context, std::slice::from_ref(&alice_public), b"m").is_err());
+        let session = SigningSession::new(&context, &[alice_public, bob_public], b"m")
+            .expect("invalid nonces");
+
+        // outsiders and mismatched nonces are rejected
+        assert!(session.partial_sign(mallory_secret, &mallory).is_err());
+        assert!(session.partial_sign(bob_secret, &alice).is_err());
+
+        // a bad partial signature is detected
+        let alice_partial = session.partial_sign(alice_secret, &alice).expect("sign");
+        assert!(
+            session
+                .verify_partial(&bob.public_key, &alice_partial)
+                .is_err()
+        );
+        let signature = session
+            .aggregate(&[alice_partial.clone(), alice_partial])
+            .expect("aggregate");
+        assert!(
+            crypto_sign_verify_detached(
+                signature.as_array(),
+                b"m",
+                context.aggregate_public_key().as_array(),
+            )
+            .is_err()
+        );
+    }
+}
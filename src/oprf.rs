@@ -0,0 +1,997 @@
+//! # Oblivious pseudorandom functions
+//!
+//! An oblivious pseudorandom function (OPRF) lets a client compute
+//! `F(key, input)`, where the server holds `key` and the client holds
+//! `input`, without the server learning the input or the output, and without
+//! the client learning the key. This module implements the ristretto255-SHA512
+//! ciphersuite from [RFC 9497](https://www.rfc-editor.org/rfc/rfc9497).
+//!
+//! RFC 9497 defines three modes, each with its own server and client types:
+//!
+//! * The base mode (OPRF), implemented by [`OprfServer`] and [`OprfClient`].
+//! * The verifiable mode (VOPRF), implemented by [`VoprfServer`] and
+//!   [`VoprfClient`]. The server also proves that it evaluated the function
+//!   with the secret key for its published public key, so a server can't tag
+//!   individual clients by using a different key for each of them.
+//! * The partially oblivious mode (POPRF), implemented by [`PoprfServer`] and
+//!   [`PoprfClient`]. Like VOPRF, but the function also takes a public input,
+//!   `info`, which both parties know, such as a user name or a key epoch.
+//!
+//! Each mode uses a distinct context string, so the same key and input give
+//! unrelated outputs in different modes.
+//!
+//! A common use is hardening password-derived keys: the client runs the
+//! password through the OPRF before deriving a key from it, so an attacker who
+//! obtains the derived data can't run an offline dictionary attack without
+//! also querying the server, which can rate limit requests.
+//!
+//! The protocol takes one round trip:
+//!
+//! 1. The client calls `blind`, keeps the client state, and sends the blinded
+//!    element to the server.
+//! 2. The server calls `blind_evaluate`, and returns the evaluated element (and
+//!    proof, for VOPRF and POPRF) to the client.
+//! 3. The client calls `finalize`, which verifies the proof, if any, and
+//!    returns the output.
+//!
+//! The server can also compute the output directly for an input it knows,
+//! with `evaluate`.
+//!
+//! ## Example
+//!
+//! ```
+//! use dryoc::oprf::*;
+//!
+//! let server = VoprfServer::gen();
+//! let public_key = server.public_key().clone();
+//!
+//! let (client, blinded_element) = VoprfClient::blind(b"input").expect("blind failed");
+//! let (evaluated_element, proof) = server
+//!     .blind_evaluate(&blinded_element)
+//!     .expect("evaluate failed");
+//! let output = client
+//!     .finalize(b"input", &evaluated_element, &proof, &public_key)
+//!     .expect("finalize failed");
+//!
+//! assert_eq!(output, server.evaluate(b"input").expect("evaluate failed"));
+//! ```
+//!
+//! With a public input, in POPRF mode:
+//!
+//! ```
+//! use dryoc::oprf::*;
+//!
+//! let server = PoprfServer::gen();
+//!
+//! let (client, blinded_element) = PoprfClient::blind(b"password").expect("blind failed");
+//! let (evaluated_element, proof) = server
+//!     .blind_evaluate(&blinded_element, b"user@example.com")
+//!     .expect("evaluate failed");
+//! let output = client
+//!     .finalize(
+//!         b"password",
+//!         &evaluated_element,
+//!         &proof,
+//!         server.public_key(),
+//!         b"user@example.com",
+//!     )
+//!     .expect("finalize failed");
+//!
+//! assert_eq!(
+//!     output,
+//!     server
+//!         .evaluate(b"password", b"user@example.com")
+//!         .expect("evaluate failed")
+//! );
+//! ```
+use curve25519_dalek::constants::RISTRETTO_BASEPOINT_TABLE;
+use curve25519_dalek::ristretto::{CompressedRistretto, RistrettoPoint};
+use curve25519_dalek::scalar::Scalar;
+use curve25519_dalek::traits::{Identity, IsIdentity};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha512};
+use subtle::ConstantTimeEq;
+use zeroize::{Zeroize, ZeroizeOnDrop};
+
+use crate::error::Error;
+use crate::ristretto_util::{decode_element, decode_scalar, encode_element, random_scalar};
+use crate::types::*;
+
+/// Length of an encoded ristretto255 group element.
+pub const OPRF_ELEMENTBYTES: usize = 32;
+/// Length of a public key, an encoded group element.
+pub const OPRF_PUBLICKEYBYTES: usize = 32;
+/// Length of a secret key, an encoded scalar.
+pub const OPRF_SECRETKEYBYTES: usize = 32;
+/// Length of a proof, which is two encoded scalars.
+pub const OPRF_PROOFBYTES: usize = 64;
+/// Length of the output of the function.
+pub const OPRF_OUTPUTBYTES: usize = 64;
+
+const MODE_OPRF: u8 = 0x00;
+const MODE_VOPRF: u8 = 0x01;
+const MODE_POPRF: u8 = 0x02;
+
+/// Encoded ristretto255 group element.
+pub type Element = StackByteArray<OPRF_ELEMENTBYTES>;
+/// Public key, an encoded group element.
+pub type PublicKey = StackByteArray<OPRF_PUBLICKEYBYTES>;
+/// Secret key, an encoded scalar.
+pub type SecretKey = StackByteArray<OPRF_SECRETKEYBYTES>;
+/// Proof that an element was evaluated with the secret key for a public key.
+pub type Proof = StackByteArray<OPRF_PROOFBYTES>;
+/// Output of the function.
+pub type Output = StackByteArray<OPRF_OUTPUTBYTES>;
+
+#[cfg_attr(
+    feature = "serde",
+    derive(Zeroize, ZeroizeOnDrop, Clone, Serialize, Deserialize)
+)]
+#[cfg_attr(not(feature = "serde"), derive(Zeroize, ZeroizeOnDrop, Clone))]
+/// Server for the base mode (OPRF), which holds the secret key.
+pub struct OprfServer {
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::redact::serialize_secret")
+    )]
+    secret_key: SecretKey,
+}
+
+/// Client state for the base mode (OPRF), between [`OprfClient::blind`] and
+/// [`OprfClient::finalize`].
+#[derive(Zeroize, ZeroizeOnDrop)]
+pub struct OprfClient {
+    blind: Scalar,
+}
+
+#[cfg_attr(
+    feature = "serde",
+    derive(Zeroize, ZeroizeOnDrop, Clone, Serialize, Deserialize)
+)]
+#[cfg_attr(not(feature = "serde"), derive(Zeroize, ZeroizeOnDrop, Clone))]
+/// Server for the verifiable mode (VOPRF), which holds the secret key.
+pub struct VoprfServer {
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::redact::serialize_secret")
+    )]
+    secret_key: SecretKey,
+    public_key: PublicKey,
+}
+
+/// Client state for the verifiable mode (VOPRF), between
+/// [`VoprfClient::blind`] and [`VoprfClient::finalize`].
+#[derive(Zeroize, ZeroizeOnDrop)]
+pub struct VoprfClient {
+    blind: Scalar,
+    blinded_element: Element,
+}
+
+#[cfg_attr(
+    feature = "serde",
+    derive(Zeroize, ZeroizeOnDrop, Clone, Serialize, Deserialize)
+)]
+#[cfg_attr(not(feature = "serde"), derive(Zeroize, ZeroizeOnDrop, Clone))]
+/// Server for the partially oblivious mode (POPRF), which holds the secret
+/// key.
+pub struct PoprfServer {
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::redact::serialize_secret")
+    )]
+    secret_key: SecretKey,
+    public_key: PublicKey,
+}
+
+/// Client state for the partially oblivious mode (POPRF), between
+/// [`PoprfClient::blind`] and [`PoprfClient::finalize`].
+#[derive(Zeroize, ZeroizeOnDrop)]
+pub struct PoprfClient {
+    blind: Scalar,
+    blinded_element: Element,
+}
+
+fn context_string(mode: u8) -> [u8; 28] {
+    let mut context = [0u8; 28];
+    context[..7].copy_from_slice(b"OPRFV1-");
+    context[7] = mode;
+    context[8..].copy_from_slice(b"-ristretto255-SHA512");
+    context
+}
+
+/// `expand_message_xmd` from RFC 9380, with SHA-512 and a 64 byte output.
+fn expand_message_xmd(message: &[&[u8]], dst: &[&[u8]]) -> [u8; 64] {
+    let dst_len = dst.iter().map(|part| part.len()).sum::<usize>() as u8;
+    let mut hasher = Sha512::new().chain_update([0u8; 128]);
+    for part in message {
+        hasher.update(part);
+    }
+    hasher.update([0, 64, 0]);
+    for part in dst {
+        hasher.update(part);
+    }
+    hasher.update([dst_len]);
+    let b0 = hasher.finalize();
+
+    let mut hasher = Sha512::new().chain_update(b0).chain_update([1]);
+    for part in dst {
+        hasher.update(part);
+    }
+    hasher.update([dst_len]);
+    hasher.finalize().into()
+}
+
+fn hash_to_group(input: &[u8], mode: u8) -> RistrettoPoint {
+    let mut uniform = expand_message_xmd(&[input], &[b"HashToGroup-", &context_string(mode)]);
+    let point = RistrettoPoint::from_uniform_bytes(&uniform);
+    uniform.zeroize();
+    point
+}
+
+fn hash_to_scalar_with_dst(input: &[&[u8]], dst: &[&[u8]]) -> Scalar {
+    let mut uniform = expand_message_xmd(input, dst);
+    let scalar = Scalar::from_bytes_mod_order_wide(&uniform);
+    uniform.zeroize();
+    scalar
+}
+
+fn hash_to_scalar(input: &[&[u8]], mode: u8) -> Scalar {
+    hash_to_scalar_with_dst(input, &[b"HashToScalar-", &context_string(mode)])
+}
+
+/// Prefixes `data` with its length, as a 2 byte big-endian integer.
+fn length_prefixed(data: &[u8]) -> [u8; 2] {
+    (data.len() as u16).to_be_bytes()
+}
+
+/// Derives a keypair from `seed` and `info`, as described in RFC 9497,
+/// section 3.2.1.
+fn derive_key_pair(seed: &[u8; 32], info: &[u8], mode: u8) -> Result<Scalar, Error> {
+    if info.len() > u16::MAX as usize {
+        return Err(dryoc_error!("info is too long"));
+    }
+    for counter in 0..=255u8 {
+        let secret = hash_to_scalar_with_dst(
+            &[seed, &length_prefixed(info), info, &[counter]],
+            &[b"DeriveKeyPair", &context_string(mode)],
+        );
+        if secret != Scalar::ZERO {
+            return Ok(secret);
+        }
+    }
+    Err(dryoc_error!("key derivation failed"))
+}
+
+/// Computes the composite elements `(M, Z)` for a batch DLEQ proof. If
+/// `secret` is provided, `Z` is computed from `M` directly.
+fn compute_composites(
+    public_key: &CompressedRistretto,
+    c: &[RistrettoPoint],
+    d: &[RistrettoPoint],
+    secret: Option<&Scalar>,
+    mode: u8,
+) -> (RistrettoPoint, RistrettoPoint) {
+    let seed_dst = [&b"Seed-"[..], &context_string(mode)].concat();
+    let seed = Sha512::new()
+        .chain_update(length_prefixed(public_key.as_bytes()))
+        .chain_update(public_key.as_bytes())
+        .chain_update(length_prefixed(&seed_dst))
+        .chain_update(&seed_dst)
+        .finalize();
+
+    let mut m = RistrettoPoint::identity();
+    let mut z = RistrettoPoint::identity();
+    for (i, (ci, di)) in c.iter().zip(d).enumerate() {
+        let ci_bytes = ci.compress();
+        let di_bytes = di.compress();
+        let weight = hash_to_scalar(
+            &[
+                &length_prefixed(&seed),
+                &seed,
+                &(i as u16).to_be_bytes(),
+                &length_prefixed(ci_bytes.as_bytes()),
+                ci_bytes.as_bytes(),
+                &length_prefixed(di_bytes.as_bytes()),
+                di_bytes.as_bytes(),
+                b"Composite",
+            ],
+            mode,
+        );
+        m += weight * ci;
+        if secret.is_none() {
+            z += weight * di;
+        }
+    }
+    if let Some(secret) = secret {
+        z = secret * m;
+    }
+    (m, z)
+}
+
+fn proof_challenge(
+    public_key: &CompressedRistretto,
+    m: &RistrettoPoint,
+    z: &RistrettoPoint,
+    t2: &RistrettoPoint,
+    t3: &RistrettoPoint,
+    mode: u8,
+) -> Scalar {
+    let elements = [
+        *public_key,
+        m.compress(),
+        z.compress(),
+        t2.compress(),
+        t3.compress(),
+    ];
+    let mut transcript = Vec::with_capacity(elements.len() * 34 + 9);
+    for element in &elements {
+        transcript.extend_from_slice(&length_prefixed(element.as_bytes()));
+        transcript.extend_from_slice(element.as_bytes());
+    }
+    transcript.extend_from_slice(b"Challenge");
+    hash_to_scalar(&[&transcript], mode)
+}
+
+/// Proves that `d = secret * c`, where `public_key = secret * G`, as
+/// described in RFC 9497, section 2.2.1.
+fn generate_proof(
+    secret: &Scalar,
+    public_key: &RistrettoPoint,
+    c: &[RistrettoPoint],
+    d: &[RistrettoPoint],
+    mut r: Scalar,
+    mode: u8,
+) -> Proof {
+    let public_key = public_key.compress();
+    let (m, z) = compute_composites(&public_key, c, d, Some(secret), mode);
+    let t2 = RISTRETTO_BASEPOINT_TABLE * &r;
+    let t3 = r * m;
+    let challenge = proof_challenge(&public_key, &m, &z, &t2, &t3, mode);
+    let s = r - challenge * secret;
+    r.zeroize();
+
+    let mut proof = Proof::new_byte_array();
+    proof.as_mut_slice()[..32].copy_from_slice(challenge.as_bytes());
+    proof.as_mut_slice()[32..].copy_from_slice(s.as_bytes());
+    proof
+}
+
+/// Verifies a proof from [`generate_proof`], as described in RFC 9497,
+/// section 2.2.2.
+fn verify_proof(
+    public_key: &RistrettoPoint,
+    c: &[RistrettoPoint],
+    d: &[RistrettoPoint],
+    proof: &Proof,
+    mode: u8,
+) -> Result<(), Error> {
+    let mut challenge = [0u8; 32];
+    let mut s = [0u8; 32];
+    challenge.copy_from_slice(&proof.as_slice()[..32]);
+    s.copy_from_slice(&proof.as_slice()[32..]);
+    let challenge = decode_scalar(&challenge)?;
+    let s = decode_scalar(&s)?;
+
+    let public_key_bytes = public_key.compress();
+    let (m, z) = compute_composites(&public_key_bytes, c, d, None, mode);
+    let t2 = RISTRETTO_BASEPOINT_TABLE * &s + challenge * public_key;
+    let t3 = s * m + challenge * z;
+    let expected = proof_challenge(&public_key_bytes, &m, &z, &t2, &t3, mode);
+
+    if expected.ct_eq(&challenge).into() {
+        Ok(())
+    } else {
+        Err(dryoc_error!("proof verification failed"))
+    }
+}
+
+fn finalize_hash(input: &[u8], info: Option<&[u8]>, unblinded: &RistrettoPoint) -> Output {
+    let unblinded = unblinded.compress();
+    let mut hasher = Sha512::new()
+        .chain_update(length_prefixed(input))
+        .chain_update(input);
+    if let Some(info) = info {
+        hasher.update(length_prefixed(info));
+        hasher.update(info);
+    }
+    let hash: [u8; 64] = hasher
+        .chain_update(length_prefixed(unblinded.as_bytes()))
+        .chain_update(unblinded.as_bytes())
+        .chain_update(b"Finalize")
+        .finalize()
+        .into();
+    Output::from(hash)
+}
+
+/// Hashes `input` to a group element, as the first step of blinding or
+/// evaluating it.
+fn hash_input(input: &[u8], mode: u8) -> Result<RistrettoPoint, Error> {
+    if input.len() > u16::MAX as usize {
+        return Err(dryoc_error!("input is too long"));
+    }
+    let input_element = hash_to_group(input, mode);
+    if input_element.is_identity() {
+        return Err(dryoc_error!("invalid input"));
+    }
+    Ok(input_element)
+}
+
+/// Returns the scalar derived from the public `info` in POPRF mode.
+fn info_scalar(info: &[u8]) -> Result<Scalar, Error> {
+    if info.len() > u16::MAX as usize {
+        return Err(dryoc_error!("info is too long"));
+    }
+    Ok(hash_to_scalar(
+        &[b"Info", &length_prefixed(info), info],
+        MODE_POPRF,
+    ))
+}
+
+/// Returns the POPRF secret `secret + H(info)`, which the server evaluates
+/// with instead of its secret key.
+fn tweak_secret(secret: &Scalar, info: &[u8]) -> Result<Scalar, Error> {
+    let tweaked = secret + info_scalar(info)?;
+    if tweaked == Scalar::ZERO {
+        Err(dryoc_error!("info is not usable with this key"))
+    } else {
+        Ok(tweaked)
+    }
+}
+
+fn keypair_from_scalar(mut secret: Scalar) -> (SecretKey, PublicKey) {
+    let keypair = (
+        SecretKey::from(secret.to_bytes()),
+        PublicKey::from((RISTRETTO_BASEPOINT_TABLE * &secret).compress().to_bytes()),
+    );
+    secret.zeroize();
+    keypair
+}
+
+fn keypair_from_secret_key<SK: ByteArray<OPRF_SECRETKEYBYTES>>(
+    secret_key: &SK,
+) -> Result<(SecretKey, PublicKey), Error> {
+    let secret = decode_scalar(secret_key.as_array())?;
+    if secret == Scalar::ZERO {
+        return Err(dryoc_error!("invalid secret key"));
+    }
+    Ok(keypair_from_scalar(secret))
+}
+
+impl OprfServer {
+    /// Generates a new server with a random secret key.
+    pub fn gen() -> Self {
+        let (secret_key, _) = keypair_from_scalar(random_scalar());
+        Self { secret_key }
+    }
+
+    /// Deterministically derives a server secret key from `seed` and `info`.
+    pub fn derive(seed: &[u8; 32], info: &[u8]) -> Result<Self, Error> {
+        let (secret_key, _) = keypair_from_scalar(derive_key_pair(seed, info, MODE_OPRF)?);
+        Ok(Self { secret_key })
+    }
+
+    /// Loads a server from `secret_key`. Returns an error if the secret key
+    /// isn't a canonical, non-zero scalar.
+    pub fn from_secret_key<SK: ByteArray<OPRF_SECRETKEYBYTES>>(
+        secret_key: &SK,
+    ) -> Result<Self, Error> {
+        let (secret_key, _) = keypair_from_secret_key(secret_key)?;
+        Ok(Self { secret_key })
+    }
+
+    /// Returns the server's secret key.
+    pub fn secret_key(&self) -> &SecretKey {
+        &self.secret_key
+    }
+
+    /// Evaluates the client's `blinded_element`.
+    pub fn blind_evaluate(&self, blinded_element: &Element) -> Result<Element, Error> {
+        let blinded = decode_element(blinded_element)?;
+        let mut secret = decode_scalar(self.secret_key.as_array())?;
+        let evaluated = secret * blinded;
+        secret.zeroize();
+        Ok(encode_element(&evaluated))
+    }
+
+    /// Computes the output of the function for `input` directly.
+    pub fn evaluate(&self, input: &[u8]) -> Result<Output, Error> {
+        let input_element = hash_input(input, MODE_OPRF)?;
+        let mut secret = decode_scalar(self.secret_key.as_array())?;
+        let evaluated = secret * input_element;
+        secret.zeroize();
+        Ok(finalize_hash(input, None, &evaluated))
+    }
+}
+
+impl OprfClient {
+    /// Blinds `input`, returning the client state and the blinded element to
+    /// send to the server.
+    pub fn blind(input: &[u8]) -> Result<(Self, Element), Error> {
+        Self::blind_with_scalar(input, random_scalar())
+    }
+
+    fn blind_with_scalar(input: &[u8], blind: Scalar) -> Result<(Self, Element), Error> {
+        let blinded_element = encode_element(&(blind * hash_input(input, MODE_OPRF)?));
+        Ok((Self { blind }, blinded_element))
+    }
+
+    /// Unblinds `evaluated_element`, and returns the output of the function
+    /// for `input`, which must be the same input that was blinded.
+    pub fn finalize(self, input: &[u8], evaluated_element: &Element) -> Result<Output, Error> {
+        if input.len() > u16::MAX as usize {
+            return Err(dryoc_error!("input is too long"));
+        }
+        let evaluated = decode_element(evaluated_element)?;
+        let unblinded = self.blind.invert() * evaluated;
+        Ok(finalize_hash(input, None, &unblinded))
+    }
+}
+
+impl VoprfServer {
+    /// Generates a new server with a random keypair.
+    pub fn gen() -> Self {
+        let (secret_key, public_key) = keypair_from_scalar(random_scalar());
+        Self {
+            secret_key,
+            public_key,
+        }
+    }
+
+    /// Deterministically derives a server keypair from `seed` and `info`.
+    pub fn derive(seed: &[u8; 32], info: &[u8]) -> Result<Self, Error> {
+        let (secret_key, public_key) =
+            keypair_from_scalar(derive_key_pair(seed, info, MODE_VOPRF)?);
+        Ok(Self {
+            secret_key,
+            public_key,
+        })
+    }
+
+    /// Loads a server from `secret_key`. Returns an error if the secret key
+    /// isn't a canonical, non-zero scalar.
+    pub fn from_secret_key<SK: ByteArray<OPRF_SECRETKEYBYTES>>(
+        secret_key: &SK,
+    ) -> Result<Self, Error> {
+        let (secret_key, public_key) = keypair_from_secret_key(secret_key)?;
+        Ok(Self {
+            secret_key,
+            public_key,
+        })
+    }
+
+    /// Returns the server's public key, which clients use to verify proofs.
+    pub fn public_key(&self) -> &PublicKey {
+        &self.public_key
+    }
+
+    /// Returns the server's secret key.
+    pub fn secret_key(&self) -> &SecretKey {
+        &self.secret_key
+    }
+
+    /// Evaluates the client's `blinded_element`, returning the evaluated
+    /// element and a proof that it was evaluated with this server's key.
+    pub fn blind_evaluate(&self, blinded_element: &Element) -> Result<(Element, Proof), Error> {
+        self.blind_evaluate_with_random(blinded_element, random_scalar())
+    }
+
+    fn blind_evaluate_with_random(
+        &self,
+        blinded_element: &Element,
+        r: Scalar,
+    ) -> Result<(Element, Proof), Error> {
+        let blinded = decode_element(blinded_element)?;
+        let mut secret = decode_scalar(self.secret_key.as_array())?;
+        let evaluated = secret * blinded;
+        let proof = generate_proof(
+            &secret,
+            &(RISTRETTO_BASEPOINT_TABLE * &secret),
+            &[blinded],
+            &[evaluated],
+            r,
+            MODE_VOPRF,
+        );
+        secret.zeroize();
+        Ok((encode_element(&evaluated), proof))
+    }
+
+    /// Computes the output of the function for `input` directly.
+    pub fn evaluate(&self, input: &[u8]) -> Result<Output, Error> {
+        let input_element = hash_input(input, MODE_VOPRF)?;
+        let mut secret = decode_scalar(self.secret_key.as_array())?;
+        let evaluated = secret * input_element;
+        secret.zeroize();
+        Ok(finalize_hash(input, None, &evaluated))
+    }
+}
+
+impl VoprfClient {
+    /// Blinds `input`, returning the client state and the blinded element to
+    /// send to the server.
+    pub fn blind(input: &[u8]) -> Result<(Self, Element), Error> {
+        Self::blind_with_scalar(input, random_scalar())
+    }
+
+    fn blind_with_scalar(input: &[u8], blind: Scalar) -> Result<(Self, Element), Error> {
+        let blinded_element = encode_element(&(blind * hash_input(input, MODE_VOPRF)?));
+        Ok((
+            Self {
+                blind,
+                blinded_element: blinded_element.clone(),
+            },
+            blinded_element,
+        ))
+    }
+
+    /// Verifies the server's `proof` for `evaluated_element` with the
+    /// server's `public_key`, and returns the output of the function for
+    /// `input`, which must be the same input that was blinded.
+    pub fn finalize<PK: ByteArray<OPRF_PUBLICKEYBYTES>>(
+        self,
+        input: &[u8],
+        evaluated_element: &Element,
+        proof: &Proof,
+        public_key: &PK,
+    ) -> Result<Output, Error> {
+        if input.len() > u16::MAX as usize {
+            return Err(dryoc_error!("input is too long"));
+        }
+        let public_key = decode_element(public_key)?;
+        let blinded = decode_element(&self.blinded_element)?;
+        let evaluated = decode_element(evaluated_element)?;
+        verify_proof(&public_key, &[blinded], &[evaluated], proof, MODE_VOPRF)?;
+
+        let unblinded = self.blind.invert() * evaluated;
+        Ok(finalize_hash(input, None, &unblinded))
+    }
+}
+
+impl PoprfServer {
+    /// Generates a new server with a random keypair.
+    pub fn gen() -> Self {
+        let (secret_key, public_key) = keypair_from_scalar(random_scalar());
+        Self {
+            secret_key,
+            public_key,
+        }
+    }
+
+    /// Deterministically derives a server keypair from `seed` and `info`.
+    /// This `info` is only used for key derivation, and is unrelated to the
+    /// public input passed to [`PoprfServer::blind_evaluate`].
+    pub fn derive(seed: &[u8; 32], info: &[u8]) -> Result<Self, Error> {
+        let (secret_key, public_key) =
+            keypair_from_scalar(derive_key_pair(seed, info, MODE_POPRF)?);
+        Ok(Self {
+            secret_key,
+            public_key,
+        })
+    }
+
+    /// Loads a server from `secret_key`. Returns an error if the secret key
+    /// isn't a canonical, non-zero scalar.
+    pub fn from_secret_key<SK: ByteArray<OPRF_SECRETKEYBYTES>>(
+        secret_key: &SK,
+    ) -> Result<Self, Error> {
+        let (secret_key, public_key) = keypair_from_secret_key(secret_key)?;
+        Ok(Self {
+            secret_key,
+            public_key,
+        })
+    }
+
+    /// Returns the server's public key, which clients use to verify proofs.
+    pub fn public_key(&self) -> &PublicKey {
+        &self.public_key
+    }
+
+    /// Returns the server's secret key.
+    pub fn secret_key(&self) -> &SecretKey {
+        &self.secret_key
+    }
+
+    /// Evaluates the client's `blinded_element` with the public input `info`,
+    /// returning the evaluated element and a proof that it was evaluated with
+    /// this server's key and `info`.
+    pub fn blind_evaluate(
+        &self,
+        blinded_element: &Element,
+        info: &[u8],
+    ) -> Result<(Element, Proof), Error> {
+        self.blind_evaluate_with_random(blinded_element, info, random_scalar())
+    }
+
+    fn blind_evaluate_with_random(
+        &self,
+        blinded_element: &Element,
+        info: &[u8],
+        r: Scalar,
+    ) -> Result<(Element, Proof), Error> {
+        let blinded = decode_element(blinded_element)?;
+        let mut secret = decode_scalar(self.secret_key.as_array())?;
+        let mut tweaked = tweak_secret(&secret, info)?;
+        secret.zeroize();
+        let evaluated = tweaked.invert() * blinded;
+        let proof = generate_proof(
+            &tweaked,
+            &(RISTRETTO_BASEPOINT_TABLE * &tweaked),
+            &[evaluated],
+            &[blinded],
+            r,
+            MODE_POPRF,
+        );
+        tweaked.zeroize();
+        Ok((encode_element(&evaluated), proof))
+    }
+
+    /// Computes the output of the function for `input` and the public input
+    /// `info` directly.
+    pub fn evaluate(&self, input: &[u8], info: &[u8]) -> Result<Output, Error> {
+        let input_element = hash_input(input, MODE_POPRF)?;
+        let mut secret = decode_scalar(self.secret_key.as_array())?;
+        let mut tweaked = tweak_secret(&secret, info)?;
+        secret.zeroize();
+        let evaluated = tweaked.invert() * input_element;
+        tweaked.zeroize();
+        Ok(finalize_hash(input, Some(info), &evaluated))
+    }
+}
+
+impl PoprfClient {
+    /// Blinds `input`, returning the client state and the blinded element to
+    /// send to the server.
+    pub fn blind(input: &[u8]) -> Result<(Self, Element), Error> {
+        Self::blind_with_scalar(input, random_scalar())
+    }
+
+    fn blind_with_scalar(input: &[u8], blind: Scalar) -> Result<(Self, Element), Error> {
+        let blinded_element = encode_element(&(blind * hash_input(input, MODE_POPRF)?));
+        Ok((
+            Self {
+                blind,
+                blinded_element: blinded_element.clone(),
+            },
+            blinded_element,
+        ))
+    }
+
+    /// Verifies the server's `proof` for `evaluated_element` with the
+    /// server's `public_key` and the public input `info`, and returns the
+    /// output of the function for `input`, which must be the same input that
+    /// was blinded.
+    pub fn finalize<PK: ByteArray<OPRF_PUBLICKEYBYTES>>(
+        self,
+        input: &[u8],
+        evaluated_element: &Element,
+        proof: &Proof,
+        public_key: &PK,
+        info: &[u8],
+    ) -> Result<Output, Error> {
+        if input.len() > u16::MAX as usize {
+            return Err(dryoc_error!("input is too long"));
+        }
+        let tweaked_key =
+            RISTRETTO_BASEPOINT_TABLE * &info_scalar(info)? + decode_element(public_key)?;
+        if tweaked_key.is_identity() {
+            return Err(dryoc_error!("info is not usable with this key"));
+        }
+        let blinded = decode_element(&self.blinded_element)?;
+        let evaluated = decode_element(evaluated_element)?;
+        verify_proof(&tweaked_key, &[evaluated], &[blinded], proof, MODE_POPRF)?;
+
+        let unblinded = self.blind.invert() * evaluated;
+        Ok(finalize_hash(input, Some(info), &unblinded))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unhex(s: &str) -> Vec<u8> {
+        hex::decode(s).expect("invalid hex")
+    }
+
+    fn scalar(s: &str) -> Scalar {
+        let mut bytes = [0u8; 32];
+        bytes.copy_from_slice(&unhex(s));
+        decode_scalar(&bytes).expect("invalid scalar")
+    }
+
+    #[test]
+    fn test_oprf_rfc9497() {
+        // RFC 9497, appendix A.1.1
+        let server = OprfServer::derive(&[0xa3; 32], b"test key").expect("derive");
+        assert_eq!(
+            server.secret_key().as_slice(),
+            unhex("5ebcea5ee37023ccb9fc2d2019f9d7737be85591ae8652ffa9ef0f4d37063b0e")
+        );
+
+        let (client, blinded_element) = OprfClient::blind_with_scalar(
+            &[0x00],
+            scalar("ca2400ec91daf0726220023bf472e744d62f237c73cc63d5b2c91b36bbe4a100"),
+        )
+        .expect("blind");
+        assert_eq!(
+            blinded_element.as_slice(),
+            unhex("0292c19359be397cb16123604817c40e71f69af27242aa1b402edb0a31c0ee1d")
+        );
+        let evaluated_element = server.blind_evaluate(&blinded_element).expect("evaluate");
+        assert_eq!(
+            evaluated_element.as_slice(),
+            unhex("b4f5719eac57ee9e91b293ae02966f5c790098c27e1d3dab8e014f83208c9b4e")
+        );
+
+        let expected = unhex("527759c3d9366f277d8c6020418d96bb393ba2afb20ff90df23fb7708264e2f3ab9135e3bd69955851de4b1f9fe8a0973396719b7912ba9ee8aa7d0b5e24bcf6");
+        let output = client
+            .finalize(&[0x00], &evaluated_element)
+            .expect("finalize");
+        assert_eq!(output.as_slice(), expected);
+        assert_eq!(
+            server.evaluate(&[0x00]).expect("evaluate").as_slice(),
+            expected
+        );
+    }
+
+    #[test]
+    fn test_voprf_rfc9497() {
+        // RFC 9497, appendix A.1.2
+        let server = VoprfServer::derive(&[0xa3; 32], b"test key").expect("derive");
+        assert_eq!(
+            server.secret_key().as_slice(),
+            unhex("e6f73f344b79b379f1a0dd37e07ff62e38d9f71345ce62ae3a9bc60b04ccd909")
+        );
+        assert_eq!(
+            server.public_key().as_slice(),
+            unhex("c803e2cc6b05fc15064549b5920659ca4a77b2cca6f04f6b357009335476ad4e")
+        );
+
+        let (client, blinded_element) = VoprfClient::blind_with_scalar(
+            &[0x00],
+            scalar("ca2400ec91daf0726220023bf472e744d62f237c73cc63d5b2c91b36bbe4a100"),
+        )
+        .expect("blind");
+        assert_eq!(
+            blinded_element.as_slice(),
+            unhex("547b06e88f4fc44bace35fcd09da7898674045d0852f08e2e4ad46032bc6f850")
+        );
+        let (evaluated_element, proof) = server
+            .blind_evaluate_with_random(
+                &blinded_element,
+                scalar("222a5e897cf59db8145db8d16e597e8facb80ae7d4e26d9881aa6f61d645fc0e"),
+            )
+            .expect("evaluate");
+        assert_eq!(
+            evaluated_element.as_slice(),
+            unhex("6435be93b8f9a99f74b6bb3f2a6606de26b54520211d0c93858338696ab5de18")
+        );
+        assert_eq!(
+            proof.as_slice(),
+            unhex("0d9ac7b78ca6d67077cff78cf45f1f7f0bd814a44a78ee88fe7c50d2f89b3d0041e030fa4cb0bbe0c97980b2ecfb37df5b11f28dd6c2fa4ef7c8545f32b0a80d")
+        );
+
+        let expected = unhex("b58cfbe118e0cb94d79b5fd6a6dafb98764dff49c14e1770b566e42402da1a7da4d8527693914139caee5bd03903af43a491351d23b430948dd50cde10d32b3c");
+        let output = client
+            .finalize(&[0x00], &evaluated_element, &proof, server.public_key())
+            .expect("finalize");
+        assert_eq!(output.as_slice(), expected);
+        assert_eq!(
+            server.evaluate(&[0x00]).expect("evaluate").as_slice(),
+            expected
+        );
+    }
+
+    #[test]
+    fn test_voprf_proof() {
+        let server = VoprfServer::gen();
+        let (client, blinded_element) = VoprfClient::blind(b"input").expect("blind");
+        let (evaluated_element, proof) = server.blind_evaluate(&blinded_element).expect("evaluate");
+
+        // a proof for a different key is rejected
+        let other = VoprfServer::gen();
+        let (_, other_proof) = other.blind_evaluate(&blinded_element).expect("evaluate");
+        let (client2, _) = VoprfClient::blind(b"input").expect("blind");
+        assert!(
+            client2
+                .finalize(
+                    b"input",
+                    &evaluated_element,
+                    &other_proof,
+                    server.public_key()
+                )
+                .is_err()
+        );
+
+        let output = client
+            .finalize(b"input", &evaluated_element, &proof, server.public_key())
+            .expect("finalize");
+        assert_eq!(output, server.evaluate(b"input").expect("evaluate"));
+        assert_ne!(output, other.evaluate(b"input").expect("evaluate"));
+
+        assert!(server.blind_evaluate(&Element::default()).is_err());
+        assert!(VoprfServer::from_secret_key(&SecretKey::default()).is_err());
+        let loaded = VoprfServer::from_secret_key(server.secret_key()).expect("load");
+        assert_eq!(loaded.public_key(), server.public_key());
+    }
+
+    #[test]
+    fn test_poprf_rfc9497() {
+        // RFC 9497, appendix A.1.3
+        let server = PoprfServer::derive(&[0xa3; 32], b"test key").expect("derive");
+        assert_eq!(
+            server.secret_key().as_slice(),
+            unhex("145c79c108538421ac164ecbe131942136d5570b16d8bf41a24d4337da981e07")
+        );
+        assert_eq!(
+            server.public_key().as_slice(),
+            unhex("c647bef38497bc6ec077c22af65b696efa43bff3b4a1975a3e8e0a1c5a79d631")
+        );
+
+        let (client, blinded_element) = PoprfClient::blind_with_scalar(
+            &[0x00],
+            scalar("ca2400ec91daf0726220023bf472e744d62f237c73cc63d5b2c91b36bbe4a100"),
+        )
+        .expect("blind");
+        assert_eq!(
+            blinded_element.as_slice(),
+            unhex("c0f1d60d9d8025f91778409074801bf4c8c2932da2510f3ce9e7db414496330c")
+        );
+        let (evaluated_element, proof) = server
+            .blind_evaluate_with_random(
+                &blinded_element,
+                b"test info",
+                scalar("222a5e897cf59db8145db8d16e597e8facb80ae7d4e26d9881aa6f61d645fc0e"),
+            )
+            .expect("evaluate");
+        assert_eq!(
+            evaluated_element.as_slice(),
+            unhex("30ee84b0875ace272c96a5925eefe05cf0ee691fd07692233313c04975bf0a4c")
+        );
+        assert_eq!(
+            proof.as_slice(),
+            unhex("aeb258c454e2f51e2e645a5d5a080a1aa66d9544f6acc67d72d23c3774368406403f3a91daebea0f64d22e6913191fc4463451ab7fa5925edd8323f66709640a")
+        );
+
+        let expected = unhex("ca688351e88afb1d841fde4401c79efebb2eb75e7998fa9737bd5a82a152406d38bd29f680504e54fd4587eddcf2f37a2617ac2fbd2993f7bdf45442ace7d221");
+        let output = client
+            .finalize(
+                &[0x00],
+                &evaluated_element,
+                &proof,
+                server.public_key(),
+                b"test info",
+            )
+            .expect("finalize");
+        assert_eq!(output.as_slice(), expected);
+        assert_eq!(
+            server
+                .evaluate(&[0x00], b"test info")
+                .expect("evaluate")
+                .as_slice(),
+            expected
+        );
+    }
+
+    #[test]
+    fn test_poprf_wrong_info() {
+        let server = PoprfServer::gen();
+        let (client, blinded_element) = PoprfClient::blind(b"input").expect("blind");
+        let (evaluated_element, proof) = server
+            .blind_evaluate(&blinded_element, b"alice")
+            .expect("evaluate");
+        client
+            .finalize(
+                b"input",
+                &evaluated_element,
+                &proof,
+                server.public_key(),
+                b"bob",
+            )
+            .expect_err("proof for different info");
+        assert_ne!(
+            server.evaluate(b"input", b"alice").expect("evaluate"),
+            server.evaluate(b"input", b"bob").expect("evaluate")
+        );
+    }
+}
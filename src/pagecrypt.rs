@@ -0,0 +1,304 @@
+//! # Encrypted pages for paged storage
+//!
+//! [`PageCipher`] encrypts and decrypts fixed-size pages in place, for use in
+//! paged storage engines, such as a SQLite VFS. Each page is encrypted with
+//! XChaCha20-Poly1305 and authenticated along with the file ID and its page
+//! number, so pages can't be swapped within a file or moved between files.
+//!
+//! Pages are rewritten many times, so the nonce can't be derived from the page
+//! number alone. Each time a page is encrypted, a fresh 16 byte random salt is
+//! generated, and the nonce is derived from the file ID, the page number, and
+//! the salt. The salt and the 16 byte tag make up a [`PAGE_TRAILERBYTES`]
+//! byte trailer, which is either stored:
+//!
+//! * in the page itself, with [`PageCipher::encrypt_page`], using the last
+//!   [`PAGE_TRAILERBYTES`] bytes of each page. With SQLite, set the number of
+//!   reserved bytes per page to [`PAGE_TRAILERBYTES`] so the database leaves
+//!   room for the trailer, or
+//! * in a sidecar file, with [`PageCipher::encrypt_page_detached`], in which
+//!   case the full page is encrypted, and the trailer of page `n` is stored at
+//!   [`PageCipher::sidecar_offset`] in the sidecar.
+//!
+//! Encryption doesn't protect against rollback: an attacker with write access
+//! can replace a page with an older version of the same page. Engines which
+//! need rollback protection should authenticate the trailers, i.e., with a
+//! Merkle tree or a signed manifest.
+//!
+//! ## Example
+//!
+//! ```
+//! use dryoc::pagecrypt::*;
+//!
+//! let cipher = PageCipher::new(&Key::gen(), &FileId::gen(), 4096).expect("invalid page size");
+//!
+//! // The last PAGE_TRAILERBYTES of each page are reserved for the trailer
+//! let mut page = vec![0u8; 4096];
+//! page[..cipher.usable_size()].fill(0x2a);
+//!
+//! cipher.encrypt_page(7, &mut page).expect("encrypt failed");
+//! assert_ne!(page[0], 0x2a);
+//!
+//! // The page number must match
+//! let mut copy = page.clone();
+//! assert!(cipher.decrypt_page(8, &mut copy).is_err());
+//!
+//! cipher.decrypt_page(7, &mut page).expect("decrypt failed");
+//! assert!(page[..cipher.usable_size()].iter().all(|b| *b == 0x2a));
+//! ```
+use zeroize::Zeroize;
+
+use crate::aead::{
+    xchacha20poly1305_ietf_decrypt_detached, xchacha20poly1305_ietf_encrypt_detached,
+};
+use crate::constants::{
+    CRYPTO_AEAD_XCHACHA20POLY1305_IETF_ABYTES, CRYPTO_AEAD_XCHACHA20POLY1305_IETF_KEYBYTES,
+    CRYPTO_AEAD_XCHACHA20POLY1305_IETF_NPUBBYTES,
+};
+use crate::error::Error;
+use crate::generichash::GenericHash;
+use crate::rng::copy_randombytes;
+pub use crate::types::*;
+
+/// Length of a page's random salt.
+pub const PAGE_SALTBYTES: usize = 16;
+/// Length of a page's trailer: the salt followed by the tag.
+pub const PAGE_TRAILERBYTES: usize = PAGE_SALTBYTES + CRYPTO_AEAD_XCHACHA20POLY1305_IETF_ABYTES;
+/// Length of a file ID.
+pub const PAGE_FILE_IDBYTES: usize = 16;
+/// Smallest supported page size, which matches SQLite's.
+pub const PAGE_MIN_SIZE: usize = 512;
+/// Largest supported page size, which matches SQLite's.
+pub const PAGE_MAX_SIZE: usize = 65536;
+
+/// Domain separation prefix for page nonces.
+const PAGE_NONCE_CONTEXT: &[u8] = b"dryoc page nonce";
+
+/// Stack-allocated page key type alias.
+pub type Key = StackByteArray<CRYPTO_AEAD_XCHACHA20POLY1305_IETF_KEYBYTES>;
+/// Stack-allocated file ID type alias.
+pub type FileId = StackByteArray<PAGE_FILE_IDBYTES>;
+/// Stack-allocated page trailer type alias, for storage in a sidecar.
+pub type PageTrailer = StackByteArray<PAGE_TRAILERBYTES>;
+
+/// Encrypts and decrypts fixed-size pages of a single file.
+///
+/// Refer to [crate::pagecrypt] for sample usage.
+#[derive(Clone)]
+pub struct PageCipher {
+    key: Key,
+    file_id: FileId,
+    page_size: usize,
+}
+
+impl PageCipher {
+    /// Returns a new page cipher for the file identified by `file_id`, with
+    /// `page_size` byte pages. The page size must be a power of 2 between
+    /// [`PAGE_MIN_SIZE`] and [`PAGE_MAX_SIZE`]. File IDs should be random and
+    /// unique for each file encrypted with the same key.
+    pub fn new(key: &Key, file_id: &FileId, page_size: usize) -> Result<Self, Error> {
+        if !page_size.is_power_of_two() || !(PAGE_MIN_SIZE..=PAGE_MAX_SIZE).contains(&page_size) {
+            return Err(dryoc_error!(format!(
+                "page size {} must be a power of 2 between {} and {}",
+                page_size, PAGE_MIN_SIZE, PAGE_MAX_SIZE
+            )));
+        }
+        Ok(Self {
+            key: key.clone(),
+            file_id: file_id.clone(),
+            page_size,
+        })
+    }
+
+    /// Returns the page size.
+    pub fn page_size(&self) -> usize {
+        self.page_size
+    }
+
+    /// Returns the number of bytes of each page available for data when the
+    /// trailer is stored in the page.
+    pub fn usable_size(&self) -> usize {
+        self.page_size - PAGE_TRAILERBYTES
+    }
+
+    /// Returns the offset of the trailer for `page_number` within a sidecar
+    /// file, when trailers are stored separately.
+    pub fn sidecar_offset(page_number: u64) -> u64 {
+        page_number * PAGE_TRAILERBYTES as u64
+    }
+
+    fn nonce(
+        &self,
+        page_number: u64,
+        salt: &[u8],
+    ) -> Result<[u8; CRYPTO_AEAD_XCHACHA20POLY1305_IETF_NPUBBYTES], Error> {
+        let mut state: GenericHash<CRYPTO_AEAD_XCHACHA20POLY1305_IETF_KEYBYTES, 24> =
+            GenericHash::new(None::<&Key>)?;
+        state.update(PAGE_NONCE_CONTEXT);
+        state.update(&self.associated_data(page_number));
+        state.update(salt);
+        state.finalize()
+    }
+
+    fn associated_data(&self, page_number: u64) -> [u8; PAGE_FILE_IDBYTES + 8] {
+        let mut ad = [0u8; PAGE_FILE_IDBYTES + 8];
+        ad[..PAGE_FILE_IDBYTES].copy_from_slice(self.file_id.as_slice());
+        ad[PAGE_FILE_IDBYTES..].copy_from_slice(&page_number.to_le_bytes());
+        ad
+    }
+
+    fn check_len(&self, page: &[u8]) -> Result<(), Error> {
+        if page.len() != self.page_size {
+            Err(dryoc_error!(format!(
+                "page length {} doesn't match page size {}",
+                page.len(),
+                self.page_size
+            )))
+        } else {
+            Ok(())
+        }
+    }
+
+    fn encrypt(&self, page_number: u64, data: &mut [u8]) -> Result<PageTrailer, Error> {
+        let mut trailer = PageTrailer::default();
+        let (salt, tag) = trailer.as_mut_slice().split_at_mut(PAGE_SALTBYTES);
+        copy_randombytes(salt);
+        let mut nonce = self.nonce(page_number, salt)?;
+        tag.copy_from_slice(&xchacha20poly1305_ietf_encrypt_detached(
+            data,
+            &self.associated_data(page_number),
+            &nonce,
+            self.key.as_array(),
+        ));
+        nonce.zeroize();
+        Ok(trailer)
+    }
+
+    fn decrypt(&self, page_number: u64, data: &mut [u8], trailer: &[u8]) -> Result<(), Error> {
+        let (salt, tag) = trailer.split_at(PAGE_SALTBYTES);
+        let mut nonce = self.nonce(page_number, salt)?;
+        let result = xchacha20poly1305_ietf_decrypt_detached(
+            data,
+            ByteArray::as_array(tag),
+            &self.associated_data(page_number),
+            &nonce,
+            self.key.as_array(),
+        );
+        nonce.zeroize();
+        result
+    }
+
+    /// Encrypts `page` in place as page number `page_number`. The last
+    /// [`PAGE_TRAILERBYTES`] bytes of the page are overwritten with the
+    /// trailer.
+    pub fn encrypt_page(&self, page_number: u64, page: &mut [u8]) -> Result<(), Error> {
+        self.check_len(page)?;
+        let (data, trailer) = page.split_at_mut(self.usable_size());
+        trailer.copy_from_slice(self.encrypt(page_number, data)?.as_slice());
+        Ok(())
+    }
+
+    /// Verifies and decrypts `page` in place as page number `page_number`,
+    /// using the trailer in its last [`PAGE_TRAILERBYTES`] bytes. The page is
+    /// left unchanged if verification fails.
+    pub fn decrypt_page(&self, page_number: u64, page: &mut [u8]) -> Result<(), Error> {
+        self.check_len(page)?;
+        let (data, trailer) = page.split_at_mut(self.usable_size());
+        self.decrypt(page_number, data, trailer)
+    }
+
+    /// Encrypts all of `page` in place as page number `page_number`,
+    /// returning the trailer for storage in a sidecar.
+    pub fn encrypt_page_detached(
+        &self,
+        page_number: u64,
+        page: &mut [u8],
+    ) -> Result<PageTrailer, Error> {
+        self.check_len(page)?;
+        self.encrypt(page_number, page)
+    }
+
+    /// Verifies and decrypts all of `page` in place as page number
+    /// `page_number`, using `trailer` from a sidecar. The page is left
+    /// unchanged if verification fails.
+    pub fn decrypt_page_detached(
+        &self,
+        page_number: u64,
+        page: &mut [u8],
+        trailer: &PageTrailer,
+    ) -> Result<(), Error> {
+        self.check_len(page)?;
+        self.decrypt(page_number, page, trailer.as_slice())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_page_trailer() {
+        let key = Key::gen();
+        let cipher = PageCipher::new(&key, &FileId::gen(), 1024).expect("new");
+        let plaintext = crate::rng::randombytes_buf(1024);
+
+        let mut page = plaintext.clone();
+        cipher.encrypt_page(3, &mut page).expect("encrypt");
+        let mut again = plaintext.clone();
+        cipher.encrypt_page(3, &mut again).expect("encrypt");
+        // each write uses a fresh salt
+        assert_ne!(page, again);
+
+        let mut decrypted = page.clone();
+        cipher.decrypt_page(3, &mut decrypted).expect("decrypt");
+        assert_eq!(
+            &decrypted[..1024 - PAGE_TRAILERBYTES],
+            &plaintext[..1024 - PAGE_TRAILERBYTES]
+        );
+
+        let mut tampered = page.clone();
+        tampered[10] ^= 1;
+        cipher.decrypt_page(3, &mut tampered).expect_err("tampered");
+        let other_file = PageCipher::new(&key, &FileId::gen(), 1024).unwrap();
+        let mut copy = page.clone();
+        other_file
+            .decrypt_page(3, &mut copy)
+            .expect_err("other file");
+        assert_eq!(copy, page);
+
+        cipher
+            .encrypt_page(0, &mut [0u8; 512])
+            .expect_err("wrong page length");
+    }
+
+    #[test]
+    fn test_page_sidecar() {
+        let cipher = PageCipher::new(&Key::gen(), &FileId::gen(), 512).expect("new");
+        let plaintext = crate::rng::randombytes_buf(512);
+
+        let mut page = plaintext.clone();
+        let trailer = cipher.encrypt_page_detached(9, &mut page).expect("encrypt");
+        assert_ne!(page, plaintext);
+
+        let mut decrypted = page.clone();
+        cipher
+            .decrypt_page_detached(8, &mut decrypted, &trailer)
+            .expect_err("wrong page number");
+        cipher
+            .decrypt_page_detached(9, &mut decrypted, &trailer)
+            .expect("decrypt");
+        assert_eq!(decrypted, plaintext);
+        assert_eq!(PageCipher::sidecar_offset(9), 9 * PAGE_TRAILERBYTES as u64);
+    }
+
+    #[test]
+    fn test_page_sizes() {
+        let key = Key::gen();
+        let file_id = FileId::gen();
+        for size in [0, 256, 1000, 131072] {
+            assert!(PageCipher::new(&key, &file_id, size).is_err());
+        }
+        for size in [512, 4096, 65536] {
+            assert!(PageCipher::new(&key, &file_id, size).is_ok());
+        }
+    }
+}
@@ -0,0 +1,430 @@
+//! # PIN-authenticated device pairing
+//!
+//! A pairing protocol for the "enter the code shown on the other device" flow:
+//! one device displays a short random code, the user types it into the other
+//! device, and the two devices establish a [`kx::Session`](crate::kx::Session)
+//! over an insecure channel (such as Bluetooth, or a relay server) without any
+//! prior shared keys.
+//!
+//! The code is used as the password of a balanced password-authenticated key
+//! exchange, [SPAKE2](https://www.rfc-editor.org/rfc/rfc9382) over
+//! ristretto255, followed by an exchange of key confirmation MACs. An attacker
+//! on the channel learns nothing about the code from a pairing it observes, and
+//! can't test guesses offline: each active attempt tests a single guess, and
+//! causes the pairing to fail. With the default 6 digit code, an attacker has a
+//! one in a million chance per attempt, so applications should generate a new
+//! code after a failed pairing, and limit how many attempts are made.
+//!
+//! Each device has a [`Role`]: the [initiator](Role::Initiator) is usually the
+//! device which displays the code. Both devices must use the same `context`,
+//! which should identify the application, and can also bind the pairing to
+//! device identifiers exchanged beforehand, such as from a
+//! [`ContactCard`](crate::contactcard::ContactCard).
+//!
+//! The protocol takes two round trips:
+//!
+//! 1. Each device calls [`Pairing::start`], and sends its [`PairingMessage`] to
+//!    the other device
+//! 2. Each device calls [`Pairing::finish`] with the other device's message,
+//!    and sends its [`Confirmation`] to the other device
+//! 3. Each device calls [`PendingSession::verify`] with the other device's
+//!    confirmation, which returns the session if both devices used the same
+//!    code
+//!
+//! Messages in each step can be sent in either order, or at the same time.
+//!
+//! This protocol follows the structure of SPAKE2, but isn't wire-compatible
+//! with RFC 9382.
+//!
+//! ## Example
+//!
+//! ```
+//! use dryoc::pairing::*;
+//!
+//! // The initiator displays a code, which the user enters on the responder
+//! let code = gen_code(6);
+//! let entered = code.clone();
+//!
+//! let (initiator, initiator_message) =
+//!     Pairing::start(Role::Initiator, code.as_bytes(), b"my app").expect("start failed");
+//! let (responder, responder_message) =
+//!     Pairing::start(Role::Responder, entered.as_bytes(), b"my app").expect("start failed");
+//!
+//! let (initiator, initiator_confirmation) =
+//!     initiator.finish(&responder_message).expect("finish failed");
+//! let (responder, responder_confirmation) =
+//!     responder.finish(&initiator_message).expect("finish failed");
+//!
+//! let initiator_session = initiator
+//!     .verify(&responder_confirmation)
+//!     .expect("verify failed");
+//! let responder_session = responder
+//!     .verify(&initiator_confirmation)
+//!     .expect("verify failed");
+//!
+//! assert_eq!(
+//!     initiator_session.tx_as_slice(),
+//!     responder_session.rx_as_slice()
+//! );
+//! assert_eq!(
+//!     initiator_session.rx_as_slice(),
+//!     responder_session.tx_as_slice()
+//! );
+//! ```
+use curve25519_dalek::constants::RISTRETTO_BASEPOINT_TABLE;
+use curve25519_dalek::ristretto::RistrettoPoint;
+use curve25519_dalek::scalar::Scalar;
+use curve25519_dalek::traits::IsIdentity;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha512};
+use subtle::ConstantTimeEq;
+use zeroize::{Zeroize, ZeroizeOnDrop};
+
+use crate::error::Error;
+use crate::hkdf::{expand, hmac};
+use crate::kx::{SessionKey, StackSession};
+use crate::ristretto_util::{decode_element, encode_element, random_scalar};
+use crate::rng::copy_randombytes;
+use crate::types::*;
+
+/// Length of an encoded ristretto255 group element.
+pub const PAIRING_ELEMENTBYTES: usize = 32;
+/// Length of a key confirmation MAC (HMAC-SHA512).
+pub const PAIRING_MACBYTES: usize = 64;
+/// Default number of digits in a code returned by [`gen_code`].
+pub const PAIRING_CODE_DIGITS: usize = 6;
+
+const DST_M: &[u8] = b"dryoc-pairing-v1 M";
+const DST_N: &[u8] = b"dryoc-pairing-v1 N";
+const DST_CODE: &[u8] = b"dryoc-pairing-v1 Code";
+const DST_TRANSCRIPT: &[u8] = b"dryoc-pairing-v1 Transcript";
+
+/// Encoded ristretto255 group element.
+pub type Element = StackByteArray<PAIRING_ELEMENTBYTES>;
+/// Key confirmation MAC.
+pub type Mac = StackByteArray<PAIRING_MACBYTES>;
+
+#[cfg_attr(
+    feature = "serde",
+    derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)
+)]
+#[cfg_attr(not(feature = "serde"), derive(Clone, Copy, Debug, PartialEq, Eq))]
+/// Role of a device in a pairing. The two devices must have different roles.
+pub enum Role {
+    /// The device which starts the pairing, usually the one displaying the
+    /// code
+    Initiator,
+    /// The other device, usually the one the code is entered into
+    Responder,
+}
+
+#[cfg_attr(
+    feature = "serde",
+    derive(Zeroize, Clone, Debug, Serialize, Deserialize)
+)]
+#[cfg_attr(not(feature = "serde"), derive(Zeroize, Clone, Debug))]
+/// First message of a pairing, sent to the other device.
+pub struct PairingMessage {
+    /// Ephemeral public key, masked with the code
+    pub element: Element,
+}
+
+#[cfg_attr(
+    feature = "serde",
+    derive(Zeroize, Clone, Debug, Serialize, Deserialize)
+)]
+#[cfg_attr(not(feature = "serde"), derive(Zeroize, Clone, Debug))]
+/// Second message of a pairing, sent to the other device.
+pub struct Confirmation {
+    /// Key confirmation MAC
+    pub mac: Mac,
+}
+
+/// Device state between [`Pairing::start`] and [`Pairing::finish`].
+#[derive(Zeroize, ZeroizeOnDrop)]
+pub struct Pairing {
+    #[zeroize(skip)]
+    role: Role,
+    context: Vec<u8>,
+    password: Scalar,
+    ephemeral_secret: Scalar,
+    element: Element,
+}
+
+/// Device state between [`Pairing::finish`] and [`PendingSession::verify`].
+#[derive(Zeroize, ZeroizeOnDrop)]
+pub struct PendingSession {
+    expected_mac: Mac,
+    rx_key: SessionKey,
+    tx_key: SessionKey,
+}
+
+/// Returns a random code of `digits` decimal digits, for display on one
+/// device and entry on the other. Each digit is chosen uniformly at random.
+pub fn gen_code(digits: usize) -> String {
+    let mut code = String::with_capacity(digits);
+    let mut random = [0u8; 1];
+    while code.len() < digits {
+        copy_randombytes(&mut random);
+        // Reject values which would bias the digits
+        if random[0] < 250 {
+            code.push(char::from(b'0' + random[0] % 10));
+        }
+    }
+    random.zeroize();
+    code
+}
+
+impl Pairing {
+    /// Starts a pairing with `code` and `context` in `role`, returning this
+    /// device's state, and the message to send to the other device.
+    pub fn start<Code: Bytes + ?Sized>(
+        role: Role,
+        code: &Code,
+        context: &[u8],
+    ) -> Result<(Self, PairingMessage), Error> {
+        if code.as_slice().is_empty() {
+            return Err(dryoc_error!("pairing code is empty"));
+        }
+
+        let password = hash_code(code.as_slice(), context);
+        let ephemeral_secret = random_scalar();
+        let element = encode_element(
+            &(RISTRETTO_BASEPOINT_TABLE * &ephemeral_secret + password * own_generator(role)),
+        );
+
+        let message = PairingMessage {
+            element: element.clone(),
+        };
+        Ok((
+            Self {
+                role,
+                context: context.to_vec(),
+                password,
+                ephemeral_secret,
+                element,
+            },
+            message,
+        ))
+    }
+
+    /// Finishes the key exchange with the other device's `message`, returning
+    /// this device's state, and the confirmation to send to the other device.
+    ///
+    /// This doesn't detect whether the devices used different codes: that's
+    /// done by [`PendingSession::verify`].
+    pub fn finish(self, message: &PairingMessage) -> Result<(PendingSession, Confirmation), Error> {
+        let peer_element = decode_element(&message.element)?;
+        let mut shared_point =
+            self.ephemeral_secret * (peer_element - self.password * peer_generator(self.role));
+        if shared_point.is_identity() {
+            return Err(dryoc_error!("invalid pairing message"));
+        }
+        let mut shared_secret = shared_point.compress().to_bytes();
+        shared_point.zeroize();
+
+        let (initiator_element, responder_element) = match self.role {
+            Role::Initiator => (&self.element, &message.element),
+            Role::Responder => (&message.element, &self.element),
+        };
+        let mut transcript_hash: [u8; 64] = Sha512::new()
+            .chain_update(DST_TRANSCRIPT)
+            .chain_update((self.context.len() as u64).to_be_bytes())
+            .chain_update(&self.context)
+            .chain_update(initiator_element)
+            .chain_update(responder_element)
+            .chain_update(shared_secret)
+            .chain_update(self.password.as_bytes())
+            .finalize()
+            .into();
+        shared_secret.zeroize();
+
+        let keys = derive_keys(&transcript_hash);
+        transcript_hash.zeroize();
+        let keys = keys?;
+
+        let (mac_key, peer_mac_key, rx_key, tx_key) = match self.role {
+            Role::Initiator => (
+                &keys.initiator_mac_key,
+                &keys.responder_mac_key,
+                &keys.responder_to_initiator,
+                &keys.initiator_to_responder,
+            ),
+            Role::Responder => (
+                &keys.responder_mac_key,
+                &keys.initiator_mac_key,
+                &keys.initiator_to_responder,
+                &keys.responder_to_initiator,
+            ),
+        };
+
+        let confirmation = Confirmation {
+            mac: mac(mac_key, &message.element),
+        };
+        let pending = PendingSession {
+            expected_mac: mac(peer_mac_key, &self.element),
+            rx_key: rx_key.clone(),
+            tx_key: tx_key.clone(),
+        };
+
+        Ok((pending, confirmation))
+    }
+}
+
+impl PendingSession {
+    /// Verifies the other device's `confirmation`, and returns the session if
+    /// both devices used the same code.
+    pub fn verify(self, confirmation: &Confirmation) -> Result<StackSession, Error> {
+        if confirmation
+            .mac
+            .as_slice()
+            .ct_eq(self.expected_mac.as_slice())
+            .unwrap_u8()
+            == 1
+        {
+            Ok(StackSession::from_parts(
+                self.rx_key.clone(),
+                self.tx_key.clone(),
+            ))
+        } else {
+            Err(dryoc_error!(
+                "pairing failed: incorrect code or invalid confirmation"
+            ))
+        }
+    }
+}
+
+#[derive(Zeroize, ZeroizeOnDrop)]
+struct Keys {
+    initiator_mac_key: [u8; PAIRING_MACBYTES],
+    responder_mac_key: [u8; PAIRING_MACBYTES],
+    initiator_to_responder: SessionKey,
+    responder_to_initiator: SessionKey,
+}
+
+fn derive_keys(transcript_hash: &[u8]) -> Result<Keys, Error> {
+    let mut keys = Keys {
+        initiator_mac_key: [0u8; PAIRING_MACBYTES],
+        responder_mac_key: [0u8; PAIRING_MACBYTES],
+        initiator_to_responder: SessionKey::default(),
+        responder_to_initiator: SessionKey::default(),
+    };
+    expand::<Sha512>(
+        &mut keys.initiator_mac_key,
+        transcript_hash,
+        &[b"InitiatorMAC"],
+    )?;
+    expand::<Sha512>(
+        &mut keys.responder_mac_key,
+        transcript_hash,
+        &[b"ResponderMAC"],
+    )?;
+    expand::<Sha512>(
+        keys.initiator_to_responder.as_mut_slice(),
+        transcript_hash,
+        &[b"InitiatorToResponder"],
+    )?;
+    expand::<Sha512>(
+        keys.responder_to_initiator.as_mut_slice(),
+        transcript_hash,
+        &[b"ResponderToInitiator"],
+    )?;
+    Ok(keys)
+}
+
+fn mac(key: &[u8], element: &Element) -> Mac {
+    let mut output = hmac::<Sha512>(key, &[element.as_slice()]);
+    let mac = Mac::from(<[u8; PAIRING_MACBYTES]>::from(output));
+    output.as_mut_slice().zeroize();
+    mac
+}
+
+fn hash_to_group(dst: &[u8], input: &[u8]) -> RistrettoPoint {
+    let mut hash: [u8; 64] = Sha512::new()
+        .chain_update(dst)
+        .chain_update(input)
+        .finalize()
+        .into();
+    let point = RistrettoPoint::from_uniform_bytes(&hash);
+    hash.zeroize();
+    point
+}
+
+/// Returns the generator which masks this device's element: M for the
+/// initiator, and N for the responder. Neither has a known discrete log.
+fn own_generator(role: Role) -> RistrettoPoint {
+    match role {
+        Role::Initiator => hash_to_group(DST_M, &[]),
+        Role::Responder => hash_to_group(DST_N, &[]),
+    }
+}
+
+fn peer_generator(role: Role) -> RistrettoPoint {
+    match role {
+        Role::Initiator => own_generator(Role::Responder),
+        Role::Responder => own_generator(Role::Initiator),
+    }
+}
+
+fn hash_code(code: &[u8], context: &[u8]) -> Scalar {
+    let mut hash: [u8; 64] = Sha512::new()
+        .chain_update(DST_CODE)
+        .chain_update((context.len() as u64).to_be_bytes())
+        .chain_update(context)
+        .chain_update(code)
+        .finalize()
+        .into();
+    let scalar = Scalar::from_bytes_mod_order_wide(&hash);
+    hash.zeroize();
+    scalar
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pair(
+        initiator_code: &str,
+        responder_code: &str,
+    ) -> (Result<StackSession, Error>, Result<StackSession, Error>) {
+        let (initiator, initiator_message) =
+            Pairing::start(Role::Initiator, initiator_code.as_bytes(), b"test").expect("start");
+        let (responder, responder_message) =
+            Pairing::start(Role::Responder, responder_code.as_bytes(), b"test").expect("start");
+        let (initiator, initiator_confirmation) =
+            initiator.finish(&responder_message).expect("finish");
+        let (responder, responder_confirmation) =
+            responder.finish(&initiator_message).expect("finish");
+        (
+            initiator.verify(&responder_confirmation),
+            responder.verify(&initiator_confirmation),
+        )
+    }
+
+    #[test]
+    fn test_pairing() {
+        let code = gen_code(PAIRING_CODE_DIGITS);
+        assert_eq!(code.len(), PAIRING_CODE_DIGITS);
+        assert!(code.bytes().all(|c| c.is_ascii_digit()));
+
+        let (initiator, responder) = pair(&code, &code);
+        let (initiator, responder) = (initiator.expect("verify"), responder.expect("verify"));
+        assert_eq!(initiator.tx_as_slice(), responder.rx_as_slice());
+        assert_eq!(initiator.rx_as_slice(), responder.tx_as_slice());
+        assert_ne!(initiator.tx_as_slice(), initiator.rx_as_slice());
+
+        let (initiator, responder) = pair("123456", "123457");
+        assert!(initiator.is_err());
+        assert!(responder.is_err());
+
+        // Both devices in the same role can't pair
+        let (a, a_message) = Pairing::start(Role::Initiator, b"123456", b"test").expect("start");
+        let (b, b_message) = Pairing::start(Role::Initiator, b"123456", b"test").expect("start");
+        let (a, _) = a.finish(&b_message).expect("finish");
+        let (_, b_confirmation) = b.finish(&a_message).expect("finish");
+        assert!(a.verify(&b_confirmation).is_err());
+
+        assert!(Pairing::start(Role::Initiator, b"", b"test").is_err());
+    }
+}
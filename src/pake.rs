@@ -0,0 +1,783 @@
+//! # Password-authenticated key exchange
+//!
+//! An augmented password-authenticated key exchange (PAKE) in the style of
+//! [OPAQUE](https://www.rfc-editor.org/rfc/rfc9807), built from an oblivious
+//! PRF over ristretto255, Argon2id (see [`crate::pwhash`]), and a 3DH key
+//! exchange. It lets a client log in with a password without ever revealing
+//! the password to the server, neither during registration nor login.
+//!
+//! The server stores a [`RegistrationRecord`] for each user, which can't be
+//! used to mount an offline dictionary attack without also compromising the
+//! server's [`ServerSetup`]. A successful login proves knowledge of the
+//! password to the server, authenticates the server to the client, and gives
+//! both parties the same [`SessionKey`].
+//!
+//! Each side of the protocol is a small state machine:
+//!
+//! * Registration: [`ClientRegistration::start`] → [`ServerSetup::register`] →
+//!   [`ClientRegistration::finish`], after which the server stores the
+//!   resulting [`RegistrationRecord`]
+//! * Login: [`ClientLogin::start`] → [`ServerSetup::login`] →
+//!   [`ClientLogin::finish`] → [`ServerLogin::finish`]
+//!
+//! The credential identifier (such as a user ID) passed to the server is
+//! chosen by the application, and should be sent alongside the client's
+//! requests. The client's password hashing [`Config`] must be the same for
+//! registration and every subsequent login; only its memory and ops limits
+//! are used.
+//!
+//! This protocol follows the structure of OPAQUE, but isn't wire-compatible
+//! with RFC 9807, and doesn't hide whether a credential identifier is
+//! registered.
+//!
+//! If the `serde` feature is enabled, the [`serde::Deserialize`] and
+//! [`serde::Serialize`] traits will be implemented for the protocol messages,
+//! [`RegistrationRecord`], and [`ServerSetup`].
+//!
+//! ## Example
+//!
+//! ```
+//! use dryoc::pake::*;
+//! use dryoc::pwhash::Config;
+//!
+//! # let config = Config::interactive().with_memlimit(8192).with_opslimit(1);
+//! # /*
+//! let config = Config::interactive();
+//! # */
+//! let server = ServerSetup::gen();
+//!
+//! // Registration
+//! let (client, request) =
+//!     ClientRegistration::start(b"correct horse", config.clone()).expect("start failed");
+//! let response = server
+//!     .register(b"alice", &request)
+//!     .expect("register failed");
+//! let record = client.finish(&response).expect("finish failed");
+//!
+//! // Login
+//! let (client, request) =
+//!     ClientLogin::start(b"correct horse", config.clone()).expect("start failed");
+//! let (server_login, response) = server
+//!     .login(b"alice", &record, &request)
+//!     .expect("login failed");
+//! let (finalization, client_session_key) = client.finish(&response).expect("finish failed");
+//! let server_session_key = server_login.finish(&finalization).expect("finish failed");
+//!
+//! assert_eq!(client_session_key, server_session_key);
+//!
+//! // The wrong password is detected by the client
+//! let (client, request) = ClientLogin::start(b"battery staple", config).expect("start failed");
+//! let (_, response) = server
+//!     .login(b"alice", &record, &request)
+//!     .expect("login failed");
+//! assert!(client.finish(&response).is_err());
+//! ```
+use curve25519_dalek::constants::RISTRETTO_BASEPOINT_TABLE;
+use curve25519_dalek::ristretto::RistrettoPoint;
+use curve25519_dalek::scalar::Scalar;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha512};
+use subtle::ConstantTimeEq;
+use zeroize::{Zeroize, ZeroizeOnDrop};
+
+use crate::classic::crypto_pwhash::{crypto_pwhash, PasswordHashAlgorithm};
+use crate::constants::CRYPTO_PWHASH_SALTBYTES;
+use crate::error::Error;
+use crate::hkdf::{expand, hmac};
+use crate::pwhash::Config;
+use crate::ristretto_util::{decode_element, encode_element, random_scalar};
+use crate::rng::copy_randombytes;
+use crate::types::*;
+
+/// Length of an encoded ristretto255 group element.
+pub const PAKE_ELEMENTBYTES: usize = 32;
+/// Length of a public key.
+pub const PAKE_PUBLICKEYBYTES: usize = 32;
+/// Length of a secret key.
+pub const PAKE_SECRETKEYBYTES: usize = 32;
+/// Length of a nonce.
+pub const PAKE_NONCEBYTES: usize = 32;
+/// Length of a message authentication code (HMAC-SHA512).
+pub const PAKE_MACBYTES: usize = 64;
+/// Length of the shared session key.
+pub const PAKE_SESSIONKEYBYTES: usize = 32;
+
+const OPRF_SEEDBYTES: usize = 32;
+
+const DST_HASH_TO_GROUP: &[u8] = b"dryoc-pake-v1 HashToGroup";
+const DST_FINALIZE: &[u8] = b"dryoc-pake-v1 Finalize";
+const DST_OPRF_KEY: &[u8] = b"dryoc-pake-v1 OprfKey";
+const DST_TRANSCRIPT: &[u8] = b"dryoc-pake-v1 Transcript";
+
+/// Encoded ristretto255 group element.
+pub type Element = StackByteArray<PAKE_ELEMENTBYTES>;
+/// Public key, an encoded ristretto255 group element.
+pub type PublicKey = StackByteArray<PAKE_PUBLICKEYBYTES>;
+/// Secret key, an encoded ristretto255 scalar.
+pub type SecretKey = StackByteArray<PAKE_SECRETKEYBYTES>;
+/// Random nonce.
+pub type Nonce = StackByteArray<PAKE_NONCEBYTES>;
+/// Message authentication code.
+pub type Mac = StackByteArray<PAKE_MACBYTES>;
+/// Session key shared by the client and server after a successful login.
+pub type SessionKey = StackByteArray<PAKE_SESSIONKEYBYTES>;
+
+#[cfg_attr(
+    feature = "serde",
+    derive(Zeroize, ZeroizeOnDrop, Clone, Serialize, Deserialize)
+)]
+#[cfg_attr(not(feature = "serde"), derive(Zeroize, ZeroizeOnDrop, Clone))]
+/// The server's long-term keys: a static keypair, and a seed from which a
+/// distinct OPRF key is derived for each credential identifier. This must be
+/// kept secret, and the same setup must be used for registration and login.
+pub struct ServerSetup {
+    public_key: PublicKey,
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::redact::serialize_secret")
+    )]
+    secret_key: SecretKey,
+    oprf_seed: StackByteArray<OPRF_SEEDBYTES>,
+}
+
+#[cfg_attr(
+    feature = "serde",
+    derive(Zeroize, Clone, Debug, Serialize, Deserialize)
+)]
+#[cfg_attr(not(feature = "serde"), derive(Zeroize, Clone, Debug))]
+/// Registration request, sent from the client to the server.
+pub struct RegistrationRequest {
+    /// Blinded password element
+    pub blinded_element: Element,
+}
+
+#[cfg_attr(
+    feature = "serde",
+    derive(Zeroize, Clone, Debug, Serialize, Deserialize)
+)]
+#[cfg_attr(not(feature = "serde"), derive(Zeroize, Clone, Debug))]
+/// Registration response, sent from the server to the client.
+pub struct RegistrationResponse {
+    /// Blinded password element, evaluated with the server's OPRF key
+    pub evaluated_element: Element,
+    /// Server's static public key
+    pub server_public_key: PublicKey,
+}
+
+#[cfg_attr(
+    feature = "serde",
+    derive(Zeroize, Clone, Debug, Serialize, Deserialize)
+)]
+#[cfg_attr(not(feature = "serde"), derive(Zeroize, Clone, Debug))]
+/// Registration record, sent from the client to the server at the end of
+/// registration, and stored by the server with the credential identifier.
+pub struct RegistrationRecord {
+    /// Client's static public key
+    pub client_public_key: PublicKey,
+    /// Nonce from which the client's keys are derived
+    pub envelope_nonce: Nonce,
+    /// Tag binding the client's keys to the server's public key
+    pub envelope_tag: Mac,
+}
+
+#[cfg_attr(
+    feature = "serde",
+    derive(Zeroize, Clone, Debug, Serialize, Deserialize)
+)]
+#[cfg_attr(not(feature = "serde"), derive(Zeroize, Clone, Debug))]
+/// Login request, sent from the client to the server.
+pub struct LoginRequest {
+    /// Blinded password element
+    pub blinded_element: Element,
+    /// Client's nonce
+    pub client_nonce: Nonce,
+    /// Client's ephemeral public key
+    pub client_ephemeral_public_key: PublicKey,
+}
+
+#[cfg_attr(
+    feature = "serde",
+    derive(Zeroize, Clone, Debug, Serialize, Deserialize)
+)]
+#[cfg_attr(not(feature = "serde"), derive(Zeroize, Clone, Debug))]
+/// Login response, sent from the server to the client.
+pub struct LoginResponse {
+    /// Blinded password element, evaluated with the server's OPRF key
+    pub evaluated_element: Element,
+    /// Server's static public key
+    pub server_public_key: PublicKey,
+    /// Nonce from the registration record
+    pub envelope_nonce: Nonce,
+    /// Tag from the registration record
+    pub envelope_tag: Mac,
+    /// Server's nonce
+    pub server_nonce: Nonce,
+    /// Server's ephemeral public key
+    pub server_ephemeral_public_key: PublicKey,
+    /// Server's key confirmation
+    pub server_mac: Mac,
+}
+
+#[cfg_attr(
+    feature = "serde",
+    derive(Zeroize, Clone, Debug, Serialize, Deserialize)
+)]
+#[cfg_attr(not(feature = "serde"), derive(Zeroize, Clone, Debug))]
+/// Login finalization, sent from the client to the server.
+pub struct LoginFinalization {
+    /// Client's key confirmation
+    pub client_mac: Mac,
+}
+
+/// Client state between [`ClientRegistration::start`] and
+/// [`ClientRegistration::finish`].
+#[derive(Zeroize, ZeroizeOnDrop)]
+pub struct ClientRegistration {
+    password: Vec<u8>,
+    blind: Scalar,
+    #[zeroize(skip)]
+    config: Config,
+}
+
+/// Client state between [`ClientLogin::start`] and [`ClientLogin::finish`].
+#[derive(Zeroize, ZeroizeOnDrop)]
+pub struct ClientLogin {
+    password: Vec<u8>,
+    blind: Scalar,
+    ephemeral_secret: Scalar,
+    request: LoginRequest,
+    #[zeroize(skip)]
+    config: Config,
+}
+
+/// Server state between [`ServerSetup::login`] and [`ServerLogin::finish`].
+#[derive(Zeroize, ZeroizeOnDrop)]
+pub struct ServerLogin {
+    expected_client_mac: Mac,
+    session_key: SessionKey,
+}
+
+#[derive(Zeroize, ZeroizeOnDrop)]
+struct Keys {
+    server_mac_key: [u8; PAKE_MACBYTES],
+    client_mac_key: [u8; PAKE_MACBYTES],
+    session_key: SessionKey,
+}
+
+impl ServerSetup {
+    /// Generates a new random server setup.
+    pub fn gen() -> Self {
+        let secret = random_scalar();
+        let mut oprf_seed = StackByteArray::<OPRF_SEEDBYTES>::new_byte_array();
+        copy_randombytes(oprf_seed.as_mut_slice());
+
+        Self {
+            public_key: encode_element(&(RISTRETTO_BASEPOINT_TABLE * &secret)),
+            secret_key: SecretKey::from(secret.to_bytes()),
+            oprf_seed,
+        }
+    }
+
+    /// Returns the server's static public key.
+    pub fn public_key(&self) -> &PublicKey {
+        &self.public_key
+    }
+
+    /// Evaluates a client's registration `request` for `credential_id`.
+    pub fn register(
+        &self,
+        credential_id: &[u8],
+        request: &RegistrationRequest,
+    ) -> Result<RegistrationResponse, Error> {
+        Ok(RegistrationResponse {
+            evaluated_element: self.evaluate(credential_id, &request.blinded_element)?,
+            server_public_key: self.public_key.clone(),
+        })
+    }
+
+    /// Evaluates a client's login `request` for `credential_id`, using the
+    /// `record` stored at registration. Returns the server's state, which is
+    /// used to verify the client's [`LoginFinalization`], and the response to
+    /// send to the client.
+    pub fn login(
+        &self,
+        credential_id: &[u8],
+        record: &RegistrationRecord,
+        request: &LoginRequest,
+    ) -> Result<(ServerLogin, LoginResponse), Error> {
+        let client_public_key = decode_element(&record.client_public_key)?;
+        let client_ephemeral_public_key = decode_element(&request.client_ephemeral_public_key)?;
+
+        let ephemeral_secret = random_scalar();
+        let mut response = LoginResponse {
+            evaluated_element: self.evaluate(credential_id, &request.blinded_element)?,
+            server_public_key: self.public_key.clone(),
+            envelope_nonce: record.envelope_nonce.clone(),
+            envelope_tag: record.envelope_tag.clone(),
+            server_nonce: Nonce::gen(),
+            server_ephemeral_public_key: encode_element(
+                &(RISTRETTO_BASEPOINT_TABLE * &ephemeral_secret),
+            ),
+            server_mac: Mac::default(),
+        };
+
+        let mut secret = Scalar::from_bytes_mod_order(*self.secret_key.as_array());
+        let transcript_hash = transcript_hash(request, &response, &record.client_public_key);
+        let keys = derive_keys(
+            [
+                ephemeral_secret * client_ephemeral_public_key,
+                secret * client_ephemeral_public_key,
+                ephemeral_secret * client_public_key,
+            ],
+            &transcript_hash,
+        );
+        secret.zeroize();
+        let keys = keys?;
+
+        response.server_mac = mac(&keys.server_mac_key, &[&transcript_hash]);
+        let state = ServerLogin {
+            expected_client_mac: mac(
+                &keys.client_mac_key,
+                &[&transcript_hash, response.server_mac.as_slice()],
+            ),
+            session_key: keys.session_key.clone(),
+        };
+
+        Ok((state, response))
+    }
+
+    fn evaluate(&self, credential_id: &[u8], blinded_element: &Element) -> Result<Element, Error> {
+        let blinded_element = decode_element(blinded_element)?;
+
+        let mut wide = [0u8; 64];
+        expand::<Sha512>(
+            &mut wide,
+            self.oprf_seed.as_slice(),
+            &[DST_OPRF_KEY, credential_id],
+        )?;
+        let mut oprf_key = Scalar::from_bytes_mod_order_wide(&wide);
+        wide.zeroize();
+
+        let evaluated_element = encode_element(&(oprf_key * blinded_element));
+        oprf_key.zeroize();
+
+        Ok(evaluated_element)
+    }
+}
+
+impl ServerLogin {
+    /// Verifies the client's `finalization`, and returns the session key if
+    /// the client logged in with the correct password.
+    pub fn finish(self, finalization: &LoginFinalization) -> Result<SessionKey, Error> {
+        if finalization
+            .client_mac
+            .as_slice()
+            .ct_eq(self.expected_client_mac.as_slice())
+            .unwrap_u8()
+            == 1
+        {
+            Ok(self.session_key.clone())
+        } else {
+            Err(dryoc_error!("client authentication failed"))
+        }
+    }
+}
+
+impl ClientRegistration {
+    /// Starts registration for `password`, hardened with the password hashing
+    /// limits from `config`. Returns the client's state, and the request to
+    /// send to the server.
+    pub fn start<Password: Bytes + ?Sized>(
+        password: &Password,
+        config: Config,
+    ) -> Result<(Self, RegistrationRequest), Error> {
+        let (blind, blinded_element) = blind(password.as_slice());
+
+        Ok((
+            Self {
+                password: password.as_slice().to_vec(),
+                blind,
+                config,
+            },
+            RegistrationRequest { blinded_element },
+        ))
+    }
+
+    /// Finishes registration with the server's `response`, returning the
+    /// record to send to the server for storage.
+    pub fn finish(self, response: &RegistrationResponse) -> Result<RegistrationRecord, Error> {
+        let mut randomized_password = randomized_password(
+            &self.password,
+            &self.blind,
+            &response.evaluated_element,
+            &self.config,
+        )?;
+        let envelope_nonce = Nonce::gen();
+        let envelope = open_envelope(
+            &randomized_password,
+            &envelope_nonce,
+            &response.server_public_key,
+        );
+        randomized_password.zeroize();
+        let (client_public_key, mut secret, envelope_tag) = envelope?;
+        secret.zeroize();
+
+        Ok(RegistrationRecord {
+            client_public_key,
+            envelope_nonce,
+            envelope_tag,
+        })
+    }
+}
+
+impl ClientLogin {
+    /// Starts a login for `password`, hardened with the password hashing
+    /// limits from `config`, which must match those used at registration.
+    /// Returns the client's state, and the request to send to the server.
+    pub fn start<Password: Bytes + ?Sized>(
+        password: &Password,
+        config: Config,
+    ) -> Result<(Self, LoginRequest), Error> {
+        let (blind, blinded_element) = blind(password.as_slice());
+        let ephemeral_secret = random_scalar();
+        let request = LoginRequest {
+            blinded_element,
+            client_nonce: Nonce::gen(),
+            client_ephemeral_public_key: encode_element(
+                &(RISTRETTO_BASEPOINT_TABLE * &ephemeral_secret),
+            ),
+        };
+
+        Ok((
+            Self {
+                password: password.as_slice().to_vec(),
+                blind,
+                ephemeral_secret,
+                request: request.clone(),
+                config,
+            },
+            request,
+        ))
+    }
+
+    /// Finishes a login with the server's `response`. Returns the
+    /// finalization to send to the server, and the session key.
+    ///
+    /// Returns an error if the password is incorrect, or if the server
+    /// couldn't be authenticated.
+    pub fn finish(
+        self,
+        response: &LoginResponse,
+    ) -> Result<(LoginFinalization, SessionKey), Error> {
+        let mut randomized_password = randomized_password(
+            &self.password,
+            &self.blind,
+            &response.evaluated_element,
+            &self.config,
+        )?;
+        let envelope = open_envelope(
+            &randomized_password,
+            &response.envelope_nonce,
+            &response.server_public_key,
+        );
+        randomized_password.zeroize();
+        let (client_public_key, mut secret, mut envelope_tag) = envelope?;
+
+        let valid_envelope = envelope_tag
+            .as_slice()
+            .ct_eq(response.envelope_tag.as_slice());
+        envelope_tag.zeroize();
+        if valid_envelope.unwrap_u8() == 0 {
+            secret.zeroize();
+            return Err(dryoc_error!(
+                "incorrect password or invalid server response"
+            ));
+        }
+
+        let server_public_key = decode_element(&response.server_public_key);
+        let server_ephemeral_public_key = decode_element(&response.server_ephemeral_public_key);
+        let (server_public_key, server_ephemeral_public_key) =
+            match (server_public_key, server_ephemeral_public_key) {
+                (Ok(server_public_key), Ok(server_ephemeral_public_key)) => {
+                    (server_public_key, server_ephemeral_public_key)
+                }
+                (Err(err), _) | (_, Err(err)) => {
+                    secret.zeroize();
+                    return Err(err);
+                }
+            };
+
+        let transcript_hash = transcript_hash(&self.request, response, &client_public_key);
+        let keys = derive_keys(
+            [
+                self.ephemeral_secret * server_ephemeral_public_key,
+                self.ephemeral_secret * server_public_key,
+                secret * server_ephemeral_public_key,
+            ],
+            &transcript_hash,
+        );
+        secret.zeroize();
+        let keys = keys?;
+
+        let expected_server_mac = mac(&keys.server_mac_key, &[&transcript_hash]);
+        if response
+            .server_mac
+            .as_slice()
+            .ct_eq(expected_server_mac.as_slice())
+            .unwrap_u8()
+            == 0
+        {
+            return Err(dryoc_error!("server authentication failed"));
+        }
+
+        let finalization = LoginFinalization {
+            client_mac: mac(
+                &keys.client_mac_key,
+                &[&transcript_hash, response.server_mac.as_slice()],
+            ),
+        };
+
+        Ok((finalization, keys.session_key.clone()))
+    }
+}
+
+/// Hashes `password` to a group element, and blinds it with a random scalar.
+fn blind(password: &[u8]) -> (Scalar, Element) {
+    let mut hash: [u8; 64] = Sha512::new()
+        .chain_update(DST_HASH_TO_GROUP)
+        .chain_update(password)
+        .finalize()
+        .into();
+    let point = RistrettoPoint::from_uniform_bytes(&hash);
+    hash.zeroize();
+
+    let blind = random_scalar();
+    (blind, encode_element(&(blind * point)))
+}
+
+/// Unblinds the server's evaluation of the password, and hardens the OPRF
+/// output with Argon2id.
+fn randomized_password(
+    password: &[u8],
+    blind: &Scalar,
+    evaluated_element: &Element,
+    config: &Config,
+) -> Result<[u8; 64], Error> {
+    let evaluated_element = decode_element(evaluated_element)?;
+    let mut unblinded = (blind.invert() * evaluated_element).compress().to_bytes();
+    let mut oprf_output: [u8; 64] = Sha512::new()
+        .chain_update(DST_FINALIZE)
+        .chain_update((password.len() as u64).to_be_bytes())
+        .chain_update(password)
+        .chain_update(unblinded)
+        .finalize()
+        .into();
+    unblinded.zeroize();
+
+    // The OPRF output is already unique to the password, credential, and
+    // server, so a fixed salt is sufficient here
+    let mut hardened = [0u8; 64];
+    let result = crypto_pwhash(
+        &mut hardened,
+        &oprf_output,
+        &[0u8; CRYPTO_PWHASH_SALTBYTES],
+        config.opslimit(),
+        config.memlimit(),
+        PasswordHashAlgorithm::Argon2id13,
+    );
+
+    let mut prk = hmac::<Sha512>(&[], &[&oprf_output, &hardened]);
+    oprf_output.zeroize();
+    hardened.zeroize();
+    let mut randomized_password = [0u8; 64];
+    randomized_password.copy_from_slice(&prk);
+    prk.as_mut_slice().zeroize();
+
+    match result {
+        Ok(()) => Ok(randomized_password),
+        Err(err) => {
+            randomized_password.zeroize();
+            Err(err)
+        }
+    }
+}
+
+/// Derives the client's static keypair from the randomized password and
+/// envelope nonce, and computes the envelope tag which binds them to the
+/// server's public key.
+fn open_envelope(
+    randomized_password: &[u8],
+    envelope_nonce: &Nonce,
+    server_public_key: &PublicKey,
+) -> Result<(PublicKey, Scalar, Mac), Error> {
+    let mut auth_key = [0u8; 64];
+    let mut seed = [0u8; 64];
+    let result = expand::<Sha512>(
+        &mut auth_key,
+        randomized_password,
+        &[envelope_nonce.as_slice(), b"AuthKey"],
+    )
+    .and_then(|()| {
+        expand::<Sha512>(
+            &mut seed,
+            randomized_password,
+            &[envelope_nonce.as_slice(), b"PrivateKey"],
+        )
+    });
+    let secret = Scalar::from_bytes_mod_order_wide(&seed);
+    seed.zeroize();
+
+    let client_public_key = encode_element(&(RISTRETTO_BASEPOINT_TABLE * &secret));
+    let envelope_tag = mac(
+        &auth_key,
+        &[
+            envelope_nonce.as_slice(),
+            client_public_key.as_slice(),
+            server_public_key.as_slice(),
+        ],
+    );
+    auth_key.zeroize();
+
+    result.map(|()| (client_public_key, secret, envelope_tag))
+}
+
+fn transcript_hash(
+    request: &LoginRequest,
+    response: &LoginResponse,
+    client_public_key: &PublicKey,
+) -> [u8; 64] {
+    Sha512::new()
+        .chain_update(DST_TRANSCRIPT)
+        .chain_update(client_public_key)
+        .chain_update(&request.blinded_element)
+        .chain_update(&request.client_nonce)
+        .chain_update(&request.client_ephemeral_public_key)
+        .chain_update(&response.server_public_key)
+        .chain_update(&response.evaluated_element)
+        .chain_update(&response.envelope_nonce)
+        .chain_update(&response.envelope_tag)
+        .chain_update(&response.server_nonce)
+        .chain_update(&response.server_ephemeral_public_key)
+        .finalize()
+        .into()
+}
+
+/// Derives the key confirmation and session keys from the three
+/// Diffie-Hellman shared secrets.
+fn derive_keys(
+    mut shared_secrets: [RistrettoPoint; 3],
+    transcript_hash: &[u8],
+) -> Result<Keys, Error> {
+    let mut ikm = [[0u8; PAKE_ELEMENTBYTES]; 3];
+    for (bytes, shared_secret) in ikm.iter_mut().zip(shared_secrets.iter()) {
+        *bytes = shared_secret.compress().to_bytes();
+    }
+    shared_secrets.zeroize();
+
+    let mut prk = hmac::<Sha512>(&[], &[&ikm[0], &ikm[1], &ikm[2]]);
+    ikm.zeroize();
+
+    let mut keys = Keys {
+        server_mac_key: [0u8; PAKE_MACBYTES],
+        client_mac_key: [0u8; PAKE_MACBYTES],
+        session_key: SessionKey::default(),
+    };
+    let mut handshake_secret = [0u8; 64];
+    let result = expand::<Sha512>(
+        &mut handshake_secret,
+        &prk,
+        &[b"HandshakeSecret", transcript_hash],
+    )
+    .and_then(|()| {
+        expand::<Sha512>(
+            keys.session_key.as_mut_slice(),
+            &prk,
+            &[b"SessionKey", transcript_hash],
+        )
+    })
+    .and_then(|()| expand::<Sha512>(&mut keys.server_mac_key, &handshake_secret, &[b"ServerMAC"]))
+    .and_then(|()| expand::<Sha512>(&mut keys.client_mac_key, &handshake_secret, &[b"ClientMAC"]));
+    prk.as_mut_slice().zeroize();
+    handshake_secret.zeroize();
+
+    result.map(|()| keys)
+}
+
+fn mac(key: &[u8], message: &[&[u8]]) -> Mac {
+    let mut output = hmac::<Sha512>(key, message);
+    let mac = Mac::from(<[u8; PAKE_MACBYTES]>::from(output));
+    output.as_mut_slice().zeroize();
+    mac
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> Config {
+        Config::interactive().with_memlimit(8192).with_opslimit(1)
+    }
+
+    fn register(server: &ServerSetup, credential_id: &[u8], password: &[u8]) -> RegistrationRecord {
+        let (client, request) = ClientRegistration::start(password, config()).expect("start");
+        let response = server.register(credential_id, &request).expect("register");
+        client.finish(&response).expect("finish")
+    }
+
+    #[test]
+    fn test_login() {
+        let server = ServerSetup::gen();
+        let record = register(&server, b"alice", b"hunter2");
+
+        for _ in 0..2 {
+            let (client, request) = ClientLogin::start(b"hunter2", config()).expect("start");
+            let (server_login, response) =
+                server.login(b"alice", &record, &request).expect("login");
+            let (finalization, client_key) = client.finish(&response).expect("finish");
+            let server_key = server_login.finish(&finalization).expect("finish");
+            assert_eq!(client_key, server_key);
+        }
+
+        // Wrong password
+        let (client, request) = ClientLogin::start(b"hunter3", config()).expect("start");
+        let (_, response) = server.login(b"alice", &record, &request).expect("login");
+        assert!(client.finish(&response).is_err());
+
+        // Record evaluated for a different credential identifier
+        let (client, request) = ClientLogin::start(b"hunter2", config()).expect("start");
+        let (_, response) = server.login(b"bob", &record, &request).expect("login");
+        assert!(client.finish(&response).is_err());
+
+        // Different server setup
+        let (client, request) = ClientLogin::start(b"hunter2", config()).expect("start");
+        let (_, response) = ServerSetup::gen()
+            .login(b"alice", &record, &request)
+            .expect("login");
+        assert!(client.finish(&response).is_err());
+    }
+
+    #[test]
+    fn test_tampering() {
+        let server = ServerSetup::gen();
+        let record = register(&server, b"alice", b"hunter2");
+
+        // Tampered server response
+        let (client, request) = ClientLogin::start(b"hunter2", config()).expect("start");
+        let (_, mut response) = server.login(b"alice", &record, &request).expect("login");
+        response.server_nonce[0] ^= 1;
+        assert!(client.finish(&response).is_err());
+
+        // Tampered client finalization
+        let (client, request) = ClientLogin::start(b"hunter2", config()).expect("start");
+        let (server_login, response) = server.login(b"alice", &record, &request).expect("login");
+        let (mut finalization, _) = client.finish(&response).expect("finish");
+        finalization.client_mac[0] ^= 1;
+        assert!(server_login.finish(&finalization).is_err());
+
+        // Invalid group elements are rejected
+        let (_, mut request) = ClientLogin::start(b"hunter2", config()).expect("start");
+        request.client_ephemeral_public_key = PublicKey::default();
+        assert!(server.login(b"alice", &record, &request).is_err());
+    }
+}
@@ -0,0 +1,307 @@
+//! # Algorithm policy and self-tests
+//!
+//! Deployments with compliance requirements can disable algorithms at runtime
+//! with [`disable`], for example to forbid Salsa20-based constructions. The
+//! policy is process-wide, and is enforced by the high-level APIs which use
+//! each algorithm, which return an error when it's disabled:
+//!
+//! * XSalsa20-Poly1305 ([`Algorithms::XSALSA20POLY1305`]), used by
+//!   [`DryocBox`](crate::dryocbox::DryocBox) and
+//!   [`DryocSecretBox`](crate::dryocsecretbox::DryocSecretBox)
+//! * XChaCha20-Poly1305 ([`Algorithms::XCHACHA20POLY1305`]), used when pushing
+//!   to and pulling from a [`DryocStream`](crate::dryocstream::DryocStream)
+//! * X25519 ([`Algorithms::X25519`]), used by
+//!   [`DryocBox`](crate::dryocbox::DryocBox) and [`kx`](crate::kx) sessions
+//! * Ed25519 ([`Algorithms::ED25519`]), used when signing and verifying with
+//!   [`sign`](crate::sign)
+//! * Argon2 ([`Algorithms::ARGON2`]), used by [`PwHash`](crate::pwhash::PwHash)
+//!   and keypairs derived from passphrases
+//!
+//! [`DryocSecretBox::encrypt`](crate::dryocsecretbox::DryocSecretBox::encrypt)
+//! and [`SecretBoxCipher::new`](crate::dryocsecretbox::SecretBoxCipher::new)
+//! can't return an error, and panic instead. Use
+//! [`DryocSecretBox::try_encrypt`](crate::dryocsecretbox::DryocSecretBox::try_encrypt)
+//! and [`SecretBoxCipher::try_new`](crate::dryocsecretbox::SecretBoxCipher::try_new)
+//! where the policy may disable XSalsa20-Poly1305. The low-level
+//! [`classic`](crate::classic) functions aren't affected by the policy.
+//!
+//! [`self_test`] runs known-answer tests of each enabled algorithm, similar to
+//! the power-on self-tests of a FIPS 140 module, and should be called once at
+//! startup, after the policy has been set. Algorithms which fail their
+//! self-test are disabled.
+//!
+//! ## Example
+//!
+//! ```
+//! use dryoc::dryocbox::DryocBox;
+//! use dryoc::dryocstream::{DryocStream, Header, Key, Tag};
+//! use dryoc::keypair::StackKeyPair;
+//! use dryoc::policy::{self, Algorithms};
+//! use dryoc::types::*;
+//!
+//! // Forbid Salsa20-based constructions, and test everything else
+//! policy::disable(Algorithms::XSALSA20POLY1305);
+//! policy::self_test().expect("self-test failed");
+//!
+//! let keypair = StackKeyPair::gen();
+//! assert!(DryocBox::seal_to_vecbox(b"hello", &keypair.public_key).is_err());
+//!
+//! // Secret streams use XChaCha20, which is still allowed
+//! let (mut stream, _header): (_, Header) = DryocStream::init_push(&Key::gen());
+//! assert!(stream.push_to_vec(b"hello", None, Tag::FINAL).is_ok());
+//! ```
+
+use std::sync::atomic::{AtomicU32, Ordering};
+
+use bitflags::bitflags;
+
+use crate::error::Error;
+
+bitflags! {
+    /// A set of algorithms which can be disabled by policy.
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+    pub struct Algorithms: u32 {
+        /// The XSalsa20 stream cipher with Poly1305, used by boxes and secret
+        /// boxes.
+        const XSALSA20POLY1305 = 1 << 0;
+        /// The XChaCha20 stream cipher with Poly1305, used by secret streams.
+        const XCHACHA20POLY1305 = 1 << 1;
+        /// X25519 key agreement, used by boxes and key exchange.
+        const X25519 = 1 << 2;
+        /// Ed25519 signatures.
+        const ED25519 = 1 << 3;
+        /// The Argon2i and Argon2id password hashing functions.
+        const ARGON2 = 1 << 4;
+    }
+}
+
+static DISABLED: AtomicU32 = AtomicU32::new(0);
+
+fn names(algorithms: Algorithms) -> String {
+    algorithms
+        .iter_names()
+        .map(|(name, _)| name)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Disables `algorithms` for the rest of the process, or until they're enabled
+/// again with [`enable`].
+pub fn disable(algorithms: Algorithms) {
+    DISABLED.fetch_or(algorithms.bits(), Ordering::SeqCst);
+}
+
+/// Enables `algorithms`, which were previously disabled with [`disable`], or
+/// by a failed [`self_test`].
+pub fn enable(algorithms: Algorithms) {
+    DISABLED.fetch_and(!algorithms.bits(), Ordering::SeqCst);
+}
+
+/// Returns the currently disabled algorithms.
+pub fn disabled() -> Algorithms {
+    Algorithms::from_bits_truncate(DISABLED.load(Ordering::SeqCst))
+}
+
+/// Returns `true` if all of `algorithms` are enabled.
+pub fn is_enabled(algorithms: Algorithms) -> bool {
+    !disabled().intersects(algorithms)
+}
+
+/// Returns an error if any of `algorithms` are disabled.
+pub(crate) fn require(algorithms: Algorithms) -> Result<(), Error> {
+    check(disabled(), algorithms)
+}
+
+fn check(disabled: Algorithms, algorithms: Algorithms) -> Result<(), Error> {
+    let disabled = disabled & algorithms;
+    if disabled.is_empty() {
+        Ok(())
+    } else {
+        Err(dryoc_error!(format!(
+            "{} disabled by policy",
+            names(disabled)
+        )))
+    }
+}
+
+const KEY: [u8; 32] = [0x42; 32];
+const NONCE: [u8; 24] = [0x24; 24];
+const MESSAGE: &[u8] = b"dryoc self-test";
+
+fn known_answer(name: &str, actual: &[u8], expected: &str) -> Result<(), Error> {
+    let mut expected_bytes = vec![0u8; expected.len() / 2];
+    crate::utils::hex_decode_into(&mut expected_bytes, expected)?;
+    if actual == expected_bytes.as_slice() {
+        Ok(())
+    } else {
+        Err(dryoc_error!(format!("{} known-answer test failed", name)))
+    }
+}
+
+fn test_xsalsa20poly1305() -> Result<(), Error> {
+    use crate::classic::crypto_secretbox::{crypto_secretbox_easy, crypto_secretbox_open_easy};
+
+    let mut ciphertext = [0u8; MESSAGE.len() + 16];
+    crypto_secretbox_easy(&mut ciphertext, MESSAGE, &NONCE, &KEY)?;
+    known_answer(
+        "XSalsa20-Poly1305",
+        &ciphertext,
+        "d53dbbedf2fdcce1e0296602d3eb983c369fac4fa2b7db74d96921f0c2d0d2",
+    )?;
+    let mut message = [0u8; MESSAGE.len()];
+    crypto_secretbox_open_easy(&mut message, &ciphertext, &NONCE, &KEY)?;
+    known_answer(
+        "XSalsa20-Poly1305",
+        &message,
+        "6472796f632073656c662d74657374",
+    )
+}
+
+fn test_xchacha20poly1305() -> Result<(), Error> {
+    use crate::aead::{xchacha20poly1305_ietf_decrypt, xchacha20poly1305_ietf_encrypt};
+    use crate::dryocstream::{DryocStream, Header, Tag};
+
+    let ciphertext = xchacha20poly1305_ietf_encrypt(MESSAGE, b"dryoc", &NONCE, &KEY);
+    known_answer(
+        "XChaCha20-Poly1305",
+        &ciphertext,
+        "c12cae0a0d043fc3ce49124e6bfb79482697425979ec618acbd0377bcd8289",
+    )?;
+    let message = xchacha20poly1305_ietf_decrypt(&ciphertext, b"dryoc", &NONCE, &KEY)?;
+    known_answer(
+        "XChaCha20-Poly1305",
+        &message,
+        "6472796f632073656c662d74657374",
+    )?;
+
+    // secret streams use random headers, so check that a stream round trips
+    let (mut push, header): (_, Header) = DryocStream::init_push(&KEY);
+    let ciphertext = push.push_to_vec(&MESSAGE, None, Tag::FINAL)?;
+    let (message, tag) = DryocStream::init_pull(&KEY, &header).pull_to_vec(&ciphertext, None)?;
+    if message != MESSAGE || tag != Tag::FINAL {
+        return Err(dryoc_error!("XChaCha20-Poly1305 stream test failed"));
+    }
+    Ok(())
+}
+
+fn test_x25519() -> Result<(), Error> {
+    use crate::classic::crypto_core::crypto_scalarmult_base;
+
+    let mut public_key = [0u8; 32];
+    crypto_scalarmult_base(&mut public_key, &KEY);
+    known_answer(
+        "X25519",
+        &public_key,
+        "132c442be010fbd57e72603328aa76e71fccc1503aae219327d14d9c9993f472",
+    )
+}
+
+fn test_ed25519() -> Result<(), Error> {
+    use crate::classic::crypto_sign::{
+        crypto_sign_detached, crypto_sign_seed_keypair, crypto_sign_verify_detached,
+    };
+
+    let (public_key, secret_key) = crypto_sign_seed_keypair(&KEY);
+    let mut signature = [0u8; 64];
+    crypto_sign_detached(&mut signature, MESSAGE, &secret_key)?;
+    known_answer(
+        "Ed25519",
+        &signature,
+        "5f9441d3eaa4f8cac13cd2c075474ef0531c863f02f5064c0dd61a2ab8bb4696\
+         782587848e4d3741fa4187194b5492cb9d90242835908403bb5e18f0ee94f90d",
+    )?;
+    crypto_sign_verify_detached(&signature, MESSAGE, &public_key)
+}
+
+fn test_argon2() -> Result<(), Error> {
+    use crate::classic::crypto_pwhash::{crypto_pwhash, PasswordHashAlgorithm};
+
+    let salt = &NONCE[..16];
+    let mut hash = [0u8; 32];
+    crypto_pwhash(
+        &mut hash,
+        b"password",
+        salt,
+        1,
+        8192,
+        PasswordHashAlgorithm::Argon2id13,
+    )?;
+    known_answer(
+        "Argon2id",
+        &hash,
+        "7f955d9b7eeea362b76e40037b95e6e39d119c586698927105ac5e7c27136377",
+    )?;
+    crypto_pwhash(
+        &mut hash,
+        b"password",
+        salt,
+        3,
+        8192,
+        PasswordHashAlgorithm::Argon2i13,
+    )?;
+    known_answer(
+        "Argon2i",
+        &hash,
+        "817c5a8942518cf5d7d11f281ba7bcb2fbf5abddcf7418e0dbd421a79f80f096",
+    )
+}
+
+type SelfTest = fn() -> Result<(), Error>;
+
+/// Runs known-answer tests of every enabled algorithm. Any algorithm which
+/// fails its test is disabled, and an error describing the failures is
+/// returned.
+pub fn self_test() -> Result<(), Error> {
+    let tests: [(Algorithms, SelfTest); 5] = [
+        (Algorithms::XSALSA20POLY1305, test_xsalsa20poly1305),
+        (Algorithms::XCHACHA20POLY1305, test_xchacha20poly1305),
+        (Algorithms::X25519, test_x25519),
+        (Algorithms::ED25519, test_ed25519),
+        (Algorithms::ARGON2, test_argon2),
+    ];
+
+    let mut failures = Vec::new();
+    for (algorithm, test) in tests {
+        if !is_enabled(algorithm) {
+            continue;
+        }
+        if let Err(err) = test() {
+            disable(algorithm);
+            failures.push(err.to_string());
+        }
+    }
+
+    if failures.is_empty() {
+        Ok(())
+    } else {
+        Err(dryoc_error!(format!(
+            "self-test failed: {}",
+            failures.join("; ")
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_policy() {
+        // the policy is process-wide, so only test without changing it
+        self_test().expect("self-test failed");
+
+        let disabled = Algorithms::ARGON2 | Algorithms::ED25519;
+        assert!(check(disabled, Algorithms::X25519).is_ok());
+        let err = check(disabled, Algorithms::X25519 | Algorithms::ARGON2)
+            .expect_err("should be disabled");
+        assert!(err.to_string().starts_with("ARGON2 disabled by policy"));
+        let err = check(disabled, Algorithms::all()).expect_err("should be disabled");
+        assert!(
+            err.to_string()
+                .starts_with("ED25519, ARGON2 disabled by policy")
+        );
+
+        assert!(known_answer("test", b"ab", "6162").is_ok());
+        assert!(known_answer("test", b"ab", "6163").is_err());
+    }
+}
@@ -0,0 +1,56 @@
+//! # Prelude
+//!
+//! Re-exports the traits and types which are needed for most uses of this
+//! crate, so that a single `use` is sufficient:
+//!
+//! ```
+//! use dryoc::prelude::*;
+//!
+//! let secret_key = dryocsecretbox::Key::gen();
+//! let nonce = dryocsecretbox::Nonce::gen();
+//! let message = b"hey";
+//!
+//! let dryocsecretbox = DryocSecretBox::encrypt_to_vecbox(message, &nonce, &secret_key);
+//! let decrypted = dryocsecretbox
+//!     .decrypt_to_vec(&nonce, &secret_key)
+//!     .expect("unable to decrypt");
+//!
+//! assert_eq!(message, decrypted.as_slice());
+//! ```
+//!
+//! Key, nonce, and other type aliases have the same names across modules (such
+//! as [`dryocbox::Nonce`] and [`dryocsecretbox::Nonce`]), so they're not
+//! re-exported directly. Instead, the modules themselves are re-exported, and
+//! the aliases can be referred to by module.
+//!
+//! With the `nightly` feature enabled, the [protected](crate::protected)
+//! memory traits and type aliases are also re-exported.
+
+pub use crate::auth::Auth;
+pub use crate::dryocbox::DryocBox;
+pub use crate::dryocsecretbox::DryocSecretBox;
+pub use crate::dryocstream::{DryocStream, Tag};
+pub use crate::error::Error;
+pub use crate::generichash::GenericHash;
+pub use crate::kdf::{Kdf, StackKdf};
+pub use crate::keyedhash::{KeyedHash, StackKeyedHash};
+pub use crate::keypair::{KeyPair, StackKeyPair};
+pub use crate::kx::{Session, StackSession};
+pub use crate::onetimeauth::OnetimeAuth;
+#[cfg(any(feature = "nightly", all(doc, not(doctest))))]
+#[cfg_attr(all(feature = "nightly", doc), doc(cfg(feature = "nightly")))]
+pub use crate::protected::{
+    HeapByteArray, HeapBytes, Lock, Lockable, Locked, LockedBytes, LockedRO, NewLocked,
+    NewLockedFromSlice, NoAccess, ProtectNoAccess, ProtectReadOnly, ProtectReadWrite, Protected,
+    Unlock, Unlocked, UnlockedRO,
+};
+pub use crate::pwhash::{PwHash, VecPwHash};
+pub use crate::sign::{SignedMessage, SigningKeyPair, VecSignedMessage};
+pub use crate::types::{
+    ByteArray, Bytes, MutByteArray, MutBytes, NewByteArray, NewBytes, ResizableBytes,
+    StackByteArray,
+};
+pub use crate::{
+    auth, dryocbox, dryocsecretbox, dryocstream, generichash, kdf, keyedhash, kx, onetimeauth,
+    pwhash, sign,
+};
@@ -0,0 +1,499 @@
+//! # Privacy Pass tokens
+//!
+//! This module implements the privately verifiable issuance protocol from
+//! [RFC 9578](https://www.rfc-editor.org/rfc/rfc9578), section 5, on top of
+//! the ristretto255-SHA512 VOPRF in [`oprf`](crate::oprf). An [`Issuer`]
+//! issues tokens to clients without learning which token it issued to whom,
+//! and can later check that a token it's presented with is one it issued.
+//!
+//! RFC 9578 registers token type `0x0001` for the P-384 VOPRF. The
+//! ristretto255 ciphersuite isn't registered, so this module uses the token
+//! type [`TOKEN_TYPE`], and its tokens only interoperate with other
+//! implementations using the same ciphersuite and token type.
+//!
+//! The flow is:
+//!
+//! 1. The origin sends the client a [`TokenChallenge`].
+//! 2. The client calls [`Client::begin`] with the issuer's public key and the
+//!    challenge, and sends the [`TokenRequest`] to the issuer.
+//! 3. The issuer calls [`Issuer::issue`], and returns the [`TokenResponse`].
+//! 4. The client calls [`Client::finalize`] to get a [`Token`], which it
+//!    presents to the origin.
+//! 5. The origin (or the issuer, on its behalf) calls [`Issuer::redeem`].
+//!
+//! Tokens can be redeemed more than once. Tracking spent tokens, for example
+//! by storing each redeemed token's nonce, is left to the caller.
+//!
+//! ## Example
+//!
+//! ```
+//! use dryoc::privacypass::*;
+//!
+//! let issuer = Issuer::gen();
+//! let challenge =
+//!     TokenChallenge::new("issuer.example", &[], "origin.example").expect("challenge failed");
+//!
+//! let (client, request) = Client::begin(issuer.public_key(), &challenge).expect("begin failed");
+//! let response = issuer.issue(&request).expect("issue failed");
+//! let token = client.finalize(&response).expect("finalize failed");
+//!
+//! issuer.redeem(&token, &challenge).expect("redeem failed");
+//! ```
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use subtle::ConstantTimeEq;
+use zeroize::{Zeroize, ZeroizeOnDrop};
+
+use crate::error::Error;
+use crate::oprf::{
+    Element, Output, Proof, PublicKey, SecretKey, VoprfClient, VoprfServer, OPRF_ELEMENTBYTES,
+    OPRF_OUTPUTBYTES, OPRF_PROOFBYTES, OPRF_PUBLICKEYBYTES, OPRF_SECRETKEYBYTES,
+};
+use crate::rng::copy_randombytes;
+use crate::types::*;
+
+/// Token type used for tokens issued by this module.
+pub const TOKEN_TYPE: u16 = 0x0005;
+/// Length of a token nonce.
+pub const TOKEN_NONCEBYTES: usize = 32;
+/// Length of a token key ID, the SHA-256 hash of the issuer's public key.
+pub const TOKEN_KEYIDBYTES: usize = 32;
+/// Length of a serialized [`TokenRequest`].
+pub const TOKEN_REQUESTBYTES: usize = 3 + OPRF_ELEMENTBYTES;
+/// Length of a serialized [`TokenResponse`].
+pub const TOKEN_RESPONSEBYTES: usize = OPRF_ELEMENTBYTES + OPRF_PROOFBYTES;
+/// Length of a serialized [`Token`].
+pub const TOKENBYTES: usize = 2 + TOKEN_NONCEBYTES + 32 + TOKEN_KEYIDBYTES + OPRF_OUTPUTBYTES;
+
+const REDEMPTION_CONTEXT_MAX: usize = 32;
+
+fn token_key_id<PK: ByteArray<OPRF_PUBLICKEYBYTES>>(public_key: &PK) -> [u8; TOKEN_KEYIDBYTES] {
+    Sha256::digest(public_key.as_slice()).into()
+}
+
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize, Debug, Clone))]
+#[cfg_attr(not(feature = "serde"), derive(Debug, Clone))]
+/// Challenge sent by an origin to a client, which binds a token to an issuer
+/// and, optionally, to a redemption context and a set of origins.
+pub struct TokenChallenge {
+    issuer_name: String,
+    redemption_context: Vec<u8>,
+    origin_info: String,
+}
+
+impl TokenChallenge {
+    /// Returns a new challenge. `redemption_context` must be empty or 32
+    /// bytes, and `origin_info` may be empty, or a comma-separated list of
+    /// origin names.
+    pub fn new(
+        issuer_name: &str,
+        redemption_context: &[u8],
+        origin_info: &str,
+    ) -> Result<Self, Error> {
+        if issuer_name.is_empty() || issuer_name.len() > u16::MAX as usize {
+            return Err(dryoc_error!("invalid issuer name length"));
+        }
+        if !(redemption_context.is_empty() || redemption_context.len() == REDEMPTION_CONTEXT_MAX) {
+            return Err(dryoc_error!(format!(
+                "redemption context must be empty or {} bytes",
+                REDEMPTION_CONTEXT_MAX
+            )));
+        }
+        if origin_info.len() > u16::MAX as usize {
+            return Err(dryoc_error!("origin info is too long"));
+        }
+        Ok(Self {
+            issuer_name: issuer_name.into(),
+            redemption_context: redemption_context.into(),
+            origin_info: origin_info.into(),
+        })
+    }
+
+    /// Returns the issuer name.
+    pub fn issuer_name(&self) -> &str {
+        &self.issuer_name
+    }
+
+    /// Returns the redemption context.
+    pub fn redemption_context(&self) -> &[u8] {
+        &self.redemption_context
+    }
+
+    /// Returns the origin info.
+    pub fn origin_info(&self) -> &str {
+        &self.origin_info
+    }
+
+    /// Returns the serialized challenge.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut output = Vec::with_capacity(
+            7 + self.issuer_name.len() + self.redemption_context.len() + self.origin_info.len(),
+        );
+        output.extend_from_slice(&TOKEN_TYPE.to_be_bytes());
+        output.extend_from_slice(&(self.issuer_name.len() as u16).to_be_bytes());
+        output.extend_from_slice(self.issuer_name.as_bytes());
+        output.push(self.redemption_context.len() as u8);
+        output.extend_from_slice(&self.redemption_context);
+        output.extend_from_slice(&(self.origin_info.len() as u16).to_be_bytes());
+        output.extend_from_slice(self.origin_info.as_bytes());
+        output
+    }
+
+    /// Decodes a serialized challenge.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        fn take<'a>(bytes: &mut &'a [u8], len: usize) -> Result<&'a [u8], Error> {
+            if bytes.len() < len {
+                return Err(dryoc_error!("token challenge is truncated"));
+            }
+            let (head, tail) = bytes.split_at(len);
+            *bytes = tail;
+            Ok(head)
+        }
+        fn take_u16(bytes: &mut &[u8]) -> Result<usize, Error> {
+            let len = take(bytes, 2)?;
+            Ok(u16::from_be_bytes([len[0], len[1]]) as usize)
+        }
+
+        let mut bytes = bytes;
+        if take_u16(&mut bytes)? != TOKEN_TYPE as usize {
+            return Err(dryoc_error!("unsupported token type"));
+        }
+        let len = take_u16(&mut bytes)?;
+        let issuer_name = take(&mut bytes, len)?;
+        let len = take(&mut bytes, 1)?[0] as usize;
+        let redemption_context = take(&mut bytes, len)?;
+        let len = take_u16(&mut bytes)?;
+        let origin_info = take(&mut bytes, len)?;
+        if !bytes.is_empty() {
+            return Err(dryoc_error!("trailing data after token challenge"));
+        }
+
+        Self::new(
+            std::str::from_utf8(issuer_name)
+                .map_err(|_| dryoc_error!("issuer name is not valid UTF-8"))?,
+            redemption_context,
+            std::str::from_utf8(origin_info)
+                .map_err(|_| dryoc_error!("origin info is not valid UTF-8"))?,
+        )
+    }
+
+    /// Returns the SHA-256 digest of the serialized challenge, which is
+    /// included in tokens issued for this challenge.
+    pub fn digest(&self) -> [u8; 32] {
+        Sha256::digest(self.to_bytes()).into()
+    }
+}
+
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize, Debug, Clone))]
+#[cfg_attr(not(feature = "serde"), derive(Debug, Clone))]
+/// Token request, sent by a client to an issuer.
+pub struct TokenRequest {
+    /// Last byte of the issuer's token key ID
+    pub truncated_token_key_id: u8,
+    /// Blinded token input
+    pub blinded_msg: Element,
+}
+
+impl TokenRequest {
+    /// Returns the serialized request.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut output = Vec::with_capacity(TOKEN_REQUESTBYTES);
+        output.extend_from_slice(&TOKEN_TYPE.to_be_bytes());
+        output.push(self.truncated_token_key_id);
+        output.extend_from_slice(self.blinded_msg.as_slice());
+        output
+    }
+
+    /// Decodes a serialized request.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        if bytes.len() != TOKEN_REQUESTBYTES {
+            return Err(dryoc_error!("invalid token request length"));
+        }
+        if bytes[..2] != TOKEN_TYPE.to_be_bytes() {
+            return Err(dryoc_error!("unsupported token type"));
+        }
+        Ok(Self {
+            truncated_token_key_id: bytes[2],
+            blinded_msg: Element::try_from(&bytes[3..])?,
+        })
+    }
+}
+
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize, Debug, Clone))]
+#[cfg_attr(not(feature = "serde"), derive(Debug, Clone))]
+/// Token response, sent by an issuer to a client.
+pub struct TokenResponse {
+    /// Evaluated element
+    pub evaluate_msg: Element,
+    /// Proof that the element was evaluated with the issuer's key
+    pub evaluate_proof: Proof,
+}
+
+impl TokenResponse {
+    /// Returns the serialized response.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut output = Vec::with_capacity(TOKEN_RESPONSEBYTES);
+        output.extend_from_slice(self.evaluate_msg.as_slice());
+        output.extend_from_slice(self.evaluate_proof.as_slice());
+        output
+    }
+
+    /// Decodes a serialized response.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        if bytes.len() != TOKEN_RESPONSEBYTES {
+            return Err(dryoc_error!("invalid token response length"));
+        }
+        Ok(Self {
+            evaluate_msg: Element::try_from(&bytes[..OPRF_ELEMENTBYTES])?,
+            evaluate_proof: Proof::try_from(&bytes[OPRF_ELEMENTBYTES..])?,
+        })
+    }
+}
+
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize, Debug, Clone))]
+#[cfg_attr(not(feature = "serde"), derive(Debug, Clone))]
+/// Token, presented by a client to an origin.
+pub struct Token {
+    /// Random nonce chosen by the client, unique to this token
+    pub nonce: [u8; TOKEN_NONCEBYTES],
+    /// Digest of the [`TokenChallenge`] the token was issued for
+    pub challenge_digest: [u8; 32],
+    /// SHA-256 hash of the issuer's public key
+    pub token_key_id: [u8; TOKEN_KEYIDBYTES],
+    /// VOPRF output for the token input
+    pub authenticator: Output,
+}
+
+impl Token {
+    /// Returns the input to the VOPRF for this token, which is the serialized
+    /// token without the authenticator.
+    fn token_input(&self) -> Vec<u8> {
+        let mut output = Vec::with_capacity(TOKENBYTES - OPRF_OUTPUTBYTES);
+        output.extend_from_slice(&TOKEN_TYPE.to_be_bytes());
+        output.extend_from_slice(&self.nonce);
+        output.extend_from_slice(&self.challenge_digest);
+        output.extend_from_slice(&self.token_key_id);
+        output
+    }
+
+    /// Returns the serialized token.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut output = self.token_input();
+        output.extend_from_slice(self.authenticator.as_slice());
+        output
+    }
+
+    /// Decodes a serialized token.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        if bytes.len() != TOKENBYTES {
+            return Err(dryoc_error!("invalid token length"));
+        }
+        if bytes[..2] != TOKEN_TYPE.to_be_bytes() {
+            return Err(dryoc_error!("unsupported token type"));
+        }
+        let mut token = Self {
+            nonce: [0u8; TOKEN_NONCEBYTES],
+            challenge_digest: [0u8; 32],
+            token_key_id: [0u8; TOKEN_KEYIDBYTES],
+            authenticator: Output::try_from(&bytes[TOKENBYTES - OPRF_OUTPUTBYTES..])?,
+        };
+        token.nonce.copy_from_slice(&bytes[2..34]);
+        token.challenge_digest.copy_from_slice(&bytes[34..66]);
+        token.token_key_id.copy_from_slice(&bytes[66..98]);
+        Ok(token)
+    }
+}
+
+#[cfg_attr(
+    feature = "serde",
+    derive(Zeroize, ZeroizeOnDrop, Clone, Serialize, Deserialize)
+)]
+#[cfg_attr(not(feature = "serde"), derive(Zeroize, ZeroizeOnDrop, Clone))]
+/// Token issuer, which holds the VOPRF secret key.
+pub struct Issuer {
+    server: VoprfServer,
+    token_key_id: [u8; TOKEN_KEYIDBYTES],
+}
+
+impl Issuer {
+    fn from_server(server: VoprfServer) -> Self {
+        let token_key_id = token_key_id(server.public_key());
+        Self {
+            server,
+            token_key_id,
+        }
+    }
+
+    /// Generates a new issuer with a random keypair.
+    pub fn gen() -> Self {
+        Self::from_server(VoprfServer::gen())
+    }
+
+    /// Loads an issuer from `secret_key`.
+    pub fn from_secret_key<SK: ByteArray<OPRF_SECRETKEYBYTES>>(
+        secret_key: &SK,
+    ) -> Result<Self, Error> {
+        Ok(Self::from_server(VoprfServer::from_secret_key(secret_key)?))
+    }
+
+    /// Returns the issuer's public key, which clients need to request tokens.
+    pub fn public_key(&self) -> &PublicKey {
+        self.server.public_key()
+    }
+
+    /// Returns the issuer's secret key.
+    pub fn secret_key(&self) -> &SecretKey {
+        self.server.secret_key()
+    }
+
+    /// Returns the issuer's token key ID.
+    pub fn token_key_id(&self) -> &[u8; TOKEN_KEYIDBYTES] {
+        &self.token_key_id
+    }
+
+    /// Issues a token for `request`.
+    ///
+    /// Any access control for issuance, such as rate limiting or attesting the
+    /// client, has to happen before calling this.
+    pub fn issue(&self, request: &TokenRequest) -> Result<TokenResponse, Error> {
+        if request.truncated_token_key_id != self.token_key_id[TOKEN_KEYIDBYTES - 1] {
+            return Err(dryoc_error!("token request is for a different key"));
+        }
+        let (evaluate_msg, evaluate_proof) = self.server.blind_evaluate(&request.blinded_msg)?;
+        Ok(TokenResponse {
+            evaluate_msg,
+            evaluate_proof,
+        })
+    }
+
+    /// Checks that `token` was issued by this issuer for `challenge`.
+    ///
+    /// This doesn't check whether the token was already redeemed.
+    pub fn redeem(&self, token: &Token, challenge: &TokenChallenge) -> Result<(), Error> {
+        if !bool::from(token.token_key_id.ct_eq(&self.token_key_id)) {
+            return Err(dryoc_error!("token was issued with a different key"));
+        }
+        if !bool::from(token.challenge_digest.ct_eq(&challenge.digest())) {
+            return Err(dryoc_error!("token was issued for a different challenge"));
+        }
+        let expected = self.server.evaluate(&token.token_input())?;
+        if bool::from(expected.as_slice().ct_eq(token.authenticator.as_slice())) {
+            Ok(())
+        } else {
+            Err(dryoc_error!("invalid token"))
+        }
+    }
+}
+
+/// Client state for a single token request, between [`Client::begin`] and
+/// [`Client::finalize`].
+pub struct Client {
+    voprf: VoprfClient,
+    public_key: PublicKey,
+    token: Token,
+}
+
+impl Client {
+    /// Starts requesting a token from the issuer with `public_key` for
+    /// `challenge`, returning the client state and the request to send to the
+    /// issuer.
+    pub fn begin<PK: ByteArray<OPRF_PUBLICKEYBYTES>>(
+        public_key: &PK,
+        challenge: &TokenChallenge,
+    ) -> Result<(Self, TokenRequest), Error> {
+        let mut token = Token {
+            nonce: [0u8; TOKEN_NONCEBYTES],
+            challenge_digest: challenge.digest(),
+            token_key_id: token_key_id(public_key),
+            authenticator: Output::default(),
+        };
+        copy_randombytes(&mut token.nonce);
+
+        let (voprf, blinded_msg) = VoprfClient::blind(&token.token_input())?;
+        let request = TokenRequest {
+            truncated_token_key_id: token.token_key_id[TOKEN_KEYIDBYTES - 1],
+            blinded_msg,
+        };
+        Ok((
+            Self {
+                voprf,
+                public_key: PublicKey::from(*public_key.as_array()),
+                token,
+            },
+            request,
+        ))
+    }
+
+    /// Verifies the issuer's `response`, and returns the finished token.
+    pub fn finalize(self, response: &TokenResponse) -> Result<Token, Error> {
+        let Self {
+            voprf,
+            public_key,
+            mut token,
+        } = self;
+        token.authenticator = voprf.finalize(
+            &token.token_input(),
+            &response.evaluate_msg,
+            &response.evaluate_proof,
+            &public_key,
+        )?;
+        Ok(token)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_issue_and_redeem() {
+        let issuer = Issuer::gen();
+        let challenge = TokenChallenge::new("issuer.example", &[7u8; 32], "a.example,b.example")
+            .expect("challenge");
+        let challenge =
+            TokenChallenge::from_bytes(&challenge.to_bytes()).expect("decode challenge");
+
+        let (client, request) = Client::begin(issuer.public_key(), &challenge).expect("begin");
+        let request = TokenRequest::from_bytes(&request.to_bytes()).expect("decode request");
+        let response = issuer.issue(&request).expect("issue");
+        let response = TokenResponse::from_bytes(&response.to_bytes()).expect("decode response");
+        let token = client.finalize(&response).expect("finalize");
+        let token = Token::from_bytes(&token.to_bytes()).expect("decode token");
+
+        issuer.redeem(&token, &challenge).expect("redeem");
+
+        let other_challenge =
+            TokenChallenge::new("issuer.example", &[], "a.example").expect("challenge");
+        issuer
+            .redeem(&token, &other_challenge)
+            .expect_err("wrong challenge");
+        Issuer::gen()
+            .redeem(&token, &challenge)
+            .expect_err("wrong issuer");
+
+        let mut forged = token.clone();
+        forged.nonce[0] ^= 1;
+        issuer
+            .redeem(&forged, &challenge)
+            .expect_err("forged token");
+    }
+
+    #[test]
+    fn test_wrong_issuer_key() {
+        let issuer = Issuer::gen();
+        let other = Issuer::gen();
+        let challenge = TokenChallenge::new("issuer.example", &[], "").expect("challenge");
+
+        let (client, mut request) = Client::begin(other.public_key(), &challenge).expect("begin");
+        // force the key ID check to pass, the proof must still fail
+        request.truncated_token_key_id = issuer.token_key_id()[TOKEN_KEYIDBYTES - 1];
+        let response = issuer.issue(&request).expect("issue");
+        client
+            .finalize(&response)
+            .expect_err("proof from wrong key");
+
+        TokenChallenge::new("", &[], "").expect_err("empty issuer name");
+        TokenChallenge::new("issuer.example", &[0u8; 16], "").expect_err("bad context");
+    }
+}
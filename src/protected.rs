@@ -5,7 +5,11 @@
 //! Windows. Similar to libsodium's `sodium_mlock` and `sodium_mprotect_*`
 //! functions.
 //!
-//! On Linux, sets `MADV_DONTDUMP` with `madvise()` on locked regions.
+//! On Linux, sets `MADV_DONTDUMP` with `madvise()` on locked regions, and
+//! allocations can optionally be backed by `memfd_secret()`, which removes them
+//! from the kernel's direct map. Refer to [`set_allocator_backend()`]. The
+//! `madvise()` advice given for every allocation, such as `MADV_WIPEONFORK`,
+//! can be configured with [`set_allocation_policy()`].
 //!
 //! The protected memory features leverage Rust's [`Allocator`] API, which
 //! requires nightly Rust. This crate must be built with the `nightly` feature
@@ -68,13 +72,79 @@
 //!
 //! Running the code above produces as `signal: 10, SIGBUS: access to undefined
 //! memory` panic.
+//!
+//! Memory that's no-access can be accessed temporarily, without consuming it,
+//! with [`Protected::with_readonly`] and [`Protected::with_readwrite`]. These
+//! restore no-access protection when the closure returns, or if it panics.
+//!
+//! ## Capabilities
+//!
+//! Locking memory may fail when the locked memory limit is too low, which is
+//! common in containers and under service managers. Use [`capabilities()`] to
+//! check at startup whether locking will likely succeed, along with advice on
+//! how to fix it when it won't. Alternatively, set a [`LockPolicy`] with
+//! [`set_lock_policy()`] to continue without locking when it fails, and use
+//! [`lockable_memory_remaining()`] to monitor how much more memory can be
+//! locked.
+//!
+//! ## Thread safety
+//!
+//! [`Protected`], [`ProtectedBox`], [`HeapBytes`], and [`HeapByteArray`] are
+//! [`Send`] and [`Sync`] whenever their contents are. Changing a region's
+//! protection consumes it, or requires `&mut` access, so a region can never be
+//! made inaccessible while another thread holds a reference to it.
+//!
+//! To share one region between threads without copying it, such as a locked
+//! precalculated key used by every worker of a server, use
+//! [`SharedProtected`], which keeps the region read-only, and makes it
+//! writable only while a writer holds its lock.
+//!
+//! ```
+//! use dryoc::protected::*;
+//!
+//! let key = HeapBytes::from_slice_into_locked(b"shared secret").expect("lock failed");
+//! let shared = SharedProtected::new(key).expect("mprotect failed");
+//!
+//! let workers: Vec<_> = (0..4)
+//!     .map(|_| {
+//!         let shared = shared.clone();
+//!         std::thread::spawn(move || shared.read().expect("read failed").as_slice().len())
+//!     })
+//!     .collect();
+//! for worker in workers {
+//!     assert_eq!(worker.join().expect("worker failed"), 13);
+//! }
+//!
+//! shared
+//!     .with_readwrite(|key| key[0] = b'S')
+//!     .expect("write failed");
+//! assert_eq!(
+//!     shared.read().expect("read failed").as_slice(),
+//!     b"Shared secret"
+//! );
+//! ```
+//!
+//! ## Serialization
+//!
+//! Locked memory is [redacted](crate::redact) when serialized with a
+//! human-readable format. To persist locked secrets, such as keys, enable the
+//! `serde` feature, and serialize them with [`SealedSerialize::sealed`], which
+//! encrypts them with a key you provide. They're decrypted directly into
+//! locked memory when deserialized with an [`Unseal`] seed, so the plaintext
+//! is never copied into unprotected memory.
 use std::alloc::{AllocError, Allocator, Layout};
 use std::marker::PhantomData;
 use std::ptr;
+use std::sync::atomic::{AtomicU8, AtomicUsize, Ordering};
+use std::sync::{Arc, RwLock, RwLockReadGuard};
 
 use lazy_static::lazy_static;
 use zeroize::{Zeroize, ZeroizeOnDrop};
 
+#[cfg(feature = "serde")]
+use crate::constants::{
+    CRYPTO_SECRETBOX_KEYBYTES, CRYPTO_SECRETBOX_MACBYTES, CRYPTO_SECRETBOX_NONCEBYTES,
+};
 use crate::error;
 use crate::rng::copy_randombytes;
 pub use crate::types::*;
@@ -127,7 +197,17 @@ pub trait Lockable<A: Zeroize + Bytes> {
     /// Windows. By default, the protect mode is set to ReadWrite (i.e., no
     /// exec) using `mprotect()` on UNIX, or `VirtualProtect()` on Windows.
     /// On Linux, it will also set `MADV_DONTDUMP` using `madvise()`.
-    fn mlock(self) -> Result<Protected<A, traits::ReadWrite, traits::Locked>, std::io::Error>;
+    ///
+    /// What happens when locking fails is determined by the global
+    /// [`LockPolicy`].
+    fn mlock(self) -> Result<Protected<A, traits::ReadWrite, traits::Locked>, crate::error::Error>;
+
+    /// Same as [`Lockable::mlock`], except locking is done according to
+    /// `policy`, rather than the global [`LockPolicy`].
+    fn mlock_with_policy(
+        self,
+        policy: LockPolicy,
+    ) -> Result<Protected<A, traits::ReadWrite, traits::Locked>, crate::error::Error>;
 }
 
 /// Protected region of memory that can be locked.
@@ -136,28 +216,39 @@ pub trait Lock<A: Zeroize + Bytes, PM: traits::ProtectMode> {
     /// Windows. By default, the protect mode is set to ReadWrite (i.e., no
     /// exec) using `mprotect()` on UNIX, or `VirtualProtect()` on Windows.
     /// On Linux, it will also set `MADV_DONTDUMP` using `madvise()`.
-    fn mlock(self) -> Result<Protected<A, PM, traits::Locked>, std::io::Error>;
+    ///
+    /// What happens when locking fails is determined by the global
+    /// [`LockPolicy`].
+    fn mlock(self) -> Result<Protected<A, PM, traits::Locked>, crate::error::Error>;
+
+    /// Same as [`Lock::mlock`], except locking is done according to `policy`,
+    /// rather than the global [`LockPolicy`].
+    fn mlock_with_policy(
+        self,
+        policy: LockPolicy,
+    ) -> Result<Protected<A, PM, traits::Locked>, crate::error::Error>;
 }
 
 /// Protected region of memory that can be locked (i.e., is already locked).
 pub trait Unlock<A: Zeroize + Bytes, PM: traits::ProtectMode> {
     /// Unlocks a region of memory, using `munlock()` on UNIX, or
     /// `VirtualLock()` on Windows.
-    fn munlock(self) -> Result<Protected<A, PM, traits::Unlocked>, std::io::Error>;
+    fn munlock(self) -> Result<Protected<A, PM, traits::Unlocked>, crate::error::Error>;
 }
 
 /// Protected region of memory that can be set as read-only.
 pub trait ProtectReadOnly<A: Zeroize + Bytes, PM: traits::ProtectMode, LM: traits::LockMode> {
     /// Protects a region of memory as read-only (and no exec), using
     /// `mprotect()` on UNIX, or `VirtualProtect()` on Windows.
-    fn mprotect_readonly(self) -> Result<Protected<A, traits::ReadOnly, LM>, std::io::Error>;
+    fn mprotect_readonly(self) -> Result<Protected<A, traits::ReadOnly, LM>, crate::error::Error>;
 }
 
 /// Protected region of memory that can be set as read-write.
 pub trait ProtectReadWrite<A: Zeroize + Bytes, PM: traits::ProtectMode, LM: traits::LockMode> {
     /// Protects a region of memory as read-write (and no exec), using
     /// `mprotect()` on UNIX, or `VirtualProtect()` on Windows.
-    fn mprotect_readwrite(self) -> Result<Protected<A, traits::ReadWrite, LM>, std::io::Error>;
+    fn mprotect_readwrite(self)
+    -> Result<Protected<A, traits::ReadWrite, LM>, crate::error::Error>;
 }
 
 /// Protected region of memory that can be set as no-access. Must be unlocked.
@@ -166,21 +257,21 @@ pub trait ProtectNoAccess<A: Zeroize + Bytes, PM: traits::ProtectMode> {
     /// `mprotect()` on UNIX, or `VirtualProtect()` on Windows.
     fn mprotect_noaccess(
         self,
-    ) -> Result<Protected<A, traits::NoAccess, traits::Unlocked>, std::io::Error>;
+    ) -> Result<Protected<A, traits::NoAccess, traits::Unlocked>, crate::error::Error>;
 }
 
 /// Bytes which can be allocated and protected.
 pub trait NewLocked<A: Zeroize + NewBytes + Lockable<A>> {
     /// Returns a new locked byte array.
-    fn new_locked() -> Result<Protected<A, traits::ReadWrite, traits::Locked>, std::io::Error>;
+    fn new_locked() -> Result<Protected<A, traits::ReadWrite, traits::Locked>, crate::error::Error>;
     /// Returns a new locked byte array.
     fn new_readonly_locked()
-    -> Result<Protected<A, traits::ReadOnly, traits::Locked>, std::io::Error>;
+    -> Result<Protected<A, traits::ReadOnly, traits::Locked>, crate::error::Error>;
     /// Returns a new locked byte array, filled with random data.
-    fn gen_locked() -> Result<Protected<A, traits::ReadWrite, traits::Locked>, std::io::Error>;
+    fn gen_locked() -> Result<Protected<A, traits::ReadWrite, traits::Locked>, crate::error::Error>;
     /// Returns a new read-only, locked byte array, filled with random data.
     fn gen_readonly_locked()
-    -> Result<Protected<A, traits::ReadOnly, traits::Locked>, std::io::Error>;
+    -> Result<Protected<A, traits::ReadOnly, traits::Locked>, crate::error::Error>;
 }
 
 /// Create a new region of protected memory from a slice.
@@ -217,6 +308,19 @@ pub mod ptypes {
     pub type UnlockedRO<T> = super::Protected<T, super::traits::ReadOnly, super::traits::Unlocked>;
     /// Locked, read-write, page-aligned bytes type alias
     pub type LockedBytes = Locked<super::HeapBytes>;
+    /// Locked, read-write, protected box type alias
+    pub type LockedBox<T> = super::ProtectedBox<T, super::traits::ReadWrite, super::traits::Locked>;
+    /// Locked, read-only, protected box type alias
+    pub type LockedROBox<T> =
+        super::ProtectedBox<T, super::traits::ReadOnly, super::traits::Locked>;
+    /// Unlocked, no-access, protected box type alias
+    pub type NoAccessBox<T> =
+        super::ProtectedBox<T, super::traits::NoAccess, super::traits::Unlocked>;
+    /// Unlocked, read-write, protected box type alias
+    pub type UnlockedBox<T> =
+        super::ProtectedBox<T, super::traits::ReadWrite, super::traits::Unlocked>;
+    /// Locked, shared, read-only memory region type alias
+    pub type SharedLocked<T> = super::SharedProtected<T, super::traits::Locked>;
 }
 
 impl<T: Zeroize + NewBytes + ResizableBytes + Lockable<T> + NewLocked<T>> Clone for Locked<T> {
@@ -255,11 +359,58 @@ impl<T: Zeroize + NewBytes + Clone> Clone for UnlockedRO<T> {
 
 pub use ptypes::*;
 
+/// Determines what happens when locking memory fails, such as when the
+/// `RLIMIT_MEMLOCK` limit is reached. Set globally with [`set_lock_policy()`],
+/// or per allocation with [`Lockable::mlock_with_policy`] and
+/// [`Lock::mlock_with_policy`].
+///
+/// Regions which couldn't be locked are still returned as
+/// [`Locked`](ptypes::Locked), and are still protected, wiped when dropped,
+/// and excluded from core dumps, but may be swapped to disk. Use
+/// [`Protected::is_locked`] to check whether a region is actually locked, and
+/// [`lockable_memory_remaining()`] to check how much more memory can be
+/// locked.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LockPolicy {
+    /// Return an error when locking fails.
+    #[default]
+    Require,
+    /// Try to lock memory, but continue without locking if it fails. Intended
+    /// for long-running services, which should degrade gracefully rather than
+    /// fail.
+    BestEffort,
+    /// Don't lock memory at all.
+    Disabled,
+}
+
+static LOCK_POLICY: AtomicU8 = AtomicU8::new(LockPolicy::Require as u8);
+
+/// Sets the global [`LockPolicy`], used when locking memory without an
+/// explicit policy, such as with [`NewLocked::new_locked`] or
+/// [`Lockable::mlock`].
+pub fn set_lock_policy(policy: LockPolicy) {
+    LOCK_POLICY.store(policy as u8, Ordering::SeqCst);
+}
+
+/// Returns the global [`LockPolicy`], as set by [`set_lock_policy()`].
+pub fn lock_policy() -> LockPolicy {
+    match LOCK_POLICY.load(Ordering::SeqCst) {
+        p if p == LockPolicy::BestEffort as u8 => LockPolicy::BestEffort,
+        p if p == LockPolicy::Disabled as u8 => LockPolicy::Disabled,
+        _ => LockPolicy::Require,
+    }
+}
+
 fn dryoc_mlock(data: &[u8]) -> Result<(), std::io::Error> {
     if data.is_empty() {
         // no-op
         return Ok(());
     }
+    #[cfg(target_os = "linux")]
+    if secretmem::contains(data) {
+        // secret memory is never swapped, and can't be locked
+        return Ok(());
+    }
     #[cfg(unix)]
     {
         #[cfg(target_os = "linux")]
@@ -274,7 +425,10 @@ fn dryoc_mlock(data: &[u8]) -> Result<(), std::io::Error> {
         use libc::{c_void, mlock as c_mlock};
         let ret = unsafe { c_mlock(data.as_ptr() as *const c_void, data.len()) };
         match ret {
-            0 => Ok(()),
+            0 => {
+                crate::fork_safety::regions::insert(data);
+                Ok(())
+            }
             _ => Err(std::io::Error::last_os_error()),
         }
     }
@@ -296,10 +450,14 @@ fn dryoc_munlock(data: &[u8]) -> Result<(), std::io::Error> {
         // no-op
         return Ok(());
     }
+    #[cfg(target_os = "linux")]
+    if secretmem::contains(data) {
+        return Ok(());
+    }
     #[cfg(unix)]
     {
         #[cfg(target_os = "linux")]
-        {
+        if !allocation_policy().dont_dump {
             // undo MADV_DONTDUMP
             use libc::{madvise, MADV_DODUMP};
             unsafe {
@@ -307,6 +465,8 @@ fn dryoc_munlock(data: &[u8]) -> Result<(), std::io::Error> {
             }
         }
 
+        crate::fork_safety::regions::remove(data);
+
         use libc::{c_void, munlock as c_munlock};
         let ret = unsafe { c_munlock(data.as_ptr() as *const c_void, data.len()) };
         match ret {
@@ -465,12 +625,20 @@ impl<A: Zeroize + Bytes, PM: traits::ProtectMode, LM: traits::LockMode> Protecte
         }
     }
 
+    /// Returns `true` if this region is actually locked in memory. Regions
+    /// can be [`Locked`](ptypes::Locked) without being locked in memory, if
+    /// locking failed under [`LockPolicy::BestEffort`], or was skipped under
+    /// [`LockPolicy::Disabled`].
+    pub fn is_locked(&self) -> bool {
+        matches!(&self.i, Some(d) if d.lm == int::LockMode::Locked)
+    }
+
     fn swap_some_or_err<F, OPM: traits::ProtectMode, OLM: traits::LockMode>(
         &mut self,
         f: F,
-    ) -> Result<Protected<A, OPM, OLM>, std::io::Error>
+    ) -> Result<Protected<A, OPM, OLM>, crate::error::Error>
     where
-        F: Fn(&mut int::InternalData<A>) -> Result<Protected<A, OPM, OLM>, std::io::Error>,
+        F: Fn(&mut int::InternalData<A>) -> Result<Protected<A, OPM, OLM>, crate::error::Error>,
     {
         match &mut self.i {
             Some(d) => {
@@ -479,10 +647,7 @@ impl<A: Zeroize + Bytes, PM: traits::ProtectMode, LM: traits::LockMode> Protecte
                 std::mem::swap(&mut new.i, &mut self.i);
                 Ok(new)
             }
-            _ => Err(std::io::Error::new(
-                std::io::ErrorKind::InvalidData,
-                "unexpected empty internal struct",
-            )),
+            _ => Err(dryoc_error!("unexpected empty internal struct")),
         }
     }
 }
@@ -490,7 +655,7 @@ impl<A: Zeroize + Bytes, PM: traits::ProtectMode, LM: traits::LockMode> Protecte
 impl<A: Zeroize + Bytes, PM: traits::ProtectMode, LM: traits::LockMode> Unlock<A, PM>
     for Protected<A, PM, LM>
 {
-    fn munlock(mut self) -> Result<Protected<A, PM, traits::Unlocked>, std::io::Error> {
+    fn munlock(mut self) -> Result<Protected<A, PM, traits::Unlocked>, crate::error::Error> {
         self.swap_some_or_err(|old| {
             dryoc_munlock(old.a.as_slice())?;
             // update internal state
@@ -503,11 +668,27 @@ impl<A: Zeroize + Bytes, PM: traits::ProtectMode, LM: traits::LockMode> Unlock<A
 impl<A: Zeroize + Bytes + Default, PM: traits::ProtectMode> Lock<A, PM>
     for Protected<A, PM, traits::Unlocked>
 {
-    fn mlock(mut self) -> Result<Protected<A, PM, traits::Locked>, std::io::Error> {
+    fn mlock(self) -> Result<Protected<A, PM, traits::Locked>, crate::error::Error> {
+        self.mlock_with_policy(lock_policy())
+    }
+
+    fn mlock_with_policy(
+        mut self,
+        policy: LockPolicy,
+    ) -> Result<Protected<A, PM, traits::Locked>, crate::error::Error> {
         self.swap_some_or_err(|old| {
-            dryoc_mlock(old.a.as_slice())?;
-            // update internal state
-            old.lm = int::LockMode::Locked;
+            let locked = match policy {
+                LockPolicy::Require => {
+                    dryoc_mlock(old.a.as_slice())?;
+                    true
+                }
+                LockPolicy::BestEffort => dryoc_mlock(old.a.as_slice()).is_ok(),
+                LockPolicy::Disabled => false,
+            };
+            // update internal state, so that only locked regions are unlocked
+            if locked {
+                old.lm = int::LockMode::Locked;
+            }
             Ok(Protected::<A, PM, traits::Locked>::new())
         })
     }
@@ -516,7 +697,9 @@ impl<A: Zeroize + Bytes + Default, PM: traits::ProtectMode> Lock<A, PM>
 impl<A: Zeroize + Bytes, PM: traits::ProtectMode, LM: traits::LockMode> ProtectReadOnly<A, PM, LM>
     for Protected<A, PM, LM>
 {
-    fn mprotect_readonly(mut self) -> Result<Protected<A, traits::ReadOnly, LM>, std::io::Error> {
+    fn mprotect_readonly(
+        mut self,
+    ) -> Result<Protected<A, traits::ReadOnly, LM>, crate::error::Error> {
         self.swap_some_or_err(|old| {
             dryoc_mprotect_readonly(old.a.as_slice())?;
             // update internal state
@@ -529,7 +712,9 @@ impl<A: Zeroize + Bytes, PM: traits::ProtectMode, LM: traits::LockMode> ProtectR
 impl<A: Zeroize + Bytes, PM: traits::ProtectMode, LM: traits::LockMode> ProtectReadWrite<A, PM, LM>
     for Protected<A, PM, LM>
 {
-    fn mprotect_readwrite(mut self) -> Result<Protected<A, traits::ReadWrite, LM>, std::io::Error> {
+    fn mprotect_readwrite(
+        mut self,
+    ) -> Result<Protected<A, traits::ReadWrite, LM>, crate::error::Error> {
         self.swap_some_or_err(|old| {
             dryoc_mprotect_readwrite(old.a.as_slice())?;
             // update internal state
@@ -544,7 +729,7 @@ impl<A: Zeroize + Bytes, PM: traits::ProtectMode> ProtectNoAccess<A, PM>
 {
     fn mprotect_noaccess(
         mut self,
-    ) -> Result<Protected<A, traits::NoAccess, traits::Unlocked>, std::io::Error> {
+    ) -> Result<Protected<A, traits::NoAccess, traits::Unlocked>, crate::error::Error> {
         self.swap_some_or_err(|old| {
             dryoc_mprotect_noaccess(old.a.as_slice())?;
             // update internal state
@@ -554,6 +739,88 @@ impl<A: Zeroize + Bytes, PM: traits::ProtectMode> ProtectNoAccess<A, PM>
     }
 }
 
+/// Restores a region's protection with `restore` when dropped, so that it's
+/// restored even when unwinding from a panic.
+struct RestoreGuard {
+    ptr: *const u8,
+    len: usize,
+    restore: fn(&[u8]) -> Result<(), std::io::Error>,
+}
+
+impl RestoreGuard {
+    fn new(data: &[u8], restore: fn(&[u8]) -> Result<(), std::io::Error>) -> Self {
+        Self {
+            ptr: data.as_ptr(),
+            len: data.len(),
+            restore,
+        }
+    }
+
+    fn region(&self) -> &[u8] {
+        // the region outlives the guard, which never leaves the scope that
+        // borrows it
+        unsafe { std::slice::from_raw_parts(self.ptr, self.len) }
+    }
+
+    /// Restores protection, returning any error, rather than logging it like
+    /// when dropped.
+    fn restore(self) -> Result<(), std::io::Error> {
+        let result = (self.restore)(self.region());
+        std::mem::forget(self);
+        result
+    }
+}
+
+impl Drop for RestoreGuard {
+    fn drop(&mut self) {
+        (self.restore)(self.region())
+            .map_err(|err| eprintln!("mprotect error on unwind = {:?}", err))
+            .ok();
+    }
+}
+
+impl<A: Zeroize + Bytes> Protected<A, traits::NoAccess, traits::Unlocked> {
+    /// Temporarily protects this region as read-only, and calls `f` with its
+    /// contents, returning the result. No-access protection is restored when
+    /// `f` returns, or if it panics. Takes `&mut self` so that the region
+    /// can't be accessed elsewhere while it's readable.
+    pub fn with_readonly<R, F: FnOnce(&[u8]) -> R>(
+        &mut self,
+        f: F,
+    ) -> Result<R, crate::error::Error> {
+        let d = self
+            .i
+            .as_ref()
+            .ok_or_else(|| dryoc_error!("unexpected empty internal struct"))?;
+        dryoc_mprotect_readonly(d.a.as_slice())?;
+        let guard = RestoreGuard::new(d.a.as_slice(), dryoc_mprotect_noaccess);
+        let result = f(d.a.as_slice());
+        guard.restore()?;
+        Ok(result)
+    }
+
+    /// Temporarily protects this region as read-write, and calls `f` with its
+    /// contents, returning the result. No-access protection is restored when
+    /// `f` returns, or if it panics.
+    pub fn with_readwrite<R, F: FnOnce(&mut [u8]) -> R>(
+        &mut self,
+        f: F,
+    ) -> Result<R, crate::error::Error>
+    where
+        A: MutBytes,
+    {
+        let d = self
+            .i
+            .as_mut()
+            .ok_or_else(|| dryoc_error!("unexpected empty internal struct"))?;
+        dryoc_mprotect_readwrite(d.a.as_slice())?;
+        let guard = RestoreGuard::new(d.a.as_slice(), dryoc_mprotect_noaccess);
+        let result = f(d.a.as_mut_slice());
+        guard.restore()?;
+        Ok(result)
+    }
+}
+
 impl<A: Zeroize + Bytes + AsRef<[u8]>, LM: traits::LockMode> AsRef<[u8]>
     for Protected<A, traits::ReadOnly, LM>
 {
@@ -627,8 +894,10 @@ impl<const LENGTH: usize> StackByteArray<LENGTH> {
     /// wrapper.
     pub fn mlock(
         self,
-    ) -> Result<Protected<HeapByteArray<LENGTH>, traits::ReadWrite, traits::Locked>, std::io::Error>
-    {
+    ) -> Result<
+        Protected<HeapByteArray<LENGTH>, traits::ReadWrite, traits::Locked>,
+        crate::error::Error,
+    > {
         Protected::<HeapByteArray<LENGTH>, traits::ReadWrite, traits::Unlocked>::new_with(
             self.into(),
         )
@@ -640,8 +909,10 @@ impl<const LENGTH: usize> StackByteArray<LENGTH> {
     /// Returns a readonly protected [StackByteArray].
     pub fn mprotect_readonly(
         self,
-    ) -> Result<Protected<HeapByteArray<LENGTH>, traits::ReadOnly, traits::Unlocked>, std::io::Error>
-    {
+    ) -> Result<
+        Protected<HeapByteArray<LENGTH>, traits::ReadOnly, traits::Unlocked>,
+        crate::error::Error,
+    > {
         Protected::<HeapByteArray<LENGTH>, traits::ReadWrite, traits::Unlocked>::new_with(
             self.into(),
         )
@@ -653,26 +924,62 @@ impl<const LENGTH: usize> Lockable<HeapByteArray<LENGTH>> for HeapByteArray<LENG
     /// Locks a [HeapByteArray], and returns a [Protected] wrapper.
     fn mlock(
         self,
-    ) -> Result<Protected<HeapByteArray<LENGTH>, traits::ReadWrite, traits::Locked>, std::io::Error>
-    {
+    ) -> Result<
+        Protected<HeapByteArray<LENGTH>, traits::ReadWrite, traits::Locked>,
+        crate::error::Error,
+    > {
         Protected::<HeapByteArray<LENGTH>, traits::ReadWrite, traits::Unlocked>::new_with(self)
             .mlock()
     }
+
+    /// Locks a [HeapByteArray] according to `policy`, and returns a
+    /// [Protected] wrapper.
+    fn mlock_with_policy(
+        self,
+        policy: LockPolicy,
+    ) -> Result<
+        Protected<HeapByteArray<LENGTH>, traits::ReadWrite, traits::Locked>,
+        crate::error::Error,
+    > {
+        Protected::<HeapByteArray<LENGTH>, traits::ReadWrite, traits::Unlocked>::new_with(self)
+            .mlock_with_policy(policy)
+    }
 }
 
 impl Lockable<HeapBytes> for HeapBytes {
     /// Locks a [HeapBytes], and returns a [Protected] wrapper.
     fn mlock(
         self,
-    ) -> Result<Protected<HeapBytes, traits::ReadWrite, traits::Locked>, std::io::Error> {
+    ) -> Result<Protected<HeapBytes, traits::ReadWrite, traits::Locked>, crate::error::Error> {
         Protected::<HeapBytes, traits::ReadWrite, traits::Unlocked>::new_with(self).mlock()
     }
+
+    /// Locks a [HeapBytes] according to `policy`, and returns a [Protected]
+    /// wrapper.
+    fn mlock_with_policy(
+        self,
+        policy: LockPolicy,
+    ) -> Result<Protected<HeapBytes, traits::ReadWrite, traits::Locked>, crate::error::Error> {
+        Protected::<HeapBytes, traits::ReadWrite, traits::Unlocked>::new_with(self)
+            .mlock_with_policy(policy)
+    }
 }
 
 #[derive(Clone)]
 /// Custom page-aligned allocator implementation. Creates blocks of page-aligned
 /// heap-allocated memory regions, with no-access pages before and after the
 /// allocated region of memory.
+///
+/// The padding between the end of each region and the aft no-access page is
+/// filled with random canary bytes, which are verified when the region is
+/// freed, similar to libsodium's `sodium_malloc()`. Overwritten canaries are
+/// counted by [`heap_corruptions()`]. Canaries zeroed by `MADV_WIPEONFORK` in
+/// a forked child (see [`AllocationPolicy`]) aren't treated as overwritten.
+///
+/// Regions are zeroed before they're freed. Because reallocation (such as
+/// when a [`Vec`] grows) allocates a new region, copies, and frees the old
+/// one, no copies of the contents are left behind when a [`HeapBytes`] is
+/// resized.
 pub struct PageAlignedAllocator;
 
 lazy_static! {
@@ -696,6 +1003,331 @@ fn _page_round(size: usize, pagesize: usize) -> usize {
     size + (pagesize - size % pagesize)
 }
 
+lazy_static! {
+    static ref CANARY: [u8; 16] = {
+        let mut canary = [0u8; 16];
+        copy_randombytes(&mut canary);
+        canary
+    };
+}
+
+static HEAP_CORRUPTIONS: AtomicUsize = AtomicUsize::new(0);
+
+/// Returns the padding between the end of a region of `size` bytes at `ptr`,
+/// and the aft guard page. Because regions are rounded up to whole pages,
+/// there's always at least 1 byte of padding.
+unsafe fn canary_region<'a>(ptr: *mut u8, size: usize, pagesize: usize) -> &'a mut [u8] {
+    std::slice::from_raw_parts_mut(ptr.add(size), _page_round(size, pagesize) - size)
+}
+
+fn write_canary(padding: &mut [u8], offset: usize) {
+    for (i, b) in padding.iter_mut().enumerate() {
+        *b = CANARY[(offset + i) % CANARY.len()];
+    }
+}
+
+fn check_canary(padding: &[u8], offset: usize) -> bool {
+    padding
+        .iter()
+        .enumerate()
+        .all(|(i, b)| *b == CANARY[(offset + i) % CANARY.len()])
+}
+
+/// Verifies the canary following the allocation of `capacity` bytes at `ptr`.
+fn verify_canary(ptr: *const u8, capacity: usize) -> Result<(), crate::error::Error> {
+    if capacity == 0 {
+        // nothing is allocated
+        return Ok(());
+    }
+    let padding = unsafe { canary_region(ptr as *mut u8, capacity, *PAGESIZE) };
+    if check_canary(padding, capacity) || canary_wiped(ptr, padding) {
+        Ok(())
+    } else {
+        Err(dryoc_error!(format!(
+            "heap corruption detected: canary after {} byte region was overwritten",
+            capacity
+        )))
+    }
+}
+
+/// Returns the number of regions allocated by [`PageAlignedAllocator`] which
+/// were found to have been written past their end (i.e., whose canary was
+/// overwritten) when they were freed. Corruption is also reported on standard
+/// error when it's detected.
+pub fn heap_corruptions() -> usize {
+    HEAP_CORRUPTIONS.load(Ordering::SeqCst)
+}
+
+/// Backing store for new [`PageAlignedAllocator`] allocations, selected with
+/// [`set_allocator_backend()`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum AllocatorBackend {
+    /// Page-aligned heap memory, from `posix_memalign()` on UNIX, or
+    /// `VirtualAlloc()` on Windows.
+    #[default]
+    Heap,
+    /// Secret memory from `memfd_secret()`, available on Linux 5.14 and later.
+    /// Secret memory is removed from the kernel's direct map, so it's not
+    /// accessible to the kernel (or other processes) even through kernel
+    /// bugs. Falls back to [`AllocatorBackend::Heap`] when `memfd_secret()`
+    /// is unavailable, such as on other platforms, older kernels, or kernels
+    /// booted with `secretmem.enable=0`.
+    ///
+    /// Secret memory is always shared with child processes after `fork()`,
+    /// and is charged against `RLIMIT_MEMLOCK` whether or not it's locked.
+    MemfdSecret,
+}
+
+static ALLOCATOR_BACKEND: AtomicU8 = AtomicU8::new(AllocatorBackend::Heap as u8);
+
+/// Sets the backing store used by [`PageAlignedAllocator`] (and therefore
+/// [`HeapBytes`] and [`HeapByteArray`]) for new allocations. Existing
+/// allocations are unaffected.
+pub fn set_allocator_backend(backend: AllocatorBackend) {
+    ALLOCATOR_BACKEND.store(backend as u8, Ordering::SeqCst);
+}
+
+/// Returns the backing store used by [`PageAlignedAllocator`] for new
+/// allocations, as set by [`set_allocator_backend()`].
+pub fn allocator_backend() -> AllocatorBackend {
+    match ALLOCATOR_BACKEND.load(Ordering::SeqCst) {
+        b if b == AllocatorBackend::MemfdSecret as u8 => AllocatorBackend::MemfdSecret,
+        _ => AllocatorBackend::Heap,
+    }
+}
+
+/// Returns `true` if [`AllocatorBackend::MemfdSecret`] is supported by the
+/// running kernel, in which case it won't fall back to the heap.
+pub fn memfd_secret_available() -> bool {
+    #[cfg(target_os = "linux")]
+    {
+        *secretmem::AVAILABLE
+    }
+    #[cfg(not(target_os = "linux"))]
+    {
+        false
+    }
+}
+
+/// Advice given to the kernel about the data region of each new
+/// [`PageAlignedAllocator`] allocation, set with [`set_allocation_policy()`].
+/// Only has an effect on Linux, and each option is ignored where the kernel
+/// doesn't support it.
+///
+/// By default, allocations are excluded from core dumps (`MADV_DONTDUMP`), but
+/// not wiped in forked children (`MADV_WIPEONFORK`), because processes which
+/// load keys and then fork workers expect the keys to be available in the
+/// workers.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AllocationPolicy {
+    dont_dump: bool,
+    wipe_on_fork: bool,
+}
+
+impl Default for AllocationPolicy {
+    fn default() -> Self {
+        Self {
+            dont_dump: true,
+            wipe_on_fork: false,
+        }
+    }
+}
+
+impl AllocationPolicy {
+    const DONT_DUMP: u8 = 1;
+    const WIPE_ON_FORK: u8 = 2;
+
+    /// Returns this policy with `dont_dump`, which excludes allocations from
+    /// core dumps with `MADV_DONTDUMP`.
+    #[must_use]
+    pub fn with_dont_dump(self, dont_dump: bool) -> Self {
+        Self { dont_dump, ..self }
+    }
+
+    /// Returns this policy with `wipe_on_fork`, which replaces allocations
+    /// with zeroes in forked children with `MADV_WIPEONFORK` (Linux 4.14 and
+    /// later). Has no effect on [`AllocatorBackend::MemfdSecret`] allocations,
+    /// which are always shared with children.
+    #[must_use]
+    pub fn with_wipe_on_fork(self, wipe_on_fork: bool) -> Self {
+        Self {
+            wipe_on_fork,
+            ..self
+        }
+    }
+
+    /// Returns `true` if allocations are excluded from core dumps.
+    pub fn dont_dump(&self) -> bool {
+        self.dont_dump
+    }
+
+    /// Returns `true` if allocations are wiped in forked children.
+    pub fn wipe_on_fork(&self) -> bool {
+        self.wipe_on_fork
+    }
+
+    fn bits(&self) -> u8 {
+        (if self.dont_dump { Self::DONT_DUMP } else { 0 })
+            | (if self.wipe_on_fork {
+                Self::WIPE_ON_FORK
+            } else {
+                0
+            })
+    }
+
+    fn from_bits(bits: u8) -> Self {
+        Self {
+            dont_dump: bits & Self::DONT_DUMP != 0,
+            wipe_on_fork: bits & Self::WIPE_ON_FORK != 0,
+        }
+    }
+}
+
+static ALLOCATION_POLICY: AtomicU8 = AtomicU8::new(AllocationPolicy::DONT_DUMP);
+
+/// Sets the [`AllocationPolicy`] used by [`PageAlignedAllocator`] (and
+/// therefore [`HeapBytes`] and [`HeapByteArray`]) for new allocations.
+/// Existing allocations are unaffected.
+pub fn set_allocation_policy(policy: AllocationPolicy) {
+    ALLOCATION_POLICY.store(policy.bits(), Ordering::SeqCst);
+}
+
+/// Returns the [`AllocationPolicy`] used by [`PageAlignedAllocator`] for new
+/// allocations, as set by [`set_allocation_policy()`].
+pub fn allocation_policy() -> AllocationPolicy {
+    AllocationPolicy::from_bits(ALLOCATION_POLICY.load(Ordering::SeqCst))
+}
+
+#[cfg(target_os = "linux")]
+fn madvise_region(ptr: *mut u8, len: usize, advice: i32) {
+    // advice is best effort, as it's not supported by every kernel, or for
+    // secret memory
+    unsafe { libc::madvise(ptr as *mut libc::c_void, len, advice) };
+}
+
+/// Regions given `MADV_WIPEONFORK`, whose canaries are zeroed along with their
+/// contents in forked children.
+#[cfg(target_os = "linux")]
+static WIPE_ON_FORK_REGIONS: std::sync::Mutex<Vec<usize>> = std::sync::Mutex::new(Vec::new());
+
+/// Returns `true` if the canary in `padding` was zeroed because the region at
+/// `ptr` was wiped when this process was forked, rather than overwritten.
+fn canary_wiped(ptr: *const u8, padding: &[u8]) -> bool {
+    #[cfg(target_os = "linux")]
+    {
+        padding.iter().all(|b| *b == 0)
+            && WIPE_ON_FORK_REGIONS
+                .lock()
+                .unwrap_or_else(|err| err.into_inner())
+                .contains(&(ptr as usize))
+    }
+    #[cfg(not(target_os = "linux"))]
+    {
+        let _ = (ptr, padding);
+        false
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod secretmem {
+    //! Allocations backed by `memfd_secret()`. Secret mappings are recorded,
+    //! so that they can be unmapped rather than freed, and aren't passed to
+    //! `mlock()`, which fails for secret memory (it's never swapped anyway).
+    use std::ptr;
+    use std::sync::Mutex;
+
+    use lazy_static::lazy_static;
+    use libc::c_void;
+
+    static MAPPINGS: Mutex<Vec<(usize, usize)>> = Mutex::new(Vec::new());
+
+    lazy_static! {
+        pub(super) static ref AVAILABLE: bool = {
+            let fd = unsafe { libc::syscall(libc::SYS_memfd_secret, 0) } as i32;
+            if fd >= 0 {
+                unsafe { libc::close(fd) };
+            }
+            fd >= 0
+        };
+    }
+
+    /// Maps `size` bytes, of which all but the first and last `pagesize` bytes
+    /// are secret memory, returning `None` if `memfd_secret()` fails. Only the
+    /// secret pages are charged against `RLIMIT_MEMLOCK`, as the guard pages
+    /// are mapped separately.
+    pub(super) fn map(size: usize, pagesize: usize) -> Option<*mut c_void> {
+        if !*AVAILABLE {
+            return None;
+        }
+        let out = unsafe {
+            libc::mmap(
+                ptr::null_mut(),
+                size,
+                libc::PROT_NONE,
+                libc::MAP_PRIVATE | libc::MAP_ANONYMOUS,
+                -1,
+                0,
+            )
+        };
+        if out == libc::MAP_FAILED {
+            return None;
+        }
+        let fd = unsafe { libc::syscall(libc::SYS_memfd_secret, 0) } as i32;
+        let secret_size = size - 2 * pagesize;
+        let secret = unsafe {
+            if fd >= 0 && libc::ftruncate(fd, secret_size as libc::off_t) == 0 {
+                libc::mmap(
+                    out.add(pagesize),
+                    secret_size,
+                    libc::PROT_READ | libc::PROT_WRITE,
+                    libc::MAP_SHARED | libc::MAP_FIXED,
+                    fd,
+                    0,
+                )
+            } else {
+                libc::MAP_FAILED
+            }
+        };
+        if fd >= 0 {
+            // the mapping keeps the memory alive
+            unsafe { libc::close(fd) };
+        }
+        if secret == libc::MAP_FAILED {
+            unsafe { libc::munmap(out, size) };
+            return None;
+        }
+        MAPPINGS
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .push((out as usize, size));
+        Some(out)
+    }
+
+    /// Returns `true` if `data` is within a secret mapping.
+    pub(super) fn contains(data: &[u8]) -> bool {
+        let addr = data.as_ptr() as usize;
+        MAPPINGS
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .iter()
+            .any(|(base, size)| addr >= *base && addr + data.len() <= base + size)
+    }
+
+    /// Unmaps `ptr` if it's a secret mapping of `size` bytes, returning
+    /// `false` if it isn't.
+    pub(super) fn unmap(ptr: *mut c_void, size: usize) -> bool {
+        let mut mappings = MAPPINGS.lock().unwrap_or_else(|err| err.into_inner());
+        match mappings.iter().position(|m| *m == (ptr as usize, size)) {
+            Some(pos) => {
+                mappings.swap_remove(pos);
+                unsafe { libc::munmap(ptr, size) };
+                true
+            }
+            None => false,
+        }
+    }
+}
+
 unsafe impl Allocator for PageAlignedAllocator {
     #[inline]
     fn allocate(&self, layout: Layout) -> Result<ptr::NonNull<[u8]>, AllocError> {
@@ -704,16 +1336,30 @@ unsafe impl Allocator for PageAlignedAllocator {
         #[cfg(unix)]
         let out = {
             use libc::posix_memalign;
-            let mut out = ptr::null_mut();
 
-            // allocate full pages, in addition to an extra page at the start and
-            // end which will remain locked with no access permitted.
-            let ret = unsafe { posix_memalign(&mut out, pagesize, size) };
-            if ret != 0 {
-                return Err(AllocError);
-            }
+            #[cfg(target_os = "linux")]
+            let secret = match allocator_backend() {
+                AllocatorBackend::MemfdSecret => secretmem::map(size, pagesize),
+                AllocatorBackend::Heap => None,
+            };
+            #[cfg(not(target_os = "linux"))]
+            let secret = None;
+
+            match secret {
+                Some(out) => out,
+                None => {
+                    let mut out = ptr::null_mut();
+
+                    // allocate full pages, in addition to an extra page at the start and
+                    // end which will remain locked with no access permitted.
+                    let ret = unsafe { posix_memalign(&mut out, pagesize, size) };
+                    if ret != 0 {
+                        return Err(AllocError);
+                    }
 
-            out
+                    out
+                }
+            }
         };
         #[cfg(windows)]
         let out = {
@@ -755,6 +1401,31 @@ unsafe impl Allocator for PageAlignedAllocator {
             .map_err(|err| eprintln!("mprotect error = {:?}, in allocator", err))
             .ok();
 
+        // fill the padding between the region and the aft guard page with
+        // canary bytes, to detect small overflows which don't reach the guard
+        // page
+        write_canary(
+            unsafe { canary_region(out.add(pagesize) as *mut u8, layout.size(), pagesize) },
+            layout.size(),
+        );
+
+        #[cfg(target_os = "linux")]
+        {
+            let policy = allocation_policy();
+            let data = unsafe { out.add(pagesize) as *mut u8 };
+            let data_len = _page_round(layout.size(), pagesize);
+            if policy.dont_dump {
+                madvise_region(data, data_len, libc::MADV_DONTDUMP);
+            }
+            if policy.wipe_on_fork {
+                madvise_region(data, data_len, libc::MADV_WIPEONFORK);
+                WIPE_ON_FORK_REGIONS
+                    .lock()
+                    .unwrap_or_else(|err| err.into_inner())
+                    .push(data as usize);
+            }
+        }
+
         unsafe { Ok(ptr::NonNull::new_unchecked(slice)) }
     }
 
@@ -762,6 +1433,28 @@ unsafe impl Allocator for PageAlignedAllocator {
     unsafe fn deallocate(&self, ptr: ptr::NonNull<u8>, layout: Layout) {
         let pagesize = *PAGESIZE;
 
+        let padding = canary_region(ptr.as_ptr(), layout.size(), pagesize);
+        if !check_canary(padding, layout.size()) && !canary_wiped(ptr.as_ptr(), padding) {
+            HEAP_CORRUPTIONS.fetch_add(1, Ordering::SeqCst);
+            eprintln!(
+                "heap corruption detected: canary after {} byte region was overwritten",
+                layout.size()
+            );
+        }
+
+        #[cfg(target_os = "linux")]
+        {
+            let mut regions = WIPE_ON_FORK_REGIONS
+                .lock()
+                .unwrap_or_else(|err| err.into_inner());
+            if let Some(pos) = regions.iter().position(|r| *r == ptr.as_ptr() as usize) {
+                regions.swap_remove(pos);
+            }
+        }
+
+        // wipe the region before it's freed, including any spare capacity
+        std::slice::from_raw_parts_mut(ptr.as_ptr(), layout.size()).zeroize();
+
         let ptr = ptr.as_ptr().offset(-(pagesize as isize));
 
         // unlock the fore protected region
@@ -781,7 +1474,25 @@ unsafe impl Allocator for PageAlignedAllocator {
 
         #[cfg(unix)]
         {
-            libc::free(ptr as *mut libc::c_void);
+            #[cfg(target_os = "linux")]
+            let unmapped = secretmem::unmap(
+                ptr as *mut libc::c_void,
+                _page_round(layout.size(), pagesize) + 2 * pagesize,
+            );
+            #[cfg(not(target_os = "linux"))]
+            let unmapped = false;
+
+            if !unmapped {
+                // undo the allocation policy, before the pages are reused by
+                // the heap
+                #[cfg(target_os = "linux")]
+                {
+                    let data_len = _page_round(layout.size(), pagesize);
+                    madvise_region(ptr.add(pagesize), data_len, libc::MADV_DODUMP);
+                    madvise_region(ptr.add(pagesize), data_len, libc::MADV_KEEPONFORK);
+                }
+                libc::free(ptr as *mut libc::c_void);
+            }
         }
         #[cfg(windows)]
         {
@@ -807,26 +1518,48 @@ pub struct HeapByteArray<const LENGTH: usize>(Vec<u8, PageAlignedAllocator>);
 #[derive(Zeroize, ZeroizeOnDrop, Debug, PartialEq, Eq, Clone)]
 pub struct HeapBytes(Vec<u8, PageAlignedAllocator>);
 
+impl<const LENGTH: usize> HeapByteArray<LENGTH> {
+    /// Verifies that nothing has been written past the end of this array,
+    /// which [`PageAlignedAllocator`] detects with canary bytes, returning an
+    /// error if the canary has been overwritten. Canaries are also verified
+    /// when the array is freed, see [`heap_corruptions()`].
+    pub fn verify_canary(&self) -> Result<(), crate::error::Error> {
+        verify_canary(self.0.as_ptr(), self.0.capacity())
+    }
+}
+
+impl HeapBytes {
+    /// Verifies that nothing has been written past the end of this buffer's
+    /// capacity, which [`PageAlignedAllocator`] detects with canary bytes,
+    /// returning an error if the canary has been overwritten. Canaries are
+    /// also verified when the buffer is freed, see [`heap_corruptions()`].
+    pub fn verify_canary(&self) -> Result<(), crate::error::Error> {
+        verify_canary(self.0.as_ptr(), self.0.capacity())
+    }
+}
+
 impl<A: Zeroize + NewBytes + Lockable<A>> NewLocked<A> for A {
-    fn new_locked() -> Result<Protected<Self, traits::ReadWrite, traits::Locked>, std::io::Error> {
+    fn new_locked()
+    -> Result<Protected<Self, traits::ReadWrite, traits::Locked>, crate::error::Error> {
         Self::new_bytes().mlock()
     }
 
     fn new_readonly_locked()
-    -> Result<Protected<Self, traits::ReadOnly, traits::Locked>, std::io::Error> {
+    -> Result<Protected<Self, traits::ReadOnly, traits::Locked>, crate::error::Error> {
         Self::new_bytes()
             .mlock()
             .and_then(|p| p.mprotect_readonly())
     }
 
-    fn gen_locked() -> Result<Protected<Self, traits::ReadWrite, traits::Locked>, std::io::Error> {
+    fn gen_locked()
+    -> Result<Protected<Self, traits::ReadWrite, traits::Locked>, crate::error::Error> {
         let mut res = Self::new_bytes().mlock()?;
         copy_randombytes(res.as_mut_slice());
         Ok(res)
     }
 
     fn gen_readonly_locked()
-    -> Result<Protected<Self, traits::ReadOnly, traits::Locked>, std::io::Error> {
+    -> Result<Protected<Self, traits::ReadOnly, traits::Locked>, crate::error::Error> {
         Self::gen_locked().and_then(|s| s.mprotect_readonly())
     }
 }
@@ -848,8 +1581,7 @@ impl<A: Zeroize + NewBytes + ResizableBytes + Lockable<A>> NewLockedFromSlice<A>
     fn from_slice_into_readonly_locked(
         src: &[u8],
     ) -> Result<Protected<Self, traits::ReadOnly, traits::Locked>, crate::error::Error> {
-        Self::from_slice_into_locked(src)
-            .and_then(|s| s.mprotect_readonly().map_err(|err| err.into()))
+        Self::from_slice_into_locked(src).and_then(|s| s.mprotect_readonly())
     }
 }
 
@@ -874,8 +1606,67 @@ impl<const LENGTH: usize> NewLockedFromSlice<HeapByteArray<LENGTH>> for HeapByte
     fn from_slice_into_readonly_locked(
         other: &[u8],
     ) -> Result<Protected<Self, traits::ReadOnly, traits::Locked>, crate::error::Error> {
-        Self::from_slice_into_locked(other)
-            .and_then(|s| s.mprotect_readonly().map_err(|err| err.into()))
+        Self::from_slice_into_locked(other).and_then(|s| s.mprotect_readonly())
+    }
+}
+
+impl<const LENGTH: usize> Locked<HeapByteArray<LENGTH>> {
+    /// Returns a new locked byte array, decoded from the hexadecimal string
+    /// `hex` directly into locked memory. Decoding is constant-time, and no
+    /// intermediate copies of the decoded bytes are made.
+    ///
+    /// ```
+    /// use dryoc::protected::*;
+    ///
+    /// let key = Locked::<HeapByteArray<4>>::from_hex("deadbeef").expect("decode failed");
+    /// assert_eq!(key.as_slice(), &[0xde, 0xad, 0xbe, 0xef]);
+    /// ```
+    pub fn from_hex(hex: &str) -> Result<Self, crate::error::Error> {
+        let mut res = HeapByteArray::<LENGTH>::new_locked()?;
+        crate::utils::hex_decode_into(res.as_mut_slice(), hex)?;
+        Ok(res)
+    }
+
+    /// Returns a new locked byte array, decoded from the base64 string
+    /// `base64` (standard alphabet, padding optional) directly into locked
+    /// memory. Decoding is constant-time, and no intermediate copies of the
+    /// decoded bytes are made.
+    ///
+    /// ```
+    /// use dryoc::protected::*;
+    ///
+    /// let key = Locked::<HeapByteArray<4>>::from_base64("3q2+7w==").expect("decode failed");
+    /// assert_eq!(key.as_slice(), &[0xde, 0xad, 0xbe, 0xef]);
+    /// ```
+    pub fn from_base64(base64: &str) -> Result<Self, crate::error::Error> {
+        let mut res = HeapByteArray::<LENGTH>::new_locked()?;
+        crate::utils::base64_decode_into(res.as_mut_slice(), base64)?;
+        Ok(res)
+    }
+}
+
+impl Locked<HeapBytes> {
+    /// Returns new locked bytes, decoded from the hexadecimal string `hex`
+    /// directly into locked memory. Decoding is constant-time, and no
+    /// intermediate copies of the decoded bytes are made.
+    pub fn from_hex(hex: &str) -> Result<Self, crate::error::Error> {
+        let mut bytes = HeapBytes::default();
+        bytes.resize(hex.len() / 2, 0);
+        let mut res = bytes.mlock()?;
+        crate::utils::hex_decode_into(res.as_mut_slice(), hex)?;
+        Ok(res)
+    }
+
+    /// Returns new locked bytes, decoded from the base64 string `base64`
+    /// (standard alphabet, padding optional) directly into locked memory.
+    /// Decoding is constant-time, and no intermediate copies of the decoded
+    /// bytes are made.
+    pub fn from_base64(base64: &str) -> Result<Self, crate::error::Error> {
+        let mut bytes = HeapBytes::default();
+        bytes.resize(crate::utils::base64_decoded_len(base64)?, 0);
+        let mut res = bytes.mlock()?;
+        crate::utils::base64_decode_into(res.as_mut_slice(), base64)?;
+        Ok(res)
     }
 }
 
@@ -942,7 +1733,13 @@ impl MutBytes for HeapBytes {
 }
 
 impl ResizableBytes for HeapBytes {
+    /// Resizes `self`, wiping any bytes truncated when shrinking. Regions
+    /// left behind when growing are wiped by the
+    /// [allocator](PageAlignedAllocator).
     fn resize(&mut self, new_len: usize, value: u8) {
+        if new_len < self.0.len() {
+            self.0[new_len..].zeroize();
+        }
         self.0.resize(new_len, value);
     }
 }
@@ -1446,6 +2243,739 @@ impl<A: Zeroize + Bytes, PM: traits::ProtectMode, LM: traits::LockMode> Zeroize
     }
 }
 
+/// Holds any [`Zeroize`] value, such as a struct of keys, in a protected
+/// region of memory, with the same locking and protection states as
+/// [`Protected`]. Use this to protect composite secrets without flattening
+/// them to bytes.
+///
+/// The value is moved into a heap-allocated, page-aligned region, with
+/// no-access guard pages around it. It's zeroized when the box is dropped.
+/// Only the value's own memory is protected: memory it owns elsewhere, such as
+/// the buffer of a [`Vec`] field, isn't. Protected boxes work best with values
+/// that store their secrets inline, such as structs of byte arrays.
+///
+/// Moving a value into a box may leave copies of it on the stack, as with any
+/// move in Rust. To avoid that, create the box with a default value, and fill
+/// it in place.
+///
+/// ```
+/// use dryoc::protected::*;
+/// use zeroize::Zeroize;
+///
+/// #[derive(Default, Zeroize)]
+/// struct Keys {
+///     encryption: [u8; 32],
+///     authentication: [u8; 32],
+/// }
+///
+/// let mut keys = ProtectedBox::new_locked(Keys::default()).expect("lock failed");
+/// dryoc::rng::copy_randombytes(&mut keys.encryption);
+/// dryoc::rng::copy_randombytes(&mut keys.authentication);
+///
+/// // Make the keys read-only while they're in use
+/// let keys = keys.mprotect_readonly().expect("mprotect failed");
+/// assert_ne!(keys.encryption, keys.authentication);
+/// ```
+pub struct ProtectedBox<T: Zeroize, PM: traits::ProtectMode, LM: traits::LockMode> {
+    i: Option<Box<T, PageAlignedAllocator>>,
+    lm: int::LockMode,
+    pm: int::ProtectMode,
+    p: PhantomData<PM>,
+    l: PhantomData<LM>,
+}
+
+impl<T: Zeroize> ProtectedBox<T, traits::ReadWrite, traits::Unlocked> {
+    /// Moves `value` into a new, unlocked, protected box.
+    pub fn new(value: T) -> Self {
+        Self {
+            i: Some(Box::new_in(value, PageAlignedAllocator)),
+            lm: int::LockMode::Unlocked,
+            pm: int::ProtectMode::ReadWrite,
+            p: PhantomData,
+            l: PhantomData,
+        }
+    }
+
+    /// Moves `value` into a new, locked, protected box.
+    pub fn new_locked(
+        value: T,
+    ) -> Result<ProtectedBox<T, traits::ReadWrite, traits::Locked>, crate::error::Error> {
+        Self::new(value).mlock()
+    }
+
+    /// Moves `value` into a new, locked, read-only protected box.
+    pub fn new_readonly_locked(
+        value: T,
+    ) -> Result<ProtectedBox<T, traits::ReadOnly, traits::Locked>, crate::error::Error> {
+        Self::new_locked(value).and_then(|b| b.mprotect_readonly())
+    }
+}
+
+impl<T: Zeroize, PM: traits::ProtectMode, LM: traits::LockMode> ProtectedBox<T, PM, LM> {
+    fn region(&self) -> &[u8] {
+        match &self.i {
+            Some(i) => unsafe {
+                std::slice::from_raw_parts(&**i as *const T as *const u8, std::mem::size_of::<T>())
+            },
+            None => &[],
+        }
+    }
+
+    fn into_state<OPM: traits::ProtectMode, OLM: traits::LockMode>(
+        mut self,
+    ) -> ProtectedBox<T, OPM, OLM> {
+        ProtectedBox {
+            i: self.i.take(),
+            lm: self.lm.clone(),
+            pm: self.pm.clone(),
+            p: PhantomData,
+            l: PhantomData,
+        }
+    }
+
+    /// Returns `true` if this box is actually locked in memory. See
+    /// [`Protected::is_locked`].
+    pub fn is_locked(&self) -> bool {
+        self.lm == int::LockMode::Locked
+    }
+
+    /// Unlocks the box's memory, consuming it, and returns an unlocked box.
+    pub fn munlock(self) -> Result<ProtectedBox<T, PM, traits::Unlocked>, crate::error::Error> {
+        if self.is_locked() {
+            dryoc_munlock(self.region())?;
+        }
+        let mut unlocked = self.into_state();
+        unlocked.lm = int::LockMode::Unlocked;
+        Ok(unlocked)
+    }
+
+    /// Protects the box's memory as read-only, consuming it, and returns a
+    /// read-only box.
+    pub fn mprotect_readonly(
+        self,
+    ) -> Result<ProtectedBox<T, traits::ReadOnly, LM>, crate::error::Error> {
+        dryoc_mprotect_readonly(self.region())?;
+        let mut readonly = self.into_state();
+        readonly.pm = int::ProtectMode::ReadOnly;
+        Ok(readonly)
+    }
+
+    /// Protects the box's memory as read-write, consuming it, and returns a
+    /// read-write box.
+    pub fn mprotect_readwrite(
+        self,
+    ) -> Result<ProtectedBox<T, traits::ReadWrite, LM>, crate::error::Error> {
+        dryoc_mprotect_readwrite(self.region())?;
+        let mut readwrite = self.into_state();
+        readwrite.pm = int::ProtectMode::ReadWrite;
+        Ok(readwrite)
+    }
+}
+
+impl<T: Zeroize, PM: traits::ProtectMode> ProtectedBox<T, PM, traits::Unlocked> {
+    /// Locks the box's memory according to the global [`LockPolicy`],
+    /// consuming it, and returns a locked box.
+    pub fn mlock(self) -> Result<ProtectedBox<T, PM, traits::Locked>, crate::error::Error> {
+        self.mlock_with_policy(lock_policy())
+    }
+
+    /// Locks the box's memory according to `policy`, consuming it, and
+    /// returns a locked box.
+    pub fn mlock_with_policy(
+        self,
+        policy: LockPolicy,
+    ) -> Result<ProtectedBox<T, PM, traits::Locked>, crate::error::Error> {
+        let locked = match policy {
+            LockPolicy::Require => {
+                dryoc_mlock(self.region())?;
+                true
+            }
+            LockPolicy::BestEffort => dryoc_mlock(self.region()).is_ok(),
+            LockPolicy::Disabled => false,
+        };
+        let mut boxed = self.into_state();
+        if locked {
+            boxed.lm = int::LockMode::Locked;
+        }
+        Ok(boxed)
+    }
+
+    /// Protects the box's memory as no-access, consuming it, and returns a
+    /// no-access box.
+    pub fn mprotect_noaccess(
+        self,
+    ) -> Result<ProtectedBox<T, traits::NoAccess, traits::Unlocked>, crate::error::Error> {
+        dryoc_mprotect_noaccess(self.region())?;
+        let mut noaccess = self.into_state();
+        noaccess.pm = int::ProtectMode::NoAccess;
+        Ok(noaccess)
+    }
+}
+
+impl<T: Zeroize, LM: traits::LockMode> std::ops::Deref for ProtectedBox<T, traits::ReadOnly, LM> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        self.i.as_ref().unwrap()
+    }
+}
+
+impl<T: Zeroize, LM: traits::LockMode> std::ops::Deref for ProtectedBox<T, traits::ReadWrite, LM> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        self.i.as_ref().unwrap()
+    }
+}
+
+impl<T: Zeroize, LM: traits::LockMode> std::ops::DerefMut
+    for ProtectedBox<T, traits::ReadWrite, LM>
+{
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.i.as_mut().unwrap()
+    }
+}
+
+impl<T: Zeroize, PM: traits::ProtectMode, LM: traits::LockMode> Drop for ProtectedBox<T, PM, LM> {
+    fn drop(&mut self) {
+        if self.i.is_none() {
+            return;
+        }
+        if self.pm != int::ProtectMode::ReadWrite {
+            dryoc_mprotect_readwrite(self.region())
+                .map_err(|err| eprintln!("mprotect_readwrite error on drop = {:?}", err))
+                .ok();
+        }
+        if let Some(i) = self.i.as_mut() {
+            i.zeroize();
+        }
+        if self.is_locked() {
+            dryoc_munlock(self.region())
+                .map_err(|err| eprintln!("dryoc_munlock error on drop = {:?}", err))
+                .ok();
+        }
+    }
+}
+
+/// A protected region shared between threads, without copying it. The region
+/// is kept read-only, and shared with a [`RwLock`]: any number of readers can
+/// access it at once, and writers make it writable only while they hold the
+/// lock, which excludes readers. Cloning returns another handle to the same
+/// region, which is wiped and unlocked when the last handle is dropped.
+///
+/// Refer to [crate::protected] for sample usage.
+pub struct SharedProtected<A: Zeroize + Bytes, LM: traits::LockMode> {
+    inner: Arc<RwLock<Protected<A, traits::ReadOnly, LM>>>,
+}
+
+impl<A: Zeroize + Bytes, LM: traits::LockMode> SharedProtected<A, LM> {
+    /// Protects `protected` as read-only, consuming it, and returns a new
+    /// shared region.
+    pub fn new<PM: traits::ProtectMode>(
+        protected: Protected<A, PM, LM>,
+    ) -> Result<Self, crate::error::Error> {
+        Ok(Self {
+            inner: Arc::new(RwLock::new(protected.mprotect_readonly()?)),
+        })
+    }
+
+    /// Returns read-only access to the region, blocking while a writer holds
+    /// the lock. Returns an error if a writer panicked while holding it.
+    pub fn read(
+        &self,
+    ) -> Result<RwLockReadGuard<'_, Protected<A, traits::ReadOnly, LM>>, crate::error::Error> {
+        self.inner
+            .read()
+            .map_err(|_| dryoc_error!("shared region is poisoned"))
+    }
+
+    /// Temporarily protects the region as read-write, while holding the lock
+    /// exclusively, and calls `f` with its contents, returning the result.
+    /// Read-only protection is restored when `f` returns, or if it panics.
+    pub fn with_readwrite<R, F: FnOnce(&mut [u8]) -> R>(
+        &self,
+        f: F,
+    ) -> Result<R, crate::error::Error>
+    where
+        A: MutBytes,
+    {
+        let mut protected = self
+            .inner
+            .write()
+            .map_err(|_| dryoc_error!("shared region is poisoned"))?;
+        let d = protected
+            .i
+            .as_mut()
+            .ok_or_else(|| dryoc_error!("unexpected empty internal struct"))?;
+        dryoc_mprotect_readwrite(d.a.as_slice())?;
+        let guard = RestoreGuard::new(d.a.as_slice(), dryoc_mprotect_readonly);
+        let result = f(d.a.as_mut_slice());
+        guard.restore()?;
+        Ok(result)
+    }
+}
+
+impl<A: Zeroize + Bytes, LM: traits::LockMode> Clone for SharedProtected<A, LM> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: Arc::clone(&self.inner),
+        }
+    }
+}
+
+/// Serialization of protected memory as a secret box, encrypted with a
+/// caller-provided key, for persisting locked secrets without serializing
+/// their plaintext. Implemented for read-only and read-write [`Protected`]
+/// regions.
+///
+/// The sealed form is the nonce, followed by the tag and ciphertext, as
+/// produced by [`DryocSecretBox`](crate::dryocsecretbox::DryocSecretBox) with a
+/// random nonce, and is deserialized with an [`Unseal`] seed, which decrypts
+/// directly into locked memory.
+///
+/// ```
+/// use dryoc::dryocsecretbox::Key;
+/// use dryoc::protected::*;
+/// use dryoc::types::*;
+/// use serde::de::DeserializeSeed;
+///
+/// let storage_key = Key::gen();
+/// let secret = HeapBytes::from_slice_into_locked(b"locked secret").expect("lock failed");
+///
+/// let json = serde_json::to_string(&secret.sealed(&storage_key)).expect("serialize failed");
+///
+/// let mut deserializer = serde_json::Deserializer::from_str(&json);
+/// let unsealed = Unseal::<HeapBytes, _>::new(&storage_key)
+///     .deserialize(&mut deserializer)
+///     .expect("deserialize failed");
+/// assert_eq!(unsealed.as_slice(), b"locked secret");
+///
+/// // Decryption fails with any other key
+/// let mut deserializer = serde_json::Deserializer::from_str(&json);
+/// assert!(
+///     Unseal::<HeapBytes, _>::new(&Key::gen())
+///         .deserialize(&mut deserializer)
+///         .is_err()
+/// );
+/// ```
+#[cfg(feature = "serde")]
+#[cfg_attr(all(feature = "nightly", doc), doc(cfg(feature = "serde")))]
+pub trait SealedSerialize: Bytes {
+    /// Returns a wrapper which serializes `self` encrypted with `key`, and a
+    /// new random nonce.
+    fn sealed<'a, Key: ByteArray<CRYPTO_SECRETBOX_KEYBYTES>>(
+        &'a self,
+        key: &'a Key,
+    ) -> Sealed<'a, Self, Key> {
+        Sealed { value: self, key }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<A: Zeroize + Bytes, LM: traits::LockMode> SealedSerialize
+    for Protected<A, traits::ReadOnly, LM>
+{
+}
+
+#[cfg(feature = "serde")]
+impl<A: Zeroize + Bytes, LM: traits::LockMode> SealedSerialize
+    for Protected<A, traits::ReadWrite, LM>
+{
+}
+
+/// Serializes the wrapped value encrypted with a key, as returned by
+/// [`SealedSerialize::sealed`].
+#[cfg(feature = "serde")]
+#[cfg_attr(all(feature = "nightly", doc), doc(cfg(feature = "serde")))]
+pub struct Sealed<'a, T: Bytes + ?Sized, Key: ByteArray<CRYPTO_SECRETBOX_KEYBYTES>> {
+    value: &'a T,
+    key: &'a Key,
+}
+
+#[cfg(feature = "serde")]
+impl<T: Bytes + ?Sized, Key: ByteArray<CRYPTO_SECRETBOX_KEYBYTES>> serde::Serialize
+    for Sealed<'_, T, Key>
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        use crate::dryocsecretbox::{DryocSecretBox, Nonce, VecBox};
+
+        let nonce = Nonce::gen();
+        let sealed: VecBox = DryocSecretBox::try_encrypt(self.value.as_slice(), &nonce, self.key)
+            .map_err(serde::ser::Error::custom)?;
+        let mut bytes = Vec::with_capacity(
+            CRYPTO_SECRETBOX_NONCEBYTES + CRYPTO_SECRETBOX_MACBYTES + self.value.len(),
+        );
+        bytes.extend_from_slice(nonce.as_slice());
+        bytes.extend_from_slice(sealed.tag().as_slice());
+        bytes.extend_from_slice(sealed.data().as_slice());
+        serializer.serialize_bytes(&bytes)
+    }
+}
+
+/// Deserializes a value serialized with [`SealedSerialize::sealed`], decrypting
+/// it with `key` directly into locked memory, which is wiped if decryption
+/// fails. Implemented for [`LockedBytes`](ptypes::LockedBytes) and
+/// [`Locked<HeapByteArray>`](ptypes::Locked), which must match the length of
+/// the sealed value.
+///
+/// Refer to [`SealedSerialize`] for sample usage.
+#[cfg(feature = "serde")]
+#[cfg_attr(all(feature = "nightly", doc), doc(cfg(feature = "serde")))]
+pub struct Unseal<'a, A, Key: ByteArray<CRYPTO_SECRETBOX_KEYBYTES>> {
+    key: &'a Key,
+    phantom: PhantomData<A>,
+}
+
+#[cfg(feature = "serde")]
+impl<'a, A, Key: ByteArray<CRYPTO_SECRETBOX_KEYBYTES>> Unseal<'a, A, Key> {
+    /// Returns a new seed which decrypts with `key`.
+    pub fn new(key: &'a Key) -> Self {
+        Self {
+            key,
+            phantom: PhantomData,
+        }
+    }
+
+    fn unseal<'de, D: serde::Deserializer<'de>>(
+        &self,
+        deserializer: D,
+        new_locked: impl FnOnce(usize) -> Result<Locked<A>, crate::error::Error>,
+    ) -> Result<Locked<A>, D::Error>
+    where
+        A: Zeroize + MutBytes,
+    {
+        use serde::de::Error;
+
+        use crate::dryocsecretbox::DryocSecretBoxRef;
+
+        // the ciphertext isn't secret, so it's fine to copy it
+        let bytes = deserializer.deserialize_bytes(CiphertextVisitor)?;
+        let (nonce, sealed) =
+            DryocSecretBoxRef::from_nonce_prefixed_bytes(&bytes).map_err(D::Error::custom)?;
+        let mut output = new_locked(sealed.data().len()).map_err(D::Error::custom)?;
+        sealed
+            .decrypt_into(output.as_mut_slice(), nonce, self.key)
+            .map_err(D::Error::custom)?;
+        Ok(output)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, Key: ByteArray<CRYPTO_SECRETBOX_KEYBYTES>> serde::de::DeserializeSeed<'de>
+    for Unseal<'_, HeapBytes, Key>
+{
+    type Value = ptypes::LockedBytes;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        self.unseal(deserializer, |len| {
+            let mut locked = HeapBytes::new_locked()?;
+            locked.resize(len, 0);
+            Ok(locked)
+        })
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, const LENGTH: usize, Key: ByteArray<CRYPTO_SECRETBOX_KEYBYTES>>
+    serde::de::DeserializeSeed<'de> for Unseal<'_, HeapByteArray<LENGTH>, Key>
+{
+    type Value = ptypes::Locked<HeapByteArray<LENGTH>>;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        self.unseal(deserializer, |len| {
+            if len != LENGTH {
+                return Err(dryoc_error!(format!(
+                    "sealed value has length {}, expected {}",
+                    len, LENGTH
+                )));
+            }
+            HeapByteArray::<LENGTH>::new_locked()
+        })
+    }
+}
+
+#[cfg(feature = "serde")]
+struct CiphertextVisitor;
+
+#[cfg(feature = "serde")]
+impl<'de> serde::de::Visitor<'de> for CiphertextVisitor {
+    type Value = Vec<u8>;
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(formatter, "sealed bytes")
+    }
+
+    fn visit_seq<S>(self, mut seq: S) -> Result<Self::Value, S::Error>
+    where
+        S: serde::de::SeqAccess<'de>,
+    {
+        // the hint comes from the untrusted input, so don't preallocate more
+        // than a page for it, like serde's own cautious size hints
+        let mut bytes = Vec::with_capacity(seq.size_hint().unwrap_or(0).min(4096));
+        while let Some(byte) = seq.next_element()? {
+            bytes.push(byte);
+        }
+        Ok(bytes)
+    }
+
+    fn visit_bytes<E>(self, v: &[u8]) -> Result<Self::Value, E>
+    where
+        E: serde::de::Error,
+    {
+        Ok(v.to_vec())
+    }
+
+    fn visit_byte_buf<E>(self, v: Vec<u8>) -> Result<Self::Value, E>
+    where
+        E: serde::de::Error,
+    {
+        Ok(v)
+    }
+}
+
+/// Report of this process's ability to lock memory, as returned by
+/// [`capabilities()`]. Fields which can't be determined on the current
+/// platform are `None`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Capabilities {
+    /// The system page size. Locked regions are always rounded up to whole
+    /// pages.
+    pub page_size: usize,
+    /// The soft `RLIMIT_MEMLOCK` limit in bytes, or `None` if unlimited.
+    pub memlock_limit: Option<u64>,
+    /// The hard `RLIMIT_MEMLOCK` limit in bytes, or `None` if unlimited. The
+    /// soft limit can be raised up to this value without privileges.
+    pub memlock_hard_limit: Option<u64>,
+    /// Whether `CAP_IPC_LOCK` is in the effective capability set (Linux
+    /// only), which exempts the process from `RLIMIT_MEMLOCK`.
+    pub cap_ipc_lock: Option<bool>,
+    /// The smallest memory limit of the process's cgroup and its ancestors
+    /// (Linux only), which locked memory is charged against. `None` if
+    /// there is no limit.
+    pub cgroup_memory_limit: Option<u64>,
+    /// Whether a single page could actually be locked.
+    pub mlock_probe: bool,
+}
+
+impl Capabilities {
+    /// Returns `true` if locking `bytes` bytes of memory will likely succeed.
+    /// Memory which is already locked by the process isn't accounted for.
+    pub fn can_lock(&self, bytes: usize) -> bool {
+        self.advice(bytes).is_empty()
+    }
+
+    /// Returns remediation advice for each problem which would prevent
+    /// locking `bytes` bytes of memory, or an empty list if there are none.
+    pub fn advice(&self, bytes: usize) -> Vec<String> {
+        let mut advice = vec![];
+        if !self.mlock_probe {
+            advice.push(
+                "locking a single page failed, raise the locked memory limit (`ulimit -l`, \
+                 `LimitMEMLOCK=` for systemd units, or `--ulimit memlock=` for Docker) or grant \
+                 CAP_IPC_LOCK"
+                    .into(),
+            );
+        }
+        let pages = bytes / self.page_size + usize::from(bytes % self.page_size != 0);
+        let required = (pages as u64).saturating_mul(self.page_size as u64);
+        if self.cap_ipc_lock != Some(true) {
+            if let Some(limit) = self.memlock_limit.filter(|limit| *limit < required) {
+                match self.memlock_hard_limit {
+                    Some(hard_limit) if hard_limit < required => advice.push(format!(
+                        "RLIMIT_MEMLOCK hard limit of {} bytes is below the {} bytes required, \
+                         raise it (`LimitMEMLOCK=` for systemd units, or `--ulimit memlock=` for \
+                         Docker) or grant CAP_IPC_LOCK",
+                        hard_limit, required
+                    )),
+                    _ => advice.push(format!(
+                        "RLIMIT_MEMLOCK soft limit of {} bytes is below the {} bytes required, \
+                         raise it with `ulimit -l` or `setrlimit()`",
+                        limit, required
+                    )),
+                }
+            }
+        }
+        if let Some(limit) = self.cgroup_memory_limit.filter(|limit| *limit < required) {
+            advice.push(format!(
+                "cgroup memory limit of {} bytes is below the {} bytes required, raise the \
+                 container or service memory limit",
+                limit, required
+            ));
+        }
+        advice
+    }
+
+    /// Returns an error with remediation advice if locking `bytes` bytes of
+    /// memory will likely fail. Intended to be called at startup, so that
+    /// misconfigured deployments fail fast.
+    pub fn require(&self, bytes: usize) -> Result<(), crate::error::Error> {
+        let advice = self.advice(bytes);
+        if advice.is_empty() {
+            Ok(())
+        } else {
+            Err(dryoc_error!(format!(
+                "unable to lock {} bytes of memory: {}",
+                bytes,
+                advice.join("; ")
+            )))
+        }
+    }
+}
+
+#[cfg(unix)]
+fn memlock_limits() -> (Option<u64>, Option<u64>) {
+    use libc::{getrlimit, rlimit, RLIMIT_MEMLOCK, RLIM_INFINITY};
+
+    let mut limits = rlimit {
+        rlim_cur: 0,
+        rlim_max: 0,
+    };
+    if unsafe { getrlimit(RLIMIT_MEMLOCK, &mut limits) } != 0 {
+        return (None, None);
+    }
+    // rlim_t is only u64 on some platforms
+    #[allow(clippy::useless_conversion)]
+    let limit = |value: libc::rlim_t| {
+        if value == RLIM_INFINITY {
+            None
+        } else {
+            u64::try_from(value).ok()
+        }
+    };
+    (limit(limits.rlim_cur), limit(limits.rlim_max))
+}
+
+#[cfg(windows)]
+fn memlock_limits() -> (Option<u64>, Option<u64>) {
+    (None, None)
+}
+
+#[cfg(target_os = "linux")]
+fn parse_cap_ipc_lock(status: &str) -> Option<bool> {
+    const CAP_IPC_LOCK: u32 = 14;
+
+    let caps = status
+        .lines()
+        .find_map(|line| line.strip_prefix("CapEff:"))?;
+    let caps = u64::from_str_radix(caps.trim(), 16).ok()?;
+    Some(caps & (1 << CAP_IPC_LOCK) != 0)
+}
+
+#[cfg(target_os = "linux")]
+fn parse_cgroup_limit(value: &str) -> Option<u64> {
+    match value.trim() {
+        "max" => None,
+        // cgroup v1 reports "unlimited" as the largest page-aligned i64
+        value => value.parse().ok().filter(|limit| *limit < (1 << 62)),
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn cgroup_memory_limit() -> Option<u64> {
+    use std::path::Path;
+
+    let cgroups = std::fs::read_to_string("/proc/self/cgroup").ok()?;
+    let (root, file, path) = cgroups.lines().find_map(|line| {
+        let mut parts = line.splitn(3, ':');
+        let (id, controllers, path) = (parts.next()?, parts.next()?, parts.next()?);
+        if id == "0" && controllers.is_empty() {
+            Some(("/sys/fs/cgroup", "memory.max", path))
+        } else if controllers.split(',').any(|c| c == "memory") {
+            Some(("/sys/fs/cgroup/memory", "memory.limit_in_bytes", path))
+        } else {
+            None
+        }
+    })?;
+
+    // limits of ancestor cgroups apply too, so take the smallest
+    Path::new(path.trim_start_matches('/'))
+        .ancestors()
+        .filter_map(|dir| std::fs::read_to_string(Path::new(root).join(dir).join(file)).ok())
+        .filter_map(|value| parse_cgroup_limit(&value))
+        .min()
+}
+
+/// Probes whether this process will likely be able to lock memory, returning a
+/// structured [`Capabilities`] report. On Linux, this checks the
+/// `RLIMIT_MEMLOCK` limits, `CAP_IPC_LOCK`, and cgroup memory limits, in
+/// addition to test locking a single page.
+///
+/// Call this at startup to fail fast with clear remediation advice, rather than
+/// erroring on the first allocation of locked memory:
+///
+/// ```
+/// use dryoc::protected::*;
+///
+/// let capabilities = capabilities();
+/// if let Err(err) = capabilities.require(64 * 1024) {
+///     eprintln!("warning: {}", err);
+/// }
+/// ```
+pub fn capabilities() -> Capabilities {
+    let (memlock_limit, memlock_hard_limit) = memlock_limits();
+    #[cfg(target_os = "linux")]
+    let (cap_ipc_lock, cgroup_memory_limit) = (
+        std::fs::read_to_string("/proc/self/status")
+            .ok()
+            .and_then(|status| parse_cap_ipc_lock(&status)),
+        cgroup_memory_limit(),
+    );
+    #[cfg(not(target_os = "linux"))]
+    let (cap_ipc_lock, cgroup_memory_limit) = (None, None);
+
+    Capabilities {
+        page_size: *PAGESIZE,
+        memlock_limit,
+        memlock_hard_limit,
+        cap_ipc_lock,
+        cgroup_memory_limit,
+        mlock_probe: HeapByteArray::<1>::new_locked().is_ok(),
+    }
+}
+
+/// Returns the number of bytes of memory which this process can still lock
+/// before reaching its `RLIMIT_MEMLOCK` limit. Returns `None` if there is no
+/// limit (including when the process has `CAP_IPC_LOCK`), or if it can't be
+/// determined on the current platform, which is currently anything other than
+/// Linux.
+///
+/// Locked regions are rounded up to whole pages, so compare against
+/// [`Capabilities::page_size`] multiples. Other processes and cgroup limits
+/// may also prevent locking; see [`capabilities()`].
+pub fn lockable_memory_remaining() -> Option<u64> {
+    #[cfg(target_os = "linux")]
+    {
+        let status = std::fs::read_to_string("/proc/self/status").ok()?;
+        if parse_cap_ipc_lock(&status) == Some(true) {
+            return None;
+        }
+        let (limit, _) = memlock_limits();
+        Some(limit?.saturating_sub(parse_locked_memory(&status)?))
+    }
+    #[cfg(not(target_os = "linux"))]
+    {
+        None
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn parse_locked_memory(status: &str) -> Option<u64> {
+    let locked = status
+        .lines()
+        .find_map(|line| line.strip_prefix("VmLck:"))?;
+    let kilobytes: u64 = locked.trim().strip_suffix("kB")?.trim().parse().ok()?;
+    Some(kilobytes * 1024)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1481,6 +3011,149 @@ mod tests {
         readwrite_key.as_mut_slice()[0] = 0;
     }
 
+    #[test]
+    fn test_protected_box() {
+        #[derive(Default, Zeroize)]
+        struct Keys {
+            a: [u8; 32],
+            b: [u8; 32],
+        }
+
+        let mut keys = ProtectedBox::new_locked(Keys::default()).expect("lock failed");
+        keys.a = [1; 32];
+        keys.b[0] = 2;
+        assert!(keys.is_locked());
+
+        let keys = keys.mprotect_readonly().expect("mprotect failed");
+        assert_eq!(keys.a, [1; 32]);
+        let keys = keys
+            .munlock()
+            .and_then(|k| k.mprotect_noaccess())
+            .and_then(|k| k.mprotect_readwrite())
+            .expect("unprotect failed");
+        assert!(!keys.is_locked());
+        assert_eq!(keys.b[0], 2);
+
+        // dropping a read-only box restores access before wiping it
+        let readonly = ProtectedBox::new_readonly_locked(Keys::default()).expect("lock failed");
+        drop(readonly);
+        let _empty = ProtectedBox::new_locked(()).expect("lock failed");
+    }
+
+    #[test]
+    fn test_scoped_unprotect() {
+        let mut noaccess = HeapBytes::from_slice_into_locked(b"secret")
+            .and_then(|b| b.munlock())
+            .and_then(|b| b.mprotect_noaccess())
+            .expect("mprotect failed");
+
+        let len = noaccess
+            .with_readonly(|b| b.len())
+            .expect("unprotect failed");
+        assert_eq!(len, 6);
+        noaccess
+            .with_readwrite(|b| b[0] = b'S')
+            .expect("unprotect failed");
+
+        // protection is restored even if the closure panics
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            noaccess.with_readonly(|_| panic!("oops")).ok();
+        }));
+        assert!(result.is_err());
+
+        let readonly = noaccess.mprotect_readonly().expect("mprotect failed");
+        assert_eq!(readonly.as_slice(), b"Secret");
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_sealed_serialize() {
+        use bincode::Options;
+        use serde::de::DeserializeSeed;
+
+        use crate::dryocsecretbox::Key;
+
+        let storage_key = Key::gen();
+        let secret = HeapByteArray::<32>::gen_readonly_locked().expect("lock failed");
+
+        let bytes = bincode::serialize(&secret.sealed(&storage_key)).expect("serialize failed");
+        // the plaintext isn't serialized
+        assert!(
+            !bytes
+                .windows(secret.len())
+                .any(|window| window == secret.as_slice())
+        );
+
+        let unsealed = Unseal::<HeapByteArray<32>, _>::new(&storage_key)
+            .deserialize(&mut bincode::Deserializer::from_slice(
+                &bytes,
+                bincode::DefaultOptions::new()
+                    .with_fixint_encoding()
+                    .allow_trailing_bytes(),
+            ))
+            .expect("deserialize failed");
+        assert_eq!(unsealed.as_slice(), secret.as_slice());
+
+        let json = serde_json::to_string(&secret.sealed(&storage_key)).expect("serialize failed");
+        let unsealed = Unseal::<HeapBytes, _>::new(&storage_key)
+            .deserialize(&mut serde_json::Deserializer::from_str(&json))
+            .expect("deserialize failed");
+        assert_eq!(unsealed.as_slice(), secret.as_slice());
+
+        // the length must match fixed-size arrays
+        assert!(
+            Unseal::<HeapByteArray<16>, _>::new(&storage_key)
+                .deserialize(&mut serde_json::Deserializer::from_str(&json))
+                .is_err()
+        );
+        assert!(
+            Unseal::<HeapBytes, _>::new(&Key::gen())
+                .deserialize(&mut serde_json::Deserializer::from_str(&json))
+                .is_err()
+        );
+        assert!(
+            Unseal::<HeapBytes, _>::new(&storage_key)
+                .deserialize(&mut serde_json::Deserializer::from_str("[1,2,3]"))
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_shared_protected() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<Locked<HeapBytes>>();
+        assert_send_sync::<NoAccess<HeapByteArray<32>>>();
+        assert_send_sync::<LockedBox<[u8; 32]>>();
+        assert_send_sync::<SharedLocked<HeapBytes>>();
+
+        let key = HeapByteArray::<32>::gen_locked().expect("lock failed");
+        let expected = key.as_slice().to_vec();
+        let shared = SharedProtected::new(key).expect("mprotect failed");
+
+        std::thread::scope(|scope| {
+            for _ in 0..4 {
+                let shared = shared.clone();
+                let expected = &expected;
+                scope.spawn(move || {
+                    let key = shared.read().expect("read failed");
+                    assert_eq!(key.as_slice(), expected.as_slice());
+                });
+            }
+        });
+
+        shared
+            .with_readwrite(|key| key.fill(1))
+            .expect("write failed");
+        assert_eq!(shared.read().expect("read failed").as_slice(), &[1; 32]);
+
+        // a panicking writer restores protection, but poisons the lock
+        let writer = shared.clone();
+        std::thread::spawn(move || writer.with_readwrite(|_| panic!("oops")))
+            .join()
+            .expect_err("should panic");
+        assert!(shared.read().is_err());
+    }
+
     #[test]
     fn test_allocator() {
         let mut vec: Vec<i32, _> = Vec::new_in(PageAlignedAllocator);
@@ -1498,6 +3171,69 @@ mod tests {
         assert_eq!([1, 2, 3, 0, 1], vec.as_slice());
     }
 
+    #[test]
+    fn test_canary() {
+        let mut bytes = HeapBytes::new_bytes();
+        bytes.resize(100, 1);
+        bytes.verify_canary().expect("canary overwritten");
+        let array = HeapByteArray::<32>::gen();
+        array.verify_canary().expect("canary overwritten");
+
+        let corruptions = heap_corruptions();
+        let capacity = bytes.0.capacity();
+        unsafe { *bytes.as_mut_slice().as_mut_ptr().add(capacity) ^= 1 };
+        assert!(bytes.verify_canary().is_err());
+        drop(bytes);
+        assert!(heap_corruptions() > corruptions);
+    }
+
+    #[test]
+    fn test_resize_wipes() {
+        let mut bytes = HeapBytes::new_bytes();
+        bytes.resize(64, 0xff);
+        bytes.resize(16, 0);
+        assert_eq!(bytes.as_slice(), [0xff; 16]);
+        // the truncated bytes remain allocated, but have been wiped
+        let allocated = unsafe { std::slice::from_raw_parts(bytes.as_slice().as_ptr(), 64) };
+        assert_eq!(&allocated[16..], [0; 48]);
+
+        bytes.resize(100_000, 1);
+        assert_eq!(&bytes.as_slice()[..16], [0xff; 16]);
+        assert_eq!(&bytes.as_slice()[16..], vec![1; 99_984]);
+    }
+
+    #[test]
+    fn test_error_propagation() {
+        fn lock_and_protect() -> Result<LockedRO<HeapBytes>, crate::error::Error> {
+            let mut locked = HeapBytes::from_slice_into_locked(b"some bytes")?;
+            locked.as_mut_slice()[0] = b'S';
+            let unlocked = locked.munlock()?;
+            let locked = unlocked.mlock()?;
+            locked.mprotect_readonly()
+        }
+
+        let readonly = lock_and_protect().expect("lock and protect failed");
+        assert_eq!(readonly.as_slice(), b"Some bytes");
+    }
+
+    #[test]
+    fn test_decode_into_locked() {
+        let key = Locked::<HeapByteArray<8>>::from_hex("0001020304050607").expect("hex failed");
+        assert_eq!(key.as_slice(), &[0, 1, 2, 3, 4, 5, 6, 7]);
+        let key = Locked::<HeapByteArray<8>>::from_base64("AAECAwQFBgc").expect("base64 failed");
+        assert_eq!(key.as_slice(), &[0, 1, 2, 3, 4, 5, 6, 7]);
+
+        assert!(Locked::<HeapByteArray<8>>::from_hex("00010203").is_err());
+        assert!(Locked::<HeapByteArray<8>>::from_hex("000102030405060z").is_err());
+        assert!(Locked::<HeapByteArray<8>>::from_base64("AAECAwQFBg==").is_err());
+
+        let bytes = LockedBytes::from_hex("cafe").expect("hex failed");
+        assert_eq!(bytes.as_slice(), &[0xca, 0xfe]);
+        let bytes = LockedBytes::from_base64("yv4=").expect("base64 failed");
+        assert_eq!(bytes.as_slice(), &[0xca, 0xfe]);
+        assert!(LockedBytes::from_hex("caf").is_err());
+    }
+
     // #[test]
     // fn test_crash() {
     //     use crate::protected::*;
@@ -1512,4 +3248,165 @@ mod tests {
     //         ptr::write(readonly_locked.as_slice().as_ptr() as *mut u8, 0) //
     // <- crash happens here     };
     // }
+
+    #[test]
+    fn test_capabilities() {
+        let caps = capabilities();
+        assert!(caps.page_size > 0);
+        assert_eq!(caps.can_lock(1), caps.require(1).is_ok());
+
+        let caps = Capabilities {
+            page_size: 4096,
+            memlock_limit: Some(65536),
+            memlock_hard_limit: Some(65536),
+            cap_ipc_lock: Some(false),
+            cgroup_memory_limit: None,
+            mlock_probe: true,
+        };
+        assert!(caps.can_lock(65536));
+        assert!(!caps.can_lock(65537));
+        assert!(caps.advice(65537)[0].contains("hard limit of 65536 bytes"));
+        let err = caps.require(usize::MAX).expect_err("should fail");
+        assert!(err.to_string().contains("unable to lock"));
+
+        let caps = Capabilities {
+            memlock_hard_limit: None,
+            ..caps
+        };
+        assert!(caps.advice(65537)[0].contains("ulimit -l"));
+
+        let caps = Capabilities {
+            cap_ipc_lock: Some(true),
+            cgroup_memory_limit: Some(1 << 20),
+            ..caps
+        };
+        assert!(caps.can_lock(1 << 20));
+        assert!(!caps.can_lock((1 << 20) + 1));
+
+        let caps = Capabilities {
+            mlock_probe: false,
+            ..caps
+        };
+        assert!(!caps.can_lock(1));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_capabilities_parsing() {
+        assert_eq!(
+            parse_cap_ipc_lock("Name:\tdryoc\nCapEff:\t0000003fffffffff\n"),
+            Some(true)
+        );
+        assert_eq!(
+            parse_cap_ipc_lock("CapEff:\t0000000000000000\n"),
+            Some(false)
+        );
+        assert_eq!(parse_cap_ipc_lock("Name:\tdryoc\n"), None);
+        assert_eq!(parse_cgroup_limit("max\n"), None);
+        assert_eq!(parse_cgroup_limit("1048576\n"), Some(1048576));
+        assert_eq!(parse_cgroup_limit("9223372036854771712\n"), None);
+        assert_eq!(
+            parse_locked_memory("Name:\tdryoc\nVmLck:\t      12 kB\n"),
+            Some(12288)
+        );
+        assert_eq!(parse_locked_memory("Name:\tdryoc\n"), None);
+    }
+
+    /// Held by tests which change the global allocator settings, so that they
+    /// don't change them while another such test is running. Other tests are
+    /// unaffected by the settings, apart from where their memory comes from.
+    static GLOBAL_SETTINGS: std::sync::Mutex<()> = std::sync::Mutex::new(());
+
+    #[test]
+    fn test_lock_policy() {
+        assert_eq!(lock_policy(), LockPolicy::Require);
+
+        let secret = || {
+            let mut secret = HeapBytes::new_bytes();
+            secret.resize(6, 0);
+            secret.copy_from_slice(b"secret");
+            secret
+        };
+
+        let locked = secret()
+            .mlock_with_policy(LockPolicy::Require)
+            .expect("lock failed");
+        assert!(locked.is_locked());
+        let unlocked = secret()
+            .mlock_with_policy(LockPolicy::Disabled)
+            .expect("lock failed");
+        assert!(!unlocked.is_locked());
+        assert_eq!(unlocked.as_slice(), b"secret");
+
+        // exceed the limit, if there is one and it's small enough to allocate
+        // more than it
+        if let Some(remaining) = lockable_memory_remaining().filter(|r| *r <= 64 << 20) {
+            let mut too_big = HeapBytes::new_bytes();
+            too_big.resize(remaining as usize + 2 * *PAGESIZE, 0);
+            let too_big = too_big
+                .mlock_with_policy(LockPolicy::BestEffort)
+                .expect("best effort lock failed");
+            assert!(!too_big.is_locked());
+            let mut too_big = HeapBytes::new_bytes();
+            too_big.resize(remaining as usize + 2 * *PAGESIZE, 0);
+            assert!(too_big.mlock_with_policy(LockPolicy::Require).is_err());
+        }
+    }
+
+    #[test]
+    fn test_memfd_secret_backend() {
+        let _settings = GLOBAL_SETTINGS
+            .lock()
+            .unwrap_or_else(|err| err.into_inner());
+        let previous = allocator_backend();
+        set_allocator_backend(AllocatorBackend::MemfdSecret);
+        assert_eq!(allocator_backend(), AllocatorBackend::MemfdSecret);
+
+        // falls back to the heap if memfd_secret() is unavailable
+        let mut locked = HeapBytes::from_slice_into_locked(b"secret").expect("lock failed");
+        locked.resize(64, 1);
+        assert_eq!(&locked.as_slice()[..6], b"secret");
+        let readonly = locked.mprotect_readonly().expect("mprotect failed");
+        assert_eq!(readonly.len(), 64);
+        drop(readonly);
+
+        set_allocator_backend(previous);
+        assert_eq!(allocator_backend(), previous);
+        #[cfg(not(target_os = "linux"))]
+        assert!(!memfd_secret_available());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_allocation_policy() {
+        let settings = GLOBAL_SETTINGS
+            .lock()
+            .unwrap_or_else(|err| err.into_inner());
+        let previous = allocation_policy();
+        let policy = AllocationPolicy::default().with_wipe_on_fork(true);
+        assert!(policy.dont_dump() && policy.wipe_on_fork());
+        set_allocation_policy(policy);
+        assert_eq!(allocation_policy(), policy);
+        let wiped = HeapBytes::from_slice_into_locked(b"secret").expect("lock failed");
+        set_allocation_policy(previous);
+        let kept = HeapBytes::from_slice_into_locked(b"secret").expect("lock failed");
+        drop(settings);
+
+        // secret memory is always shared with children, so it's not wiped
+        let shared = secretmem::contains(wiped.as_slice());
+        let pid = unsafe { libc::fork() };
+        if pid == 0 {
+            let ok = (shared || wiped.as_slice() == [0u8; 6]) && kept.as_slice() == b"secret";
+            // the wiped canary isn't mistaken for heap corruption
+            let corruptions = heap_corruptions();
+            drop(wiped);
+            let ok = ok && heap_corruptions() == corruptions;
+            unsafe { libc::_exit(if ok { 0 } else { 1 }) };
+        }
+        assert!(pid > 0, "fork failed");
+        let mut status = 0;
+        unsafe { libc::waitpid(pid, &mut status, 0) };
+        assert!(libc::WIFEXITED(status) && libc::WEXITSTATUS(status) == 0);
+        assert_eq!(wiped.as_slice(), b"secret");
+    }
 }
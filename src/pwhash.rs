@@ -82,11 +82,44 @@
 //! // now you can use `keypair` with DryocBox
 //! ```
 //!
+//! ## Progress and cancellation
+//!
+//! Hashing with sensitive parameters can take several seconds. A [`Monitor`]
+//! reports progress as hashing proceeds, and aborts it when its
+//! [`CancellationToken`] is cancelled, such as from a UI thread.
+//!
+//! ```
+//! use dryoc::pwhash::*;
+//!
+//! let password = b"Out, out, brief candle!";
+//!
+//! let token = CancellationToken::new();
+//! let mut monitor = Monitor::new()
+//!     .with_progress(|progress| println!("{:.0}%", progress.fraction() * 100.0))
+//!     .with_cancellation(token.clone());
+//!
+//! let pwhash: VecPwHash = PwHash::hash_monitored(password, Config::interactive(), &mut monitor)
+//!     .expect("unable to hash");
+//! pwhash.verify(password).expect("verification failed");
+//!
+//! // Once cancelled, hashing stops at the next segment
+//! token.cancel();
+//! pwhash
+//!     .verify_monitored(password, &mut monitor)
+//!     .expect_err("hashing should have been cancelled");
+//! ```
+//!
 //! ## String-based encoding
 //!
 //! See [`PwHash::to_string()`] for an example of using the string-based
 //! encoding API, compatible with `crypto_pwhash_str*` functions.
 //!
+//! Stored hashes are decoded and compared in constant time. Because the
+//! parameters are stored alongside the hash, anyone who can modify stored
+//! hashes can also weaken their parameters; use
+//! [`PwHash::verify_with_params_pinned`] to reject hashes whose parameters
+//! differ from your policy.
+//!
 //! ## Additional resources
 //!
 //! * See <https://libsodium.gitbook.io/doc/password_hashing> for additional
@@ -94,6 +127,9 @@
 //! * Refer to the [protected] module for details on usage with protected
 //!   memory.
 
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 use subtle::ConstantTimeEq;
@@ -103,6 +139,7 @@ use crate::classic::crypto_pwhash;
 use crate::constants::*;
 use crate::error::Error;
 use crate::keypair;
+use crate::policy::Algorithms;
 use crate::rng::copy_randombytes;
 use crate::types::*;
 
@@ -162,6 +199,16 @@ impl Config {
         Self { opslimit, ..self }
     }
 
+    /// Returns the operations limit of this config.
+    pub fn opslimit(&self) -> u64 {
+        self.opslimit
+    }
+
+    /// Returns the memory limit of this config, in bytes.
+    pub fn memlimit(&self) -> usize {
+        self.memlimit
+    }
+
     /// Provides a password hash configuration for interactive hashing.
     pub fn interactive() -> Self {
         Self {
@@ -202,6 +249,125 @@ impl Default for Config {
     }
 }
 
+/// Progress of a password hash computation, as reported by a [`Monitor`].
+///
+/// Progress is counted in Argon2 segments: each pass over memory is made of
+/// 4 segments.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Progress {
+    /// Number of segments completed so far
+    pub completed: u32,
+    /// Total number of segments
+    pub total: u32,
+}
+
+impl Progress {
+    /// Returns the fraction of work completed, between 0 and 1.
+    pub fn fraction(&self) -> f64 {
+        if self.total == 0 {
+            1.0
+        } else {
+            f64::from(self.completed) / f64::from(self.total)
+        }
+    }
+}
+
+/// A token for cooperatively cancelling password hashing with a [`Monitor`].
+/// Clones share the same state, so one clone can be cancelled from another
+/// thread while hashing proceeds.
+#[derive(Clone, Debug, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancellationToken {
+    /// Returns a new, uncancelled token.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cancels this token, and any hashing it's monitoring.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+    }
+
+    /// Returns true if this token has been cancelled.
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+}
+
+type ProgressCallback<'a> = Box<dyn FnMut(Progress) + 'a>;
+
+/// Progress and cancellation hooks for password hashing, for use with
+/// [`PwHash::hash_monitored`], [`PwHash::hash_with_salt_monitored`], and
+/// [`PwHash::verify_monitored`].
+///
+/// The progress callback is called before hashing starts, and after each
+/// segment. Cancellation is checked at the same points, and returns an error
+/// from the hashing function.
+///
+/// Refer to [crate::pwhash] for sample usage.
+#[derive(Default)]
+pub struct Monitor<'a> {
+    on_progress: Option<ProgressCallback<'a>>,
+    cancellation: Option<CancellationToken>,
+}
+
+impl<'a> Monitor<'a> {
+    /// Returns a new monitor, without any hooks.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns this monitor with `callback`, which is called with the
+    /// progress of hashing.
+    #[must_use]
+    pub fn with_progress(self, callback: impl FnMut(Progress) + 'a) -> Self {
+        Self {
+            on_progress: Some(Box::new(callback)),
+            ..self
+        }
+    }
+
+    /// Returns this monitor with `token`, which cancels hashing when
+    /// cancelled.
+    #[must_use]
+    pub fn with_cancellation(self, token: CancellationToken) -> Self {
+        Self {
+            cancellation: Some(token),
+            ..self
+        }
+    }
+
+    fn hash(
+        &mut self,
+        output: &mut [u8],
+        password: &[u8],
+        salt: &[u8],
+        config: &Config,
+    ) -> Result<(), Error> {
+        crate::policy::require(Algorithms::ARGON2)?;
+        crypto_pwhash::crypto_pwhash_with_progress(
+            output,
+            password,
+            salt,
+            config.opslimit,
+            config.memlimit,
+            config.algorithm.clone(),
+            &mut |completed, total| {
+                if let Some(callback) = self.on_progress.as_mut() {
+                    callback(Progress { completed, total });
+                }
+                !self
+                    .cancellation
+                    .as_ref()
+                    .map_or(false, CancellationToken::is_cancelled)
+            },
+        )
+    }
+}
+
 #[cfg_attr(
     feature = "serde",
     derive(Zeroize, Clone, Debug, Serialize, Deserialize)
@@ -266,6 +432,16 @@ impl<Hash: NewBytes + ResizableBytes + Zeroize, Salt: NewBytes + ResizableBytes
     /// Hashes `password` with a random salt and `config`, returning
     /// the hash, salt, and config upon success.
     pub fn hash<Password: Bytes>(password: &Password, config: Config) -> Result<Self, Error> {
+        Self::hash_monitored(password, config, &mut Monitor::new())
+    }
+
+    /// Same as [`PwHash::hash`], but reports progress to and can be cancelled
+    /// with `monitor`.
+    pub fn hash_monitored<Password: Bytes>(
+        password: &Password,
+        config: Config,
+        monitor: &mut Monitor,
+    ) -> Result<Self, Error> {
         let mut hash = Hash::new_bytes();
         let mut salt = Salt::new_bytes();
 
@@ -274,13 +450,11 @@ impl<Hash: NewBytes + ResizableBytes + Zeroize, Salt: NewBytes + ResizableBytes
         salt.resize(config.salt_length, 0);
         copy_randombytes(salt.as_mut_slice());
 
-        crypto_pwhash::crypto_pwhash(
+        monitor.hash(
             hash.as_mut_slice(),
             password.as_slice(),
             salt.as_slice(),
-            config.opslimit,
-            config.memlimit,
-            config.algorithm.clone(),
+            &config,
         )?;
 
         Ok(Self { hash, salt, config })
@@ -351,7 +525,51 @@ impl<Hash: NewBytes + ResizableBytes + Zeroize, Salt: NewBytes + ResizableBytes
 impl<Hash: NewBytes + ResizableBytes + Zeroize, Salt: Bytes + Clone + Zeroize> PwHash<Hash, Salt> {
     /// Verifies that this hash, salt, and config is valid for `password`.
     pub fn verify<Password: Bytes>(&self, password: &Password) -> Result<(), Error> {
-        let computed = Self::hash_with_salt(password, self.salt.clone(), self.config.clone())?;
+        self.verify_monitored(password, &mut Monitor::new())
+    }
+
+    /// Verifies that this hash, salt, and config is valid for `password`, but
+    /// only if the config exactly matches `policy`. Returns an error without
+    /// hashing if it doesn't.
+    ///
+    /// Use this when verifying stored hashes which could have been tampered
+    /// with, to prevent an attacker from downgrading them to weaker
+    /// parameters. Hashes with outdated parameters must be rehashed upon
+    /// login, before the policy changes.
+    pub fn verify_with_params_pinned<Password: Bytes>(
+        &self,
+        password: &Password,
+        policy: &Config,
+    ) -> Result<(), Error> {
+        let config = &self.config;
+        if config.algorithm != policy.algorithm
+            || config.opslimit != policy.opslimit
+            || config.memlimit != policy.memlimit
+            || config.hash_length != policy.hash_length
+            || config.salt_length != policy.salt_length
+            || self.salt.as_slice().len() != policy.salt_length
+            || self.hash.as_slice().len() != policy.hash_length
+        {
+            return Err(dryoc_error!(
+                "password hash parameters don't match the policy"
+            ));
+        }
+        self.verify(password)
+    }
+
+    /// Same as [`PwHash::verify`], but reports progress to and can be
+    /// cancelled with `monitor`.
+    pub fn verify_monitored<Password: Bytes>(
+        &self,
+        password: &Password,
+        monitor: &mut Monitor,
+    ) -> Result<(), Error> {
+        let computed = Self::hash_with_salt_monitored(
+            password,
+            self.salt.clone(),
+            self.config.clone(),
+            monitor,
+        )?;
 
         if self
             .hash
@@ -372,18 +590,27 @@ impl<Hash: NewBytes + ResizableBytes + Zeroize, Salt: Bytes + Clone + Zeroize> P
         password: &Password,
         salt: Salt,
         config: Config,
+    ) -> Result<Self, Error> {
+        Self::hash_with_salt_monitored(password, salt, config, &mut Monitor::new())
+    }
+
+    /// Same as [`PwHash::hash_with_salt`], but reports progress to and can be
+    /// cancelled with `monitor`.
+    pub fn hash_with_salt_monitored<Password: Bytes>(
+        password: &Password,
+        salt: Salt,
+        config: Config,
+        monitor: &mut Monitor,
     ) -> Result<Self, Error> {
         let mut hash = Hash::new_bytes();
 
         hash.resize(config.hash_length, 0);
 
-        crypto_pwhash::crypto_pwhash(
+        monitor.hash(
             hash.as_mut_slice(),
             password.as_slice(),
             salt.as_slice(),
-            config.opslimit,
-            config.memlimit,
-            config.algorithm.clone(),
+            &config,
         )?;
 
         Ok(Self { hash, salt, config })
@@ -449,6 +676,8 @@ impl<Salt: Bytes + Zeroize> PwHash<Hash, Salt> {
         salt: Salt,
         config: Config,
     ) -> Result<keypair::KeyPair<PublicKey, SecretKey>, Error> {
+        crate::policy::require(Algorithms::ARGON2)?;
+
         let mut secret_key = SecretKey::new_byte_array();
 
         crypto_pwhash::crypto_pwhash(
@@ -466,6 +695,39 @@ impl<Salt: Bytes + Zeroize> PwHash<Hash, Salt> {
     }
 }
 
+/// Derives a keypair seed from `passphrase` and `salt` into `seed`, using
+/// Argon2id with `config`. Used by
+/// [`KeyPair::from_passphrase`](crate::keypair::KeyPair::from_passphrase).
+pub(crate) fn derive_seed(
+    seed: &mut [u8],
+    passphrase: &[u8],
+    salt: &[u8],
+    config: &Config,
+) -> Result<(), Error> {
+    crate::policy::require(Algorithms::ARGON2)?;
+    if !matches!(
+        config.algorithm,
+        crypto_pwhash::PasswordHashAlgorithm::Argon2id13
+    ) {
+        return Err(dryoc_error!("keypairs can only be derived with Argon2id"));
+    }
+    if salt.len() < CRYPTO_PWHASH_SALTBYTES {
+        return Err(dryoc_error!(format!(
+            "salt must be at least {} bytes, got {}",
+            CRYPTO_PWHASH_SALTBYTES,
+            salt.len()
+        )));
+    }
+    crypto_pwhash::crypto_pwhash(
+        seed,
+        passphrase,
+        salt,
+        config.opslimit,
+        config.memlimit,
+        config.algorithm.clone(),
+    )
+}
+
 impl PwHash<Hash, Salt> {
     /// Hashes `password` using default (interactive) config parameters,
     /// returning the `Vec<u8>`-based hash and salt, with config, upon success.
@@ -501,6 +763,45 @@ mod tests {
             .expect_err("verification should have failed");
     }
 
+    #[test]
+    fn test_monitor() {
+        let password = b"super secrit password";
+        let config = Config::interactive().with_opslimit(2).with_memlimit(8192);
+        let mut reports = vec![];
+        let token = CancellationToken::new();
+
+        let mut monitor = Monitor::new()
+            .with_progress(|progress| reports.push(progress))
+            .with_cancellation(token.clone());
+        let pwhash: VecPwHash =
+            PwHash::hash_monitored(password, config, &mut monitor).expect("unable to hash");
+        pwhash.verify(password).expect("verification failed");
+        drop(monitor);
+
+        assert_eq!(
+            reports.first(),
+            Some(&Progress {
+                completed: 0,
+                total: 8
+            })
+        );
+        assert_eq!(reports.last().map(Progress::fraction), Some(1.0));
+        assert_eq!(reports.len(), 9);
+
+        let cancel = token.clone();
+        let mut monitor = Monitor::new()
+            .with_progress(move |progress| {
+                if progress.completed == 3 {
+                    cancel.cancel()
+                }
+            })
+            .with_cancellation(token.clone());
+        pwhash
+            .verify_monitored(password, &mut monitor)
+            .expect_err("should have been cancelled");
+        assert!(token.is_cancelled());
+    }
+
     #[cfg(feature = "base64")]
     #[test]
     fn test_pwhash_str() {
@@ -518,6 +819,28 @@ mod tests {
             .expect_err("verification should have failed");
     }
 
+    #[test]
+    fn test_verify_with_params_pinned() {
+        let password = b"super secrit password";
+        let policy = Config::interactive().with_memlimit(8192).with_opslimit(2);
+
+        let pwhash: VecPwHash = PwHash::hash(password, policy.clone()).expect("unable to hash");
+        pwhash
+            .verify_with_params_pinned(password, &policy)
+            .expect("verification failed");
+        pwhash
+            .verify_with_params_pinned(b"invalid password", &policy)
+            .expect_err("verification should have failed");
+
+        // A downgraded hash is rejected, even with the right password
+        let downgraded: VecPwHash =
+            PwHash::hash(password, policy.clone().with_opslimit(1)).expect("unable to hash");
+        downgraded.verify(password).expect("verification failed");
+        downgraded
+            .verify_with_params_pinned(password, &policy)
+            .expect_err("downgraded hash should have been rejected");
+    }
+
     #[test]
     #[cfg(feature = "nightly")]
     fn test_protected() {
@@ -0,0 +1,148 @@
+//! # Redaction of secrets in human-readable serialization
+//!
+//! Types which hold secret keys, such as [`KeyPair`](crate::keypair::KeyPair),
+//! serialize their secrets in full, so that they can be stored and loaded
+//! again. That's a hazard when the same types end up in logs, debug dumps, or
+//! config files, which are usually written with a human-readable format like
+//! JSON.
+//!
+//! With redaction enabled, secret fields are replaced with [`REDACTED`] when
+//! serialized with a human-readable serializer (one for which
+//! [`Serializer::is_human_readable`] returns true). Binary serializers, such as
+//! bincode, are unaffected, and keep full fidelity. Redaction can be enabled
+//! for a single value by wrapping it in [`Redacted`], or for the whole process
+//! with [`set_redact_secrets`].
+//!
+//! With the `nightly` feature, locked [protected](crate::protected) memory is
+//! always treated as secret, and is redacted in the same way.
+//!
+//! Redacted output can't be deserialized.
+//!
+//! ## Example
+//!
+//! ```
+//! use dryoc::keypair::StackKeyPair;
+//! use dryoc::redact::*;
+//!
+//! let keypair = StackKeyPair::gen();
+//!
+//! // Secrets are included by default
+//! let json = serde_json::to_string(&keypair).expect("serialize failed");
+//! assert!(!json.contains(REDACTED));
+//!
+//! // But not when redacted
+//! let json = serde_json::to_string(&Redacted(&keypair)).expect("serialize failed");
+//! assert!(json.contains(REDACTED));
+//! assert!(serde_json::from_str::<StackKeyPair>(&json).is_err());
+//!
+//! // Binary serializers keep the secrets
+//! let bytes = bincode::serialize(&Redacted(&keypair)).expect("serialize failed");
+//! let decoded: StackKeyPair = bincode::deserialize(&bytes).expect("deserialize failed");
+//! assert_eq!(decoded, keypair);
+//! ```
+
+use std::cell::Cell;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use serde::{Serialize, Serializer};
+
+use crate::types::Bytes;
+
+/// Marker which replaces redacted secrets.
+pub const REDACTED: &str = "[redacted]";
+
+static REDACT_SECRETS: AtomicBool = AtomicBool::new(false);
+
+thread_local! {
+    static REDACTING: Cell<usize> = const { Cell::new(0) };
+}
+
+/// Enables or disables redaction of secrets for all human-readable
+/// serialization in this process.
+pub fn set_redact_secrets(enabled: bool) {
+    REDACT_SECRETS.store(enabled, Ordering::SeqCst);
+}
+
+/// Returns true if secrets are currently being redacted, either process-wide,
+/// or because a [`Redacted`] value is being serialized on this thread.
+pub fn redacting() -> bool {
+    REDACT_SECRETS.load(Ordering::SeqCst) || REDACTING.with(|depth| depth.get() > 0)
+}
+
+/// Wraps a value, so that its secrets are redacted when it's serialized with
+/// a human-readable serializer.
+///
+/// Refer to [crate::redact] for sample usage.
+#[derive(Clone, Copy, Debug)]
+pub struct Redacted<'a, T: ?Sized>(pub &'a T);
+
+struct DepthGuard;
+
+impl DepthGuard {
+    fn enter() -> Self {
+        REDACTING.with(|depth| depth.set(depth.get() + 1));
+        Self
+    }
+}
+
+impl Drop for DepthGuard {
+    fn drop(&mut self) {
+        REDACTING.with(|depth| depth.set(depth.get() - 1));
+    }
+}
+
+impl<T: Serialize + ?Sized> Serialize for Redacted<'_, T> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let _guard = DepthGuard::enter();
+        self.0.serialize(serializer)
+    }
+}
+
+/// Serializes a secret `value` as bytes, or as [`REDACTED`] when redacting
+/// with a human-readable serializer. Used with `#[serde(serialize_with)]` on
+/// secret fields.
+pub(crate) fn serialize_secret<S, T>(value: &T, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+    T: Bytes + ?Sized,
+{
+    if serializer.is_human_readable() && redacting() {
+        serializer.serialize_str(REDACTED)
+    } else {
+        serializer.serialize_bytes(value.as_slice())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keypair::StackKeyPair;
+    use crate::kx::Session;
+    use crate::sign::SigningKeyPair;
+
+    #[test]
+    fn test_redacted() {
+        let keypair = SigningKeyPair::gen_with_defaults();
+        let json = serde_json::to_value(Redacted(&keypair)).expect("serialize failed");
+        assert_eq!(json["secret_key"], REDACTED);
+        assert_eq!(
+            json["public_key"],
+            serde_json::to_value(&keypair.public_key).expect("serialize failed")
+        );
+        // only while wrapped
+        assert!(!redacting());
+        let json = serde_json::to_value(&keypair).expect("serialize failed");
+        assert_ne!(json["secret_key"], REDACTED);
+
+        let client = StackKeyPair::gen();
+        let server = StackKeyPair::gen();
+        let session =
+            Session::new_client_with_defaults(&client, &server.public_key).expect("session failed");
+        let nested = serde_json::to_value(Redacted(&vec![session])).expect("serialize failed");
+        assert_eq!(nested[0]["rx_key"], REDACTED);
+        assert_eq!(nested[0]["tx_key"], REDACTED);
+    }
+}
@@ -0,0 +1,519 @@
+//! # Proxy re-encryption
+//!
+//! Proxy re-encryption lets the recipient of an encrypted data key (the
+//! delegator) share it with another recipient (the delegatee) through a
+//! semi-trusted proxy, such as a storage server. The delegator issues a
+//! [`ReencryptionGrant`] to the proxy, which the proxy uses to transform data
+//! keys encrypted for the delegator into data keys the delegatee can decrypt.
+//! The proxy never learns the data key, and can't decrypt the data keys it
+//! transforms.
+//!
+//! The scheme is a single-hop, unidirectional variant of Umbral over
+//! ristretto255. A data key is encrypted under a key derived from `r·A`,
+//! where `A` is the delegator's public key and `R = r·G` is stored alongside
+//! the ciphertext. To grant access, the delegator picks an ephemeral `x`,
+//! derives `d` from the Diffie-Hellman shared secret `x·B` with the
+//! delegatee's public key `B`, and gives the proxy the re-encryption key
+//! `rk = a/d` along with `X = x·G`. The proxy replaces `R` with `rk·R`, and
+//! the delegatee recovers `r·A = d·rk·R` by deriving `d` from `b·X`.
+//!
+//! Data keys are encrypted with XChaCha20-Poly1305, so a proxy which
+//! misbehaves (or uses the wrong grant) produces a data key which fails to
+//! decrypt, rather than the wrong key.
+//!
+//! A few limitations to keep in mind:
+//!
+//! * A proxy which colludes with the delegatee can recover the delegator's
+//!   secret key from the grant. Only grant access to delegatees you'd trust
+//!   with the proxy's cooperation.
+//! * Grants can't be revoked cryptographically: the proxy must delete them.
+//! * Re-encrypted data keys can't be re-encrypted again.
+//!
+//! ## Example
+//!
+//! ```
+//! use dryoc::reencrypt::*;
+//!
+//! let alice = ReencryptionKeyPair::gen();
+//! let bob = ReencryptionKeyPair::gen();
+//!
+//! // Encrypt a data key for Alice
+//! let data_key = b"a 32 byte data key for a payload";
+//! let encrypted = EncryptedDataKey::encrypt(data_key, alice.public_key()).expect("encrypt");
+//!
+//! // Alice grants Bob access, and gives the grant to the proxy
+//! let grant = alice.grant(bob.public_key()).expect("grant");
+//!
+//! // The proxy transforms the data key for Bob, without learning it
+//! let for_bob = grant.reencrypt(&encrypted).expect("reencrypt");
+//! assert_eq!(bob.decrypt(&for_bob).expect("decrypt"), data_key);
+//!
+//! // Alice can still decrypt the original, but not the transformed key
+//! assert_eq!(alice.decrypt(&encrypted).expect("decrypt"), data_key);
+//! assert!(alice.decrypt(&for_bob).is_err());
+//! ```
+use curve25519_dalek::constants::RISTRETTO_BASEPOINT_TABLE;
+use curve25519_dalek::ristretto::RistrettoPoint;
+use curve25519_dalek::scalar::Scalar;
+use curve25519_dalek::traits::IsIdentity;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha512};
+use zeroize::{Zeroize, ZeroizeOnDrop};
+
+use crate::aead::{xchacha20poly1305_ietf_decrypt, xchacha20poly1305_ietf_encrypt};
+use crate::constants::CRYPTO_AEAD_XCHACHA20POLY1305_IETF_NPUBBYTES;
+use crate::error::Error;
+use crate::ristretto_util::{decode_element, decode_scalar, random_scalar};
+use crate::rng::copy_randombytes;
+use crate::types::*;
+
+/// Length of a public key, an encoded ristretto255 group element.
+pub const REENCRYPT_PUBLICKEYBYTES: usize = 32;
+/// Length of a secret key, an encoded ristretto255 scalar.
+pub const REENCRYPT_SECRETKEYBYTES: usize = 32;
+
+const DST_KEY: &[u8] = b"dryoc-reencrypt-v1 Key";
+const DST_DELEGATION: &[u8] = b"dryoc-reencrypt-v1 Delegation";
+
+/// Public key, an encoded ristretto255 group element.
+pub type PublicKey = StackByteArray<REENCRYPT_PUBLICKEYBYTES>;
+/// Secret key, an encoded ristretto255 scalar.
+pub type SecretKey = StackByteArray<REENCRYPT_SECRETKEYBYTES>;
+/// Encoded ristretto255 group element.
+pub type Element = StackByteArray<32>;
+/// Nonce for the encrypted data key.
+pub type Nonce = StackByteArray<CRYPTO_AEAD_XCHACHA20POLY1305_IETF_NPUBBYTES>;
+
+fn delegation_scalar(
+    delegation_element: &[u8; 32],
+    delegatee: &[u8; 32],
+    shared: &RistrettoPoint,
+) -> Scalar {
+    let mut shared = shared.compress().to_bytes();
+    let mut hash: [u8; 64] = Sha512::new()
+        .chain_update(DST_DELEGATION)
+        .chain_update(delegation_element)
+        .chain_update(delegatee)
+        .chain_update(shared)
+        .finalize()
+        .into();
+    let scalar = Scalar::from_bytes_mod_order_wide(&hash);
+    shared.zeroize();
+    hash.zeroize();
+    scalar
+}
+
+fn derive_key(ephemeral: &[u8; 32], recipient: &[u8; 32], shared: &RistrettoPoint) -> [u8; 32] {
+    let mut shared = shared.compress().to_bytes();
+    let mut hash: [u8; 64] = Sha512::new()
+        .chain_update(DST_KEY)
+        .chain_update(ephemeral)
+        .chain_update(recipient)
+        .chain_update(shared)
+        .finalize()
+        .into();
+    let mut key = [0u8; 32];
+    key.copy_from_slice(&hash[..32]);
+    shared.zeroize();
+    hash.zeroize();
+    key
+}
+
+fn associated_data(ephemeral: &[u8; 32], recipient: &[u8; 32]) -> [u8; 64] {
+    let mut aad = [0u8; 64];
+    aad[..32].copy_from_slice(ephemeral);
+    aad[32..].copy_from_slice(recipient);
+    aad
+}
+
+#[cfg_attr(
+    feature = "serde",
+    derive(Zeroize, ZeroizeOnDrop, Clone, Serialize, Deserialize)
+)]
+#[cfg_attr(not(feature = "serde"), derive(Zeroize, ZeroizeOnDrop, Clone))]
+/// A keypair for receiving encrypted data keys, and granting others access
+/// to them.
+pub struct ReencryptionKeyPair {
+    public_key: PublicKey,
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::redact::serialize_secret")
+    )]
+    secret_key: SecretKey,
+}
+
+impl ReencryptionKeyPair {
+    /// Generates a new random keypair.
+    pub fn gen() -> Self {
+        Self::from_secret_scalar(random_scalar())
+    }
+
+    /// Loads a keypair from `secret_key`. Returns an error if the secret key
+    /// isn't a canonical, non-zero scalar.
+    pub fn from_secret_key<SK: ByteArray<REENCRYPT_SECRETKEYBYTES>>(
+        secret_key: &SK,
+    ) -> Result<Self, Error> {
+        let secret = decode_scalar(secret_key.as_array())?;
+        if secret == Scalar::ZERO {
+            return Err(dryoc_error!("invalid secret key"));
+        }
+        Ok(Self::from_secret_scalar(secret))
+    }
+
+    fn from_secret_scalar(mut secret: Scalar) -> Self {
+        let keypair = Self {
+            public_key: PublicKey::from(
+                (RISTRETTO_BASEPOINT_TABLE * &secret).compress().to_bytes(),
+            ),
+            secret_key: SecretKey::from(secret.to_bytes()),
+        };
+        secret.zeroize();
+        keypair
+    }
+
+    /// Returns the public key, which data keys are encrypted for.
+    pub fn public_key(&self) -> &PublicKey {
+        &self.public_key
+    }
+
+    /// Returns the secret key.
+    pub fn secret_key(&self) -> &SecretKey {
+        &self.secret_key
+    }
+
+    /// Returns a grant which lets a proxy re-encrypt data keys encrypted for
+    /// this keypair, so that the holder of `delegatee` can decrypt them.
+    pub fn grant<PK: ByteArray<REENCRYPT_PUBLICKEYBYTES>>(
+        &self,
+        delegatee: &PK,
+    ) -> Result<ReencryptionGrant, Error> {
+        if delegatee.as_array() == self.public_key.as_array() {
+            return Err(dryoc_error!("can't grant access to the same keypair"));
+        }
+        let delegatee_point = decode_element(delegatee.as_array())?;
+        let mut ephemeral = random_scalar();
+        let delegation_element = (RISTRETTO_BASEPOINT_TABLE * &ephemeral)
+            .compress()
+            .to_bytes();
+        let mut d = delegation_scalar(
+            &delegation_element,
+            delegatee.as_array(),
+            &(ephemeral * delegatee_point),
+        );
+        let mut secret = decode_scalar(self.secret_key.as_array())?;
+        let mut rekey = secret * d.invert();
+        let grant = ReencryptionGrant {
+            delegator: self.public_key.clone(),
+            delegatee: PublicKey::from(*delegatee.as_array()),
+            delegation_element: Element::from(delegation_element),
+            rekey: SecretKey::from(rekey.to_bytes()),
+        };
+        ephemeral.zeroize();
+        d.zeroize();
+        secret.zeroize();
+        rekey.zeroize();
+        Ok(grant)
+    }
+
+    /// Decrypts `encrypted`, which must have been encrypted for this keypair,
+    /// or re-encrypted for it with a grant. Returns the data key.
+    pub fn decrypt(&self, encrypted: &EncryptedDataKey) -> Result<Vec<u8>, Error> {
+        let mut secret = decode_scalar(self.secret_key.as_array())?;
+        let shared = match &encrypted.reencryption {
+            None => {
+                if encrypted.recipient != self.public_key {
+                    secret.zeroize();
+                    return Err(dryoc_error!("data key is encrypted for another recipient"));
+                }
+                secret * decode_element(encrypted.ephemeral.as_array())?
+            }
+            Some(reencryption) => {
+                if reencryption.delegatee != self.public_key {
+                    secret.zeroize();
+                    return Err(dryoc_error!(
+                        "data key is re-encrypted for another recipient"
+                    ));
+                }
+                let delegation_element =
+                    decode_element(reencryption.delegation_element.as_array())?;
+                let mut d = delegation_scalar(
+                    reencryption.delegation_element.as_array(),
+                    self.public_key.as_array(),
+                    &(secret * delegation_element),
+                );
+                let shared = d * decode_element(reencryption.element.as_array())?;
+                d.zeroize();
+                shared
+            }
+        };
+        secret.zeroize();
+        if shared.is_identity() {
+            return Err(dryoc_error!("invalid encrypted data key"));
+        }
+
+        let mut key = derive_key(
+            encrypted.ephemeral.as_array(),
+            encrypted.recipient.as_array(),
+            &shared,
+        );
+        let data_key = xchacha20poly1305_ietf_decrypt(
+            &encrypted.ciphertext,
+            &associated_data(
+                encrypted.ephemeral.as_array(),
+                encrypted.recipient.as_array(),
+            ),
+            encrypted.nonce.as_array(),
+            &key,
+        );
+        key.zeroize();
+        data_key
+    }
+}
+
+#[cfg_attr(
+    feature = "serde",
+    derive(Zeroize, ZeroizeOnDrop, Clone, Serialize, Deserialize)
+)]
+#[cfg_attr(not(feature = "serde"), derive(Zeroize, ZeroizeOnDrop, Clone))]
+/// A grant from a delegator to a delegatee, held by the proxy, which
+/// re-encrypts data keys encrypted for the delegator.
+pub struct ReencryptionGrant {
+    delegator: PublicKey,
+    delegatee: PublicKey,
+    delegation_element: Element,
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::redact::serialize_secret")
+    )]
+    rekey: SecretKey,
+}
+
+impl ReencryptionGrant {
+    /// Returns the delegator's public key.
+    pub fn delegator(&self) -> &PublicKey {
+        &self.delegator
+    }
+
+    /// Returns the delegatee's public key.
+    pub fn delegatee(&self) -> &PublicKey {
+        &self.delegatee
+    }
+
+    /// Re-encrypts `encrypted`, which must be encrypted for the delegator, so
+    /// the delegatee can decrypt it.
+    pub fn reencrypt(&self, encrypted: &EncryptedDataKey) -> Result<EncryptedDataKey, Error> {
+        if encrypted.reencryption.is_some() {
+            return Err(dryoc_error!("data key has already been re-encrypted"));
+        }
+        if encrypted.recipient != self.delegator {
+            return Err(dryoc_error!("data key is encrypted for another recipient"));
+        }
+        let mut rekey = decode_scalar(self.rekey.as_array())?;
+        let element = rekey * decode_element(encrypted.ephemeral.as_array())?;
+        rekey.zeroize();
+
+        let mut reencrypted = encrypted.clone();
+        reencrypted.reencryption = Some(Reencryption {
+            element: Element::from(element.compress().to_bytes()),
+            delegation_element: self.delegation_element.clone(),
+            delegatee: self.delegatee.clone(),
+        });
+        Ok(reencrypted)
+    }
+}
+
+#[cfg_attr(
+    feature = "serde",
+    derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)
+)]
+#[cfg_attr(not(feature = "serde"), derive(Clone, Debug, PartialEq, Eq))]
+struct Reencryption {
+    element: Element,
+    delegation_element: Element,
+    delegatee: PublicKey,
+}
+
+#[cfg_attr(
+    feature = "serde",
+    derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)
+)]
+#[cfg_attr(not(feature = "serde"), derive(Clone, Debug, PartialEq, Eq))]
+/// A data key encrypted for a recipient, which may have been re-encrypted
+/// for a delegatee.
+///
+/// Refer to [crate::reencrypt] for sample usage.
+pub struct EncryptedDataKey {
+    recipient: PublicKey,
+    ephemeral: Element,
+    nonce: Nonce,
+    ciphertext: Vec<u8>,
+    reencryption: Option<Reencryption>,
+}
+
+impl EncryptedDataKey {
+    /// Encrypts `data_key` for the holder of `recipient`.
+    pub fn encrypt<DK: Bytes + ?Sized, PK: ByteArray<REENCRYPT_PUBLICKEYBYTES>>(
+        data_key: &DK,
+        recipient: &PK,
+    ) -> Result<Self, Error> {
+        let recipient_point = decode_element(recipient.as_array())?;
+        let mut r = random_scalar();
+        let ephemeral = (RISTRETTO_BASEPOINT_TABLE * &r).compress().to_bytes();
+        let shared = r * recipient_point;
+        r.zeroize();
+
+        let mut key = derive_key(&ephemeral, recipient.as_array(), &shared);
+        let mut nonce = Nonce::default();
+        copy_randombytes(nonce.as_mut_slice());
+        let ciphertext = xchacha20poly1305_ietf_encrypt(
+            data_key.as_slice(),
+            &associated_data(&ephemeral, recipient.as_array()),
+            nonce.as_array(),
+            &key,
+        );
+        key.zeroize();
+
+        Ok(Self {
+            recipient: PublicKey::from(*recipient.as_array()),
+            ephemeral: Element::from(ephemeral),
+            nonce,
+            ciphertext,
+            reencryption: None,
+        })
+    }
+
+    /// Returns the public key of the recipient the data key was originally
+    /// encrypted for.
+    pub fn recipient(&self) -> &PublicKey {
+        &self.recipient
+    }
+
+    /// Returns the public key of the delegatee, if the data key has been
+    /// re-encrypted.
+    pub fn delegatee(&self) -> Option<&PublicKey> {
+        self.reencryption
+            .as_ref()
+            .map(|reencryption| &reencryption.delegatee)
+    }
+
+    /// Serializes this encrypted data key into a new [`Vec`], as: recipient
+    /// (32) ‖ ephemeral element (32) ‖ nonce (24) ‖ flag (1), then if the
+    /// flag is 1, re-encrypted element (32) ‖ delegation element (32) ‖
+    /// delegatee (32); then the ciphertext.
+    pub fn to_vec(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(185 + self.ciphertext.len());
+        bytes.extend_from_slice(self.recipient.as_slice());
+        bytes.extend_from_slice(self.ephemeral.as_slice());
+        bytes.extend_from_slice(self.nonce.as_slice());
+        match &self.reencryption {
+            None => bytes.push(0),
+            Some(reencryption) => {
+                bytes.push(1);
+                bytes.extend_from_slice(reencryption.element.as_slice());
+                bytes.extend_from_slice(reencryption.delegation_element.as_slice());
+                bytes.extend_from_slice(reencryption.delegatee.as_slice());
+            }
+        }
+        bytes.extend_from_slice(&self.ciphertext);
+        bytes
+    }
+
+    /// Parses an encrypted data key from `bytes`, as produced by
+    /// [`EncryptedDataKey::to_vec`].
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        const HEADER: usize = 32 + 32 + CRYPTO_AEAD_XCHACHA20POLY1305_IETF_NPUBBYTES + 1;
+        if bytes.len() < HEADER {
+            return Err(dryoc_error!("encrypted data key is too short"));
+        }
+        let recipient = PublicKey::try_from(&bytes[..32])?;
+        let ephemeral = Element::try_from(&bytes[32..64])?;
+        let nonce = Nonce::try_from(&bytes[64..HEADER - 1])?;
+        let (reencryption, ciphertext) = match bytes[HEADER - 1] {
+            0 => (None, &bytes[HEADER..]),
+            1 if bytes.len() >= HEADER + 96 => (
+                Some(Reencryption {
+                    element: Element::try_from(&bytes[HEADER..HEADER + 32])?,
+                    delegation_element: Element::try_from(&bytes[HEADER + 32..HEADER + 64])?,
+                    delegatee: PublicKey::try_from(&bytes[HEADER + 64..HEADER + 96])?,
+                }),
+                &bytes[HEADER + 96..],
+            ),
+            _ => return Err(dryoc_error!("invalid encrypted data key")),
+        };
+        Ok(Self {
+            recipient,
+            ephemeral,
+            nonce,
+            ciphertext: ciphertext.to_vec(),
+            reencryption,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reencrypt() {
+        let alice = ReencryptionKeyPair::gen();
+        let bob = ReencryptionKeyPair::gen();
+        let carol = ReencryptionKeyPair::gen();
+        let data_key = crate::rng::randombytes_buf(32);
+
+        let encrypted = EncryptedDataKey::encrypt(&data_key, alice.public_key()).expect("encrypt");
+        assert_eq!(alice.decrypt(&encrypted).expect("decrypt"), data_key);
+        bob.decrypt(&encrypted).expect_err("bob can't decrypt");
+
+        let grant = alice.grant(bob.public_key()).expect("grant");
+        let for_bob = grant.reencrypt(&encrypted).expect("reencrypt");
+        assert_eq!(for_bob.delegatee(), Some(bob.public_key()));
+        assert_eq!(bob.decrypt(&for_bob).expect("decrypt"), data_key);
+        alice.decrypt(&for_bob).expect_err("alice can't decrypt");
+        carol.decrypt(&for_bob).expect_err("carol can't decrypt");
+
+        // single hop only, and only for the delegator's data keys
+        grant.reencrypt(&for_bob).expect_err("already re-encrypted");
+        let for_carol = EncryptedDataKey::encrypt(&data_key, carol.public_key()).expect("encrypt");
+        grant
+            .reencrypt(&for_carol)
+            .expect_err("wrong delegator should fail");
+
+        // a grant for carol doesn't let bob decrypt
+        let carol_grant = alice.grant(carol.public_key()).expect("grant");
+        let mut tampered = carol_grant.reencrypt(&encrypted).expect("reencrypt");
+        if let Some(reencryption) = tampered.reencryption.as_mut() {
+            reencryption.delegatee = bob.public_key().clone();
+        }
+        bob.decrypt(&tampered).expect_err("wrong grant should fail");
+    }
+
+    #[test]
+    fn test_serialization() {
+        let alice = ReencryptionKeyPair::gen();
+        let bob = ReencryptionKeyPair::gen();
+        let encrypted =
+            EncryptedDataKey::encrypt(b"data key", alice.public_key()).expect("encrypt");
+        let for_bob = alice
+            .grant(bob.public_key())
+            .expect("grant")
+            .reencrypt(&encrypted)
+            .expect("reencrypt");
+
+        for edk in [&encrypted, &for_bob] {
+            let parsed = EncryptedDataKey::from_bytes(&edk.to_vec()).expect("parse");
+            assert_eq!(&parsed, edk);
+        }
+        assert_eq!(
+            bob.decrypt(&EncryptedDataKey::from_bytes(&for_bob.to_vec()).unwrap())
+                .expect("decrypt"),
+            b"data key"
+        );
+
+        EncryptedDataKey::from_bytes(&for_bob.to_vec()[..100]).expect_err("truncated");
+        let keypair = ReencryptionKeyPair::from_secret_key(alice.secret_key()).expect("load");
+        assert_eq!(keypair.public_key(), alice.public_key());
+        assert!(alice.grant(alice.public_key()).is_err());
+    }
+}
@@ -0,0 +1,302 @@
+//! # Anti-replay windows
+//!
+//! [`WindowedReceiver`] accepts messages carrying explicit sequence numbers in
+//! any order, within a sliding window, and rejects replays, similar to the
+//! anti-replay windows of IPsec and DTLS. It complements
+//! [`DryocStream`](crate::dryocstream::DryocStream), which requires messages
+//! to arrive strictly in order, for protocols built on boxes or AEADs over
+//! unreliable transports, where messages may be dropped or reordered.
+//!
+//! The window tracks the highest sequence number received so far, and which of
+//! the `window_size` sequence numbers up to it have been received. Messages
+//! with a higher sequence number advance the window. Messages inside the
+//! window are accepted once each, and messages older than the window are
+//! rejected, because there's no record of whether they've been received.
+//! Sequence numbers inside the window which haven't been received yet are
+//! reported by [`WindowedReceiver::missing`].
+//!
+//! The sequence number must be authenticated along with the message, otherwise
+//! an attacker can replay a message under a different sequence number. Either
+//! include it in the associated data with [`sequence_aad`], or, with boxes,
+//! which don't take associated data, derive the nonce from it with
+//! [`sequence_nonce`]. Check the sequence number with
+//! [`WindowedReceiver::check`] before decrypting, and record it with
+//! [`WindowedReceiver::accept`] only after the message has been authenticated,
+//! so that forgeries can't advance the window.
+//!
+//! If the `serde` feature is enabled, the [`serde::Deserialize`] and
+//! [`serde::Serialize`] traits will be implemented for [`WindowedReceiver`],
+//! so that its state can be saved and restored across restarts.
+//!
+//! ## Example
+//!
+//! ```
+//! use dryoc::dryocsecretbox::{DryocSecretBox, Key, Nonce};
+//! use dryoc::replay::*;
+//! use dryoc::types::*;
+//!
+//! // Each direction must use its own key, because nonces are derived from
+//! // sequence numbers
+//! let key = Key::gen();
+//!
+//! let messages: Vec<(u64, DryocSecretBox<_, Vec<u8>>)> = (0..4)
+//!     .map(|seq| {
+//!         let nonce: Nonce = sequence_nonce(seq);
+//!         (
+//!             seq,
+//!             DryocSecretBox::encrypt_to_vecbox(b"hello", &nonce, &key),
+//!         )
+//!     })
+//!     .collect();
+//!
+//! let mut receiver = WindowedReceiver::new(64).expect("window failed");
+//! let mut receive = |(seq, message): &(u64, DryocSecretBox<_, Vec<u8>>)| {
+//!     receiver.check(*seq)?;
+//!     let nonce: Nonce = sequence_nonce(*seq);
+//!     let plaintext = message.decrypt_to_vec(&nonce, &key)?;
+//!     receiver.accept(*seq)?;
+//!     Ok::<_, dryoc::Error>(plaintext)
+//! };
+//!
+//! // Messages can arrive out of order, but each is only accepted once
+//! receive(&messages[2]).expect("receive failed");
+//! receive(&messages[0]).expect("receive failed");
+//! assert!(receive(&messages[2]).is_err());
+//! assert!(receive(&messages[0]).is_err());
+//! receive(&messages[1]).expect("receive failed");
+//! ```
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::constants::CRYPTO_BOX_NONCEBYTES;
+use crate::error::Error;
+use crate::types::*;
+
+/// Maximum size of a [`WindowedReceiver`]'s window.
+pub const REPLAY_MAX_WINDOW: u32 = 1 << 16;
+/// Length of the sequence number prefix added by [`sequence_aad`].
+pub const SEQUENCE_BYTES: usize = 8;
+
+/// Returns `associated_data` prefixed with `sequence`, as an 8 byte big-endian
+/// integer, for use as the associated data of an AEAD.
+pub fn sequence_aad(sequence: u64, associated_data: &[u8]) -> Vec<u8> {
+    let mut aad = Vec::with_capacity(SEQUENCE_BYTES + associated_data.len());
+    aad.extend_from_slice(&sequence.to_be_bytes());
+    aad.extend_from_slice(associated_data);
+    aad
+}
+
+/// Splits associated data produced by [`sequence_aad`] into its sequence
+/// number and the remaining associated data.
+pub fn split_sequence_aad(aad: &[u8]) -> Result<(u64, &[u8]), Error> {
+    if aad.len() < SEQUENCE_BYTES {
+        return Err(dryoc_error!("associated data is missing sequence number"));
+    }
+    let (sequence, rest) = aad.split_at(SEQUENCE_BYTES);
+    let sequence = u64::from_be_bytes(sequence.try_into().expect("sequence length"));
+    Ok((sequence, rest))
+}
+
+/// Returns a box nonce derived from `sequence`, as an 8 byte little-endian
+/// integer followed by zeros. Decryption then fails if the sequence number
+/// doesn't match the one the message was encrypted with.
+///
+/// Deterministic nonces are only safe if every sequence number is used at most
+/// once per key, so each direction of a conversation must use a different key,
+/// such as the rx and tx keys of a [`kx`](crate::kx) session.
+pub fn sequence_nonce<Nonce: NewByteArray<CRYPTO_BOX_NONCEBYTES>>(sequence: u64) -> Nonce {
+    let mut nonce = Nonce::new_byte_array();
+    nonce.as_mut_array()[..SEQUENCE_BYTES].copy_from_slice(&sequence.to_le_bytes());
+    nonce
+}
+
+#[cfg_attr(
+    feature = "serde",
+    derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)
+)]
+#[cfg_attr(not(feature = "serde"), derive(Clone, Debug, PartialEq, Eq))]
+/// A sliding window of received sequence numbers, which accepts messages out
+/// of order, and rejects replays.
+///
+/// Refer to [crate::replay] for details and sample usage.
+pub struct WindowedReceiver {
+    window_size: u32,
+    highest: Option<u64>,
+    /// One bit per sequence number, indexed by the sequence number modulo the
+    /// number of bits
+    received: Vec<u64>,
+}
+
+impl WindowedReceiver {
+    /// Returns a new receiver, which accepts sequence numbers up to
+    /// `window_size - 1` below the highest one received. `window_size` must
+    /// be between 1 and [`REPLAY_MAX_WINDOW`].
+    pub fn new(window_size: u32) -> Result<Self, Error> {
+        validate!(1, REPLAY_MAX_WINDOW, window_size, "window_size");
+        Ok(Self {
+            window_size,
+            highest: None,
+            received: vec![0; words(window_size)],
+        })
+    }
+
+    /// Returns the size of the window.
+    pub fn window_size(&self) -> u32 {
+        self.window_size
+    }
+
+    /// Returns the highest sequence number received, if any.
+    pub fn highest(&self) -> Option<u64> {
+        self.highest
+    }
+
+    /// Returns `false` if the state is inconsistent, such as after
+    /// deserializing tampered data.
+    fn is_valid(&self) -> bool {
+        (1..=REPLAY_MAX_WINDOW).contains(&self.window_size)
+            && self.received.len() == words(self.window_size)
+    }
+
+    fn bit(&self, sequence: u64) -> (usize, u64) {
+        let index = sequence % (self.received.len() as u64 * 64);
+        ((index / 64) as usize, 1 << (index % 64))
+    }
+
+    fn is_received(&self, sequence: u64) -> bool {
+        let (word, mask) = self.bit(sequence);
+        self.received[word] & mask != 0
+    }
+
+    /// Returns `Ok(())` if a message with `sequence` would be accepted, without
+    /// recording it. Returns an error if the sequence number has already been
+    /// received, or is older than the window.
+    pub fn check(&self, sequence: u64) -> Result<(), Error> {
+        if !self.is_valid() {
+            return Err(dryoc_error!("invalid window state"));
+        }
+        match self.highest {
+            Some(highest) if sequence <= highest => {
+                if highest - sequence >= self.window_size as u64 {
+                    Err(dryoc_error!(format!(
+                        "sequence number {} is older than the window",
+                        sequence
+                    )))
+                } else if self.is_received(sequence) {
+                    Err(dryoc_error!(format!(
+                        "sequence number {} has already been received",
+                        sequence
+                    )))
+                } else {
+                    Ok(())
+                }
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Records `sequence` as received, advancing the window if it's the
+    /// highest so far. Returns an error, without recording it, if it would be
+    /// rejected by [`WindowedReceiver::check`].
+    ///
+    /// Only call this after the message has been authenticated.
+    pub fn accept(&mut self, sequence: u64) -> Result<(), Error> {
+        self.check(sequence)?;
+        match self.highest {
+            Some(highest) if sequence <= highest => (),
+            Some(highest) if sequence - highest < self.received.len() as u64 * 64 => {
+                // clear the bits of the sequence numbers skipped over, which
+                // belonged to sequence numbers that have left the window
+                for skipped in highest + 1..sequence {
+                    let (word, mask) = self.bit(skipped);
+                    self.received[word] &= !mask;
+                }
+                self.highest = Some(sequence);
+            }
+            _ => {
+                self.received.iter_mut().for_each(|word| *word = 0);
+                self.highest = Some(sequence);
+            }
+        }
+        let (word, mask) = self.bit(sequence);
+        self.received[word] |= mask;
+        Ok(())
+    }
+
+    /// Returns the sequence numbers inside the window, below the highest one
+    /// received, which haven't been received, in ascending order. Sequences
+    /// are assumed to start at 0, so sequence numbers before the first one
+    /// received are reported too: after receiving 5 and then 3, the missing
+    /// sequence numbers are 0, 1, 2 and 4.
+    pub fn missing(&self) -> Vec<u64> {
+        match self.highest {
+            Some(highest) if self.is_valid() => {
+                let lowest = highest.saturating_sub(self.window_size as u64 - 1);
+                (lowest..highest)
+                    .filter(|sequence| !self.is_received(*sequence))
+                    .collect()
+            }
+            _ => Vec::new(),
+        }
+    }
+}
+
+fn words(window_size: u32) -> usize {
+    (window_size as usize + 63) / 64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_windowed_receiver() {
+        let mut receiver = WindowedReceiver::new(100).expect("window failed");
+        assert!(receiver.missing().is_empty());
+
+        receiver.accept(5).expect("accept failed");
+        receiver.accept(3).expect("accept failed");
+        assert!(receiver.accept(5).is_err());
+        assert!(receiver.accept(3).is_err());
+        assert_eq!(receiver.missing(), vec![0, 1, 2, 4]);
+
+        // Advancing past the end of the bitmap doesn't resurrect old bits
+        receiver.accept(130).expect("accept failed");
+        assert!(receiver.check(30).is_err());
+        receiver.accept(31).expect("accept failed");
+        assert!(receiver.accept(31).is_err());
+        assert_eq!(receiver.missing().len(), 98);
+        receiver.accept(131 + 128).expect("accept failed");
+        receiver.accept(131 + 127).expect("accept failed");
+        assert_eq!(receiver.highest(), Some(259));
+        receiver.accept(200).expect("accept failed");
+        assert!(receiver.check(159).is_err());
+        assert!(receiver.check(200).is_err());
+        assert!(receiver.check(160).is_ok());
+
+        #[cfg(feature = "serde")]
+        {
+            let json = serde_json::to_string(&receiver).expect("serialize failed");
+            let restored: WindowedReceiver =
+                serde_json::from_str(&json).expect("deserialize failed");
+            assert_eq!(restored, receiver);
+            assert!(restored.check(200).is_err());
+        }
+
+        assert!(WindowedReceiver::new(0).is_err());
+        assert!(WindowedReceiver::new(REPLAY_MAX_WINDOW + 1).is_err());
+    }
+
+    #[test]
+    fn test_sequence_aad() {
+        let aad = sequence_aad(42, b"header");
+        assert_eq!(
+            split_sequence_aad(&aad).expect("split failed"),
+            (42, &b"header"[..])
+        );
+        assert!(split_sequence_aad(b"short").is_err());
+
+        let nonce: crate::dryocbox::Nonce = sequence_nonce(1);
+        assert_ne!(nonce, sequence_nonce::<crate::dryocbox::Nonce>(2));
+    }
+}
@@ -0,0 +1,46 @@
+//! Helpers for scalars and group elements of ristretto255, shared by the
+//! protocols built on it, and not exposed as part of the public API.
+use curve25519_dalek::ristretto::{CompressedRistretto, RistrettoPoint};
+use curve25519_dalek::scalar::Scalar;
+use curve25519_dalek::traits::IsIdentity;
+use zeroize::Zeroize;
+
+use crate::error::Error;
+use crate::rng::copy_randombytes;
+use crate::types::*;
+
+/// Returns a uniformly random non-zero scalar, reduced from 64 random bytes.
+/// Zero is rejected because it would be an identity key or blind.
+pub(crate) fn random_scalar() -> Scalar {
+    loop {
+        let mut wide = [0u8; 64];
+        copy_randombytes(&mut wide);
+        let scalar = Scalar::from_bytes_mod_order_wide(&wide);
+        wide.zeroize();
+        if scalar != Scalar::ZERO {
+            return scalar;
+        }
+    }
+}
+
+/// Encodes a group element in its compressed form.
+pub(crate) fn encode_element(point: &RistrettoPoint) -> StackByteArray<32> {
+    StackByteArray::from(point.compress().to_bytes())
+}
+
+/// Decodes a compressed group element, rejecting invalid encodings and the
+/// identity.
+pub(crate) fn decode_element<E: ByteArray<32> + ?Sized>(
+    element: &E,
+) -> Result<RistrettoPoint, Error> {
+    CompressedRistretto(*element.as_array())
+        .decompress()
+        .filter(|point| !point.is_identity())
+        .ok_or_else(|| dryoc_error!("invalid group element"))
+}
+
+/// Decodes a scalar, rejecting encodings which aren't fully reduced.
+pub(crate) fn decode_scalar(scalar: &[u8; 32]) -> Result<Scalar, Error> {
+    Option::from(Scalar::from_canonical_bytes(*scalar))
+        .ok_or_else(|| dryoc_error!("invalid scalar"))
+}
@@ -1,17 +1,706 @@
-/// Provides random data up to `len` from the OS's random number generator.
-pub fn randombytes_buf(len: usize) -> Vec<u8> {
-    use rand_core::{OsRng, RngCore};
+//! By default, random data is provided by a thread-local buffered DRBG
+//! (deterministic random bit generator), which is seeded from the OS's random
+//! number generator. This avoids making a system call for every nonce or key
+//! generated, which dominates the cost of encrypting small messages.
+//!
+//! The DRBG is based on ChaCha20 with fast key erasure: each time its buffer is
+//! refilled, the key is replaced with the first block of keystream, so earlier
+//! outputs can't be recovered from the current state. Bytes are erased from
+//! the buffer as soon as they're handed out. The DRBG is reseeded from the OS
+//! after every [reseed interval](Config::with_reseed_interval) bytes of
+//! output, and whenever the process has forked.
+//!
+//! The source can be configured globally with [`set_config`], or for the
+//! current thread only with [`set_thread_config`]:
+//!
+//! ```
+//! use dryoc::rng::*;
+//!
+//! // Draw directly from the OS for every request on this thread
+//! set_thread_config(Some(Config::default().with_source(Source::Os)));
+//! let r = randombytes_buf(32);
+//! assert_eq!(r.len(), 32);
+//!
+//! // Revert to the global config
+//! set_thread_config(None);
+//! ```
+//!
+//! Reducing random bytes modulo `n` is biased towards smaller values, unless
+//! `n` is a power of two. Use [`uniform`] for unbiased integers in a range, and
+//! [`shuffle`] and [`sample`] for random permutations and selections, such as
+//! for mix networks or committee elections:
+//!
+//! ```
+//! use dryoc::rng::*;
+//!
+//! let roll = uniform(6) + 1;
+//! assert!((1..=6).contains(&roll));
+//!
+//! let mut members: Vec<u32> = (0..100).collect();
+//! shuffle(&mut members);
+//! let committee = sample(&members, 10).expect("sample failed");
+//! assert_eq!(committee.len(), 10);
+//! ```
+//!
+//! Random identifiers can be generated with [`token`], [`random_string`] and
+//! [`Uuid`], without additional dependencies:
+//!
+//! ```
+//! use dryoc::rng::*;
+//!
+//! // 32 URL-safe characters, with 192 bits of entropy
+//! let session_id = token(32);
+//! assert_eq!(session_id.len(), 32);
+//!
+//! let pin = random_string(6, b"0123456789").expect("invalid alphabet");
+//! assert!(pin.bytes().all(|c| c.is_ascii_digit()));
+//!
+//! // Time-ordered UUIDs are well suited to database keys
+//! let id = Uuid::new_v7();
+//! assert_eq!(id.version(), 7);
+//! assert_eq!(id.to_string().parse::<Uuid>().expect("parse failed"), id);
+//! ```
+use std::cell::{Cell, RefCell};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+
+use chacha20::cipher::{KeyIvInit, StreamCipher};
+use chacha20::{ChaCha20, Key, Nonce};
+use rand_core::{OsRng, RngCore};
+use zeroize::Zeroize;
+
+use crate::error::Error;
+
+/// Number of bytes of output buffered by the DRBG between refills.
+pub const DRBG_BUFFER_BYTES: usize = 512;
+/// Default number of bytes of DRBG output between reseeds from the OS.
+pub const DRBG_DEFAULT_RESEED_INTERVAL: u64 = 1 << 20;
+
+/// Source of random data, for use with [`Config`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Source {
+    /// Draws directly from the OS's random number generator, with one system
+    /// call per request.
+    Os,
+    /// Draws from a thread-local buffered DRBG, which is periodically reseeded
+    /// from the OS.
+    Drbg,
+}
+
+/// Random number generation configuration. Provides reasonable default values
+/// with [`Config::default()`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Config {
+    source: Source,
+    reseed_interval: u64,
+}
+
+impl Config {
+    /// Returns this config with `source`.
+    #[must_use]
+    pub fn with_source(self, source: Source) -> Self {
+        Self { source, ..self }
+    }
+
+    /// Returns this config with `reseed_interval`, the number of bytes the
+    /// DRBG may output before it's reseeded from the OS. An interval of 0
+    /// reseeds before every request.
+    #[must_use]
+    pub fn with_reseed_interval(self, reseed_interval: u64) -> Self {
+        Self {
+            reseed_interval,
+            ..self
+        }
+    }
+
+    /// Returns the source of random data.
+    pub fn source(&self) -> Source {
+        self.source
+    }
+
+    /// Returns the reseed interval, in bytes.
+    pub fn reseed_interval(&self) -> u64 {
+        self.reseed_interval
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            source: Source::Drbg,
+            reseed_interval: DRBG_DEFAULT_RESEED_INTERVAL,
+        }
+    }
+}
+
+static GLOBAL_USE_OS: AtomicBool = AtomicBool::new(false);
+static GLOBAL_RESEED_INTERVAL: AtomicU64 = AtomicU64::new(DRBG_DEFAULT_RESEED_INTERVAL);
+
+thread_local! {
+    static THREAD_CONFIG: Cell<Option<Config>> = const { Cell::new(None) };
+    static DRBG: RefCell<Option<Drbg>> = const { RefCell::new(None) };
+}
+
+/// Sets the global random number generation config, which applies to all
+/// threads without their own [thread config](set_thread_config).
+pub fn set_config(config: Config) {
+    GLOBAL_USE_OS.store(config.source == Source::Os, Ordering::Relaxed);
+    GLOBAL_RESEED_INTERVAL.store(config.reseed_interval, Ordering::Relaxed);
+}
+
+/// Sets (or with `None`, clears) the random number generation config for the
+/// current thread, which overrides the [global config](set_config).
+pub fn set_thread_config(config: Option<Config>) {
+    THREAD_CONFIG.with(|c| c.set(config));
+}
+
+/// Returns the random number generation config in effect for the current
+/// thread.
+pub fn config() -> Config {
+    THREAD_CONFIG
+        .try_with(|c| c.get())
+        .ok()
+        .flatten()
+        .unwrap_or_else(|| Config {
+            source: if GLOBAL_USE_OS.load(Ordering::Relaxed) {
+                Source::Os
+            } else {
+                Source::Drbg
+            },
+            reseed_interval: GLOBAL_RESEED_INTERVAL.load(Ordering::Relaxed),
+        })
+}
 
+/// Forces the current thread's DRBG to reseed from the OS before its next use.
+pub fn reseed() {
+    DRBG.try_with(|drbg| {
+        if let Some(drbg) = drbg.borrow_mut().as_mut() {
+            drbg.state.seeded = 0;
+        }
+    })
+    .ok();
+}
+
+/// Provides random data up to `len` from the configured random number
+/// generator.
+pub fn randombytes_buf(len: usize) -> Vec<u8> {
     let mut r: Vec<u8> = vec![0; len];
-    OsRng.fill_bytes(r.as_mut_slice());
+    copy_randombytes(r.as_mut_slice());
 
     r
 }
 
-/// Provides random data up to length of `data` from the OS's random number
-/// generator.
+/// Provides random data up to length of `data` from the configured random
+/// number generator. Requests which fit within the DRBG's buffer (such as
+/// nonces and keys) don't require any system calls.
 pub fn copy_randombytes(dest: &mut [u8]) {
-    use rand_core::{OsRng, RngCore};
+    let config = config();
+    if config.source == Source::Os {
+        OsRng.fill_bytes(dest);
+        return;
+    }
+
+    let filled = DRBG
+        .try_with(|drbg| {
+            drbg.borrow_mut()
+                .get_or_insert_with(Drbg::new)
+                .fill(dest, config.reseed_interval)
+        })
+        .is_ok();
+    if !filled {
+        // the thread-local DRBG is unavailable while the thread is exiting
+        OsRng.fill_bytes(dest);
+    }
+}
+
+/// Returns a uniformly distributed random integer in the range
+/// `0..upper_bound`, without modulo bias, using the configured random number
+/// generator. Returns 0 if `upper_bound` is less than 2.
+///
+/// Values which would bias the result are rejected and redrawn, so the running
+/// time varies, but is independent of the value returned.
+pub fn uniform(upper_bound: u64) -> u64 {
+    if upper_bound < 2 {
+        return 0;
+    }
+    // 2^64 mod upper_bound; values below this are rejected, so that the
+    // remaining range is a multiple of upper_bound
+    let min = upper_bound.wrapping_neg() % upper_bound;
+    let mut bytes = [0u8; 8];
+    loop {
+        copy_randombytes(&mut bytes);
+        let r = u64::from_le_bytes(bytes);
+        if r >= min {
+            bytes.zeroize();
+            return r % upper_bound;
+        }
+    }
+}
+
+/// Shuffles `items` in place into a uniformly random permutation, using the
+/// Fisher–Yates algorithm with [`uniform`].
+///
+/// Every shuffle of the same number of items performs the same sequence of
+/// swaps by position, so the number of operations doesn't depend on the
+/// permutation, but the memory locations accessed do, which may be observable
+/// through cache timing by an attacker on the same machine.
+pub fn shuffle<T>(items: &mut [T]) {
+    for i in (1..items.len()).rev() {
+        let j = uniform(i as u64 + 1) as usize;
+        items.swap(i, j);
+    }
+}
+
+/// Returns `amount` distinct indices in the range `0..len`, in random order,
+/// chosen uniformly without replacement. Returns an error if `amount` is
+/// greater than `len`.
+pub fn sample_indices(len: usize, amount: usize) -> Result<Vec<usize>, Error> {
+    if amount > len {
+        return Err(dryoc_error!(format!(
+            "cannot sample {} items from {}",
+            amount, len
+        )));
+    }
+    // a partial Fisher–Yates shuffle, stopping after `amount` items
+    let mut indices: Vec<usize> = (0..len).collect();
+    for i in 0..amount {
+        let j = i + uniform((len - i) as u64) as usize;
+        indices.swap(i, j);
+    }
+    indices.truncate(amount);
+    Ok(indices)
+}
+
+/// Returns references to `amount` distinct items of `items` (by position), in
+/// random order, chosen uniformly without replacement. Returns an error if
+/// `amount` is greater than the number of items.
+pub fn sample<T>(items: &[T], amount: usize) -> Result<Vec<&T>, Error> {
+    Ok(sample_indices(items.len(), amount)?
+        .into_iter()
+        .map(|i| &items[i])
+        .collect())
+}
+
+/// Alphabet used by [`token`]: the URL-safe base64 alphabet, from RFC 4648.
+pub const TOKEN_ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+/// Returns a random string of `len` characters from the URL-safe base64
+/// alphabet ([`TOKEN_ALPHABET`]), suitable for session IDs, API keys and other
+/// secret tokens. Each character carries 6 bits of entropy, so use at least 22
+/// characters for 128 bits.
+pub fn token(len: usize) -> String {
+    let mut bytes = randombytes_buf(len);
+    let token = bytes
+        .iter()
+        .map(|b| TOKEN_ALPHABET[(b & 0x3f) as usize] as char)
+        .collect();
+    bytes.zeroize();
+    token
+}
+
+/// Returns a random string of `len` characters, each chosen uniformly from
+/// `alphabet` with [`uniform`]. Returns an error if `alphabet` is empty, or
+/// contains non-ASCII characters.
+pub fn random_string(len: usize, alphabet: &[u8]) -> Result<String, Error> {
+    if alphabet.is_empty() || !alphabet.is_ascii() {
+        return Err(dryoc_error!("alphabet must be non-empty ASCII"));
+    }
+    Ok((0..len)
+        .map(|_| alphabet[uniform(alphabet.len() as u64) as usize] as char)
+        .collect())
+}
+
+/// A universally unique identifier (UUID), as specified in RFC 9562,
+/// generated from the configured random number generator.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Uuid([u8; 16]);
+
+impl Uuid {
+    /// Returns a new random (version 4) UUID, with 122 bits of entropy.
+    pub fn new_v4() -> Self {
+        let mut bytes = [0u8; 16];
+        copy_randombytes(&mut bytes);
+        Self::with_version(bytes, 4)
+    }
+
+    /// Returns a new time-ordered (version 7) UUID, which starts with the
+    /// number of milliseconds since the UNIX epoch, followed by 74 random bits.
+    /// UUIDs generated in different milliseconds sort in the order they were
+    /// generated, which makes them well suited to database keys, but they
+    /// reveal when they were created.
+    pub fn new_v7() -> Self {
+        let millis = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or_default();
+        let mut bytes = [0u8; 16];
+        bytes[..6].copy_from_slice(&millis.to_be_bytes()[2..]);
+        copy_randombytes(&mut bytes[6..]);
+        Self::with_version(bytes, 7)
+    }
+
+    fn with_version(mut bytes: [u8; 16], version: u8) -> Self {
+        bytes[6] = (bytes[6] & 0x0f) | (version << 4);
+        // RFC 9562 variant
+        bytes[8] = (bytes[8] & 0x3f) | 0x80;
+        Self(bytes)
+    }
+
+    /// Returns a UUID from `bytes`, in network byte order.
+    pub fn from_bytes(bytes: [u8; 16]) -> Self {
+        Self(bytes)
+    }
+
+    /// Returns the bytes of this UUID, in network byte order.
+    pub fn as_bytes(&self) -> &[u8; 16] {
+        &self.0
+    }
+
+    /// Returns the version of this UUID, such as 4 or 7.
+    pub fn version(&self) -> u8 {
+        self.0[6] >> 4
+    }
+}
+
+impl std::fmt::Display for Uuid {
+    /// Formats this UUID in the standard hyphenated, lowercase form, such as
+    /// `0190b8a2-1f2e-7c3d-8e4f-5a6b7c8d9e0f`.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (i, b) in self.0.iter().enumerate() {
+            if matches!(i, 4 | 6 | 8 | 10) {
+                f.write_str("-")?;
+            }
+            write!(f, "{:02x}", b)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::str::FromStr for Uuid {
+    type Err = Error;
+
+    /// Parses a UUID in the standard hyphenated form, in either case.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || dryoc_error!(format!("invalid UUID: {:?}", s));
+        let groups: Vec<&str> = s.split('-').collect();
+        if groups.iter().map(|g| g.len()).collect::<Vec<_>>() != [8, 4, 4, 4, 12] {
+            return Err(invalid());
+        }
+        let hex = groups.concat();
+        if !hex.bytes().all(|c| c.is_ascii_hexdigit()) {
+            return Err(invalid());
+        }
+        let mut bytes = [0u8; 16];
+        for (i, b) in bytes.iter_mut().enumerate() {
+            *b = u8::from_str_radix(&hex[2 * i..2 * i + 2], 16).map_err(|_| invalid())?;
+        }
+        Ok(Self(bytes))
+    }
+}
+
+#[repr(C)]
+struct State {
+    // zero whenever the state must be reseeded, including after fork() on
+    // platforms which support wiping memory in the child
+    seeded: u8,
+    key: [u8; 32],
+    buffer: [u8; DRBG_BUFFER_BYTES],
+    available: usize,
+    since_reseed: u64,
+    fork_generation: u64,
+    pid: u32,
+}
+
+impl Zeroize for State {
+    fn zeroize(&mut self) {
+        self.seeded.zeroize();
+        self.key.zeroize();
+        self.buffer.zeroize();
+        self.available.zeroize();
+        self.since_reseed.zeroize();
+        self.fork_generation.zeroize();
+        self.pid.zeroize();
+    }
+}
+
+struct Drbg {
+    state: &'static mut State,
+    // true if the state is wiped in the child after fork(), otherwise the
+    // process ID must be checked on every request
+    wipe_on_fork: bool,
+}
+
+impl Drbg {
+    fn new() -> Self {
+        #[cfg(target_os = "linux")]
+        {
+            use libc::{
+                madvise, mmap, munmap, MADV_WIPEONFORK, MAP_ANONYMOUS, MAP_FAILED, MAP_PRIVATE,
+                PROT_READ, PROT_WRITE,
+            };
+
+            let size = std::mem::size_of::<State>();
+            let ptr = unsafe {
+                mmap(
+                    std::ptr::null_mut(),
+                    size,
+                    PROT_READ | PROT_WRITE,
+                    MAP_PRIVATE | MAP_ANONYMOUS,
+                    -1,
+                    0,
+                )
+            };
+            if ptr != MAP_FAILED {
+                if unsafe { madvise(ptr, size, MADV_WIPEONFORK) } == 0 {
+                    // anonymous mappings are zeroed, which is a valid unseeded
+                    // state
+                    return Self {
+                        state: unsafe { &mut *(ptr as *mut State) },
+                        wipe_on_fork: true,
+                    };
+                }
+                unsafe { munmap(ptr, size) };
+            }
+        }
+
+        Self {
+            state: Box::leak(Box::new(State {
+                seeded: 0,
+                key: [0u8; 32],
+                buffer: [0u8; DRBG_BUFFER_BYTES],
+                available: 0,
+                since_reseed: 0,
+                fork_generation: 0,
+                pid: 0,
+            })),
+            wipe_on_fork: false,
+        }
+    }
+
+    fn reseed(&mut self) {
+        let state = &mut *self.state;
+        OsRng.fill_bytes(&mut state.key);
+        state.buffer.zeroize();
+        state.available = 0;
+        state.since_reseed = 0;
+        state.fork_generation = crate::fork_safety::fork_generation();
+        if !self.wipe_on_fork {
+            state.pid = std::process::id();
+        }
+        state.seeded = 1;
+    }
+
+    fn refill(&mut self) {
+        let state = &mut *self.state;
+        let mut cipher = ChaCha20::new(Key::from_slice(&state.key), &Nonce::default());
+        let mut next_key = [0u8; 32];
+        cipher.apply_keystream(&mut next_key);
+        state.buffer.zeroize();
+        cipher.apply_keystream(&mut state.buffer);
+        state.key.copy_from_slice(&next_key);
+        next_key.zeroize();
+        state.available = DRBG_BUFFER_BYTES;
+    }
+
+    fn fill(&mut self, dest: &mut [u8], reseed_interval: u64) {
+        if self.state.seeded == 0
+            || self.state.since_reseed >= reseed_interval
+            || self.state.fork_generation != crate::fork_safety::fork_generation()
+            || (!self.wipe_on_fork && self.state.pid != std::process::id())
+        {
+            self.reseed();
+        }
+
+        for chunk in dest.chunks_mut(DRBG_BUFFER_BYTES) {
+            if self.state.available < chunk.len() {
+                self.refill();
+            }
+            let state = &mut *self.state;
+            let start = DRBG_BUFFER_BYTES - state.available;
+            let end = start + chunk.len();
+            chunk.copy_from_slice(&state.buffer[start..end]);
+            state.buffer[start..end].zeroize();
+            state.available -= chunk.len();
+        }
+        self.state.since_reseed = self.state.since_reseed.saturating_add(dest.len() as u64);
+    }
+}
+
+impl Drop for Drbg {
+    fn drop(&mut self) {
+        self.state.zeroize();
+        let ptr = self.state as *mut State;
+        #[cfg(target_os = "linux")]
+        if self.wipe_on_fork {
+            unsafe { libc::munmap(ptr as *mut libc::c_void, std::mem::size_of::<State>()) };
+            return;
+        }
+        drop(unsafe { Box::from_raw(ptr) });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_drbg() {
+        set_thread_config(Some(Config::default()));
+        assert_eq!(config().source(), Source::Drbg);
+
+        let a = randombytes_buf(32);
+        let b = randombytes_buf(32);
+        assert_ne!(a, b);
+
+        // larger than the buffer, and not a multiple of it
+        let large = randombytes_buf(DRBG_BUFFER_BYTES * 3 + 7);
+        assert!(large.chunks(32).all(|chunk| chunk != a.as_slice()));
+
+        let mut drbg = Drbg::new();
+        let mut out = [0u8; 16];
+        drbg.fill(&mut out, 32);
+        drbg.fill(&mut out, 32);
+        assert_eq!(drbg.state.since_reseed, 32);
+        let key = drbg.state.key;
+        drbg.fill(&mut out, 32);
+        assert_eq!(drbg.state.since_reseed, 16);
+        assert_ne!(drbg.state.key, key);
+
+        set_thread_config(None);
+    }
+
+    #[test]
+    fn test_uniform() {
+        assert_eq!(uniform(0), 0);
+        assert_eq!(uniform(1), 0);
+
+        let mut counts = [0usize; 6];
+        for _ in 0..6000 {
+            counts[uniform(6) as usize] += 1;
+        }
+        assert!(counts.iter().all(|count| (700..1300).contains(count)));
+        assert!(uniform(u64::MAX) < u64::MAX);
+    }
+
+    #[test]
+    fn test_shuffle_sample() {
+        let mut items: Vec<u32> = (0..100).collect();
+        shuffle(&mut items);
+        assert_ne!(items, (0..100).collect::<Vec<u32>>());
+        let mut sorted = items.clone();
+        sorted.sort_unstable();
+        assert_eq!(sorted, (0..100).collect::<Vec<u32>>());
+        shuffle::<u32>(&mut []);
+
+        let mut sampled = sample(&items, 10).expect("sample failed");
+        sampled.sort_unstable();
+        sampled.dedup();
+        assert_eq!(sampled.len(), 10);
+        assert_eq!(sample(&items, 100).expect("sample failed").len(), 100);
+        assert!(sample(&items, 0).expect("sample failed").is_empty());
+        assert!(sample(&items, 101).is_err());
+
+        // every index is eventually sampled first
+        let mut seen = [false; 5];
+        for _ in 0..200 {
+            seen[sample_indices(5, 1).expect("sample failed")[0]] = true;
+        }
+        assert!(seen.iter().all(|seen| *seen));
+    }
+
+    #[test]
+    fn test_tokens() {
+        let a = token(22);
+        assert_eq!(a.len(), 22);
+        assert!(a.bytes().all(|c| TOKEN_ALPHABET.contains(&c)));
+        assert_ne!(a, token(22));
+        assert!(token(0).is_empty());
+
+        let s = random_string(100, b"ab").expect("random string failed");
+        assert!(s.contains('a') && s.contains('b') && s.len() == 100);
+        assert!(random_string(1, b"").is_err());
+        assert!(random_string(1, "é".as_bytes()).is_err());
+    }
+
+    #[test]
+    fn test_uuid() {
+        let v4 = Uuid::new_v4();
+        assert_eq!(v4.version(), 4);
+        assert_eq!(v4.as_bytes()[8] & 0xc0, 0x80);
+        assert_ne!(v4, Uuid::new_v4());
+
+        let v7 = Uuid::new_v7();
+        assert_eq!(v7.version(), 7);
+        assert_eq!(v7.as_bytes()[8] & 0xc0, 0x80);
+        std::thread::sleep(std::time::Duration::from_millis(2));
+        assert!(Uuid::new_v7() > v7);
+
+        let text = "0190B8A2-1F2E-7C3D-8E4F-5A6B7C8D9E0F";
+        let parsed: Uuid = text.parse().expect("parse failed");
+        assert_eq!(parsed.to_string(), text.to_lowercase());
+        assert_eq!(parsed.version(), 7);
+        assert_eq!(Uuid::from_bytes(*parsed.as_bytes()), parsed);
+        for invalid in [
+            "0190b8a21f2e7c3d8e4f5a6b7c8d9e0f",
+            "0190b8a2-1f2e-7c3d-8e4f-5a6b7c8d9e0g",
+            "+190b8a2-1f2e-7c3d-8e4f-5a6b7c8d9e0f",
+            "0190b8a2-1f2e-7c3d-8e4f-5a6b7c8d9e0f-",
+        ] {
+            assert!(invalid.parse::<Uuid>().is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_thread_config() {
+        let os = Config::default()
+            .with_source(Source::Os)
+            .with_reseed_interval(0);
+        set_thread_config(Some(os));
+        assert_eq!(config(), os);
+        assert_eq!(randombytes_buf(8).len(), 8);
+
+        // thread configs don't leak across threads
+        std::thread::spawn(|| assert_eq!(config().source(), Source::Drbg))
+            .join()
+            .expect("thread failed");
+
+        set_thread_config(None);
+        assert_eq!(config(), Config::default());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_fork() {
+        set_thread_config(Some(Config::default()));
+        // make sure the DRBG is seeded and has buffered output
+        randombytes_buf(1);
+
+        let mut fds = [0; 2];
+        assert_eq!(unsafe { libc::pipe(fds.as_mut_ptr()) }, 0);
+        let pid = unsafe { libc::fork() };
+        if pid == 0 {
+            let mut out = [0u8; 32];
+            copy_randombytes(&mut out);
+            unsafe {
+                libc::write(fds[1], out.as_ptr() as *const libc::c_void, out.len());
+                libc::_exit(0);
+            }
+        }
+        assert!(pid > 0, "fork failed");
+
+        let mut child = [0u8; 32];
+        let read = unsafe { libc::read(fds[0], child.as_mut_ptr() as *mut libc::c_void, 32) };
+        unsafe {
+            libc::waitpid(pid, std::ptr::null_mut(), 0);
+            libc::close(fds[0]);
+            libc::close(fds[1]);
+        }
+        assert_eq!(read, 32);
+
+        let mut parent = [0u8; 32];
+        copy_randombytes(&mut parent);
+        assert_ne!(parent, child);
 
-    OsRng.fill_bytes(dest);
+        set_thread_config(None);
+    }
 }
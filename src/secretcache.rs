@@ -0,0 +1,231 @@
+//! # In-memory secret cache
+//!
+//! [`SecretCache`] holds secrets (such as decrypted per-tenant data keys) keyed
+//! by an identifier, with optional time-based expiry. It provides a single
+//! place to keep secrets for the lifetime of a process, and to purge them
+//! explicitly when they're no longer needed.
+//!
+//! Any type implementing [`Zeroize`] may be stored, although the cache is
+//! intended to be used with the [protected](crate::protected) memory types
+//! (i.e., [`Locked`](crate::protected::Locked) or
+//! [`LockedRO`](crate::protected::LockedRO)). Values are zeroized when they're
+//! replaced, removed, expired, or when the cache is dropped.
+//!
+//! Expired entries are never returned by [`SecretCache::get`], but they're only
+//! released from memory when [`SecretCache::purge_expired`],
+//! [`SecretCache::purge`], or [`SecretCache::remove`] is called, or when they
+//! are replaced.
+//!
+//! ## Example
+//!
+//! ```
+//! use std::time::Duration;
+//!
+//! use dryoc::dryocsecretbox::Key;
+//! use dryoc::secretcache::SecretCache;
+//! use dryoc::types::*;
+//!
+//! // Create a cache where entries expire after 5 minutes by default
+//! let mut cache = SecretCache::with_ttl(Duration::from_secs(300));
+//!
+//! cache.insert("tenant-1", Key::gen());
+//! // Entries can also have their own TTL, or never expire
+//! cache.insert_with_ttl("tenant-2", Key::gen(), Some(Duration::from_secs(60)));
+//! cache.insert_with_ttl("tenant-3", Key::gen(), None);
+//!
+//! assert!(cache.get("tenant-1").is_some());
+//! assert!(cache.get("tenant-4").is_none());
+//!
+//! // Wipe everything when done
+//! cache.purge();
+//! assert!(cache.is_empty());
+//! ```
+
+use std::borrow::Borrow;
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::time::{Duration, Instant};
+
+use zeroize::Zeroize;
+
+struct Entry<T: Zeroize> {
+    value: T,
+    expires_at: Option<Instant>,
+}
+
+impl<T: Zeroize> Entry<T> {
+    fn is_expired(&self, now: Instant) -> bool {
+        matches!(self.expires_at, Some(expires_at) if now >= expires_at)
+    }
+}
+
+impl<T: Zeroize> Drop for Entry<T> {
+    fn drop(&mut self) {
+        self.value.zeroize()
+    }
+}
+
+/// In-memory cache of secrets keyed by `K`, with optional time-based expiry.
+/// Does not implement [Clone] or [std::fmt::Debug].
+///
+/// Refer to [crate::secretcache] for sample usage.
+pub struct SecretCache<K: Eq + Hash, T: Zeroize> {
+    entries: HashMap<K, Entry<T>>,
+    default_ttl: Option<Duration>,
+}
+
+impl<K: Eq + Hash, T: Zeroize> SecretCache<K, T> {
+    /// Returns a new, empty cache whose entries never expire by default.
+    pub fn new() -> Self {
+        Self {
+            entries: HashMap::new(),
+            default_ttl: None,
+        }
+    }
+
+    /// Returns a new, empty cache whose entries expire after `ttl` by default.
+    pub fn with_ttl(ttl: Duration) -> Self {
+        Self {
+            entries: HashMap::new(),
+            default_ttl: Some(ttl),
+        }
+    }
+
+    /// Returns the default TTL for new entries, if any.
+    pub fn default_ttl(&self) -> Option<Duration> {
+        self.default_ttl
+    }
+
+    /// Inserts `value` with the cache's default TTL, replacing (and zeroizing)
+    /// any previous value for `key`.
+    pub fn insert(&mut self, key: K, value: T) {
+        self.insert_with_ttl(key, value, self.default_ttl)
+    }
+
+    /// Inserts `value` which expires after `ttl`, or never if `ttl` is `None`
+    /// (or too large to represent, such as [`Duration::MAX`]), replacing (and
+    /// zeroizing) any previous value for `key`.
+    pub fn insert_with_ttl(&mut self, key: K, value: T, ttl: Option<Duration>) {
+        let expires_at = ttl.and_then(|ttl| Instant::now().checked_add(ttl));
+        self.entries.insert(key, Entry { value, expires_at });
+    }
+
+    /// Returns a reference to the value for `key`, or `None` if the key is
+    /// missing or the entry has expired.
+    pub fn get<Q>(&self, key: &Q) -> Option<&T>
+    where
+        K: Borrow<Q>,
+        Q: Eq + Hash + ?Sized,
+    {
+        let now = Instant::now();
+        self.entries
+            .get(key)
+            .filter(|entry| !entry.is_expired(now))
+            .map(|entry| &entry.value)
+    }
+
+    /// Returns true if the cache contains an unexpired value for `key`.
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Eq + Hash + ?Sized,
+    {
+        self.get(key).is_some()
+    }
+
+    /// Removes the entry for `key`, zeroizing its value. Returns true if an
+    /// entry (expired or not) was removed.
+    pub fn remove<Q>(&mut self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Eq + Hash + ?Sized,
+    {
+        self.entries.remove(key).is_some()
+    }
+
+    /// Removes and zeroizes all expired entries, returning the number of
+    /// entries removed.
+    pub fn purge_expired(&mut self) -> usize {
+        let now = Instant::now();
+        let before = self.entries.len();
+        self.entries.retain(|_, entry| !entry.is_expired(now));
+        before - self.entries.len()
+    }
+
+    /// Removes and zeroizes all entries.
+    pub fn purge(&mut self) {
+        self.entries.clear()
+    }
+
+    /// Returns an iterator over the keys of all unexpired entries, for
+    /// auditing the contents of the cache without accessing the secrets.
+    pub fn keys(&self) -> impl Iterator<Item = &K> {
+        let now = Instant::now();
+        self.entries
+            .iter()
+            .filter(move |(_, entry)| !entry.is_expired(now))
+            .map(|(key, _)| key)
+    }
+
+    /// Returns the number of entries in the cache, including expired entries
+    /// which have not yet been purged.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns true if the cache contains no entries.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+impl<K: Eq + Hash, T: Zeroize> Default for SecretCache<K, T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dryocsecretbox::Key;
+    use crate::types::*;
+
+    #[test]
+    fn test_insert_get_remove() {
+        let mut cache = SecretCache::new();
+        let key = Key::gen();
+
+        cache.insert("a", key.clone());
+        assert_eq!(cache.get("a"), Some(&key));
+        assert!(cache.contains_key("a"));
+        assert!(!cache.contains_key("b"));
+        assert_eq!(cache.keys().collect::<Vec<_>>(), vec![&"a"]);
+
+        assert!(cache.remove("a"));
+        assert!(!cache.remove("a"));
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn test_ttl() {
+        let mut cache = SecretCache::with_ttl(Duration::from_secs(0));
+        cache.insert("expired", Key::gen());
+        cache.insert_with_ttl("forever", Key::gen(), None);
+        cache.insert_with_ttl("later", Key::gen(), Some(Duration::from_secs(3600)));
+        cache.insert_with_ttl("max", Key::gen(), Some(Duration::MAX));
+
+        assert!(cache.get("expired").is_none());
+        assert!(cache.get("forever").is_some());
+        assert!(cache.get("later").is_some());
+        assert!(cache.get("max").is_some());
+        assert_eq!(cache.len(), 4);
+        assert_eq!(cache.keys().count(), 3);
+
+        assert_eq!(cache.purge_expired(), 1);
+        assert_eq!(cache.len(), 3);
+
+        cache.purge();
+        assert!(cache.is_empty());
+    }
+}
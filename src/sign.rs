@@ -78,16 +78,23 @@ use serde::{Deserialize, Serialize};
 use subtle::ConstantTimeEq;
 use zeroize::{Zeroize, ZeroizeOnDrop};
 
+use crate::canonical::{self, CanonicalDecode, CanonicalEncode};
+use crate::cbor::Value;
+use crate::classic::crypto_kdf::crypto_kdf_derive_from_key;
 use crate::classic::crypto_sign::{
     crypto_sign_detached, crypto_sign_final_create, crypto_sign_final_verify, crypto_sign_init,
     crypto_sign_keypair_inplace, crypto_sign_seed_keypair_inplace, crypto_sign_update,
     crypto_sign_verify_detached, SignerState,
 };
 use crate::constants::{
-    CRYPTO_SIGN_BYTES, CRYPTO_SIGN_PUBLICKEYBYTES, CRYPTO_SIGN_SECRETKEYBYTES,
-    CRYPTO_SIGN_SEEDBYTES,
+    CRYPTO_KDF_CONTEXTBYTES, CRYPTO_KDF_KEYBYTES, CRYPTO_SIGN_BYTES, CRYPTO_SIGN_PUBLICKEYBYTES,
+    CRYPTO_SIGN_SECRETKEYBYTES, CRYPTO_SIGN_SEEDBYTES,
 };
 use crate::error::Error;
+use crate::formats::{
+    decode_checksummed_of, decode_envelope_of, encode_checksummed, encode_envelope, Format, Kind,
+};
+use crate::policy::Algorithms;
 use crate::types::*;
 
 /// Stack-allocated public key for message signing.
@@ -112,6 +119,10 @@ pub struct SigningKeyPair<
     /// Public key
     pub public_key: PublicKey,
     /// Secret key
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::redact::serialize_secret")
+    )]
     pub secret_key: SecretKey,
 }
 
@@ -148,6 +159,37 @@ impl<
         Self::from_seed(&seed)
     }
 
+    /// Returns the public key for `secret_key`, recomputed from the seed half
+    /// of the secret key. Useful when only the 64-byte secret key was
+    /// persisted.
+    ///
+    /// Returns an error if the public key half of `secret_key` doesn't match
+    /// the recomputed public key, which indicates that the secret key is
+    /// corrupt.
+    pub fn public_from_secret(secret_key: &SecretKey) -> Result<PublicKey, Error> {
+        let mut public_key = PublicKey::new_byte_array();
+        let mut recomputed = [0u8; CRYPTO_SIGN_SECRETKEYBYTES];
+        let mut seed = [0u8; CRYPTO_SIGN_SEEDBYTES];
+        seed.copy_from_slice(&secret_key.as_slice()[..CRYPTO_SIGN_SEEDBYTES]);
+
+        crypto_sign_seed_keypair_inplace(public_key.as_mut_array(), &mut recomputed, &seed);
+        seed.zeroize();
+        recomputed.zeroize();
+
+        if public_key
+            .as_slice()
+            .ct_eq(&secret_key.as_slice()[CRYPTO_SIGN_SEEDBYTES..])
+            .unwrap_u8()
+            == 1
+        {
+            Ok(public_key)
+        } else {
+            Err(dryoc_error!(
+                "public key doesn't match secret key, secret key may be corrupt"
+            ))
+        }
+    }
+
     /// Derives a signing keypair from `seed`, returning
     /// a new keypair.
     pub fn from_seed<Seed: ByteArray<CRYPTO_SIGN_SEEDBYTES>>(seed: &Seed) -> Self {
@@ -165,6 +207,53 @@ impl<
             secret_key,
         }
     }
+
+    /// Deterministically derives a signing keypair from `main_key`, `context`,
+    /// and `subkey_id`, using the same derivation as
+    /// [`Kdf`](crate::kdf::Kdf). The derived subkey is used as the seed for
+    /// [`SigningKeyPair::from_seed`].
+    ///
+    /// This allows many signing keypairs to be recreated from a single stored
+    /// main key. Different contexts or subkey IDs give unrelated keypairs.
+    pub fn derive<
+        MainKey: ByteArray<CRYPTO_KDF_KEYBYTES>,
+        Context: ByteArray<CRYPTO_KDF_CONTEXTBYTES>,
+    >(
+        main_key: &MainKey,
+        context: &Context,
+        subkey_id: u64,
+    ) -> Result<Self, Error> {
+        let mut seed = [0u8; CRYPTO_SIGN_SEEDBYTES];
+        crypto_kdf_derive_from_key(
+            &mut seed,
+            subkey_id,
+            context.as_array(),
+            main_key.as_array(),
+        )?;
+        let keypair = Self::from_seed(&seed);
+        seed.zeroize();
+        Ok(keypair)
+    }
+
+    /// Deserializes a signing keypair from a checksummed container, as
+    /// produced by [`SigningKeyPair::to_checksummed_bytes`]. Refer to
+    /// [crate::formats] for details.
+    pub fn from_checksummed_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        let payload = decode_checksummed_of(bytes, Kind::SigningKeyPair)?;
+        validate!(
+            CRYPTO_SIGN_SECRETKEYBYTES,
+            CRYPTO_SIGN_SECRETKEYBYTES,
+            payload.len(),
+            "payload"
+        );
+
+        let mut keypair = Self::new();
+        keypair.secret_key.copy_from_slice(payload);
+        keypair
+            .public_key
+            .copy_from_slice(&payload[CRYPTO_SIGN_SEEDBYTES..]);
+        Ok(keypair)
+    }
 }
 
 impl
@@ -253,7 +342,7 @@ pub mod protected {
         >
     {
         /// Returns a new locked signing keypair.
-        pub fn new_locked_keypair() -> Result<Self, std::io::Error> {
+        pub fn new_locked_keypair() -> Result<Self, crate::error::Error> {
             Ok(Self {
                 public_key: HeapByteArray::<CRYPTO_SIGN_PUBLICKEYBYTES>::new_locked()?,
                 secret_key: HeapByteArray::<CRYPTO_SIGN_SECRETKEYBYTES>::new_locked()?,
@@ -261,7 +350,7 @@ pub mod protected {
         }
 
         /// Returns a new randomly generated locked signing keypair.
-        pub fn gen_locked_keypair() -> Result<Self, std::io::Error> {
+        pub fn gen_locked_keypair() -> Result<Self, crate::error::Error> {
             let mut res = Self::new_locked_keypair()?;
 
             crypto_sign_keypair_inplace(
@@ -280,7 +369,7 @@ pub mod protected {
         >
     {
         /// Returns a new randomly generated locked, read-only signing keypair.
-        pub fn gen_readonly_locked_keypair() -> Result<Self, std::io::Error> {
+        pub fn gen_readonly_locked_keypair() -> Result<Self, crate::error::Error> {
             let mut public_key = HeapByteArray::<CRYPTO_SIGN_PUBLICKEYBYTES>::new_locked()?;
             let mut secret_key = HeapByteArray::<CRYPTO_SIGN_SECRETKEYBYTES>::new_locked()?;
 
@@ -326,6 +415,8 @@ impl<
         &self,
         message: Message,
     ) -> Result<SignedMessage<Signature, Message>, Error> {
+        crate::policy::require(Algorithms::ED25519)?;
+
         let mut signature = Signature::new_byte_array();
         crypto_sign_detached(
             signature.as_mut_array(),
@@ -344,6 +435,19 @@ impl<
     ) -> Result<SignedMessage<StackByteArray<CRYPTO_SIGN_BYTES>, Vec<u8>>, Error> {
         self.sign(Vec::from(message.as_slice()))
     }
+
+    /// Serializes this keypair into a checksummed container, which detects
+    /// truncation and corruption when it's loaded with
+    /// [`SigningKeyPair::from_checksummed_bytes`]. Only the secret key is
+    /// stored, as it contains the public key. Refer to [crate::formats] for
+    /// details. Can be used with protected memory.
+    pub fn to_checksummed_bytes<Output: NewBytes + ResizableBytes>(&self) -> Output {
+        encode_checksummed(
+            Format::CURRENT,
+            Kind::SigningKeyPair,
+            self.secret_key.as_slice(),
+        )
+    }
 }
 
 impl Default for SigningKeyPair<PublicKey, SecretKey> {
@@ -379,6 +483,8 @@ impl IncrementalSigner {
         self,
         secret_key: &SecretKey,
     ) -> Result<Signature, Error> {
+        crate::policy::require(Algorithms::ED25519)?;
+
         let mut signature = Signature::new_byte_array();
 
         crypto_sign_final_create(self.state, signature.as_mut_array(), secret_key.as_array())?;
@@ -395,6 +501,8 @@ impl IncrementalSigner {
         signature: &Signature,
         public_key: &PublicKey,
     ) -> Result<(), Error> {
+        crate::policy::require(Algorithms::ED25519)?;
+
         crypto_sign_final_verify(self.state, signature.as_array(), public_key.as_array())?;
 
         Ok(())
@@ -407,6 +515,123 @@ impl Default for IncrementalSigner {
     }
 }
 
+/// Signature algorithms which can be used with [`Signer`] and [`Verifier`],
+/// along with their identifiers in the JOSE and COSE registries.
+///
+/// dryoc only implements EdDSA (Ed25519, and optionally Ed448). The other
+/// algorithms are listed so that external implementations, such as ECDSA over
+/// P-256 or secp256k1, can be plugged into the higher-level signed formats.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum SignatureAlgorithm {
+    /// EdDSA, with Ed25519 or Ed448
+    EdDSA,
+    /// ECDSA using P-256 and SHA-256
+    Es256,
+    /// ECDSA using secp256k1 and SHA-256
+    Es256K,
+    /// ECDSA using P-384 and SHA-384
+    Es384,
+    /// ECDSA using P-521 and SHA-512
+    Es512,
+}
+
+impl SignatureAlgorithm {
+    /// Returns the JWS `alg` header value for this algorithm.
+    pub fn jws_name(&self) -> &'static str {
+        match self {
+            Self::EdDSA => "EdDSA",
+            Self::Es256 => "ES256",
+            Self::Es256K => "ES256K",
+            Self::Es384 => "ES384",
+            Self::Es512 => "ES512",
+        }
+    }
+
+    /// Returns the COSE algorithm identifier for this algorithm.
+    pub fn cose_id(&self) -> i64 {
+        match self {
+            Self::EdDSA => -8,
+            Self::Es256 => -7,
+            Self::Es256K => -47,
+            Self::Es384 => -35,
+            Self::Es512 => -36,
+        }
+    }
+
+    /// Returns the algorithm with the COSE identifier `cose_id`.
+    pub(crate) fn from_cose_id(cose_id: i64) -> Result<Self, Error> {
+        [
+            Self::EdDSA,
+            Self::Es256,
+            Self::Es256K,
+            Self::Es384,
+            Self::Es512,
+        ]
+        .into_iter()
+        .find(|algorithm| algorithm.cose_id() == cose_id)
+        .ok_or_else(|| dryoc_error!(format!("unknown signature algorithm {}", cose_id)))
+    }
+}
+
+/// A signing key which produces detached signatures. The higher-level signed
+/// formats (such as JWS and COSE) accept any [`Signer`], so signature
+/// algorithms that dryoc doesn't implement can be used by implementing this
+/// trait.
+///
+/// Implemented by [`SigningKeyPair`].
+pub trait Signer {
+    /// Returns the algorithm of the signatures produced by this signer.
+    fn algorithm(&self) -> SignatureAlgorithm;
+
+    /// Signs `message`, returning the detached signature, encoded as
+    /// required by the JOSE and COSE specifications for this signer's
+    /// algorithm (for ECDSA, the fixed-length `r || s` encoding).
+    fn try_sign(&self, message: &[u8]) -> Result<Vec<u8>, Error>;
+}
+
+/// A public key which verifies detached signatures produced by a [`Signer`].
+///
+/// Implemented by [`PublicKey`].
+pub trait Verifier {
+    /// Returns the algorithm of the signatures accepted by this verifier.
+    fn algorithm(&self) -> SignatureAlgorithm;
+
+    /// Verifies that `signature` is a valid signature of `message`.
+    fn verify(&self, message: &[u8], signature: &[u8]) -> Result<(), Error>;
+}
+
+impl<
+    PublicKey: ByteArray<CRYPTO_SIGN_PUBLICKEYBYTES> + Zeroize,
+    SecretKey: ByteArray<CRYPTO_SIGN_SECRETKEYBYTES> + Zeroize,
+> Signer for SigningKeyPair<PublicKey, SecretKey>
+{
+    fn algorithm(&self) -> SignatureAlgorithm {
+        SignatureAlgorithm::EdDSA
+    }
+
+    fn try_sign(&self, message: &[u8]) -> Result<Vec<u8>, Error> {
+        let mut signature = vec![0u8; CRYPTO_SIGN_BYTES];
+        crypto_sign_detached(
+            signature.as_mut_slice().try_into().expect("wrong length"),
+            message,
+            self.secret_key.as_array(),
+        )?;
+        Ok(signature)
+    }
+}
+
+impl Verifier for PublicKey {
+    fn algorithm(&self) -> SignatureAlgorithm {
+        SignatureAlgorithm::EdDSA
+    }
+
+    fn verify(&self, message: &[u8], signature: &[u8]) -> Result<(), Error> {
+        let signature = Signature::try_from(signature)?;
+        crypto_sign_verify_detached(signature.as_array(), message, self.as_array())
+    }
+}
+
 impl<Signature: ByteArray<CRYPTO_SIGN_BYTES> + Zeroize, Message: Bytes + Zeroize>
     SignedMessage<Signature, Message>
 {
@@ -415,12 +640,27 @@ impl<Signature: ByteArray<CRYPTO_SIGN_BYTES> + Zeroize, Message: Bytes + Zeroize
         &self,
         public_key: &PublicKey,
     ) -> Result<(), Error> {
+        crate::policy::require(Algorithms::ED25519)?;
+
         crypto_sign_verify_detached(
             self.signature.as_array(),
             self.message.as_slice(),
             public_key.as_array(),
         )
     }
+
+    /// Alias for [`SignedMessage::verify`], kept for API symmetry with
+    /// [`DryocBox::verify_only`](crate::dryocbox::DryocBox::verify_only) and
+    /// [`DryocSecretBox::verify_only`](crate::dryocsecretbox::DryocSecretBox::verify_only),
+    /// so that relays can check any container the same way. Unlike boxes,
+    /// signed messages are never decrypted or copied when they're verified,
+    /// so the two methods are identical.
+    pub fn verify_only<PublicKey: ByteArray<CRYPTO_SIGN_PUBLICKEYBYTES>>(
+        &self,
+        public_key: &PublicKey,
+    ) -> Result<(), Error> {
+        self.verify(public_key)
+    }
 }
 
 impl<
@@ -448,13 +688,21 @@ impl<
             })
         }
     }
+
+    /// Initializes a [`SignedMessage`] from a versioned envelope, as produced
+    /// by [`SignedMessage::to_versioned_vec`]. Refer to [crate::formats] for
+    /// details.
+    pub fn from_versioned_bytes(bytes: &'a [u8]) -> Result<Self, Error> {
+        let (_kind, payload) = decode_envelope_of(bytes, &[Kind::SignedMessage])?;
+        Self::from_bytes(payload)
+    }
 }
 
 impl<Signature: ByteArray<CRYPTO_SIGN_BYTES> + Zeroize, Message: Bytes + Zeroize>
     SignedMessage<Signature, Message>
 {
-    /// Returns a new box with `tag`, `data` and (optional) `ephemeral_pk`,
-    /// consuming each.
+    /// Returns a new signed message with `signature` and `message`, consuming
+    /// both.
     pub fn from_parts(signature: Signature, message: Message) -> Self {
         Self { signature, message }
     }
@@ -464,12 +712,28 @@ impl<Signature: ByteArray<CRYPTO_SIGN_BYTES> + Zeroize, Message: Bytes + Zeroize
         self.to_bytes()
     }
 
-    /// Moves the tag, data, and (optional) ephemeral public key out of this
-    /// instance, returning them as a tuple.
+    /// Copies `self` into a new [`Vec`], wrapped in a versioned envelope with
+    /// the current [`Format`]. Refer to [crate::formats] for details.
+    pub fn to_versioned_vec(&self) -> Vec<u8> {
+        encode_envelope(Format::CURRENT, Kind::SignedMessage, &self.to_vec())
+    }
+
+    /// Moves the signature and message out of this instance, returning them
+    /// as a tuple.
     pub fn into_parts(self) -> (Signature, Message) {
         (self.signature, self.message)
     }
 
+    /// Returns a reference to the signature.
+    pub fn signature(&self) -> &Signature {
+        &self.signature
+    }
+
+    /// Returns a reference to the message.
+    pub fn message(&self) -> &Message {
+        &self.message
+    }
+
     /// Copies `self` into the target. Can be used with protected memory.
     pub fn to_bytes<Bytes: NewBytes + ResizableBytes>(&self) -> Bytes {
         let mut data = Bytes::new_bytes();
@@ -521,10 +785,58 @@ impl<Signature: ByteArray<CRYPTO_SIGN_BYTES> + Zeroize, Message: Bytes + Zeroize
     }
 }
 
+impl<Signature: ByteArray<CRYPTO_SIGN_BYTES> + Zeroize, Message: Bytes + Zeroize> CanonicalEncode
+    for SignedMessage<Signature, Message>
+{
+    fn to_canonical_bytes(&self) -> Vec<u8> {
+        canonical::encode(
+            "dryoc/SignedMessage",
+            vec![
+                Value::Bytes(self.signature.as_slice().to_vec()),
+                Value::Bytes(self.message.as_slice().to_vec()),
+            ],
+        )
+    }
+}
+
+impl<
+    Signature: NewByteArray<CRYPTO_SIGN_BYTES> + Zeroize,
+    Message: NewBytes + ResizableBytes + Zeroize,
+> CanonicalDecode for SignedMessage<Signature, Message>
+{
+    fn from_canonical_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        let fields = canonical::decode("dryoc/SignedMessage", bytes, 2)?;
+        Ok(Self {
+            signature: canonical::byte_array_field(&fields[0])?,
+            message: canonical::new_bytes_field(&fields[1])?,
+        })
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_canonical_signed_message() {
+        let keypair = SigningKeyPair::gen_with_defaults();
+        let signed = keypair
+            .sign_with_defaults(b"canonical".to_vec())
+            .expect("signing failed");
+
+        let bytes = signed.to_canonical_bytes();
+        let decoded = VecSignedMessage::from_canonical_bytes(&bytes).expect("decode failed");
+        decoded
+            .verify(&keypair.public_key)
+            .expect("verification failed");
+        assert_eq!(decoded.to_canonical_bytes(), bytes);
+
+        VecSignedMessage::from_canonical_bytes(&bytes[..bytes.len() - 1])
+            .expect_err("truncated input should fail");
+        crate::dryocsecretbox::VecBox::from_canonical_bytes(&bytes)
+            .expect_err("wrong structure should fail");
+    }
+
     #[test]
     fn test_message_signing() {
         let keypair = SigningKeyPair::gen_with_defaults();
@@ -535,5 +847,58 @@ mod tests {
         signed_message
             .verify(&keypair.public_key)
             .expect("verification failed");
+        signed_message
+            .verify_only(&keypair.public_key)
+            .expect("verification failed");
+
+        // reassembled from separately transmitted parts
+        let reassembled = VecSignedMessage::from_parts(
+            signed_message.signature().clone(),
+            signed_message.message().clone(),
+        );
+        assert_eq!(reassembled.message(), message);
+        reassembled
+            .verify(&keypair.public_key)
+            .expect("verification failed");
+    }
+
+    #[test]
+    fn test_public_from_secret() {
+        let keypair = SigningKeyPair::gen_with_defaults();
+
+        let public_key: PublicKey =
+            SigningKeyPair::public_from_secret(&keypair.secret_key).expect("public key failed");
+        assert_eq!(public_key, keypair.public_key);
+
+        let mut corrupt = keypair.secret_key.clone();
+        corrupt[CRYPTO_SIGN_SEEDBYTES] ^= 1;
+        SigningKeyPair::<PublicKey, SecretKey>::public_from_secret(&corrupt)
+            .expect_err("corrupt secret key should fail");
+    }
+
+    #[test]
+    fn test_derive() {
+        use crate::kdf::{Context, Key};
+
+        let main_key = Key::gen();
+        let context = Context::from(*b"signing_");
+
+        let keypair_1: SigningKeyPair<PublicKey, SecretKey> =
+            SigningKeyPair::derive(&main_key, &context, 7).expect("derive failed");
+        let keypair_2: SigningKeyPair<PublicKey, SecretKey> =
+            SigningKeyPair::derive(&main_key, &context, 7).expect("derive failed");
+        let keypair_3: SigningKeyPair<PublicKey, SecretKey> =
+            SigningKeyPair::derive(&main_key, &Context::from(*b"signing2"), 7)
+                .expect("derive failed");
+
+        assert_eq!(keypair_1.public_key, keypair_2.public_key);
+        assert_ne!(keypair_1.public_key, keypair_3.public_key);
+
+        let signed_message = keypair_1
+            .sign_with_defaults(b"derived")
+            .expect("signing failed");
+        signed_message
+            .verify(&keypair_2.public_key)
+            .expect("verification failed");
     }
 }
@@ -0,0 +1,470 @@
+//! # Encrypted spooling
+//!
+//! [`EncryptedSpool`] is a buffer for processing large sensitive payloads
+//! without writing plaintext to disk. Data is held in locked memory until it
+//! exceeds a threshold, at which point it's spooled into an encrypted
+//! temporary file. The spool implements [`Read`], [`Write`], and [`Seek`], so
+//! it can be used anywhere a file would be.
+//!
+//! Once spooled, data is stored in fixed-size chunks of
+//! [`SPOOL_CHUNK_BYTES`], each of which is encrypted as its own secret stream
+//! (see [`crate::dryocstream`]) with a fresh header every time it's written.
+//! Each chunk's index and length are authenticated as associated data, so
+//! chunks can't be reordered or truncated. The key is generated randomly and
+//! held in read-only locked memory, and is never written anywhere, so the file
+//! is unreadable once the spool is dropped. The chunk currently being read or
+//! written is cached in locked memory.
+//!
+//! On Unix-like systems, the temporary file is unlinked immediately after it's
+//! created. On other platforms, it's removed when the spool is dropped.
+//!
+//! ## Example
+//!
+//! ```
+//! use std::io::{Read, Seek, SeekFrom, Write};
+//!
+//! use dryoc::spool::EncryptedSpool;
+//!
+//! // keep up to 1 KiB in memory, then spool to disk
+//! let mut spool = EncryptedSpool::new(1024).expect("spool failed");
+//!
+//! let data = vec![42u8; 100_000];
+//! spool.write_all(&data).expect("write failed");
+//! assert!(spool.is_spooled());
+//!
+//! spool.seek(SeekFrom::Start(0)).expect("seek failed");
+//! let mut read_back = Vec::new();
+//! spool.read_to_end(&mut read_back).expect("read failed");
+//! assert_eq!(read_back, data);
+//! ```
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use crate::classic::crypto_secretstream_xchacha20poly1305::{
+    crypto_secretstream_xchacha20poly1305_init_pull,
+    crypto_secretstream_xchacha20poly1305_init_push, crypto_secretstream_xchacha20poly1305_pull,
+    crypto_secretstream_xchacha20poly1305_push, Header, State,
+};
+use crate::constants::{
+    CRYPTO_SECRETSTREAM_XCHACHA20POLY1305_ABYTES,
+    CRYPTO_SECRETSTREAM_XCHACHA20POLY1305_HEADERBYTES,
+    CRYPTO_SECRETSTREAM_XCHACHA20POLY1305_KEYBYTES,
+    CRYPTO_SECRETSTREAM_XCHACHA20POLY1305_TAG_FINAL,
+};
+use crate::error::Error;
+use crate::protected::*;
+
+/// Size of each encrypted chunk of a spooled file, in bytes.
+pub const SPOOL_CHUNK_BYTES: usize = 16 * 1024;
+
+const TAG_BYTES: usize = CRYPTO_SECRETSTREAM_XCHACHA20POLY1305_ABYTES;
+const SLOT_BYTES: usize =
+    CRYPTO_SECRETSTREAM_XCHACHA20POLY1305_HEADERBYTES + SPOOL_CHUNK_BYTES + TAG_BYTES;
+
+type Key = LockedRO<HeapByteArray<CRYPTO_SECRETSTREAM_XCHACHA20POLY1305_KEYBYTES>>;
+
+enum Storage {
+    Memory(LockedBytes),
+    File(SpoolFile),
+}
+
+struct SpoolFile {
+    file: File,
+    cache: LockedBytes,
+    cache_index: Option<u64>,
+    cache_dirty: bool,
+    // only set on platforms where the file can't be unlinked while open
+    path: Option<PathBuf>,
+}
+
+/// A [`Read`] + [`Write`] + [`Seek`] buffer which is kept in locked memory
+/// until it exceeds a threshold, after which it's spooled to an encrypted
+/// temporary file. Refer to [crate::spool] for details.
+pub struct EncryptedSpool {
+    threshold: usize,
+    dir: PathBuf,
+    key: Key,
+    storage: Storage,
+    len: u64,
+    position: u64,
+}
+
+impl EncryptedSpool {
+    /// Returns a new spool which keeps up to `threshold` bytes in locked
+    /// memory, spooling to a temporary file in [`std::env::temp_dir`] beyond
+    /// that.
+    pub fn new(threshold: usize) -> Result<Self, Error> {
+        Self::new_in(threshold, std::env::temp_dir())
+    }
+
+    /// Returns a new spool which keeps up to `threshold` bytes in locked
+    /// memory, spooling to a temporary file in `dir` beyond that.
+    pub fn new_in<P: AsRef<Path>>(threshold: usize, dir: P) -> Result<Self, Error> {
+        let mut memory = HeapBytes::new_locked()?;
+        memory.resize(threshold, 0);
+        Ok(Self {
+            threshold,
+            dir: dir.as_ref().to_path_buf(),
+            key: HeapByteArray::gen_readonly_locked()?,
+            storage: Storage::Memory(memory),
+            len: 0,
+            position: 0,
+        })
+    }
+
+    /// Returns the length of the data in this spool, in bytes.
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    /// Returns true if this spool is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns true if this spool's data has been spooled to disk.
+    pub fn is_spooled(&self) -> bool {
+        matches!(self.storage, Storage::File(_))
+    }
+
+    fn spool_to_file(&mut self) -> Result<(), Error> {
+        let memory = match &self.storage {
+            Storage::Memory(memory) => memory,
+            Storage::File(_) => return Ok(()),
+        };
+
+        let (file, path) = create_temp_file(&self.dir)?;
+        let mut spool_file = SpoolFile {
+            file,
+            cache: HeapBytes::new_locked()?,
+            cache_index: None,
+            cache_dirty: false,
+            path,
+        };
+        spool_file.cache.resize(SPOOL_CHUNK_BYTES, 0);
+
+        let data = &memory.as_slice()[..self.len as usize];
+        for (index, chunk) in data.chunks(SPOOL_CHUNK_BYTES).enumerate() {
+            spool_file.cache.as_mut_slice()[..chunk.len()].copy_from_slice(chunk);
+            spool_file.cache_index = Some(index as u64);
+            spool_file.cache_dirty = true;
+            spool_file.flush_chunk(&self.key, self.len)?;
+        }
+
+        // dropping the memory storage here zeroizes and unlocks it
+        self.storage = Storage::File(spool_file);
+        Ok(())
+    }
+
+    fn write_at_position(&mut self, buf: &[u8]) -> Result<usize, Error> {
+        let end = self.position + buf.len() as u64;
+        if end > self.threshold as u64 {
+            self.spool_to_file()?;
+        }
+
+        match &mut self.storage {
+            Storage::Memory(memory) => {
+                let start = self.position as usize;
+                memory.as_mut_slice()[start..start + buf.len()].copy_from_slice(buf);
+                self.position = end;
+                self.len = self.len.max(end);
+                Ok(buf.len())
+            }
+            Storage::File(spool_file) => {
+                let index = self.position / SPOOL_CHUNK_BYTES as u64;
+                let offset = (self.position % SPOOL_CHUNK_BYTES as u64) as usize;
+                let count = buf.len().min(SPOOL_CHUNK_BYTES - offset);
+                spool_file.load_chunk(&self.key, self.len, index)?;
+                spool_file.cache.as_mut_slice()[offset..offset + count]
+                    .copy_from_slice(&buf[..count]);
+                spool_file.cache_dirty = true;
+                self.position += count as u64;
+                self.len = self.len.max(self.position);
+                Ok(count)
+            }
+        }
+    }
+
+    fn read_at_position(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        if self.position >= self.len {
+            return Ok(0);
+        }
+        let available = (self.len - self.position).min(buf.len() as u64) as usize;
+
+        match &mut self.storage {
+            Storage::Memory(memory) => {
+                let start = self.position as usize;
+                buf[..available].copy_from_slice(&memory.as_slice()[start..start + available]);
+                self.position += available as u64;
+                Ok(available)
+            }
+            Storage::File(spool_file) => {
+                let index = self.position / SPOOL_CHUNK_BYTES as u64;
+                let offset = (self.position % SPOOL_CHUNK_BYTES as u64) as usize;
+                let count = available.min(SPOOL_CHUNK_BYTES - offset);
+                spool_file.load_chunk(&self.key, self.len, index)?;
+                buf[..count].copy_from_slice(&spool_file.cache.as_slice()[offset..offset + count]);
+                self.position += count as u64;
+                Ok(count)
+            }
+        }
+    }
+}
+
+impl SpoolFile {
+    /// Loads chunk `index` into the cache, flushing the previously cached
+    /// chunk if needed. Chunks at or beyond `len` are loaded as zeros.
+    fn load_chunk(&mut self, key: &Key, len: u64, index: u64) -> Result<(), Error> {
+        if self.cache_index == Some(index) {
+            return Ok(());
+        }
+        self.flush_chunk(key, len)?;
+
+        let cache = self.cache.as_mut_slice();
+        cache.iter_mut().for_each(|b| *b = 0);
+        let start = index * SPOOL_CHUNK_BYTES as u64;
+        if start < len {
+            let chunk_len = chunk_len(len, index);
+            let mut record =
+                vec![
+                    0u8;
+                    CRYPTO_SECRETSTREAM_XCHACHA20POLY1305_HEADERBYTES + chunk_len + TAG_BYTES
+                ];
+            self.file.seek(SeekFrom::Start(index * SLOT_BYTES as u64))?;
+            self.file.read_exact(&mut record)?;
+
+            let (header, ciphertext) =
+                record.split_at(CRYPTO_SECRETSTREAM_XCHACHA20POLY1305_HEADERBYTES);
+            let mut state = State::new();
+            crypto_secretstream_xchacha20poly1305_init_pull(
+                &mut state,
+                <&Header>::try_from(header)?,
+                key.as_array(),
+            );
+            let mut tag = 0u8;
+            crypto_secretstream_xchacha20poly1305_pull(
+                &mut state,
+                &mut cache[..chunk_len],
+                &mut tag,
+                ciphertext,
+                Some(&chunk_ad(index, chunk_len)),
+            )?;
+            if tag != CRYPTO_SECRETSTREAM_XCHACHA20POLY1305_TAG_FINAL {
+                return Err(dryoc_error!("invalid spool chunk tag"));
+            }
+        }
+        self.cache_index = Some(index);
+        Ok(())
+    }
+
+    /// Encrypts and writes the cached chunk, if it's been modified.
+    fn flush_chunk(&mut self, key: &Key, len: u64) -> Result<(), Error> {
+        let index = match self.cache_index {
+            Some(index) if self.cache_dirty => index,
+            _ => return Ok(()),
+        };
+        let chunk_len = chunk_len(len, index);
+
+        let mut record =
+            vec![0u8; CRYPTO_SECRETSTREAM_XCHACHA20POLY1305_HEADERBYTES + chunk_len + TAG_BYTES];
+        let (header, ciphertext) =
+            record.split_at_mut(CRYPTO_SECRETSTREAM_XCHACHA20POLY1305_HEADERBYTES);
+        let mut state = State::new();
+        crypto_secretstream_xchacha20poly1305_init_push(
+            &mut state,
+            <&mut Header>::try_from(header)?,
+            key.as_array(),
+        );
+        crypto_secretstream_xchacha20poly1305_push(
+            &mut state,
+            ciphertext,
+            &self.cache.as_slice()[..chunk_len],
+            Some(&chunk_ad(index, chunk_len)),
+            CRYPTO_SECRETSTREAM_XCHACHA20POLY1305_TAG_FINAL,
+        )?;
+
+        self.file.seek(SeekFrom::Start(index * SLOT_BYTES as u64))?;
+        self.file.write_all(&record)?;
+        self.cache_dirty = false;
+        Ok(())
+    }
+}
+
+impl Drop for SpoolFile {
+    fn drop(&mut self) {
+        if let Some(path) = &self.path {
+            std::fs::remove_file(path).ok();
+        }
+    }
+}
+
+fn chunk_len(len: u64, index: u64) -> usize {
+    let start = index * SPOOL_CHUNK_BYTES as u64;
+    (len.saturating_sub(start)).min(SPOOL_CHUNK_BYTES as u64) as usize
+}
+
+fn chunk_ad(index: u64, chunk_len: usize) -> [u8; 16] {
+    let mut ad = [0u8; 16];
+    ad[..8].copy_from_slice(&index.to_le_bytes());
+    ad[8..].copy_from_slice(&(chunk_len as u64).to_le_bytes());
+    ad
+}
+
+fn create_temp_file(dir: &Path) -> Result<(File, Option<PathBuf>), Error> {
+    let mut name = [0u8; 16];
+    crate::rng::copy_randombytes(&mut name);
+    let name: String = name.iter().map(|b| format!("{:02x}", b)).collect();
+    let path = dir.join(format!("dryoc-spool-{}", name));
+
+    let file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create_new(true)
+        .open(&path)?;
+
+    if cfg!(unix) {
+        std::fs::remove_file(&path)?;
+        Ok((file, None))
+    } else {
+        Ok((file, Some(path)))
+    }
+}
+
+fn to_io_error(err: Error) -> io::Error {
+    io::Error::new(io::ErrorKind::Other, err)
+}
+
+impl Write for EncryptedSpool {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        // fill any gap left by seeking past the end with zeros
+        while self.position > self.len {
+            let gap_end = self.position;
+            self.position = self.len;
+            let zeros = [0u8; 1024];
+            let count = (gap_end - self.len).min(zeros.len() as u64) as usize;
+            self.write_at_position(&zeros[..count])
+                .map_err(to_io_error)?;
+            self.position = gap_end;
+        }
+        self.write_at_position(buf).map_err(to_io_error)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match &mut self.storage {
+            Storage::Memory(_) => Ok(()),
+            Storage::File(spool_file) => {
+                spool_file
+                    .flush_chunk(&self.key, self.len)
+                    .map_err(to_io_error)?;
+                spool_file.file.flush()
+            }
+        }
+    }
+}
+
+impl Read for EncryptedSpool {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.read_at_position(buf).map_err(to_io_error)
+    }
+}
+
+impl Seek for EncryptedSpool {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let position = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::End(offset) => offset_position(self.len, offset),
+            SeekFrom::Current(offset) => offset_position(self.position, offset),
+        };
+        match position {
+            Some(position) => {
+                self.position = position;
+                Ok(position)
+            }
+            None => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "invalid seek to a negative or overflowing position",
+            )),
+        }
+    }
+}
+
+fn offset_position(base: u64, offset: i64) -> Option<u64> {
+    if offset >= 0 {
+        base.checked_add(offset as u64)
+    } else {
+        base.checked_sub(offset.unsigned_abs())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_spool() {
+        let mut spool = EncryptedSpool::new(100).expect("spool failed");
+        let mut model: Vec<u8> = Vec::new();
+
+        spool.write_all(b"in memory").expect("write failed");
+        model.extend_from_slice(b"in memory");
+        assert!(!spool.is_spooled());
+
+        let data: Vec<u8> = (0..50_000u32).map(|i| (i % 251) as u8).collect();
+        spool.write_all(&data).expect("write failed");
+        model.extend_from_slice(&data);
+        assert!(spool.is_spooled());
+        assert_eq!(spool.len(), model.len() as u64);
+
+        // overwrite across a chunk boundary
+        let position = SPOOL_CHUNK_BYTES as u64 - 3;
+        spool.seek(SeekFrom::Start(position)).expect("seek failed");
+        spool.write_all(b"boundary").expect("write failed");
+        model[position as usize..position as usize + 8].copy_from_slice(b"boundary");
+
+        // seek past the end, leaving a zero-filled gap
+        spool.seek(SeekFrom::End(40_000)).expect("seek failed");
+        spool.write_all(b"tail").expect("write failed");
+        model.resize(model.len() + 40_000, 0);
+        model.extend_from_slice(b"tail");
+        assert_eq!(spool.len(), model.len() as u64);
+
+        spool.seek(SeekFrom::Start(0)).expect("seek failed");
+        let mut read_back = Vec::new();
+        spool.read_to_end(&mut read_back).expect("read failed");
+        assert_eq!(read_back, model);
+
+        spool.seek(SeekFrom::End(-4)).expect("seek failed");
+        let mut tail = [0u8; 4];
+        spool.read_exact(&mut tail).expect("read failed");
+        assert_eq!(&tail, b"tail");
+
+        assert!(spool.seek(SeekFrom::Current(-1_000_000)).is_err());
+    }
+
+    #[test]
+    fn test_spool_tampering() {
+        let dir = std::env::temp_dir();
+        let mut spool = EncryptedSpool::new_in(0, &dir).expect("spool failed");
+        spool
+            .write_all(&vec![7u8; SPOOL_CHUNK_BYTES * 2])
+            .expect("write failed");
+        spool.flush().expect("flush failed");
+
+        if let Storage::File(spool_file) = &mut spool.storage {
+            spool_file
+                .file
+                .seek(SeekFrom::Start(SLOT_BYTES as u64 + 100))
+                .expect("seek failed");
+            spool_file.file.write_all(b"x").expect("write failed");
+        }
+
+        spool.seek(SeekFrom::Start(0)).expect("seek failed");
+        let mut buf = vec![0u8; SPOOL_CHUNK_BYTES * 2];
+        // the first chunk decrypts, but the tampered second chunk fails
+        assert!(spool.read_exact(&mut buf).is_err());
+    }
+}
@@ -0,0 +1,302 @@
+//! # Time-lock puzzles
+//!
+//! [`TimeLock`] encrypts a payload (usually a key) such that it can only be
+//! recovered after a tunable amount of sequential computation, without any
+//! trusted party. This is useful for delayed release, such as escrowing a key
+//! which should become available to anyone after roughly a day of work, even if
+//! its creator disappears.
+//!
+//! The puzzle is a sequence of hash chains, one per checkpoint. Each chain
+//! starts from a random seed, and is computed by hashing the seed
+//! `iterations_per_checkpoint` times with the [generic
+//! hash](crate::generichash) (BLAKE2b-256). The output of each chain is a
+//! [secret box](crate::dryocsecretbox) key which decrypts the seed of the next
+//! chain, and the output of the last chain decrypts the payload.
+//!
+//! Solving the puzzle requires computing the chains one after another, because
+//! each seed is only revealed by the previous chain. The creator knows every
+//! seed, and computes the chains in parallel when locking, so locking with `n`
+//! checkpoints on `n` cores takes about `1/n`th of the time needed to solve the
+//! puzzle. Each [`Checkpoint`] reached while solving can be saved, so that a
+//! long-running solver can resume after an interruption.
+//!
+//! The delay is only as accurate as your estimate of the solver's hashing
+//! speed: a faster machine solves the puzzle sooner, and hash chains can't be
+//! sped up by parallelism, but can be with specialized hardware. Measure the
+//! speed of the hardware you expect solvers to use, and choose the number of
+//! iterations with a margin for error.
+//!
+//! If the `serde` feature is enabled, the [`serde::Deserialize`] and
+//! [`serde::Serialize`] traits will be implemented for [`TimeLock`] and
+//! [`Checkpoint`].
+//!
+//! ## Example
+//!
+//! ```
+//! use dryoc::dryocsecretbox::Key;
+//! use dryoc::timelock::*;
+//! use dryoc::types::*;
+//!
+//! let key = Key::gen();
+//!
+//! // In practice, use many more iterations, such as 10^9 or more
+//! let timelock = TimeLock::lock(&key, 1000, 4).expect("lock failed");
+//!
+//! let mut checkpoint = timelock.start();
+//! let unlocked: Vec<u8> = loop {
+//!     match timelock.advance(&checkpoint).expect("advance failed") {
+//!         Step::Checkpoint(next) => {
+//!             // Save `next` to resume from here later
+//!             checkpoint = next;
+//!         }
+//!         Step::Unlocked(payload) => break payload,
+//!     }
+//! };
+//! assert_eq!(unlocked, key.as_slice());
+//! ```
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use zeroize::{Zeroize, ZeroizeOnDrop};
+
+use crate::constants::{
+    CRYPTO_GENERICHASH_BYTES, CRYPTO_GENERICHASH_KEYBYTES, CRYPTO_SECRETBOX_KEYBYTES,
+};
+use crate::dryocsecretbox::{DryocSecretBox, Key, Nonce, VecBox};
+use crate::error::Error;
+use crate::generichash::GenericHash;
+use crate::types::*;
+
+/// Length of each hash chain's seed, in bytes.
+pub const TIMELOCK_SEEDBYTES: usize = CRYPTO_GENERICHASH_BYTES;
+/// Maximum number of checkpoints in a [`TimeLock`].
+pub const TIMELOCK_MAX_CHECKPOINTS: u32 = 1 << 16;
+
+/// Seed of a hash chain.
+pub type Seed = StackByteArray<TIMELOCK_SEEDBYTES>;
+
+#[cfg_attr(
+    feature = "serde",
+    derive(Zeroize, Clone, Debug, Serialize, Deserialize)
+)]
+#[cfg_attr(not(feature = "serde"), derive(Zeroize, Clone, Debug))]
+/// A payload locked by a time-lock puzzle.
+///
+/// Refer to [crate::timelock] for sample usage.
+pub struct TimeLock {
+    iterations_per_checkpoint: u64,
+    first_seed: Seed,
+    /// The seed of each chain after the first, encrypted with the output of
+    /// the previous chain
+    locked_seeds: Vec<VecBox>,
+    /// The payload, encrypted with the output of the last chain
+    locked_payload: VecBox,
+}
+
+#[cfg_attr(
+    feature = "serde",
+    derive(Zeroize, ZeroizeOnDrop, Clone, Debug, Serialize, Deserialize)
+)]
+#[cfg_attr(not(feature = "serde"), derive(Zeroize, ZeroizeOnDrop, Clone, Debug))]
+/// Progress towards solving a [`TimeLock`]: the index of the next hash chain
+/// to compute, and its seed.
+pub struct Checkpoint {
+    index: u32,
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::redact::serialize_secret")
+    )]
+    seed: Seed,
+}
+
+impl Checkpoint {
+    /// Returns the index of the next hash chain to compute.
+    pub fn index(&self) -> u32 {
+        self.index
+    }
+}
+
+/// Result of [`TimeLock::advance`].
+pub enum Step<Output> {
+    /// The next checkpoint, if there are hash chains remaining
+    Checkpoint(Checkpoint),
+    /// The unlocked payload, after the last hash chain
+    Unlocked(Output),
+}
+
+/// Computes a hash chain of `iterations` from `seed`, returning a secret box
+/// key.
+fn hash_chain(seed: &Seed, iterations: u64) -> Key {
+    let mut state = *seed.as_array();
+    for _ in 0..iterations {
+        state =
+            GenericHash::<CRYPTO_GENERICHASH_KEYBYTES, CRYPTO_SECRETBOX_KEYBYTES>::digest(&state);
+    }
+    let key = Key::from(state);
+    state.zeroize();
+    key
+}
+
+// Each key is only used once, so a fixed nonce is safe
+fn nonce() -> Nonce {
+    Nonce::default()
+}
+
+impl TimeLock {
+    /// Locks `payload` behind `checkpoints` hash chains of
+    /// `iterations_per_checkpoint` iterations each. Solving the puzzle takes
+    /// `checkpoints * iterations_per_checkpoint` sequential hashes, while
+    /// locking computes the chains in parallel, using up to one thread per
+    /// core.
+    pub fn lock<Payload: Bytes + ?Sized>(
+        payload: &Payload,
+        iterations_per_checkpoint: u64,
+        checkpoints: u32,
+    ) -> Result<Self, Error> {
+        validate!(1, TIMELOCK_MAX_CHECKPOINTS, checkpoints, "checkpoints");
+        if iterations_per_checkpoint == 0 {
+            return Err(dryoc_error!("iterations_per_checkpoint must be at least 1"));
+        }
+
+        let seeds: Vec<Seed> = (0..checkpoints).map(|_| Seed::gen()).collect();
+        let threads = std::thread::available_parallelism()
+            .map(|n| n.get())
+            .unwrap_or(1);
+        let chunk_size = (seeds.len() + threads - 1) / threads;
+        let keys: Vec<Key> = std::thread::scope(|scope| {
+            let handles: Vec<_> = seeds
+                .chunks(chunk_size)
+                .map(|chunk| {
+                    scope.spawn(move || {
+                        chunk
+                            .iter()
+                            .map(|seed| hash_chain(seed, iterations_per_checkpoint))
+                            .collect::<Vec<_>>()
+                    })
+                })
+                .collect();
+            handles
+                .into_iter()
+                .flat_map(|handle| handle.join().expect("hash chain thread panicked"))
+                .collect()
+        });
+
+        let locked_seeds = keys
+            .iter()
+            .zip(seeds.iter().skip(1))
+            .map(|(key, seed)| DryocSecretBox::try_encrypt_to_vecbox(seed, &nonce(), key))
+            .collect::<Result<_, _>>()?;
+        let locked_payload = DryocSecretBox::try_encrypt_to_vecbox(
+            payload,
+            &nonce(),
+            keys.last().expect("at least one checkpoint"),
+        )?;
+
+        Ok(Self {
+            iterations_per_checkpoint,
+            first_seed: seeds[0].clone(),
+            locked_seeds,
+            locked_payload,
+        })
+    }
+
+    /// Returns the number of hash iterations between checkpoints.
+    pub fn iterations_per_checkpoint(&self) -> u64 {
+        self.iterations_per_checkpoint
+    }
+
+    /// Returns the number of checkpoints, i.e., hash chains.
+    pub fn checkpoints(&self) -> u32 {
+        self.locked_seeds.len() as u32 + 1
+    }
+
+    /// Returns the checkpoint from which to start solving the puzzle.
+    pub fn start(&self) -> Checkpoint {
+        Checkpoint {
+            index: 0,
+            seed: self.first_seed.clone(),
+        }
+    }
+
+    /// Computes the hash chain following `checkpoint`, which takes
+    /// [`iterations_per_checkpoint`](TimeLock::iterations_per_checkpoint)
+    /// hashes, and returns the next checkpoint, or the payload if this was the
+    /// last chain. Returns an error if `checkpoint` doesn't belong to this
+    /// puzzle.
+    pub fn advance<Output: NewBytes + ResizableBytes + Zeroize>(
+        &self,
+        checkpoint: &Checkpoint,
+    ) -> Result<Step<Output>, Error> {
+        let index = checkpoint.index as usize;
+        if index > self.locked_seeds.len() {
+            return Err(dryoc_error!(format!(
+                "checkpoint {} is out of range",
+                checkpoint.index
+            )));
+        }
+
+        let key = hash_chain(&checkpoint.seed, self.iterations_per_checkpoint);
+        match self.locked_seeds.get(index) {
+            Some(locked_seed) => {
+                let mut seed: Vec<u8> = locked_seed.decrypt(&nonce(), &key)?;
+                let next = Seed::try_from(seed.as_slice());
+                seed.zeroize();
+                Ok(Step::Checkpoint(Checkpoint {
+                    index: checkpoint.index + 1,
+                    seed: next?,
+                }))
+            }
+            None => Ok(Step::Unlocked(self.locked_payload.decrypt(&nonce(), &key)?)),
+        }
+    }
+
+    /// Solves the puzzle from the start, and returns the payload. This can
+    /// take a long time; use [`TimeLock::advance`] to report progress, or to
+    /// save checkpoints.
+    pub fn unlock<Output: NewBytes + ResizableBytes + Zeroize>(&self) -> Result<Output, Error> {
+        let mut checkpoint = self.start();
+        loop {
+            match self.advance(&checkpoint)? {
+                Step::Checkpoint(next) => checkpoint = next,
+                Step::Unlocked(payload) => return Ok(payload),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_timelock() {
+        let payload = b"release the kraken";
+        let timelock = TimeLock::lock(payload, 100, 3).expect("lock");
+        assert_eq!(timelock.checkpoints(), 3);
+
+        let unlocked: Vec<u8> = timelock.unlock().expect("unlock");
+        assert_eq!(unlocked, payload);
+
+        // Resume from a saved checkpoint
+        let checkpoint = match timelock.advance::<Vec<u8>>(&timelock.start()) {
+            Ok(Step::Checkpoint(checkpoint)) => checkpoint,
+            _ => panic!("expected a checkpoint"),
+        };
+        assert_eq!(checkpoint.index(), 1);
+        let checkpoint = match timelock.advance::<Vec<u8>>(&checkpoint.clone()) {
+            Ok(Step::Checkpoint(checkpoint)) => checkpoint,
+            _ => panic!("expected a checkpoint"),
+        };
+        match timelock.advance::<Vec<u8>>(&checkpoint) {
+            Ok(Step::Unlocked(unlocked)) => assert_eq!(unlocked, payload),
+            _ => panic!("expected the payload"),
+        }
+
+        // A checkpoint from another puzzle doesn't decrypt
+        let other = TimeLock::lock(payload, 100, 3).expect("lock");
+        assert!(timelock.advance::<Vec<u8>>(&other.start()).is_err());
+
+        assert!(TimeLock::lock(payload, 0, 1).is_err());
+        assert!(TimeLock::lock(payload, 1, 0).is_err());
+    }
+}
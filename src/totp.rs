@@ -0,0 +1,347 @@
+//! # One-time passwords
+//!
+//! [`Hotp`] and [`Totp`] implement HMAC-based one-time passwords ([RFC
+//! 4226](https://www.rfc-editor.org/rfc/rfc4226)) and time-based one-time
+//! passwords ([RFC 6238](https://www.rfc-editor.org/rfc/rfc6238)), with
+//! HMAC-SHA1, HMAC-SHA256, or HMAC-SHA512. These are compatible with the
+//! usual authenticator apps, which default to [`Algorithm::Sha1`], 6 digits,
+//! and a 30 second time step.
+//!
+//! The shared secret is held in locked memory for the lifetime of the
+//! generator, and codes are verified in constant time: every candidate within
+//! the window is computed and compared, regardless of where (or whether) a
+//! match is found.
+//!
+//! Verification returns the counter (or time step) which matched. Callers
+//! should store it, and reject codes for counters or time steps they've
+//! already accepted, to prevent codes from being replayed.
+//!
+//! ## Example
+//!
+//! ```
+//! use dryoc::totp::*;
+//!
+//! let totp =
+//!     Totp::from_slice(b"12345678901234567890", Algorithm::Sha1, 6, 30).expect("totp failed");
+//!
+//! let code = totp.generate_at(1_111_111_109);
+//! assert_eq!(code, "081804");
+//!
+//! // Accept codes from one time step either side of the current time
+//! let step = totp
+//!     .verify_at(&code, 1_111_111_109 + 30, 1)
+//!     .expect("verify failed");
+//! assert_eq!(step, 1_111_111_109 / 30);
+//!
+//! assert!(totp.verify_at(&code, 1_111_111_109 + 90, 1).is_err());
+//! ```
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use sha1::Sha1;
+use sha2::digest::Digest;
+use sha2::digest::core_api::BlockSizeUser;
+use sha2::{Sha256, Sha512};
+use subtle::{Choice, ConditionallySelectable, ConstantTimeEq};
+use zeroize::Zeroize;
+
+use crate::error::Error;
+use crate::hkdf::hmac;
+use crate::protected::*;
+
+/// Minimum number of digits in a code.
+pub const DIGITS_MIN: u32 = 6;
+/// Maximum number of digits in a code.
+pub const DIGITS_MAX: u32 = 9;
+/// Default TOTP time step, in seconds.
+pub const TOTP_STEP_DEFAULT: u64 = 30;
+
+/// HMAC hash function used to generate codes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Algorithm {
+    /// HMAC-SHA1, the default for RFC 4226 and most authenticator apps
+    #[default]
+    Sha1,
+    /// HMAC-SHA256
+    Sha256,
+    /// HMAC-SHA512
+    Sha512,
+}
+
+/// HMAC-based one-time password generator, as described in RFC 4226. Refer to
+/// [crate::totp] for details.
+pub struct Hotp {
+    secret: LockedBytes,
+    algorithm: Algorithm,
+    digits: u32,
+}
+
+impl Hotp {
+    /// Returns a new HOTP generator for `secret`, producing codes of `digits`
+    /// length, which must be between [`DIGITS_MIN`] and [`DIGITS_MAX`].
+    pub fn new(secret: LockedBytes, algorithm: Algorithm, digits: u32) -> Result<Self, Error> {
+        validate!(DIGITS_MIN, DIGITS_MAX, digits, "digits");
+
+        Ok(Self {
+            secret,
+            algorithm,
+            digits,
+        })
+    }
+
+    /// Copies `secret` into locked memory, and returns a new HOTP generator
+    /// for it. Refer to [`Hotp::new`] for details.
+    pub fn from_slice(secret: &[u8], algorithm: Algorithm, digits: u32) -> Result<Self, Error> {
+        Self::new(
+            HeapBytes::from_slice_into_locked(secret)?,
+            algorithm,
+            digits,
+        )
+    }
+
+    /// Returns the algorithm used by this generator.
+    pub fn algorithm(&self) -> Algorithm {
+        self.algorithm
+    }
+
+    /// Returns the number of digits in codes from this generator.
+    pub fn digits(&self) -> u32 {
+        self.digits
+    }
+
+    /// Returns the code for `counter`.
+    pub fn generate(&self, counter: u64) -> String {
+        let mut code = self.code(counter);
+        let result = String::from_utf8_lossy(&code[..self.digits as usize]).into_owned();
+        code.zeroize();
+        result
+    }
+
+    /// Verifies `code` against the codes for each counter from `counter` to
+    /// `counter + look_ahead` (inclusive), in constant time. Returns the
+    /// counter which follows the matched one, which should be stored and
+    /// used for the next verification.
+    pub fn verify(&self, code: &str, counter: u64, look_ahead: u64) -> Result<u64, Error> {
+        self.verify_range(code, counter, counter.saturating_add(look_ahead))
+            .map(|matched| matched + 1)
+    }
+
+    fn verify_range(&self, code: &str, first: u64, last: u64) -> Result<u64, Error> {
+        let code = code.as_bytes();
+        if code.len() != self.digits as usize {
+            return Err(dryoc_error!(format!(
+                "code length of {} doesn't match {} digits",
+                code.len(),
+                self.digits
+            )));
+        }
+
+        let mut found = Choice::from(0);
+        let mut matched = 0u64;
+        for counter in first..=last {
+            let mut candidate = self.code(counter);
+            let is_match = candidate[..code.len()].ct_eq(code);
+            matched.conditional_assign(&counter, is_match);
+            found |= is_match;
+            candidate.zeroize();
+        }
+
+        if found.unwrap_u8() == 1 {
+            Ok(matched)
+        } else {
+            Err(dryoc_error!("one-time passwords do not match"))
+        }
+    }
+
+    fn code(&self, counter: u64) -> [u8; DIGITS_MAX as usize] {
+        let secret = self.secret.as_slice();
+        let mut value = match self.algorithm {
+            Algorithm::Sha1 => truncate::<Sha1>(secret, counter),
+            Algorithm::Sha256 => truncate::<Sha256>(secret, counter),
+            Algorithm::Sha512 => truncate::<Sha512>(secret, counter),
+        };
+
+        let mut code = [0u8; DIGITS_MAX as usize];
+        for digit in code[..self.digits as usize].iter_mut().rev() {
+            *digit = b'0' + (value % 10) as u8;
+            value /= 10;
+        }
+        value.zeroize();
+
+        code
+    }
+}
+
+/// Time-based one-time password generator, as described in RFC 6238. Refer
+/// to [crate::totp] for details.
+pub struct Totp {
+    hotp: Hotp,
+    step: u64,
+}
+
+impl Totp {
+    /// Returns a new TOTP generator for `secret`, producing codes of `digits`
+    /// length which change every `step` seconds. Use [`TOTP_STEP_DEFAULT`]
+    /// for the usual 30 second step.
+    pub fn new(
+        secret: LockedBytes,
+        algorithm: Algorithm,
+        digits: u32,
+        step: u64,
+    ) -> Result<Self, Error> {
+        if step == 0 {
+            return Err(dryoc_error!("time step must be greater than 0"));
+        }
+
+        Ok(Self {
+            hotp: Hotp::new(secret, algorithm, digits)?,
+            step,
+        })
+    }
+
+    /// Copies `secret` into locked memory, and returns a new TOTP generator
+    /// for it. Refer to [`Totp::new`] for details.
+    pub fn from_slice(
+        secret: &[u8],
+        algorithm: Algorithm,
+        digits: u32,
+        step: u64,
+    ) -> Result<Self, Error> {
+        Self::new(
+            HeapBytes::from_slice_into_locked(secret)?,
+            algorithm,
+            digits,
+            step,
+        )
+    }
+
+    /// Returns the time step of this generator, in seconds.
+    pub fn step(&self) -> u64 {
+        self.step
+    }
+
+    /// Returns the code for `unix_time`, in seconds since the Unix epoch.
+    pub fn generate_at(&self, unix_time: u64) -> String {
+        self.hotp.generate(unix_time / self.step)
+    }
+
+    /// Returns the code for the current system time.
+    pub fn generate_now(&self) -> Result<String, Error> {
+        Ok(self.generate_at(now()?))
+    }
+
+    /// Verifies `code` against the codes for each time step within `window`
+    /// steps either side of `unix_time`, in constant time. Returns the time
+    /// step which matched.
+    pub fn verify_at(&self, code: &str, unix_time: u64, window: u64) -> Result<u64, Error> {
+        let step = unix_time / self.step;
+        self.hotp.verify_range(
+            code,
+            step.saturating_sub(window),
+            step.saturating_add(window),
+        )
+    }
+
+    /// Verifies `code` against the current system time. Refer to
+    /// [`Totp::verify_at`] for details.
+    pub fn verify_now(&self, code: &str, window: u64) -> Result<u64, Error> {
+        self.verify_at(code, now()?, window)
+    }
+}
+
+fn now() -> Result<u64, Error> {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .map_err(|err| dryoc_error!(format!("system time before Unix epoch: {}", err)))
+}
+
+/// Computes the HMAC of `counter` with `key`, and applies the dynamic
+/// truncation described in RFC 4226, section 5.3.
+fn truncate<D: Digest + BlockSizeUser>(key: &[u8], counter: u64) -> u32 {
+    let mut mac = hmac::<D>(key, &[&counter.to_be_bytes()]);
+    let offset = (mac[mac.len() - 1] & 0xf) as usize;
+    let value = u32::from_be_bytes([
+        mac[offset] & 0x7f,
+        mac[offset + 1],
+        mac[offset + 2],
+        mac[offset + 3],
+    ]);
+    mac.as_mut_slice().zeroize();
+
+    value
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hotp_rfc4226() {
+        // RFC 4226, appendix D
+        let hotp = Hotp::from_slice(b"12345678901234567890", Algorithm::Sha1, 6).expect("hotp");
+        let expected = [
+            "755224", "287082", "359152", "969429", "338314", "254676", "287922", "162583",
+            "399871", "520489",
+        ];
+        for (counter, code) in expected.iter().enumerate() {
+            assert_eq!(hotp.generate(counter as u64), *code);
+        }
+
+        assert_eq!(hotp.verify("969429", 1, 5).expect("verify"), 4);
+        assert!(hotp.verify("969429", 4, 5).is_err());
+        assert!(hotp.verify("969429", 0, 2).is_err());
+        assert!(hotp.verify("96942", 0, 5).is_err());
+
+        assert!(Hotp::from_slice(b"secret", Algorithm::Sha1, 5).is_err());
+        assert!(Hotp::from_slice(b"secret", Algorithm::Sha1, 10).is_err());
+    }
+
+    #[test]
+    fn test_totp_rfc6238() {
+        // RFC 6238, appendix B
+        let sha1 = Totp::from_slice(b"12345678901234567890", Algorithm::Sha1, 8, 30).expect("sha1");
+        let sha256 = Totp::from_slice(
+            b"12345678901234567890123456789012",
+            Algorithm::Sha256,
+            8,
+            30,
+        )
+        .expect("sha256");
+        let sha512 = Totp::from_slice(
+            b"1234567890123456789012345678901234567890123456789012345678901234",
+            Algorithm::Sha512,
+            8,
+            30,
+        )
+        .expect("sha512");
+
+        let vectors = [
+            (59, "94287082", "46119246", "90693936"),
+            (1111111109, "07081804", "68084774", "25091201"),
+            (1111111111, "14050471", "67062674", "99943326"),
+            (1234567890, "89005924", "91819424", "93441116"),
+            (2000000000, "69279037", "90698825", "38618901"),
+            (20000000000, "65353130", "77737706", "47863826"),
+        ];
+        for (time, code1, code256, code512) in vectors {
+            assert_eq!(sha1.generate_at(time), code1);
+            assert_eq!(sha256.generate_at(time), code256);
+            assert_eq!(sha512.generate_at(time), code512);
+
+            assert_eq!(
+                sha256.verify_at(code256, time, 0).expect("verify"),
+                time / 30
+            );
+            assert_eq!(
+                sha512.verify_at(code512, time - 30, 1).expect("verify"),
+                time / 30
+            );
+            assert!(sha1.verify_at(code1, time + 60, 1).is_err());
+        }
+
+        let code = sha1.generate_now().expect("generate");
+        sha1.verify_now(&code, 1).expect("verify");
+
+        assert!(Totp::from_slice(b"secret", Algorithm::Sha1, 6, 0).is_err());
+    }
+}
@@ -0,0 +1,187 @@
+//! # Transcript hashing
+//!
+//! [`Transcript`] is a running hash of the messages exchanged in an
+//! interactive protocol, in the style of STROBE and Merlin, built on the
+//! [generic hash](crate::generichash) (BLAKE2b-512). Protocol designers can
+//! use it to bind every message to everything that came before it, and to
+//! derive challenges for the Fiat–Shamir transform, which turns an interactive
+//! proof into a non-interactive one.
+//!
+//! Every operation is labeled, and the labels, along with the lengths of all
+//! inputs, are hashed into the transcript. This provides domain separation:
+//! the same bytes absorbed under different labels, or split into messages
+//! differently, result in different transcripts, and therefore different
+//! challenges. A transcript is initialized with a protocol label, so that
+//! transcripts of different protocols never collide.
+//!
+//! The transcript keeps a 64 byte chaining value. Absorbing a message with
+//! label `l` replaces the chaining value `s` with
+//!
+//! `BLAKE2b-512(s ‖ "A" ‖ len(l) ‖ l ‖ len(m) ‖ m)`
+//!
+//! where lengths are 8 byte little-endian integers. A challenge of `n` bytes
+//! computes the seed `k = BLAKE2b-512(s ‖ "C" ‖ len(l) ‖ l ‖ n)`, outputs
+//! `BLAKE2b-512(key = k, "O" ‖ i)` for block `i = 0, 1, ...`, truncated to `n`
+//! bytes, and replaces the chaining value with `BLAKE2b-512(key = k, "S")`, so
+//! every challenge also depends on the challenges before it.
+//!
+//! Both parties must perform the same sequence of operations, with the same
+//! labels, in the same order, to arrive at the same challenges.
+//!
+//! ## Example
+//!
+//! ```
+//! use dryoc::transcript::Transcript;
+//!
+//! // The prover and verifier each keep a transcript of the protocol
+//! let mut prover = Transcript::new(b"example-protocol-v1");
+//! let mut verifier = Transcript::new(b"example-protocol-v1");
+//!
+//! prover.absorb(b"commitment", b"prover's commitment");
+//! let challenge: [u8; 32] = prover.challenge(b"challenge");
+//!
+//! // The verifier absorbs the same message, and derives the same challenge
+//! verifier.absorb(b"commitment", b"prover's commitment");
+//! assert_eq!(verifier.challenge::<32>(b"challenge"), challenge);
+//! ```
+
+use zeroize::{Zeroize, ZeroizeOnDrop};
+
+use crate::constants::{CRYPTO_GENERICHASH_BYTES_MAX, CRYPTO_GENERICHASH_KEYBYTES_MAX};
+use crate::generichash::GenericHash;
+
+/// Length of a transcript's chaining value, in bytes.
+pub const TRANSCRIPT_STATEBYTES: usize = CRYPTO_GENERICHASH_BYTES_MAX;
+
+const DOMAIN: &[u8] = b"dryoc-transcript-v1";
+const OP_ABSORB: u8 = b'A';
+const OP_CHALLENGE: u8 = b'C';
+const OP_OUTPUT: u8 = b'O';
+const OP_STATE: u8 = b'S';
+
+type Blake2b512 = GenericHash<CRYPTO_GENERICHASH_KEYBYTES_MAX, CRYPTO_GENERICHASH_BYTES_MAX>;
+
+fn new_hasher(key: Option<&[u8; CRYPTO_GENERICHASH_KEYBYTES_MAX]>) -> Blake2b512 {
+    Blake2b512::new(key).expect("valid hash parameters")
+}
+
+fn finalize(hasher: Blake2b512) -> [u8; TRANSCRIPT_STATEBYTES] {
+    hasher.finalize().expect("valid hash parameters")
+}
+
+fn update_prefixed(hasher: &mut Blake2b512, data: &[u8]) {
+    hasher.update(&(data.len() as u64).to_le_bytes());
+    hasher.update(data);
+}
+
+/// A labeled transcript of a protocol's messages, from which challenges are
+/// derived.
+///
+/// Refer to [crate::transcript] for details and sample usage.
+#[derive(Clone, Zeroize, ZeroizeOnDrop)]
+pub struct Transcript {
+    state: [u8; TRANSCRIPT_STATEBYTES],
+}
+
+impl Transcript {
+    /// Returns a new transcript for the protocol identified by
+    /// `protocol_label`, which should be unique to the protocol, and include
+    /// its version.
+    pub fn new(protocol_label: &[u8]) -> Self {
+        let mut hasher = new_hasher(None);
+        update_prefixed(&mut hasher, DOMAIN);
+        update_prefixed(&mut hasher, protocol_label);
+        Self {
+            state: finalize(hasher),
+        }
+    }
+
+    /// Absorbs `message` into the transcript, under `label`.
+    pub fn absorb(&mut self, label: &[u8], message: &[u8]) {
+        let mut hasher = new_hasher(None);
+        hasher.update(&self.state);
+        hasher.update(&[OP_ABSORB]);
+        update_prefixed(&mut hasher, label);
+        update_prefixed(&mut hasher, message);
+        self.state = finalize(hasher);
+    }
+
+    /// Absorbs `value` into the transcript as an 8 byte little-endian
+    /// integer, under `label`.
+    pub fn absorb_u64(&mut self, label: &[u8], value: u64) {
+        self.absorb(label, &value.to_le_bytes())
+    }
+
+    /// Fills `output` with a challenge derived from the transcript so far,
+    /// under `label`. The challenge is absorbed into the transcript, so
+    /// subsequent challenges differ.
+    pub fn challenge_bytes(&mut self, label: &[u8], output: &mut [u8]) {
+        let mut hasher = new_hasher(None);
+        hasher.update(&self.state);
+        hasher.update(&[OP_CHALLENGE]);
+        update_prefixed(&mut hasher, label);
+        hasher.update(&(output.len() as u64).to_le_bytes());
+        let mut seed = finalize(hasher);
+
+        for (i, chunk) in output.chunks_mut(TRANSCRIPT_STATEBYTES).enumerate() {
+            let mut hasher = new_hasher(Some(&seed));
+            hasher.update(&[OP_OUTPUT]);
+            hasher.update(&(i as u64).to_le_bytes());
+            let mut block = finalize(hasher);
+            chunk.copy_from_slice(&block[..chunk.len()]);
+            block.zeroize();
+        }
+
+        let mut hasher = new_hasher(Some(&seed));
+        hasher.update(&[OP_STATE]);
+        self.state = finalize(hasher);
+        seed.zeroize();
+    }
+
+    /// Returns a challenge of `LENGTH` bytes derived from the transcript so
+    /// far, under `label`. See [`Transcript::challenge_bytes`].
+    pub fn challenge<const LENGTH: usize>(&mut self, label: &[u8]) -> [u8; LENGTH] {
+        let mut output = [0u8; LENGTH];
+        self.challenge_bytes(label, &mut output);
+        output
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_transcript() {
+        let transcript = |messages: &[(&[u8], &[u8])]| {
+            let mut transcript = Transcript::new(b"test");
+            for (label, message) in messages {
+                transcript.absorb(label, message);
+            }
+            transcript.challenge::<32>(b"challenge")
+        };
+
+        let challenge = transcript(&[(b"a", b"hello"), (b"b", b"world")]);
+        assert_eq!(challenge, transcript(&[(b"a", b"hello"), (b"b", b"world")]));
+        // labels, message boundaries and order are all bound
+        assert_ne!(challenge, transcript(&[(b"a", b"hello"), (b"c", b"world")]));
+        assert_ne!(challenge, transcript(&[(b"a", b"hellow"), (b"b", b"orld")]));
+        assert_ne!(challenge, transcript(&[(b"b", b"world"), (b"a", b"hello")]));
+        assert_ne!(challenge, transcript(&[(b"ah", b"ello"), (b"b", b"world")]));
+
+        let mut a = Transcript::new(b"test");
+        let mut b = Transcript::new(b"other");
+        assert_ne!(a.challenge::<32>(b"c"), b.challenge::<32>(b"c"));
+
+        // successive challenges differ, and challenges are bound to their
+        // length
+        let mut a = Transcript::new(b"test");
+        let mut b = a.clone();
+        let first: [u8; 32] = a.challenge(b"c");
+        assert_ne!(first, a.challenge::<32>(b"c"));
+        let mut long = [0u8; 200];
+        b.challenge_bytes(b"c", &mut long);
+        assert_ne!(&long[..32], &first);
+        assert_ne!(&long[..64], &long[64..128]);
+    }
+}
@@ -0,0 +1,642 @@
+//! # TreeKEM ratchet tree building blocks
+//!
+//! Low-level building blocks for TreeKEM, the continuous group key agreement
+//! protocol at the core of Messaging Layer Security (MLS), as described in
+//! [RFC 9420](https://www.rfc-editor.org/rfc/rfc9420). This module provides
+//! the ratchet tree, the derivation of path and node secrets, and the
+//! encryption of path secrets to the copath with [HPKE](crate::hpke), using
+//! the primitives of the `MLS_128_DHKEMX25519_CHACHA20POLY1305_SHA256_Ed25519`
+//! ciphersuite.
+//!
+//! It does not implement the rest of MLS (framing, proposals, commits, the key
+//! schedule, or credentials), which are left to the application. In
+//! particular, the `group_context` passed to each function must be the
+//! serialized MLS `GroupContext` (or an equivalent application-defined
+//! context), which binds the encrypted path secrets to the group's state.
+//!
+//! The tree is stored in the array representation from RFC 9420, section 4.1:
+//! leaves are at even node indices, and parent nodes at odd node indices. The
+//! number of leaves must be a power of two, with unoccupied leaves left blank.
+//!
+//! ## Example
+//!
+//! ```
+//! use dryoc::kem::*;
+//! use dryoc::treekem::*;
+//!
+//! // Each member of a group of 4 generates a leaf keypair
+//! let mut members: Vec<PrivateState> = (0..4)
+//!     .map(|leaf| PrivateState::new(leaf, DhKemX25519::gen_keypair()))
+//!     .collect();
+//! let mut trees: Vec<RatchetTree> = (0..4)
+//!     .map(|_| {
+//!         let mut tree = RatchetTree::new(4).expect("new tree failed");
+//!         for member in &members {
+//!             tree.set_leaf(member.leaf(), Some(member.leaf_public_key().clone()))
+//!                 .expect("set leaf failed");
+//!         }
+//!         tree
+//!     })
+//!     .collect();
+//!
+//! // Member 0 updates its path, and sends the update path to the group
+//! let (path, commit_secret) = trees[0]
+//!     .encap(&mut members[0], b"group context")
+//!     .expect("encap failed");
+//!
+//! // Every other member processes the update path, and derives the same
+//! // commit secret
+//! for i in 1..4 {
+//!     let secret = trees[i]
+//!         .decap(&mut members[i], 0, &path, b"group context")
+//!         .expect("decap failed");
+//!     assert_eq!(secret, commit_secret);
+//! }
+//! ```
+use std::collections::BTreeMap;
+
+use sha2::Sha256;
+use zeroize::Zeroize;
+
+use crate::error::Error;
+use crate::kem::{Ciphertext, DhKemX25519, Kem};
+use crate::keypair::{PublicKey, StackKeyPair};
+use crate::rng::copy_randombytes;
+use crate::types::*;
+use crate::{hkdf, hpke};
+
+/// Length of path secrets, node secrets, and the commit secret.
+pub const TREEKEM_SECRETBYTES: usize = 32;
+
+/// A path secret, node secret, or commit secret.
+pub type Secret = StackByteArray<TREEKEM_SECRETBYTES>;
+
+/// Ratchet tree array arithmetic, from RFC 9420, appendix C. Node indices are
+/// `u32`, and the number of leaves `n` must be a power of two.
+pub mod math {
+    /// Returns the level of node `x` in the tree. Leaves are at level 0.
+    pub fn level(x: u32) -> u32 {
+        x.trailing_ones()
+    }
+
+    /// Returns the number of nodes in a tree with `n` leaves.
+    pub fn node_width(n: u32) -> u32 {
+        if n == 0 { 0 } else { 2 * (n - 1) + 1 }
+    }
+
+    /// Returns the index of the root node of a tree with `n` leaves.
+    pub fn root(n: u32) -> u32 {
+        match node_width(n) {
+            0 => 0,
+            w => (1 << (31 - w.leading_zeros())) - 1,
+        }
+    }
+
+    /// Returns the left child of the parent node `x`.
+    pub fn left(x: u32) -> u32 {
+        let k = level(x);
+        debug_assert!(k > 0, "leaf nodes have no children");
+        x ^ (1 << (k - 1))
+    }
+
+    /// Returns the right child of the parent node `x`.
+    pub fn right(x: u32) -> u32 {
+        let k = level(x);
+        debug_assert!(k > 0, "leaf nodes have no children");
+        x ^ (3 << (k - 1))
+    }
+
+    /// Returns the parent of node `x`, in a tree with `n` leaves. The root
+    /// node has no parent.
+    pub fn parent(x: u32, n: u32) -> Option<u32> {
+        if x == root(n) {
+            return None;
+        }
+        let k = level(x);
+        let b = (x >> (k + 1)) & 1;
+        Some((x | (1 << k)) ^ (b << (k + 1)))
+    }
+
+    /// Returns the sibling of node `x`, in a tree with `n` leaves.
+    pub fn sibling(x: u32, n: u32) -> Option<u32> {
+        let p = parent(x, n)?;
+        Some(if x < p { right(p) } else { left(p) })
+    }
+
+    /// Returns the direct path of node `x`: its ancestors, from its parent up
+    /// to and including the root.
+    pub fn direct_path(x: u32, n: u32) -> Vec<u32> {
+        let mut path = vec![];
+        let mut x = x;
+        while let Some(p) = parent(x, n) {
+            path.push(p);
+            x = p;
+        }
+        path
+    }
+
+    /// Returns the copath of node `x`: the sibling of `x`, followed by the
+    /// siblings of each node in its direct path, excluding the root.
+    pub fn copath(x: u32, n: u32) -> Vec<u32> {
+        std::iter::once(x)
+            .chain(direct_path(x, n))
+            .filter_map(|node| sibling(node, n))
+            .collect()
+    }
+
+    /// Returns `true` if node `x` is in the subtree rooted at node `root`.
+    pub fn is_in_subtree(x: u32, root: u32) -> bool {
+        let half_width = (1u32 << level(root)) - 1;
+        x >= root - half_width && x <= root + half_width
+    }
+}
+
+fn encode_varint(output: &mut Vec<u8>, len: usize) {
+    if len < 1 << 6 {
+        output.push(len as u8);
+    } else if len < 1 << 14 {
+        output.extend_from_slice(&(0x4000 | len as u16).to_be_bytes());
+    } else {
+        output.extend_from_slice(&(0x8000_0000 | len as u32).to_be_bytes());
+    }
+}
+
+fn encode_labeled(label: &[u8], context: &[u8]) -> Result<Vec<u8>, Error> {
+    const PREFIX: &[u8] = b"MLS 1.0 ";
+
+    if context.len() >= 1 << 30 {
+        return Err(dryoc_error!("context too long"));
+    }
+    let mut output = Vec::with_capacity(PREFIX.len() + label.len() + context.len() + 8);
+    encode_varint(&mut output, PREFIX.len() + label.len());
+    output.extend_from_slice(PREFIX);
+    output.extend_from_slice(label);
+    encode_varint(&mut output, context.len());
+    output.extend_from_slice(context);
+    Ok(output)
+}
+
+/// MLS `ExpandWithLabel()`, from RFC 9420, section 8. Fills `output` with
+/// secret material derived from `secret`, bound to `label` and `context`.
+pub fn expand_with_label(
+    output: &mut [u8],
+    secret: &[u8],
+    label: &[u8],
+    context: &[u8],
+) -> Result<(), Error> {
+    if output.len() > u16::MAX as usize {
+        return Err(dryoc_error!("output too long"));
+    }
+    let length = (output.len() as u16).to_be_bytes();
+    let labeled = encode_labeled(label, context)?;
+    hkdf::expand::<Sha256>(output, secret, &[&length, &labeled])
+}
+
+/// MLS `DeriveSecret()`, from RFC 9420, section 8.
+pub fn derive_secret(secret: &Secret, label: &[u8]) -> Result<Secret, Error> {
+    let mut output = Secret::new_byte_array();
+    expand_with_label(output.as_mut_slice(), secret.as_slice(), label, b"")?;
+    Ok(output)
+}
+
+/// Derives the node keypair from `path_secret`, as described in RFC 9420,
+/// section 7.4.
+pub fn derive_node_keypair(path_secret: &Secret) -> Result<StackKeyPair, Error> {
+    let mut node_secret = derive_secret(path_secret, b"node")?;
+    let keypair = DhKemX25519::derive_keypair(node_secret.as_slice());
+    node_secret.zeroize();
+    keypair
+}
+
+/// An HPKE ciphertext, containing an encrypted path secret.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HpkeCiphertext {
+    /// The HPKE encapsulated key.
+    pub kem_output: Ciphertext,
+    /// The encrypted path secret.
+    pub ciphertext: Vec<u8>,
+}
+
+/// MLS `EncryptWithLabel()`, from RFC 9420, section 5.1.3. Encrypts
+/// `plaintext` to `public_key`, bound to `label` and `context`.
+pub fn encrypt_with_label(
+    public_key: &PublicKey,
+    label: &[u8],
+    context: &[u8],
+    plaintext: &[u8],
+) -> Result<HpkeCiphertext, Error> {
+    let info = encode_labeled(label, context)?;
+    let (kem_output, ciphertext) =
+        hpke::seal_base::<DhKemX25519>(public_key, &info, b"", plaintext)?;
+    Ok(HpkeCiphertext {
+        kem_output,
+        ciphertext,
+    })
+}
+
+/// MLS `DecryptWithLabel()`, from RFC 9420, section 5.1.3.
+pub fn decrypt_with_label(
+    keypair: &StackKeyPair,
+    label: &[u8],
+    context: &[u8],
+    ciphertext: &HpkeCiphertext,
+) -> Result<Vec<u8>, Error> {
+    let info = encode_labeled(label, context)?;
+    hpke::open_base::<DhKemX25519>(
+        &ciphertext.kem_output,
+        keypair,
+        &info,
+        b"",
+        &ciphertext.ciphertext,
+    )
+}
+
+/// A node in an [`UpdatePath`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UpdatePathNode {
+    /// The node's new public key.
+    pub public_key: PublicKey,
+    /// The node's path secret, encrypted to each node in the resolution of
+    /// the corresponding copath node, in order.
+    pub encrypted_path_secret: Vec<HpkeCiphertext>,
+}
+
+/// An update path, which replaces the keys along a member's filtered direct
+/// path, as generated by [`RatchetTree::encap`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UpdatePath {
+    /// The member's new leaf public key.
+    pub leaf_public_key: PublicKey,
+    /// The new keys for each node in the member's filtered direct path.
+    pub nodes: Vec<UpdatePathNode>,
+}
+
+/// A member's private view of the ratchet tree: their leaf index, and the
+/// private keys they hold for nodes in the tree.
+pub struct PrivateState {
+    leaf: u32,
+    keys: BTreeMap<u32, StackKeyPair>,
+}
+
+impl PrivateState {
+    /// Returns a new private state for the member at `leaf` (the leaf index,
+    /// not the node index), with their leaf keypair.
+    pub fn new(leaf: u32, leaf_keypair: StackKeyPair) -> Self {
+        let mut keys = BTreeMap::new();
+        keys.insert(2 * leaf, leaf_keypair);
+        Self { leaf, keys }
+    }
+
+    /// Returns the leaf index of this member.
+    pub fn leaf(&self) -> u32 {
+        self.leaf
+    }
+
+    /// Returns this member's leaf public key.
+    pub fn leaf_public_key(&self) -> &PublicKey {
+        &self.keys[&(2 * self.leaf)].public_key
+    }
+
+    /// Returns the keypair held for the node at `node` (a node index), if
+    /// any.
+    pub fn keypair(&self, node: u32) -> Option<&StackKeyPair> {
+        self.keys.get(&node)
+    }
+}
+
+/// A ratchet tree, which holds the public key (if any) of each node.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RatchetTree {
+    leaves: u32,
+    nodes: Vec<Option<PublicKey>>,
+}
+
+impl RatchetTree {
+    /// Returns a new, blank tree with `leaves` leaves, which must be a
+    /// non-zero power of two.
+    pub fn new(leaves: u32) -> Result<Self, Error> {
+        if !leaves.is_power_of_two() || leaves > 1 << 30 {
+            return Err(dryoc_error!(format!(
+                "number of leaves must be a power of two, got {}",
+                leaves
+            )));
+        }
+        Ok(Self {
+            leaves,
+            nodes: vec![None; math::node_width(leaves) as usize],
+        })
+    }
+
+    /// Returns the number of leaves in this tree.
+    pub fn leaves(&self) -> u32 {
+        self.leaves
+    }
+
+    /// Returns the public key of the node at `node` (a node index), or `None`
+    /// if the node is blank.
+    pub fn public_key(&self, node: u32) -> Option<&PublicKey> {
+        self.nodes.get(node as usize).and_then(Option::as_ref)
+    }
+
+    /// Sets (or with `None`, blanks) the public key of the leaf at `leaf` (a
+    /// leaf index).
+    pub fn set_leaf(&mut self, leaf: u32, public_key: Option<PublicKey>) -> Result<(), Error> {
+        if leaf >= self.leaves {
+            return Err(dryoc_error!(format!("leaf index {} out of range", leaf)));
+        }
+        self.nodes[2 * leaf as usize] = public_key;
+        Ok(())
+    }
+
+    /// Returns the resolution of the node at `node`, from RFC 9420, section
+    /// 4.1.1: the minimal set of non-blank nodes covering its subtree.
+    pub fn resolution(&self, node: u32) -> Vec<u32> {
+        if self.nodes[node as usize].is_some() {
+            vec![node]
+        } else if math::level(node) == 0 {
+            vec![]
+        } else {
+            let mut resolution = self.resolution(math::left(node));
+            resolution.extend(self.resolution(math::right(node)));
+            resolution
+        }
+    }
+
+    /// Returns the filtered direct path of the leaf at `leaf`, as pairs of
+    /// each direct path node and its child on the copath, omitting nodes
+    /// whose copath child has an empty resolution.
+    pub fn filtered_direct_path(&self, leaf: u32) -> Vec<(u32, u32)> {
+        let x = 2 * leaf;
+        math::direct_path(x, self.leaves)
+            .into_iter()
+            .zip(math::copath(x, self.leaves))
+            .filter(|(_, copath_node)| !self.resolution(*copath_node).is_empty())
+            .collect()
+    }
+
+    fn apply_path(&mut self, leaf: u32, path: &UpdatePath, filtered: &[(u32, u32)]) {
+        for node in math::direct_path(2 * leaf, self.leaves) {
+            self.nodes[node as usize] = None;
+        }
+        for ((node, _), path_node) in filtered.iter().zip(&path.nodes) {
+            self.nodes[*node as usize] = Some(path_node.public_key.clone());
+        }
+        self.nodes[2 * leaf as usize] = Some(path.leaf_public_key.clone());
+    }
+
+    fn forget_blank_keys(&self, private: &mut PrivateState) {
+        private
+            .keys
+            .retain(|node, keypair| self.public_key(*node) == Some(&keypair.public_key));
+    }
+
+    /// Generates a fresh leaf keypair and new keys along the filtered direct
+    /// path of `private`'s leaf, returning the [`UpdatePath`] to send to the
+    /// group along with the new commit secret. Updates this tree and
+    /// `private` with the new keys.
+    pub fn encap(
+        &mut self,
+        private: &mut PrivateState,
+        group_context: &[u8],
+    ) -> Result<(UpdatePath, Secret), Error> {
+        let leaf = private.leaf;
+        if leaf >= self.leaves {
+            return Err(dryoc_error!(format!("leaf index {} out of range", leaf)));
+        }
+        let filtered = self.filtered_direct_path(leaf);
+
+        let mut leaf_secret = Secret::new_byte_array();
+        copy_randombytes(leaf_secret.as_mut_slice());
+        let leaf_keypair = derive_node_keypair(&leaf_secret)?;
+        let mut path_secret = derive_secret(&leaf_secret, b"path")?;
+        leaf_secret.zeroize();
+
+        let mut nodes = Vec::with_capacity(filtered.len());
+        let mut keypairs = Vec::with_capacity(filtered.len());
+        for (node, copath_node) in &filtered {
+            let keypair = derive_node_keypair(&path_secret)?;
+            let encrypted_path_secret = self
+                .resolution(*copath_node)
+                .into_iter()
+                .map(|r| {
+                    let public_key = self.nodes[r as usize]
+                        .as_ref()
+                        .expect("resolution contains only non-blank nodes");
+                    encrypt_with_label(
+                        public_key,
+                        b"UpdatePathNode",
+                        group_context,
+                        path_secret.as_slice(),
+                    )
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            nodes.push(UpdatePathNode {
+                public_key: keypair.public_key.clone(),
+                encrypted_path_secret,
+            });
+            keypairs.push((*node, keypair));
+            path_secret = derive_secret(&path_secret, b"path")?;
+        }
+
+        let path = UpdatePath {
+            leaf_public_key: leaf_keypair.public_key.clone(),
+            nodes,
+        };
+        self.apply_path(leaf, &path, &filtered);
+        private.keys.insert(2 * leaf, leaf_keypair);
+        private.keys.extend(keypairs);
+        self.forget_blank_keys(private);
+
+        Ok((path, path_secret))
+    }
+
+    /// Processes an [`UpdatePath`] from the member at `sender` (a leaf
+    /// index), decrypting the path secret for the lowest common ancestor with
+    /// `private`'s leaf, and deriving the path secrets above it. Verifies that
+    /// the derived public keys match the update path, then updates this tree
+    /// and `private` with the new keys, returning the commit secret.
+    pub fn decap(
+        &mut self,
+        private: &mut PrivateState,
+        sender: u32,
+        path: &UpdatePath,
+        group_context: &[u8],
+    ) -> Result<Secret, Error> {
+        if sender >= self.leaves || sender == private.leaf {
+            return Err(dryoc_error!(format!(
+                "invalid sender leaf index {}",
+                sender
+            )));
+        }
+        let filtered = self.filtered_direct_path(sender);
+        if filtered.len() != path.nodes.len() {
+            return Err(dryoc_error!(format!(
+                "update path has {} nodes, expected {}",
+                path.nodes.len(),
+                filtered.len()
+            )));
+        }
+
+        let own_node = 2 * private.leaf;
+        let start = filtered
+            .iter()
+            .position(|(_, copath_node)| math::is_in_subtree(own_node, *copath_node))
+            .ok_or_else(|| dryoc_error!("no common ancestor in filtered direct path"))?;
+        let resolution = self.resolution(filtered[start].1);
+        let encrypted = &path.nodes[start].encrypted_path_secret;
+        if encrypted.len() != resolution.len() {
+            return Err(dryoc_error!(format!(
+                "update path node has {} encrypted path secrets, expected {}",
+                encrypted.len(),
+                resolution.len()
+            )));
+        }
+        let (keypair, ciphertext) = resolution
+            .iter()
+            .zip(encrypted)
+            .find_map(|(r, ciphertext)| private.keys.get(r).map(|kp| (kp, ciphertext)))
+            .ok_or_else(|| dryoc_error!("no private key for the copath resolution"))?;
+
+        let mut decrypted =
+            decrypt_with_label(keypair, b"UpdatePathNode", group_context, ciphertext)?;
+        let path_secret = Secret::try_from(decrypted.as_slice());
+        decrypted.zeroize();
+        let mut path_secret = path_secret?;
+
+        let mut keypairs = Vec::with_capacity(filtered.len() - start);
+        for ((node, _), path_node) in filtered[start..].iter().zip(&path.nodes[start..]) {
+            let keypair = derive_node_keypair(&path_secret)?;
+            if keypair.public_key != path_node.public_key {
+                return Err(dryoc_error!(format!(
+                    "derived public key for node {} doesn't match update path",
+                    node
+                )));
+            }
+            keypairs.push((*node, keypair));
+            path_secret = derive_secret(&path_secret, b"path")?;
+        }
+
+        self.apply_path(sender, path, &filtered);
+        private.keys.extend(keypairs);
+        self.forget_blank_keys(private);
+
+        Ok(path_secret)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_math() {
+        // RFC 9420, appendix C, with 8 leaves
+        assert_eq!(math::node_width(8), 15);
+        assert_eq!(math::root(8), 7);
+        assert_eq!(math::root(1), 0);
+        assert_eq!(math::level(7), 3);
+        assert_eq!(math::left(7), 3);
+        assert_eq!(math::right(7), 11);
+        assert_eq!(math::parent(0, 8), Some(1));
+        assert_eq!(math::parent(5, 8), Some(3));
+        assert_eq!(math::parent(11, 8), Some(7));
+        assert_eq!(math::parent(7, 8), None);
+        assert_eq!(math::sibling(4, 8), Some(6));
+        assert_eq!(math::direct_path(0, 8), vec![1, 3, 7]);
+        assert_eq!(math::copath(0, 8), vec![2, 5, 11]);
+        assert_eq!(math::direct_path(10, 8), vec![9, 11, 7]);
+        assert_eq!(math::copath(10, 8), vec![8, 13, 3]);
+        assert!(math::is_in_subtree(4, 3));
+        assert!(!math::is_in_subtree(8, 3));
+    }
+
+    #[test]
+    fn test_expand_with_label() {
+        // the label is prefixed, and both label and context are length
+        // prefixed
+        assert_eq!(
+            encode_labeled(b"node", b"ctx").expect("encode failed"),
+            b"\x0cMLS 1.0 node\x03ctx"
+        );
+        let secret = Secret::from([7u8; 32]);
+        assert_ne!(
+            derive_secret(&secret, b"path").expect("derive failed"),
+            derive_secret(&secret, b"node").expect("derive failed")
+        );
+    }
+
+    fn group(size: u32, leaves: u32) -> (Vec<PrivateState>, Vec<RatchetTree>) {
+        let members: Vec<PrivateState> = (0..size)
+            .map(|leaf| PrivateState::new(leaf, DhKemX25519::gen_keypair()))
+            .collect();
+        let trees = (0..size)
+            .map(|_| {
+                let mut tree = RatchetTree::new(leaves).expect("new tree failed");
+                for member in &members {
+                    tree.set_leaf(member.leaf(), Some(member.leaf_public_key().clone()))
+                        .expect("set leaf failed");
+                }
+                tree
+            })
+            .collect();
+        (members, trees)
+    }
+
+    fn update(members: &mut [PrivateState], trees: &mut [RatchetTree], sender: usize) {
+        let (path, commit_secret) = trees[sender]
+            .encap(&mut members[sender], b"group context")
+            .expect("encap failed");
+        for i in (0..members.len()).filter(|i| *i != sender) {
+            let secret = trees[i]
+                .decap(&mut members[i], sender as u32, &path, b"group context")
+                .expect("decap failed");
+            assert_eq!(secret, commit_secret);
+        }
+        for tree in trees.iter() {
+            assert_eq!(tree, &trees[sender]);
+        }
+    }
+
+    #[test]
+    fn test_update_paths() {
+        // 6 members in a tree of 8 leaves, leaving some leaves blank
+        let (mut members, mut trees) = group(6, 8);
+        for sender in [0, 5, 2, 3, 0] {
+            update(&mut members, &mut trees, sender);
+        }
+
+        // each member holds keys for its leaf and the non-blank nodes in its
+        // direct path only
+        for member in &members {
+            for (node, keypair) in &member.keys {
+                assert!(*node == 2 * member.leaf || math::is_in_subtree(2 * member.leaf, *node));
+                assert_eq!(trees[0].public_key(*node), Some(&keypair.public_key));
+            }
+        }
+    }
+
+    #[test]
+    fn test_invalid_paths() {
+        let (mut members, mut trees) = group(4, 4);
+        let (mut path, _) = trees[0]
+            .encap(&mut members[0], b"group context")
+            .expect("encap failed");
+
+        trees[1]
+            .clone()
+            .decap(&mut members[1], 0, &path, b"other context")
+            .expect_err("should fail with wrong context");
+
+        path.nodes[1].public_key = DhKemX25519::gen_keypair().public_key.clone();
+        trees[1]
+            .decap(&mut members[1], 0, &path, b"group context")
+            .expect_err("should fail with wrong public key");
+
+        path.nodes.pop();
+        trees[2]
+            .decap(&mut members[2], 0, &path, b"group context")
+            .expect_err("should fail with wrong length");
+
+        RatchetTree::new(3).expect_err("should require power of two");
+    }
+}
@@ -1,3 +1,5 @@
+use std::borrow::Cow;
+
 use lazy_static::__Deref;
 use zeroize::{Zeroize, ZeroizeOnDrop};
 
@@ -239,6 +241,136 @@ impl ResizableBytes for Vec<u8> {
     }
 }
 
+impl Bytes for Box<[u8]> {
+    #[inline]
+    fn as_slice(&self) -> &[u8] {
+        self
+    }
+
+    #[inline]
+    fn len(&self) -> usize {
+        <[u8]>::len(self)
+    }
+
+    #[inline]
+    fn is_empty(&self) -> bool {
+        <[u8]>::is_empty(self)
+    }
+}
+
+impl NewBytes for Box<[u8]> {
+    fn new_bytes() -> Self {
+        Box::default()
+    }
+}
+
+impl MutBytes for Box<[u8]> {
+    #[inline]
+    fn as_mut_slice(&mut self) -> &mut [u8] {
+        self
+    }
+
+    fn copy_from_slice(&mut self, other: &[u8]) {
+        <[u8]>::copy_from_slice(self, other)
+    }
+}
+
+impl Bytes for Cow<'_, [u8]> {
+    #[inline]
+    fn as_slice(&self) -> &[u8] {
+        self
+    }
+
+    #[inline]
+    fn len(&self) -> usize {
+        <[u8]>::len(self)
+    }
+
+    #[inline]
+    fn is_empty(&self) -> bool {
+        <[u8]>::is_empty(self)
+    }
+}
+
+/// Borrowed data is copied into an owned buffer when it's first mutated.
+impl MutBytes for Cow<'_, [u8]> {
+    #[inline]
+    fn as_mut_slice(&mut self) -> &mut [u8] {
+        self.to_mut()
+    }
+
+    fn copy_from_slice(&mut self, other: &[u8]) {
+        <[u8]>::copy_from_slice(self.to_mut(), other)
+    }
+}
+
+#[cfg(feature = "bytes")]
+#[cfg_attr(all(feature = "nightly", doc), doc(cfg(feature = "bytes")))]
+impl Bytes for bytes::Bytes {
+    #[inline]
+    fn as_slice(&self) -> &[u8] {
+        self
+    }
+
+    #[inline]
+    fn len(&self) -> usize {
+        <[u8]>::len(self)
+    }
+
+    #[inline]
+    fn is_empty(&self) -> bool {
+        <[u8]>::is_empty(self)
+    }
+}
+
+#[cfg(feature = "bytes")]
+#[cfg_attr(all(feature = "nightly", doc), doc(cfg(feature = "bytes")))]
+impl Bytes for bytes::BytesMut {
+    #[inline]
+    fn as_slice(&self) -> &[u8] {
+        self
+    }
+
+    #[inline]
+    fn len(&self) -> usize {
+        <[u8]>::len(self)
+    }
+
+    #[inline]
+    fn is_empty(&self) -> bool {
+        <[u8]>::is_empty(self)
+    }
+}
+
+#[cfg(feature = "bytes")]
+#[cfg_attr(all(feature = "nightly", doc), doc(cfg(feature = "bytes")))]
+impl NewBytes for bytes::BytesMut {
+    fn new_bytes() -> Self {
+        bytes::BytesMut::new()
+    }
+}
+
+#[cfg(feature = "bytes")]
+#[cfg_attr(all(feature = "nightly", doc), doc(cfg(feature = "bytes")))]
+impl MutBytes for bytes::BytesMut {
+    #[inline]
+    fn as_mut_slice(&mut self) -> &mut [u8] {
+        self
+    }
+
+    fn copy_from_slice(&mut self, other: &[u8]) {
+        <[u8]>::copy_from_slice(self, other)
+    }
+}
+
+#[cfg(feature = "bytes")]
+#[cfg_attr(all(feature = "nightly", doc), doc(cfg(feature = "bytes")))]
+impl ResizableBytes for bytes::BytesMut {
+    fn resize(&mut self, new_len: usize, value: u8) {
+        bytes::BytesMut::resize(self, new_len, value);
+    }
+}
+
 impl Bytes for [u8] {
     #[inline]
     fn as_slice(&self) -> &[u8] {
@@ -494,7 +626,7 @@ impl<const LENGTH: usize> From<[u8; LENGTH]> for StackByteArray<LENGTH> {
     }
 }
 
-impl<'a, const LENGTH: usize> TryFrom<&'a [u8]> for StackByteArray<LENGTH> {
+impl<const LENGTH: usize> TryFrom<&[u8]> for StackByteArray<LENGTH> {
     type Error = crate::error::Error;
 
     fn try_from(src: &[u8]) -> Result<Self, Self::Error> {
@@ -541,4 +673,44 @@ mod tests {
         let mut vec = vec![1, 2];
         let _ = <Vec<u8> as MutByteArray<2>>::as_mut_array(&mut vec)[1];
     }
+
+    #[test]
+    fn test_box_and_cow_bytes() {
+        use crate::dryocsecretbox::*;
+
+        let key = Key::gen();
+        let nonce = Nonce::gen();
+        let message: &[u8] = b"hello";
+
+        let boxed: Box<[u8]> = message.into();
+        let mut cow = Cow::Borrowed(message);
+        assert_eq!(Bytes::as_slice(&boxed), Bytes::as_slice(&cow));
+
+        let from_box = DryocSecretBox::encrypt_to_vecbox(&boxed, &nonce, &key);
+        let from_cow = DryocSecretBox::encrypt_to_vecbox(&cow, &nonce, &key);
+        assert_eq!(from_box.to_vec(), from_cow.to_vec());
+
+        // Mutating a borrowed Cow copies it, leaving the original untouched
+        MutBytes::as_mut_slice(&mut cow)[0] = b'j';
+        assert!(matches!(cow, Cow::Owned(_)));
+        assert_eq!(message, b"hello");
+        assert_eq!(Bytes::as_slice(&cow), b"jello");
+    }
+
+    #[cfg(feature = "bytes")]
+    #[test]
+    fn test_bytes_crate() {
+        use crate::dryocsecretbox::*;
+
+        let key = Key::gen();
+        let nonce = Nonce::gen();
+        let message = bytes::Bytes::from_static(b"hello");
+
+        let dryocsecretbox = DryocSecretBox::encrypt_to_vecbox(&message, &nonce, &key);
+        let decrypted: bytes::BytesMut = dryocsecretbox
+            .decrypt(&nonce, &key)
+            .expect("decrypt failed");
+
+        assert_eq!(decrypted.freeze(), message);
+    }
 }
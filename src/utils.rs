@@ -1,3 +1,8 @@
+use subtle::ConstantTimeEq;
+use zeroize::Zeroize;
+
+use crate::error::Error;
+
 /// Increments `bytes` in constant time, representing a large little-endian
 /// integer; equivalent to `sodium_increment`.
 #[inline]
@@ -16,6 +21,219 @@ pub fn sodium_increment(bytes: &mut [u8]) {
     increment_bytes(bytes)
 }
 
+/// Decodes the hexadecimal string `input` into `output` in constant time,
+/// similar to `sodium_hex2bin`. Upper and lower case digits are accepted, and
+/// `input` must be exactly twice as long as `output`.
+///
+/// Invalid input is detected without branching on the decoded values, so the
+/// time taken depends only on the length of `input`.
+pub fn hex_decode_into(output: &mut [u8], input: &str) -> Result<(), Error> {
+    let input = input.as_bytes();
+    if input.len() != 2 * output.len() {
+        return Err(dryoc_error!(format!(
+            "hex input length {} doesn't match expected {}",
+            input.len(),
+            2 * output.len()
+        )));
+    }
+
+    let mut invalid = 0u8;
+    for (out, pair) in output.iter_mut().zip(input.chunks_exact(2)) {
+        let (hi, hi_valid) = ct_hex_value(pair[0]);
+        let (lo, lo_valid) = ct_hex_value(pair[1]);
+        invalid |= !(hi_valid & lo_valid);
+        *out = (hi << 4) | lo;
+    }
+
+    if invalid == 0 {
+        Ok(())
+    } else {
+        output.zeroize();
+        Err(dryoc_error!("invalid hex input"))
+    }
+}
+
+/// Returns the length of the output of decoding the base64 string `input`
+/// with [`base64_decode_into`], or an error if the length of `input` isn't
+/// valid.
+pub fn base64_decoded_len(input: &str) -> Result<usize, Error> {
+    let unpadded = input.trim_end_matches('=');
+    let padding = input.len() - unpadded.len();
+    if unpadded.len() % 4 == 1 || padding > 2 || (padding > 0 && input.len() % 4 != 0) {
+        return Err(dryoc_error!("invalid base64 input length"));
+    }
+    Ok(unpadded.len() * 3 / 4)
+}
+
+/// Decodes the base64 string `input`, using the standard alphabet (RFC 4648,
+/// section 4) with optional padding, into `output` in constant time, similar
+/// to `sodium_base642bin`. The decoded length must match the length of
+/// `output`, which can be computed with [`base64_decoded_len`].
+///
+/// Invalid input is detected without branching on the decoded values, so the
+/// time taken depends only on the length of `input`.
+pub fn base64_decode_into(output: &mut [u8], input: &str) -> Result<(), Error> {
+    let decoded_len = base64_decoded_len(input)?;
+    if decoded_len != output.len() {
+        return Err(dryoc_error!(format!(
+            "base64 decoded length {} doesn't match expected {}",
+            decoded_len,
+            output.len()
+        )));
+    }
+
+    let mut invalid = 0u8;
+    let mut acc = 0u32;
+    let mut acc_len = 0u32;
+    let mut out = output.iter_mut();
+    for c in input.trim_end_matches('=').bytes() {
+        let (value, valid) = ct_base64_value(c);
+        invalid |= !valid;
+        acc = (acc << 6) | value as u32;
+        acc_len += 6;
+        if acc_len >= 8 {
+            acc_len -= 8;
+            if let Some(out) = out.next() {
+                *out = (acc >> acc_len) as u8;
+            }
+        }
+    }
+    // leftover bits must be zero for the encoding to be canonical
+    invalid |= ((acc & ((1 << acc_len) - 1)) != 0) as u8 * 0xff;
+    acc.zeroize();
+
+    if invalid == 0 {
+        Ok(())
+    } else {
+        output.zeroize();
+        Err(dryoc_error!("invalid base64 input"))
+    }
+}
+
+/// Compares the secrets `a` and `b`, which may differ in length, in constant
+/// time, returning `true` if they're equal. Both are compared as if padded
+/// with zeros to `max_len` bytes, along with their lengths, so the time taken
+/// depends only on `max_len`, and not on the contents or lengths of `a` and
+/// `b`. Use this for comparing tokens or passwords, where even their length
+/// must not leak.
+///
+/// Returns an error if either is longer than `max_len`. The error doesn't
+/// include their lengths.
+pub fn ct_eq_padded(a: &[u8], b: &[u8], max_len: usize) -> Result<bool, Error> {
+    if a.len() > max_len || b.len() > max_len {
+        return Err(dryoc_error!("secret is longer than max_len"));
+    }
+
+    let mut diff = (a.len() ^ b.len()) as u64;
+    for i in 0..max_len {
+        diff |= (ct_load_padded(a, i) ^ ct_load_padded(b, i)) as u64;
+    }
+    Ok(diff.ct_eq(&0).into())
+}
+
+/// Returns `x[i]` if `i < x.len()`, `0` otherwise, without branching on the
+/// length of `x`. Out of range indices read `x[0]` instead.
+#[inline]
+fn ct_load_padded(x: &[u8], i: usize) -> u8 {
+    let mask = 0usize.wrapping_sub(ct_lt_usize(i, x.len()));
+    x.get(i & mask).copied().unwrap_or(0) & mask as u8
+}
+
+/// Returns `1` if `x < y`, `0` otherwise.
+#[inline]
+fn ct_lt_usize(x: usize, y: usize) -> usize {
+    (x ^ ((x ^ y) | (x.wrapping_sub(y) ^ y))) >> (usize::BITS - 1)
+}
+
+/// Returns `0xff` if `x == y`, `0` otherwise.
+#[inline]
+fn ct_eq_u8(x: u8, y: u8) -> u8 {
+    ((0u32.wrapping_sub((x ^ y) as u32) >> 8) as u8) ^ 0xff
+}
+
+/// Returns `0xff` if `x < y`, `0` otherwise.
+#[inline]
+fn ct_lt_u8(x: u8, y: u8) -> u8 {
+    ((x as u32).wrapping_sub(y as u32) >> 8) as u8
+}
+
+/// Returns the value of hex digit `c`, and `0xff` if `c` is valid (`0`
+/// otherwise).
+#[inline]
+fn ct_hex_value(c: u8) -> (u8, u8) {
+    let num = c ^ b'0';
+    let num_valid = ct_lt_u8(num, 10);
+    let alpha = (c & !32).wrapping_sub(b'A' - 10);
+    let alpha_valid = !ct_lt_u8(alpha, 10) & ct_lt_u8(alpha, 16);
+    let valid = num_valid | alpha_valid;
+    ((num_valid & num) | (alpha_valid & alpha), valid)
+}
+
+/// Returns the value of base64 digit `c`, and `0xff` if `c` is valid (`0`
+/// otherwise).
+#[inline]
+fn ct_base64_value(c: u8) -> (u8, u8) {
+    let upper = !ct_lt_u8(c, b'A') & ct_lt_u8(c, b'Z' + 1);
+    let lower = !ct_lt_u8(c, b'a') & ct_lt_u8(c, b'z' + 1);
+    let digit = !ct_lt_u8(c, b'0') & ct_lt_u8(c, b'9' + 1);
+    let plus = ct_eq_u8(c, b'+');
+    let slash = ct_eq_u8(c, b'/');
+    let value = (upper & c.wrapping_sub(b'A'))
+        | (lower & c.wrapping_sub(b'a').wrapping_add(26))
+        | (digit & c.wrapping_sub(b'0').wrapping_add(52))
+        | (plus & 62)
+        | (slash & 63);
+    (value, upper | lower | digit | plus | slash)
+}
+
+/// Returns a copy of `data` in scratch memory, for plaintext which is never
+/// returned to the caller. With the `nightly` feature this is locked memory,
+/// otherwise a heap buffer. Either way, it's zeroized when dropped.
+#[cfg(feature = "nightly")]
+pub(crate) fn scratch_copy(data: &[u8]) -> Result<crate::protected::LockedBytes, Error> {
+    use crate::protected::{HeapBytes, NewLockedFromSlice};
+
+    HeapBytes::from_slice_into_locked(data)
+}
+
+/// Returns a copy of `data` in scratch memory, for plaintext which is never
+/// returned to the caller. With the `nightly` feature this is locked memory,
+/// otherwise a heap buffer. Either way, it's zeroized when dropped.
+#[cfg(not(feature = "nightly"))]
+pub(crate) fn scratch_copy(data: &[u8]) -> Result<zeroize::Zeroizing<Vec<u8>>, Error> {
+    Ok(zeroize::Zeroizing::new(data.to_vec()))
+}
+
+/// Splits a reference to the first `N` bytes of `bytes` off the rest, without
+/// copying. Returns an error naming `what` if `bytes` is too short.
+pub(crate) fn split_array_ref<'a, const N: usize>(
+    bytes: &'a [u8],
+    what: &str,
+) -> Result<(&'a [u8; N], &'a [u8]), Error> {
+    if bytes.len() < N {
+        return Err(dryoc_error!(format!(
+            "bytes of len {} too short for {}, expected at least {}",
+            bytes.len(),
+            what,
+            N
+        )));
+    }
+    let (head, rest) = bytes.split_at(N);
+    Ok((head.try_into().expect("length checked"), rest))
+}
+
+/// Checks that `output` can hold a message of `len` bytes, returning the part
+/// of it to decrypt into.
+pub(crate) fn output_prefix(output: &mut [u8], len: usize) -> Result<&mut [u8], Error> {
+    let available = output.len();
+    output.get_mut(..len).ok_or_else(|| {
+        dryoc_error!(format!(
+            "output of len {} too short for message of len {}",
+            available, len
+        ))
+    })
+}
+
 #[inline]
 pub(crate) fn xor_buf(out: &mut [u8], in_: &[u8]) {
     let len = std::cmp::min(out.len(), in_.len());
@@ -48,7 +266,7 @@ pub(crate) fn load_u32_le(bytes: &[u8]) -> u32 {
 
 #[inline]
 pub(crate) fn rotr64(x: u64, b: u64) -> u64 {
-    (x >> b) | (x << (64 - b))
+    x.rotate_right(b as u32)
 }
 
 #[cfg(test)]
@@ -114,6 +332,77 @@ mod tests {
         assert_eq!([1, 0, 0], a);
     }
 
+    #[test]
+    fn test_hex_decode() {
+        use crate::rng::copy_randombytes;
+
+        for len in [0, 1, 16, 33] {
+            let mut data = vec![0u8; len];
+            copy_randombytes(&mut data);
+
+            let mut output = vec![0u8; len];
+            hex_decode_into(&mut output, &hex::encode(&data)).expect("decode failed");
+            assert_eq!(output, data);
+            hex_decode_into(&mut output, &hex::encode_upper(&data)).expect("decode failed");
+            assert_eq!(output, data);
+        }
+
+        let mut output = [0u8; 2];
+        hex_decode_into(&mut output, "0aFf").expect("decode failed");
+        assert_eq!(output, [0x0a, 0xff]);
+        for bad in ["0g00", "00 0", "0x00", "000", "00000"] {
+            hex_decode_into(&mut output, bad).expect_err("decode should fail");
+        }
+    }
+
+    #[test]
+    fn test_base64_decode() {
+        use base64::engine::general_purpose;
+        use base64::Engine as _;
+
+        use crate::rng::copy_randombytes;
+
+        for len in 0..70 {
+            let mut data = vec![0u8; len];
+            copy_randombytes(&mut data);
+
+            for encoded in [
+                general_purpose::STANDARD.encode(&data),
+                general_purpose::STANDARD_NO_PAD.encode(&data),
+            ] {
+                let decoded_len = base64_decoded_len(&encoded).expect("length failed");
+                assert_eq!(decoded_len, len);
+                let mut output = vec![0u8; decoded_len];
+                base64_decode_into(&mut output, &encoded).expect("decode failed");
+                assert_eq!(output, data);
+            }
+        }
+
+        let mut output = [0u8; 1];
+        for bad in ["AA=A", "A===", "AB==", "AA-=", "A", "AA=", "A\u{e9}=="] {
+            base64_decode_into(&mut output, bad).expect_err("decode should fail");
+        }
+        base64_decode_into(&mut [0u8; 2], "AA==").expect_err("length mismatch should fail");
+    }
+
+    #[test]
+    fn test_ct_eq_padded() {
+        assert!(ct_eq_padded(b"token", b"token", 16).expect("compare failed"));
+        assert!(ct_eq_padded(b"", b"", 16).expect("compare failed"));
+        assert!(ct_eq_padded(b"", b"", 0).expect("compare failed"));
+        assert!(!ct_eq_padded(b"token", b"tokem", 16).expect("compare failed"));
+        // padding doesn't make different lengths equal
+        assert!(!ct_eq_padded(b"token", b"token\0", 16).expect("compare failed"));
+        assert!(!ct_eq_padded(b"", b"\0", 16).expect("compare failed"));
+        assert!(!ct_eq_padded(b"token", b"", 16).expect("compare failed"));
+        assert!(ct_eq_padded(b"token", b"token", 5).expect("compare failed"));
+        ct_eq_padded(b"token", b"token", 4).expect_err("should be too long");
+
+        for (x, y) in [(0, 1), (1, 1), (2, 1), (0, usize::MAX), (usize::MAX, 0)] {
+            assert_eq!(ct_lt_usize(x, y), (x < y) as usize);
+        }
+    }
+
     #[test]
     fn test_sodium_increment() {
         use libsodium_sys::sodium_increment as so_sodium_increment;
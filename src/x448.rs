@@ -0,0 +1,235 @@
+//! # X448 key exchange
+//!
+//! This module implements the X448 Diffie-Hellman function from
+//! [RFC 7748](https://www.rfc-editor.org/rfc/rfc7748), over Curve448 (the
+//! "Goldilocks" curve). X448 provides a ~224-bit security level, compared to
+//! ~128 bits for X25519, and is intended for deployments where policy
+//! requires a stronger curve. For everything else, prefer
+//! [`DryocBox`](crate::dryocbox::DryocBox) or [`kx`](crate::kx).
+//!
+//! The keys in this module are distinct from the X25519 keys used elsewhere
+//! in the crate, and can't be used interchangeably.
+//!
+//! The raw shared secret should not be used directly as a key. Pass it
+//! through a KDF, such as [`Kdf`](crate::kdf::Kdf) or HKDF, along with both
+//! public keys.
+//!
+//! This module requires the `curve448` feature.
+//!
+//! ## Example
+//!
+//! ```
+//! use dryoc::x448::*;
+//!
+//! let alice = KeyPair::gen_with_defaults();
+//! let bob = KeyPair::gen_with_defaults();
+//!
+//! let alice_shared: SharedSecret = alice
+//!     .diffie_hellman(&bob.public_key)
+//!     .expect("key exchange failed");
+//! let bob_shared: SharedSecret = bob
+//!     .diffie_hellman(&alice.public_key)
+//!     .expect("key exchange failed");
+//!
+//! assert_eq!(alice_shared, bob_shared);
+//! ```
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use subtle::ConstantTimeEq;
+use zeroize::{Zeroize, ZeroizeOnDrop};
+
+use crate::curve448;
+use crate::error::Error;
+use crate::types::*;
+
+/// Length of an X448 public key, in bytes.
+pub const X448_PUBLICKEYBYTES: usize = 56;
+/// Length of an X448 secret key, in bytes.
+pub const X448_SECRETKEYBYTES: usize = 56;
+/// Length of an X448 shared secret, in bytes.
+pub const X448_SHAREDSECRETBYTES: usize = 56;
+
+/// Stack-allocated X448 public key.
+pub type PublicKey = StackByteArray<X448_PUBLICKEYBYTES>;
+/// Stack-allocated X448 secret key.
+pub type SecretKey = StackByteArray<X448_SECRETKEYBYTES>;
+/// Stack-allocated X448 shared secret.
+pub type SharedSecret = StackByteArray<X448_SHAREDSECRETBYTES>;
+
+/// The u-coordinate of the Curve448 base point.
+const BASE_POINT: [u8; 56] = {
+    let mut u = [0u8; 56];
+    u[0] = 5;
+    u
+};
+
+fn clamp(secret_key: &[u8; X448_SECRETKEYBYTES]) -> [u8; X448_SECRETKEYBYTES] {
+    let mut scalar = *secret_key;
+    scalar[0] &= 252;
+    scalar[55] |= 128;
+    scalar
+}
+
+/// Computes the X448 function for `secret_key` and the u-coordinate
+/// `public_key`, placing the result into `output`.
+///
+/// Returns an error if the result is all zeros, which happens when
+/// `public_key` is a point of small order.
+pub fn scalarmult(
+    output: &mut [u8; X448_SHAREDSECRETBYTES],
+    secret_key: &[u8; X448_SECRETKEYBYTES],
+    public_key: &[u8; X448_PUBLICKEYBYTES],
+) -> Result<(), Error> {
+    let mut scalar = clamp(secret_key);
+    *output = curve448::x448(&scalar, public_key);
+    scalar.zeroize();
+
+    if bool::from(output.ct_eq(&[0u8; X448_SHAREDSECRETBYTES])) {
+        Err(dryoc_error!("invalid public key, shared secret is zero"))
+    } else {
+        Ok(())
+    }
+}
+
+/// Computes the X448 public key for `secret_key`, placing the result into
+/// `public_key`.
+pub fn scalarmult_base(
+    public_key: &mut [u8; X448_PUBLICKEYBYTES],
+    secret_key: &[u8; X448_SECRETKEYBYTES],
+) {
+    let mut scalar = clamp(secret_key);
+    *public_key = curve448::x448(&scalar, &BASE_POINT);
+    scalar.zeroize();
+}
+
+#[cfg_attr(
+    feature = "serde",
+    derive(Zeroize, ZeroizeOnDrop, Serialize, Deserialize, Debug, Clone)
+)]
+#[cfg_attr(not(feature = "serde"), derive(Zeroize, ZeroizeOnDrop, Debug, Clone))]
+/// An X448 keypair for key exchange.
+pub struct KeyPair<
+    PublicKey: ByteArray<X448_PUBLICKEYBYTES> + Zeroize,
+    SecretKey: ByteArray<X448_SECRETKEYBYTES> + Zeroize,
+> {
+    /// Public key
+    pub public_key: PublicKey,
+    /// Secret key
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::redact::serialize_secret")
+    )]
+    pub secret_key: SecretKey,
+}
+
+impl<
+    PublicKey: NewByteArray<X448_PUBLICKEYBYTES> + Zeroize,
+    SecretKey: NewByteArray<X448_SECRETKEYBYTES> + Zeroize,
+> KeyPair<PublicKey, SecretKey>
+{
+    /// Randomly generates a new keypair.
+    pub fn gen() -> Self {
+        Self::from_secret_key(SecretKey::gen())
+    }
+
+    /// Derives a keypair from `secret_key`, and consumes it, returning a new
+    /// keypair.
+    pub fn from_secret_key(secret_key: SecretKey) -> Self {
+        let mut public_key = PublicKey::new_byte_array();
+        scalarmult_base(public_key.as_mut_array(), secret_key.as_array());
+        Self {
+            public_key,
+            secret_key,
+        }
+    }
+
+    /// Computes the shared secret between this keypair and `public_key`.
+    ///
+    /// Returns an error if `public_key` is a point of small order.
+    pub fn diffie_hellman<
+        OtherPublicKey: ByteArray<X448_PUBLICKEYBYTES>,
+        SharedSecret: NewByteArray<X448_SHAREDSECRETBYTES>,
+    >(
+        &self,
+        public_key: &OtherPublicKey,
+    ) -> Result<SharedSecret, Error> {
+        let mut shared_secret = SharedSecret::new_byte_array();
+        scalarmult(
+            shared_secret.as_mut_array(),
+            self.secret_key.as_array(),
+            public_key.as_array(),
+        )?;
+        Ok(shared_secret)
+    }
+}
+
+impl KeyPair<PublicKey, SecretKey> {
+    /// Randomly generates a new keypair, using default types
+    /// (stack-allocated byte arrays).
+    pub fn gen_with_defaults() -> Self {
+        Self::gen()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unhex<const N: usize>(s: &str) -> StackByteArray<N> {
+        StackByteArray::try_from(hex::decode(s).expect("invalid hex").as_slice())
+            .expect("wrong length")
+    }
+
+    #[test]
+    fn test_rfc7748_vectors() {
+        // RFC 7748, section 5.2
+        let mut output = [0u8; X448_SHAREDSECRETBYTES];
+        scalarmult(
+            &mut output,
+            unhex("3d262fddf9ec8e88495266fea19a34d28882acef045104d0d1aae121700a779c984c24f8cdd78fbff44943eba368f54b29259a4f1c600ad3").as_array(),
+            unhex("06fce640fa3487bfda5f6cf2d5263f8aad88334cbd07437f020f08f9814dc031ddbdc38c19c6da2583fa5429db94ada18aa7a7fb4ef8a086").as_array(),
+        )
+        .expect("scalarmult failed");
+        assert_eq!(
+            hex::encode(output),
+            "ce3e4ff95a60dc6697da1db1d85e6afbdf79b50a2412d7546d5f239fe14fbaadeb445fc66a01b0779d98223961111e21766282f73dd96b6f"
+        );
+
+        // RFC 7748, section 6.2
+        let alice = KeyPair::<PublicKey, SecretKey>::from_secret_key(unhex(
+            "9a8f4925d1519f5775cf46b04b5800d4ee9ee8bae8bc5565d498c28dd9c9baf574a9419744897391006382a6f127ab1d9ac2d8c0a598726b",
+        ));
+        let bob = KeyPair::<PublicKey, SecretKey>::from_secret_key(unhex(
+            "1c306a7ac2a0e2e0990b294470cba339e6453772b075811d8fad0d1d6927c120bb5ee8972b0d3e21374c9c921b09d1b0366f10b65173992d",
+        ));
+        assert_eq!(
+            hex::encode(alice.public_key.as_slice()),
+            "9b08f7cc31b7e3e67d22d5aea121074a273bd2b83de09c63faa73d2c22c5d9bbc836647241d953d40c5b12da88120d53177f80e532c41fa0"
+        );
+        assert_eq!(
+            hex::encode(bob.public_key.as_slice()),
+            "3eb7a829b0cd20f5bcfc0b599b6feccf6da4627107bdb0d4f345b43027d8b972fc3e34fb4232a13ca706dcb57aec3dae07bdc1c67bf33609"
+        );
+
+        let shared: SharedSecret = alice
+            .diffie_hellman(&bob.public_key)
+            .expect("key exchange failed");
+        assert_eq!(
+            hex::encode(shared.as_slice()),
+            "07fff4181ac6cc95ec1c16a94a0f74d12da232ce40a77552281d282bb60c0b56fd2464c335543936521c24403085d59a449a5037514a879d"
+        );
+        let shared_bob: SharedSecret = bob
+            .diffie_hellman(&alice.public_key)
+            .expect("key exchange failed");
+        assert_eq!(shared, shared_bob);
+    }
+
+    #[test]
+    fn test_small_order() {
+        let keypair = KeyPair::gen_with_defaults();
+        keypair
+            .diffie_hellman::<_, SharedSecret>(&PublicKey::new_byte_array())
+            .expect_err("should reject zero point");
+    }
+}
@@ -0,0 +1,131 @@
+//! # Zeroizing global allocator
+//!
+//! The types in this crate zeroize their contents when dropped, but
+//! intermediate buffers can still leave copies of secrets behind in freed
+//! heap memory. A [`Vec`] which grows is reallocated, and the old allocation
+//! is freed without being wiped, as are temporary buffers which are dropped
+//! without being zeroized, both within this crate and in your own code.
+//!
+//! [`ZeroizingAllocator`] wraps another global allocator (the system allocator,
+//! by default), and wipes every allocation before it's freed, including the old
+//! allocation when a buffer is reallocated. Install it as your program's
+//! global allocator:
+//!
+//! ```
+//! use dryoc::zeroizing_alloc::ZeroizingAllocator;
+//!
+//! # #[cfg(not(feature = "global-zeroizing-alloc"))]
+//! #[global_allocator]
+//! static ALLOCATOR: ZeroizingAllocator = ZeroizingAllocator::system();
+//!
+//! let mut secret = b"attack at dawn".to_vec();
+//! secret.extend_from_slice(b", and bring snacks"); // the old buffer is wiped
+//! ```
+//!
+//! Alternatively, the `global-zeroizing-alloc` feature installs it with the
+//! system allocator for you. Don't use that feature if your program already
+//! has a global allocator; wrap your allocator instead, such as with
+//! `ZeroizingAllocator::new(Jemalloc)`.
+//!
+//! Wiping every allocation has a cost proportional to the amount of memory
+//! freed, which is usually small compared to the cost of filling it. For
+//! locked, guarded memory for secrets themselves, use the
+//! [protected](crate::protected) memory features instead.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+
+use zeroize::Zeroize;
+
+/// A global allocator which wipes allocations before freeing them.
+///
+/// Refer to [crate::zeroizing_alloc] for details.
+#[derive(Debug, Default)]
+pub struct ZeroizingAllocator<A: GlobalAlloc = System>(A);
+
+impl ZeroizingAllocator<System> {
+    /// Returns a zeroizing allocator which wraps the system allocator.
+    pub const fn system() -> Self {
+        Self(System)
+    }
+}
+
+impl<A: GlobalAlloc> ZeroizingAllocator<A> {
+    /// Returns a zeroizing allocator which wraps `allocator`.
+    pub const fn new(allocator: A) -> Self {
+        Self(allocator)
+    }
+}
+
+unsafe impl<A: GlobalAlloc> GlobalAlloc for ZeroizingAllocator<A> {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        self.0.alloc(layout)
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        self.0.alloc_zeroed(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        std::slice::from_raw_parts_mut(ptr, layout.size()).zeroize();
+        self.0.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        // The wrapped allocator may move the allocation without wiping the
+        // old one, so always move it here instead
+        let new_layout = Layout::from_size_align_unchecked(new_size, layout.align());
+        let new_ptr = self.0.alloc(new_layout);
+        if !new_ptr.is_null() {
+            std::ptr::copy_nonoverlapping(ptr, new_ptr, layout.size().min(new_size));
+            self.dealloc(ptr, layout);
+        }
+        new_ptr
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+
+    /// Checks that each allocation is wiped before it's freed.
+    struct CheckWiped {
+        freed: AtomicUsize,
+    }
+
+    unsafe impl GlobalAlloc for CheckWiped {
+        unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+            System.alloc(layout)
+        }
+
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+            let contents = std::slice::from_raw_parts(ptr, layout.size());
+            assert!(contents.iter().all(|&b| b == 0), "freed without wiping");
+            self.freed.fetch_add(1, Ordering::SeqCst);
+            System.dealloc(ptr, layout)
+        }
+    }
+
+    #[test]
+    fn test_zeroizing_allocator() {
+        let allocator = ZeroizingAllocator::new(CheckWiped {
+            freed: AtomicUsize::new(0),
+        });
+
+        unsafe {
+            let layout = Layout::from_size_align(16, 8).expect("layout");
+            let ptr = allocator.alloc(layout);
+            std::ptr::write_bytes(ptr, 0xaa, 16);
+
+            let ptr = allocator.realloc(ptr, layout, 4096);
+            let grown = std::slice::from_raw_parts(ptr, 16);
+            assert_eq!(grown, [0xaa; 16]);
+            assert_eq!(allocator.0.freed.load(Ordering::SeqCst), 1);
+
+            std::ptr::write_bytes(ptr, 0xbb, 4096);
+            allocator.dealloc(ptr, Layout::from_size_align(4096, 8).expect("layout"));
+            assert_eq!(allocator.0.freed.load(Ordering::SeqCst), 2);
+        }
+    }
+}
@@ -0,0 +1,110 @@
+#![cfg(feature = "cli")]
+
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Output, Stdio};
+
+fn dryoc(args: &[&str], stdin: &[u8]) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_dryoc"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("failed to run dryoc");
+    child
+        .stdin
+        .take()
+        .expect("no stdin")
+        .write_all(stdin)
+        .expect("failed to write stdin");
+    child.wait_with_output().expect("failed to wait for dryoc")
+}
+
+fn dryoc_ok(args: &[&str], stdin: &[u8]) -> Vec<u8> {
+    let output = dryoc(args, stdin);
+    assert!(
+        output.status.success(),
+        "dryoc {:?} failed: {}",
+        args,
+        String::from_utf8_lossy(&output.stderr)
+    );
+    output.stdout
+}
+
+fn write_file(name: &str, contents: &[u8]) -> PathBuf {
+    let path = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(name);
+    std::fs::write(&path, contents).expect("failed to write file");
+    path
+}
+
+#[test]
+fn test_keygen_seal_unseal() {
+    let keypair = write_file("cli-box-keypair.json", &dryoc_ok(&["keygen", "box"], b""));
+    let keypair = keypair.to_str().unwrap();
+    let public_key = write_file("cli-box-pubkey.json", &dryoc_ok(&["pubkey", keypair], b""));
+    let public_key = public_key.to_str().unwrap();
+    assert!(
+        !std::fs::read_to_string(public_key)
+            .unwrap()
+            .contains("secret_key")
+    );
+
+    let sealed = dryoc_ok(&["seal", public_key], b"hello");
+    assert_eq!(dryoc_ok(&["unseal", keypair], &sealed), b"hello");
+
+    // the full keypair can also be used to seal
+    let sealed = dryoc_ok(&["seal", keypair], b"hello");
+    assert_eq!(dryoc_ok(&["unseal", keypair], &sealed), b"hello");
+
+    // the public key alone can't unseal
+    assert!(!dryoc(&["unseal", public_key], &sealed).status.success());
+}
+
+#[test]
+fn test_sign_verify() {
+    let keypair = write_file("cli-sign-keypair.json", &dryoc_ok(&["keygen", "sign"], b""));
+    let keypair = keypair.to_str().unwrap();
+    let public_key = write_file("cli-sign-pubkey.json", &dryoc_ok(&["pubkey", keypair], b""));
+    let public_key = public_key.to_str().unwrap();
+    assert!(
+        !std::fs::read_to_string(public_key)
+            .unwrap()
+            .contains("secret_key")
+    );
+
+    let signed = dryoc_ok(&["sign", keypair], b"hello");
+    assert_eq!(dryoc_ok(&["verify", public_key], &signed), b"hello");
+    assert_eq!(dryoc_ok(&["verify", keypair], &signed), b"hello");
+
+    let other = write_file("cli-sign-other.json", &dryoc_ok(&["keygen", "sign"], b""));
+    assert!(
+        !dryoc(&["verify", other.to_str().unwrap()], &signed)
+            .status
+            .success()
+    );
+}
+
+#[test]
+fn test_hash() {
+    // BLAKE2b-256 of the empty message
+    assert_eq!(
+        dryoc_ok(&["hash"], b""),
+        b"0e5751c026e543b2e8ab2eb06099daa1d1e5df47778f7787faab45cdf12fe3a8\n"
+    );
+    assert_ne!(dryoc_ok(&["hash"], b"hello"), dryoc_ok(&["hash"], b""));
+}
+
+#[test]
+fn test_usage() {
+    assert!(dryoc_ok(&["help"], b"").starts_with(b"Usage: dryoc"));
+    assert_eq!(dryoc(&["frobnicate"], b"").status.code(), Some(2));
+}
+
+#[test]
+fn test_pwhash_verify() {
+    let hash = write_file("cli-pwhash.json", &dryoc_ok(&["pwhash"], b"hunter2"));
+    let hash = hash.to_str().unwrap();
+    assert_eq!(dryoc_ok(&["pwhash-verify", hash], b"hunter2"), b"ok\n");
+    assert!(!dryoc(&["pwhash-verify", hash], b"hunter3").status.success());
+}
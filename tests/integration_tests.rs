@@ -477,3 +477,87 @@ fn test_dryocbox_seal() {
 
     assert_eq!(message, decrypted.as_slice());
 }
+
+#[cfg(feature = "nightly")]
+#[test]
+fn test_decrypt_to_protected() {
+    use dryoc::dryocbox::protected::*;
+    use dryoc::dryocbox::DryocBox;
+    use dryoc::dryocsecretbox::DryocSecretBox;
+    use dryoc::dryocstream::{DryocStream, Tag};
+    use dryoc::{dryocsecretbox, dryocstream};
+
+    let message = b"Secret message for locked memory";
+
+    let sender_keypair = LockedKeyPair::gen_locked_keypair().expect("keypair");
+    let recipient_keypair = LockedKeyPair::gen_locked_keypair().expect("keypair");
+    let nonce = Nonce::gen_readonly_locked().expect("nonce failed");
+
+    let dryocbox: LockedBox = DryocBox::encrypt(
+        message,
+        &nonce,
+        &recipient_keypair.public_key,
+        &sender_keypair.secret_key,
+    )
+    .expect("encrypt failed");
+    let decrypted = dryocbox
+        .decrypt_to::<LockedBytes>(
+            &nonce,
+            &sender_keypair.public_key,
+            &recipient_keypair.secret_key,
+        )
+        .expect("decrypt failed");
+    assert_eq!(message, decrypted.as_slice());
+
+    let dryocbox: LockedBox =
+        DryocBox::seal(message, &recipient_keypair.public_key).expect("seal failed");
+    let unsealed = dryocbox
+        .unseal_to::<LockedBytes>(&recipient_keypair)
+        .expect("unseal failed");
+    assert_eq!(message, unsealed.as_slice());
+
+    let secret_key = dryocsecretbox::protected::Key::gen_locked().expect("key failed");
+    let nonce = dryocsecretbox::protected::Nonce::gen_locked().expect("nonce failed");
+    let dryocsecretbox: dryocsecretbox::protected::LockedBox =
+        DryocSecretBox::encrypt(message, &nonce, &secret_key);
+    let decrypted = dryocsecretbox
+        .decrypt_to::<LockedBytes>(&nonce, &secret_key)
+        .expect("decrypt failed");
+    assert_eq!(message, decrypted.as_slice());
+
+    let key = dryocstream::protected::Key::gen_locked().expect("key failed");
+    let (mut push_stream, header): (_, dryocstream::protected::Header) =
+        DryocStream::init_push(&key);
+    let c1: LockedBytes = push_stream
+        .push(message, None, Tag::FINAL)
+        .expect("encrypt failed");
+    let mut pull_stream = DryocStream::init_pull(&key, &header);
+    let (m1, tag1) = pull_stream
+        .pull_to::<LockedBytes>(&c1, None)
+        .expect("decrypt failed");
+    assert_eq!(message, m1.as_slice());
+    assert_eq!(tag1, Tag::FINAL);
+}
+
+#[cfg(feature = "nightly")]
+#[test]
+fn test_generichash_protected() {
+    use dryoc::constants::{CRYPTO_GENERICHASH_BYTES, CRYPTO_GENERICHASH_KEYBYTES};
+    use dryoc::generichash::protected::*;
+    use dryoc::generichash::GenericHash;
+
+    let key = Key::gen_readonly_locked().expect("key failed");
+
+    let mut hasher =
+        GenericHash::<CRYPTO_GENERICHASH_KEYBYTES, CRYPTO_GENERICHASH_BYTES>::new(Some(&key))
+            .expect("new failed");
+    hasher.update(b"incremental ");
+    hasher.update(b"input");
+    let hash = hasher
+        .finalize_into_readonly_locked()
+        .expect("finalize failed");
+
+    let expected: Locked<Hash> =
+        GenericHash::hash_with_defaults(b"incremental input", Some(&key)).expect("hash failed");
+    assert_eq!(hash.as_slice(), expected.as_slice());
+}
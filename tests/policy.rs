@@ -0,0 +1,56 @@
+// The algorithm policy is process-wide, so it's tested in its own binary, with
+// a single test which restores the policy before it returns.
+
+#[test]
+fn test_policy_enforcement() {
+    use dryoc::dryocbox::{DryocBox, KeyPair};
+    use dryoc::dryocsecretbox::*;
+    use dryoc::policy::{self, Algorithms};
+
+    let secret_key = Key::gen();
+    let nonce = Nonce::gen();
+    let keypair = KeyPair::gen();
+    let sealed = DryocSecretBox::encrypt_to_vecbox(b"hey", &nonce, &secret_key);
+
+    policy::disable(Algorithms::XSALSA20POLY1305);
+    assert!(!policy::is_enabled(Algorithms::XSALSA20POLY1305));
+
+    let err = DryocSecretBox::try_encrypt_to_vecbox(b"hey", &nonce, &secret_key)
+        .expect_err("should be disabled");
+    assert!(
+        err.to_string()
+            .starts_with("XSALSA20POLY1305 disabled by policy")
+    );
+    assert!(sealed.decrypt_to_vec(&nonce, &secret_key).is_err());
+    assert!(SecretBoxCipher::try_new(&secret_key, &[0u8; 16]).is_err());
+    assert!(DryocBox::seal_to_vecbox(b"hey", &keypair.public_key).is_err());
+
+    let panicked =
+        std::panic::catch_unwind(|| DryocSecretBox::encrypt_to_vecbox(b"hey", &nonce, &secret_key))
+            .is_err();
+    assert!(panicked, "encrypt should panic when disabled");
+
+    #[cfg(all(feature = "nightly", feature = "serde"))]
+    {
+        use dryoc::protected::*;
+
+        let secret = HeapBytes::from_slice_into_locked(b"hey").expect("lock failed");
+        assert!(bincode::serialize(&secret.sealed(&secret_key)).is_err());
+    }
+
+    policy::enable(Algorithms::XSALSA20POLY1305);
+    assert!(policy::disabled().is_empty());
+
+    let sealed = DryocSecretBox::try_encrypt_to_vecbox(b"hey", &nonce, &secret_key)
+        .expect("should be enabled");
+    let decrypted = sealed
+        .decrypt_to_vec(&nonce, &secret_key)
+        .expect("decrypt failed");
+    assert_eq!(decrypted, b"hey");
+    let cipher = SecretBoxCipher::try_new(&secret_key, &[0u8; 16]).expect("should be enabled");
+    assert!(
+        cipher
+            .decrypt::<Vec<u8>, _, _, _>(&cipher.encrypt_to_vecbox(b"hey", &[0u8; 8]), &[0u8; 8])
+            .is_ok()
+    );
+}